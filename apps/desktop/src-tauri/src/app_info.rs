@@ -0,0 +1,79 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::Manager;
+
+use crate::signal_relay::SignalRelay;
+use crate::web_update::WebUpdateState;
+
+/// Everything the frontend wants to know about the shell at startup. Fields
+/// that may be missing are omitted rather than `null`, and new ones are only
+/// ever added, so older frontends keep working.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AppInfo {
+    pub app_version: String,
+    /// Absent in dev builds, where the frontend comes from the dev server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_bundle_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle_server_port: Option<u16>,
+    /// Only while the embedded signaling relay is running.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal_relay_port: Option<u16>,
+    pub os: String,
+    pub arch: String,
+    pub debug: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_data_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_cache_dir: Option<PathBuf>,
+    pub protocol_version: String,
+}
+
+fn collect(web: Option<&WebUpdateState>, relay: Option<&SignalRelay>, app_data_dir: Option<PathBuf>, app_cache_dir: Option<PathBuf>) -> AppInfo {
+    AppInfo {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        web_bundle_version: web.and_then(|s| s.active_version.lock().ok().map(|g| g.clone())),
+        bundle_server_port: web.map(|s| s.server.port()),
+        signal_relay_port: relay.and_then(SignalRelay::port),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        debug: cfg!(debug_assertions),
+        app_data_dir,
+        app_cache_dir,
+        protocol_version: voxelle_protocol::CRATE_VERSION.to_string(),
+    }
+}
+
+pub fn get(app: &tauri::AppHandle) -> AppInfo {
+    let web = app.try_state::<WebUpdateState>();
+    let relay = app.try_state::<SignalRelay>();
+    collect(web.as_deref(), relay.as_deref(), app.path().app_data_dir().ok(), app.path().app_cache_dir().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dev_builds_omit_the_bundle_fields() {
+        let info = collect(None, None, Some(PathBuf::from("/data")), None);
+        assert_eq!(info.web_bundle_version, None);
+        assert_eq!(info.bundle_server_port, None);
+        assert_eq!(info.os, std::env::consts::OS);
+        assert_eq!(info.protocol_version, voxelle_protocol::CRATE_VERSION);
+
+        let json = serde_json::to_value(&info).unwrap();
+        let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["app_data_dir", "app_version", "arch", "debug", "os", "protocol_version"]);
+    }
+
+    #[test]
+    fn reports_the_signal_relay_port_while_running() {
+        let relay = SignalRelay::default();
+        assert_eq!(collect(None, Some(&relay), None, None).signal_relay_port, None);
+        let port = tauri::async_runtime::block_on(relay.start(None, false)).unwrap().port;
+        assert_eq!(collect(None, Some(&relay), None, None).signal_relay_port, Some(port));
+        relay.shutdown();
+    }
+}
@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio::time::Instant;
+
+use crate::web_update::{self, ApplyMode, UpdateChannel, WebUpdateError, WebUpdateState};
+
+pub const EVENT_WEB_UPDATE_AVAILABLE: &str = "voxelle:web-update-available";
+pub(crate) const SETTINGS_FILE: &str = "web_update_settings.json";
+pub(crate) const DECISIONS_FILE: &str = "web_update_decisions.json";
+pub const MAX_INTERVAL_HOURS: u32 = 24 * 7;
+pub const MAX_DOWNLOAD_MB: u32 = 2048;
+pub const MAX_RETRY_ATTEMPTS: u32 = 10;
+pub const MAX_SNOOZE_HOURS: u32 = 24 * 30;
+pub const DEFAULT_DOWNLOAD_DEADLINE_MINS: u32 = 15;
+pub const MAX_DOWNLOAD_DEADLINE_MINS: u32 = 24 * 60;
+pub const MAX_TRUSTED_ZIP_HOSTS: usize = 16;
+// Background downloads stop sooner so they don't hold the operation lock for
+// long; the partial file is kept and the next tick resumes it.
+const BACKGROUND_DOWNLOAD_DEADLINE: Duration = Duration::from_secs(5 * 60);
+// The first check is delayed by a pseudo-random amount in this range so that a
+// fleet of machines started together doesn't hit the feed at the same moment.
+const FIRST_CHECK_JITTER_SECS: std::ops::RangeInclusive<u64> = 60..=300;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebUpdateSettings {
+    pub auto_check: bool,
+    pub interval_hours: u32,
+    /// Download and activate found updates while the main window is not focused.
+    pub auto_download: bool,
+    pub channel: UpdateChannel,
+    /// Bundle zips larger than this are refused.
+    pub max_download_mb: u32,
+    /// Tries per manifest fetch or zip download when the failure is transient.
+    pub retry_attempts: u32,
+    pub apply_mode: ApplyMode,
+    /// How long a download may take, manifest fetch and retries included.
+    pub download_deadline_mins: u32,
+    /// Hosts bundles may be downloaded from besides the feed's own; for
+    /// self-hosted feeds that keep their zips on a CDN.
+    pub trusted_zip_hosts: Vec<String>,
+}
+
+impl Default for WebUpdateSettings {
+    fn default() -> Self {
+        Self {
+            auto_check: true,
+            interval_hours: 6,
+            auto_download: false,
+            channel: UpdateChannel::Stable,
+            max_download_mb: web_update::DEFAULT_MAX_DOWNLOAD_MB,
+            retry_attempts: web_update::DEFAULT_RETRY_ATTEMPTS,
+            apply_mode: ApplyMode::default(),
+            download_deadline_mins: DEFAULT_DOWNLOAD_DEADLINE_MINS,
+            trusted_zip_hosts: Vec::new(),
+        }
+    }
+}
+
+impl WebUpdateSettings {
+    fn validate(&self) -> Result<(), WebUpdateError> {
+        let invalid = |e: String| Err(WebUpdateError::InvalidSettings(e));
+        if self.interval_hours == 0 || self.interval_hours > MAX_INTERVAL_HOURS {
+            return invalid(format!("interval_hours must be 1-{MAX_INTERVAL_HOURS}"));
+        }
+        if self.max_download_mb == 0 || self.max_download_mb > MAX_DOWNLOAD_MB {
+            return invalid(format!("max_download_mb must be 1-{MAX_DOWNLOAD_MB}"));
+        }
+        if self.retry_attempts == 0 || self.retry_attempts > MAX_RETRY_ATTEMPTS {
+            return invalid(format!("retry_attempts must be 1-{MAX_RETRY_ATTEMPTS}"));
+        }
+        if self.download_deadline_mins == 0 || self.download_deadline_mins > MAX_DOWNLOAD_DEADLINE_MINS {
+            return invalid(format!("download_deadline_mins must be 1-{MAX_DOWNLOAD_DEADLINE_MINS}"));
+        }
+        if self.trusted_zip_hosts.len() > MAX_TRUSTED_ZIP_HOSTS {
+            return invalid(format!("at most {MAX_TRUSTED_ZIP_HOSTS} trusted_zip_hosts"));
+        }
+        if let Some(host) = self.trusted_zip_hosts.iter().find(|h| !web_update::is_bare_host(h)) {
+            return invalid(format!("trusted_zip_hosts entry {host:?} is not a bare hostname"));
+        }
+        Ok(())
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(u64::from(self.interval_hours) * 3600)
+    }
+
+    /// The budget for one `download_and_activate`.
+    pub fn download_deadline(&self, background: bool) -> Duration {
+        let deadline = Duration::from_secs(u64::from(self.download_deadline_mins) * 60);
+        if background {
+            deadline.min(BACKGROUND_DOWNLOAD_DEADLINE)
+        } else {
+            deadline
+        }
+    }
+}
+
+fn load_settings_in(cache: &Path) -> WebUpdateSettings {
+    std::fs::read(cache.join(SETTINGS_FILE))
+        .ok()
+        .and_then(|b| serde_json::from_slice::<WebUpdateSettings>(&b).ok())
+        .filter(|s| s.validate().is_ok())
+        .unwrap_or_default()
+}
+
+fn save_settings_in(cache: &Path, settings: &WebUpdateSettings) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(cache.join(SETTINGS_FILE), json).map_err(|e| e.to_string())
+}
+
+pub fn load_persisted_settings(app: &tauri::AppHandle) -> Result<WebUpdateSettings, String> {
+    Ok(load_settings_in(&web_update::cache_root(app)?))
+}
+
+pub fn get_settings(state: &WebUpdateState) -> WebUpdateSettings {
+    state.settings.lock().map(|g| g.clone()).unwrap_or_default()
+}
+
+pub fn set_channel(state: &WebUpdateState, app: &tauri::AppHandle, channel: UpdateChannel) -> Result<(), WebUpdateError> {
+    set_settings(state, app, WebUpdateSettings { channel, ..get_settings(state) })
+}
+
+pub fn set_settings(state: &WebUpdateState, app: &tauri::AppHandle, settings: WebUpdateSettings) -> Result<(), WebUpdateError> {
+    settings.validate()?;
+    save_settings_in(&web_update::cache_root(app)?, &settings)?;
+    if let Ok(mut g) = state.settings.lock() {
+        *g = settings;
+    }
+    // Wake the background task so a new interval applies now rather than after the old one.
+    state.settings_changed.notify_one();
+    Ok(())
+}
+
+/// What the user said about offered updates. Both are dropped when a feed's
+/// `min_version` makes the offer mandatory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateDecisions {
+    /// Checks offering exactly this version report `skipped` instead of `available`.
+    pub skipped_version: Option<String>,
+    /// Unix milliseconds. Background checks keep running but don't announce
+    /// updates before then.
+    pub snoozed_until: Option<u64>,
+}
+
+fn load_decisions_in(cache: &Path) -> UpdateDecisions {
+    std::fs::read(cache.join(DECISIONS_FILE))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn save_decisions_in(cache: &Path, decisions: &UpdateDecisions) -> Result<(), String> {
+    if *decisions == UpdateDecisions::default() {
+        return match std::fs::remove_file(cache.join(DECISIONS_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        };
+    }
+    let json = serde_json::to_vec_pretty(decisions).map_err(|e| e.to_string())?;
+    std::fs::write(cache.join(DECISIONS_FILE), json).map_err(|e| e.to_string())
+}
+
+pub fn load_persisted_decisions(app: &tauri::AppHandle) -> Result<UpdateDecisions, String> {
+    Ok(load_decisions_in(&web_update::cache_root(app)?))
+}
+
+/// The decisions in effect; a snooze that has run out reads as none.
+pub fn get_decisions(state: &WebUpdateState) -> UpdateDecisions {
+    let mut decisions = state.decisions.lock().map(|g| g.clone()).unwrap_or_default();
+    decisions.snoozed_until = decisions.snoozed_until.filter(|&t| t > web_update::unix_ms_now());
+    decisions
+}
+
+fn update_decisions(state: &WebUpdateState, cache: &Path, f: impl FnOnce(&mut UpdateDecisions)) -> Result<UpdateDecisions, String> {
+    let mut g = state.decisions.lock().map_err(|_| "decisions lock poisoned".to_string())?;
+    let mut next = g.clone();
+    f(&mut next);
+    save_decisions_in(cache, &next)?;
+    *g = next.clone();
+    Ok(next)
+}
+
+/// `None` un-skips.
+pub fn skip_version(state: &WebUpdateState, app: &tauri::AppHandle, version: Option<String>) -> Result<Option<String>, WebUpdateError> {
+    let version = version.as_deref().map(web_update::validate_bundle_version).transpose().map_err(WebUpdateError::InvalidSettings)?;
+    let cache = web_update::cache_root(app)?;
+    Ok(update_decisions(state, &cache, |d| d.skipped_version = version)?.skipped_version)
+}
+
+/// Zero hours ends a snooze. Returns the new deadline.
+pub fn snooze(state: &WebUpdateState, app: &tauri::AppHandle, hours: u32) -> Result<Option<u64>, WebUpdateError> {
+    if hours > MAX_SNOOZE_HOURS {
+        return Err(WebUpdateError::InvalidSettings(format!("snooze hours must be 0-{MAX_SNOOZE_HOURS}")));
+    }
+    let until = (hours > 0).then(|| web_update::unix_ms_now() + u64::from(hours) * 3_600_000);
+    let cache = web_update::cache_root(app)?;
+    Ok(update_decisions(state, &cache, |d| d.snoozed_until = until)?.snoozed_until)
+}
+
+/// Called when an offer turns out to be mandatory: nothing the user decided may hold it back.
+pub(crate) fn clear_decisions(state: &WebUpdateState, cache: &Path) {
+    if let Err(e) = update_decisions(state, cache, |d| *d = UpdateDecisions::default()) {
+        tracing::warn!("clearing web update decisions failed: {e}");
+    }
+}
+
+fn first_check_delay() -> Duration {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let (lo, hi) = (*FIRST_CHECK_JITTER_SECS.start(), *FIRST_CHECK_JITTER_SECS.end());
+    Duration::from_secs(lo + u64::from(nanos) % (hi - lo + 1))
+}
+
+// Holds the background task so it can be stopped on exit.
+#[derive(Default)]
+pub struct AutoUpdateTask(Mutex<Option<tauri::async_runtime::JoinHandle<()>>>);
+
+impl AutoUpdateTask {
+    pub fn start(&self, app: tauri::AppHandle) {
+        if let Ok(mut g) = self.0.lock() {
+            if g.is_none() {
+                *g = Some(tauri::async_runtime::spawn(run(app)));
+            }
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some(task) = self.0.lock().ok().and_then(|mut g| g.take()) {
+            task.abort();
+        }
+    }
+}
+
+async fn run(app: tauri::AppHandle) {
+    let state = app.state::<WebUpdateState>().inner().clone();
+    let first_due = Instant::now() + first_check_delay();
+    let mut last_check: Option<Instant> = None;
+    loop {
+        let settings = get_settings(&state);
+        let due = last_check.map(|t| t + settings.interval()).unwrap_or(first_due);
+        if settings.auto_check && Instant::now() >= due {
+            tick(&app, &state, &settings).await;
+            last_check = Some(Instant::now());
+            continue;
+        }
+        if settings.auto_check {
+            tokio::select! {
+                _ = tokio::time::sleep_until(due) => {}
+                _ = state.settings_changed.notified() => {}
+            }
+        } else {
+            state.settings_changed.notified().await;
+        }
+    }
+}
+
+async fn tick(app: &tauri::AppHandle, state: &WebUpdateState, settings: &WebUpdateSettings) {
+    let result = match web_update::check_in_background(state, app).await {
+        Ok(r) => r,
+        // Offline, timeouts, 5xx, or a manual operation that will report on its
+        // own: nothing to tell anyone; try again next tick.
+        Err(e) if e.is_transient() => return,
+        Err(e) => {
+            tracing::warn!("web update check failed: {e}");
+            return;
+        }
+    };
+    if !result.available {
+        return;
+    }
+    // Mandatory offers have already cleared any snooze.
+    if get_decisions(state).snoozed_until.is_none() {
+        let _ = app.emit(EVENT_WEB_UPDATE_AVAILABLE, &result);
+    }
+    if result.mandatory {
+        apply_mandatory(app, state, result.min_version.unwrap_or_default()).await;
+        return;
+    }
+    // Downgrades after a channel switch always wait for the user to confirm.
+    if settings.auto_download && !result.downgrade && is_idle(app) {
+        match web_update::download_and_activate(state, app, false, false, settings.apply_mode, settings.download_deadline(true)).await {
+            Err(e) if !e.is_transient() => tracing::warn!("web update auto-download failed: {e}"),
+            _ => {}
+        }
+    }
+}
+
+// The active bundle is too old to keep running, so this ignores `auto_download`,
+// focus and `apply_mode`. If it can't be installed the frontend is told, so it can block.
+async fn apply_mandatory(app: &tauri::AppHandle, state: &WebUpdateState, min_version: String) {
+    // The full deadline: until this succeeds the frontend may be blocked.
+    let deadline = get_settings(state).download_deadline(false);
+    match web_update::download_and_activate(state, app, false, false, ApplyMode::Immediate, deadline).await {
+        Ok(r) => {
+            let applied = web_update::MandatoryUpdateApplied { activated_version: r.activated_version, min_version };
+            let _ = app.emit(web_update::EVENT_WEB_UPDATE_MANDATORY_APPLIED, &applied);
+        }
+        // Most likely the same update being installed by hand; the next tick re-checks.
+        Err(web_update::WebUpdateError::Busy(op)) => tracing::info!("mandatory web update waits for the running {}", op.as_str()),
+        Err(error) => {
+            tracing::error!("mandatory web update failed: {error}");
+            let active_version = state.active_version.lock().map(|g| g.clone()).unwrap_or_default();
+            let blocked = web_update::MandatoryUpdateBlocked { active_version, min_version, error: error.into() };
+            let _ = app.emit(web_update::EVENT_WEB_UPDATE_BLOCKED, &blocked);
+        }
+    }
+}
+
+// Swapping the bundle under someone who is using the app is disruptive, so only
+// auto-download while the main window is in the background.
+fn is_idle(app: &tauri::AppHandle) -> bool {
+    app.get_webview_window("main")
+        .map(|w| !w.is_focused().unwrap_or(true))
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_or_invalid_settings_fall_back_to_defaults() {
+        let cache = tempfile::tempdir().unwrap();
+        assert_eq!(load_settings_in(cache.path()), WebUpdateSettings::default());
+
+        std::fs::write(cache.path().join(SETTINGS_FILE), "not json").unwrap();
+        assert_eq!(load_settings_in(cache.path()), WebUpdateSettings::default());
+
+        std::fs::write(cache.path().join(SETTINGS_FILE), r#"{"interval_hours":0}"#).unwrap();
+        assert_eq!(load_settings_in(cache.path()), WebUpdateSettings::default());
+    }
+
+    #[test]
+    fn settings_round_trip_and_fill_missing_fields() {
+        let cache = tempfile::tempdir().unwrap();
+        let s = WebUpdateSettings {
+            auto_check: false,
+            interval_hours: 24,
+            auto_download: true,
+            channel: UpdateChannel::Beta,
+            max_download_mb: 250,
+            retry_attempts: 5,
+            apply_mode: ApplyMode::OnNextLaunch,
+            download_deadline_mins: 60,
+            trusted_zip_hosts: vec!["cdn.example.com".into()],
+        };
+        save_settings_in(cache.path(), &s).unwrap();
+        assert_eq!(load_settings_in(cache.path()), s);
+
+        std::fs::write(cache.path().join(SETTINGS_FILE), r#"{"auto_download":true}"#).unwrap();
+        assert_eq!(
+            load_settings_in(cache.path()),
+            WebUpdateSettings { auto_download: true, ..WebUpdateSettings::default() }
+        );
+    }
+
+    #[test]
+    fn interval_is_bounded() {
+        for hours in [0, MAX_INTERVAL_HOURS + 1] {
+            let s = WebUpdateSettings { interval_hours: hours, ..WebUpdateSettings::default() };
+            assert!(s.validate().is_err());
+        }
+        assert_eq!(WebUpdateSettings::default().interval(), Duration::from_secs(6 * 3600));
+        let s = WebUpdateSettings { max_download_mb: 0, ..WebUpdateSettings::default() };
+        assert!(s.validate().is_err());
+        for retry_attempts in [0, MAX_RETRY_ATTEMPTS + 1] {
+            let s = WebUpdateSettings { retry_attempts, ..WebUpdateSettings::default() };
+            assert!(s.validate().is_err());
+        }
+        for download_deadline_mins in [0, MAX_DOWNLOAD_DEADLINE_MINS + 1] {
+            let s = WebUpdateSettings { download_deadline_mins, ..WebUpdateSettings::default() };
+            assert!(s.validate().is_err());
+        }
+        for host in ["", "https://cdn.example.com", "cdn.example.com/zips", "cdn.example.com:443", "cdn example.com"] {
+            let s = WebUpdateSettings { trusted_zip_hosts: vec![host.into()], ..WebUpdateSettings::default() };
+            assert!(s.validate().is_err(), "{host}");
+        }
+    }
+
+    #[test]
+    fn background_downloads_get_a_shorter_deadline() {
+        let s = WebUpdateSettings::default();
+        assert_eq!(s.download_deadline(false), Duration::from_secs(15 * 60));
+        assert_eq!(s.download_deadline(true), BACKGROUND_DOWNLOAD_DEADLINE);
+        let s = WebUpdateSettings { download_deadline_mins: 2, ..WebUpdateSettings::default() };
+        assert_eq!(s.download_deadline(true), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn invalid_settings_have_a_code() {
+        let s = WebUpdateSettings { interval_hours: 0, ..WebUpdateSettings::default() };
+        let e = web_update::WebUpdateCommandError::from(s.validate().unwrap_err());
+        assert_eq!(e.code, web_update::WebUpdateErrorCode::InvalidSettings);
+        assert_eq!(e.message, format!("interval_hours must be 1-{MAX_INTERVAL_HOURS}"));
+    }
+
+    #[test]
+    fn decisions_round_trip_and_clearing_removes_the_file() {
+        let cache = tempfile::tempdir().unwrap();
+        assert_eq!(load_decisions_in(cache.path()), UpdateDecisions::default());
+
+        let d = UpdateDecisions { skipped_version: Some("0.4.2".into()), snoozed_until: Some(1_700_000_000_000) };
+        save_decisions_in(cache.path(), &d).unwrap();
+        assert_eq!(load_decisions_in(cache.path()), d);
+
+        save_decisions_in(cache.path(), &UpdateDecisions::default()).unwrap();
+        assert!(!cache.path().join(DECISIONS_FILE).exists());
+        save_decisions_in(cache.path(), &UpdateDecisions::default()).unwrap();
+    }
+
+    #[test]
+    fn first_check_is_jittered_by_minutes() {
+        for _ in 0..100 {
+            let d = first_check_delay().as_secs();
+            assert!(FIRST_CHECK_JITTER_SECS.contains(&d), "{d}");
+        }
+    }
+}
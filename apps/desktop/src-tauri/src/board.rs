@@ -0,0 +1,423 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+pub const EVENT_BOARD_CHANGED: &str = "voxelle:board-changed";
+const SETTINGS_FILE: &str = "board_settings.json";
+const MAX_AUTHOR_LEN: usize = 64;
+
+// Serialized to the frontend as `{ "code": "not_a_workspace", "detail": ... }`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "code", content = "detail", rename_all = "snake_case")]
+pub enum BoardError {
+    /// The root exists but has no `.isnad` directory (and `create` wasn't set).
+    NotAWorkspace(String),
+    /// Missing, not a directory, or otherwise unusable as a root.
+    InvalidRoot(String),
+    /// Outside the home directory and not picked by the user.
+    OutsideAllowedRoots(String),
+    /// Rejected before anything was written (bad type, task id or payload).
+    InvalidDirective(String),
+    Io(String),
+}
+
+impl std::fmt::Display for BoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAWorkspace(root) => write!(f, "{root} is not an isnad workspace"),
+            Self::InvalidRoot(e) => write!(f, "invalid workspace root: {e}"),
+            Self::OutsideAllowedRoots(root) => write!(f, "{root} is outside the home directory; open it from the workspace picker"),
+            Self::InvalidDirective(e) => write!(f, "invalid directive: {e}"),
+            Self::Io(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<anyhow::Error> for BoardError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Io(format!("{e:#}"))
+    }
+}
+
+impl From<String> for BoardError {
+    fn from(e: String) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoardSettings {
+    /// Stamped as `author` on directives sent from the desktop app.
+    pub author: String,
+}
+
+impl Default for BoardSettings {
+    fn default() -> Self {
+        let user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default();
+        let author = if validate_author(&user).is_ok() { user } else { "human".to_string() };
+        Self { author }
+    }
+}
+
+fn validate_author(author: &str) -> Result<(), String> {
+    if author.trim().is_empty() {
+        return Err("author must be non-empty".into());
+    }
+    if author.len() > MAX_AUTHOR_LEN {
+        return Err("author too long".into());
+    }
+    Ok(())
+}
+
+fn load_settings_in(dir: &Path) -> BoardSettings {
+    std::fs::read(dir.join(SETTINGS_FILE))
+        .ok()
+        .and_then(|b| serde_json::from_slice::<BoardSettings>(&b).ok())
+        .filter(|s| validate_author(&s.author).is_ok())
+        .unwrap_or_default()
+}
+
+fn save_settings_in(dir: &Path, settings: &BoardSettings) -> Result<(), String> {
+    validate_author(&settings.author)?;
+    let json = serde_json::to_vec_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(SETTINGS_FILE), json).map_err(|e| e.to_string())
+}
+
+fn settings_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+pub fn get_settings(app: &tauri::AppHandle) -> Result<BoardSettings, String> {
+    Ok(load_settings_in(&settings_dir(app)?))
+}
+
+pub fn set_settings(app: &tauri::AppHandle, settings: &BoardSettings) -> Result<(), String> {
+    save_settings_in(&settings_dir(app)?, settings)
+}
+
+/// Roots the user chose in the native folder dialog. Those may live anywhere;
+/// everything else has to be under the home directory, so a compromised
+/// webview can't point the board at arbitrary paths.
+#[derive(Default)]
+pub struct PickedRoots(Mutex<BTreeSet<PathBuf>>);
+
+impl PickedRoots {
+    pub fn allow(&self, root: &Path) {
+        if let (Ok(root), Ok(mut g)) = (root.canonicalize(), self.0.lock()) {
+            g.insert(root);
+        }
+    }
+
+    pub fn revoke(&self, root: &Path) {
+        let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        if let Ok(mut g) = self.0.lock() {
+            g.remove(&canonical);
+        }
+    }
+
+    fn contains(&self, root: &Path) -> bool {
+        self.0.lock().is_ok_and(|g| g.iter().any(|picked| root.starts_with(picked)))
+    }
+}
+
+// Canonicalizes `root` (resolving `..` and symlinks before the containment
+// check) and checks it is somewhere the board may read and write.
+fn resolve_root(root: &str, home: Option<&Path>, picked: &PickedRoots) -> Result<PathBuf, BoardError> {
+    if root.trim().is_empty() {
+        return Err(BoardError::InvalidRoot("root must be non-empty".into()));
+    }
+    let canonical = Path::new(root)
+        .canonicalize()
+        .map_err(|e| BoardError::InvalidRoot(format!("{root}: {e}")))?;
+    if !canonical.is_dir() {
+        return Err(BoardError::InvalidRoot(format!("{root} is not a directory")));
+    }
+    let in_home = home
+        .and_then(|h| h.canonicalize().ok())
+        .is_some_and(|h| canonical.starts_with(&h) && canonical != h);
+    if !in_home && !picked.contains(&canonical) {
+        return Err(BoardError::OutsideAllowedRoots(canonical.display().to_string()));
+    }
+    Ok(canonical)
+}
+
+fn fold_in(root: &str, create: bool, home: Option<&Path>, picked: &PickedRoots) -> Result<isnad::Board, BoardError> {
+    let root = resolve_root(root, home, picked)?;
+    if !isnad::paths_for(&root).isnad_dir.is_dir() {
+        if !create {
+            return Err(BoardError::NotAWorkspace(root.display().to_string()));
+        }
+        isnad::scaffold(&root, false)?;
+    }
+    let board = isnad::fold(&root)?;
+    isnad::write_state(&root, &board)?;
+    Ok(board)
+}
+
+/// `resolve_root` with the user's home directory.
+pub(crate) fn resolve(app: &tauri::AppHandle, picked: &PickedRoots, root: &str) -> Result<PathBuf, BoardError> {
+    let home = app.path().home_dir().ok();
+    resolve_root(root, home.as_deref(), picked)
+}
+
+/// Folds the workspace at `root`, writes the derived state files, and returns
+/// the board. With `create`, a root without `.isnad` is scaffolded first.
+pub fn fold(app: &tauri::AppHandle, picked: &PickedRoots, root: &str, create: bool) -> Result<isnad::Board, BoardError> {
+    let home = app.path().home_dir().ok();
+    fold_in(root, create, home.as_deref(), picked)
+}
+
+pub struct DirectiveInput {
+    pub directive_type: String,
+    pub task_id: Option<String>,
+    pub payload: Option<Value>,
+    pub rationale: Option<String>,
+}
+
+// Same rules as voxelle-board's append-directive.
+fn build_directive(input: DirectiveInput, author: &str) -> Result<Value, BoardError> {
+    let invalid = BoardError::InvalidDirective;
+    let d_type = input.directive_type.trim();
+    if d_type.is_empty() {
+        return Err(invalid("missing type".into()));
+    }
+    let task_id = input.task_id.filter(|t| !t.trim().is_empty());
+    if isnad::TASK_SCOPED_DIRECTIVES.contains(&d_type) && task_id.is_none() {
+        return Err(invalid(format!("{d_type} requires a task_id")));
+    }
+    if let Some(task_id) = &task_id {
+        isnad::validate_task_id(task_id).map_err(|e| invalid(e.to_string()))?;
+    }
+    let payload = input.payload.unwrap_or_else(|| serde_json::json!({}));
+    if !payload.is_object() {
+        return Err(invalid("payload must be a JSON object".into()));
+    }
+
+    let mut directive = serde_json::json!({
+        "id": isnad::new_id("D", 12),
+        "ts": isnad::utc_now(),
+        "type": d_type,
+        "author": author,
+        "meta": { "via": "desktop" },
+        "payload": payload
+    });
+    if let Some(task_id) = task_id {
+        directive["task_id"] = Value::String(task_id);
+    }
+    if let Some(rationale) = input.rationale.filter(|r| !r.trim().is_empty()) {
+        directive["rationale"] = Value::String(rationale);
+    }
+    Ok(directive)
+}
+
+// The desktop app is a human's write path, and humans only ever write
+// directives. The target is always the control log; there is no way to
+// pass a different file in.
+fn append_control(paths: &isnad::Paths, directive: &Value) -> Result<(), BoardError> {
+    isnad::append_record(paths, &paths.control, directive)?;
+    Ok(())
+}
+
+fn append_directive_in(
+    root: &str,
+    home: Option<&Path>,
+    picked: &PickedRoots,
+    author: &str,
+    input: DirectiveInput,
+) -> Result<String, BoardError> {
+    let root = resolve_root(root, home, picked)?;
+    let paths = isnad::paths_for(&root);
+    if !paths.isnad_dir.is_dir() {
+        return Err(BoardError::NotAWorkspace(root.display().to_string()));
+    }
+    let directive = build_directive(input, author)?;
+    append_control(&paths, &directive)?;
+    Ok(directive["id"].as_str().unwrap_or_default().to_string())
+}
+
+#[derive(Clone, Serialize)]
+struct BoardChanged {
+    root: String,
+    directive_id: String,
+}
+
+/// Appends a directive to the workspace's control log as the configured
+/// author, tells open board views to refresh, and returns the directive id.
+pub fn append_directive(app: &tauri::AppHandle, picked: &PickedRoots, root: &str, input: DirectiveInput) -> Result<String, BoardError> {
+    let home = app.path().home_dir().ok();
+    let author = get_settings(app)?.author;
+    let directive_id = append_directive_in(root, home.as_deref(), picked, &author, input)?;
+    let _ = app.emit(EVENT_BOARD_CHANGED, BoardChanged { root: root.to_string(), directive_id: directive_id.clone() });
+    Ok(directive_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root_str(p: &Path) -> String {
+        p.display().to_string()
+    }
+
+    #[test]
+    fn roots_must_be_under_home_or_picked() {
+        let home = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let project = home.path().join("projects").join("demo");
+        std::fs::create_dir_all(&project).unwrap();
+        let picked = PickedRoots::default();
+
+        let resolved = resolve_root(&root_str(&project), Some(home.path()), &picked).unwrap();
+        assert_eq!(resolved, project.canonicalize().unwrap());
+
+        // `..` is resolved before the check.
+        let escape = project.join("..").join("..").join("..");
+        for root in [root_str(elsewhere.path()), root_str(&escape), root_str(home.path())] {
+            assert!(matches!(
+                resolve_root(&root, Some(home.path()), &picked),
+                Err(BoardError::OutsideAllowedRoots(_))
+            ));
+        }
+        assert!(matches!(
+            resolve_root(&root_str(&project), None, &picked),
+            Err(BoardError::OutsideAllowedRoots(_))
+        ));
+
+        picked.allow(elsewhere.path());
+        let nested = elsewhere.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        assert!(resolve_root(&root_str(&nested), Some(home.path()), &picked).is_ok());
+
+        picked.revoke(elsewhere.path());
+        assert!(resolve_root(&root_str(&nested), Some(home.path()), &picked).is_err());
+    }
+
+    #[test]
+    fn missing_roots_and_files_are_invalid() {
+        let home = tempfile::tempdir().unwrap();
+        let picked = PickedRoots::default();
+        let file = home.path().join("file.txt");
+        std::fs::write(&file, "x").unwrap();
+        for root in [String::new(), root_str(&home.path().join("missing")), root_str(&file)] {
+            assert!(matches!(
+                resolve_root(&root, Some(home.path()), &picked),
+                Err(BoardError::InvalidRoot(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn fold_scaffolds_only_when_asked() {
+        let home = tempfile::tempdir().unwrap();
+        let project = home.path().join("demo");
+        std::fs::create_dir(&project).unwrap();
+        let picked = PickedRoots::default();
+        let root = root_str(&project);
+
+        assert_eq!(
+            fold_in(&root, false, Some(home.path()), &picked).unwrap_err(),
+            BoardError::NotAWorkspace(root_str(&project.canonicalize().unwrap()))
+        );
+        assert!(!project.join(".isnad").exists());
+
+        let board = fold_in(&root, true, Some(home.path()), &picked).unwrap();
+        assert!(board.cards.is_empty());
+        let paths = isnad::paths_for(&project);
+        assert!(paths.ledger.is_file() && paths.board_json.is_file() && paths.board_md.is_file());
+
+        // Once scaffolded, no flag is needed.
+        assert!(fold_in(&root, false, Some(home.path()), &picked).is_ok());
+    }
+
+    fn directive(d_type: &str, task_id: Option<&str>, payload: Option<Value>) -> DirectiveInput {
+        DirectiveInput {
+            directive_type: d_type.into(),
+            task_id: task_id.map(Into::into),
+            payload,
+            rationale: Some("because".into()),
+        }
+    }
+
+    #[test]
+    fn directives_go_to_control_and_never_the_ledger() {
+        let home = tempfile::tempdir().unwrap();
+        let project = home.path().join("demo");
+        std::fs::create_dir(&project).unwrap();
+        let picked = PickedRoots::default();
+        let root = root_str(&project);
+        fold_in(&root, true, Some(home.path()), &picked).unwrap();
+        let paths = isnad::paths_for(&project);
+        let ledger_before = std::fs::read(&paths.ledger).unwrap();
+
+        let opened = append_directive_in(&root, Some(home.path()), &picked, "alice", directive("open_task", None, Some(serde_json::json!({ "title": "Ship it" })))).unwrap();
+        let status = append_directive_in(&root, Some(home.path()), &picked, "alice", directive("set_status", Some("T_1"), Some(serde_json::json!({ "status": "doing" })))).unwrap();
+        assert_ne!(opened, status);
+
+        assert_eq!(std::fs::read(&paths.ledger).unwrap(), ledger_before);
+        let control = isnad::read_jsonl_values(&paths.control).unwrap();
+        assert_eq!(control.len(), 2);
+        assert_eq!(control[0]["id"], opened.as_str());
+        assert_eq!(control[1]["task_id"], "T_1");
+        assert_eq!(control[1]["author"], "alice");
+        assert_eq!(control[1]["rationale"], "because");
+        assert_eq!(control[1]["meta"]["via"], "desktop");
+    }
+
+    #[test]
+    fn invalid_directives_write_nothing() {
+        let home = tempfile::tempdir().unwrap();
+        let project = home.path().join("demo");
+        std::fs::create_dir(&project).unwrap();
+        let picked = PickedRoots::default();
+        let root = root_str(&project);
+
+        // No workspace yet, and directives don't scaffold one.
+        assert!(matches!(
+            append_directive_in(&root, Some(home.path()), &picked, "alice", directive("open_task", None, None)),
+            Err(BoardError::NotAWorkspace(_))
+        ));
+        assert!(!project.join(".isnad").exists());
+
+        fold_in(&root, true, Some(home.path()), &picked).unwrap();
+        for bad in [
+            directive(" ", None, None),
+            directive("set_status", None, None),
+            directive("note", Some("  "), None),
+            directive("note", Some("T 1"), None),
+            directive("note", Some("-T1"), None),
+            directive("open_task", None, Some(serde_json::json!(["not", "an", "object"]))),
+        ] {
+            assert!(matches!(
+                append_directive_in(&root, Some(home.path()), &picked, "alice", bad),
+                Err(BoardError::InvalidDirective(_))
+            ));
+        }
+        assert!(isnad::read_jsonl_values(&isnad::paths_for(&project).control).unwrap().is_empty());
+    }
+
+    #[test]
+    fn author_setting_round_trips_and_falls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load_settings_in(dir.path()), BoardSettings::default());
+        assert!(save_settings_in(dir.path(), &BoardSettings { author: " ".into() }).is_err());
+
+        let s = BoardSettings { author: "alice".into() };
+        save_settings_in(dir.path(), &s).unwrap();
+        assert_eq!(load_settings_in(dir.path()), s);
+
+        std::fs::write(dir.path().join(SETTINGS_FILE), r#"{"author":""}"#).unwrap();
+        assert_eq!(load_settings_in(dir.path()), BoardSettings::default());
+    }
+
+    #[test]
+    fn errors_serialize_with_a_code() {
+        let json = serde_json::to_value(BoardError::NotAWorkspace("/x".into())).unwrap();
+        assert_eq!(json, serde_json::json!({ "code": "not_a_workspace", "detail": "/x" }));
+    }
+}
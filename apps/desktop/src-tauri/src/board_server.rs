@@ -0,0 +1,150 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::board::{self, BoardError, PickedRoots};
+
+// Embedded `voxelle-board serve` instances, one per canonical workspace root.
+#[derive(Default)]
+pub struct BoardServers(Mutex<HashMap<PathBuf, voxelle_board::BoardServer>>);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BoardServeInfo {
+    /// Opens the board; carries the server's token.
+    pub url: String,
+}
+
+fn new_token() -> Result<String, String> {
+    let mut secret = [0u8; 32];
+    getrandom::getrandom(&mut secret).map_err(|e| e.to_string())?;
+    Ok(hex::encode(secret))
+}
+
+impl BoardServers {
+    fn url(&self, root: &Path) -> Option<String> {
+        self.0.lock().ok()?.get(root).map(|s| s.url().to_string())
+    }
+
+    // `root` is already resolved and canonical.
+    async fn serve_in(&self, root: PathBuf, author: String) -> Result<BoardServeInfo, BoardError> {
+        if let Some(url) = self.url(&root) {
+            return Ok(BoardServeInfo { url });
+        }
+        if !isnad::paths_for(&root).isnad_dir.is_dir() {
+            return Err(BoardError::NotAWorkspace(root.display().to_string()));
+        }
+        let config = voxelle_board::BoardConfig {
+            root: root.clone(),
+            author,
+            via: "desktop".into(),
+            operator: None,
+            token: Some(new_token()?),
+        };
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let server = voxelle_board::BoardServer::start(addr, config).await.map_err(|e| BoardError::Io(e.to_string()))?;
+
+        let mut g = self.0.lock().map_err(|_| "board server lock poisoned".to_string())?;
+        // If another serve for this root finished first, keep its server; dropping ours stops it.
+        let url = g.entry(root).or_insert(server).url().to_string();
+        Ok(BoardServeInfo { url })
+    }
+
+    /// Stops the server for `root`; false if none was running.
+    pub fn stop(&self, root: &str) -> bool {
+        let key = Path::new(root).canonicalize().unwrap_or_else(|_| PathBuf::from(root));
+        match self.0.lock().ok().and_then(|mut g| g.remove(&key)) {
+            Some(server) => {
+                server.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn stop_all(&self) {
+        let servers: Vec<_> = self.0.lock().map(|mut g| g.drain().map(|(_, s)| s).collect()).unwrap_or_default();
+        servers.into_iter().for_each(voxelle_board::BoardServer::stop);
+    }
+}
+
+/// Serves the board for `root` on localhost, or returns the URL of the server
+/// already running for it.
+pub async fn serve(app: &tauri::AppHandle, servers: &BoardServers, picked: &PickedRoots, root: &str) -> Result<BoardServeInfo, BoardError> {
+    let root = board::resolve(app, picked, root)?;
+    let author = board::get_settings(app)?.author;
+    servers.serve_in(root, author).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    fn get(url: &str, cookie: Option<&str>) -> String {
+        let rest = url.strip_prefix("http://").unwrap();
+        let (host, path) = rest.split_once('/').unwrap();
+        let mut s = std::net::TcpStream::connect(host).unwrap();
+        let cookie = cookie.map(|c| format!("Cookie: {c}\r\n")).unwrap_or_default();
+        write!(s, "GET /{path} HTTP/1.1\r\nHost: {host}\r\n{cookie}Connection: close\r\n\r\n").unwrap();
+        let mut out = String::new();
+        s.read_to_string(&mut out).unwrap();
+        out
+    }
+
+    fn workspace() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        isnad::scaffold(&root, false).unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn one_server_per_root() {
+        let (_dir, root) = workspace();
+        let servers = BoardServers::default();
+        let first = tauri::async_runtime::block_on(servers.serve_in(root.clone(), "ada".into())).unwrap();
+        let again = tauri::async_runtime::block_on(servers.serve_in(root.clone(), "ada".into())).unwrap();
+        assert_eq!(first, again);
+        assert!(first.url.starts_with("http://127.0.0.1:"), "{}", first.url);
+
+        assert!(servers.stop(root.to_str().unwrap()));
+        assert!(!servers.stop(root.to_str().unwrap()));
+        let restarted = tauri::async_runtime::block_on(servers.serve_in(root, "ada".into())).unwrap();
+        assert_ne!(restarted, first);
+        servers.stop_all();
+    }
+
+    #[test]
+    fn board_needs_the_token() {
+        let (_dir, root) = workspace();
+        let servers = BoardServers::default();
+        let url = tauri::async_runtime::block_on(servers.serve_in(root, "ada".into())).unwrap().url;
+        let base = &url[..url.find("/t/").unwrap()];
+
+        assert!(get(&format!("{base}/api/board"), None).starts_with("HTTP/1.1 403"));
+        assert!(get(&format!("{base}/t/{}", "00".repeat(32)), None).starts_with("HTTP/1.1 403"));
+
+        let entered = get(&url, None);
+        assert!(entered.starts_with("HTTP/1.1 303"), "{entered}");
+        let cookie = entered
+            .lines()
+            .find_map(|l| l.strip_prefix("set-cookie: ").or_else(|| l.strip_prefix("Set-Cookie: ")))
+            .and_then(|c| c.split(';').next())
+            .unwrap()
+            .to_string();
+        let board = get(&format!("{base}/api/board"), Some(&cookie));
+        assert!(board.starts_with("HTTP/1.1 200"), "{board}");
+        servers.stop_all();
+    }
+
+    #[test]
+    fn serving_needs_a_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let servers = BoardServers::default();
+        let err = tauri::async_runtime::block_on(servers.serve_in(root.clone(), "ada".into())).unwrap_err();
+        assert_eq!(err, BoardError::NotAWorkspace(root.display().to_string()));
+    }
+}
@@ -0,0 +1,266 @@
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+use crate::board::{self, BoardError, PickedRoots};
+use crate::notify::BoardAlerts;
+
+pub const EVENT_ISNAD_CHANGED: &str = "voxelle:isnad-changed";
+// Changes are batched over this window, so at most ~4 events a second per root.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+// Only the append-only logs; `state/` is rewritten by every fold, and
+// reporting it would have board views re-folding in a loop.
+const WATCHED_FILES: [&str; 2] = ["ledger.jsonl", "control.jsonl"];
+
+#[derive(Debug, Clone, Serialize)]
+struct IsnadChanged {
+    root: String,
+    files: Vec<String>,
+}
+
+// Collects file names from `rx` and hands them to `emit` in batches, one per
+// `DEBOUNCE` at most. Returns once every sender is gone.
+fn debounce(rx: mpsc::Receiver<String>, emit: impl Fn(Vec<String>)) {
+    while let Ok(first) = rx.recv() {
+        let deadline = Instant::now() + DEBOUNCE;
+        let mut files = BTreeSet::from([first]);
+        let mut disconnected = false;
+        loop {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(f) => {
+                    files.insert(f);
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    disconnected = true;
+                    break;
+                }
+            }
+        }
+        emit(files.into_iter().collect());
+        if disconnected {
+            return;
+        }
+    }
+}
+
+fn watched_file(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    WATCHED_FILES.contains(&name).then(|| name.to_string())
+}
+
+/// A watcher on one workspace's `.isnad` directory. Watching the directory
+/// rather than the files means logs that are deleted and recreated (e.g. by
+/// `init --force`) keep being reported. Dropping it stops the watcher, which
+/// closes the channel and ends the debounce thread.
+pub struct Watch {
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl Watch {
+    pub fn start(root: &Path, on_change: impl Fn(Vec<String>) + Send + 'static) -> Result<Self, String> {
+        use notify::Watcher;
+
+        let isnad_dir = isnad::paths_for(root).isnad_dir;
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else { return };
+            if matches!(event.kind, notify::EventKind::Access(_)) {
+                return;
+            }
+            for name in event.paths.iter().filter_map(|p| watched_file(p)) {
+                let _ = tx.send(name);
+            }
+        })
+        .map_err(|e| e.to_string())?;
+        watcher
+            .watch(&isnad_dir, notify::RecursiveMode::NonRecursive)
+            .map_err(|e| format!("watch {}: {e}", isnad_dir.display()))?;
+        std::thread::Builder::new()
+            .name("isnad-watch".into())
+            .spawn(move || debounce(rx, on_change))
+            .map_err(|e| e.to_string())?;
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+struct Entry {
+    _watch: Watch,
+    // Window label -> number of watch calls it hasn't undone.
+    refs: HashMap<String, usize>,
+}
+
+/// One watcher per workspace root, shared by every window interested in it.
+#[derive(Default)]
+pub struct BoardWatchers(Mutex<HashMap<PathBuf, Entry>>);
+
+impl BoardWatchers {
+    fn add(&self, root: PathBuf, window: &str, start: impl FnOnce(&Path) -> Result<Watch, String>) -> Result<(), String> {
+        let mut g = self.0.lock().map_err(|_| "board watcher lock poisoned")?;
+        let entry = match g.entry(root) {
+            std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+            std::collections::hash_map::Entry::Vacant(e) => {
+                let watch = start(e.key())?;
+                e.insert(Entry { _watch: watch, refs: HashMap::new() })
+            }
+        };
+        *entry.refs.entry(window.to_string()).or_default() += 1;
+        Ok(())
+    }
+
+    fn remove(&self, root: &Path, window: &str) {
+        let Ok(mut g) = self.0.lock() else { return };
+        let Some(entry) = g.get_mut(root) else { return };
+        if let Some(n) = entry.refs.get_mut(window) {
+            *n -= 1;
+            if *n == 0 {
+                entry.refs.remove(window);
+            }
+        }
+        if entry.refs.is_empty() {
+            g.remove(root);
+        }
+    }
+
+    /// Drops every reference `window` held; called when it is destroyed.
+    pub fn remove_window(&self, window: &str) {
+        if let Ok(mut g) = self.0.lock() {
+            g.retain(|_, entry| {
+                entry.refs.remove(window);
+                !entry.refs.is_empty()
+            });
+        }
+    }
+
+    /// Stops every watcher; called on exit.
+    pub fn clear(&self) {
+        if let Ok(mut g) = self.0.lock() {
+            g.clear();
+        }
+    }
+
+    #[cfg(test)]
+    fn is_watching(&self, root: &Path) -> bool {
+        self.0.lock().is_ok_and(|g| g.contains_key(root))
+    }
+}
+
+/// Starts (or shares) the watcher for `root` on behalf of `window`. Changes
+/// are emitted to all windows as `{ root, files }`, and new unread directives
+/// or blocked cards raise a notification.
+pub fn watch(app: &tauri::AppHandle, watchers: &BoardWatchers, picked: &PickedRoots, root: &str, window: &str) -> Result<(), BoardError> {
+    let root = board::resolve(app, picked, root)?;
+    if !isnad::paths_for(&root).isnad_dir.is_dir() {
+        return Err(BoardError::NotAWorkspace(root.display().to_string()));
+    }
+    let app = app.clone();
+    watchers.add(root, window, move |root| {
+        let root_str = root.display().to_string();
+        let alerts = BoardAlerts::new(&app, root);
+        Watch::start(root, move |files| {
+            let _ = app.emit(EVENT_ISNAD_CHANGED, IsnadChanged { root: root_str.clone(), files });
+            alerts.changed();
+        })
+    })?;
+    Ok(())
+}
+
+pub fn unwatch(watchers: &BoardWatchers, root: &str, window: &str) {
+    let canonical = Path::new(root).canonicalize().unwrap_or_else(|_| PathBuf::from(root));
+    watchers.remove(&canonical, window);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        isnad::scaffold(dir.path(), false).unwrap();
+        dir
+    }
+
+    fn append(path: &Path) {
+        isnad::append_jsonl(path, &serde_json::json!({ "id": isnad::new_id("D", 12), "type": "note" })).unwrap();
+    }
+
+    #[test]
+    fn appending_to_the_logs_fires_an_event() {
+        let dir = workspace();
+        let paths = isnad::paths_for(dir.path());
+        let (tx, rx) = mpsc::channel();
+        let _watch = Watch::start(dir.path(), move |files| tx.send(files).unwrap()).unwrap();
+
+        append(&paths.control);
+        let files = rx.recv_timeout(Duration::from_secs(5)).expect("no change event");
+        assert_eq!(files, vec!["control.jsonl"]);
+
+        // Recreated files are still seen.
+        std::fs::remove_file(&paths.ledger).unwrap();
+        append(&paths.ledger);
+        let mut seen = BTreeSet::new();
+        while let Ok(files) = rx.recv_timeout(Duration::from_secs(5)) {
+            seen.extend(files);
+            if seen.contains("ledger.jsonl") {
+                break;
+            }
+        }
+        assert!(seen.contains("ledger.jsonl"), "{seen:?}");
+    }
+
+    #[test]
+    fn derived_state_is_not_reported() {
+        let dir = workspace();
+        let (tx, rx) = mpsc::channel();
+        let _watch = Watch::start(dir.path(), move |files| tx.send(files).unwrap()).unwrap();
+
+        let board = isnad::fold(dir.path()).unwrap();
+        isnad::write_state(dir.path(), &board).unwrap();
+        assert!(rx.recv_timeout(DEBOUNCE * 4).is_err());
+    }
+
+    #[test]
+    fn bursts_are_batched() {
+        let (tx, rx) = mpsc::channel();
+        let (out_tx, out_rx) = mpsc::channel();
+        let t = std::thread::spawn(move || debounce(rx, move |files| out_tx.send(files).unwrap()));
+        for _ in 0..50 {
+            tx.send("ledger.jsonl".to_string()).unwrap();
+            tx.send("control.jsonl".to_string()).unwrap();
+        }
+        drop(tx);
+        t.join().unwrap();
+        let batches: Vec<_> = out_rx.iter().collect();
+        assert_eq!(batches, vec![vec!["control.jsonl".to_string(), "ledger.jsonl".to_string()]]);
+    }
+
+    #[test]
+    fn watchers_are_shared_and_reference_counted() {
+        let dir = workspace();
+        let root = dir.path().canonicalize().unwrap();
+        let watchers = BoardWatchers::default();
+        let starts = std::cell::Cell::new(0);
+        let start = |r: &Path| {
+            starts.set(starts.get() + 1);
+            Watch::start(r, |_| {})
+        };
+
+        watchers.add(root.clone(), "main", start).unwrap();
+        watchers.add(root.clone(), "main", start).unwrap();
+        watchers.add(root.clone(), "other", start).unwrap();
+        assert_eq!(starts.get(), 1);
+
+        watchers.remove(&root, "main");
+        watchers.remove_window("other");
+        assert!(watchers.is_watching(&root), "main still holds one reference");
+        watchers.remove(&root, "main");
+        assert!(!watchers.is_watching(&root));
+
+        watchers.add(root.clone(), "main", start).unwrap();
+        watchers.clear();
+        assert!(!watchers.is_watching(&root));
+    }
+}
@@ -0,0 +1,186 @@
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Manager;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+pub const MAX_CLEAR_TTL_SECS: u32 = 60 * 60;
+
+/// The system clipboard, behind a trait so the clearing rules can be tested.
+trait Clipboard {
+    /// `None` when the clipboard holds no text.
+    fn read_text(&self) -> Result<Option<String>, String>;
+    fn write_text(&self, text: &str) -> Result<(), String>;
+    fn clear(&self) -> Result<(), String>;
+}
+
+struct SystemClipboard<'a>(&'a tauri::AppHandle);
+
+impl Clipboard for SystemClipboard<'_> {
+    fn read_text(&self) -> Result<Option<String>, String> {
+        // Non-text content reads as an error on some platforms; either way it isn't ours.
+        Ok(self.0.clipboard().read_text().ok())
+    }
+
+    fn write_text(&self, text: &str) -> Result<(), String> {
+        self.0.clipboard().write_text(text).map_err(|e| e.to_string())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        self.0.clipboard().clear().map_err(|e| e.to_string())
+    }
+}
+
+// Only a hash of what was copied is kept, so the secret isn't held in memory
+// for the whole TTL.
+struct PendingClear {
+    hash: [u8; 32],
+    generation: u64,
+}
+
+fn hash(text: &str) -> [u8; 32] {
+    Sha256::digest(text.as_bytes()).into()
+}
+
+#[derive(Default)]
+struct State {
+    generation: u64,
+    pending: Option<PendingClear>,
+}
+
+/// The one clipboard clear waiting to happen. A newer copy replaces it, so
+/// only the latest copy is ever cleared.
+#[derive(Default)]
+pub struct SensitiveCopies(Mutex<State>);
+
+impl SensitiveCopies {
+    /// Writes `text` and returns the generation to pass to `expire` once the TTL is up.
+    fn copy(&self, clipboard: &dyn Clipboard, text: &str) -> Result<u64, String> {
+        let mut state = self.0.lock().map_err(|_| "clipboard lock poisoned")?;
+        clipboard.write_text(text)?;
+        state.generation += 1;
+        state.pending = Some(PendingClear { hash: hash(text), generation: state.generation });
+        Ok(state.generation)
+    }
+
+    /// Clears the clipboard if `generation` is still the pending copy and the
+    /// clipboard still holds exactly what it wrote. Returns whether it cleared.
+    fn expire(&self, clipboard: &dyn Clipboard, generation: u64) -> Result<bool, String> {
+        let mut state = self.0.lock().map_err(|_| "clipboard lock poisoned")?;
+        // Cancelled, or superseded by a later copy that clears on its own schedule.
+        let Some(copied) = state.pending.as_ref().filter(|p| p.generation == generation).map(|p| p.hash) else {
+            return Ok(false);
+        };
+        state.pending = None;
+        // Something the user copied since is theirs to keep.
+        if clipboard.read_text()?.is_none_or(|current| hash(&current) != copied) {
+            return Ok(false);
+        }
+        clipboard.clear()?;
+        Ok(true)
+    }
+
+    /// Keeps the latest copy on the clipboard. Returns whether a clear was pending.
+    pub fn cancel_clear(&self) -> bool {
+        self.0.lock().is_ok_and(|mut g| g.pending.take().is_some())
+    }
+}
+
+/// Copies `text` and clears it from the clipboard after `ttl_secs`, unless
+/// something else has been copied by then.
+pub fn copy_sensitive(app: &tauri::AppHandle, copies: &SensitiveCopies, text: &str, ttl_secs: u32) -> Result<(), String> {
+    if ttl_secs == 0 || ttl_secs > MAX_CLEAR_TTL_SECS {
+        return Err(format!("ttl_seconds must be 1-{MAX_CLEAR_TTL_SECS}"));
+    }
+    let generation = copies.copy(&SystemClipboard(app), text)?;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(u64::from(ttl_secs))).await;
+        let copies = app.state::<SensitiveCopies>();
+        if let Err(e) = copies.expire(&SystemClipboard(&app), generation) {
+            tracing::warn!("clearing copied text from the clipboard failed: {e}");
+        }
+    });
+    Ok(())
+}
+
+/// Clears a pending copy right away, so it doesn't outlive the app; called on exit.
+pub fn clear_pending(app: &tauri::AppHandle, copies: &SensitiveCopies) {
+    let generation = copies.0.lock().ok().and_then(|g| g.pending.as_ref().map(|p| p.generation));
+    if let Some(generation) = generation {
+        let _ = copies.expire(&SystemClipboard(app), generation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeClipboard(Mutex<Option<String>>);
+
+    impl FakeClipboard {
+        fn contents(&self) -> Option<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl Clipboard for FakeClipboard {
+        fn read_text(&self) -> Result<Option<String>, String> {
+            Ok(self.contents())
+        }
+
+        fn write_text(&self, text: &str) -> Result<(), String> {
+            *self.0.lock().unwrap() = Some(text.to_string());
+            Ok(())
+        }
+
+        fn clear(&self) -> Result<(), String> {
+            *self.0.lock().unwrap() = None;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn clears_only_what_it_copied() {
+        let clipboard = FakeClipboard::default();
+        let copies = SensitiveCopies::default();
+
+        let generation = copies.copy(&clipboard, "voxelle://invite/abc").unwrap();
+        assert_eq!(clipboard.contents().as_deref(), Some("voxelle://invite/abc"));
+        assert!(copies.expire(&clipboard, generation).unwrap());
+        assert_eq!(clipboard.contents(), None);
+        assert!(!copies.expire(&clipboard, generation).unwrap(), "a clear happens once");
+
+        // The user copied something else in the meantime; leave it alone.
+        let generation = copies.copy(&clipboard, "pairing 123-456").unwrap();
+        clipboard.write_text("grocery list").unwrap();
+        assert!(!copies.expire(&clipboard, generation).unwrap());
+        assert_eq!(clipboard.contents().as_deref(), Some("grocery list"));
+    }
+
+    #[test]
+    fn later_copies_replace_earlier_clears() {
+        let clipboard = FakeClipboard::default();
+        let copies = SensitiveCopies::default();
+
+        let first = copies.copy(&clipboard, "session-1").unwrap();
+        let second = copies.copy(&clipboard, "session-2").unwrap();
+        assert!(!copies.expire(&clipboard, first).unwrap(), "the first copy's timer no longer clears");
+        assert_eq!(clipboard.contents().as_deref(), Some("session-2"));
+        assert!(copies.expire(&clipboard, second).unwrap());
+        assert_eq!(clipboard.contents(), None);
+    }
+
+    #[test]
+    fn cancelled_clears_keep_the_copy() {
+        let clipboard = FakeClipboard::default();
+        let copies = SensitiveCopies::default();
+
+        let generation = copies.copy(&clipboard, "voxelle://invite/abc").unwrap();
+        assert!(copies.cancel_clear());
+        assert!(!copies.cancel_clear());
+        assert!(!copies.expire(&clipboard, generation).unwrap());
+        assert_eq!(clipboard.contents().as_deref(), Some("voxelle://invite/abc"));
+    }
+}
@@ -0,0 +1,237 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager, Url};
+
+pub const EVENT_DEEP_LINK: &str = "voxelle:deep-link";
+pub const SCHEME: &str = "voxelle";
+// Same bound the relay applies to session ids.
+const MAX_SID_CHARS: usize = 128;
+const MAX_RELAY_CHARS: usize = 2048;
+// Links that pile up before the UI is ready beyond this are dropped.
+const MAX_PENDING: usize = 16;
+
+/// A validated `voxelle://` link. Only this structured form ever reaches the
+/// webview; the raw URL does not.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeepLink {
+    /// `voxelle://join?invite=<base64url invite>`
+    Join { invite: Box<voxelle_protocol::Invite> },
+    /// `voxelle://session?sid=<hex>&relay=<ws(s) url>`
+    Session { sid: String, relay: String },
+}
+
+// The one value of `name` in the query; repeated parameters are ambiguous, so rejected.
+fn single_param(url: &Url, name: &str) -> Result<String, String> {
+    let mut values = url.query_pairs().filter(|(k, _)| k == name).map(|(_, v)| v.into_owned());
+    let value = values.next().ok_or_else(|| format!("missing {name}"))?;
+    if values.next().is_some() {
+        return Err(format!("{name} given more than once"));
+    }
+    Ok(value)
+}
+
+fn validate_sid(sid: &str) -> Result<(), String> {
+    if sid.is_empty() || sid.len() > MAX_SID_CHARS || !sid.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("sid must be 1-128 hex characters".into());
+    }
+    Ok(())
+}
+
+// Relays are untrusted but still see the SDP, so plain `ws:` is only allowed
+// for a relay on this machine.
+fn validate_relay(relay: &str) -> Result<(), String> {
+    if relay.len() > MAX_RELAY_CHARS {
+        return Err("relay url too long".into());
+    }
+    let url = Url::parse(relay).map_err(|_| "relay is not a url".to_string())?;
+    let loopback = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    match url.scheme() {
+        "wss" => {}
+        "ws" if loopback => {}
+        _ => return Err("relay must be a wss:// url".into()),
+    }
+    if url.host_str().is_none_or(str::is_empty) || !url.username().is_empty() || url.password().is_some() {
+        return Err("relay url must have a host and no credentials".into());
+    }
+    Ok(())
+}
+
+pub fn parse(link: &str) -> Result<DeepLink, String> {
+    let url = Url::parse(link.trim()).map_err(|_| "not a url".to_string())?;
+    if url.scheme() != SCHEME {
+        return Err(format!("not a {SCHEME}:// link"));
+    }
+    if !matches!(url.path(), "" | "/") {
+        return Err("unexpected path".into());
+    }
+    match url.host_str() {
+        Some("join") => {
+            let invite = single_param(&url, "invite")?;
+            let invite = voxelle_protocol::parse_invite(&invite).map_err(|e| e.to_string())?;
+            // The webview checks the invite's own signature when joining; a
+            // device without a valid delegation doesn't get that far.
+            invite
+                .verify_issuer_delegation(voxelle_protocol::CanonicalTime::now().unix_ms())
+                .map_err(|e| format!("invite issuer: {e}"))?;
+            Ok(DeepLink::Join { invite: Box::new(invite) })
+        }
+        Some("session") => {
+            let sid = single_param(&url, "sid")?;
+            let relay = single_param(&url, "relay")?;
+            validate_sid(&sid)?;
+            validate_relay(&relay)?;
+            Ok(DeepLink::Session { sid: sid.to_ascii_lowercase(), relay })
+        }
+        _ => Err("unknown link type".into()),
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    ready: bool,
+    pending: Vec<DeepLink>,
+}
+
+/// Holds links that arrive before the frontend is listening (cold start from a
+/// link, or while the main window is still loading).
+#[derive(Default)]
+pub struct DeepLinks(Mutex<Inner>);
+
+impl DeepLinks {
+    // Whether `link` can be emitted now; otherwise it's queued.
+    fn offer(&self, link: DeepLink) -> Option<DeepLink> {
+        let mut g = self.0.lock().ok()?;
+        if g.ready {
+            return Some(link);
+        }
+        if g.pending.len() < MAX_PENDING {
+            g.pending.push(link);
+        }
+        None
+    }
+
+    /// Marks the frontend as listening and hands over what was queued.
+    pub fn ready(&self) -> Vec<DeepLink> {
+        let Ok(mut g) = self.0.lock() else {
+            return Vec::new();
+        };
+        g.ready = true;
+        std::mem::take(&mut g.pending)
+    }
+}
+
+/// Validates each link and emits it, or queues it until the frontend calls
+/// `ready`. Invalid links are logged and dropped.
+pub fn handle(app: &tauri::AppHandle, urls: &[Url]) {
+    let Some(links) = app.try_state::<DeepLinks>() else {
+        return;
+    };
+    for url in urls {
+        match parse(url.as_str()) {
+            Ok(link) => {
+                if let Some(link) = links.offer(link) {
+                    let _ = app.emit(EVENT_DEEP_LINK, &link);
+                }
+            }
+            Err(e) => tracing::warn!("ignoring {SCHEME}:// link: {e}"),
+        }
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_links_are_validated() {
+        let sid = "ab".repeat(32);
+        let link = format!("voxelle://session?sid={}&relay=wss%3A%2F%2Frelay.example%2Fws", sid.to_uppercase());
+        assert_eq!(
+            parse(&link).unwrap(),
+            DeepLink::Session { sid: sid.clone(), relay: "wss://relay.example/ws".into() }
+        );
+        assert!(parse(&format!("voxelle://session/?sid={sid}&relay=ws://127.0.0.1:8788/ws")).is_ok());
+
+        for bad in [
+            format!("voxelle://session?sid={sid}"),
+            "voxelle://session?sid=not-hex&relay=wss://r.example".to_string(),
+            format!("voxelle://session?sid={}&relay=wss://r.example", "a".repeat(MAX_SID_CHARS + 1)),
+            format!("voxelle://session?sid={sid}&relay=ws://r.example"),
+            format!("voxelle://session?sid={sid}&relay=https://r.example"),
+            format!("voxelle://session?sid={sid}&relay=wss://user:pw@r.example"),
+            format!("voxelle://session?sid={sid}&sid={sid}&relay=wss://r.example"),
+            format!("voxelle://session/extra?sid={sid}&relay=wss://r.example"),
+            format!("https://session?sid={sid}&relay=wss://r.example"),
+            "voxelle://unknown?x=1".to_string(),
+            "not a url".to_string(),
+        ] {
+            assert!(parse(&bad).is_err(), "{bad}");
+        }
+    }
+
+    // An invite from a device `principal` delegated to until `expires_ts`.
+    fn invite_payload(expires_ts: i64) -> String {
+        use base64::Engine;
+        use ed25519_dalek::pkcs8::EncodePublicKey;
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let key = |sk: &SigningKey| {
+            let spki = sk.verifying_key().to_public_key_der().unwrap();
+            (voxelle_protocol::principal_id_from_spki_der(spki.as_bytes()), b64.encode(spki.as_bytes()))
+        };
+        let principal = SigningKey::from_bytes(&[1; 32]);
+        let (principal_id, principal_pub) = key(&principal);
+        let (device_id, device_pub) = key(&SigningKey::from_bytes(&[2; 32]));
+        let mut cert = voxelle_protocol::DelegationCert {
+            v: 1,
+            principal_id: principal_id.clone(),
+            principal_pub,
+            device_pub: device_pub.clone(),
+            device_id: device_id.clone(),
+            not_before_ts: 0,
+            expires_ts,
+            scopes: Vec::new(),
+            sig: String::new(),
+        };
+        cert.sig = b64.encode(principal.sign(&cert.signature_input()).to_bytes());
+        let invite = serde_json::json!({
+            "v": 1, "space_id": "sp", "invite_id": "inv", "issued_ts": 1_700_000_000_000_i64,
+            "expires_ts": 1_800_000_000_000_i64, "issuer_principal_id": principal_id, "issuer_device_id": device_id,
+            "issuer_device_pub": device_pub, "issuer_delegation": cert, "scopes": ["space:sp:read"], "sig": "s"
+        });
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(invite.to_string())
+    }
+
+    #[test]
+    fn join_links_go_through_invite_parsing() {
+        match parse(&format!("voxelle://join?invite={}", invite_payload(4_000_000_000_000))).unwrap() {
+            DeepLink::Join { invite } => assert_eq!(invite.space_id, "sp"),
+            other => panic!("{other:?}"),
+        }
+        let err = parse(&format!("voxelle://join?invite={}", invite_payload(1))).unwrap_err();
+        assert!(err.contains("expired"), "{err}");
+        assert!(parse("voxelle://join").is_err());
+        let err = parse("voxelle://join?invite=%%%").unwrap_err();
+        assert!(err.contains("base64url"), "{err}");
+        // Well-formed base64url that isn't an invite.
+        assert!(parse("voxelle://join?invite=e30").is_err());
+    }
+
+    #[test]
+    fn links_queue_until_the_frontend_is_ready() {
+        let links = DeepLinks::default();
+        let link = DeepLink::Session { sid: "ab".into(), relay: "wss://r.example".into() };
+        for _ in 0..MAX_PENDING + 3 {
+            assert_eq!(links.offer(link.clone()), None);
+        }
+        assert_eq!(links.ready().len(), MAX_PENDING);
+        assert_eq!(links.offer(link.clone()), Some(link));
+        assert!(links.ready().is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+use base64::Engine;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, EncodePrivateKey, EncodePublicKey};
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+use voxelle_protocol::{Envelope, EnvelopeLimits, PrincipalId, SignedEnvelope};
+use zeroize::Zeroizing;
+
+use crate::secrets::{SecretError, Secrets};
+
+/// Secret namespace holding each identity's PKCS#8 private key, keyed by
+/// principal id. Only this module reads it; the webview commands can't.
+pub const NAMESPACE: &str = "identity";
+// The public half of each identity. Never any key material.
+const IDENTITIES_FILE: &str = "identities.json";
+const MAX_LABEL_CHARS: usize = 64;
+const MAX_IDENTITIES: usize = 32;
+
+/// Envelope kinds the webview may have signed. Envelope signatures are already
+/// domain-separated, but an open kind would still let a script mint messages
+/// for protocols the app doesn't speak.
+pub const SIGNING_CONTEXTS: &[&str] = &["chat.msg", "chat.edit", "chat.delete", "chat.ack", "sync.have"];
+
+// Serializes read-modify-write of the identities file across concurrent commands.
+static IDENTITIES_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Identity {
+    pub principal_id: String,
+    pub label: String,
+    /// SPKI DER, standard Base64, as in `SignedEnvelope::sender_pub`.
+    pub principal_pub_spki_b64: String,
+    /// Unix ms.
+    pub created_at: i64,
+}
+
+// Serialized to the frontend as `{ "code": "context_not_allowed", "detail": ... }`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "code", content = "detail", rename_all = "snake_case")]
+pub enum IdentityError {
+    UnknownIdentity(String),
+    ContextNotAllowed(String),
+    InvalidLabel(String),
+    InvalidPayload(String),
+    /// Deleting needs a presence check, and it couldn't be made or didn't pass.
+    AuthRequired(String),
+    AuthDenied,
+    Other(String),
+}
+
+impl std::fmt::Display for IdentityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownIdentity(id) => write!(f, "no identity {id} on this device"),
+            Self::ContextNotAllowed(kind) => write!(f, "signing \"{kind}\" messages is not allowed"),
+            Self::InvalidLabel(e) => write!(f, "invalid label: {e}"),
+            Self::InvalidPayload(e) => write!(f, "invalid payload: {e}"),
+            Self::AuthRequired(e) => write!(f, "deleting an identity requires authentication, which didn't succeed: {e}"),
+            Self::AuthDenied => write!(f, "authentication was cancelled"),
+            Self::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<String> for IdentityError {
+    fn from(e: String) -> Self {
+        Self::Other(e)
+    }
+}
+
+impl From<SecretError> for IdentityError {
+    fn from(e: SecretError) -> Self {
+        match e {
+            SecretError::AuthRequired(e) => Self::AuthRequired(e),
+            SecretError::AuthDenied => Self::AuthDenied,
+            SecretError::Other(e) => Self::Other(e),
+        }
+    }
+}
+
+fn settings_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn load_in(dir: &Path) -> Vec<Identity> {
+    std::fs::read(dir.join(IDENTITIES_FILE))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+// Written to a sibling temp file and renamed, like the secret index.
+fn save_in(dir: &Path, identities: &[Identity]) -> Result<(), String> {
+    let path = dir.join(IDENTITIES_FILE);
+    let json = serde_json::to_vec_pretty(identities).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, &path).map_err(|e| e.to_string())
+}
+
+fn validate_label(label: &str) -> Result<String, IdentityError> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err(IdentityError::InvalidLabel("must be non-empty".into()));
+    }
+    if label.chars().count() > MAX_LABEL_CHARS {
+        return Err(IdentityError::InvalidLabel(format!("must be at most {MAX_LABEL_CHARS} characters")));
+    }
+    if label.chars().any(char::is_control) {
+        return Err(IdentityError::InvalidLabel("must not contain control characters".into()));
+    }
+    Ok(label.to_string())
+}
+
+fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn create_in(secrets: &Secrets, dir: &Path, label: &str) -> Result<Identity, IdentityError> {
+    let label = validate_label(label)?;
+    let _guard = IDENTITIES_LOCK.lock().map_err(|_| "identity lock poisoned".to_string())?;
+    let mut identities = load_in(dir);
+    if identities.len() >= MAX_IDENTITIES {
+        return Err(IdentityError::Other(format!("at most {MAX_IDENTITIES} identities per device")));
+    }
+
+    let mut seed = Zeroizing::new([0u8; 32]);
+    getrandom::getrandom(&mut *seed).map_err(|e| e.to_string())?;
+    let key = SigningKey::from_bytes(&seed);
+    let spki = key.verifying_key().to_public_key_der().map_err(|e| e.to_string())?;
+    let pkcs8 = key.to_pkcs8_der().map_err(|e| e.to_string())?;
+    let principal_id = PrincipalId::from_spki_der(spki.as_bytes()).to_string();
+
+    let stored = Zeroizing::new(base64::engine::general_purpose::STANDARD.encode(pkcs8.as_bytes()));
+    secrets.set(NAMESPACE, &principal_id, &stored, None)?;
+    let identity = Identity {
+        principal_id,
+        label,
+        principal_pub_spki_b64: base64::engine::general_purpose::STANDARD.encode(spki.as_bytes()),
+        created_at: now_ms(),
+    };
+    identities.push(identity.clone());
+    if let Err(e) = save_in(dir, &identities) {
+        // Don't leave a key behind that nothing lists.
+        let _ = secrets.delete(NAMESPACE, &identity.principal_id);
+        return Err(e.into());
+    }
+    Ok(identity)
+}
+
+fn signing_key(secrets: &Secrets, principal_id: &str) -> Result<SigningKey, IdentityError> {
+    let stored = Zeroizing::new(
+        secrets.get(NAMESPACE, principal_id)?.ok_or_else(|| IdentityError::UnknownIdentity(principal_id.to_string()))?,
+    );
+    let der = Zeroizing::new(
+        base64::engine::general_purpose::STANDARD
+            .decode(stored.as_bytes())
+            .map_err(|_| format!("stored key for {principal_id} is damaged"))?,
+    );
+    SigningKey::from_pkcs8_der(&der).map_err(|_| IdentityError::Other(format!("stored key for {principal_id} is damaged")))
+}
+
+fn sign_in(
+    secrets: &Secrets,
+    dir: &Path,
+    principal_id: &str,
+    context: &str,
+    payload_json: &str,
+) -> Result<SignedEnvelope, IdentityError> {
+    if !SIGNING_CONTEXTS.contains(&context) {
+        return Err(IdentityError::ContextNotAllowed(context.to_string()));
+    }
+    // Only listed identities sign, so a key left behind by a failed delete stays unusable.
+    if !load_in(dir).iter().any(|i| i.principal_id == principal_id) {
+        return Err(IdentityError::UnknownIdentity(principal_id.to_string()));
+    }
+    let sender: PrincipalId = principal_id.parse().map_err(|_| IdentityError::UnknownIdentity(principal_id.to_string()))?;
+    let payload = serde_json::from_str(payload_json).map_err(|e| IdentityError::InvalidPayload(e.to_string()))?;
+    let envelope = Envelope::new(context, sender, now_ms(), payload);
+    envelope
+        .validate(&EnvelopeLimits::default())
+        .map_err(|e| IdentityError::InvalidPayload(e.to_string()))?;
+    let key = signing_key(secrets, principal_id)?;
+    envelope.sign(&key).map_err(|e| IdentityError::InvalidPayload(e.to_string()))
+}
+
+fn delete_in(secrets: &Secrets, dir: &Path, principal_id: &str) -> Result<(), IdentityError> {
+    let label = load_in(dir)
+        .into_iter()
+        .find(|i| i.principal_id == principal_id)
+        .map(|i| i.label)
+        .ok_or_else(|| IdentityError::UnknownIdentity(principal_id.to_string()))?;
+    // Asked outside the lock: the prompt can sit open for as long as the user likes.
+    secrets.authenticate(&format!("delete the identity \"{label}\""))?;
+
+    let _guard = IDENTITIES_LOCK.lock().map_err(|_| "identity lock poisoned".to_string())?;
+    let mut identities = load_in(dir);
+    identities.retain(|i| i.principal_id != principal_id);
+    save_in(dir, &identities)?;
+    secrets.delete(NAMESPACE, principal_id)?;
+    Ok(())
+}
+
+/// Generates a keypair and keeps the private key in the secret store. Only the
+/// public half comes back.
+pub fn create(app: &tauri::AppHandle, secrets: &Secrets, label: &str) -> Result<Identity, IdentityError> {
+    create_in(secrets, &settings_dir(app)?, label)
+}
+
+pub fn list(app: &tauri::AppHandle) -> Result<Vec<Identity>, String> {
+    Ok(load_in(&settings_dir(app)?))
+}
+
+/// Signs `payload_json` as a `context` envelope from `principal_id`.
+pub fn sign(
+    app: &tauri::AppHandle,
+    secrets: &Secrets,
+    principal_id: &str,
+    context: &str,
+    payload_json: &str,
+) -> Result<SignedEnvelope, IdentityError> {
+    sign_in(secrets, &settings_dir(app)?, principal_id, context, payload_json)
+}
+
+/// Needs the user present, like reading a protected secret: anything signed by
+/// the identity can no longer be re-signed once it's gone.
+pub fn delete(app: &tauri::AppHandle, secrets: &Secrets, principal_id: &str) -> Result<(), IdentityError> {
+    delete_in(secrets, &settings_dir(app)?, principal_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret_file_store::FileStore;
+    use crate::secrets::UserPresence;
+
+    struct Presence(Result<bool, String>);
+
+    impl UserPresence for Presence {
+        fn confirm(&self, _reason: &str) -> Result<bool, String> {
+            self.0.clone()
+        }
+    }
+
+    fn file_backed(dir: &Path, answer: Result<bool, String>) -> Secrets {
+        Secrets::new(Box::new(FileStore::for_tests(dir)), dir.join("secret_keys.json"))
+            .with_presence(Box::new(Presence(answer)))
+    }
+
+    #[test]
+    fn created_identities_sign_verifiable_envelopes() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = file_backed(dir.path(), Ok(true));
+
+        let laptop = create_in(&secrets, dir.path(), "  Laptop ").unwrap();
+        let spare = create_in(&secrets, dir.path(), "Spare").unwrap();
+        assert_eq!(laptop.label, "Laptop");
+        assert_ne!(laptop.principal_id, spare.principal_id);
+        assert_eq!(load_in(dir.path()), vec![laptop.clone(), spare]);
+        // Only public material is written next to the app data.
+        let listed = std::fs::read_to_string(dir.path().join(IDENTITIES_FILE)).unwrap();
+        let stored = secrets.get(NAMESPACE, &laptop.principal_id).unwrap().unwrap();
+        assert!(!listed.contains(&stored));
+
+        let signed = sign_in(&secrets, dir.path(), &laptop.principal_id, "chat.msg", r#"{"text":"hi"}"#).unwrap();
+        signed.verify().unwrap();
+        assert_eq!(signed.envelope.sender.as_str(), laptop.principal_id);
+        assert_eq!(signed.sender_pub, laptop.principal_pub_spki_b64);
+        assert_eq!(signed.envelope.payload, serde_json::json!({ "text": "hi" }));
+    }
+
+    #[test]
+    fn signing_is_limited_to_app_contexts() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = file_backed(dir.path(), Ok(true));
+        let id = create_in(&secrets, dir.path(), "Laptop").unwrap().principal_id;
+
+        assert_eq!(
+            sign_in(&secrets, dir.path(), &id, "login.challenge", "{}").unwrap_err(),
+            IdentityError::ContextNotAllowed("login.challenge".into())
+        );
+        assert!(matches!(
+            sign_in(&secrets, dir.path(), &id, "chat.msg", "{not json").unwrap_err(),
+            IdentityError::InvalidPayload(_)
+        ));
+        // Signed payloads are integers-only.
+        assert!(matches!(
+            sign_in(&secrets, dir.path(), &id, "chat.msg", r#"{"x":1.5}"#).unwrap_err(),
+            IdentityError::InvalidPayload(_)
+        ));
+        let other = "ed25519:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+        assert_eq!(
+            sign_in(&secrets, dir.path(), other, "chat.msg", "{}").unwrap_err(),
+            IdentityError::UnknownIdentity(other.into())
+        );
+        assert!(matches!(create_in(&secrets, dir.path(), " ").unwrap_err(), IdentityError::InvalidLabel(_)));
+    }
+
+    #[test]
+    fn deleting_needs_the_user_present() {
+        let dir = tempfile::tempdir().unwrap();
+        let id = create_in(&file_backed(dir.path(), Ok(true)), dir.path(), "Laptop").unwrap().principal_id;
+
+        let denied = file_backed(dir.path(), Ok(false));
+        assert_eq!(delete_in(&denied, dir.path(), &id).unwrap_err(), IdentityError::AuthDenied);
+        let unavailable = file_backed(dir.path(), Err("no prompt".into()));
+        assert_eq!(
+            delete_in(&unavailable, dir.path(), &id).unwrap_err(),
+            IdentityError::AuthRequired("no prompt".into())
+        );
+        assert!(sign_in(&denied, dir.path(), &id, "chat.msg", "{}").is_ok());
+
+        let secrets = file_backed(dir.path(), Ok(true));
+        delete_in(&secrets, dir.path(), &id).unwrap();
+        assert!(load_in(dir.path()).is_empty());
+        assert_eq!(secrets.get(NAMESPACE, &id).unwrap(), None);
+        assert_eq!(
+            sign_in(&secrets, dir.path(), &id, "chat.msg", "{}").unwrap_err(),
+            IdentityError::UnknownIdentity(id.clone())
+        );
+        assert_eq!(delete_in(&secrets, dir.path(), &id).unwrap_err(), IdentityError::UnknownIdentity(id));
+    }
+}
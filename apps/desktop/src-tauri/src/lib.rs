@@ -1,57 +1,353 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 use tauri::Manager;
+use tauri_plugin_deep_link::DeepLinkExt;
 
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
-}
-
+mod app_info;
+mod auto_update;
+mod board;
+mod board_server;
+mod board_watch;
+mod clipboard;
+mod deep_link;
+mod identity;
+mod logs;
+mod notify;
+mod secret_export;
+mod secret_file_store;
+mod secrets;
+mod signal_relay;
+#[cfg(desktop)]
+mod single_instance;
+#[cfg(desktop)]
+mod tray;
+mod update_network;
 mod web_update;
+mod workspace;
 
-fn keyring_entry(key: &str) -> Result<keyring::Entry, String> {
-    if key.trim().is_empty() {
-        return Err("key must be non-empty".into());
-    }
-    if key.len() > 256 {
-        return Err("key too long".into());
-    }
-    Ok(keyring::Entry::new("voxelle", key).map_err(|e| e.to_string())?)
+// Works in dev too, where no web update state is managed; bundle fields are omitted then.
+#[tauri::command]
+fn voxelle_app_info(app: tauri::AppHandle) -> app_info::AppInfo {
+    app_info::get(&app)
 }
 
+// Hosts the signaling relay in-process for LAN-only or private use. Binds
+// localhost unless `allow_lan` is set; `port` defaults to a free one.
+// The bundle's runtime config advertises the relay while it runs.
 #[tauri::command]
-fn voxelle_secret_get(key: String) -> Result<Option<String>, String> {
-    let entry = keyring_entry(&key)?;
-    match entry.get_password() {
-        Ok(v) => Ok(Some(v)),
-        Err(e) => {
-            // Treat "missing" as None; anything else is an error.
-            let msg = e.to_string();
-            let msg_l = msg.to_lowercase();
-            if msg_l.contains("no entry") || msg_l.contains("not found") || msg_l.contains("item not found") {
-                Ok(None)
-            } else {
-                Err(msg)
-            }
-        }
+async fn voxelle_signal_start(
+    app: tauri::AppHandle,
+    relay: tauri::State<'_, signal_relay::SignalRelay>,
+    port: Option<u16>,
+    allow_lan: Option<bool>,
+) -> Result<signal_relay::SignalRelayInfo, signal_relay::SignalRelayError> {
+    let info = relay.start(port, allow_lan.unwrap_or(false)).await?;
+    if let Some(state) = app.try_state::<web_update::WebUpdateState>() {
+        state.server.update_config(|c| c.signal_relay_url = Some(info.url.clone()));
     }
+    Ok(info)
 }
 
 #[tauri::command]
-fn voxelle_secret_set(key: String, value: String) -> Result<(), String> {
-    if value.len() > 256 * 1024 {
-        return Err("value too large".into());
+fn voxelle_signal_stop(
+    app: tauri::AppHandle,
+    relay: tauri::State<signal_relay::SignalRelay>,
+) -> Result<(), signal_relay::SignalRelayError> {
+    relay.stop()?;
+    if let Some(state) = app.try_state::<web_update::WebUpdateState>() {
+        state.server.update_config(|c| c.signal_relay_url = None);
     }
-    let entry = keyring_entry(&key)?;
-    entry.set_password(&value).map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[tauri::command]
-fn voxelle_secret_delete(key: String) -> Result<(), String> {
-    let entry = keyring_entry(&key)?;
-    // Ignore if not found.
-    let _ = entry.delete_credential();
-    Ok(())
+fn voxelle_signal_status(relay: tauri::State<signal_relay::SignalRelay>) -> signal_relay::SignalRelayStatus {
+    relay.status()
+}
+
+// Secrets stored with `require_auth` prompt for Touch ID, Windows Hello or a
+// native passphrase prompt first. Async so the prompt doesn't block the main thread.
+#[tauri::command]
+async fn voxelle_secret_get(
+    app: tauri::AppHandle,
+    namespace: Option<String>,
+    key: String,
+) -> Result<Option<String>, secrets::SecretError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<secrets::Secrets>().get_checked(&secrets::webview_namespace(namespace)?, &key)
+    })
+    .await
+    .map_err(|e| secrets::SecretError::Other(e.to_string()))?
+}
+
+// `require_auth` omitted keeps the key's current setting.
+#[tauri::command]
+fn voxelle_secret_set(
+    state: tauri::State<secrets::Secrets>,
+    namespace: Option<String>,
+    key: String,
+    value: String,
+    require_auth: Option<bool>,
+) -> Result<(), String> {
+    state.set(&secrets::webview_namespace(namespace)?, &key, &value, require_auth)
+}
+
+#[tauri::command]
+fn voxelle_secret_requires_auth(
+    state: tauri::State<secrets::Secrets>,
+    namespace: Option<String>,
+    key: String,
+) -> Result<bool, String> {
+    Ok(state.requires_auth(&secrets::webview_namespace(namespace)?, &key))
+}
+
+// How long one successful check covers further protected reads; 0 asks every time.
+#[tauri::command]
+fn voxelle_secret_get_auth_window(state: tauri::State<secrets::Secrets>) -> u32 {
+    state.auth_window_secs()
+}
+
+#[tauri::command]
+fn voxelle_secret_set_auth_window(state: tauri::State<secrets::Secrets>, secs: u32) -> Result<(), String> {
+    state.set_auth_window_secs(secs)
+}
+
+#[tauri::command]
+fn voxelle_secret_delete(state: tauri::State<secrets::Secrets>, namespace: Option<String>, key: String) -> Result<(), String> {
+    state.delete(&secrets::webview_namespace(namespace)?, &key)
+}
+
+#[tauri::command]
+fn voxelle_secret_list(state: tauri::State<secrets::Secrets>, namespace: Option<String>) -> Result<Vec<String>, String> {
+    state.list(&secrets::webview_namespace(namespace)?)
+}
+
+#[tauri::command]
+fn voxelle_secret_delete_namespace(state: tauri::State<secrets::Secrets>, namespace: String) -> Result<usize, String> {
+    state.delete_namespace(&secrets::webview_namespace(Some(namespace))?)
+}
+
+#[tauri::command]
+fn voxelle_secret_backend(state: tauri::State<secrets::Secrets>) -> secrets::BackendInfo {
+    state.backend_info()
+}
+
+// Unlocks a passphrase-protected file store for this session.
+#[tauri::command]
+fn voxelle_secret_unlock(state: tauri::State<secrets::Secrets>, passphrase: String) -> Result<(), String> {
+    state.unlock(&passphrase)
+}
+
+// Sets the file store's passphrase after a presence check; off the main thread
+// for the prompt and Argon2id.
+#[tauri::command]
+async fn voxelle_secret_set_passphrase(app: tauri::AppHandle, passphrase: String) -> Result<(), secrets::SecretError> {
+    tauri::async_runtime::spawn_blocking(move || app.state::<secrets::Secrets>().set_passphrase(&passphrase))
+        .await
+        .map_err(|e| secrets::SecretError::Other(e.to_string()))?
+}
+
+// Off the main thread: exports containing protected secrets prompt like `voxelle_secret_get`.
+#[tauri::command]
+async fn voxelle_secret_export(app: tauri::AppHandle, passphrase: String) -> Result<String, secret_export::SecretExportError> {
+    tauri::async_runtime::spawn_blocking(move || secret_export::export(&app.state::<secrets::Secrets>(), &passphrase))
+        .await
+        .map_err(|e| secret_export::SecretExportError::Other(e.to_string()))?
+}
+
+// Off the main thread too: Argon2id at the blob's cost parameters takes a while.
+#[tauri::command]
+async fn voxelle_secret_import(
+    app: tauri::AppHandle,
+    blob: String,
+    passphrase: String,
+    overwrite: bool,
+) -> Result<Vec<secret_export::ImportedSecret>, secret_export::SecretExportError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        secret_export::import(&app.state::<secrets::Secrets>(), &blob, &passphrase, overwrite)
+    })
+    .await
+    .map_err(|e| secret_export::SecretExportError::Other(e.to_string()))?
+}
+
+// The private key goes straight into the secret store under a namespace the
+// webview can't read; only the principal id and SPKI come back.
+#[tauri::command]
+fn voxelle_identity_create(
+    app: tauri::AppHandle,
+    state: tauri::State<secrets::Secrets>,
+    label: String,
+) -> Result<identity::Identity, identity::IdentityError> {
+    identity::create(&app, &state, &label)
+}
+
+#[tauri::command]
+fn voxelle_identity_list(app: tauri::AppHandle) -> Result<Vec<identity::Identity>, String> {
+    identity::list(&app)
+}
+
+// `context` is the envelope kind, limited to `identity::SIGNING_CONTEXTS`.
+#[tauri::command]
+fn voxelle_identity_sign(
+    app: tauri::AppHandle,
+    state: tauri::State<secrets::Secrets>,
+    principal_id: String,
+    context: String,
+    payload_json: String,
+) -> Result<voxelle_protocol::SignedEnvelope, identity::IdentityError> {
+    identity::sign(&app, &state, &principal_id, &context, &payload_json)
+}
+
+// Prompts like a protected secret read, so off the main thread.
+#[tauri::command]
+async fn voxelle_identity_delete(app: tauri::AppHandle, principal_id: String) -> Result<(), identity::IdentityError> {
+    tauri::async_runtime::spawn_blocking(move || identity::delete(&app, &app.state::<secrets::Secrets>(), &principal_id))
+        .await
+        .map_err(|e| identity::IdentityError::Other(e.to_string()))?
+}
+
+// Errors come back as `{ code, detail }` so the UI can tell "not an isnad
+// workspace" apart from IO failures.
+#[tauri::command]
+fn voxelle_board_fold(
+    app: tauri::AppHandle,
+    picked: tauri::State<board::PickedRoots>,
+    root: String,
+    create: bool,
+) -> Result<isnad::Board, board::BoardError> {
+    board::fold(&app, &picked, &root, create)
+}
+
+#[tauri::command]
+fn voxelle_board_directive(
+    app: tauri::AppHandle,
+    picked: tauri::State<board::PickedRoots>,
+    root: String,
+    directive_type: String,
+    task_id: Option<String>,
+    payload: Option<serde_json::Value>,
+    rationale: Option<String>,
+) -> Result<String, board::BoardError> {
+    let input = board::DirectiveInput { directive_type, task_id, payload, rationale };
+    board::append_directive(&app, &picked, &root, input)
+}
+
+// Reference-counted per window; a destroyed window drops its references.
+#[tauri::command]
+fn voxelle_board_watch(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    watchers: tauri::State<board_watch::BoardWatchers>,
+    picked: tauri::State<board::PickedRoots>,
+    root: String,
+) -> Result<(), board::BoardError> {
+    board_watch::watch(&app, &watchers, &picked, &root, window.label())
+}
+
+#[tauri::command]
+fn voxelle_board_unwatch(window: tauri::Window, watchers: tauri::State<board_watch::BoardWatchers>, root: String) {
+    board_watch::unwatch(&watchers, &root, window.label())
+}
+
+// Serves the board to a browser on localhost; the returned URL carries the server's token.
+#[tauri::command]
+async fn voxelle_board_serve(
+    app: tauri::AppHandle,
+    servers: tauri::State<'_, board_server::BoardServers>,
+    picked: tauri::State<'_, board::PickedRoots>,
+    root: String,
+) -> Result<board_server::BoardServeInfo, board::BoardError> {
+    board_server::serve(&app, &servers, &picked, &root).await
+}
+
+#[tauri::command]
+fn voxelle_board_stop(servers: tauri::State<board_server::BoardServers>, root: String) -> bool {
+    servers.stop(&root)
+}
+
+#[tauri::command]
+fn voxelle_board_get_settings(app: tauri::AppHandle) -> Result<board::BoardSettings, String> {
+    board::get_settings(&app)
+}
+
+#[tauri::command]
+fn voxelle_board_set_settings(app: tauri::AppHandle, settings: board::BoardSettings) -> Result<(), String> {
+    board::set_settings(&app, &settings)
+}
+
+// For things the frontend wants the user to see while the window is in the
+// background. `kind` defaults to `message` and is gated by the notification
+// settings; denied notifications fall back to bouncing or flashing the app icon.
+#[tauri::command]
+fn voxelle_notify(app: tauri::AppHandle, title: String, body: String, kind: Option<notify::NotifyKind>) -> Result<notify::NotifyOutcome, String> {
+    notify::notify(&app, kind.unwrap_or(notify::NotifyKind::Message), &title, &body)
+}
+
+#[tauri::command]
+fn voxelle_notify_get_settings(app: tauri::AppHandle) -> Result<notify::NotifySettings, String> {
+    notify::get_settings(&app)
+}
+
+#[tauri::command]
+fn voxelle_notify_set_settings(app: tauri::AppHandle, settings: notify::NotifySettings) -> Result<(), String> {
+    notify::set_settings(&app, &settings)
+}
+
+// For invites, session ids and pairing codes: cleared from the clipboard after
+// `ttl_seconds` unless the user has copied something else since. A later copy
+// replaces the pending clear.
+#[tauri::command]
+fn voxelle_copy_sensitive(
+    app: tauri::AppHandle,
+    copies: tauri::State<clipboard::SensitiveCopies>,
+    text: String,
+    ttl_seconds: u32,
+) -> Result<(), String> {
+    clipboard::copy_sensitive(&app, &copies, &text, ttl_seconds)
+}
+
+// Leaves the last sensitive copy on the clipboard; false when no clear was pending.
+#[tauri::command]
+fn voxelle_copy_cancel_clear(copies: tauri::State<clipboard::SensitiveCopies>) -> bool {
+    copies.cancel_clear()
+}
+
+// Shows the folder dialog; resolves to null when the user cancels.
+#[tauri::command]
+async fn voxelle_workspace_open(app: tauri::AppHandle) -> Result<Option<workspace::Workspace>, board::BoardError> {
+    workspace::open(&app).await
+}
+
+#[tauri::command]
+fn voxelle_workspace_recent(app: tauri::AppHandle) -> Result<Vec<workspace::Workspace>, String> {
+    workspace::recent(&app)
+}
+
+#[tauri::command]
+fn voxelle_workspace_forget(app: tauri::AppHandle, path: String) -> Result<(), String> {
+    workspace::forget(&app, &path)
+}
+
+// Called once the frontend listens for `voxelle:deep-link`; returns links that
+// arrived before then. Later links are emitted directly.
+#[tauri::command]
+fn voxelle_deep_links_ready(links: tauri::State<deep_link::DeepLinks>) -> Vec<deep_link::DeepLink> {
+    links.ready()
+}
+
+// The tail of today's log for a diagnostics view; `level_filter` keeps that
+// level and more severe ones.
+#[tauri::command]
+fn voxelle_logs_recent(
+    logs: tauri::State<logs::Logs>,
+    lines: Option<usize>,
+    level_filter: Option<String>,
+) -> Result<Vec<logs::LogEntry>, String> {
+    logs::recent(&logs, lines, level_filter.as_deref())
+}
+
+#[tauri::command]
+fn voxelle_logs_open_folder(app: tauri::AppHandle, logs: tauri::State<logs::Logs>) -> Result<(), String> {
+    logs::open_folder(&app, &logs)
 }
 
 #[tauri::command]
@@ -59,35 +355,266 @@ fn web_update_status(state: tauri::State<web_update::WebUpdateState>) -> web_upd
     web_update::status(&state)
 }
 
+// Base for building asset URLs against the local bundle server, token included.
 #[tauri::command]
-fn web_update_set_feed(state: tauri::State<web_update::WebUpdateState>, app: tauri::AppHandle, url: String) -> Result<(), String> {
-    web_update::set_feed(&state, &app, &url)
+fn web_bundle_base_url(state: tauri::State<web_update::WebUpdateState>) -> String {
+    state.server.base_url()
 }
 
 #[tauri::command]
-async fn web_update_check(state: tauri::State<'_, web_update::WebUpdateState>) -> Result<web_update::WebUpdateCheckResult, String> {
-    web_update::check(&state).await
+fn web_update_set_feed(state: tauri::State<web_update::WebUpdateState>, app: tauri::AppHandle, url: web_update::FeedUrls) -> Result<(), web_update::WebUpdateCommandError> {
+    Ok(web_update::set_feed(&state, &app, url)?)
 }
 
+// What the last check tried, feed by feed; `None` before the first check.
+#[tauri::command]
+fn web_update_last_check_details(state: tauri::State<web_update::WebUpdateState>) -> Option<web_update::LastCheck> {
+    web_update::last_check_details(&state)
+}
+
+// Newest first; `limit` defaults to DEFAULT_HISTORY_LIMIT.
+#[tauri::command]
+fn web_update_history(app: tauri::AppHandle, limit: Option<usize>) -> Result<Vec<web_update::UpdateHistoryEntry>, web_update::WebUpdateCommandError> {
+    Ok(web_update::history(&app, limit.unwrap_or(web_update::DEFAULT_HISTORY_LIMIT))?)
+}
+
+#[tauri::command]
+async fn web_update_check(
+    state: tauri::State<'_, web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+) -> Result<web_update::WebUpdateCheckResult, web_update::WebUpdateCommandError> {
+    Ok(web_update::check(&state, &app).await?)
+}
+
+// For manifests that link their release notes (`notes_url` in the check result)
+// instead of inlining them.
+#[tauri::command]
+async fn web_update_fetch_notes(
+    state: tauri::State<'_, web_update::WebUpdateState>,
+    url: String,
+) -> Result<String, web_update::WebUpdateCommandError> {
+    Ok(web_update::fetch_release_notes(&state, &url).await?)
+}
+
+// `force` installs the offer even if it's the active version or older, e.g.
+// to reinstall a bundle that looks damaged.
 #[tauri::command]
 async fn web_update_download(
     state: tauri::State<'_, web_update::WebUpdateState>,
     app: tauri::AppHandle,
-) -> Result<web_update::WebUpdateDownloadResult, String> {
-    web_update::download_and_activate(&state, &app).await
+    allow_downgrade: Option<bool>,
+    force: Option<bool>,
+) -> Result<web_update::WebUpdateDownloadResult, web_update::WebUpdateCommandError> {
+    let settings = auto_update::get_settings(&state);
+    let deadline = settings.download_deadline(false);
+    let (allow_downgrade, force) = (allow_downgrade.unwrap_or(false), force.unwrap_or(false));
+    Ok(web_update::download_and_activate(&state, &app, allow_downgrade, force, settings.apply_mode, deadline).await?)
+}
+
+#[tauri::command]
+fn web_update_get_settings(state: tauri::State<web_update::WebUpdateState>) -> auto_update::WebUpdateSettings {
+    auto_update::get_settings(&state)
+}
+
+#[tauri::command]
+fn web_update_set_settings(
+    state: tauri::State<web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+    settings: auto_update::WebUpdateSettings,
+) -> Result<(), web_update::WebUpdateCommandError> {
+    Ok(auto_update::set_settings(&state, &app, settings)?)
+}
+
+// Checks offering exactly this version report `skipped`; `None` un-skips.
+#[tauri::command]
+fn web_update_skip_version(
+    state: tauri::State<web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+    version: Option<String>,
+) -> Result<Option<String>, web_update::WebUpdateCommandError> {
+    Ok(auto_update::skip_version(&state, &app, version)?)
+}
+
+#[tauri::command]
+fn web_update_get_skipped_version(state: tauri::State<web_update::WebUpdateState>) -> Option<String> {
+    auto_update::get_decisions(&state).skipped_version
+}
+
+// Background checks stop announcing updates for `hours`; 0 ends the snooze.
+// Returns the deadline in unix milliseconds.
+#[tauri::command]
+fn web_update_snooze(
+    state: tauri::State<web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+    hours: u32,
+) -> Result<Option<u64>, web_update::WebUpdateCommandError> {
+    Ok(auto_update::snooze(&state, &app, hours)?)
+}
+
+#[tauri::command]
+fn web_update_get_snooze(state: tauri::State<web_update::WebUpdateState>) -> Option<u64> {
+    auto_update::get_decisions(&state).snoozed_until
+}
+
+#[tauri::command]
+fn web_update_get_network_config(state: tauri::State<web_update::WebUpdateState>) -> update_network::NetworkConfig {
+    update_network::get_config(&state)
+}
+
+#[tauri::command]
+fn web_update_set_network_config(
+    state: tauri::State<web_update::WebUpdateState>,
+    secrets: tauri::State<secrets::Secrets>,
+    app: tauri::AppHandle,
+    config: update_network::NetworkConfig,
+) -> Result<(), web_update::WebUpdateCommandError> {
+    Ok(update_network::set_config(&state, &app, &secrets, config)?)
+}
+
+#[tauri::command]
+fn web_update_set_channel(
+    state: tauri::State<web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+    channel: web_update::UpdateChannel,
+) -> Result<(), web_update::WebUpdateCommandError> {
+    Ok(auto_update::set_channel(&state, &app, channel)?)
+}
+
+// Setting an override needs `confirm` in release builds; clearing it never does.
+#[tauri::command]
+fn web_update_set_dev_override(
+    state: tauri::State<web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+    path_or_url: Option<String>,
+    confirm: Option<bool>,
+) -> Result<Option<web_update::DevOverride>, web_update::WebUpdateCommandError> {
+    Ok(web_update::set_dev_override(&state, &app, path_or_url, confirm.unwrap_or(false))?)
+}
+
+// Async so the deletions run off the main thread.
+#[tauri::command]
+async fn web_update_reset(
+    state: tauri::State<'_, web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+    keep_settings: bool,
+) -> Result<web_update::WebUpdateResetResult, web_update::WebUpdateCommandError> {
+    Ok(web_update::reset(&state, &app, keep_settings)?)
+}
+
+#[tauri::command]
+fn voxelle_get_storage_root(app: tauri::AppHandle) -> Result<web_update::StorageLocation, String> {
+    web_update::storage_location(&app)
+}
+
+// Moves bundles, downloads and update state under `path`, or back to the OS
+// cache dir with `None`. Async so the copy runs off the main thread.
+#[tauri::command]
+async fn voxelle_set_storage_root(
+    state: tauri::State<'_, web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+    path: Option<String>,
+) -> Result<web_update::StorageMoveResult, web_update::WebUpdateCommandError> {
+    Ok(web_update::move_storage(&state, &app, path.as_deref())?)
+}
+
+#[tauri::command]
+fn web_update_rollback(
+    state: tauri::State<web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+) -> Result<web_update::WebUpdateRollbackResult, web_update::WebUpdateCommandError> {
+    Ok(web_update::rollback(&state, &app)?)
+}
+
+#[tauri::command]
+fn web_update_prune(
+    state: tauri::State<web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+    keep: Option<usize>,
+) -> Result<web_update::WebUpdatePruneResult, web_update::WebUpdateCommandError> {
+    let _op = state.operation.try_begin(web_update::UpdateOperation::Prune)?;
+    let keep = match keep {
+        Some(k) => {
+            web_update::persist_keep_bundles(&app, k)?;
+            k
+        }
+        None => web_update::load_persisted_keep_bundles(&app)?,
+    };
+    Ok(web_update::prune_bundles(&app, keep)?)
+}
+
+#[tauri::command]
+fn web_update_verify_active(
+    state: tauri::State<web_update::WebUpdateState>,
+    app: tauri::AppHandle,
+) -> Result<web_update::BundleVerifyReport, web_update::WebUpdateCommandError> {
+    Ok(web_update::verify_active(&state, &app)?)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .plugin(tauri_plugin_deep_link::init())
         .setup(|app| {
+            // First, so everything after it lands in the log file.
+            app.manage(logs::init(app.handle()));
+
+            // Then, before anything else starts: a second launch (say, an invite link
+            // clicked while the app is open) hands its arguments to the running
+            // instance and exits instead of starting its own servers and watchers.
+            #[cfg(desktop)]
+            let instance = match single_instance::acquire(app.handle()) {
+                Ok(single_instance::Launch::Primary(instance)) => instance,
+                Ok(single_instance::Launch::Forwarded) => std::process::exit(0),
+                Err(e) => {
+                    tracing::error!("{e}");
+                    app.state::<logs::Logs>().flush();
+                    std::process::exit(1);
+                }
+            };
+
+            // Secrets and the board work in dev too, so set them up before the early return.
+            app.manage(secrets::Secrets::open(app.handle())?);
+            let picked = board::PickedRoots::default();
+            workspace::restore_picked(app.handle(), &picked);
+            app.manage(picked);
+            app.manage(board_watch::BoardWatchers::default());
+            app.manage(board_server::BoardServers::default());
+            app.manage(signal_relay::SignalRelay::default());
+            app.manage(notify::Notifier::default());
+            app.manage(clipboard::SensitiveCopies::default());
+            notify::listen(app.handle());
+
+            app.manage(deep_link::DeepLinks::default());
+            // Installed builds register the scheme at install time; this covers
+            // dev runs and AppImages on Linux and Windows.
+            #[cfg(any(windows, target_os = "linux"))]
+            let _ = app.deep_link().register_all();
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| deep_link::handle(&handle, &event.urls()));
+            // The link the app was launched with, if any.
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                deep_link::handle(app.handle(), &urls);
+            }
+            // Links from later launches take the same path, once it's set up.
+            #[cfg(desktop)]
+            {
+                single_instance::listen(app.handle(), &instance)?;
+                app.manage(instance);
+            }
+
             // In dev, keep using the configured devUrl.
             if cfg!(debug_assertions) {
                 return Ok(());
             }
 
             let embedded_version = env!("CARGO_PKG_VERSION").to_string();
-            let persisted_active = web_update::load_persisted_active_version(&app.handle()).unwrap_or_default();
+            // Finish or undo an activation a crash interrupted, before anything reads the active version.
+            web_update::recover_activation(app.handle());
+            // An update downloaded in `on_next_launch` mode becomes active now.
+            if let Some(v) = web_update::apply_pending_activation(app.handle(), &embedded_version) {
+                tracing::info!("activated pending web update {v}");
+            }
+            let persisted_active = web_update::load_persisted_active_version(app.handle()).unwrap_or_default();
             let active_version = if persisted_active.trim().is_empty() {
                 embedded_version.clone()
             } else {
@@ -105,43 +632,178 @@ pub fn run() {
                 }
             };
 
-            let root_dir = web_update::ensure_embedded_bundle(&app.handle(), embedded_zip, &active_version)?;
+            // Falls back to another bundle if the active one was damaged on disk.
+            let (active_version, root_dir, repair) =
+                web_update::ensure_active_bundle(app.handle(), embedded_zip, &embedded_version, &active_version)?;
             // Ensure active version is persisted so status works and later updates compare correctly.
-            let _ = web_update::persist_active_version(&app.handle(), &active_version);
+            let _ = web_update::persist_active_version(app.handle(), &active_version);
+            // Finish removing bundles an earlier prune couldn't delete.
+            let _ = web_update::retry_pending_prunes(app.handle());
+            // Extraction dirs left behind by a crash mid-install.
+            let _ = web_update::sweep_orphaned_tmp_dirs(app.handle());
 
-            // Start localhost server that serves the currently active bundle from disk.
-            let server = web_update::WebBundleServer::start(root_dir)?;
+            // Start localhost server that serves the currently active bundle from disk,
+            // on last launch's port if possible so the webview keeps its origin and storage.
+            let preferred_port = web_update::load_persisted_server_port(app.handle()).unwrap_or_default();
+            let server = web_update::WebBundleServer::start(root_dir, preferred_port)?;
+            if preferred_port != Some(server.port()) {
+                let _ = web_update::persist_server_port(app.handle(), server.port());
+            }
 
-            // Restore persisted feed URL (optional; can be empty).
-            let feed_url = web_update::load_persisted_feed_url(&app.handle()).unwrap_or_default();
+            // Restore persisted feed URLs (optional; can be empty).
+            let feed_urls = web_update::load_persisted_feed_urls(app.handle()).unwrap_or_default();
+            let settings = auto_update::load_persisted_settings(app.handle()).unwrap_or_default();
+            let decisions = auto_update::load_persisted_decisions(app.handle()).unwrap_or_default();
+            let active_channel = web_update::load_persisted_active_channel(app.handle()).unwrap_or_default();
+            let last_check = web_update::load_persisted_last_check(app.handle()).unwrap_or_default();
+            let last_activation = web_update::load_persisted_last_activation(app.handle()).unwrap_or_default();
+            let (network, client) = update_network::load_persisted(app.handle(), &app.state::<secrets::Secrets>());
+            server.update_config(|c| {
+                c.active_version = active_version.clone();
+                c.active_channel = active_channel;
+            });
 
             app.manage(web_update::WebUpdateState {
                 server: server.clone(),
                 active_version: std::sync::Arc::new(std::sync::Mutex::new(active_version.clone())),
-                feed_url: std::sync::Arc::new(std::sync::Mutex::new(feed_url)),
+                active_channel: std::sync::Arc::new(std::sync::Mutex::new(active_channel)),
+                embedded_version: embedded_version.clone(),
+                embedded_zip,
+                feed_urls: std::sync::Arc::new(std::sync::Mutex::new(feed_urls)),
+                settings: std::sync::Arc::new(std::sync::Mutex::new(settings)),
+                settings_changed: Default::default(),
+                decisions: std::sync::Arc::new(std::sync::Mutex::new(decisions)),
+                network: std::sync::Arc::new(std::sync::Mutex::new(network)),
+                client: std::sync::Arc::new(std::sync::Mutex::new(client)),
+                operation: Default::default(),
+                last_check: std::sync::Arc::new(std::sync::Mutex::new(last_check)),
+                last_activation: std::sync::Arc::new(std::sync::Mutex::new(last_activation)),
+                repair,
+                dev_override: Default::default(),
             });
 
+            #[cfg(desktop)]
+            tray::create(app.handle());
+
             // Navigate the main window to the localhost server.
-            if let Some(w) = app.get_webview_window("main") {
-                let url: tauri::Url = format!("http://127.0.0.1:{}/", server.port())
-                    .parse()
-                    .expect("localhost URL should be parseable");
-                w.navigate(url).map_err(|e| e.to_string())?;
-            }
+            web_update::navigate_main_window(app.handle(), &server)?;
+
+            // Periodic update checks; stopped in the Exit handler below.
+            let task = auto_update::AutoUpdateTask::default();
+            task.start(app.handle().clone());
+            app.manage(task);
 
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                if let Some(watchers) = window.try_state::<board_watch::BoardWatchers>() {
+                    watchers.remove_window(window.label());
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
-            greet,
+            voxelle_app_info,
+            voxelle_signal_start,
+            voxelle_signal_stop,
+            voxelle_signal_status,
             voxelle_secret_get,
             voxelle_secret_set,
+            voxelle_secret_requires_auth,
+            voxelle_secret_get_auth_window,
+            voxelle_secret_set_auth_window,
             voxelle_secret_delete,
+            voxelle_secret_list,
+            voxelle_secret_delete_namespace,
+            voxelle_secret_backend,
+            voxelle_secret_unlock,
+            voxelle_secret_set_passphrase,
+            voxelle_secret_export,
+            voxelle_secret_import,
+            voxelle_identity_create,
+            voxelle_identity_list,
+            voxelle_identity_sign,
+            voxelle_identity_delete,
+            voxelle_board_fold,
+            voxelle_board_directive,
+            voxelle_board_watch,
+            voxelle_board_unwatch,
+            voxelle_board_serve,
+            voxelle_board_stop,
+            voxelle_board_get_settings,
+            voxelle_board_set_settings,
+            voxelle_notify,
+            voxelle_notify_get_settings,
+            voxelle_notify_set_settings,
+            voxelle_copy_sensitive,
+            voxelle_copy_cancel_clear,
+            voxelle_workspace_open,
+            voxelle_workspace_recent,
+            voxelle_workspace_forget,
+            voxelle_deep_links_ready,
+            voxelle_logs_recent,
+            voxelle_logs_open_folder,
             web_update_status,
+            web_bundle_base_url,
             web_update_set_feed,
+            web_update_last_check_details,
+            web_update_history,
             web_update_check,
-            web_update_download
+            web_update_fetch_notes,
+            web_update_download,
+            web_update_rollback,
+            web_update_reset,
+            voxelle_get_storage_root,
+            voxelle_set_storage_root,
+            web_update_set_dev_override,
+            web_update_prune,
+            web_update_verify_active,
+            web_update_get_settings,
+            web_update_set_settings,
+            web_update_skip_version,
+            web_update_get_skipped_version,
+            web_update_snooze,
+            web_update_get_snooze,
+            web_update_get_network_config,
+            web_update_set_network_config,
+            web_update_set_channel
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // First, so a launch from here on waits to take over rather
+                // than handing its link to a closing app.
+                #[cfg(desktop)]
+                if let Some(instance) = app.try_state::<single_instance::Instance>() {
+                    instance.close();
+                }
+                if let Some(task) = app.try_state::<auto_update::AutoUpdateTask>() {
+                    task.stop();
+                }
+                if let Some(state) = app.try_state::<web_update::WebUpdateState>() {
+                    state.server.clone().shutdown();
+                }
+                if let Some(watchers) = app.try_state::<board_watch::BoardWatchers>() {
+                    watchers.clear();
+                }
+                if let Some(servers) = app.try_state::<board_server::BoardServers>() {
+                    servers.stop_all();
+                }
+                if let Some(relay) = app.try_state::<signal_relay::SignalRelay>() {
+                    relay.shutdown();
+                }
+                if let Some(copies) = app.try_state::<clipboard::SensitiveCopies>() {
+                    clipboard::clear_pending(app, &copies);
+                }
+                // Last, so whatever the steps above logged reaches the file.
+                if let Some(logs) = app.try_state::<logs::Logs>() {
+                    logs.flush();
+                }
+            }
+        });
 }
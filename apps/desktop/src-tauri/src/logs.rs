@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+use tracing_subscriber::prelude::*;
+
+const LOG_FILE_PREFIX: &str = "voxelle";
+const LOG_FILE_SUFFIX: &str = "log";
+/// One file a day; the appender deletes the oldest beyond this many.
+pub const KEEP_LOG_FILES: usize = 7;
+pub const DEFAULT_RECENT_LINES: usize = 200;
+pub const MAX_RECENT_LINES: usize = 5000;
+// Only the end of today's file is read for `recent`; a busy day can be large.
+const MAX_TAIL_BYTES: u64 = 4 * 1024 * 1024;
+// Overrides the default `info` filter, in `RUST_LOG` syntax (e.g. `appsdesktop_lib=debug`).
+const FILTER_ENV: &str = "VOXELLE_LOG";
+
+/// Managed state: where the log files go, and the guard that flushes the
+/// background writer. `None` dir when the app log dir couldn't be created.
+pub struct Logs {
+    dir: Option<PathBuf>,
+    guard: Mutex<Option<tracing_appender::non_blocking::WorkerGuard>>,
+}
+
+impl Logs {
+    pub fn dir(&self) -> Result<&Path, String> {
+        self.dir.as_deref().ok_or_else(|| "file logging is unavailable".to_string())
+    }
+
+    /// Writes out whatever the background writer still holds; called on exit.
+    pub fn flush(&self) {
+        if let Ok(mut g) = self.guard.lock() {
+            g.take();
+        }
+    }
+}
+
+// The file's format. Shared with tests that check what ends up in it.
+pub(crate) fn file_layer<S, W>(writer: W) -> impl tracing_subscriber::Layer<S>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_ansi(false)
+        .with_writer(writer)
+}
+
+/// Logs to stderr as before, and to daily JSON-lines files in the app log dir.
+/// File writes go through a background thread, so a slow disk never holds up
+/// a command; when its buffer is full, lines are dropped instead.
+pub fn init(app: &tauri::AppHandle) -> Logs {
+    let filter = tracing_subscriber::EnvFilter::try_from_env(FILTER_ENV)
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    let console = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    let (file, dir, guard) = match open_appender(app) {
+        Ok((dir, appender)) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(file_layer(writer)), Some(dir), Some(guard))
+        }
+        Err(e) => {
+            eprintln!("logging to the console only: {e}");
+            (None, None, None)
+        }
+    };
+    if let Err(e) = tracing_subscriber::registry().with(filter).with(console).with(file).try_init() {
+        eprintln!("logging was already set up: {e}");
+    }
+    Logs { dir, guard: Mutex::new(guard) }
+}
+
+fn open_appender(app: &tauri::AppHandle) -> Result<(PathBuf, tracing_appender::rolling::RollingFileAppender), String> {
+    let dir = app.path().app_log_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let appender = tracing_appender::rolling::RollingFileAppender::builder()
+        .rotation(tracing_appender::rolling::Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(KEEP_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| e.to_string())?;
+    Ok((dir, appender))
+}
+
+/// One line of the log file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// The event's other fields.
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    /// Names of the spans the event happened in, outermost first.
+    pub spans: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RawEntry {
+    timestamp: String,
+    level: String,
+    target: String,
+    #[serde(default)]
+    fields: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    spans: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl From<RawEntry> for LogEntry {
+    fn from(mut raw: RawEntry) -> Self {
+        let message = match raw.fields.remove("message") {
+            Some(serde_json::Value::String(s)) => s,
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+        let spans = raw
+            .spans
+            .iter()
+            .filter_map(|s| s.get("name").and_then(|n| n.as_str()).map(str::to_string))
+            .collect();
+        Self { timestamp: raw.timestamp, level: raw.level, target: raw.target, message, fields: raw.fields, spans }
+    }
+}
+
+fn parse_level(level: &str) -> Result<tracing::Level, String> {
+    level.trim().parse().map_err(|_| format!("unknown log level {level:?}; use error, warn, info, debug or trace"))
+}
+
+// Files are named `voxelle.<yyyy-mm-dd>.log`, so the latest sorts last.
+fn current_log_file(dir: &Path) -> Result<Option<PathBuf>, String> {
+    let mut logs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+                n.starts_with(&format!("{LOG_FILE_PREFIX}.")) && n.ends_with(&format!(".{LOG_FILE_SUFFIX}"))
+            })
+        })
+        .collect();
+    logs.sort();
+    Ok(logs.pop())
+}
+
+fn read_tail(path: &Path, max_bytes: u64) -> Result<String, String> {
+    let mut f = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let len = f.metadata().map_err(|e| e.to_string())?.len();
+    let start = len.saturating_sub(max_bytes);
+    f.seek(std::io::SeekFrom::Start(start)).map_err(|e| e.to_string())?;
+    let mut bytes = Vec::new();
+    f.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    // Started mid-file: the first line is probably cut off.
+    Ok(match (start > 0, text.split_once('\n')) {
+        (true, Some((_, rest))) => rest.to_string(),
+        _ => text,
+    })
+}
+
+/// The last `lines` entries of the current log at `level_filter` or more
+/// severe, oldest first. Lines that don't parse (say, cut short by a crash)
+/// are skipped.
+pub fn recent_in(dir: &Path, lines: Option<usize>, level_filter: Option<&str>) -> Result<Vec<LogEntry>, String> {
+    let lines = lines.unwrap_or(DEFAULT_RECENT_LINES).min(MAX_RECENT_LINES);
+    let max_level = level_filter.map(parse_level).transpose()?.unwrap_or(tracing::Level::TRACE);
+    let Some(path) = current_log_file(dir)? else {
+        return Ok(Vec::new());
+    };
+    let text = read_tail(&path, MAX_TAIL_BYTES)?;
+    let mut out: Vec<LogEntry> = text
+        .lines()
+        .rev()
+        .filter_map(|l| serde_json::from_str::<RawEntry>(l).ok())
+        .filter(|e| parse_level(&e.level).is_ok_and(|level| level <= max_level))
+        .take(lines)
+        .map(LogEntry::from)
+        .collect();
+    out.reverse();
+    Ok(out)
+}
+
+pub fn recent(logs: &Logs, lines: Option<usize>, level_filter: Option<&str>) -> Result<Vec<LogEntry>, String> {
+    recent_in(logs.dir()?, lines, level_filter)
+}
+
+pub fn open_folder(app: &tauri::AppHandle, logs: &Logs) -> Result<(), String> {
+    use tauri_plugin_opener::OpenerExt;
+    let dir = logs.dir()?;
+    app.opener().open_path(dir.to_string_lossy(), None::<&str>).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(level: &str, message: &str) -> String {
+        serde_json::json!({
+            "timestamp": "2026-10-16T12:00:00.000000Z",
+            "level": level,
+            "fields": { "message": message, "version": "0.2.0" },
+            "target": "appsdesktop_lib::web_update",
+            "span": { "name": "activate" },
+            "spans": [{ "name": "download" }, { "name": "activate" }],
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn recent_entries_come_from_the_latest_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(recent_in(dir.path(), None, None), Ok(Vec::new()));
+
+        std::fs::write(dir.path().join("voxelle.2026-10-15.log"), line("ERROR", "yesterday") + "\n").unwrap();
+        let today = [
+            line("INFO", "checking"),
+            line("WARN", "feed failed"),
+            "{\"timestamp\":\"2026-10-16T12:00:01".to_string(),
+            line("DEBUG", "chunk"),
+            line("ERROR", "activation failed"),
+        ];
+        std::fs::write(dir.path().join("voxelle.2026-10-16.log"), today.join("\n") + "\n").unwrap();
+        std::fs::write(dir.path().join("other.log"), line("ERROR", "not ours")).unwrap();
+
+        let messages = |lines, level| -> Vec<String> {
+            recent_in(dir.path(), lines, level).unwrap().into_iter().map(|e| e.message).collect()
+        };
+        assert_eq!(messages(None, None), vec!["checking", "feed failed", "chunk", "activation failed"]);
+        assert_eq!(messages(None, Some("warn")), vec!["feed failed", "activation failed"]);
+        assert_eq!(messages(Some(1), Some("info")), vec!["activation failed"]);
+
+        let entry = recent_in(dir.path(), Some(1), None).unwrap().remove(0);
+        assert_eq!(entry.level, "ERROR");
+        assert_eq!(entry.spans, vec!["download", "activate"]);
+        assert_eq!(entry.fields.get("version"), Some(&serde_json::json!("0.2.0")));
+        assert!(!entry.fields.contains_key("message"));
+        assert!(recent_in(dir.path(), None, Some("loud")).is_err());
+    }
+
+    #[test]
+    fn tails_start_on_a_whole_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("voxelle.2026-10-16.log");
+        std::fs::write(&path, "first line\nsecond line\nthird\n").unwrap();
+        assert_eq!(read_tail(&path, 1024).unwrap(), "first line\nsecond line\nthird\n");
+        assert_eq!(read_tail(&path, 16).unwrap(), "third\n");
+    }
+}
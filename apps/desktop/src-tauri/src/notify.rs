@@ -0,0 +1,402 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Listener, Manager};
+use tauri_plugin_notification::{NotificationExt, PermissionState};
+
+use crate::web_update::EVENT_WEB_UPDATE_READY;
+
+const SETTINGS_FILE: &str = "notification_settings.json";
+// Alerts of one kind raised within this window go out as a single notification,
+// so a burst of directives is one summary rather than twenty.
+const COALESCE: Duration = Duration::from_secs(3);
+// Bodies of a summary list this many alerts and then "…".
+const SUMMARY_LINES: usize = 3;
+const MAX_TITLE_LEN: usize = 256;
+const MAX_BODY_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyKind {
+    /// A web update finished downloading and was activated.
+    UpdateReady,
+    /// A directive arrived that the agent hasn't acknowledged.
+    Directive,
+    /// A card moved into `blocked`.
+    Blocked,
+    /// Anything else the frontend wants to say.
+    Message,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifySettings {
+    pub update_ready: bool,
+    pub directive: bool,
+    pub blocked: bool,
+    pub message: bool,
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        Self { update_ready: true, directive: true, blocked: true, message: true }
+    }
+}
+
+impl NotifySettings {
+    fn enabled(&self, kind: NotifyKind) -> bool {
+        match kind {
+            NotifyKind::UpdateReady => self.update_ready,
+            NotifyKind::Directive => self.directive,
+            NotifyKind::Blocked => self.blocked,
+            NotifyKind::Message => self.message,
+        }
+    }
+}
+
+fn load_settings_in(dir: &Path) -> NotifySettings {
+    std::fs::read(dir.join(SETTINGS_FILE))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings_in(dir: &Path, settings: &NotifySettings) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(SETTINGS_FILE), json).map_err(|e| e.to_string())
+}
+
+fn settings_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+pub fn get_settings(app: &tauri::AppHandle) -> Result<NotifySettings, String> {
+    Ok(load_settings_in(&settings_dir(app)?))
+}
+
+pub fn set_settings(app: &tauri::AppHandle, settings: &NotifySettings) -> Result<(), String> {
+    save_settings_in(&settings_dir(app)?, settings)?;
+    // Turning directive notifications off takes the unread count off the icon too.
+    refresh_badge(app);
+    Ok(())
+}
+
+fn enabled(app: &tauri::AppHandle, kind: NotifyKind) -> bool {
+    get_settings(app).map(|s| s.enabled(kind)).unwrap_or(true)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Alert {
+    title: String,
+    body: String,
+}
+
+// One notification standing in for every alert of `kind` in a batch.
+fn summarize(kind: NotifyKind, mut alerts: Vec<Alert>) -> Option<Alert> {
+    let n = alerts.len();
+    // Only the newest update matters; earlier ones were replaced by it.
+    if n <= 1 || kind == NotifyKind::UpdateReady {
+        return alerts.pop();
+    }
+    let title = match kind {
+        NotifyKind::Directive => format!("{n} new directives"),
+        NotifyKind::Blocked => format!("{n} tasks blocked"),
+        NotifyKind::UpdateReady | NotifyKind::Message => format!("{n} notifications"),
+    };
+    let mut lines: Vec<&str> = alerts.iter().take(SUMMARY_LINES).map(|a| a.body.as_str()).collect();
+    if n > SUMMARY_LINES {
+        lines.push("…");
+    }
+    Some(Alert { title, body: lines.join("\n") })
+}
+
+// Alerts waiting out the coalescing window, per kind.
+#[derive(Default)]
+struct Pending(HashMap<NotifyKind, Vec<Alert>>);
+
+impl Pending {
+    /// Queues `alert`. True when it starts a batch, which the caller flushes
+    /// after `COALESCE`.
+    fn push(&mut self, kind: NotifyKind, alert: Alert) -> bool {
+        let queued = self.0.entry(kind).or_default();
+        queued.push(alert);
+        queued.len() == 1
+    }
+
+    fn take(&mut self, kind: NotifyKind) -> Option<Alert> {
+        summarize(kind, self.0.remove(&kind)?)
+    }
+}
+
+/// Batches Rust-initiated alerts and tracks unread directives per watched
+/// workspace for the dock/taskbar badge.
+#[derive(Default)]
+pub struct Notifier {
+    pending: Mutex<Pending>,
+    unread: Mutex<HashMap<PathBuf, usize>>,
+}
+
+/// What `notify` did with a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyOutcome {
+    Shown,
+    /// Notifications are denied or unavailable; the dock icon bounced or the
+    /// taskbar button flashed instead, where the platform supports it.
+    Attention,
+    /// Turned off for this kind in the notification settings.
+    Disabled,
+}
+
+fn validate(title: &str, body: &str) -> Result<(), String> {
+    if title.trim().is_empty() {
+        return Err("title must be non-empty".into());
+    }
+    if title.len() > MAX_TITLE_LEN {
+        return Err(format!("title exceeds {MAX_TITLE_LEN} bytes"));
+    }
+    if body.len() > MAX_BODY_LEN {
+        return Err(format!("body exceeds {MAX_BODY_LEN} bytes"));
+    }
+    Ok(())
+}
+
+fn deliver(app: &tauri::AppHandle, alert: &Alert) -> NotifyOutcome {
+    let granted = app.notification().permission_state().is_ok_and(|p| p == PermissionState::Granted);
+    if granted && app.notification().builder().title(&alert.title).body(&alert.body).show().is_ok() {
+        return NotifyOutcome::Shown;
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.request_user_attention(Some(tauri::UserAttentionType::Informational));
+    }
+    NotifyOutcome::Attention
+}
+
+/// Shows a notification for the frontend right away; the frontend decides
+/// when one is worth showing, so neither focus nor batching applies.
+pub fn notify(app: &tauri::AppHandle, kind: NotifyKind, title: &str, body: &str) -> Result<NotifyOutcome, String> {
+    validate(title, body)?;
+    if !enabled(app, kind) {
+        return Ok(NotifyOutcome::Disabled);
+    }
+    Ok(deliver(app, &Alert { title: title.to_string(), body: body.to_string() }))
+}
+
+fn main_window_focused(app: &tauri::AppHandle) -> bool {
+    app.get_webview_window("main")
+        .is_some_and(|w| w.is_focused().unwrap_or(false) && !w.is_minimized().unwrap_or(false))
+}
+
+// Raised by the app itself. Dropped while the main window has focus, since the
+// user is looking at it; otherwise batched per kind and shown after `COALESCE`.
+fn raise(app: &tauri::AppHandle, kind: NotifyKind, alert: Alert) {
+    if main_window_focused(app) || !enabled(app, kind) {
+        return;
+    }
+    let Some(notifier) = app.try_state::<Notifier>() else { return };
+    let first = notifier.pending.lock().is_ok_and(|mut g| g.push(kind, alert));
+    if !first {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(COALESCE).await;
+        let batch = app.state::<Notifier>().pending.lock().ok().and_then(|mut g| g.take(kind));
+        if let Some(alert) = batch {
+            deliver(&app, &alert);
+        }
+    });
+}
+
+/// Notifies when a web update is ready. Needs `Notifier` to be managed.
+pub fn listen(app: &tauri::AppHandle) {
+    let handle = app.clone();
+    app.listen_any(EVENT_WEB_UPDATE_READY, move |event| {
+        let version = serde_json::from_str::<String>(event.payload()).unwrap_or_default();
+        let body = format!("Voxelle web {version} has been installed; reload to start using it.");
+        raise(&handle, NotifyKind::UpdateReady, Alert { title: "Update ready".into(), body });
+    });
+}
+
+// Puts the unread directive count of every watched workspace on the app icon,
+// where the platform has badges.
+fn refresh_badge(app: &tauri::AppHandle) {
+    let Some(notifier) = app.try_state::<Notifier>() else { return };
+    let total: usize = notifier.unread.lock().map(|g| g.values().sum()).unwrap_or(0);
+    let count = (total > 0 && enabled(app, NotifyKind::Directive)).then_some(total as i64);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_badge_count(count);
+    }
+}
+
+fn set_unread(app: &tauri::AppHandle, root: &Path, count: usize) {
+    let Some(notifier) = app.try_state::<Notifier>() else { return };
+    if let Ok(mut g) = notifier.unread.lock() {
+        if count == 0 {
+            g.remove(root);
+        } else {
+            g.insert(root.to_path_buf(), count);
+        }
+    }
+    refresh_badge(app);
+}
+
+// The parts of a board that notifications are about.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct BoardSnapshot {
+    // Directive id -> title of the card it is for.
+    unread: BTreeMap<String, String>,
+    // Task id -> title.
+    blocked: BTreeMap<String, String>,
+}
+
+impl BoardSnapshot {
+    fn of(board: &isnad::Board) -> Self {
+        let title = |task_id: &str| board.cards.get(task_id).map_or_else(|| task_id.to_string(), |c| c.title.clone());
+        let unread = board
+            .unread_directives
+            .iter()
+            .flat_map(|(task_id, ids)| ids.iter().map(move |id| (id.clone(), title(task_id))))
+            .collect();
+        let blocked = board
+            .cards
+            .values()
+            .filter(|c| c.status == "blocked")
+            .map(|c| (c.task_id.clone(), c.title.clone()))
+            .collect();
+        Self { unread, blocked }
+    }
+
+    // What appeared between `self` and `next` that is worth telling the user about.
+    fn alerts(&self, next: &Self, workspace: &str) -> Vec<(NotifyKind, Alert)> {
+        let directives = next.unread.iter().filter(|(id, _)| !self.unread.contains_key(*id)).map(|(_, title)| {
+            (NotifyKind::Directive, Alert { title: "New directive".into(), body: format!("{title} ({workspace})") })
+        });
+        let blocked = next.blocked.iter().filter(|(id, _)| !self.blocked.contains_key(*id)).map(|(_, title)| {
+            (NotifyKind::Blocked, Alert { title: "Task blocked".into(), body: format!("{title} ({workspace})") })
+        });
+        directives.chain(blocked).collect()
+    }
+}
+
+/// Follows one watched workspace: alerts on new unread directives and cards
+/// entering `blocked`, and keeps its unread count on the badge until dropped
+/// along with the watcher.
+pub struct BoardAlerts {
+    app: tauri::AppHandle,
+    root: PathBuf,
+    workspace: String,
+    last: Mutex<BoardSnapshot>,
+}
+
+impl BoardAlerts {
+    /// What the board holds now is the baseline; only later changes alert.
+    pub fn new(app: &tauri::AppHandle, root: &Path) -> Self {
+        let last = isnad::fold(root).map(|b| BoardSnapshot::of(&b)).unwrap_or_default();
+        set_unread(app, root, last.unread.len());
+        let workspace = root.file_name().map_or_else(|| root.display().to_string(), |n| n.to_string_lossy().into_owned());
+        Self { app: app.clone(), root: root.to_path_buf(), workspace, last: Mutex::new(last) }
+    }
+
+    /// Called with each batch of changes to the workspace's logs.
+    pub fn changed(&self) {
+        // A log caught mid-append folds again on the next change.
+        let Ok(board) = isnad::fold(&self.root) else { return };
+        let next = BoardSnapshot::of(&board);
+        let Ok(mut last) = self.last.lock() else { return };
+        for (kind, a) in last.alerts(&next, &self.workspace) {
+            raise(&self.app, kind, a);
+        }
+        set_unread(&self.app, &self.root, next.unread.len());
+        *last = next;
+    }
+}
+
+impl Drop for BoardAlerts {
+    fn drop(&mut self) {
+        set_unread(&self.app, &self.root, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directive(body: &str) -> Alert {
+        Alert { title: "New directive".into(), body: body.into() }
+    }
+
+    #[test]
+    fn bursts_become_one_summary() {
+        let mut pending = Pending::default();
+        assert!(pending.push(NotifyKind::Directive, directive("a")));
+        for body in ["b", "c", "d", "e"] {
+            assert!(!pending.push(NotifyKind::Directive, directive(body)));
+        }
+        assert!(pending.push(NotifyKind::Blocked, directive("x")), "each kind batches on its own");
+
+        let summary = pending.take(NotifyKind::Directive).unwrap();
+        assert_eq!(summary, Alert { title: "5 new directives".into(), body: "a\nb\nc\n…".into() });
+        assert_eq!(pending.take(NotifyKind::Directive), None);
+        assert_eq!(pending.take(NotifyKind::Blocked), Some(directive("x")));
+        assert!(pending.push(NotifyKind::Directive, directive("f")), "taking ends the batch");
+
+        let updates = vec![directive("0.2.0"), directive("0.3.0")];
+        assert_eq!(summarize(NotifyKind::UpdateReady, updates), Some(directive("0.3.0")));
+    }
+
+    #[test]
+    fn only_new_directives_and_newly_blocked_cards_alert() {
+        let map = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let before = BoardSnapshot { unread: map(&[("D1", "Ship it")]), blocked: map(&[("T1", "Ship it")]) };
+        let after = BoardSnapshot {
+            unread: map(&[("D1", "Ship it"), ("D2", "Write docs")]),
+            blocked: map(&[("T1", "Ship it"), ("T2", "Write docs")]),
+        };
+        let alerts = before.alerts(&after, "voxelle");
+        assert_eq!(
+            alerts,
+            vec![
+                (NotifyKind::Directive, Alert { title: "New directive".into(), body: "Write docs (voxelle)".into() }),
+                (NotifyKind::Blocked, Alert { title: "Task blocked".into(), body: "Write docs (voxelle)".into() }),
+            ]
+        );
+        assert!(after.alerts(&before, "voxelle").is_empty(), "acks and unblocks are quiet");
+    }
+
+    #[test]
+    fn snapshots_read_unread_directives_and_blocked_cards() {
+        let dir = tempfile::tempdir().unwrap();
+        isnad::scaffold(dir.path(), false).unwrap();
+        let paths = isnad::paths_for(dir.path());
+        let opened = serde_json::json!({ "id": "R1", "type": "task_opened", "task_id": "T1", "claim": "Ship it", "ts": "2026-01-01T00:00:00Z" });
+        isnad::append_jsonl(&paths.ledger, &opened).unwrap();
+        let pause = serde_json::json!({ "id": "D1", "type": "pause", "task_id": "T1", "ts": "2026-01-01T00:00:01Z" });
+        isnad::append_jsonl(&paths.control, &pause).unwrap();
+
+        let snapshot = BoardSnapshot::of(&isnad::fold(dir.path()).unwrap());
+        assert_eq!(snapshot.unread.get("D1").map(String::as_str), Some("Ship it"));
+        assert_eq!(snapshot.blocked.get("T1").map(String::as_str), Some("Ship it"));
+    }
+
+    #[test]
+    fn settings_round_trip_and_gate_kinds() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load_settings_in(dir.path()), NotifySettings::default());
+        let s = NotifySettings { directive: false, ..NotifySettings::default() };
+        save_settings_in(dir.path(), &s).unwrap();
+        assert_eq!(load_settings_in(dir.path()), s);
+        assert!(!s.enabled(NotifyKind::Directive));
+        assert!(s.enabled(NotifyKind::Blocked));
+
+        std::fs::write(dir.path().join(SETTINGS_FILE), r#"{"blocked":false}"#).unwrap();
+        assert_eq!(load_settings_in(dir.path()), NotifySettings { blocked: false, ..NotifySettings::default() });
+        assert!(validate(" ", "").is_err());
+        assert!(validate("t", &"x".repeat(MAX_BODY_LEN + 1)).is_err());
+        assert!(validate("t", "b").is_ok());
+    }
+}
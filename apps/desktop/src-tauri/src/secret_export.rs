@@ -0,0 +1,387 @@
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::secrets::{is_internal_namespace, SecretError, Secrets};
+
+// Export blob: base64 of the JSON `ExportFile` below. The secrets themselves are
+// JSON (`Plaintext`) sealed with XChaCha20-Poly1305 under a key derived from the
+// passphrase with Argon2id. Plaintext only ever exists in zeroized memory.
+const FORMAT_VERSION: u32 = 1;
+const MIN_PASSPHRASE_LEN: usize = 8;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+// Argon2id cost for new exports: 64 MiB, 3 passes.
+pub(crate) const KDF_M_KIB: u32 = 64 * 1024;
+pub(crate) const KDF_T: u32 = 3;
+pub(crate) const KDF_P: u32 = 1;
+// Imports refuse costs beyond a small multiple of what export writes, so a
+// crafted blob can't exhaust memory or stall the import.
+const MAX_KDF_M_KIB: u32 = 4 * KDF_M_KIB;
+const MAX_KDF_T: u32 = 4 * KDF_T;
+const MAX_KDF_P: u32 = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct KdfParams {
+    alg: String,
+    m_kib: u32,
+    t: u32,
+    p: u32,
+    salt: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ExportFile {
+    v: u32,
+    kdf: KdfParams,
+    /// SHA-256 of the second half of the derived key. Lets a wrong passphrase
+    /// be told apart from a damaged ciphertext.
+    key_check: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Plaintext {
+    v: u32,
+    secrets: Vec<ExportedSecret>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedSecret {
+    namespace: String,
+    key: String,
+    value: String,
+    /// Blobs from before the flag was exported read as unprotected.
+    #[serde(default)]
+    require_auth: bool,
+}
+
+impl Drop for ExportedSecret {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.value.zeroize();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Created,
+    Skipped,
+    Overwritten,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportedSecret {
+    pub namespace: String,
+    pub key: String,
+    pub status: ImportStatus,
+}
+
+// Serialized to the frontend as `{ "code": "wrong_passphrase", "detail": ... }`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "code", content = "detail", rename_all = "snake_case")]
+pub enum SecretExportError {
+    WrongPassphrase,
+    CorruptBlob(String),
+    UnsupportedVersion(u32),
+    /// Some secret needs a presence check, and it couldn't be made or didn't pass.
+    AuthRequired(String),
+    AuthDenied,
+    Other(String),
+}
+
+impl std::fmt::Display for SecretExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongPassphrase => write!(f, "wrong passphrase"),
+            Self::CorruptBlob(e) => write!(f, "secret export is damaged: {e}"),
+            Self::UnsupportedVersion(v) => write!(f, "secret export version {v} is not supported"),
+            Self::AuthRequired(e) => write!(f, "exporting protected secrets requires authentication, which didn't succeed: {e}"),
+            Self::AuthDenied => write!(f, "authentication was cancelled"),
+            Self::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<String> for SecretExportError {
+    fn from(e: String) -> Self {
+        Self::Other(e)
+    }
+}
+
+impl From<SecretError> for SecretExportError {
+    fn from(e: SecretError) -> Self {
+        match e {
+            SecretError::AuthRequired(e) => Self::AuthRequired(e),
+            SecretError::AuthDenied => Self::AuthDenied,
+            SecretError::Other(e) => Self::Other(e),
+        }
+    }
+}
+
+fn corrupt(what: &str) -> SecretExportError {
+    SecretExportError::CorruptBlob(what.to_string())
+}
+
+// 32 bytes of cipher key followed by 32 bytes hashed into `key_check`.
+pub(crate) fn derive_key(passphrase: &str, salt: &[u8], m_kib: u32, t: u32, p: u32) -> Result<Zeroizing<[u8; 64]>, String> {
+    let params = argon2::Params::new(m_kib, t, p, Some(64)).map_err(|e| e.to_string())?;
+    let argon = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut out = Zeroizing::new([0u8; 64]);
+    argon
+        .hash_password_into(passphrase.as_bytes(), salt, out.as_mut())
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+// Binds the header to the ciphertext so its parameters can't be swapped.
+fn associated_data(kdf: &KdfParams) -> Vec<u8> {
+    format!("voxelle-secrets:{}:{}:{}:{}:{}:{}", FORMAT_VERSION, kdf.alg, kdf.m_kib, kdf.t, kdf.p, kdf.salt).into_bytes()
+}
+
+fn seal(plaintext: &[u8], passphrase: &str, kdf: KdfParams, nonce: [u8; NONCE_LEN]) -> Result<ExportFile, String> {
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let salt = b64.decode(&kdf.salt).map_err(|e| e.to_string())?;
+    let key = derive_key(passphrase, &salt, kdf.m_kib, kdf.t, kdf.p)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key[..32]));
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad: &associated_data(&kdf) })
+        .map_err(|_| "encryption failed".to_string())?;
+    Ok(ExportFile {
+        v: FORMAT_VERSION,
+        key_check: hex::encode(Sha256::digest(&key[32..])),
+        kdf,
+        nonce: b64.encode(nonce),
+        ciphertext: b64.encode(ciphertext),
+    })
+}
+
+fn open(file: &ExportFile, passphrase: &str) -> Result<Zeroizing<Vec<u8>>, SecretExportError> {
+    if file.v != FORMAT_VERSION {
+        return Err(SecretExportError::UnsupportedVersion(file.v));
+    }
+    let kdf = &file.kdf;
+    if kdf.alg != "argon2id" {
+        return Err(corrupt("unknown key derivation"));
+    }
+    if kdf.m_kib > MAX_KDF_M_KIB || kdf.t > MAX_KDF_T || kdf.p > MAX_KDF_P {
+        return Err(corrupt("key derivation cost out of range"));
+    }
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let salt = b64.decode(&kdf.salt).map_err(|_| corrupt("bad salt"))?;
+    let nonce = b64.decode(&file.nonce).map_err(|_| corrupt("bad nonce"))?;
+    let ciphertext = b64.decode(&file.ciphertext).map_err(|_| corrupt("bad ciphertext"))?;
+    if salt.len() < SALT_LEN || nonce.len() != NONCE_LEN {
+        return Err(corrupt("bad salt or nonce length"));
+    }
+
+    let key = derive_key(passphrase, &salt, kdf.m_kib, kdf.t, kdf.p).map_err(|_| corrupt("bad key derivation parameters"))?;
+    if hex::encode(Sha256::digest(&key[32..])) != file.key_check {
+        return Err(SecretExportError::WrongPassphrase);
+    }
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key[..32]));
+    cipher
+        .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &associated_data(kdf) })
+        .map(Zeroizing::new)
+        .map_err(|_| corrupt("ciphertext failed authentication"))
+}
+
+fn encode_blob(file: &ExportFile) -> Result<String, String> {
+    let json = serde_json::to_vec(file).map_err(|e| e.to_string())?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(json))
+}
+
+fn decode_blob(blob: &str) -> Result<ExportFile, SecretExportError> {
+    let json = base64::engine::general_purpose::STANDARD
+        .decode(blob.trim())
+        .map_err(|_| corrupt("not base64"))?;
+    serde_json::from_slice(&json).map_err(|_| corrupt("not a secret export"))
+}
+
+fn random<const N: usize>() -> Result<[u8; N], String> {
+    let mut out = [0u8; N];
+    getrandom::getrandom(&mut out).map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+#[tracing::instrument(skip_all)]
+pub fn export(store: &Secrets, passphrase: &str) -> Result<String, SecretExportError> {
+    export_with_cost(store, passphrase, (KDF_M_KIB, KDF_T, KDF_P))
+}
+
+fn export_with_cost(store: &Secrets, passphrase: &str, (m_kib, t, p): (u32, u32, u32)) -> Result<String, SecretExportError> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(SecretExportError::Other(format!(
+            "passphrase must be at least {MIN_PASSPHRASE_LEN} characters"
+        )));
+    }
+    let listed = store.list_all()?;
+    // The blob's passphrase is the caller's choice, so exporting is as good as reading.
+    if listed.iter().any(|(namespace, key)| store.requires_auth(namespace, key)) {
+        store.authenticate("export your secrets")?;
+    }
+    let mut secrets = Vec::new();
+    for (namespace, key) in listed {
+        // Deleted since it was listed; nothing to export.
+        if let Some(value) = store.get(&namespace, &key)? {
+            let require_auth = store.requires_auth(&namespace, &key);
+            secrets.push(ExportedSecret { namespace, key, value, require_auth });
+        }
+    }
+    let count = secrets.len();
+    let plaintext = Zeroizing::new(
+        serde_json::to_vec(&Plaintext { v: FORMAT_VERSION, secrets }).map_err(|e| e.to_string())?,
+    );
+    let kdf = KdfParams {
+        alg: "argon2id".into(),
+        m_kib,
+        t,
+        p,
+        salt: base64::engine::general_purpose::STANDARD.encode(random::<SALT_LEN>()?),
+    };
+    let file = seal(&plaintext, passphrase, kdf, random::<NONCE_LEN>()?)?;
+    tracing::info!(count, "secrets exported");
+    Ok(encode_blob(&file)?)
+}
+
+#[tracing::instrument(skip(store, blob, passphrase))]
+pub fn import(store: &Secrets, blob: &str, passphrase: &str, overwrite: bool) -> Result<Vec<ImportedSecret>, SecretExportError> {
+    let plaintext = open(&decode_blob(blob)?, passphrase)?;
+    let contents: Plaintext = serde_json::from_slice(&plaintext).map_err(|_| corrupt("bad contents"))?;
+    if contents.v != FORMAT_VERSION {
+        return Err(SecretExportError::UnsupportedVersion(contents.v));
+    }
+
+    let mut report = Vec::with_capacity(contents.secrets.len());
+    for s in &contents.secrets {
+        // Exports never include these, so a blob that does was made to plant them.
+        if is_internal_namespace(&s.namespace) {
+            report.push(ImportedSecret { namespace: s.namespace.clone(), key: s.key.clone(), status: ImportStatus::Skipped });
+            continue;
+        }
+        let existing = store.get(&s.namespace, &s.key)?;
+        let status = match (existing.is_some(), overwrite) {
+            (true, false) => ImportStatus::Skipped,
+            (true, true) => ImportStatus::Overwritten,
+            (false, _) => ImportStatus::Created,
+        };
+        if status != ImportStatus::Skipped {
+            store.set(&s.namespace, &s.key, &s.value, Some(s.require_auth))?;
+        }
+        report.push(ImportedSecret { namespace: s.namespace.clone(), key: s.key.clone(), status });
+    }
+    let written = report.iter().filter(|r| r.status != ImportStatus::Skipped).count();
+    tracing::info!(count = written, skipped = report.len() - written, "secrets imported");
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters; the cost doesn't matter for correctness.
+    fn kdf() -> KdfParams {
+        KdfParams {
+            alg: "argon2id".into(),
+            m_kib: 64,
+            t: 1,
+            p: 1,
+            salt: base64::engine::general_purpose::STANDARD.encode([9u8; SALT_LEN]),
+        }
+    }
+
+    fn sealed(plaintext: &[u8], passphrase: &str) -> String {
+        encode_blob(&seal(plaintext, passphrase, kdf(), [3u8; NONCE_LEN]).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let blob = sealed(b"secret stuff", "correct horse");
+        let file = decode_blob(&blob).unwrap();
+        assert_eq!(&open(&file, "correct horse").unwrap()[..], b"secret stuff");
+        assert!(!blob.contains("secret"));
+    }
+
+    #[test]
+    fn wrong_passphrase_is_distinct_from_damage() {
+        let file = decode_blob(&sealed(b"secret stuff", "correct horse")).unwrap();
+        assert_eq!(open(&file, "battery staple"), Err(SecretExportError::WrongPassphrase));
+
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let mut ct = b64.decode(&file.ciphertext).unwrap();
+        ct[0] ^= 1;
+        let tampered = ExportFile { ciphertext: b64.encode(ct), ..file.clone() };
+        assert!(matches!(open(&tampered, "correct horse"), Err(SecretExportError::CorruptBlob(_))));
+
+        // Changing the header breaks authentication too.
+        let mut kdf = file.kdf.clone();
+        kdf.alg = "argon2i".into();
+        assert!(matches!(open(&ExportFile { kdf, ..file.clone() }, "correct horse"), Err(SecretExportError::CorruptBlob(_))));
+    }
+
+    #[test]
+    fn rejects_garbage_and_unknown_versions() {
+        assert!(matches!(decode_blob("not base64!"), Err(SecretExportError::CorruptBlob(_))));
+        let not_json = base64::engine::general_purpose::STANDARD.encode("hello");
+        assert!(matches!(decode_blob(&not_json), Err(SecretExportError::CorruptBlob(_))));
+
+        let file = decode_blob(&sealed(b"x", "correct horse")).unwrap();
+        assert_eq!(
+            open(&ExportFile { v: 2, ..file.clone() }, "correct horse"),
+            Err(SecretExportError::UnsupportedVersion(2))
+        );
+        let mut kdf = file.kdf.clone();
+        kdf.m_kib = MAX_KDF_M_KIB + 1;
+        assert!(matches!(open(&ExportFile { kdf, ..file }, "correct horse"), Err(SecretExportError::CorruptBlob(_))));
+    }
+
+    struct Present;
+
+    impl crate::secrets::UserPresence for Present {
+        fn confirm(&self, _reason: &str) -> Result<bool, String> {
+            Ok(true)
+        }
+    }
+
+    fn store(dir: &std::path::Path) -> Secrets {
+        let files = crate::secret_file_store::FileStore::for_tests(dir);
+        Secrets::new(Box::new(files), dir.join("index.json")).with_presence(Box::new(Present))
+    }
+
+    #[test]
+    fn require_auth_survives_a_round_trip() {
+        let (from, to) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let source = store(from.path());
+        source.set("default", "space.passphrase", "hunter2", Some(true)).unwrap();
+        source.set("default", "theme", "dark", None).unwrap();
+        let blob = export_with_cost(&source, "correct horse", (64, 1, 1)).unwrap();
+
+        let target = store(to.path());
+        import(&target, &blob, "correct horse", false).unwrap();
+        assert_eq!(target.get("default", "space.passphrase").unwrap().as_deref(), Some("hunter2"));
+        assert!(target.requires_auth("default", "space.passphrase"));
+        assert!(!target.requires_auth("default", "theme"));
+    }
+
+    #[test]
+    fn errors_serialize_with_a_code() {
+        assert_eq!(
+            serde_json::to_value(SecretExportError::WrongPassphrase).unwrap(),
+            serde_json::json!({ "code": "wrong_passphrase" })
+        );
+        assert_eq!(
+            serde_json::to_value(ImportedSecret {
+                namespace: "default".into(),
+                key: "openai".into(),
+                status: ImportStatus::Overwritten
+            })
+            .unwrap(),
+            serde_json::json!({ "namespace": "default", "key": "openai", "status": "overwritten" })
+        );
+    }
+}
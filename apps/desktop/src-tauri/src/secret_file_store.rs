@@ -0,0 +1,370 @@
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+use crate::secret_export::{derive_key, KDF_M_KIB, KDF_P, KDF_T};
+use crate::secrets::{Backend, SecretStore};
+
+// Fallback for machines without an OS keyring. All secrets live in one file,
+// sealed with XChaCha20-Poly1305 under a key derived from a random per-machine
+// secret (kept beside it, owner-only) and, optionally, a passphrase the user
+// enters once per session. Without a passphrase this is only as strong as the
+// file permissions, which is why the backend is reported to the UI.
+const STORE_FILE: &str = "secrets.enc";
+const MACHINE_KEY_FILE: &str = "secrets.key";
+const FORMAT_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+// namespace -> key -> value
+type Contents = BTreeMap<String, BTreeMap<String, String>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PassphraseKdf {
+    m_kib: u32,
+    t: u32,
+    p: u32,
+    salt: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoreFile {
+    v: u32,
+    /// Present when the store needs a passphrase as well as the machine key.
+    passphrase: Option<PassphraseKdf>,
+    key_check: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+struct Session {
+    kdf: PassphraseKdf,
+    key: Zeroizing<[u8; 32]>,
+}
+
+pub struct FileStore {
+    path: PathBuf,
+    machine_key_path: PathBuf,
+    kdf_cost: (u32, u32, u32),
+    session: Mutex<Option<Session>>,
+    // Serializes read-modify-write of the store file.
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(STORE_FILE),
+            machine_key_path: dir.join(MACHINE_KEY_FILE),
+            kdf_cost: (KDF_M_KIB, KDF_T, KDF_P),
+            session: Mutex::new(None),
+            lock: Mutex::new(()),
+        }
+    }
+
+    // Cheap Argon2 parameters; the cost doesn't matter for correctness.
+    #[cfg(test)]
+    pub fn for_tests(dir: &Path) -> Self {
+        Self { kdf_cost: (64, 1, 1), ..Self::new(dir) }
+    }
+
+    fn machine_key(&self) -> Result<Zeroizing<[u8; 32]>, String> {
+        if let Ok(bytes) = std::fs::read(&self.machine_key_path) {
+            let bytes = Zeroizing::new(bytes);
+            let mut key = Zeroizing::new([0u8; 32]);
+            if bytes.len() != key.len() {
+                return Err("secret store machine key is damaged".into());
+            }
+            key.copy_from_slice(&bytes);
+            return Ok(key);
+        }
+        let mut key = Zeroizing::new([0u8; 32]);
+        getrandom::getrandom(key.as_mut()).map_err(|e| e.to_string())?;
+        write_private(&self.machine_key_path, key.as_ref())?;
+        Ok(key)
+    }
+
+    // 32 bytes of cipher key, then 32 bytes hashed into `key_check`.
+    fn store_key(&self, passphrase_key: Option<&[u8; 32]>) -> Result<Zeroizing<[u8; 64]>, String> {
+        let machine = self.machine_key()?;
+        let mut out = Zeroizing::new([0u8; 64]);
+        for (half, label) in out.chunks_mut(32).zip([&b"enc"[..], &b"check"[..]]) {
+            let mut h = Sha256::new();
+            h.update(b"voxelle-secret-file:");
+            h.update(label);
+            h.update(machine.as_ref());
+            if let Some(pk) = passphrase_key {
+                h.update(pk);
+            }
+            half.copy_from_slice(&h.finalize());
+        }
+        Ok(out)
+    }
+
+    fn passphrase_key(&self, passphrase: &str, kdf: &PassphraseKdf) -> Result<Zeroizing<[u8; 32]>, String> {
+        let salt = base64::engine::general_purpose::STANDARD.decode(&kdf.salt).map_err(|e| e.to_string())?;
+        let derived = derive_key(passphrase, &salt, kdf.m_kib, kdf.t, kdf.p)?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        key.copy_from_slice(&derived[..32]);
+        Ok(key)
+    }
+
+    fn read_file(&self) -> Result<Option<StoreFile>, String> {
+        match std::fs::read(&self.path) {
+            Ok(b) => serde_json::from_slice(&b).map(Some).map_err(|_| "secret store file is damaged".to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    // The session key, if it belongs to `kdf`.
+    fn session_key(&self, kdf: &PassphraseKdf) -> Result<Zeroizing<[u8; 32]>, String> {
+        let session = self.session.lock().map_err(|_| "secret store lock poisoned")?;
+        match session.as_ref() {
+            Some(s) if &s.kdf == kdf => Ok(s.key.clone()),
+            _ => Err("secret store is locked; unlock it with the passphrase".into()),
+        }
+    }
+
+    fn load(&self) -> Result<Contents, String> {
+        let Some(file) = self.read_file()? else {
+            return Ok(Contents::new());
+        };
+        if file.v != FORMAT_VERSION {
+            return Err(format!("secret store version {} is not supported", file.v));
+        }
+        let passphrase_key = file.passphrase.as_ref().map(|kdf| self.session_key(kdf)).transpose()?;
+        let key = self.store_key(passphrase_key.as_deref())?;
+        if hex::encode(Sha256::digest(&key[32..])) != file.key_check {
+            return Err("secret store key doesn't match (machine key replaced?)".into());
+        }
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let nonce = b64.decode(&file.nonce).map_err(|_| "secret store file is damaged")?;
+        let ciphertext = b64.decode(&file.ciphertext).map_err(|_| "secret store file is damaged")?;
+        if nonce.len() != NONCE_LEN {
+            return Err("secret store file is damaged".into());
+        }
+        let plaintext = XChaCha20Poly1305::new(Key::from_slice(&key[..32]))
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .map(Zeroizing::new)
+            .map_err(|_| "secret store file is damaged")?;
+        serde_json::from_slice(&plaintext).map_err(|_| "secret store file is damaged".to_string())
+    }
+
+    fn save(&self, contents: &Contents, kdf: Option<PassphraseKdf>) -> Result<(), String> {
+        let passphrase_key = kdf.as_ref().map(|kdf| self.session_key(kdf)).transpose()?;
+        let key = self.store_key(passphrase_key.as_deref())?;
+        let plaintext = Zeroizing::new(serde_json::to_vec(contents).map_err(|e| e.to_string())?);
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| e.to_string())?;
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&key[..32]))
+            .encrypt(XNonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| "encryption failed".to_string())?;
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let file = StoreFile {
+            v: FORMAT_VERSION,
+            passphrase: kdf,
+            key_check: hex::encode(Sha256::digest(&key[32..])),
+            nonce: b64.encode(nonce),
+            ciphertext: b64.encode(ciphertext),
+        };
+        let json = serde_json::to_vec_pretty(&file).map_err(|e| e.to_string())?;
+        write_private(&self.path, &json)
+    }
+
+    // Passphrase settings for the next write: the file's if it has any,
+    // otherwise those of a passphrase set this session.
+    fn kdf_for_write(&self, file: Option<&StoreFile>) -> Option<PassphraseKdf> {
+        file.and_then(|f| f.passphrase.clone())
+            .or_else(|| self.session.lock().ok().and_then(|g| g.as_ref().map(|s| s.kdf.clone())))
+    }
+
+    fn modify(&self, f: impl FnOnce(&mut Contents)) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|_| "secret store lock poisoned")?;
+        let mut contents = self.load()?;
+        f(&mut contents);
+        contents.retain(|_, keys| !keys.is_empty());
+        let kdf = self.kdf_for_write(self.read_file()?.as_ref());
+        self.save(&contents, kdf)
+    }
+}
+
+// Written to a sibling temp file and renamed into place, readable only by the owner.
+pub(crate) fn write_private(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut f = opts.open(&tmp).map_err(|e| e.to_string())?;
+    std::io::Write::write_all(&mut f, bytes).map_err(|e| e.to_string())?;
+    f.sync_all().map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+impl SecretStore for FileStore {
+    fn backend(&self) -> Backend {
+        Backend::File
+    }
+
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, String> {
+        let _guard = self.lock.lock().map_err(|_| "secret store lock poisoned")?;
+        Ok(self.load()?.get(namespace).and_then(|keys| keys.get(key)).cloned())
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: &str) -> Result<(), String> {
+        self.modify(|c| {
+            c.entry(namespace.to_string()).or_default().insert(key.to_string(), value.to_string());
+        })
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<(), String> {
+        self.modify(|c| {
+            if let Some(keys) = c.get_mut(namespace) {
+                keys.remove(key);
+            }
+        })
+    }
+
+    fn passphrase_protected(&self) -> bool {
+        let in_file = self.read_file().ok().flatten().is_some_and(|f| f.passphrase.is_some());
+        in_file || self.session.lock().is_ok_and(|g| g.is_some())
+    }
+
+    fn locked(&self) -> bool {
+        match self.read_file() {
+            Ok(Some(StoreFile { passphrase: Some(kdf), .. })) => self.session_key(&kdf).is_err(),
+            _ => false,
+        }
+    }
+
+    // Unlocks a passphrase-protected store for this session.
+    fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|_| "secret store lock poisoned")?;
+        let file = self.read_file()?;
+        let Some(kdf) = file.as_ref().and_then(|f| f.passphrase.clone()) else {
+            return Err("secret store has no passphrase to unlock".into());
+        };
+        let key = self.passphrase_key(passphrase, &kdf)?;
+        let check = self.store_key(Some(&key))?;
+        if file.is_some_and(|f| hex::encode(Sha256::digest(&check[32..])) != f.key_check) {
+            return Err("wrong passphrase".into());
+        }
+        *self.session.lock().map_err(|_| "secret store lock poisoned")? = Some(Session { kdf, key });
+        Ok(())
+    }
+
+    // Protects a store that has no passphrase yet, re-encrypting what's there.
+    fn set_passphrase(&self, passphrase: &str) -> Result<(), String> {
+        if passphrase.is_empty() {
+            return Err("passphrase must be non-empty".into());
+        }
+        let _guard = self.lock.lock().map_err(|_| "secret store lock poisoned")?;
+        let file = self.read_file()?;
+        if file.as_ref().is_some_and(|f| f.passphrase.is_some()) || self.session.lock().is_ok_and(|g| g.is_some()) {
+            return Err("secret store already has a passphrase".into());
+        }
+        let contents = self.load()?;
+        let mut salt = [0u8; SALT_LEN];
+        getrandom::getrandom(&mut salt).map_err(|e| e.to_string())?;
+        let (m_kib, t, p) = self.kdf_cost;
+        let kdf = PassphraseKdf { m_kib, t, p, salt: base64::engine::general_purpose::STANDARD.encode(salt) };
+        let key = self.passphrase_key(passphrase, &kdf)?;
+        *self.session.lock().map_err(|_| "secret store lock poisoned")? = Some(Session { kdf: kdf.clone(), key });
+        if file.is_some() {
+            self.save(&contents, Some(kdf))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::for_tests(dir.path());
+        assert_eq!(store.get("default", "openai").unwrap(), None);
+        store.set("default", "openai", "sk-secret-value").unwrap();
+        store.set("work", "openai", "sk-other").unwrap();
+        assert_eq!(store.get("default", "openai").unwrap().as_deref(), Some("sk-secret-value"));
+
+        let reopened = FileStore::for_tests(dir.path());
+        assert_eq!(reopened.get("work", "openai").unwrap().as_deref(), Some("sk-other"));
+        reopened.delete("work", "openai").unwrap();
+        reopened.delete("work", "missing").unwrap();
+        assert_eq!(store.get("work", "openai").unwrap(), None);
+
+        let on_disk = std::fs::read_to_string(dir.path().join(STORE_FILE)).unwrap();
+        assert!(!on_disk.contains("sk-secret-value") && !on_disk.contains("openai"));
+        assert!(!dir.path().join("secrets.tmp").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        FileStore::for_tests(dir.path()).set("default", "k", "v").unwrap();
+        for name in [STORE_FILE, MACHINE_KEY_FILE] {
+            let mode = std::fs::metadata(dir.path().join(name)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "{name}");
+        }
+    }
+
+    #[test]
+    fn passphrase_locks_the_store_until_unlocked() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::for_tests(dir.path());
+        store.set("default", "k", "v").unwrap();
+        assert!(!store.passphrase_protected());
+
+        // Unlocking needs a passphrase to exist; setting one keeps what was stored.
+        assert_eq!(store.unlock("hunter22").unwrap_err(), "secret store has no passphrase to unlock");
+        store.set_passphrase("hunter22").unwrap();
+        assert_eq!(store.set_passphrase("other").unwrap_err(), "secret store already has a passphrase");
+        assert!(store.passphrase_protected() && !store.locked());
+        assert_eq!(store.get("default", "k").unwrap().as_deref(), Some("v"));
+
+        let next_session = FileStore::for_tests(dir.path());
+        assert!(next_session.locked());
+        assert!(next_session.get("default", "k").is_err());
+        assert!(next_session.set("default", "k2", "v2").is_err());
+        assert_eq!(next_session.unlock("nope").unwrap_err(), "wrong passphrase");
+        assert!(next_session.locked());
+
+        next_session.unlock("hunter22").unwrap();
+        assert!(!next_session.locked());
+        assert_eq!(next_session.get("default", "k").unwrap().as_deref(), Some("v"));
+    }
+
+    #[test]
+    fn passphrase_set_before_the_first_write_applies_to_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::for_tests(dir.path());
+        store.set_passphrase("hunter22").unwrap();
+        store.set("default", "k", "v").unwrap();
+        assert!(FileStore::for_tests(dir.path()).locked());
+    }
+
+    #[test]
+    fn a_replaced_machine_key_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        FileStore::for_tests(dir.path()).set("default", "k", "v").unwrap();
+        std::fs::write(dir.path().join(MACHINE_KEY_FILE), [0u8; 32]).unwrap();
+        let err = FileStore::for_tests(dir.path()).get("default", "k").unwrap_err();
+        assert!(err.contains("machine key"), "{err}");
+    }
+}
@@ -0,0 +1,994 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::secret_file_store::FileStore;
+
+// OS keyrings can't enumerate entries, so the names of stored keys (never the
+// values) are tracked in an index next to the app data.
+const INDEX_FILE: &str = "secret_keys.json";
+const MAX_KEY_LEN: usize = 256;
+const MAX_VALUE_LEN: usize = 256 * 1024;
+pub const DEFAULT_NAMESPACE: &str = "default";
+const MAX_NAMESPACE_LEN: usize = 64;
+const AUTH_SETTINGS_FILE: &str = "secret_auth.json";
+// Verifier for the passphrase the Linux presence check asks for.
+#[cfg(not(any(target_os = "macos", windows)))]
+const PRESENCE_FILE: &str = "secret_presence.json";
+pub const DEFAULT_AUTH_WINDOW_SECS: u32 = 60;
+// Longer than this and a script could wait for one legitimate prompt and then
+// read everything for as long as it liked.
+pub const MAX_AUTH_WINDOW_SECS: u32 = 15 * 60;
+
+// Key names in each namespace.
+type Index = BTreeMap<String, BTreeSet<String>>;
+
+// What the index file holds: key names, and which of those need the user to
+// be present before the webview may read them. Never any values.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IndexFile {
+    keys: Index,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    require_auth: Index,
+}
+
+// Serializes read-modify-write of the index across concurrent commands.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+// Namespaces become part of the keyring service name, so they are limited to
+// characters every backend handles without escaping.
+fn validate_namespace(namespace: &str) -> Result<(), String> {
+    if namespace.is_empty() {
+        return Err("namespace must be non-empty".into());
+    }
+    if namespace.len() > MAX_NAMESPACE_LEN {
+        return Err("namespace too long".into());
+    }
+    if !namespace.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.')) {
+        return Err("namespace may only contain letters, digits, '-', '_' and '.'".into());
+    }
+    Ok(())
+}
+
+pub fn namespace_or_default(namespace: Option<String>) -> String {
+    namespace.unwrap_or_else(|| DEFAULT_NAMESPACE.to_string())
+}
+
+// Namespaces only the app's Rust side reads and writes. The webview can't
+// name them, and they're left out of exports.
+const INTERNAL_NAMESPACES: &[&str] = &[crate::identity::NAMESPACE];
+
+pub fn is_internal_namespace(namespace: &str) -> bool {
+    INTERNAL_NAMESPACES.contains(&namespace)
+}
+
+/// `namespace_or_default` for the webview commands, refusing internal namespaces.
+pub fn webview_namespace(namespace: Option<String>) -> Result<String, String> {
+    let namespace = namespace_or_default(namespace);
+    if is_internal_namespace(&namespace) {
+        return Err(format!("the \"{namespace}\" namespace is reserved"));
+    }
+    Ok(namespace)
+}
+
+// The default namespace keeps the original `voxelle` service so secrets stored
+// before namespaces existed are still found.
+fn service_for(namespace: &str) -> String {
+    if namespace == DEFAULT_NAMESPACE {
+        "voxelle".to_string()
+    } else {
+        format!("voxelle::{namespace}")
+    }
+}
+
+fn validate(namespace: &str, key: &str) -> Result<(), String> {
+    validate_namespace(namespace)?;
+    if key.trim().is_empty() {
+        return Err("key must be non-empty".into());
+    }
+    if key.len() > MAX_KEY_LEN {
+        return Err("key too long".into());
+    }
+    Ok(())
+}
+
+fn is_missing(e: &keyring::Error) -> bool {
+    if matches!(e, keyring::Error::NoEntry) {
+        return true;
+    }
+    let msg = e.to_string().to_lowercase();
+    msg.contains("no entry") || msg.contains("not found") || msg.contains("item not found")
+}
+
+fn read_index_file(path: &Path) -> IndexFile {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Current(IndexFile),
+        // Before `require_auth` the file was just the key names.
+        Namespaced(Index),
+        // Before namespaces the index was a flat list of default-namespace keys.
+        Flat(BTreeSet<String>),
+    }
+
+    let Some(stored) = std::fs::read(path).ok().and_then(|b| serde_json::from_slice(&b).ok()) else {
+        return IndexFile::default();
+    };
+    let keys = match stored {
+        Stored::Current(file) => return file,
+        Stored::Namespaced(index) => index,
+        Stored::Flat(keys) if keys.is_empty() => Index::new(),
+        Stored::Flat(keys) => Index::from([(DEFAULT_NAMESPACE.to_string(), keys)]),
+    };
+    IndexFile { keys, require_auth: Index::new() }
+}
+
+fn read_index(path: &Path) -> Index {
+    read_index_file(path).keys
+}
+
+// Written to a sibling temp file and renamed over the index, so a crash never
+// leaves it half-written.
+fn write_index(path: &Path, file: &IndexFile) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(file).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+// `f` gets the namespace's key names and the subset of them that require auth.
+// Flags for keys that are no longer indexed are dropped with them.
+fn update_index_file(
+    path: &Path,
+    namespace: &str,
+    f: impl FnOnce(&mut BTreeSet<String>, &mut BTreeSet<String>),
+) -> Result<(), String> {
+    let _guard = INDEX_LOCK.lock().map_err(|_| "secret index lock poisoned")?;
+    let mut file = read_index_file(path);
+    let before = file.clone();
+    let keys = file.keys.entry(namespace.to_string()).or_default();
+    let flags = file.require_auth.entry(namespace.to_string()).or_default();
+    f(keys, flags);
+    flags.retain(|k| keys.contains(k));
+    file.keys.retain(|_, keys| !keys.is_empty());
+    file.require_auth.retain(|_, keys| !keys.is_empty());
+    if file != before {
+        write_index(path, &file)?;
+    }
+    Ok(())
+}
+
+fn update_index(path: &Path, namespace: &str, f: impl FnOnce(&mut BTreeSet<String>)) -> Result<(), String> {
+    update_index_file(path, namespace, |keys, _| f(keys))
+}
+
+fn requires_auth_in(path: &Path, namespace: &str, key: &str) -> bool {
+    read_index_file(path).require_auth.get(namespace).is_some_and(|keys| keys.contains(key))
+}
+
+// Keys whose entry is gone from the store (deleted outside the app, keyring
+// reset) are dropped from the index. Keys that can't be checked are kept.
+fn list_in(path: &Path, namespace: &str, exists: impl Fn(&str) -> Result<bool, String>) -> Result<Vec<String>, String> {
+    let mut live = Vec::new();
+    update_index(path, namespace, |keys| {
+        keys.retain(|k| exists(k).unwrap_or(true));
+        live = keys.iter().cloned().collect();
+    })?;
+    Ok(live)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Keyring,
+    /// `secret_file_store::FileStore`, used when no OS keyring is available.
+    File,
+}
+
+/// Where secret values are kept. Validation and the key index are handled by
+/// `Secrets`, so every backend behaves the same to the commands.
+pub trait SecretStore: Send + Sync {
+    fn backend(&self) -> Backend;
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, String>;
+    fn set(&self, namespace: &str, key: &str, value: &str) -> Result<(), String>;
+    /// Deleting a missing entry is not an error.
+    fn delete(&self, namespace: &str, key: &str) -> Result<(), String>;
+
+    fn passphrase_protected(&self) -> bool {
+        false
+    }
+
+    fn locked(&self) -> bool {
+        false
+    }
+
+    fn unlock(&self, _passphrase: &str) -> Result<(), String> {
+        Err("this secret store doesn't use a passphrase".into())
+    }
+
+    fn set_passphrase(&self, _passphrase: &str) -> Result<(), String> {
+        Err("this secret store doesn't use a passphrase".into())
+    }
+}
+
+pub struct KeyringStore;
+
+impl KeyringStore {
+    // Namespaces become part of the keyring service name.
+    fn entry(namespace: &str, key: &str) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(&service_for(namespace), key).map_err(|e| e.to_string())
+    }
+
+    /// Whether an OS keyring backend can be reached at all. A missing probe
+    /// entry is fine; a platform or storage-access failure is not.
+    pub fn available() -> bool {
+        let probe = keyring::Entry::new("voxelle", "__voxelle_probe__").and_then(|e| e.get_password());
+        !matches!(
+            probe,
+            Err(keyring::Error::PlatformFailure(_)) | Err(keyring::Error::NoStorageAccess(_))
+        )
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn backend(&self) -> Backend {
+        Backend::Keyring
+    }
+
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, String> {
+        match Self::entry(namespace, key)?.get_password() {
+            Ok(v) => Ok(Some(v)),
+            // Treat "missing" as None; anything else is an error.
+            Err(e) if is_missing(&e) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: &str) -> Result<(), String> {
+        Self::entry(namespace, key)?.set_password(value).map_err(|e| e.to_string())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<(), String> {
+        match Self::entry(namespace, key)?.delete_credential() {
+            Err(e) if !is_missing(&e) => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Serialized to the frontend as `{ "code": "auth_denied", "detail": ... }`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "code", content = "detail", rename_all = "snake_case")]
+pub enum SecretError {
+    /// The secret needs the user present and the check couldn't be made or
+    /// didn't pass (no biometrics set up, wrong passphrase, lockout).
+    AuthRequired(String),
+    /// The user cancelled the check.
+    AuthDenied,
+    Other(String),
+}
+
+impl std::fmt::Display for SecretError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AuthRequired(e) => write!(f, "this secret requires authentication, which didn't succeed: {e}"),
+            Self::AuthDenied => write!(f, "authentication was cancelled"),
+            Self::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<String> for SecretError {
+    fn from(e: String) -> Self {
+        Self::Other(e)
+    }
+}
+
+/// Confirms the user is at the machine before a protected secret goes to the
+/// webview, so a script that can invoke commands can't read it on its own.
+pub trait UserPresence: Send + Sync {
+    /// `Ok(false)` when the user cancels; `Err` when there's no way to ask or
+    /// the check fails.
+    fn confirm(&self, reason: &str) -> Result<bool, String>;
+}
+
+/// Touch ID or Windows Hello, either of which falls back to the device
+/// password, where the platform has them.
+#[cfg(any(target_os = "macos", windows))]
+pub struct OsPresence;
+
+#[cfg(any(target_os = "macos", windows))]
+impl UserPresence for OsPresence {
+    fn confirm(&self, reason: &str) -> Result<bool, String> {
+        use robius_authentication::{AndroidText, BiometricStrength, Context, PolicyBuilder, Text, WindowsText};
+
+        let policy = PolicyBuilder::new()
+            .biometrics(Some(BiometricStrength::Strong))
+            .password(true)
+            .build()
+            .ok_or("no authentication policy for this platform")?;
+        let text = Text {
+            android: AndroidText { title: "Voxelle", subtitle: None, description: None },
+            apple: reason,
+            windows: WindowsText::new("Voxelle", reason).ok_or("prompt text too long")?,
+        };
+        match Context::new(()).blocking_authenticate(text, &policy) {
+            Ok(()) => Ok(true),
+            Err(robius_authentication::Error::UserCanceled) => Ok(false),
+            Err(e) => Err(format!("authentication failed: {e:?}")),
+        }
+    }
+}
+
+/// A passphrase prompt, for platforms without biometric or device password
+/// prompts. Drawn by zenity or kdialog, so the webview can't answer it. The
+/// first check asks the user to choose the passphrase; only an Argon2id
+/// verifier for it is kept, in the app data dir.
+#[cfg(not(any(target_os = "macos", windows)))]
+pub struct OsPresence(pub PathBuf);
+
+#[cfg(not(any(target_os = "macos", windows)))]
+impl UserPresence for OsPresence {
+    fn confirm(&self, reason: &str) -> Result<bool, String> {
+        use crate::secret_export::{KDF_M_KIB, KDF_P, KDF_T};
+
+        let path = self.0.join(PRESENCE_FILE);
+        let ask = |what: &str| prompt_passphrase(&format!("Voxelle wants to {reason}.\n\n{what}"));
+        if let Some(check) = read_presence_check(&path)? {
+            let Some(passphrase) = ask("Enter your Voxelle passphrase:")? else {
+                return Ok(false);
+            };
+            return if check.matches(&passphrase)? { Ok(true) } else { Err("wrong passphrase".into()) };
+        }
+        let Some(chosen) = ask("Choose a passphrase for protected secrets:")? else {
+            return Ok(false);
+        };
+        if chosen.is_empty() {
+            return Err("passphrase must be non-empty".into());
+        }
+        let Some(again) = ask("Enter the passphrase again:")? else {
+            return Ok(false);
+        };
+        if *chosen != *again {
+            return Err("the passphrases didn't match".into());
+        }
+        let check = PresenceCheck::new(&chosen, (KDF_M_KIB, KDF_T, KDF_P))?;
+        let json = serde_json::to_vec_pretty(&check).map_err(|e| e.to_string())?;
+        crate::secret_file_store::write_private(&path, &json)?;
+        Ok(true)
+    }
+}
+
+// `None` when the user cancels. Tries zenity, then kdialog.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn prompt_passphrase(text: &str) -> Result<Option<zeroize::Zeroizing<String>>, String> {
+    let prompts: [(&str, &[&str]); 2] = [
+        ("zenity", &["--entry", "--hide-text", "--title=Voxelle", "--text"]),
+        ("kdialog", &["--title", "Voxelle", "--password"]),
+    ];
+    for (program, args) in prompts {
+        let output = match std::process::Command::new(program).args(args).arg(text).output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("{program}: {e}")),
+        };
+        // Both exit 1 on Cancel.
+        match output.status.code() {
+            Some(0) => {}
+            Some(1) => return Ok(None),
+            _ => return Err(format!("{program} couldn't show the passphrase prompt")),
+        }
+        let mut passphrase =
+            zeroize::Zeroizing::new(String::from_utf8(output.stdout).map_err(|_| "passphrase isn't UTF-8".to_string())?);
+        let len = passphrase.trim_end_matches(['\n', '\r']).len();
+        passphrase.truncate(len);
+        return Ok(Some(passphrase));
+    }
+    Err("no passphrase prompt available; install zenity or kdialog".into())
+}
+
+/// What `PRESENCE_FILE` holds: Argon2id parameters and the SHA-256 of the
+/// key they derive from the passphrase.
+#[cfg(not(any(target_os = "macos", windows)))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PresenceCheck {
+    m_kib: u32,
+    t: u32,
+    p: u32,
+    salt: String,
+    check: String,
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+impl PresenceCheck {
+    fn new(passphrase: &str, (m_kib, t, p): (u32, u32, u32)) -> Result<Self, String> {
+        use base64::Engine;
+
+        let mut salt = [0u8; 16];
+        getrandom::getrandom(&mut salt).map_err(|e| e.to_string())?;
+        let check = presence_digest(passphrase, &salt, (m_kib, t, p))?;
+        Ok(Self { m_kib, t, p, salt: base64::engine::general_purpose::STANDARD.encode(salt), check })
+    }
+
+    fn matches(&self, passphrase: &str) -> Result<bool, String> {
+        use base64::Engine;
+
+        let salt = base64::engine::general_purpose::STANDARD
+            .decode(&self.salt)
+            .map_err(|_| "presence passphrase file is damaged".to_string())?;
+        let actual = presence_digest(passphrase, &salt, (self.m_kib, self.t, self.p))?;
+        Ok(voxelle_protocol::ct_eq_str(&actual, &self.check))
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn presence_digest(passphrase: &str, salt: &[u8], (m_kib, t, p): (u32, u32, u32)) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+
+    let key = crate::secret_export::derive_key(passphrase, salt, m_kib, t, p)?;
+    Ok(hex::encode(Sha256::digest(key.as_ref())))
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn read_presence_check(path: &Path) -> Result<Option<PresenceCheck>, String> {
+    match std::fs::read(path) {
+        Ok(b) => serde_json::from_slice(&b).map(Some).map_err(|_| "presence passphrase file is damaged".to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// For stores opened without a way to prompt; protected secrets stay unreadable.
+struct NoPresence;
+
+impl UserPresence for NoPresence {
+    fn confirm(&self, _reason: &str) -> Result<bool, String> {
+        Err("no user presence check on this system".into())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct AuthSettings {
+    /// How long a successful check covers further protected reads; 0 asks every time.
+    window_secs: u32,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        Self { window_secs: DEFAULT_AUTH_WINDOW_SECS }
+    }
+}
+
+fn load_auth_settings(path: &Path) -> AuthSettings {
+    std::fs::read(path)
+        .ok()
+        .and_then(|b| serde_json::from_slice::<AuthSettings>(&b).ok())
+        .filter(|s| s.window_secs <= MAX_AUTH_WINDOW_SECS)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackendInfo {
+    pub backend: Backend,
+    pub passphrase_protected: bool,
+    /// A passphrase-protected file store that hasn't been unlocked this session.
+    pub locked: bool,
+}
+
+/// Managed state behind the secret commands.
+pub struct Secrets {
+    store: Box<dyn SecretStore>,
+    index: PathBuf,
+    presence: Box<dyn UserPresence>,
+    // When the user last passed a presence check. Memory only, so every launch
+    // starts out needing one. Held across the prompt so concurrent reads share it.
+    last_auth: Mutex<Option<Instant>>,
+}
+
+impl Secrets {
+    /// Protected secrets can't be read until `with_presence` supplies a check.
+    pub fn new(store: Box<dyn SecretStore>, index: PathBuf) -> Self {
+        Self { store, index, presence: Box::new(NoPresence), last_auth: Mutex::new(None) }
+    }
+
+    pub fn with_presence(mut self, presence: Box<dyn UserPresence>) -> Self {
+        self.presence = presence;
+        self
+    }
+
+    /// Uses the OS keyring when there is one, and the encrypted file store in
+    /// the app data dir otherwise (headless Linux, some sandboxes).
+    pub fn open(app: &tauri::AppHandle) -> Result<Self, String> {
+        let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let store: Box<dyn SecretStore> = if KeyringStore::available() {
+            Box::new(KeyringStore)
+        } else {
+            tracing::warn!("no OS keyring available; storing secrets in an encrypted file");
+            Box::new(FileStore::new(&dir))
+        };
+        #[cfg(any(target_os = "macos", windows))]
+        let presence = OsPresence;
+        #[cfg(not(any(target_os = "macos", windows)))]
+        let presence = OsPresence(dir.clone());
+        Ok(Self::new(store, dir.join(INDEX_FILE)).with_presence(Box::new(presence)))
+    }
+
+    fn auth_settings_path(&self) -> PathBuf {
+        self.index.with_file_name(AUTH_SETTINGS_FILE)
+    }
+
+    pub fn auth_window_secs(&self) -> u32 {
+        load_auth_settings(&self.auth_settings_path()).window_secs
+    }
+
+    pub fn set_auth_window_secs(&self, window_secs: u32) -> Result<(), String> {
+        if window_secs > MAX_AUTH_WINDOW_SECS {
+            return Err(format!("auth window must be at most {MAX_AUTH_WINDOW_SECS} seconds"));
+        }
+        let json = serde_json::to_vec_pretty(&AuthSettings { window_secs }).map_err(|e| e.to_string())?;
+        std::fs::write(self.auth_settings_path(), json).map_err(|e| e.to_string())
+    }
+
+    pub fn requires_auth(&self, namespace: &str, key: &str) -> bool {
+        requires_auth_in(&self.index, namespace, key)
+    }
+
+    /// Passes when a check succeeded within the auth window, and otherwise
+    /// asks the user, giving `reason` as what the check is for.
+    #[tracing::instrument(skip(self))]
+    pub fn authenticate(&self, reason: &str) -> Result<(), SecretError> {
+        let mut last = self.last_auth.lock().map_err(|_| "secret auth lock poisoned".to_string())?;
+        let window = Duration::from_secs(u64::from(self.auth_window_secs()));
+        if last.is_some_and(|at| at.elapsed() < window) {
+            return Ok(());
+        }
+        match self.presence.confirm(reason) {
+            Ok(true) => {
+                *last = Some(Instant::now());
+                tracing::info!("user presence confirmed");
+                Ok(())
+            }
+            Ok(false) => {
+                tracing::warn!("user presence check denied");
+                Err(SecretError::AuthDenied)
+            }
+            Err(e) => {
+                tracing::warn!("user presence check unavailable: {e}");
+                Err(SecretError::AuthRequired(e))
+            }
+        }
+    }
+
+    /// `get` for the webview: secrets stored with `require_auth` need the user
+    /// to pass a presence check first.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_checked(&self, namespace: &str, key: &str) -> Result<Option<String>, SecretError> {
+        validate(namespace, key)?;
+        if self.requires_auth(namespace, key) {
+            self.authenticate(&format!("read the secret \"{key}\""))?;
+        }
+        let value = self.store.get(namespace, key)?;
+        tracing::debug!(found = value.is_some(), "secret read by the webview");
+        Ok(value)
+    }
+
+    pub fn backend_info(&self) -> BackendInfo {
+        BackendInfo {
+            backend: self.store.backend(),
+            passphrase_protected: self.store.passphrase_protected(),
+            locked: self.store.locked(),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        self.store.unlock(passphrase).inspect_err(|e| tracing::warn!("unlocking the secret store failed: {e}"))
+    }
+
+    /// Sets the file store's passphrase. Needs a presence check: whoever sets
+    /// it decides who can unlock the store later.
+    #[tracing::instrument(skip_all)]
+    pub fn set_passphrase(&self, passphrase: &str) -> Result<(), SecretError> {
+        self.authenticate("set a passphrase for your secrets")?;
+        self.store
+            .set_passphrase(passphrase)
+            .inspect_err(|e| tracing::warn!("setting the secret store passphrase failed: {e}"))?;
+        Ok(())
+    }
+
+    pub fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, String> {
+        validate(namespace, key)?;
+        self.store.get(namespace, key)
+    }
+
+    /// `require_auth` marks the key as needing a presence check to read;
+    /// `None` keeps whatever it was.
+    // Never the value: only its name and where it goes are recorded.
+    #[tracing::instrument(level = "debug", skip(self, value))]
+    pub fn set(&self, namespace: &str, key: &str, value: &str, require_auth: Option<bool>) -> Result<(), String> {
+        validate(namespace, key)?;
+        if value.len() > MAX_VALUE_LEN {
+            return Err("value too large".into());
+        }
+        self.store.set(namespace, key, value).inspect_err(|e| tracing::warn!("storing a secret failed: {e}"))?;
+        tracing::debug!("secret stored");
+        update_index_file(&self.index, namespace, |keys, flags| {
+            keys.insert(key.to_string());
+            match require_auth {
+                Some(true) => {
+                    flags.insert(key.to_string());
+                }
+                Some(false) => {
+                    flags.remove(key);
+                }
+                None => {}
+            }
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn delete(&self, namespace: &str, key: &str) -> Result<(), String> {
+        validate(namespace, key)?;
+        // Ignore failures, as before; the index entry goes either way.
+        let _ = self.store.delete(namespace, key);
+        update_index(&self.index, namespace, |keys| {
+            keys.remove(key);
+        })?;
+        tracing::debug!("secret deleted");
+        Ok(())
+    }
+
+    pub fn list(&self, namespace: &str) -> Result<Vec<String>, String> {
+        validate_namespace(namespace)?;
+        list_in(&self.index, namespace, |key| self.store.get(namespace, key).map(|v| v.is_some()))
+    }
+
+    /// Every indexed `(namespace, key)` outside the internal namespaces, pruned like `list`.
+    pub fn list_all(&self) -> Result<Vec<(String, String)>, String> {
+        let namespaces: Vec<String> =
+            read_index(&self.index).into_keys().filter(|ns| !is_internal_namespace(ns)).collect();
+        let mut out = Vec::new();
+        for namespace in namespaces {
+            out.extend(self.list(&namespace)?.into_iter().map(|key| (namespace.clone(), key)));
+        }
+        Ok(out)
+    }
+
+    /// Deletes every indexed secret in `namespace` and returns how many there were.
+    /// Keys that fail to delete stay indexed so a retry can find them.
+    #[tracing::instrument(skip(self))]
+    pub fn delete_namespace(&self, namespace: &str) -> Result<usize, String> {
+        validate_namespace(namespace)?;
+        let keys = read_index(&self.index).remove(namespace).unwrap_or_default();
+        let mut deleted = BTreeSet::new();
+        let mut first_err = None;
+        for key in &keys {
+            match self.store.delete(namespace, key) {
+                Ok(()) => {
+                    deleted.insert(key.clone());
+                }
+                Err(e) => {
+                    first_err.get_or_insert_with(|| format!("{key}: {e}"));
+                }
+            }
+        }
+        update_index(&self.index, namespace, |indexed| indexed.retain(|k| !deleted.contains(k)))?;
+        tracing::info!(deleted = deleted.len(), "secret namespace cleared");
+        match first_err {
+            Some(e) => Err(format!("failed to delete some secrets ({e})")),
+            None => Ok(deleted.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(entries: &[(&str, &[&str])]) -> Index {
+        entries
+            .iter()
+            .map(|(ns, keys)| (ns.to_string(), keys.iter().map(|k| k.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn index_tracks_names_and_writes_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(INDEX_FILE);
+        assert!(read_index(&path).is_empty());
+
+        update_index(&path, DEFAULT_NAMESPACE, |k| {
+            k.insert("openai".into());
+        })
+        .unwrap();
+        update_index(&path, DEFAULT_NAMESPACE, |k| {
+            k.insert("anthropic".into());
+        })
+        .unwrap();
+        assert_eq!(read_index(&path), index(&[(DEFAULT_NAMESPACE, &["anthropic", "openai"])]));
+        assert!(!path.with_extension("json.tmp").exists());
+
+        update_index(&path, DEFAULT_NAMESPACE, |k| {
+            k.remove("openai");
+        })
+        .unwrap();
+        assert_eq!(read_index(&path), index(&[(DEFAULT_NAMESPACE, &["anthropic"])]));
+    }
+
+    #[test]
+    fn corrupt_index_reads_as_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(INDEX_FILE);
+        std::fs::write(&path, "not json").unwrap();
+        assert!(read_index(&path).is_empty());
+    }
+
+    #[test]
+    fn concurrent_updates_are_not_lost() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(INDEX_FILE);
+        std::thread::scope(|s| {
+            for i in 0..16 {
+                let path = &path;
+                s.spawn(move || {
+                    update_index(path, DEFAULT_NAMESPACE, |k| {
+                        k.insert(format!("key-{i}"));
+                    })
+                    .unwrap()
+                });
+            }
+        });
+        assert_eq!(read_index(&path)[DEFAULT_NAMESPACE].len(), 16);
+    }
+
+    #[test]
+    fn list_prunes_entries_missing_from_the_keyring() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(INDEX_FILE);
+        let keys = index(&[("work", &["a", "b", "c"]), ("home", &["b"])]);
+        write_index(&path, &IndexFile { keys, ..IndexFile::default() }).unwrap();
+
+        let listed = list_in(&path, "work", |k| match k {
+            "a" => Ok(true),
+            "b" => Ok(false),
+            _ => Err("keyring locked".into()),
+        })
+        .unwrap();
+        assert_eq!(listed, vec!["a", "c"]);
+        assert_eq!(read_index(&path), index(&[("work", &["a", "c"]), ("home", &["b"])]));
+    }
+
+    #[test]
+    fn flat_index_reads_as_the_default_namespace() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(INDEX_FILE);
+        std::fs::write(&path, r#"["identity.v1", "openai"]"#).unwrap();
+        assert_eq!(read_index(&path), index(&[(DEFAULT_NAMESPACE, &["identity.v1", "openai"])]));
+
+        update_index(&path, "work", |k| {
+            k.insert("openai".into());
+        })
+        .unwrap();
+        assert_eq!(
+            read_index(&path),
+            index(&[(DEFAULT_NAMESPACE, &["identity.v1", "openai"]), ("work", &["openai"])])
+        );
+    }
+
+    fn file_backed(dir: &Path) -> Secrets {
+        Secrets::new(Box::new(FileStore::for_tests(dir)), dir.join(INDEX_FILE))
+    }
+
+    #[test]
+    fn commands_work_against_the_file_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = file_backed(dir.path());
+        assert_eq!(secrets.backend_info().backend, Backend::File);
+
+        secrets.set(DEFAULT_NAMESPACE, "openai", "sk-1", None).unwrap();
+        secrets.set("work", "openai", "sk-2", None).unwrap();
+        secrets.set(crate::identity::NAMESPACE, "ed25519:abc", "pkcs8", None).unwrap();
+        assert_eq!(secrets.get(DEFAULT_NAMESPACE, "openai").unwrap().as_deref(), Some("sk-1"));
+        assert_eq!(secrets.get("work", "openai").unwrap().as_deref(), Some("sk-2"));
+        assert_eq!(secrets.get("work", "missing").unwrap(), None);
+        assert_eq!(secrets.list("work").unwrap(), vec!["openai"]);
+        assert_eq!(
+            secrets.list_all().unwrap(),
+            vec![(DEFAULT_NAMESPACE.to_string(), "openai".to_string()), ("work".to_string(), "openai".to_string())]
+        );
+
+        assert!(webview_namespace(Some(crate::identity::NAMESPACE.into())).is_err());
+        assert_eq!(webview_namespace(None).unwrap(), DEFAULT_NAMESPACE);
+
+        secrets.delete(DEFAULT_NAMESPACE, "openai").unwrap();
+        assert_eq!(secrets.get(DEFAULT_NAMESPACE, "openai").unwrap(), None);
+        assert!(secrets.list(DEFAULT_NAMESPACE).unwrap().is_empty());
+
+        secrets.set("work", "github", "ghp", None).unwrap();
+        assert_eq!(secrets.delete_namespace("work").unwrap(), 2);
+        assert_eq!(secrets.get("work", "github").unwrap(), None);
+        secrets.delete(crate::identity::NAMESPACE, "ed25519:abc").unwrap();
+        assert!(read_index(&dir.path().join(INDEX_FILE)).is_empty());
+    }
+
+    // Answers every presence check with `answer` and counts the prompts.
+    struct Presence(Result<bool, String>, std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    impl UserPresence for Presence {
+        fn confirm(&self, _reason: &str) -> Result<bool, String> {
+            self.1.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.0.clone()
+        }
+    }
+
+    fn answering(dir: &Path, answer: Result<bool, String>) -> (Secrets, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        let prompts = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let secrets = file_backed(dir).with_presence(Box::new(Presence(answer, prompts.clone())));
+        (secrets, prompts)
+    }
+
+    #[test]
+    fn protected_secrets_need_the_user_present() {
+        let dir = tempfile::tempdir().unwrap();
+        let (secrets, prompts) = answering(dir.path(), Ok(true));
+        let prompts = || prompts.load(std::sync::atomic::Ordering::SeqCst);
+        secrets.set(DEFAULT_NAMESPACE, "space.passphrase", "hunter2", Some(true)).unwrap();
+        secrets.set(DEFAULT_NAMESPACE, "theme", "dark", None).unwrap();
+
+        assert_eq!(secrets.get_checked(DEFAULT_NAMESPACE, "theme").unwrap().as_deref(), Some("dark"));
+        assert_eq!(prompts(), 0);
+        assert_eq!(secrets.get_checked(DEFAULT_NAMESPACE, "space.passphrase").unwrap().as_deref(), Some("hunter2"));
+        assert_eq!(secrets.get_checked(DEFAULT_NAMESPACE, "space.passphrase").unwrap().as_deref(), Some("hunter2"));
+        assert_eq!(prompts(), 1, "the second read falls inside the auth window");
+
+        secrets.set_auth_window_secs(0).unwrap();
+        secrets.get_checked(DEFAULT_NAMESPACE, "space.passphrase").unwrap();
+        assert_eq!(prompts(), 2);
+        assert!(secrets.set_auth_window_secs(MAX_AUTH_WINDOW_SECS + 1).is_err());
+        assert_eq!(secrets.auth_window_secs(), 0);
+
+        // The flag lives in the index, never in the value; Rust-side reads aren't gated.
+        assert_eq!(secrets.get(DEFAULT_NAMESPACE, "space.passphrase").unwrap().as_deref(), Some("hunter2"));
+        let index = std::fs::read_to_string(dir.path().join(INDEX_FILE)).unwrap();
+        assert!(index.contains("require_auth") && !index.contains("hunter2"), "{index}");
+
+        secrets.set(DEFAULT_NAMESPACE, "space.passphrase", "hunter3", None).unwrap();
+        assert!(secrets.requires_auth(DEFAULT_NAMESPACE, "space.passphrase"), "None keeps the flag");
+        secrets.set(DEFAULT_NAMESPACE, "space.passphrase", "hunter3", Some(false)).unwrap();
+        assert!(!secrets.requires_auth(DEFAULT_NAMESPACE, "space.passphrase"));
+        secrets.set(DEFAULT_NAMESPACE, "space.passphrase", "hunter3", Some(true)).unwrap();
+        secrets.delete(DEFAULT_NAMESPACE, "space.passphrase").unwrap();
+        secrets.set(DEFAULT_NAMESPACE, "space.passphrase", "again", None).unwrap();
+        assert!(!secrets.requires_auth(DEFAULT_NAMESPACE, "space.passphrase"), "deleting drops the flag");
+    }
+
+    #[cfg(not(any(target_os = "macos", windows)))]
+    #[test]
+    fn presence_passphrase_is_verified_against_its_digest() {
+        let check = PresenceCheck::new("correct horse", (64, 1, 1)).unwrap();
+        assert!(check.matches("correct horse").unwrap());
+        assert!(!check.matches("battery staple").unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PRESENCE_FILE);
+        assert_eq!(read_presence_check(&path).unwrap(), None);
+        crate::secret_file_store::write_private(&path, &serde_json::to_vec(&check).unwrap()).unwrap();
+        let stored = read_presence_check(&path).unwrap().unwrap();
+        assert!(stored.matches("correct horse").unwrap());
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("correct horse"));
+    }
+
+    #[test]
+    fn failed_checks_are_typed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (denying, _) = answering(dir.path(), Ok(false));
+        denying.set("work", "openai", "sk", Some(true)).unwrap();
+        let e = denying.get_checked("work", "openai").unwrap_err();
+        assert_eq!(e, SecretError::AuthDenied);
+        assert_eq!(serde_json::to_value(&e).unwrap(), serde_json::json!({ "code": "auth_denied" }));
+        let e = crate::secret_export::export(&denying, "correct horse").unwrap_err();
+        assert_eq!(e, crate::secret_export::SecretExportError::AuthDenied);
+        assert_eq!(denying.set_passphrase("hunter22").unwrap_err(), SecretError::AuthDenied);
+        assert!(!denying.backend_info().passphrase_protected);
+
+        // Without a way to prompt, protected secrets can't be read at all.
+        let e = file_backed(dir.path()).get_checked("work", "openai").unwrap_err();
+        assert!(matches!(e, SecretError::AuthRequired(_)), "{e:?}");
+        assert_eq!(serde_json::to_value(&e).unwrap()["code"], "auth_required");
+    }
+
+    // Collects everything the log file would get.
+    #[derive(Clone, Default)]
+    struct Captured(std::sync::Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for Captured {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn secret_values_stay_out_of_the_logs() {
+        use tracing_subscriber::prelude::*;
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = Captured::default();
+        let subscriber = tracing_subscriber::registry().with(crate::logs::file_layer(log.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let (secrets, _) = answering(dir.path(), Ok(true));
+            secrets.set(DEFAULT_NAMESPACE, "openai", "sk-canary-3f9a", Some(true)).unwrap();
+            secrets.set("work", "github", "ghp-canary-77c1", None).unwrap();
+            assert!(secrets.set("work", "huge", &"canary".repeat(MAX_VALUE_LEN), None).is_err());
+            assert_eq!(secrets.get_checked(DEFAULT_NAMESPACE, "openai").unwrap().as_deref(), Some("sk-canary-3f9a"));
+            assert_eq!(secrets.get("work", "github").unwrap().as_deref(), Some("ghp-canary-77c1"));
+            secrets.list_all().unwrap();
+            secrets.delete(DEFAULT_NAMESPACE, "openai").unwrap();
+            assert_eq!(secrets.delete_namespace("work").unwrap(), 1);
+
+            let (denying, _) = answering(dir.path(), Ok(false));
+            denying.set("work", "github", "ghp-canary-77c2", Some(true)).unwrap();
+            assert!(denying.get_checked("work", "github").is_err());
+        });
+
+        let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+        // The operations and key names are there to diagnose with, the values never.
+        for expected in ["secret stored", "secret read by the webview", "user presence check denied", "openai", "github"] {
+            assert!(log.contains(expected), "missing {expected:?}: {log}");
+        }
+        assert!(!log.contains("canary"), "{log}");
+    }
+
+    #[test]
+    fn key_only_indexes_read_without_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(INDEX_FILE);
+        std::fs::write(&path, r#"{"work": ["openai"], "keys": ["a"]}"#).unwrap();
+        let file = read_index_file(&path);
+        assert_eq!(file.keys, index(&[("work", &["openai"]), ("keys", &["a"])]));
+        assert!(file.require_auth.is_empty());
+    }
+
+    #[test]
+    fn validation_is_shared_by_every_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = file_backed(dir.path());
+        assert!(secrets.set("bad ns", "k", "v", None).is_err());
+        assert!(secrets.set(DEFAULT_NAMESPACE, " ", "v", None).is_err());
+        assert!(secrets.set(DEFAULT_NAMESPACE, &"k".repeat(MAX_KEY_LEN + 1), "v", None).is_err());
+        assert!(secrets.set(DEFAULT_NAMESPACE, "k", &"v".repeat(MAX_VALUE_LEN + 1), None).is_err());
+        assert!(secrets.get("a/b", "k").is_err());
+    }
+
+    #[test]
+    fn namespaces_map_to_keyring_services() {
+        assert_eq!(service_for(DEFAULT_NAMESPACE), "voxelle");
+        assert_eq!(service_for("space-42"), "voxelle::space-42");
+
+        for ok in ["default", "space-42", "a.b_c", &"x".repeat(MAX_NAMESPACE_LEN)] {
+            assert!(validate_namespace(ok).is_ok(), "{ok}");
+        }
+        for bad in ["", "a b", "a/b", "a::b", "ünï", &"x".repeat(MAX_NAMESPACE_LEN + 1)] {
+            assert!(validate_namespace(bad).is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Mutex;
+
+const LAN_WARNING: &str = "the relay accepts connections from any device on your network";
+
+// The embedded signaling relay, when one is running. At most one at a time.
+#[derive(Default)]
+pub struct SignalRelay(Mutex<Option<Running>>);
+
+struct Running {
+    relay: voxelle_signal::Relay,
+    lan: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SignalRelayInfo {
+    pub port: u16,
+    /// Where this machine reaches the relay; other LAN devices use its LAN address instead.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SignalRelayStatus {
+    pub running: bool,
+    pub port: Option<u16>,
+    pub url: Option<String>,
+    /// Bound to every interface rather than just localhost.
+    pub lan: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+// Serialized to the frontend as `{ "code": "already_running", "detail": 9002 }`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "code", content = "detail", rename_all = "snake_case")]
+pub enum SignalRelayError {
+    AlreadyRunning(u16),
+    NotRunning,
+    Bind(String),
+    Other(String),
+}
+
+impl std::fmt::Display for SignalRelayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AlreadyRunning(port) => write!(f, "the signaling relay is already running on port {port}"),
+            Self::NotRunning => write!(f, "the signaling relay is not running"),
+            Self::Bind(e) => write!(f, "could not start the signaling relay: {e}"),
+            Self::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<String> for SignalRelayError {
+    fn from(e: String) -> Self {
+        Self::Other(e)
+    }
+}
+
+fn url_for(port: u16) -> String {
+    format!("ws://127.0.0.1:{port}/ws")
+}
+
+impl SignalRelay {
+    /// Starts the relay on `port` (a free one when `None`), on localhost unless
+    /// `lan` opts in to every interface.
+    pub async fn start(&self, port: Option<u16>, lan: bool) -> Result<SignalRelayInfo, SignalRelayError> {
+        if let Some(port) = self.port() {
+            return Err(SignalRelayError::AlreadyRunning(port));
+        }
+        let host = if lan { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+        let addr = SocketAddr::from((host, port.unwrap_or(0)));
+        let relay = voxelle_signal::Relay::start(addr, voxelle_signal::RelayConfig::default())
+            .await
+            .map_err(|e| SignalRelayError::Bind(e.to_string()))?;
+        let port = relay.local_addr().port();
+
+        let mut g = self.0.lock().map_err(|_| "signal relay lock poisoned".to_string())?;
+        // Another start won the race while this one was binding.
+        if let Some(running) = g.as_ref() {
+            let port = running.relay.local_addr().port();
+            relay.stop();
+            return Err(SignalRelayError::AlreadyRunning(port));
+        }
+        *g = Some(Running { relay, lan });
+        Ok(SignalRelayInfo { port, url: url_for(port) })
+    }
+
+    pub fn stop(&self) -> Result<(), SignalRelayError> {
+        let running = self.0.lock().map_err(|_| "signal relay lock poisoned".to_string())?.take();
+        let running = running.ok_or(SignalRelayError::NotRunning)?;
+        running.relay.stop();
+        Ok(())
+    }
+
+    pub fn status(&self) -> SignalRelayStatus {
+        let g = self.0.lock().ok();
+        let running = g.as_ref().and_then(|g| g.as_ref());
+        let port = running.map(|r| r.relay.local_addr().port());
+        let lan = running.is_some_and(|r| r.lan);
+        SignalRelayStatus {
+            running: running.is_some(),
+            port,
+            url: port.map(url_for),
+            lan,
+            warning: lan.then(|| LAN_WARNING.to_string()),
+        }
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.0.lock().ok()?.as_ref().map(|r| r.relay.local_addr().port())
+    }
+
+    /// Stops the relay if it is running; for app exit.
+    pub fn shutdown(&self) {
+        let _ = self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_and_stop_are_exclusive() {
+        let relay = SignalRelay::default();
+        assert_eq!(relay.stop(), Err(SignalRelayError::NotRunning));
+        assert_eq!(relay.status(), SignalRelayStatus { running: false, port: None, url: None, lan: false, warning: None });
+
+        let info = tauri::async_runtime::block_on(relay.start(None, false)).unwrap();
+        assert_ne!(info.port, 0);
+        assert_eq!(info.url, format!("ws://127.0.0.1:{}/ws", info.port));
+        assert_eq!(
+            tauri::async_runtime::block_on(relay.start(None, false)),
+            Err(SignalRelayError::AlreadyRunning(info.port))
+        );
+        let status = relay.status();
+        assert_eq!((status.running, status.port, status.lan, status.warning), (true, Some(info.port), false, None));
+        assert_eq!(relay.port(), Some(info.port));
+
+        relay.stop().unwrap();
+        assert_eq!(relay.port(), None);
+        assert_eq!(relay.stop(), Err(SignalRelayError::NotRunning));
+    }
+
+    #[test]
+    fn lan_binding_warns() {
+        let relay = SignalRelay::default();
+        tauri::async_runtime::block_on(relay.start(None, true)).unwrap();
+        let status = relay.status();
+        assert!(status.lan);
+        assert_eq!(status.warning.as_deref(), Some(LAN_WARNING));
+        relay.shutdown();
+        assert!(!relay.status().running);
+    }
+
+    #[test]
+    fn errors_serialize_with_a_code() {
+        assert_eq!(
+            serde_json::to_value(SignalRelayError::AlreadyRunning(9002)).unwrap(),
+            serde_json::json!({ "code": "already_running", "detail": 9002 })
+        );
+        assert_eq!(serde_json::to_value(SignalRelayError::NotRunning).unwrap(), serde_json::json!({ "code": "not_running" }));
+    }
+}
@@ -0,0 +1,286 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Manager, Url};
+
+use crate::deep_link;
+
+// Held with an exclusive lock by the running instance. The OS drops the lock
+// when the process dies, so a crash never leaves a stale one behind.
+const LOCK_FILE: &str = "instance.lock";
+// Where the running instance listens for launches to hand over. Separate from
+// the lock file, which Windows won't let other processes read while it's locked.
+const ENDPOINT_FILE: &str = "instance.json";
+// How long a second launch keeps trying to reach the first, or to take over
+// from one that's shutting down, before giving up.
+pub const HANDOFF_TIMEOUT: Duration = Duration::from_secs(10);
+const RETRY_EVERY: Duration = Duration::from_millis(100);
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_HANDOFF_BYTES: u64 = 64 * 1024;
+const MAX_ARGS: usize = 32;
+const ACK: &str = "ok";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Endpoint {
+    port: u16,
+    /// Only someone who can read the app data dir can hand launches over.
+    token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Handoff {
+    token: String,
+    args: Vec<String>,
+}
+
+pub enum Launch {
+    /// This is the only instance; keep it for as long as the app runs.
+    Primary(Instance),
+    /// The running instance took this launch's arguments; exit now.
+    Forwarded,
+}
+
+/// The lock that makes this process the running instance.
+pub struct Instance {
+    _lock: File,
+    dir: PathBuf,
+    // Set once the app starts exiting; handoffs after that are refused, so the
+    // second launch waits for the lock instead of losing its link.
+    closed: Arc<Mutex<bool>>,
+}
+
+fn try_lock(dir: &Path) -> Result<Option<File>, String> {
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(LOCK_FILE))
+        .map_err(|e| e.to_string())?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+        Err(std::fs::TryLockError::Error(e)) => Err(e.to_string()),
+    }
+}
+
+// Hands `args` to the running instance; `Ok` only once it has acknowledged them.
+fn forward(dir: &Path, args: &[String]) -> Result<(), String> {
+    let endpoint: Endpoint = std::fs::read(dir.join(ENDPOINT_FILE))
+        .map_err(|e| e.to_string())
+        .and_then(|b| serde_json::from_slice(&b).map_err(|e| e.to_string()))?;
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, endpoint.port));
+    let mut stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+
+    let mut line = serde_json::to_vec(&Handoff { token: endpoint.token, args: args.to_vec() }).map_err(|e| e.to_string())?;
+    line.push(b'\n');
+    stream.write_all(&line).map_err(|e| e.to_string())?;
+    let mut reply = String::new();
+    BufReader::new(stream.take(64)).read_line(&mut reply).map_err(|e| e.to_string())?;
+    match reply.trim() {
+        ACK => Ok(()),
+        "" => Err("the running instance closed the connection".into()),
+        other => Err(format!("the running instance answered {other:?}")),
+    }
+}
+
+/// Becomes the running instance, or hands `args` to the one already running.
+/// While that one is shutting down it refuses handoffs, so this keeps trying
+/// until either it answers or its lock is released and this launch takes over.
+fn acquire_in(dir: &Path, args: &[String], timeout: Duration) -> Result<Launch, String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(lock) = try_lock(dir)? {
+            return Ok(Launch::Primary(Instance { _lock: lock, dir: dir.to_path_buf(), closed: Arc::default() }));
+        }
+        let err = match forward(dir, args) {
+            Ok(()) => return Ok(Launch::Forwarded),
+            Err(e) => e,
+        };
+        if Instant::now() >= deadline {
+            return Err(format!("another instance is running but didn't take this launch: {err}"));
+        }
+        std::thread::sleep(RETRY_EVERY);
+    }
+}
+
+fn handle_handoff(stream: TcpStream, token: &str, closed: &Mutex<bool>, on_handoff: &dyn Fn(Vec<String>)) -> Result<(), String> {
+    stream.set_read_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_HANDOFF_BYTES)).read_line(&mut line).map_err(|e| e.to_string())?;
+    let handoff: Handoff = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+    if !voxelle_protocol::ct_eq_str(&handoff.token, token) {
+        return Err("wrong token".into());
+    }
+    // Held until the reply is written, so `close` can't slip in between
+    // taking the launch and acknowledging it.
+    let closed = closed.lock().map_err(|_| "instance lock poisoned".to_string())?;
+    if *closed {
+        return (&stream).write_all(b"closing\n").map_err(|e| e.to_string());
+    }
+    on_handoff(handoff.args.into_iter().take(MAX_ARGS).collect());
+    (&stream).write_all(format!("{ACK}\n").as_bytes()).map_err(|e| e.to_string())
+}
+
+impl Instance {
+    /// Starts taking launches handed over by later instances. Call once
+    /// whatever `on_handoff` needs is set up; until then they wait.
+    fn listen_with(&self, on_handoff: impl Fn(Vec<String>) + Send + 'static) -> Result<(), String> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| e.to_string())?;
+        let mut secret = [0u8; 32];
+        getrandom::getrandom(&mut secret).map_err(|e| e.to_string())?;
+        let token = hex::encode(secret);
+        let endpoint = Endpoint { port: listener.local_addr().map_err(|e| e.to_string())?.port(), token: token.clone() };
+
+        // Renamed into place, so a second launch never reads half of it.
+        let path = self.dir.join(ENDPOINT_FILE);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&endpoint).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+
+        let closed = self.closed.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = handle_handoff(stream, &token, &closed, &on_handoff) {
+                    tracing::warn!("ignoring a launch handed over by another instance: {e}");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Stops taking handoffs; called on exit. A launch that arrives later
+    /// waits for this process to go and then starts in its place.
+    pub fn close(&self) {
+        if let Ok(mut closed) = self.closed.lock() {
+            *closed = true;
+        }
+        let _ = std::fs::remove_file(self.dir.join(ENDPOINT_FILE));
+    }
+}
+
+/// `acquire_in` for this app, with its own command line.
+pub fn acquire(app: &tauri::AppHandle) -> Result<Launch, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    acquire_in(&dir, &args, HANDOFF_TIMEOUT)
+}
+
+// Arguments that look like our links. Each still goes through
+// `deep_link::parse` like a link opened natively.
+fn forwarded_links(args: &[String]) -> Vec<Url> {
+    args.iter()
+        .filter_map(|arg| Url::parse(arg).ok())
+        .filter(|url| url.scheme() == deep_link::SCHEME)
+        .collect()
+}
+
+/// Routes handed-over links like native ones, and brings the window forward
+/// either way: launching the app again is how people find it.
+pub fn listen(app: &tauri::AppHandle, instance: &Instance) -> Result<(), String> {
+    let app = app.clone();
+    instance.listen_with(move |args| deep_link::handle(&app, &forwarded_links(&args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn primary(dir: &Path) -> Instance {
+        match acquire_in(dir, &[], Duration::ZERO).unwrap() {
+            Launch::Primary(instance) => instance,
+            Launch::Forwarded => panic!("expected to be the first instance"),
+        }
+    }
+
+    #[test]
+    fn second_launches_hand_their_args_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = primary(dir.path());
+        let (tx, rx) = mpsc::channel();
+        first.listen_with(move |args| tx.send(args).unwrap()).unwrap();
+
+        let link = args(&["voxelle://session?sid=ab&relay=wss://r.example"]);
+        assert!(matches!(acquire_in(dir.path(), &link, HANDOFF_TIMEOUT).unwrap(), Launch::Forwarded));
+        assert_eq!(rx.try_recv().unwrap(), link, "acknowledged only after it was handled");
+        assert!(matches!(acquire_in(dir.path(), &[], HANDOFF_TIMEOUT).unwrap(), Launch::Forwarded));
+        assert_eq!(rx.try_recv().unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn launches_wait_while_the_first_instance_starts_listening() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = primary(dir.path());
+        let path = dir.path().to_path_buf();
+        let second = std::thread::spawn(move || acquire_in(&path, &args(&["x"]), HANDOFF_TIMEOUT).map(|l| matches!(l, Launch::Forwarded)));
+        std::thread::sleep(RETRY_EVERY * 3);
+        let (tx, rx) = mpsc::channel();
+        first.listen_with(move |args| tx.send(args).unwrap()).unwrap();
+        assert!(second.join().unwrap().unwrap());
+        assert_eq!(rx.recv().unwrap(), args(&["x"]));
+    }
+
+    #[test]
+    fn launches_during_shutdown_take_over_instead_of_getting_lost() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = primary(dir.path());
+        let (tx, rx) = mpsc::channel();
+        first.listen_with(move |args| tx.send(args).unwrap()).unwrap();
+        // The endpoint outlives `close` here, as it would if a launch read it just before.
+        let endpoint = std::fs::read(dir.path().join(ENDPOINT_FILE)).unwrap();
+        first.close();
+        std::fs::write(dir.path().join(ENDPOINT_FILE), endpoint).unwrap();
+
+        let path = dir.path().to_path_buf();
+        let second = std::thread::spawn(move || acquire_in(&path, &args(&["voxelle://join"]), HANDOFF_TIMEOUT));
+        std::thread::sleep(RETRY_EVERY * 3);
+        // The exiting process goes away, and its lock with it.
+        drop(first);
+        let Launch::Primary(second) = second.join().unwrap().unwrap() else {
+            panic!("the second launch should have taken over");
+        };
+        assert!(rx.try_recv().is_err(), "the closing instance took nothing");
+
+        // And it's the running instance now.
+        second.listen_with(|_| {}).unwrap();
+        assert!(matches!(acquire_in(dir.path(), &[], HANDOFF_TIMEOUT).unwrap(), Launch::Forwarded));
+    }
+
+    #[test]
+    fn handoffs_need_the_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = primary(dir.path());
+        let (tx, rx) = mpsc::channel();
+        first.listen_with(move |args| tx.send(args).unwrap()).unwrap();
+        let endpoint: Endpoint = serde_json::from_slice(&std::fs::read(dir.path().join(ENDPOINT_FILE)).unwrap()).unwrap();
+
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, endpoint.port)).unwrap();
+        let forged = serde_json::to_string(&Handoff { token: "0".repeat(64), args: args(&["voxelle://join"]) }).unwrap();
+        stream.write_all(format!("{forged}\n").as_bytes()).unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "");
+        assert!(rx.try_recv().is_err());
+        // Gives up rather than starting a second copy next to a live one.
+        std::fs::write(dir.path().join(ENDPOINT_FILE), serde_json::to_vec(&Endpoint { token: "0".repeat(64), ..endpoint }).unwrap()).unwrap();
+        assert!(acquire_in(dir.path(), &[], RETRY_EVERY).is_err());
+    }
+
+    #[test]
+    fn only_our_links_are_routed() {
+        let links = forwarded_links(&args(&["--flag", "voxelle://join?invite=x", "https://example.com", "C:\\path"]));
+        assert_eq!(links, vec![Url::parse("voxelle://join?invite=x").unwrap()]);
+    }
+}
@@ -0,0 +1,155 @@
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{Listener, Manager};
+
+use crate::auto_update::EVENT_WEB_UPDATE_AVAILABLE;
+use crate::web_update::{self, LastCheckResult, WebUpdateState, EVENT_WEB_UPDATE_READY};
+
+const ID_CHECK: &str = "tray-check";
+const ID_INSTALL: &str = "tray-install";
+const ID_OPEN: &str = "tray-open";
+const ID_QUIT: &str = "tray-quit";
+
+fn version_label(version: &str) -> String {
+    format!("Voxelle web {version}")
+}
+
+fn check_label(last: Option<&LastCheckResult>) -> String {
+    match last {
+        None => "Check for updates".to_string(),
+        Some(LastCheckResult::UpToDate) => "Check for updates (up to date)".to_string(),
+        Some(LastCheckResult::Available { version, downgrade: false }) => format!("Check for updates ({version} available)"),
+        Some(LastCheckResult::Available { version, downgrade: true }) => format!("Check for updates ({version} offered, older)"),
+        Some(LastCheckResult::Skipped { version }) => format!("Check for updates ({version} skipped)"),
+        Some(LastCheckResult::Failed { .. }) => "Check for updates (last check failed)".to_string(),
+    }
+}
+
+fn last_result(state: &WebUpdateState) -> Option<LastCheckResult> {
+    state.last_check.lock().ok().and_then(|g| g.as_ref().map(|c| c.result.clone()))
+}
+
+#[derive(Clone)]
+struct Items {
+    version: MenuItem<tauri::Wry>,
+    check: MenuItem<tauri::Wry>,
+    install: MenuItem<tauri::Wry>,
+}
+
+impl Items {
+    fn refresh_check(&self, app: &tauri::AppHandle) {
+        let last = last_result(&app.state::<WebUpdateState>());
+        let _ = self.check.set_text(check_label(last.as_ref()));
+        let _ = self.check.set_enabled(true);
+    }
+}
+
+/// Adds the tray icon. Needs `WebUpdateState` to be managed. Where there's no
+/// tray (some Linux desktops without an indicator host) this logs and returns.
+pub fn create(app: &tauri::AppHandle) {
+    if let Err(e) = try_create(app) {
+        tracing::warn!("system tray unavailable: {e}");
+    }
+}
+
+fn try_create(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let state = app.state::<WebUpdateState>();
+    let active = state.active_version.lock().map(|g| g.clone()).unwrap_or_default();
+    let items = Items {
+        version: MenuItem::with_id(app, "tray-version", version_label(&active), false, None::<&str>)?,
+        check: MenuItem::with_id(app, ID_CHECK, check_label(last_result(&state).as_ref()), true, None::<&str>)?,
+        // Enabled once an update has been activated but the window still shows the old bundle.
+        install: MenuItem::with_id(app, ID_INSTALL, "Install update and reload", false, None::<&str>)?,
+    };
+    let open = MenuItem::with_id(app, ID_OPEN, "Open Voxelle", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, ID_QUIT, "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &items.version,
+            &PredefinedMenuItem::separator(app)?,
+            &items.check,
+            &items.install,
+            &PredefinedMenuItem::separator(app)?,
+            &open,
+            &quit,
+        ],
+    )?;
+
+    let mut tray = TrayIconBuilder::with_id("main").tooltip("Voxelle").menu(&menu).show_menu_on_left_click(true);
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    let on_menu = items.clone();
+    tray.on_menu_event(move |app, event| on_menu_event(app, &on_menu, event.id().as_ref()))
+        .build(app)?;
+
+    let ready = items.clone();
+    app.listen_any(EVENT_WEB_UPDATE_READY, move |event| {
+        if let Ok(version) = serde_json::from_str::<String>(event.payload()) {
+            let _ = ready.version.set_text(version_label(&version));
+        }
+        let _ = ready.install.set_enabled(true);
+    });
+    let handle = app.clone();
+    app.listen_any(EVENT_WEB_UPDATE_AVAILABLE, move |_| items.refresh_check(&handle));
+    Ok(())
+}
+
+fn on_menu_event(app: &tauri::AppHandle, items: &Items, id: &str) {
+    match id {
+        ID_CHECK => {
+            let _ = items.check.set_enabled(false);
+            let _ = items.check.set_text("Checking for updates…");
+            let (app, items) = (app.clone(), items.clone());
+            tauri::async_runtime::spawn(async move {
+                let state = app.state::<WebUpdateState>().inner().clone();
+                // The outcome is recorded in `last_check`, which the label reads. While
+                // another operation runs this fails with `Busy` and the label stays as is.
+                let _ = web_update::check(&state, &app).await;
+                items.refresh_check(&app);
+            });
+        }
+        ID_INSTALL => {
+            let state = app.state::<WebUpdateState>();
+            match web_update::reload_main_window(&state, app) {
+                Ok(()) => {
+                    let _ = items.install.set_enabled(false);
+                    show_main_window(app);
+                }
+                Err(e) => tracing::warn!("reload after web update failed: {e}"),
+            }
+        }
+        ID_OPEN => show_main_window(app),
+        ID_QUIT => app.exit(0),
+        _ => {}
+    }
+}
+
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_label_reflects_the_last_result() {
+        assert_eq!(check_label(None), "Check for updates");
+        assert_eq!(check_label(Some(&LastCheckResult::UpToDate)), "Check for updates (up to date)");
+        let available = LastCheckResult::Available { version: "1.2.0".into(), downgrade: false };
+        assert_eq!(check_label(Some(&available)), "Check for updates (1.2.0 available)");
+        let older = LastCheckResult::Available { version: "1.0.0".into(), downgrade: true };
+        assert_eq!(check_label(Some(&older)), "Check for updates (1.0.0 offered, older)");
+        let skipped = LastCheckResult::Skipped { version: "1.2.0".into() };
+        assert_eq!(check_label(Some(&skipped)), "Check for updates (1.2.0 skipped)");
+        let failed = LastCheckResult::Failed { error: "offline".into() };
+        assert_eq!(check_label(Some(&failed)), "Check for updates (last check failed)");
+        assert_eq!(version_label("0.1.0"), "Voxelle web 0.1.0");
+    }
+}
@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::secrets::Secrets;
+use crate::web_update::{self, WebUpdateCommandError, WebUpdateErrorCode, WebUpdateState};
+
+pub(crate) const CONFIG_FILE: &str = "web_update_network.json";
+pub const DEFAULT_TIMEOUT_SECS: u32 = 30;
+pub const MAX_TIMEOUT_SECS: u32 = 600;
+// Proxy credentials are ordinary secrets, written by the frontend with
+// `voxelle_secret_set` and read here when the client is built.
+pub const PROXY_SECRET_NAMESPACE: &str = "web_update";
+pub const PROXY_USERNAME_KEY: &str = "proxy_username";
+pub const PROXY_PASSWORD_KEY: &str = "proxy_password";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// `http://` or `https://` proxy for all update traffic. Credentials live
+    /// in the keyring, not in the URL.
+    pub proxy_url: Option<String>,
+    /// Honor HTTP_PROXY / HTTPS_PROXY / NO_PROXY when no `proxy_url` is set.
+    pub use_system_proxy: bool,
+    /// PEM file with extra root certificates, for TLS-intercepting gateways.
+    pub extra_root_cert: Option<PathBuf>,
+    /// Applies to connecting and to each read, so large downloads on a slow
+    /// but live connection aren't cut off.
+    pub timeout_secs: u32,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy_url: None,
+            use_system_proxy: true,
+            extra_root_cert: None,
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+        }
+    }
+}
+
+// Serialized to the frontend as `{ "code": "invalid_proxy_url", "detail": ... }`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "code", content = "detail", rename_all = "snake_case")]
+pub enum NetworkConfigError {
+    InvalidProxyUrl(String),
+    /// The proxy credentials couldn't be read from the secret store (e.g. it is locked).
+    ProxyCredentials(String),
+    CertUnreadable(String),
+    CertInvalid(String),
+    InvalidTimeout,
+    Io(String),
+}
+
+impl std::fmt::Display for NetworkConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidProxyUrl(e) => write!(f, "invalid proxy url: {e}"),
+            Self::ProxyCredentials(e) => write!(f, "couldn't read the proxy credentials: {e}"),
+            Self::CertUnreadable(e) => write!(f, "couldn't read the root certificate: {e}"),
+            Self::CertInvalid(e) => write!(f, "invalid root certificate: {e}"),
+            Self::InvalidTimeout => write!(f, "timeout_secs must be 1-{MAX_TIMEOUT_SECS}"),
+            Self::Io(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<String> for NetworkConfigError {
+    fn from(e: String) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<NetworkConfigError> for WebUpdateCommandError {
+    fn from(e: NetworkConfigError) -> Self {
+        let code = match e {
+            NetworkConfigError::Io(_) => WebUpdateErrorCode::Io,
+            _ => WebUpdateErrorCode::InvalidNetworkConfig,
+        };
+        let err = WebUpdateCommandError::new(code, &e);
+        // `details.reason` carries this module's own code, e.g. `cert_unreadable`.
+        match serde_json::to_value(&e).ok().and_then(|v| v.get("code").cloned()) {
+            Some(reason) => err.with_details(serde_json::json!({ "reason": reason })),
+            None => err,
+        }
+    }
+}
+
+fn parse_proxy_url(raw: &str) -> Result<reqwest::Url, NetworkConfigError> {
+    let url = reqwest::Url::parse(raw.trim()).map_err(|e| NetworkConfigError::InvalidProxyUrl(e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(NetworkConfigError::InvalidProxyUrl(format!("unsupported scheme {:?}", url.scheme())));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(NetworkConfigError::InvalidProxyUrl("missing host".into()));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(NetworkConfigError::InvalidProxyUrl("put proxy credentials in the keyring, not the url".into()));
+    }
+    Ok(url)
+}
+
+fn load_certs(path: &Path) -> Result<Vec<reqwest::Certificate>, NetworkConfigError> {
+    let pem = std::fs::read(path).map_err(|e| NetworkConfigError::CertUnreadable(format!("{}: {e}", path.display())))?;
+    let certs = reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| NetworkConfigError::CertInvalid(e.to_string()))?;
+    if certs.is_empty() {
+        return Err(NetworkConfigError::CertInvalid(format!("no PEM certificates in {}", path.display())));
+    }
+    Ok(certs)
+}
+
+/// `(username, password)` for the proxy, if a username has been stored.
+fn proxy_credentials(secrets: &Secrets) -> Result<Option<(String, String)>, NetworkConfigError> {
+    let get = |key| secrets.get(PROXY_SECRET_NAMESPACE, key).map_err(NetworkConfigError::ProxyCredentials);
+    match get(PROXY_USERNAME_KEY)? {
+        Some(user) if !user.is_empty() => Ok(Some((user, get(PROXY_PASSWORD_KEY)?.unwrap_or_default()))),
+        _ => Ok(None),
+    }
+}
+
+pub fn build_client(config: &NetworkConfig, credentials: Option<(String, String)>) -> Result<reqwest::Client, NetworkConfigError> {
+    if config.timeout_secs == 0 || config.timeout_secs > MAX_TIMEOUT_SECS {
+        return Err(NetworkConfigError::InvalidTimeout);
+    }
+    let timeout = Duration::from_secs(u64::from(config.timeout_secs));
+    let mut builder = reqwest::Client::builder().connect_timeout(timeout).read_timeout(timeout);
+    if let Some(raw) = config.proxy_url.as_deref().filter(|u| !u.trim().is_empty()) {
+        let mut proxy = reqwest::Proxy::all(parse_proxy_url(raw)?).map_err(|e| NetworkConfigError::InvalidProxyUrl(e.to_string()))?;
+        if let Some((user, pass)) = &credentials {
+            proxy = proxy.basic_auth(user, pass);
+        }
+        builder = builder.proxy(proxy);
+    } else if !config.use_system_proxy {
+        builder = builder.no_proxy();
+    }
+    if let Some(path) = &config.extra_root_cert {
+        for cert in load_certs(path)? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    builder.build().map_err(|e| match config.extra_root_cert {
+        // With a proxy and timeouts already checked, the certificate is what's left to reject.
+        Some(_) => NetworkConfigError::CertInvalid(e.to_string()),
+        None => NetworkConfigError::Io(e.to_string()),
+    })
+}
+
+fn load_config_in(cache: &Path) -> NetworkConfig {
+    std::fs::read(cache.join(CONFIG_FILE))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+fn save_config_in(cache: &Path, config: &NetworkConfig) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(cache.join(CONFIG_FILE), json).map_err(|e| e.to_string())
+}
+
+/// The persisted config and a client built from it. A config that no longer
+/// works (certificate deleted, keyring locked) is reported and replaced by
+/// the defaults for this session, so updates still work where they can.
+pub fn load_persisted(app: &tauri::AppHandle, secrets: &Secrets) -> (NetworkConfig, reqwest::Client) {
+    let config = web_update::cache_root(app).map(|c| load_config_in(&c)).unwrap_or_default();
+    let credentials = match config.proxy_url.as_ref().map(|_| proxy_credentials(secrets)).transpose() {
+        Ok(c) => c.flatten(),
+        Err(e) => {
+            tracing::warn!("web update: {e}; connecting to the proxy without credentials");
+            None
+        }
+    };
+    match build_client(&config, credentials) {
+        Ok(client) => (config, client),
+        Err(e) => {
+            tracing::warn!("web update network config ignored: {e}");
+            let client = build_client(&NetworkConfig::default(), None).unwrap_or_default();
+            (config, client)
+        }
+    }
+}
+
+pub fn get_config(state: &WebUpdateState) -> NetworkConfig {
+    state.network.lock().map(|g| g.clone()).unwrap_or_default()
+}
+
+/// Validates `config` by building a client from it before anything is saved,
+/// so a bad proxy or certificate is reported now rather than at the next check.
+/// Also re-reads the proxy credentials; call it again after changing them.
+pub fn set_config(state: &WebUpdateState, app: &tauri::AppHandle, secrets: &Secrets, config: NetworkConfig) -> Result<(), NetworkConfigError> {
+    let credentials = if config.proxy_url.is_some() { proxy_credentials(secrets)? } else { None };
+    let client = build_client(&config, credentials)?;
+    save_config_in(&web_update::cache_root(app)?, &config)?;
+    if let Ok(mut g) = state.client.lock() {
+        *g = client;
+    }
+    if let Ok(mut g) = state.network.lock() {
+        *g = config;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ROOT_PEM: &str = "-----BEGIN CERTIFICATE-----
+MIIBjzCCATWgAwIBAgIUMG1GLkaxF1H+yiEEvo2hrRfFekgwCgYIKoZIzj0EAwIw
+HDEaMBgGA1UEAwwRdm94ZWxsZS10ZXN0LXJvb3QwIBcNMjYxMDE2MDA1ODA5WhgP
+MjEyNjA5MjIwMDU4MDlaMBwxGjAYBgNVBAMMEXZveGVsbGUtdGVzdC1yb290MFkw
+EwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEd74wuysFskvkHbC5oXYmyST796GTYzSM
+pejAp6eM9iTc0jjsUODaLL0aZaFG3S2XhZfLzjcN1zKZAvq1K7X2bqNTMFEwHQYD
+VR0OBBYEFJRgEw6LOLlz5C/mZYjcHczPXTQ4MB8GA1UdIwQYMBaAFJRgEw6LOLlz
+5C/mZYjcHczPXTQ4MA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIh
+AN4Jx8YswxdK0PWyKEI1H/3TeNqTz58jOmSpFB6kfuvFAiBFsH1taV7rx0CnC71d
+QwSY1+RIrbAHg0GI005luu0V0A==
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn proxy_urls_are_checked_up_front() {
+        assert!(parse_proxy_url("http://proxy.corp:3128").is_ok());
+        assert!(parse_proxy_url(" https://proxy.corp ").is_ok());
+        for bad in ["proxy.corp:3128", "not a url", "ftp://proxy.corp", "http://user:pw@proxy.corp:3128"] {
+            assert!(matches!(parse_proxy_url(bad), Err(NetworkConfigError::InvalidProxyUrl(_))), "{bad}");
+        }
+        let config = NetworkConfig { proxy_url: Some("socks9://x".into()), ..NetworkConfig::default() };
+        assert!(matches!(build_client(&config, None), Err(NetworkConfigError::InvalidProxyUrl(_))));
+    }
+
+    #[test]
+    fn root_certificates_must_be_readable_pem() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = NetworkConfig { extra_root_cert: Some(dir.path().join("nope.pem")), ..NetworkConfig::default() };
+        assert!(matches!(build_client(&missing, None), Err(NetworkConfigError::CertUnreadable(_))));
+
+        let junk = dir.path().join("junk.pem");
+        std::fs::write(&junk, "not a certificate").unwrap();
+        let config = NetworkConfig { extra_root_cert: Some(junk), ..NetworkConfig::default() };
+        assert!(matches!(build_client(&config, None), Err(NetworkConfigError::CertInvalid(_))));
+
+        let good = dir.path().join("root.pem");
+        std::fs::write(&good, TEST_ROOT_PEM).unwrap();
+        let config = NetworkConfig {
+            proxy_url: Some("http://proxy.corp:3128".into()),
+            extra_root_cert: Some(good),
+            ..NetworkConfig::default()
+        };
+        build_client(&config, Some(("alice".into(), "hunter2".into()))).unwrap();
+    }
+
+    #[test]
+    fn timeout_is_bounded() {
+        for timeout_secs in [0, MAX_TIMEOUT_SECS + 1] {
+            let config = NetworkConfig { timeout_secs, ..NetworkConfig::default() };
+            assert_eq!(build_client(&config, None).unwrap_err(), NetworkConfigError::InvalidTimeout);
+        }
+        build_client(&NetworkConfig { use_system_proxy: false, ..NetworkConfig::default() }, None).unwrap();
+    }
+
+    #[test]
+    fn config_round_trips_and_defaults_when_missing() {
+        let cache = tempfile::tempdir().unwrap();
+        assert_eq!(load_config_in(cache.path()), NetworkConfig::default());
+
+        let config = NetworkConfig {
+            proxy_url: Some("http://proxy.corp:3128".into()),
+            use_system_proxy: false,
+            extra_root_cert: Some(PathBuf::from("/etc/corp/root.pem")),
+            timeout_secs: 90,
+        };
+        save_config_in(cache.path(), &config).unwrap();
+        assert_eq!(load_config_in(cache.path()), config);
+    }
+
+    #[test]
+    fn errors_serialize_with_a_code() {
+        assert_eq!(
+            serde_json::to_value(NetworkConfigError::InvalidProxyUrl("missing host".into())).unwrap(),
+            serde_json::json!({ "code": "invalid_proxy_url", "detail": "missing host" })
+        );
+        assert_eq!(serde_json::to_value(NetworkConfigError::InvalidTimeout).unwrap(), serde_json::json!({ "code": "invalid_timeout" }));
+    }
+
+    #[test]
+    fn command_errors_keep_the_reason() {
+        let config = NetworkConfig { proxy_url: Some("ftp://proxy.corp".into()), ..NetworkConfig::default() };
+        let e = WebUpdateCommandError::from(build_client(&config, None).unwrap_err());
+        assert_eq!(e.code, WebUpdateErrorCode::InvalidNetworkConfig);
+        assert_eq!(e.message, "invalid proxy url: unsupported scheme \"ftp\"");
+        assert_eq!(e.details, Some(serde_json::json!({ "reason": "invalid_proxy_url" })));
+        assert!(!e.retryable);
+    }
+}
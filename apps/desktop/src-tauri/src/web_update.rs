@@ -1,27 +1,346 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::io::{Cursor, Read};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use tauri::{Emitter, Manager};
 use zip::read::ZipFile;
 
 pub const EVENT_WEB_UPDATE_READY: &str = "voxelle:web-update-ready";
+pub const EVENT_WEB_UPDATE_MANDATORY_APPLIED: &str = "voxelle:web-update-mandatory-applied";
+pub const EVENT_WEB_UPDATE_BLOCKED: &str = "voxelle:web-update-blocked";
+pub const EVENT_WEB_BUNDLE_REPAIRED: &str = "voxelle:web-bundle-repaired";
+pub const EVENT_WEB_UPDATE_PROGRESS: &str = "voxelle:web-update-progress";
+pub const EVENT_STORAGE_MOVE_PROGRESS: &str = "voxelle:storage-move-progress";
+pub const DEFAULT_KEEP_BUNDLES: usize = 3;
+pub const DEFAULT_MAX_DOWNLOAD_MB: u32 = 100;
+pub const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+// Free space kept in reserve beyond a bundle's declared size when installing it.
+const INSTALL_SPACE_SLACK: u64 = 16 * 1024 * 1024;
+/// Release notes, inline in the manifest or behind `notes_url`, are refused above this.
+pub const MAX_NOTES_BYTES: usize = 32 * 1024;
+// Waits between attempts double from this, up to RETRY_MAX_DELAY.
+const RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+const RETRY_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(8);
+// Manifests and notes are a few KB. The client's read timeout restarts with
+// every byte, so a server trickling a response needs a cap on the whole request.
+const SMALL_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+// A check is one manifest per feed, retries included. Background checks give up
+// sooner so they don't hold the operation lock while someone waits on it.
+pub const CHECK_DEADLINE: std::time::Duration = std::time::Duration::from_secs(120);
+pub const BACKGROUND_CHECK_DEADLINE: std::time::Duration = std::time::Duration::from_secs(45);
+pub const BUNDLE_FILES_NAME: &str = ".voxelle-files.json";
+// OS droppings that may appear in a bundle dir without failing verification.
+const BUNDLE_FILES_ALLOWLIST: [&str; 4] = [BUNDLE_FILES_NAME, ".DS_Store", "Thumbs.db", "desktop.ini"];
+const PREVIOUS_VERSION_FILE: &str = "web_previous_version.txt";
+const ACTIVE_VERSION_FILE: &str = "web_active_version.txt";
+// Channel each bundle was downloaded from; empty for the embedded bundle.
+const ACTIVE_CHANNEL_FILE: &str = "web_active_channel.txt";
+// An update downloaded in `on_next_launch` mode, activated by `apply_pending_activation`.
+const PENDING_ACTIVATION_FILE: &str = "web_pending_activation.json";
+// The activation, reset or storage move in progress, if any; see `ActivationJournal`.
+const ACTIVATION_JOURNAL_FILE: &str = "web_activation_journal.json";
+// Time the frontend gets to react to EVENT_WEB_UPDATE_READY before an
+// `immediate` update reloads it.
+const RELOAD_GRACE: std::time::Duration = std::time::Duration::from_millis(1500);
+const PREVIOUS_CHANNEL_FILE: &str = "web_previous_channel.txt";
+const KEEP_BUNDLES_FILE: &str = "web_keep_bundles.txt";
+// The webview's origin includes the port, and its storage is keyed by origin,
+// so the server goes back to the same port on every launch when it can.
+const SERVER_PORT_FILE: &str = "web_server_port.txt";
+// Bundle directories that could not be removed (e.g. files still open on Windows).
+const PENDING_DELETE_FILE: &str = "web_pending_delete.txt";
+// Last manifest fetched, with the validators needed to revalidate it.
+const MANIFEST_CACHE_FILE: &str = "web_manifest_cache.json";
+const LAST_CHECK_FILE: &str = "web_last_check.json";
+const FEED_FILE: &str = "web_feed_url.txt";
+// Checks, downloads, activations, rollbacks and repairs, one JSON line each.
+const HISTORY_FILE: &str = "update_history.jsonl";
+// Roughly 250 entries; together with the rotated file that keeps the last ~500.
+const HISTORY_MAX_BYTES: u64 = 32 * 1024;
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+// The zip each kept bundle was installed from, as the base for delta updates.
+const SOURCE_ZIPS_DIR: &str = "bundle_zips";
+// Progress events are emitted at most once per this many bytes.
+const PROGRESS_STEP: u64 = 256 * 1024;
 pub const DEFAULT_FEED: &str = "gh:x3haloed/voxelle";
+// In the app data dir, since it says where everything else goes. Holds the
+// folder the cache lives under; absent means the OS cache dir.
+const STORAGE_ROOT_FILE: &str = "storage_root.txt";
+// The cache is kept in a folder of this name under the storage root, so moving
+// it never mixes our files with whatever else is there.
+const CACHE_DIR_NAME: &str = "voxelle";
 
 // Update feed manifest (JSON), fetched from `feed_url`:
 // {
 //   "v": 1,
 //   "version": "0.1.1",
 //   "zip_url": "https://example.com/voxelle-web-0.1.1.zip",
-//   "sha256": "<hex-lowercase-sha256-of-zip>"
+//   "sha256": "<hex-lowercase-sha256-of-zip>",
+//   "files": [{ "path": "index.html", "sha256": "<hex>", "size": 1234 }]  // optional
 // }
+//
+// When `files` is present, the extracted bundle must match it exactly (apart from
+// `BUNDLE_FILES_ALLOWLIST`) before it is activated. The list is kept in the bundle
+// as `BUNDLE_FILES_NAME` so the active bundle can be re-verified later.
 
 #[derive(Clone)]
 pub struct WebBundleServer {
     port: u16,
-    root: Arc<Mutex<PathBuf>>,
-    _thread: Arc<std::thread::JoinHandle<()>>,
+    // Per-launch secret required on every request, so other local processes
+    // and web pages can't read the bundle or load the UI origin.
+    token: Arc<str>,
+    // Read-locked by each request until its file is open, so once `set_root`
+    // returns nothing can still be resolving a path in the old bundle and the
+    // old bundle can be deleted. Open files stay readable after that.
+    root: Arc<RwLock<PathBuf>>,
+    etags: Arc<EtagCache>,
+    // Follows the root: each bundle brings its own `csp.json`.
+    security: Arc<RwLock<SecurityHeaders>>,
+    config: Arc<RwLock<RuntimeConfig>>,
+    thread: Arc<ServerThread>,
+}
+
+// Answered by the server itself and never looked up in the bundle, so a bundle
+// can't shadow it. Nothing else under the prefix falls back to index.html.
+const RUNTIME_PATH_PREFIX: &str = "/__voxelle/";
+const RUNTIME_CONFIG_PATH: &str = "/__voxelle/config.json";
+
+/// What the web bundle learns about its host from `GET /__voxelle/config.json`.
+/// Kept current by whoever changes what it describes; nothing is recomputed
+/// per request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RuntimeConfig {
+    /// Always true: only the desktop shell serves this. In a plain browser the request fails.
+    pub desktop: bool,
+    pub app_version: String,
+    pub port: u16,
+    pub active_version: String,
+    pub active_channel: Option<UpdateChannel>,
+    /// Where this machine reaches the embedded signal relay while it runs.
+    pub signal_relay_url: Option<String>,
+    pub features: RuntimeFeatures,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RuntimeFeatures {
+    /// The embedded signal relay can be started with `voxelle_signal_start`.
+    pub signal_relay: bool,
+}
+
+// Stops the accept loop when the last `WebBundleServer` clone goes away, or
+// earlier through `WebBundleServer::shutdown`.
+struct ServerThread {
+    stop: Arc<std::sync::atomic::AtomicBool>,
+    handle: Mutex<Option<std::thread::JoinHandle<()>>>,
+}
+
+const SERVER_POLL: std::time::Duration = std::time::Duration::from_millis(200);
+const SERVER_SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+impl ServerThread {
+    fn stop(&self) {
+        self.stop.store(true, std::sync::atomic::Ordering::SeqCst);
+        let Some(handle) = self.handle.lock().ok().and_then(|mut g| g.take()) else {
+            return;
+        };
+        // The loop notices the flag within one poll; a request stuck writing to
+        // a slow client is left to finish on its own rather than hang exit.
+        let deadline = std::time::Instant::now() + SERVER_SHUTDOWN_TIMEOUT;
+        while !handle.is_finished() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        if handle.is_finished() {
+            let _ = handle.join();
+        } else {
+            tracing::warn!("web-bundle server did not stop within {:?}", SERVER_SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+impl Drop for ServerThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Strong content-hash ETags, recomputed when a file's mtime or size changes.
+#[derive(Default)]
+struct EtagCache(Mutex<HashMap<PathBuf, (std::time::SystemTime, u64, String)>>);
+
+impl EtagCache {
+    fn get(&self, path: &Path) -> Result<String, String> {
+        let meta = std::fs::metadata(path).map_err(|e| e.to_string())?;
+        let mtime = meta.modified().map_err(|e| e.to_string())?;
+        if let Some((t, len, etag)) = self.0.lock().ok().and_then(|g| g.get(path).cloned()) {
+            if t == mtime && len == meta.len() {
+                return Ok(etag);
+            }
+        }
+        let etag = format!("\"{}\"", &sha256_file(path)?[..32]);
+        if let Ok(mut g) = self.0.lock() {
+            g.insert(path.to_path_buf(), (mtime, meta.len(), etag.clone()));
+        }
+        Ok(etag)
+    }
+
+    fn clear(&self) {
+        if let Ok(mut g) = self.0.lock() {
+            g.clear();
+        }
+    }
+}
+
+/// Read from the root of the bundle being served. Listed directives replace the
+/// built-in default's, the rest keep it; anything invalid and the whole file is
+/// ignored in favour of the default.
+pub const CSP_FILE: &str = "csp.json";
+
+const DEFAULT_CSP: &[(&str, &[&str])] = &[
+    ("default-src", &["'self'"]),
+    ("script-src", &["'self'"]),
+    ("style-src", &["'self'"]),
+    ("img-src", &["'self'", "data:", "blob:"]),
+    // Signaling relays and update feeds live anywhere; peers and the local
+    // relay are on loopback.
+    ("connect-src", &["'self'", "http://127.0.0.1:*", "http://localhost:*", "https:", "ws:", "wss:"]),
+    ("frame-src", &["'self'"]),
+    ("object-src", &["'none'"]),
+    ("base-uri", &["'self'"]),
+    ("form-action", &["'self'"]),
+    ("frame-ancestors", &["'none'"]),
+];
+
+// Anything else in a `csp.json` is more likely a typo than a policy.
+const CSP_DIRECTIVES: &[&str] = &[
+    "default-src",
+    "script-src",
+    "script-src-elem",
+    "script-src-attr",
+    "style-src",
+    "style-src-elem",
+    "style-src-attr",
+    "img-src",
+    "font-src",
+    "connect-src",
+    "media-src",
+    "object-src",
+    "frame-src",
+    "child-src",
+    "worker-src",
+    "manifest-src",
+    "base-uri",
+    "form-action",
+    "frame-ancestors",
+];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CspFile {
+    #[serde(default)]
+    directives: std::collections::BTreeMap<String, Vec<String>>,
+    /// Sends COOP/COEP so the page is cross-origin isolated and gets `SharedArrayBuffer`.
+    #[serde(default)]
+    cross_origin_isolated: bool,
+    /// `false` sends the policy report-only. Honoured in debug builds only.
+    #[serde(default = "default_true")]
+    enforce: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Headers every response from the bundle server carries.
+#[derive(Debug, Clone, PartialEq)]
+struct SecurityHeaders {
+    csp: Vec<(String, Vec<String>)>,
+    report_only: bool,
+    cross_origin_isolated: bool,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self {
+            csp: DEFAULT_CSP
+                .iter()
+                .map(|(name, sources)| (name.to_string(), sources.iter().map(|s| s.to_string()).collect()))
+                .collect(),
+            report_only: false,
+            cross_origin_isolated: false,
+        }
+    }
+}
+
+impl SecurityHeaders {
+    fn load(root: &Path, allow_report_only: bool) -> Self {
+        let bytes = match std::fs::read(root.join(CSP_FILE)) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                tracing::warn!("can't read {CSP_FILE} in {}, using the default policy: {e}", root.display());
+                return Self::default();
+            }
+        };
+        Self::parse(&bytes, allow_report_only).unwrap_or_else(|e| {
+            tracing::warn!("ignoring {CSP_FILE} in {}: {e}", root.display());
+            Self::default()
+        })
+    }
+
+    fn parse(bytes: &[u8], allow_report_only: bool) -> Result<Self, String> {
+        let file: CspFile = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+        let mut headers = Self::default();
+        for (name, sources) in file.directives {
+            if !CSP_DIRECTIVES.contains(&name.as_str()) {
+                return Err(format!("unknown directive {name:?}"));
+            }
+            if sources.is_empty() {
+                return Err(format!("{name} lists no sources; use 'none' to allow nothing"));
+            }
+            // Each source ends up in a header value; keep them from starting another directive.
+            if let Some(bad) = sources
+                .iter()
+                .find(|s| s.is_empty() || !s.bytes().all(|b| b.is_ascii_graphic() && b != b';' && b != b','))
+            {
+                return Err(format!("{name} has an invalid source {bad:?}"));
+            }
+            match headers.csp.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, existing)) => *existing = sources,
+                None => headers.csp.push((name, sources)),
+            }
+        }
+        if !file.enforce && !allow_report_only {
+            tracing::warn!("{CSP_FILE} asks not to enforce the policy; only debug builds allow that");
+        }
+        headers.report_only = !file.enforce && allow_report_only;
+        headers.cross_origin_isolated = file.cross_origin_isolated;
+        Ok(headers)
+    }
+
+    fn policy(&self) -> String {
+        let directives: Vec<String> = self.csp.iter().map(|(name, sources)| format!("{name} {}", sources.join(" "))).collect();
+        directives.join("; ")
+    }
+
+    fn to_headers(&self) -> Result<Vec<tiny_http::Header>, String> {
+        let csp_name = if self.report_only {
+            "Content-Security-Policy-Report-Only"
+        } else {
+            "Content-Security-Policy"
+        };
+        let mut headers = vec![
+            header(csp_name, &self.policy())?,
+            header("X-Content-Type-Options", "nosniff")?,
+            header("Referrer-Policy", "no-referrer")?,
+        ];
+        if self.cross_origin_isolated {
+            headers.push(header("Cross-Origin-Opener-Policy", "same-origin")?);
+            headers.push(header("Cross-Origin-Embedder-Policy", "require-corp")?);
+        }
+        Ok(headers)
+    }
 }
 
 impl WebBundleServer {
@@ -29,32 +348,90 @@ impl WebBundleServer {
         self.port
     }
 
+    /// URL the webview is navigated to. Requests under it carry the token in
+    /// the path and get it back as a cookie for absolute asset URLs.
+    pub fn base_url(&self) -> String {
+        format!("http://127.0.0.1:{}{}/", self.port, token_prefix(&self.token))
+    }
+
+    /// Stops the server and waits (briefly) for its thread, releasing the port.
+    /// Other clones of this handle stop serving too.
+    pub fn shutdown(self) {
+        self.thread.stop();
+    }
+
     pub fn set_root(&self, p: PathBuf) {
-        if let Ok(mut g) = self.root.lock() {
-            *g = p;
-        }
+        let security = SecurityHeaders::load(&p, cfg!(debug_assertions));
+        let mut root = self.root.write().unwrap_or_else(|e| e.into_inner());
+        // Swapped under the root lock, so no response pairs one bundle's files
+        // with another's policy.
+        *self.security.write().unwrap_or_else(|e| e.into_inner()) = security;
+        *root = p;
+        // Don't let ETags computed for the old bundle answer requests for the new one.
+        self.etags.clear();
     }
 
-    pub fn start(root_dir: PathBuf) -> Result<Self, String> {
-        let root = Arc::new(Mutex::new(root_dir));
-        let server = tiny_http::Server::http("127.0.0.1:0").map_err(|e| e.to_string())?;
+    /// Changes what `/__voxelle/config.json` answers with from the next request on.
+    pub fn update_config(&self, f: impl FnOnce(&mut RuntimeConfig)) {
+        f(&mut self.config.write().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    /// Binds `preferred_port` if it is free, and an ephemeral port otherwise.
+    pub fn start(root_dir: PathBuf, preferred_port: Option<u16>) -> Result<Self, String> {
+        let security = Arc::new(RwLock::new(SecurityHeaders::load(&root_dir, cfg!(debug_assertions))));
+        let root = Arc::new(RwLock::new(root_dir));
+        let preferred = preferred_port
+            .filter(|p| *p != 0)
+            .and_then(|p| match tiny_http::Server::http(("127.0.0.1", p)) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    tracing::warn!("web-bundle server can't reuse port {p} ({e}); picking a new one");
+                    None
+                }
+            });
+        let server = match preferred {
+            Some(server) => server,
+            None => tiny_http::Server::http("127.0.0.1:0").map_err(|e| e.to_string())?,
+        };
         let port = server
             .server_addr()
             .to_ip()
             .ok_or_else(|| "unsupported server addr".to_string())?
             .port();
 
+        let mut secret = [0u8; 32];
+        getrandom::getrandom(&mut secret).map_err(|e| e.to_string())?;
+        let token: Arc<str> = hex::encode(secret).into();
+
         let root2 = root.clone();
-        let t = std::thread::spawn(move || loop {
-            let Some(req) = server.recv_timeout(std::time::Duration::from_millis(200)).ok().flatten() else {
-                continue;
-            };
-            if let Err(e) = handle_req(req, &root2) {
-                eprintln!("web-bundle server error: {e}");
+        let etags = Arc::new(EtagCache::default());
+        let etags2 = etags.clone();
+        let config = Arc::new(RwLock::new(RuntimeConfig {
+            desktop: true,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            port,
+            features: RuntimeFeatures { signal_relay: true },
+            ..Default::default()
+        }));
+        let config2 = config.clone();
+        let security2 = security.clone();
+        let token2 = token.clone();
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let stop2 = stop.clone();
+        // `server` moves into the thread, so the socket closes when the loop ends.
+        let t = std::thread::spawn(move || {
+            while !stop2.load(std::sync::atomic::Ordering::SeqCst) {
+                let Some(req) = server.recv_timeout(SERVER_POLL).ok().flatten() else {
+                    continue;
+                };
+                if let Err(e) = handle_req(req, &root2, &etags2, &security2, &config2, &token2) {
+                    tracing::error!("web-bundle server error: {e}");
+                }
             }
         });
 
-        Ok(Self { port, root, _thread: Arc::new(t) })
+        let thread = Arc::new(ServerThread { stop, handle: Mutex::new(Some(t)) });
+        Ok(Self { port, token, root, etags, security, config, thread })
     }
 }
 
@@ -68,412 +445,5756 @@ fn content_type_for_path(path: &Path) -> &'static str {
         "png" => "image/png",
         "ico" => "image/x-icon",
         "txt" => "text/plain; charset=utf-8",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
         _ => "application/octet-stream",
     }
 }
 
-fn handle_req(req: tiny_http::Request, root: &Arc<Mutex<PathBuf>>) -> Result<(), String> {
+// Vite names build output `<name>-<hash>.<ext>`; those files never change
+// content under the same name, so they can be cached forever.
+fn is_hashed_asset(path: &Path) -> bool {
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return false;
+    };
+    let Some((_, hash)) = stem.rsplit_once(['-', '.']) else {
+        return false;
+    };
+    (8..=64).contains(&hash.len())
+        && hash.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+        && hash.bytes().any(|b| b.is_ascii_digit())
+}
+
+fn cache_control_for(path: &Path) -> &'static str {
+    if path.extension().is_some_and(|e| e == "html") || !is_hashed_asset(path) {
+        "no-cache"
+    } else {
+        "public, max-age=31536000, immutable"
+    }
+}
+
+// `If-None-Match` uses the weak comparison, so `W/` prefixes are ignored.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.strip_prefix("W/").unwrap_or(t) == etag)
+}
+
+// Content codings the web build may emit as siblings (`app.js.br`), most preferred first.
+const PRECOMPRESSED: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+fn accepts_encoding(accept_encoding: &str, coding: &str) -> bool {
+    let mut wildcard = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        let q = parts
+            .find_map(|p| p.trim().strip_prefix("q="))
+            .and_then(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if name.eq_ignore_ascii_case(coding) {
+            return q > 0.0;
+        }
+        if name == "*" {
+            wildcard = Some(q > 0.0);
+        }
+    }
+    wildcard.unwrap_or(false)
+}
+
+// Picks a precompressed sibling of `file` the client accepts. Nothing is ever
+// compressed on the fly; without a sibling the plain file is served.
+fn precompressed_variant(file: &Path, accept_encoding: &str) -> Option<(&'static str, PathBuf)> {
+    let name = file.file_name()?.to_str()?;
+    PRECOMPRESSED.iter().find_map(|(coding, ext)| {
+        let sibling = file.with_file_name(format!("{name}.{ext}"));
+        (accepts_encoding(accept_encoding, coding) && sibling.is_file()).then_some((*coding, sibling))
+    })
+}
+
+const TOKEN_COOKIE: &str = "voxelle_token";
+
+fn token_prefix(token: &str) -> String {
+    format!("/t/{token}")
+}
+
+fn cookie_tokens(cookie_header: &str) -> impl Iterator<Item = &str> {
+    cookie_header
+        .split(';')
+        .filter_map(|c| c.trim().split_once('='))
+        .filter(|(name, _)| *name == TOKEN_COOKIE)
+        .map(|(_, value)| value.trim())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Authorized {
+    /// The token was in the path; serve the rest and set the cookie.
+    ViaPath(String),
+    ViaCookie(String),
+}
+
+// Every request needs the token, either as a `/t/<token>` path prefix or as the
+// cookie handed out for such a request. The cookie is `SameSite=Strict`, so other
+// sites can't ride on it by framing or fetching from the loopback origin.
+fn authorize(url_path: &str, cookie_header: Option<&str>, token: &str) -> Option<Authorized> {
+    if let Some(rest) = url_path.strip_prefix("/t/") {
+        let (given, rest) = rest.split_once('/').unwrap_or((rest, ""));
+        return voxelle_protocol::ct_eq_str(given, token).then(|| Authorized::ViaPath(format!("/{rest}")));
+    }
+    cookie_header
+        .into_iter()
+        .flat_map(cookie_tokens)
+        .any(|c| voxelle_protocol::ct_eq_str(c, token))
+        .then(|| Authorized::ViaCookie(url_path.to_string()))
+}
+
+fn header(name: &str, value: &str) -> Result<tiny_http::Header, String> {
+    tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).map_err(|_| "bad header".to_string())
+}
+
+fn handle_req(
+    req: tiny_http::Request,
+    root: &RwLock<PathBuf>,
+    etags: &EtagCache,
+    security: &RwLock<SecurityHeaders>,
+    config: &RwLock<RuntimeConfig>,
+    token: &str,
+) -> Result<(), String> {
+    // Every response carries these, errors included.
+    let security = security.read().unwrap_or_else(|e| e.into_inner()).to_headers()?;
     let url = req.url().split('?').next().unwrap_or("/");
-    let mut path = url.to_string();
+    let cookies = req.headers().iter().find(|h| h.field.equiv("Cookie")).map(|h| h.value.as_str());
+    let (mut path, set_cookie) = match authorize(url, cookies, token) {
+        Some(Authorized::ViaPath(p)) => (p, true),
+        Some(Authorized::ViaCookie(p)) => (p, false),
+        None => {
+            // The URL may carry a wrong token; keep it out of the log.
+            tracing::debug!("refused a bundle request without the token");
+            let mut resp = tiny_http::Response::from_string("forbidden").with_status_code(403);
+            for h in security {
+                resp.add_header(h);
+            }
+            req.respond(resp).map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+    };
+    // Path only: the token prefix is already stripped.
+    tracing::debug!(%path, "bundle request");
+    if path.starts_with(RUNTIME_PATH_PREFIX) {
+        return respond_runtime(req, &path, config, security, set_cookie.then_some(token));
+    }
     if path.is_empty() || path == "/" {
         path = "/index.html".into();
     }
 
     // Normalize and prevent traversal.
     let path = path.trim_start_matches('/');
-    let root_dir = root.lock().map_err(|_| "root lock poisoned")?.clone();
+    let root_dir = root.read().unwrap_or_else(|e| e.into_inner());
     let candidate = root_dir.join(path);
     let candidate = match candidate.canonicalize() {
         Ok(p) => p,
         Err(_) => root_dir.join("index.html"),
     };
-    if !candidate.starts_with(&root_dir) {
-        let resp = tiny_http::Response::from_string("bad path").with_status_code(400);
+    if !candidate.starts_with(&*root_dir) {
+        drop(root_dir);
+        let mut resp = tiny_http::Response::from_string("bad path").with_status_code(400);
+        for h in security {
+            resp.add_header(h);
+        }
         req.respond(resp).map_err(|e| e.to_string())?;
         return Ok(());
     }
 
-    let (data, ctype) = if candidate.is_file() {
-        (std::fs::read(&candidate).map_err(|e| e.to_string())?, content_type_for_path(&candidate))
-    } else {
-        let index = root_dir.join("index.html");
-        (std::fs::read(&index).map_err(|e| e.to_string())?, content_type_for_path(&index))
-    };
+    let file = if candidate.is_file() { candidate } else { root_dir.join("index.html") };
+    // Ranges are always served from the plain file; media, the usual reason for
+    // ranges, is already compressed and has no siblings.
+    let has_range = req.headers().iter().any(|h| h.field.equiv("Range"));
+    let encoded = req
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Accept-Encoding"))
+        .filter(|_| !has_range)
+        .and_then(|h| precompressed_variant(&file, h.value.as_str()));
+    let body_path = encoded.as_ref().map_or(&file, |(_, p)| p);
+    // ETags hash the bytes actually sent, so each encoding gets its own.
+    let etag = etags.get(body_path)?;
+    let mut cache_headers = vec![
+        header("ETag", &etag)?,
+        header("Cache-Control", cache_control_for(&file))?,
+        header("Vary", "Accept-Encoding")?,
+    ];
+    cache_headers.extend(security.iter().cloned());
+    if set_cookie {
+        cache_headers.push(header("Set-Cookie", &token_cookie(token))?);
+    }
 
-    let resp = tiny_http::Response::from_data(data).with_header(
-        tiny_http::Header::from_bytes(&b"Content-Type"[..], ctype.as_bytes()).map_err(|_| "bad header".to_string())?,
-    );
-    req.respond(resp).map_err(|e| e.to_string())?;
+    let not_modified = req
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("If-None-Match"))
+        .is_some_and(|h| etag_matches(h.value.as_str(), &etag));
+    if not_modified {
+        drop(root_dir);
+        let mut resp = tiny_http::Response::empty(304);
+        for h in cache_headers {
+            resp.add_header(h);
+        }
+        req.respond(resp).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let mut f = std::fs::File::open(body_path).map_err(|e| e.to_string())?;
+    drop(root_dir);
+    let len = f.metadata().map_err(|e| e.to_string())?.len();
+    let mut headers = cache_headers;
+    headers.push(header("Content-Type", content_type_for_path(&file))?);
+    headers.push(header("Accept-Ranges", "bytes")?);
+    if let Some((coding, _)) = &encoded {
+        headers.push(header("Content-Encoding", coding)?);
+    }
+
+    let range = req
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Range"))
+        .map_or(ByteRange::Full, |h| parse_range(h.value.as_str(), len));
+    match range {
+        ByteRange::Full => {
+            let resp = tiny_http::Response::new(tiny_http::StatusCode(200), headers, f, Some(len as usize), None);
+            req.respond(resp).map_err(|e| e.to_string())?;
+        }
+        ByteRange::Partial { start, end } => {
+            headers.push(header("Content-Range", &format!("bytes {start}-{end}/{len}"))?);
+            let n = end - start + 1;
+            f.seek(std::io::SeekFrom::Start(start)).map_err(|e| e.to_string())?;
+            let resp = tiny_http::Response::new(tiny_http::StatusCode(206), headers, f.take(n), Some(n as usize), None);
+            req.respond(resp).map_err(|e| e.to_string())?;
+        }
+        ByteRange::Unsatisfiable => {
+            let mut resp = tiny_http::Response::empty(416);
+            for h in security {
+                resp.add_header(h);
+            }
+            resp.add_header(header("Content-Range", &format!("bytes */{len}"))?);
+            req.respond(resp).map_err(|e| e.to_string())?;
+        }
+    }
     Ok(())
 }
 
+fn token_cookie(token: &str) -> String {
+    format!("{TOKEN_COOKIE}={token}; Path=/; HttpOnly; SameSite=Strict")
+}
+
+fn respond_runtime(
+    req: tiny_http::Request,
+    path: &str,
+    config: &RwLock<RuntimeConfig>,
+    security: Vec<tiny_http::Header>,
+    cookie_token: Option<&str>,
+) -> Result<(), String> {
+    if path != RUNTIME_CONFIG_PATH {
+        let mut resp = tiny_http::Response::from_string("not found").with_status_code(404);
+        for h in security {
+            resp.add_header(h);
+        }
+        return req.respond(resp).map_err(|e| e.to_string());
+    }
+    let body = serde_json::to_vec(&*config.read().unwrap_or_else(|e| e.into_inner())).map_err(|e| e.to_string())?;
+    let mut resp = tiny_http::Response::from_data(body)
+        .with_header(header("Content-Type", "application/json; charset=utf-8")?)
+        .with_header(header("Cache-Control", "no-store")?);
+    for h in security {
+        resp.add_header(h);
+    }
+    if let Some(token) = cookie_token {
+        resp.add_header(header("Set-Cookie", &token_cookie(token))?);
+    }
+    req.respond(resp).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    Full,
+    /// Inclusive byte offsets.
+    Partial { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+// Single byte ranges only. Multiple ranges and anything malformed get the
+// whole file, which RFC 9110 allows a server to do for any Range request.
+fn parse_range(value: &str, len: u64) -> ByteRange {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (first, last) = (first.trim(), last.trim());
+    if first.is_empty() {
+        // Suffix range: the last N bytes.
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(n) => ByteRange::Partial { start: len.saturating_sub(n), end: len - 1 },
+            Err(_) => ByteRange::Full,
+        };
+    }
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = match last {
+        "" => u64::MAX,
+        l => match l.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return ByteRange::Full,
+        },
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial { start, end: end.min(len - 1) }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+/// What happens once a downloaded update is installed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyMode {
+    /// Activate and reload the main window shortly after.
+    Immediate,
+    /// Leave the running bundle alone; the next launch starts on the update.
+    OnNextLaunch,
+    /// Activate and emit EVENT_WEB_UPDATE_READY; the frontend decides when to reload.
+    #[default]
+    Prompt,
+}
+
+impl UpdateChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "stable" => Some(Self::Stable),
+            "beta" => Some(Self::Beta),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct WebUpdateState {
     pub server: WebBundleServer,
     pub active_version: Arc<Mutex<String>>,
-    pub feed_url: Arc<Mutex<String>>,
+    /// Channel the active bundle came from; `None` for the embedded bundle.
+    pub active_channel: Arc<Mutex<Option<UpdateChannel>>>,
+    pub embedded_version: String,
+    /// The zip the embedded bundle is extracted from; empty in dev builds.
+    pub embedded_zip: &'static [u8],
+    /// Tried in order; the first that serves a valid manifest is used.
+    pub feed_urls: Arc<Mutex<Vec<String>>>,
+    pub settings: Arc<Mutex<crate::auto_update::WebUpdateSettings>>,
+    pub settings_changed: Arc<tokio::sync::Notify>,
+    pub decisions: Arc<Mutex<crate::auto_update::UpdateDecisions>>,
+    pub network: Arc<Mutex<crate::update_network::NetworkConfig>>,
+    /// Shared by every manifest fetch and download; rebuilt when the network config changes.
+    pub client: Arc<Mutex<reqwest::Client>>,
+    /// Held for the duration of a check, download, rollback, prune, reset or storage move;
+    /// a second one is refused with `WebUpdateError::Busy`.
+    pub operation: OperationLock,
+    pub last_check: Arc<Mutex<Option<LastCheck>>>,
+    pub last_activation: Arc<Mutex<Option<UpdateHistoryEntry>>>,
+    /// Set when this launch had to repair the active bundle; the repair event
+    /// fires before the frontend loads, so status carries it too.
+    pub repair: Option<BundleRepair>,
+    pub dev_override: Arc<Mutex<Option<DevOverride>>>,
 }
 
 #[derive(Serialize)]
 pub struct WebUpdateStatus {
     pub active_version: String,
+    pub active_channel: Option<UpdateChannel>,
+    pub channel: UpdateChannel,
+    /// The first of `feed_urls`, for callers that only know about one feed.
     pub feed_url: String,
+    pub feed_urls: Vec<String>,
     pub port: u16,
+    /// Unix milliseconds.
+    pub last_checked_at: Option<u64>,
+    pub last_check_result: Option<LastCheckResult>,
+    /// The feed the last check's manifest came from.
+    pub last_check_source: Option<String>,
+    pub repair: Option<BundleRepair>,
+    /// The most recent `activation` entry of the update history.
+    pub last_activation: Option<UpdateHistoryEntry>,
+    /// Checks offering this version report it as skipped.
+    pub skipped_version: Option<String>,
+    /// Unix milliseconds; until then background checks don't announce updates.
+    pub snoozed_until: Option<u64>,
+    /// When set, the UI isn't coming from `active_version`.
+    pub dev_override: Option<DevOverride>,
+    pub operation_in_progress: Option<UpdateOperation>,
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateOperation {
+    Check,
+    Download,
+    Rollback,
+    Prune,
+    Reset,
+    MoveStorage,
+}
+
+impl UpdateOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Check => "check",
+            Self::Download => "download",
+            Self::Rollback => "rollback",
+            Self::Prune => "prune",
+            Self::Reset => "reset",
+            Self::MoveStorage => "storage move",
+        }
+    }
+}
+
+/// Lets one update operation run at a time. Overlapping ones would race on the
+/// active version, the served root and the bundle directories, so a second
+/// operation fails fast instead of queueing behind the first.
+#[derive(Clone, Default)]
+pub struct OperationLock(Arc<Mutex<Option<UpdateOperation>>>);
+
+impl OperationLock {
+    pub fn try_begin(&self, op: UpdateOperation) -> Result<OperationGuard, WebUpdateError> {
+        let mut g = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(running) = *g {
+            return Err(WebUpdateError::Busy(running));
+        }
+        *g = Some(op);
+        Ok(OperationGuard(self.0.clone()))
+    }
+
+    pub fn current(&self) -> Option<UpdateOperation> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Ends the operation when dropped.
+#[must_use]
+pub struct OperationGuard(Arc<Mutex<Option<UpdateOperation>>>);
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum LastCheckResult {
+    UpToDate,
+    Available { version: String, downgrade: bool },
+    /// The offered version is the one the user skipped.
+    Skipped { version: String },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastCheck {
+    /// Unix milliseconds.
+    pub checked_at: u64,
+    pub result: LastCheckResult,
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Every feed that was tried, in order. Feeds after the one that answered are not contacted.
+    #[serde(default)]
+    pub sources: Vec<FeedAttempt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedAttempt {
+    /// As configured, e.g. `gh:owner/repo`.
+    pub feed: String,
+    /// What was actually fetched for the channel.
+    pub url: String,
+    /// `None` when this feed served the manifest.
+    pub error: Option<String>,
+}
+
+/// `web_update_set_feed` takes a single URL or an ordered list of fallbacks.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum FeedUrls {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl FeedUrls {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            FeedUrls::One(url) => vec![url],
+            FeedUrls::Many(urls) => urls,
+        }
+    }
+}
+
+/// How an offered version compares with the active one, by semver precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionRelation {
+    Newer,
+    Same,
+    Older,
+}
+
+#[derive(Clone, Serialize)]
 pub struct WebUpdateCheckResult {
+    /// Whether to offer the update. Usually means `relation` is `newer`;
+    /// after a channel switch an older version is offered too, with `downgrade`.
     pub available: bool,
+    /// The offer is the version the user skipped, so `available` is false.
+    /// Newer versions are offered as usual.
+    pub skipped: bool,
+    /// The offered version is older than the active one. This happens after
+    /// switching channels; downloading it needs `allow_downgrade`.
+    pub downgrade: bool,
+    /// The active version is below the feed's `min_version` and must not keep
+    /// running; the offered version has to be installed.
+    pub mandatory: bool,
+    pub min_version: Option<String>,
+    pub channel: UpdateChannel,
+    /// The feed that served the manifest.
+    pub source: Option<String>,
+    /// The active version `relation` compares against.
+    pub current_version: String,
+    /// `None` when no feed is set. A `same` or `older` offer can still be
+    /// installed by downloading with `force`.
+    pub relation: Option<VersionRelation>,
     pub version: Option<String>,
     pub zip_url: Option<String>,
     pub sha256: Option<String>,
+    /// Markdown release notes from the manifest.
+    pub notes: Option<String>,
+    /// Set when the notes aren't inline; fetch them with `web_update_fetch_notes`.
+    pub notes_url: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleRepairAction {
+    ReextractedEmbedded,
+    Reactivated,
+}
+
+/// What the startup integrity check did about a damaged active bundle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleRepair {
+    pub broken_version: String,
+    pub problem: String,
+    pub action: BundleRepairAction,
+    pub activated_version: String,
+}
+
+/// Where the UI is loaded from instead of the active bundle, for frontend
+/// development. Lives only in memory, so a restart always drops it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "target", rename_all = "snake_case")]
+pub enum DevOverride {
+    /// Served by the bundle server in place of the active bundle.
+    Dir(PathBuf),
+    /// Loaded in the window directly, e.g. a Vite dev server.
+    Url(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadKind {
+    Delta,
+    Full,
+}
+
+/// Emitted as EVENT_WEB_UPDATE_PROGRESS while a bundle or patch downloads. A
+/// failed delta is followed by a `full` download from zero.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DownloadProgress {
+    pub version: String,
+    pub kind: DownloadKind,
+    pub downloaded: u64,
+    /// `None` when the server doesn't say how large the body is.
+    pub total: Option<u64>,
+}
+
+/// One line of the update history, returned newest first by `web_update_history`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdateHistoryEntry {
+    /// Unix milliseconds.
+    pub at: u64,
+    #[serde(flatten)]
+    pub event: UpdateHistoryEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum UpdateHistoryEvent {
+    Check {
+        result: LastCheckResult,
+        /// The feed that served the manifest.
+        source: Option<String>,
+        latency_ms: u64,
+    },
+    /// One per attempt; a failed delta is followed by a `full` entry.
+    Download {
+        version: String,
+        kind: DownloadKind,
+        /// Received by this attempt, counting a resumed partial file.
+        bytes: u64,
+        duration_ms: u64,
+        /// `None` when the download verified.
+        error: Option<String>,
+    },
+    Activation {
+        version: String,
+        channel: Option<UpdateChannel>,
+        previous: String,
+    },
+    Rollback { from: String, to: String },
+    /// An older version installed on purpose, alongside its `activation`.
+    Downgrade { from: String, to: String },
+    /// The active version downloaded and installed again with `force`.
+    Reinstall { version: String },
+    Repair(BundleRepair),
+}
+
+#[derive(Clone, Serialize)]
+pub struct MandatoryUpdateApplied {
+    pub activated_version: String,
+    pub min_version: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MandatoryUpdateBlocked {
+    pub active_version: String,
+    pub min_version: String,
+    pub error: WebUpdateCommandError,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct WebBundleManifestV1 {
     v: u8,
     version: String,
     zip_url: String,
     sha256: String,
+    #[serde(default)]
+    files: Option<Vec<BundleFile>>,
+    /// Bundles older than this must update before they keep running.
+    #[serde(default)]
+    min_version: Option<String>,
+    /// Markdown release notes.
+    #[serde(default)]
+    notes: Option<String>,
+    /// Where to fetch the notes from when they aren't inline.
+    #[serde(default)]
+    notes_url: Option<String>,
+    /// A smaller alternative to `zip_url` for clients running `from_version`.
+    #[serde(default)]
+    delta: Option<BundleDelta>,
+}
+
+/// A patch that turns the zip of `from_version` into this version's zip, so
+/// the result is checked against the manifest's `sha256` like a full download.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct BundleDelta {
+    from_version: String,
+    patch_url: String,
+    /// Of the patch itself.
+    sha256: String,
+    algorithm: DeltaAlgorithm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum DeltaAlgorithm {
+    /// The new zip compressed by zstd with the old zip as a raw-content
+    /// dictionary (`zstd -D old.zip new.zip`).
+    ZstdDict,
+    /// A `bsdiff` crate patch from the old zip to the new one.
+    Bsdiff,
+    /// From a newer publisher; the full zip is downloaded instead.
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleFile {
+    /// Relative to the bundle root, `/`-separated.
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileCheckStatus {
+    Ok,
+    Missing,
+    SizeMismatch { expected: u64, actual: u64 },
+    HashMismatch,
+    /// On disk but not listed in the manifest.
+    Unexpected,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileCheck {
+    pub path: String,
+    #[serde(flatten)]
+    pub status: FileCheckStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BundleVerifyReport {
+    pub version: String,
+    pub ok: bool,
+    pub files: Vec<FileCheck>,
 }
 
 #[derive(Serialize)]
 pub struct WebUpdateDownloadResult {
     pub activated_version: String,
+    /// Installed but not active until the next launch (`on_next_launch` mode).
+    pub pending: bool,
 }
 
-fn cache_root(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let base = app
-        .path()
-        .app_cache_dir()
-        .map_err(|e| e.to_string())?
-        .join("voxelle");
-    std::fs::create_dir_all(&base).map_err(|e| e.to_string())?;
-    Ok(base)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingActivation {
+    version: String,
+    channel: Option<UpdateChannel>,
 }
 
-fn bundles_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let p = cache_root(app)?.join("web_bundles");
-    std::fs::create_dir_all(&p).map_err(|e| e.to_string())?;
-    Ok(p)
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct WebUpdateResetResult {
+    /// Paths relative to the app cache dir.
+    pub removed: Vec<String>,
+    pub bytes_reclaimed: u64,
+    /// Could not be removed now; bundles among them are retried on next startup.
+    pub failed: Vec<String>,
+    /// The embedded bundle, which is what's served after a reset.
+    pub active_version: String,
 }
 
-fn feed_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    Ok(cache_root(app)?.join("web_feed_url.txt"))
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct WebUpdatePruneResult {
+    pub removed: Vec<String>,
+    /// Could not be removed now; retried on next startup.
+    pub deferred: Vec<String>,
 }
 
-fn active_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    Ok(cache_root(app)?.join("web_active_version.txt"))
+#[derive(Serialize)]
+pub struct WebUpdateRollbackResult {
+    pub restored_version: String,
 }
 
-fn active_bundle_path(app: &tauri::AppHandle, version: &str) -> Result<PathBuf, String> {
-    Ok(bundles_dir(app)?.join(version))
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebUpdateError {
+    FeedNotSet,
+    /// No connection, DNS failure, connection refused or dropped.
+    Offline,
+    /// A request timed out, or the operation ran past its deadline; the phase
+    /// is known once the error has passed through `within`.
+    Timeout(Option<UpdatePhase>),
+    HttpStatus(u16),
+    ManifestInvalid(String),
+    HashMismatch,
+    TooLarge,
+    NotesTooLarge,
+    /// Not enough free space on the cache volume to extract the bundle.
+    InsufficientSpace { needed: u64, available: u64 },
+    /// Not enough free space under the new storage root for the cache.
+    InsufficientSpaceToMove { needed: u64, available: u64 },
+    /// The notes URL answered with something other than plain text or markdown.
+    NotesNotText(String),
+    ExtractFailed(String),
+    DowngradeNotConfirmed { channel: UpdateChannel, offered: String, active: String },
+    InvalidSettings(String),
+    /// Not an http(s) URL or a directory with an index.html.
+    InvalidDevOverride(String),
+    /// Release builds only load a dev override when the caller confirms it.
+    DevOverrideNotConfirmed,
+    /// Not an absolute, writable folder outside the app's install location.
+    InvalidStorageRoot(String),
+    /// Another update operation is running.
+    Busy(UpdateOperation),
+    /// The bundle URL, or where it redirected to, isn't https on a host
+    /// trusted for the feed the manifest came from.
+    UntrustedZipOrigin { zip_host: String, feed_host: String, https: bool },
+    /// Local files, and anything not classified above.
+    Io(String),
 }
 
-fn read_text_file(path: &Path) -> String {
-    std::fs::read_to_string(path).unwrap_or_default().trim().to_string()
+impl WebUpdateError {
+    /// Worth retrying: the same request may well succeed in a moment.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Offline | Self::Timeout(_) | Self::Busy(_) => true,
+            Self::HttpStatus(code) => matches!(code, 408 | 429 | 500..=599),
+            _ => false,
+        }
+    }
 }
 
-pub fn load_persisted_feed_url(app: &tauri::AppHandle) -> Result<String, String> {
-    let p = feed_file(app)?;
-    if !p.exists() {
-        // Default to this repo's GitHub Releases if the user has never configured a feed.
-        return Ok(DEFAULT_FEED.to_string());
+impl std::fmt::Display for WebUpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FeedNotSet => write!(f, "feed url not set"),
+            Self::Offline => write!(f, "can't reach the update server; check your connection"),
+            Self::Timeout(None) => write!(f, "the update server took too long to respond"),
+            Self::Timeout(Some(phase)) => write!(f, "the {} timed out", phase.as_str()),
+            Self::HttpStatus(code) => write!(f, "http {code}"),
+            Self::HashMismatch => write!(f, "sha256 mismatch"),
+            Self::TooLarge => write!(f, "zip exceeds the maximum download size"),
+            Self::InsufficientSpace { needed, available } => write!(
+                f,
+                "not enough disk space to install the update: needs {} MB, {} MB free",
+                needed.div_ceil(1024 * 1024),
+                available / (1024 * 1024)
+            ),
+            Self::InsufficientSpaceToMove { needed, available } => write!(
+                f,
+                "not enough disk space to move the app's storage there: needs {} MB, {} MB free",
+                needed.div_ceil(1024 * 1024),
+                available / (1024 * 1024)
+            ),
+            Self::NotesTooLarge => write!(f, "release notes exceed {} KB", MAX_NOTES_BYTES / 1024),
+            Self::NotesNotText(kind) => write!(f, "release notes must be text/plain or text/markdown, got {kind}"),
+            Self::DowngradeNotConfirmed { channel, offered, active } => write!(
+                f,
+                "{} channel offers {offered}, older than the active {active}; confirm the downgrade to install it",
+                channel.as_str()
+            ),
+            Self::InvalidDevOverride(e) => write!(f, "invalid dev override: {e}"),
+            Self::DevOverrideNotConfirmed => write!(f, "this is a release build; confirm to load the UI from a dev override"),
+            Self::InvalidStorageRoot(e) => write!(f, "invalid storage location: {e}"),
+            Self::Busy(op) => write!(f, "a web update {} is already in progress", op.as_str()),
+            Self::UntrustedZipOrigin { zip_host, feed_host, https: false } => {
+                write!(f, "refusing to download the bundle from {zip_host} over plain http (feed host {feed_host})")
+            }
+            Self::UntrustedZipOrigin { zip_host, feed_host, https: true } => write!(
+                f,
+                "bundle host {zip_host} is not trusted for the feed at {feed_host}; add it to trusted_zip_hosts to allow it"
+            ),
+            Self::ManifestInvalid(e) | Self::ExtractFailed(e) | Self::InvalidSettings(e) | Self::Io(e) => f.write_str(e),
+        }
     }
-    Ok(read_text_file(&p))
 }
 
-pub fn load_persisted_active_version(app: &tauri::AppHandle) -> Result<String, String> {
-    Ok(read_text_file(&active_file(app)?))
+impl From<String> for WebUpdateError {
+    fn from(e: String) -> Self {
+        Self::Io(e)
+    }
 }
 
-pub fn persist_feed_url(app: &tauri::AppHandle, url: &str) -> Result<(), String> {
-    std::fs::write(feed_file(app)?, url).map_err(|e| e.to_string())?;
-    Ok(())
+impl From<reqwest::Error> for WebUpdateError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout(None)
+        } else if e.is_decode() {
+            Self::ManifestInvalid(e.to_string())
+        } else if let Some(status) = e.status() {
+            Self::HttpStatus(status.as_u16())
+        } else if e.is_connect() || e.is_request() || e.is_body() {
+            Self::Offline
+        } else {
+            Self::Io(e.to_string())
+        }
+    }
 }
 
-pub fn persist_active_version(app: &tauri::AppHandle, version: &str) -> Result<(), String> {
-    std::fs::write(active_file(app)?, version).map_err(|e| e.to_string())?;
-    Ok(())
+/// The parts of a check or download that run against its deadline. Extraction
+/// is local work and isn't given one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdatePhase {
+    Manifest,
+    Delta,
+    Download,
 }
 
-pub fn ensure_embedded_bundle(app: &tauri::AppHandle, embedded_zip: &[u8], version: &str) -> Result<PathBuf, String> {
-    let version = validate_bundle_version(version)?;
-    let dir = active_bundle_path(app, &version)?;
-    let index = dir.join("index.html");
-    if index.exists() {
-        return Ok(dir);
+impl UpdatePhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Manifest => "manifest fetch",
+            Self::Delta => "delta download",
+            Self::Download => "bundle download",
+        }
     }
-    install_bundle_from_zip_bytes(app, embedded_zip, &version)?;
-    Ok(dir)
 }
 
-fn validate_bundle_version(version: &str) -> Result<String, String> {
-    let v = version.trim();
-    if v.is_empty() {
-        return Err("bundle version missing".into());
+/// Runs one phase against the operation's overall deadline. Running out of
+/// time, or a request timing out inside the phase, is a timeout of that phase.
+/// A download cut off here keeps its partial file, so the next try resumes.
+async fn within<T>(
+    deadline: tokio::time::Instant,
+    phase: UpdatePhase,
+    fut: impl std::future::Future<Output = Result<T, WebUpdateError>>,
+) -> Result<T, WebUpdateError> {
+    match tokio::time::timeout_at(deadline, fut).await {
+        Ok(Err(WebUpdateError::Timeout(None))) | Err(_) => Err(WebUpdateError::Timeout(Some(phase))),
+        Ok(r) => r,
     }
-    let parsed = semver::Version::parse(v).map_err(|_| "bundle version must be semver".to_string())?;
-    Ok(parsed.to_string())
 }
 
-fn create_unique_tmp_dir(final_dir: &Path) -> Result<PathBuf, String> {
-    use std::time::{SystemTime, UNIX_EPOCH};
-
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|_| "clock before unix epoch".to_string())?;
-    let nonce = now.as_nanos();
-    let pid = std::process::id();
-
-    let parent = final_dir.parent().ok_or_else(|| "bundle dir has no parent".to_string())?;
-    let base = final_dir
-        .file_name()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_else(|| "bundle".to_string());
+fn backoff_delay(base: std::time::Duration, retry: u32) -> std::time::Duration {
+    base.saturating_mul(1 << retry.min(16)).min(RETRY_MAX_DELAY)
+}
 
-    for attempt in 0..32u32 {
-        let candidate = parent.join(format!(".tmp-{}-{}-{}-{}", base, pid, nonce, attempt));
-        match std::fs::create_dir(&candidate) {
-            Ok(()) => return Ok(candidate),
-            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
-            Err(e) => return Err(e.to_string()),
+/// Runs `op` up to `attempts` times, backing off exponentially between
+/// attempts, for as long as it fails with a transient error.
+async fn with_retries<T, F, Fut>(what: &str, attempts: u32, base: std::time::Duration, mut op: F) -> Result<T, WebUpdateError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, WebUpdateError>>,
+{
+    let attempts = attempts.max(1);
+    let mut retry = 0;
+    loop {
+        match op().await {
+            Err(e) if e.is_transient() && retry + 1 < attempts => {
+                let delay = backoff_delay(base, retry);
+                tracing::warn!("{what} failed ({e}); retrying in {delay:?}");
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            }
+            r => return r,
         }
     }
-    Err("failed to create unique temp dir".into())
 }
 
-fn install_bundle_from_zip_bytes(app: &tauri::AppHandle, zip_bytes: &[u8], version: &str) -> Result<PathBuf, String> {
-    let final_dir = active_bundle_path(app, version)?;
-    if final_dir.join("index.html").exists() {
-        return Ok(final_dir);
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebUpdateErrorCode {
+    FeedNotSet,
+    Offline,
+    Timeout,
+    HttpStatus,
+    ManifestInvalid,
+    HashMismatch,
+    DownloadTooLarge,
+    InsufficientDiskSpace,
+    NotesTooLarge,
+    NotesUnsupportedType,
+    ExtractFailed,
+    DowngradeNotConfirmed,
+    InvalidSettings,
+    InvalidDevOverride,
+    DevOverrideNotConfirmed,
+    InvalidStorageRoot,
+    Busy,
+    UntrustedZipOrigin,
+    InvalidNetworkConfig,
+    NoPreviousVersion,
+    InvalidPreviousVersion,
+    PreviousBundleMissing,
+    Io,
+}
 
-    let base = bundles_dir(app)?;
-    let base_can = base.canonicalize().map_err(|e| e.to_string())?;
-    if !final_dir.starts_with(&base_can) && !final_dir.starts_with(&base) {
-        return Err("bundle path invalid".into());
-    }
+/// What every `web_update_*` command rejects with, so the frontend can branch
+/// on `code` instead of matching on message text.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WebUpdateCommandError {
+    pub code: WebUpdateErrorCode,
+    /// Human-readable; the same text these commands used to reject with.
+    pub message: String,
+    /// Trying again later may succeed (offline, timeouts, 5xx).
+    pub retryable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
 
-    let tmp_dir = create_unique_tmp_dir(&final_dir)?;
-    // If we fail, try to clean up, but always return the original error.
-    let extracted = extract_zip_bytes(zip_bytes, &tmp_dir);
-    if let Err(e) = extracted {
-        let _ = std::fs::remove_dir_all(&tmp_dir);
-        return Err(e);
-    }
-    if !tmp_dir.join("index.html").exists() {
-        let _ = std::fs::remove_dir_all(&tmp_dir);
-        return Err("bundle missing index.html".into());
+impl WebUpdateCommandError {
+    pub fn new(code: WebUpdateErrorCode, message: impl std::fmt::Display) -> Self {
+        Self { code, message: message.to_string(), retryable: false, details: None }
     }
 
-    if final_dir.exists() {
-        let _ = std::fs::remove_dir_all(&final_dir);
+    pub(crate) fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
     }
-    std::fs::rename(&tmp_dir, &final_dir).map_err(|e| e.to_string())?;
-    Ok(final_dir)
 }
 
-fn extract_zip_bytes(zip_bytes: &[u8], out_dir: &Path) -> Result<(), String> {
-    let mut z = zip::ZipArchive::new(Cursor::new(zip_bytes)).map_err(|e| e.to_string())?;
-    // Defensive limits: prevent zip bombs and pathological archives.
-    // A production web bundle should be far smaller than these.
-    let max_files: usize = 2048;
-    let max_total_uncompressed: u64 = 50 * 1024 * 1024;
-    let max_file_uncompressed: u64 = 10 * 1024 * 1024;
-    if z.len() > max_files {
-        return Err("zip contains too many entries".into());
+impl std::fmt::Display for WebUpdateCommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
     }
+}
 
-    let mut total_uncompressed: u64 = 0;
-    for i in 0..z.len() {
-        let f = z.by_index(i).map_err(|e| e.to_string())?;
-        let out_rel = safe_zip_entry_path(&f).ok_or_else(|| "zip entry path invalid".to_string())?;
-        if f.is_dir() {
-            let out_path = out_dir.join(out_rel);
-            std::fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
-            continue;
-        }
+impl From<WebUpdateError> for WebUpdateCommandError {
+    fn from(e: WebUpdateError) -> Self {
+        use WebUpdateErrorCode as Code;
+        let code = match &e {
+            WebUpdateError::FeedNotSet => Code::FeedNotSet,
+            WebUpdateError::Offline => Code::Offline,
+            WebUpdateError::Timeout(_) => Code::Timeout,
+            WebUpdateError::HttpStatus(_) => Code::HttpStatus,
+            WebUpdateError::ManifestInvalid(_) => Code::ManifestInvalid,
+            WebUpdateError::HashMismatch => Code::HashMismatch,
+            WebUpdateError::TooLarge => Code::DownloadTooLarge,
+            WebUpdateError::InsufficientSpace { .. } | WebUpdateError::InsufficientSpaceToMove { .. } => Code::InsufficientDiskSpace,
+            WebUpdateError::NotesTooLarge => Code::NotesTooLarge,
+            WebUpdateError::NotesNotText(_) => Code::NotesUnsupportedType,
+            WebUpdateError::ExtractFailed(_) => Code::ExtractFailed,
+            WebUpdateError::DowngradeNotConfirmed { .. } => Code::DowngradeNotConfirmed,
+            WebUpdateError::InvalidSettings(_) => Code::InvalidSettings,
+            WebUpdateError::InvalidDevOverride(_) => Code::InvalidDevOverride,
+            WebUpdateError::DevOverrideNotConfirmed => Code::DevOverrideNotConfirmed,
+            WebUpdateError::InvalidStorageRoot(_) => Code::InvalidStorageRoot,
+            WebUpdateError::Busy(_) => Code::Busy,
+            WebUpdateError::UntrustedZipOrigin { .. } => Code::UntrustedZipOrigin,
+            WebUpdateError::Io(_) => Code::Io,
+        };
+        let details = match &e {
+            WebUpdateError::HttpStatus(status) => Some(serde_json::json!({ "status": status })),
+            WebUpdateError::InsufficientSpace { needed, available } | WebUpdateError::InsufficientSpaceToMove { needed, available } => {
+                Some(serde_json::json!({ "needed_bytes": needed, "available_bytes": available }))
+            }
+            WebUpdateError::NotesTooLarge => Some(serde_json::json!({ "max_bytes": MAX_NOTES_BYTES })),
+            WebUpdateError::NotesNotText(kind) => Some(serde_json::json!({ "content_type": kind })),
+            WebUpdateError::DowngradeNotConfirmed { channel, offered, active } => {
+                Some(serde_json::json!({ "channel": channel, "offered": offered, "active": active }))
+            }
+            WebUpdateError::Busy(op) => Some(serde_json::json!({ "operation": op })),
+            WebUpdateError::Timeout(Some(phase)) => Some(serde_json::json!({ "phase": phase })),
+            WebUpdateError::UntrustedZipOrigin { zip_host, feed_host, https } => {
+                Some(serde_json::json!({ "zip_host": zip_host, "feed_host": feed_host, "https": https }))
+            }
+            _ => None,
+        };
+        Self { code, message: e.to_string(), retryable: e.is_transient(), details }
+    }
+}
 
-        let out_path = out_dir.join(out_rel);
-        if let Some(parent) = out_path.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+impl From<RollbackError> for WebUpdateCommandError {
+    fn from(e: RollbackError) -> Self {
+        if let RollbackError::Busy(op) = e {
+            return WebUpdateError::Busy(op).into();
         }
-        let mut out = std::fs::File::create(&out_path).map_err(|e| e.to_string())?;
-        let mut limited = f.take(max_file_uncompressed.saturating_add(1));
-        let written = std::io::copy(&mut limited, &mut out).map_err(|e| e.to_string())?;
-        if written > max_file_uncompressed {
-            return Err("zip entry too large".into());
-        }
-        total_uncompressed = total_uncompressed.saturating_add(written);
-        if total_uncompressed > max_total_uncompressed {
-            return Err("zip expands too large".into());
+        let (code, version) = match &e {
+            RollbackError::NoPreviousVersion => (WebUpdateErrorCode::NoPreviousVersion, None),
+            RollbackError::InvalidPreviousVersion(v) => (WebUpdateErrorCode::InvalidPreviousVersion, Some(v)),
+            RollbackError::PreviousBundleMissing(v) => (WebUpdateErrorCode::PreviousBundleMissing, Some(v)),
+            RollbackError::Busy(_) | RollbackError::Other(_) => (WebUpdateErrorCode::Io, None),
+        };
+        let err = Self::new(code, &e);
+        match version {
+            Some(v) => err.with_details(serde_json::json!({ "version": v })),
+            None => err,
         }
     }
-    Ok(())
 }
 
-fn safe_zip_entry_path(f: &ZipFile<'_>) -> Option<PathBuf> {
-    // `enclosed_name` rejects absolute paths and `..` traversal.
-    let p = f.enclosed_name()?.to_path_buf();
-    // Extra hardening: ignore Windows-y or weird paths that can sneak through.
-    let s = p.to_string_lossy();
-    if s.contains('\\') || s.contains(':') {
-        return None;
+impl From<String> for WebUpdateCommandError {
+    fn from(e: String) -> Self {
+        Self::new(WebUpdateErrorCode::Io, e)
     }
-    // Limit pathological path lengths and components.
-    if s.len() > 1024 {
-        return None;
+}
+
+// Serialized to the frontend as `{ "code": "no_previous_version", "detail": ... }`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "code", content = "detail", rename_all = "snake_case")]
+pub enum RollbackError {
+    NoPreviousVersion,
+    InvalidPreviousVersion(String),
+    PreviousBundleMissing(String),
+    Busy(UpdateOperation),
+    Other(String),
+}
+
+impl std::fmt::Display for RollbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoPreviousVersion => write!(f, "no previous web bundle to roll back to"),
+            Self::InvalidPreviousVersion(v) => write!(f, "previous bundle version {v:?} is not semver"),
+            Self::PreviousBundleMissing(v) => write!(f, "previous bundle {v} is no longer installed"),
+            Self::Busy(op) => write!(f, "{}", WebUpdateError::Busy(*op)),
+            Self::Other(e) => f.write_str(e),
+        }
     }
-    for c in p.components() {
-        let std::path::Component::Normal(os) = c else { continue };
-        if os.to_string_lossy().len() > 255 {
-            return None;
+}
+
+impl From<WebUpdateError> for RollbackError {
+    fn from(e: WebUpdateError) -> Self {
+        match e {
+            WebUpdateError::Busy(op) => Self::Busy(op),
+            e => Self::Other(e.to_string()),
         }
     }
-    Some(p)
+}
+impl From<String> for RollbackError {
+    fn from(e: String) -> Self {
+        Self::Other(e)
+    }
 }
 
-fn parse_version(s: &str) -> Option<semver::Version> {
-    semver::Version::parse(s.trim()).ok()
+pub(crate) fn cache_root(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let base = match configured_cache_root(app)? {
+        Some(dir) => dir,
+        None => default_cache_root(app)?,
+    };
+    std::fs::create_dir_all(&base).map_err(|e| e.to_string())?;
+    Ok(base)
 }
 
-async fn fetch_manifest(url: &str) -> Result<WebBundleManifestV1, String> {
-    let feed = normalize_feed_url(url);
-    let resp = reqwest::Client::new()
-        .get(feed)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-    if !resp.status().is_success() {
-        return Err(format!("manifest http {}", resp.status()));
-    }
-    let m = resp.json::<WebBundleManifestV1>().await.map_err(|e| e.to_string())?;
-    if m.v != 1 {
-        return Err("manifest.v must be 1".into());
+fn default_cache_root(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app.path().app_cache_dir().map_err(|e| e.to_string())?.join(CACHE_DIR_NAME))
+}
+
+fn storage_settings_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn read_storage_root_in(dir: &Path) -> Option<PathBuf> {
+    Some(read_text_file(&dir.join(STORAGE_ROOT_FILE))).filter(|r| !r.is_empty()).map(PathBuf::from)
+}
+
+// `None` goes back to the OS cache dir.
+fn write_storage_root_in(dir: &Path, root: Option<&Path>) -> Result<(), String> {
+    let path = dir.join(STORAGE_ROOT_FILE);
+    match root {
+        Some(root) => write_synced(&path, root.to_string_lossy().as_bytes()),
+        None => match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => {
+                sync_dir(dir);
+                Ok(())
+            }
+        },
     }
-    if m.version.trim().is_empty() || m.zip_url.trim().is_empty() || m.sha256.trim().is_empty() {
-        return Err("manifest missing fields".into());
+}
+
+/// The cache under the configured storage root, or `None` when there is no
+/// storage root or it can't be reached (a disk that isn't mounted). The OS
+/// cache dir stands in until it is back, so the app still starts.
+fn configured_cache_root(app: &tauri::AppHandle) -> Result<Option<PathBuf>, String> {
+    let Some(root) = read_storage_root_in(&storage_settings_dir(app)?) else {
+        return Ok(None);
+    };
+    if root.is_dir() {
+        return Ok(Some(root.join(CACHE_DIR_NAME)));
     }
-    // Also acts as path-hardening (bundle dir names should be safe).
-    let _ = validate_bundle_version(&m.version)?;
-    Ok(m)
+    static WARNED: std::sync::Once = std::sync::Once::new();
+    WARNED.call_once(|| tracing::warn!("storage root {} is unavailable; using the default cache dir", root.display()));
+    Ok(None)
 }
 
-fn normalize_feed_url(feed: &str) -> String {
-    let s = feed.trim();
-    if s.is_empty() {
-        return "".to_string();
+// A configured storage root that can't be reached right now.
+fn storage_root_unavailable(app: &tauri::AppHandle) -> Option<PathBuf> {
+    let root = read_storage_root_in(&storage_settings_dir(app).ok()?)?;
+    (!root.is_dir()).then_some(root)
+}
+
+fn bundles_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let p = cache_root(app)?.join("web_bundles");
+    std::fs::create_dir_all(&p).map_err(|e| e.to_string())?;
+    Ok(p)
+}
+
+fn feed_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(cache_root(app)?.join(FEED_FILE))
+}
+
+fn active_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(cache_root(app)?.join(ACTIVE_VERSION_FILE))
+}
+
+fn active_bundle_path(app: &tauri::AppHandle, version: &str) -> Result<PathBuf, String> {
+    Ok(bundles_dir(app)?.join(version))
+}
+
+fn bundle_dir_in(cache: &Path, version: &str) -> PathBuf {
+    cache.join("web_bundles").join(version)
+}
+
+fn read_text_file(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap_or_default().trim().to_string()
+}
+
+// One feed per line, so a file written when only a single feed was supported still reads.
+fn parse_feed_list(text: &str) -> Vec<String> {
+    text.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect()
+}
+
+pub fn load_persisted_feed_urls(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
+    let p = feed_file(app)?;
+    if !p.exists() {
+        // Default to this repo's GitHub Releases if the user has never configured a feed.
+        return Ok(vec![DEFAULT_FEED.to_string()]);
     }
+    Ok(parse_feed_list(&read_text_file(&p)))
+}
 
-    // Shorthand: `gh:owner/repo` (or `github:owner/repo`) resolves to the latest GitHub Release asset:
-    // https://github.com/<owner>/<repo>/releases/latest/download/voxelle-web-manifest.json
-    if let Some(rest) = s.strip_prefix("gh:").or_else(|| s.strip_prefix("github:")) {
-        let slug = rest.trim().trim_matches('/');
-        if !slug.is_empty() {
-            return format!(
-                "https://github.com/{}/releases/latest/download/voxelle-web-manifest.json",
-                slug
-            );
-        }
+pub fn load_persisted_active_version(app: &tauri::AppHandle) -> Result<String, String> {
+    Ok(read_text_file(&active_file(app)?))
+}
+
+pub fn persist_feed_urls(app: &tauri::AppHandle, urls: &[String]) -> Result<(), String> {
+    std::fs::write(feed_file(app)?, urls.join("\n")).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn persist_active_version(app: &tauri::AppHandle, version: &str) -> Result<(), String> {
+    std::fs::write(active_file(app)?, version).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn load_persisted_active_channel(app: &tauri::AppHandle) -> Result<Option<UpdateChannel>, String> {
+    Ok(UpdateChannel::parse(&read_text_file(&cache_root(app)?.join(ACTIVE_CHANNEL_FILE))))
+}
+
+fn write_channel(path: &Path, channel: Option<UpdateChannel>) -> Result<(), String> {
+    std::fs::write(path, channel.map(|c| c.as_str()).unwrap_or("")).map_err(|e| e.to_string())
+}
+
+// Remembers the version being replaced so `rollback` has somewhere to go back to.
+fn record_previous_version(
+    cache: &Path,
+    current: &str,
+    current_channel: Option<UpdateChannel>,
+    next: &str,
+) -> Result<(), String> {
+    let current = current.trim();
+    if current.is_empty() || current == next.trim() {
+        return Ok(());
     }
+    std::fs::write(cache.join(PREVIOUS_VERSION_FILE), current).map_err(|e| e.to_string())?;
+    write_channel(&cache.join(PREVIOUS_CHANNEL_FILE), current_channel)
+}
 
-    s.to_string()
+fn read_pending_in(cache: &Path) -> Option<PendingActivation> {
+    std::fs::read(cache.join(PENDING_ACTIVATION_FILE)).ok().and_then(|b| serde_json::from_slice(&b).ok())
 }
 
-pub fn status(state: &WebUpdateState) -> WebUpdateStatus {
-    WebUpdateStatus {
-        active_version: state.active_version.lock().map(|g| g.clone()).unwrap_or_default(),
-        feed_url: state.feed_url.lock().map(|g| g.clone()).unwrap_or_default(),
-        port: state.server.port(),
+// Written to a temp file and renamed, so a crash never leaves a torn marker.
+fn write_pending_in(cache: &Path, pending: &PendingActivation) -> Result<(), String> {
+    let path = cache.join(PENDING_ACTIVATION_FILE);
+    let tmp = path.with_extension("json.tmp");
+    let json = serde_json::to_vec_pretty(pending).map_err(|e| e.to_string())?;
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, &path).map_err(|e| e.to_string())
+}
+
+fn clear_pending_in(cache: &Path) {
+    let _ = std::fs::remove_file(cache.join(PENDING_ACTIVATION_FILE));
+}
+
+/// A change of active bundle, written down before any of its steps are taken
+/// and removed once all of them are. A process that dies in between leaves the
+/// entry for `recover_activation` to finish or undo on the next launch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "intent", rename_all = "snake_case")]
+enum ActivationJournal {
+    Activate(PlannedActivation),
+    /// Back to the embedded bundle `version`. Always finished: the bundles it
+    /// removes may already be gone.
+    Reset { version: String, keep_settings: bool },
+    /// The whole cache being copied from `from` to `to`. Written to both, so
+    /// whichever the storage root setting points at has it: at `from` the
+    /// copy is undone, at `to` the old copy is deleted.
+    MoveStorage { from: PathBuf, to: PathBuf },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PlannedActivation {
+    version: String,
+    channel: Option<UpdateChannel>,
+    /// The active version being replaced, recorded for `rollback` and restored
+    /// if the activation can't be finished. Empty means the embedded bundle.
+    previous: String,
+    previous_channel: Option<UpdateChannel>,
+    /// A just-extracted bundle still to be renamed to `final_dir`.
+    tmp_dir: Option<PathBuf>,
+    final_dir: PathBuf,
+}
+
+/// What `recover_activation` did with an interrupted journal entry.
+#[derive(Debug, PartialEq)]
+enum Recovered {
+    /// The activation of this version was finished.
+    Finished(String),
+    /// The bundle never made it into place; this version is active again.
+    Undone(String),
+    /// The reset back to this embedded version was finished.
+    Reset(String),
+    /// The storage move to this cache dir was finished.
+    StorageMoved(PathBuf),
+    /// The storage move was undone; the cache stays in this dir.
+    StorageMoveUndone(PathBuf),
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Ok(d) = std::fs::File::open(dir) {
+        let _ = d.sync_all();
     }
 }
 
-pub fn set_feed(state: &WebUpdateState, app: &tauri::AppHandle, url: &str) -> Result<(), String> {
-    let u = url.trim().to_string();
-    persist_feed_url(app, &u)?;
-    if let Ok(mut g) = state.feed_url.lock() {
-        *g = u;
+// Windows can't open a directory to sync it; NTFS journals renames itself.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
+
+// Written to a temp file, synced and renamed, so a crash leaves either the old
+// contents or the new, and nothing written after it can reach the disk first.
+fn write_synced(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("sync.tmp");
+    let mut f = std::fs::File::create(&tmp).map_err(|e| e.to_string())?;
+    f.write_all(contents).and_then(|()| f.sync_all()).map_err(|e| e.to_string())?;
+    drop(f);
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        sync_dir(dir);
     }
     Ok(())
 }
 
-pub async fn check(state: &WebUpdateState) -> Result<WebUpdateCheckResult, String> {
-    let feed = state.feed_url.lock().map_err(|_| "feed lock poisoned")?.clone();
-    if feed.trim().is_empty() {
-        return Ok(WebUpdateCheckResult { available: false, version: None, zip_url: None, sha256: None });
+fn read_journal_in(cache: &Path) -> Option<ActivationJournal> {
+    std::fs::read(cache.join(ACTIVATION_JOURNAL_FILE)).ok().and_then(|b| serde_json::from_slice(&b).ok())
+}
+
+fn write_journal_in(cache: &Path, entry: &ActivationJournal) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(entry).map_err(|e| e.to_string())?;
+    write_synced(&cache.join(ACTIVATION_JOURNAL_FILE), &json)
+}
+
+fn clear_journal_in(cache: &Path) {
+    let _ = std::fs::remove_file(cache.join(ACTIVATION_JOURNAL_FILE));
+    sync_dir(cache);
+}
+
+fn write_active_in(cache: &Path, version: &str, channel: Option<UpdateChannel>) -> Result<(), String> {
+    write_synced(&cache.join(ACTIVE_VERSION_FILE), version.as_bytes())?;
+    write_synced(&cache.join(ACTIVE_CHANNEL_FILE), channel.map(|c| c.as_str()).unwrap_or("").as_bytes())
+}
+
+/// Takes each step of `plan` and then clears the journal. Every step can be
+/// repeated, so recovery runs this again over an attempt that got part way.
+fn commit_activation_in(cache: &Path, plan: &PlannedActivation) -> Result<(), String> {
+    if let Some(tmp_dir) = plan.tmp_dir.as_deref().filter(|d| d.exists()) {
+        move_into_place(tmp_dir, &plan.final_dir)?;
     }
-    let m = fetch_manifest(&feed).await?;
+    if !plan.final_dir.join("index.html").is_file() {
+        return Err(format!("web bundle {} is not installed", plan.version));
+    }
+    record_previous_version(cache, &plan.previous, plan.previous_channel, &plan.version)?;
+    write_active_in(cache, &plan.version, plan.channel)?;
+    // Whatever was waiting for the next launch has been superseded (or was this).
+    clear_pending_in(cache);
+    clear_journal_in(cache);
+    Ok(())
+}
 
-    let active = state
-        .active_version
-        .lock()
-        .map_err(|_| "active lock poisoned")?
-        .clone();
-    let active_v = parse_version(&active);
-    let new_v = parse_version(&m.version);
-    let available = match (active_v, new_v) {
-        (Some(a), Some(n)) => n > a,
-        _ => m.version.trim() != active.trim(),
-    };
+/// Puts back the version `plan` was replacing and drops its extracted bundle.
+fn roll_back_in(cache: &Path, plan: &PlannedActivation) -> Result<(), String> {
+    if let Some(tmp_dir) = &plan.tmp_dir {
+        let _ = std::fs::remove_dir_all(tmp_dir);
+    }
+    if plan.previous.trim().is_empty() {
+        match std::fs::remove_file(cache.join(ACTIVE_VERSION_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+            _ => {}
+        }
+        write_synced(&cache.join(ACTIVE_CHANNEL_FILE), b"")?;
+    } else {
+        write_active_in(cache, &plan.previous, plan.previous_channel)?;
+    }
+    clear_journal_in(cache);
+    Ok(())
+}
 
-    Ok(WebUpdateCheckResult {
-        available,
-        version: Some(m.version),
-        zip_url: Some(m.zip_url),
-        sha256: Some(m.sha256),
+/// Journals `plan` and carries it out, undoing it if a step fails so the
+/// persisted version keeps matching what is served.
+fn activate_in(cache: &Path, plan: &PlannedActivation) -> Result<(), String> {
+    write_journal_in(cache, &ActivationJournal::Activate(plan.clone()))?;
+    commit_activation_in(cache, plan).inspect_err(|_| {
+        if let Err(e) = roll_back_in(cache, plan) {
+            tracing::error!("undoing the web bundle activation of {} failed; retrying at next launch: {e}", plan.version);
+        }
     })
 }
 
-pub async fn download_and_activate(
-    state: &WebUpdateState,
-    app: &tauri::AppHandle,
-) -> Result<WebUpdateDownloadResult, String> {
-    let feed = state.feed_url.lock().map_err(|_| "feed lock poisoned")?.clone();
-    if feed.trim().is_empty() {
-        return Err("feed url not set".into());
-    }
+// Only entries that point where this app would have put things are acted on.
+fn journal_paths_ok(cache: &Path, plan: &PlannedActivation) -> bool {
+    let bundles = cache.join("web_bundles");
+    let tmp_ok = |d: &Path| {
+        d.parent() == Some(bundles.as_path()) && d.file_name().is_some_and(|n| n.to_string_lossy().starts_with(".tmp-"))
+    };
+    validate_bundle_version(&plan.version).is_ok_and(|v| plan.final_dir == bundle_dir_in(cache, &v))
+        && plan.tmp_dir.as_deref().is_none_or(tmp_ok)
+}
 
-    let mut m = fetch_manifest(&feed).await?;
-    m.version = validate_bundle_version(&m.version)?;
+/// Finishes an activation the last run was in the middle of if its bundle
+/// made it to disk, and undoes it otherwise; a reset is always finished, and
+/// a storage move finished or undone by where the setting ended up.
+fn recover_activation_in(cache: &Path) -> Result<Option<Recovered>, String> {
+    if !cache.join(ACTIVATION_JOURNAL_FILE).exists() {
+        return Ok(None);
+    }
+    let plan = match read_journal_in(cache) {
+        Some(ActivationJournal::Activate(plan)) if journal_paths_ok(cache, &plan) => plan,
+        Some(ActivationJournal::Reset { version, keep_settings }) => {
+            let version = validate_bundle_version(&version)?;
+            clear_cache_in(cache, &version, keep_settings)?;
+            clear_journal_in(cache);
+            return Ok(Some(Recovered::Reset(version)));
+        }
+        Some(ActivationJournal::MoveStorage { from, to }) if move_paths_ok(cache, &from, &to) => {
+            return if cache == to {
+                finish_move_in(&from, &to)?;
+                Ok(Some(Recovered::StorageMoved(to)))
+            } else {
+                undo_move_in(&from, &to)?;
+                Ok(Some(Recovered::StorageMoveUndone(from)))
+            };
+        }
+        // Unreadable, or not something this app wrote: leave the files as they
+        // are and let the startup bundle check deal with them.
+        _ => {
+            tracing::warn!("dropping an unusable web activation journal");
+            clear_journal_in(cache);
+            return Ok(None);
+        }
+    };
+    let staged = plan.tmp_dir.as_deref().is_some_and(|d| d.join("index.html").is_file());
+    if !staged && !plan.final_dir.join("index.html").is_file() {
+        roll_back_in(cache, &plan)?;
+        return Ok(Some(Recovered::Undone(plan.previous)));
+    }
+    commit_activation_in(cache, &plan)?;
+    record_history_in(
+        cache,
+        UpdateHistoryEvent::Activation { version: plan.version.clone(), channel: plan.channel, previous: plan.previous },
+    );
+    Ok(Some(Recovered::Finished(plan.version)))
+}
 
-    let resp = reqwest::Client::new()
-        .get(&m.zip_url)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-    if !resp.status().is_success() {
-        return Err(format!("zip http {}", resp.status()));
+/// Called in `setup()` before anything else reads or changes the active version.
+#[tracing::instrument(skip_all)]
+pub fn recover_activation(app: &tauri::AppHandle) {
+    let Ok(cache) = cache_root(app) else {
+        return;
+    };
+    // Which side of a storage move this is depends on the setting, and the
+    // stand-in cache for a root that isn't mounted could be either; wait for it.
+    if storage_root_unavailable(app).is_some() && matches!(read_journal_in(&cache), Some(ActivationJournal::MoveStorage { .. })) {
+        return;
     }
-    let bytes = resp.bytes().await.map_err(|e| e.to_string())?.to_vec();
-    let digest = Sha256::digest(&bytes);
-    let got_hex = hex::encode(digest);
-    let want_hex = m.sha256.trim().to_lowercase();
-    if got_hex != want_hex {
-        return Err("sha256 mismatch".into());
+    match recover_activation_in(&cache) {
+        Ok(Some(Recovered::Finished(v))) => tracing::info!("finished the interrupted activation of web bundle {v}"),
+        Ok(Some(Recovered::Undone(v))) => tracing::info!("undid an interrupted web bundle activation; back on {v:?}"),
+        Ok(Some(Recovered::Reset(v))) => tracing::info!("finished the interrupted reset to web bundle {v}"),
+        Ok(Some(Recovered::StorageMoved(dir))) => tracing::info!("finished the interrupted storage move to {}", dir.display()),
+        Ok(Some(Recovered::StorageMoveUndone(dir))) => tracing::info!("undid an interrupted storage move; staying in {}", dir.display()),
+        Ok(None) => {}
+        Err(e) => tracing::error!("recovering an interrupted web bundle activation failed: {e}"),
     }
+}
 
-    // Extract into a temp dir first, then atomically rename into place to avoid partial bundles.
-    let final_dir = active_bundle_path(app, &m.version)?;
-    if final_dir.join("index.html").exists() {
-        // Already installed.
-        persist_active_version(app, &m.version)?;
-        if let Ok(mut g) = state.active_version.lock() {
-            *g = m.version.clone();
+/// Makes a pending update the active version, before anything reads it. A
+/// marker that is unreadable or points at a bundle that isn't installed is
+/// dropped. The switch is journaled and the marker only removed with it, so
+/// a crash part-way through is finished on the next launch.
+fn apply_pending_in(cache: &Path, embedded_version: &str) -> Result<Option<String>, String> {
+    let Some(pending) = read_pending_in(cache) else {
+        clear_pending_in(cache);
+        return Ok(None);
+    };
+    let version = match validate_bundle_version(&pending.version) {
+        Ok(v) if bundle_dir_in(cache, &v).join("index.html").is_file() => v,
+        _ => {
+            tracing::warn!("dropping pending web update {:?}: bundle not installed", pending.version);
+            clear_pending_in(cache);
+            return Ok(None);
         }
-        state.server.set_root(final_dir);
-        let _ = app.emit(EVENT_WEB_UPDATE_READY, m.version.clone());
-        return Ok(WebUpdateDownloadResult { activated_version: m.version });
-    }
+    };
+    let current = Some(read_text_file(&cache.join(ACTIVE_VERSION_FILE)))
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| embedded_version.to_string());
+    let current_channel = UpdateChannel::parse(&read_text_file(&cache.join(ACTIVE_CHANNEL_FILE)));
+    activate_in(
+        cache,
+        &PlannedActivation {
+            version: version.clone(),
+            channel: pending.channel,
+            previous: current.clone(),
+            previous_channel: current_channel,
+            tmp_dir: None,
+            final_dir: bundle_dir_in(cache, &version),
+        },
+    )?;
+    record_history_in(cache, UpdateHistoryEvent::Activation { version: version.clone(), channel: pending.channel, previous: current });
+    Ok(Some(version))
+}
 
-    let final_dir = install_bundle_from_zip_bytes(app, &bytes, &m.version)?;
+/// Called in `setup()` before the active version is loaded.
+#[tracing::instrument(skip(app))]
+pub fn apply_pending_activation(app: &tauri::AppHandle, embedded_version: &str) -> Option<String> {
+    let cache = cache_root(app).ok()?;
+    apply_pending_in(&cache, embedded_version).unwrap_or_else(|e| {
+        tracing::error!("applying pending web update failed: {e}");
+        None
+    })
+}
 
-    persist_active_version(app, &m.version)?;
-    if let Ok(mut g) = state.active_version.lock() {
-        *g = m.version.clone();
+fn rollback_target(cache: &Path) -> Result<(String, PathBuf), RollbackError> {
+    let previous = read_text_file(&cache.join(PREVIOUS_VERSION_FILE));
+    if previous.is_empty() {
+        return Err(RollbackError::NoPreviousVersion);
     }
-    state.server.set_root(final_dir);
+    let version =
+        validate_bundle_version(&previous).map_err(|_| RollbackError::InvalidPreviousVersion(previous.clone()))?;
+    let dir = bundle_dir_in(cache, &version);
+    if !dir.join("index.html").is_file() {
+        return Err(RollbackError::PreviousBundleMissing(version));
+    }
+    Ok((version, dir))
+}
+
+fn previous_channel(cache: &Path) -> Option<UpdateChannel> {
+    UpdateChannel::parse(&read_text_file(&cache.join(PREVIOUS_CHANNEL_FILE)))
+}
 
-    let _ = app.emit(EVENT_WEB_UPDATE_READY, m.version.clone());
-    Ok(WebUpdateDownloadResult { activated_version: m.version })
+pub fn load_persisted_keep_bundles(app: &tauri::AppHandle) -> Result<usize, String> {
+    Ok(read_text_file(&cache_root(app)?.join(KEEP_BUNDLES_FILE))
+        .parse()
+        .unwrap_or(DEFAULT_KEEP_BUNDLES))
+}
+
+pub fn load_persisted_server_port(app: &tauri::AppHandle) -> Result<Option<u16>, String> {
+    Ok(read_text_file(&cache_root(app)?.join(SERVER_PORT_FILE)).parse().ok())
+}
+
+pub fn persist_server_port(app: &tauri::AppHandle, port: u16) -> Result<(), String> {
+    std::fs::write(cache_root(app)?.join(SERVER_PORT_FILE), port.to_string()).map_err(|e| e.to_string())
+}
+
+pub fn persist_keep_bundles(app: &tauri::AppHandle, keep: usize) -> Result<(), String> {
+    if keep == 0 {
+        return Err("must keep at least one bundle".into());
+    }
+    std::fs::write(cache_root(app)?.join(KEEP_BUNDLES_FILE), keep.to_string()).map_err(|e| e.to_string())
+}
+
+/// Removes all but the newest `keep` bundles. The active, embedded and
+/// rollback bundles are always kept.
+#[tracing::instrument(skip(app))]
+pub fn prune_bundles(app: &tauri::AppHandle, keep: usize) -> Result<WebUpdatePruneResult, String> {
+    let cache = cache_root(app)?;
+    let Some(state) = app.try_state::<WebUpdateState>() else {
+        return Err("web update state not ready".into());
+    };
+    let active = state.active_version.lock().map_err(|_| "active lock poisoned")?.clone();
+    let previous = read_text_file(&cache.join(PREVIOUS_VERSION_FILE));
+    let pending = read_pending_in(&cache).map(|p| p.version).unwrap_or_default();
+    let protected = [active.as_str(), state.embedded_version.as_str(), previous.as_str(), pending.as_str()];
+    let mut result = retry_pending_deletes(&cache, remove_bundle_dir)?;
+    let pruned = prune_in(&cache, keep, &protected, remove_bundle_dir)?;
+    result.removed.extend(pruned.removed);
+    result.deferred.extend(pruned.deferred);
+    Ok(result)
+}
+
+/// Called at startup to finish removals that failed earlier.
+pub fn retry_pending_prunes(app: &tauri::AppHandle) -> Result<WebUpdatePruneResult, String> {
+    retry_pending_deletes(&cache_root(app)?, remove_bundle_dir)
+}
+
+fn remove_bundle_dir(dir: &Path) -> std::io::Result<()> {
+    match std::fs::remove_dir_all(dir) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        r => r,
+    }
+}
+
+fn prune_in(
+    cache: &Path,
+    keep: usize,
+    protected: &[&str],
+    remove: impl Fn(&Path) -> std::io::Result<()>,
+) -> Result<WebUpdatePruneResult, String> {
+    let base = cache.join("web_bundles");
+    if !base.is_dir() {
+        return Ok(WebUpdatePruneResult::default());
+    }
+    // Only semver-named directories are bundles; temp dirs and anything else are left alone.
+    let versions = installed_versions_in(cache);
+
+    let mut result = WebUpdatePruneResult::default();
+    for v in versions.into_iter().skip(keep) {
+        let name = v.to_string();
+        if protected.iter().any(|p| p.trim() == name) {
+            continue;
+        }
+        match remove(&base.join(&name)) {
+            Ok(()) => result.removed.push(name),
+            Err(e) => {
+                tracing::warn!("web bundle prune: deferring {name}: {e}");
+                result.deferred.push(name);
+            }
+        }
+    }
+    if !result.deferred.is_empty() {
+        let mut pending = read_pending_deletes(cache);
+        for name in &result.deferred {
+            if !pending.contains(name) {
+                pending.push(name.clone());
+            }
+        }
+        write_pending_deletes(cache, &pending)?;
+    }
+    Ok(result)
+}
+
+fn retry_pending_deletes(
+    cache: &Path,
+    remove: impl Fn(&Path) -> std::io::Result<()>,
+) -> Result<WebUpdatePruneResult, String> {
+    let pending = read_pending_deletes(cache);
+    if pending.is_empty() {
+        return Ok(WebUpdatePruneResult::default());
+    }
+    let mut result = WebUpdatePruneResult::default();
+    for name in pending {
+        // The file is ours, but don't let a tampered entry reach outside web_bundles.
+        if validate_bundle_version(&name).ok().as_deref() != Some(name.as_str()) {
+            continue;
+        }
+        match remove(&bundle_dir_in(cache, &name)) {
+            Ok(()) => result.removed.push(name),
+            Err(_) => result.deferred.push(name),
+        }
+    }
+    write_pending_deletes(cache, &result.deferred)?;
+    Ok(result)
+}
+
+fn read_pending_deletes(cache: &Path) -> Vec<String> {
+    read_text_file(&cache.join(PENDING_DELETE_FILE))
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+fn write_pending_deletes(cache: &Path, pending: &[String]) -> Result<(), String> {
+    let path = cache.join(PENDING_DELETE_FILE);
+    if pending.is_empty() {
+        return match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        };
+    }
+    std::fs::write(path, pending.join("\n")).map_err(|e| e.to_string())
+}
+
+pub fn navigate_main_window(app: &tauri::AppHandle, server: &WebBundleServer) -> Result<(), String> {
+    navigate_main_window_to(app, &server.base_url())
+}
+
+fn navigate_main_window_to(app: &tauri::AppHandle, url: &str) -> Result<(), String> {
+    if let Some(w) = app.get_webview_window("main") {
+        let url: tauri::Url = url.parse().map_err(|e| format!("bad URL {url}: {e}"))?;
+        w.navigate(url).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Reloads the UI from wherever it currently comes from: a dev override URL
+/// if one is set, the bundle server otherwise.
+pub fn reload_main_window(state: &WebUpdateState, app: &tauri::AppHandle) -> Result<(), String> {
+    match dev_override(state) {
+        Some(DevOverride::Url(url)) => navigate_main_window_to(app, &url),
+        _ => navigate_main_window(app, &state.server),
+    }
+}
+
+fn dev_override(state: &WebUpdateState) -> Option<DevOverride> {
+    state.dev_override.lock().ok().and_then(|g| g.clone())
+}
+
+fn parse_dev_override(target: &str) -> Result<DevOverride, WebUpdateError> {
+    let target = target.trim();
+    if target.contains("://") {
+        return match reqwest::Url::parse(target) {
+            Ok(u) if matches!(u.scheme(), "http" | "https") => Ok(DevOverride::Url(u.to_string())),
+            _ => Err(WebUpdateError::InvalidDevOverride(format!("{target} is not an http(s) URL"))),
+        };
+    }
+    // Canonical, so the server's traversal check compares like with like.
+    let dir = Path::new(target)
+        .canonicalize()
+        .map_err(|e| WebUpdateError::InvalidDevOverride(format!("{target}: {e}")))?;
+    if !dir.join("index.html").is_file() {
+        return Err(WebUpdateError::InvalidDevOverride(format!("{} has no index.html", dir.display())));
+    }
+    Ok(DevOverride::Dir(dir))
+}
+
+fn check_dev_override_allowed(next: Option<&DevOverride>, release: bool, confirmed: bool) -> Result<(), WebUpdateError> {
+    if next.is_some() && release && !confirmed {
+        return Err(WebUpdateError::DevOverrideNotConfirmed);
+    }
+    Ok(())
+}
+
+/// Loads the UI from a local directory or an http(s) URL instead of the
+/// active bundle, or with `None` goes back to the active bundle. Version
+/// management carries on underneath: updates still install and activate, and
+/// show up once the override is cleared.
+#[tracing::instrument(skip(state, app))]
+pub fn set_dev_override(
+    state: &WebUpdateState,
+    app: &tauri::AppHandle,
+    target: Option<String>,
+    confirmed: bool,
+) -> Result<Option<DevOverride>, WebUpdateError> {
+    let next = target.as_deref().filter(|t| !t.trim().is_empty()).map(parse_dev_override).transpose()?;
+    check_dev_override_allowed(next.as_ref(), !cfg!(debug_assertions), confirmed)?;
+    match &next {
+        Some(DevOverride::Dir(dir)) => state.server.set_root(dir.clone()),
+        _ => {
+            let active = state.active_version.lock().map_err(|_| "active lock poisoned".to_string())?.clone();
+            state.server.set_root(active_bundle_path(app, &validate_bundle_version(&active)?)?);
+        }
+    }
+    *state.dev_override.lock().map_err(|_| "dev override lock poisoned".to_string())? = next.clone();
+    reload_main_window(state, app)?;
+    Ok(next)
+}
+
+// Whether `dir` can be served: a non-empty index.html and, when the bundle
+// came with a file list, every listed file intact. Extra files are tolerated.
+fn check_bundle_dir(dir: &Path) -> Result<(), String> {
+    let index = std::fs::read(dir.join("index.html")).map_err(|_| "index.html is missing".to_string())?;
+    match std::str::from_utf8(&index) {
+        Ok(html) if !html.trim().is_empty() => {}
+        Ok(_) => return Err("index.html is empty".into()),
+        Err(_) => return Err("index.html is not text".into()),
+    }
+    let Ok(listing) = std::fs::read(dir.join(BUNDLE_FILES_NAME)) else {
+        return Ok(());
+    };
+    let files: Vec<BundleFile> =
+        serde_json::from_slice(&listing).map_err(|e| format!("{BUNDLE_FILES_NAME} is unreadable: {e}"))?;
+    validate_bundle_files(&files)?;
+    let report = verify_bundle_files(dir, "", &files)?;
+    let bad: Vec<_> = report
+        .files
+        .iter()
+        .filter(|c| !matches!(c.status, FileCheckStatus::Ok | FileCheckStatus::Unexpected))
+        .map(|c| c.path.as_str())
+        .collect();
+    if bad.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("damaged files: {}", bad.join(", ")))
+    }
+}
+
+// Installed bundle versions, newest first.
+fn installed_versions_in(cache: &Path) -> Vec<semver::Version> {
+    let mut versions: Vec<semver::Version> = std::fs::read_dir(cache.join("web_bundles"))
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| semver::Version::parse(&e.file_name().to_string_lossy()).ok())
+        .collect();
+    versions.sort_by(|a, b| b.cmp(a));
+    versions
+}
+
+/// Makes sure the active bundle can be served. A damaged one is replaced by
+/// the embedded bundle (re-extracted if it's damaged too), or failing that by
+/// the newest intact installed bundle, which becomes the active version.
+/// Extraction is attempted at most once, so a broken or empty embedded zip
+/// ends in an error rather than a loop.
+fn ensure_intact_in(
+    cache: &Path,
+    embedded_zip: &[u8],
+    embedded_version: &str,
+    active_version: &str,
+) -> Result<(String, PathBuf, Option<BundleRepair>), String> {
+    let embedded = validate_bundle_version(embedded_version)?;
+    let active = validate_bundle_version(active_version)?;
+    let dir = bundle_dir_in(cache, &active);
+    let problem = match check_bundle_dir(&dir) {
+        Ok(()) => return Ok((active, dir, None)),
+        // First launch: the embedded bundle just isn't extracted yet.
+        Err(_) if active == embedded && !dir.exists() => None,
+        Err(e) => Some(e),
+    };
+    let repaired = |version: &str, action| {
+        problem.clone().map(|problem| BundleRepair {
+            broken_version: active.clone(),
+            problem,
+            action,
+            activated_version: version.to_string(),
+        })
+    };
+
+    let embedded_dir = bundle_dir_in(cache, &embedded);
+    if embedded != active && check_bundle_dir(&embedded_dir).is_ok() {
+        return Ok((embedded.clone(), embedded_dir, repaired(&embedded, BundleRepairAction::Reactivated)));
+    }
+    let extracted = if embedded_zip.is_empty() {
+        Err("this build embeds no web bundle".to_string())
+    } else {
+        if embedded_dir.exists() {
+            std::fs::remove_dir_all(&embedded_dir).map_err(|e| e.to_string())?;
+        }
+        install_bundle_in(cache, Cursor::new(embedded_zip), &embedded, None).and_then(|d| check_bundle_dir(&d).map(|()| d))
+    };
+    let extract_err = match extracted {
+        Ok(d) => return Ok((embedded.clone(), d, repaired(&embedded, BundleRepairAction::ReextractedEmbedded))),
+        Err(e) if problem.is_none() => return Err(e),
+        Err(e) => e,
+    };
+    tracing::error!("re-extracting the embedded web bundle {embedded} failed: {extract_err}");
+
+    for v in installed_versions_in(cache) {
+        let version = v.to_string();
+        if version == active || version == embedded {
+            continue;
+        }
+        let dir = bundle_dir_in(cache, &version);
+        if check_bundle_dir(&dir).is_ok() {
+            let repair = repaired(&version, BundleRepairAction::Reactivated);
+            return Ok((version, dir, repair));
+        }
+    }
+    Err(format!(
+        "the active web bundle {active} is damaged ({}), the embedded bundle could not be restored ({extract_err}) and no other intact bundle is installed; reinstall Voxelle or delete {} and restart",
+        problem.unwrap_or_default(),
+        cache.join("web_bundles").display()
+    ))
+}
+
+/// Called in `setup()` to pick the bundle to serve; see `ensure_intact_in`.
+/// A repair is logged, persisted as the new active version (with no channel)
+/// and emitted as EVENT_WEB_BUNDLE_REPAIRED.
+pub fn ensure_active_bundle(
+    app: &tauri::AppHandle,
+    embedded_zip: &[u8],
+    embedded_version: &str,
+    active_version: &str,
+) -> Result<(String, PathBuf, Option<BundleRepair>), String> {
+    let cache = cache_root(app)?;
+    let (version, dir, repair) = ensure_intact_in(&cache, embedded_zip, embedded_version, active_version)?;
+    if let Some(repair) = &repair {
+        tracing::warn!(
+            "web bundle {} failed its startup check ({}); now serving {}",
+            repair.broken_version, repair.problem, repair.activated_version
+        );
+        // Nothing to record for `rollback`: the broken version isn't one to go back to.
+        let plan = PlannedActivation {
+            version: version.clone(),
+            channel: None,
+            previous: String::new(),
+            previous_channel: None,
+            tmp_dir: None,
+            final_dir: dir.clone(),
+        };
+        activate_in(&cache, &plan)?;
+        record_history_in(&cache, UpdateHistoryEvent::Repair(repair.clone()));
+        let _ = app.emit(EVENT_WEB_BUNDLE_REPAIRED, repair);
+    }
+    Ok((version, dir, repair))
+}
+
+pub(crate) fn validate_bundle_version(version: &str) -> Result<String, String> {
+    let v = version.trim();
+    if v.is_empty() {
+        return Err("bundle version missing".into());
+    }
+    let parsed = semver::Version::parse(v).map_err(|_| "bundle version must be semver".to_string())?;
+    Ok(parsed.to_string())
+}
+
+fn create_unique_tmp_dir(final_dir: &Path) -> Result<PathBuf, String> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| "clock before unix epoch".to_string())?;
+    let nonce = now.as_nanos();
+    let pid = std::process::id();
+
+    let parent = final_dir.parent().ok_or_else(|| "bundle dir has no parent".to_string())?;
+    let base = final_dir
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "bundle".to_string());
+
+    for attempt in 0..32u32 {
+        let candidate = parent.join(format!(".tmp-{}-{}-{}-{}", base, pid, nonce, attempt));
+        match std::fs::create_dir(&candidate) {
+            Ok(()) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Err("failed to create unique temp dir".into())
+}
+
+/// Removes `.tmp-*` extraction dirs that a run which crashed mid-install left
+/// in `web_bundles`. Returns the names removed.
+pub fn sweep_orphaned_tmp_dirs(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
+    sweep_tmp_dirs_in(&cache_root(app)?)
+}
+
+fn sweep_tmp_dirs_in(cache: &Path) -> Result<Vec<String>, String> {
+    let Ok(entries) = std::fs::read_dir(cache.join("web_bundles")) else {
+        return Ok(Vec::new());
+    };
+    let mut removed = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(".tmp-") || !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        match std::fs::remove_dir_all(entry.path()) {
+            Ok(()) => removed.push(name),
+            Err(e) => tracing::warn!("failed to remove orphaned {name}: {e}"),
+        }
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+/// What extracting `zip` will take on disk, going by the sizes its entries declare.
+/// `extract_zip` still enforces its own limits on what is actually written.
+fn declared_uncompressed_size<R: Read + Seek>(zip: R) -> Result<u64, String> {
+    let mut z = zip::ZipArchive::new(zip).map_err(|e| e.to_string())?;
+    let mut total: u64 = 0;
+    for i in 0..z.len() {
+        let f = z.by_index_raw(i).map_err(|e| e.to_string())?;
+        total = total.saturating_add(f.size());
+    }
+    Ok(total)
+}
+
+fn ensure_space_for(declared: u64, available: u64) -> Result<(), WebUpdateError> {
+    let needed = declared.saturating_add(INSTALL_SPACE_SLACK);
+    if available < needed {
+        return Err(WebUpdateError::InsufficientSpace { needed, available });
+    }
+    Ok(())
+}
+
+fn stage_bundle_from_zip<R: Read + Seek>(
+    app: &tauri::AppHandle,
+    zip: R,
+    version: &str,
+    files: Option<&[BundleFile]>,
+) -> Result<PathBuf, String> {
+    stage_bundle_in(&cache_root(app)?, zip, version, files)
+}
+
+fn install_bundle_in<R: Read + Seek>(
+    cache: &Path,
+    zip: R,
+    version: &str,
+    files: Option<&[BundleFile]>,
+) -> Result<PathBuf, String> {
+    let final_dir = bundle_dir_in(cache, version);
+    if !final_dir.join("index.html").exists() {
+        move_into_place(&stage_bundle_in(cache, zip, version, files)?, &final_dir)?;
+    }
+    Ok(final_dir)
+}
+
+// Renames an extracted bundle to its final name, replacing anything partial there.
+fn move_into_place(tmp_dir: &Path, final_dir: &Path) -> Result<(), String> {
+    if final_dir.exists() {
+        let _ = std::fs::remove_dir_all(final_dir);
+    }
+    std::fs::rename(tmp_dir, final_dir).map_err(|e| e.to_string())
+}
+
+/// Extracts and checks `zip` in a fresh `.tmp-*` dir beside where `version`
+/// goes, and returns that dir. Moving it into place, over an installed copy if
+/// there is one, is left to the caller, so an activation can journal it.
+fn stage_bundle_in<R: Read + Seek>(
+    cache: &Path,
+    zip: R,
+    version: &str,
+    files: Option<&[BundleFile]>,
+) -> Result<PathBuf, String> {
+    let final_dir = bundle_dir_in(cache, version);
+    let base = cache.join("web_bundles");
+    std::fs::create_dir_all(&base).map_err(|e| e.to_string())?;
+    let base_can = base.canonicalize().map_err(|e| e.to_string())?;
+    if !final_dir.starts_with(&base_can) && !final_dir.starts_with(&base) {
+        return Err("bundle path invalid".into());
+    }
+
+    let tmp_dir = create_unique_tmp_dir(&final_dir)?;
+    // If we fail, try to clean up, but always return the original error.
+    let extracted = extract_zip(zip, &tmp_dir);
+    if let Err(e) = extracted {
+        let _ = std::fs::remove_dir_all(&tmp_dir);
+        return Err(e);
+    }
+    if !tmp_dir.join("index.html").exists() {
+        let _ = std::fs::remove_dir_all(&tmp_dir);
+        return Err("bundle missing index.html".into());
+    }
+    if let Some(files) = files {
+        if let Err(e) = verify_and_record_files(&tmp_dir, version, files) {
+            let _ = std::fs::remove_dir_all(&tmp_dir);
+            return Err(e);
+        }
+    }
+
+    Ok(tmp_dir)
+}
+
+fn validate_bundle_files(files: &[BundleFile]) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for f in files {
+        let p = Path::new(&f.path);
+        let normal = p.components().all(|c| matches!(c, std::path::Component::Normal(_)));
+        if f.path.is_empty() || f.path.contains('\\') || f.path.contains(':') || !normal {
+            return Err(format!("manifest file path invalid: {:?}", f.path));
+        }
+        if f.sha256.len() != 64 || !f.sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("manifest sha256 invalid for {}", f.path));
+        }
+        if !seen.insert(f.path.as_str()) {
+            return Err(format!("manifest lists {} twice", f.path));
+        }
+    }
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut h = Sha256::new();
+    std::io::copy(&mut file, &mut h).map_err(|e| e.to_string())?;
+    Ok(hex::encode(h.finalize()))
+}
+
+// Relative `/`-separated paths of every regular file under `dir`.
+fn list_files(dir: &Path) -> Result<Vec<String>, String> {
+    fn walk(root: &Path, dir: &Path, out: &mut Vec<String>) -> Result<(), String> {
+        for entry in std::fs::read_dir(dir).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let path = entry.path();
+            let ty = entry.file_type().map_err(|e| e.to_string())?;
+            if ty.is_dir() {
+                walk(root, &path, out)?;
+            } else {
+                let rel = path.strip_prefix(root).map_err(|e| e.to_string())?;
+                let parts: Vec<_> = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+                out.push(parts.join("/"));
+            }
+        }
+        Ok(())
+    }
+    let mut out = Vec::new();
+    walk(dir, dir, &mut out)?;
+    out.sort();
+    Ok(out)
+}
+
+/// Checks every listed file's size and sha256, and that nothing else is present.
+pub fn verify_bundle_files(dir: &Path, version: &str, files: &[BundleFile]) -> Result<BundleVerifyReport, String> {
+    let mut checks = Vec::with_capacity(files.len());
+    for f in files {
+        let path = dir.join(&f.path);
+        let status = match std::fs::metadata(&path) {
+            Ok(meta) if meta.is_file() => {
+                if meta.len() != f.size {
+                    FileCheckStatus::SizeMismatch { expected: f.size, actual: meta.len() }
+                } else if sha256_file(&path)? != f.sha256.to_lowercase() {
+                    FileCheckStatus::HashMismatch
+                } else {
+                    FileCheckStatus::Ok
+                }
+            }
+            _ => FileCheckStatus::Missing,
+        };
+        checks.push(FileCheck { path: f.path.clone(), status });
+    }
+
+    let listed: std::collections::HashSet<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    for rel in list_files(dir)? {
+        let name = rel.rsplit('/').next().unwrap_or(&rel);
+        if listed.contains(rel.as_str()) || BUNDLE_FILES_ALLOWLIST.contains(&name) {
+            continue;
+        }
+        checks.push(FileCheck { path: rel, status: FileCheckStatus::Unexpected });
+    }
+
+    let ok = checks.iter().all(|c| c.status == FileCheckStatus::Ok);
+    Ok(BundleVerifyReport { version: version.to_string(), ok, files: checks })
+}
+
+fn verify_and_record_files(dir: &Path, version: &str, files: &[BundleFile]) -> Result<(), String> {
+    validate_bundle_files(files)?;
+    let report = verify_bundle_files(dir, version, files)?;
+    if !report.ok {
+        let bad: Vec<_> = report
+            .files
+            .iter()
+            .filter(|c| c.status != FileCheckStatus::Ok)
+            .map(|c| c.path.as_str())
+            .collect();
+        return Err(format!("bundle file verification failed: {}", bad.join(", ")));
+    }
+    let json = serde_json::to_vec(files).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(BUNDLE_FILES_NAME), json).map_err(|e| e.to_string())
+}
+
+/// Re-verifies the active bundle against the file list it was installed with.
+#[tracing::instrument(skip_all)]
+pub fn verify_active(state: &WebUpdateState, app: &tauri::AppHandle) -> Result<BundleVerifyReport, String> {
+    let version = state.active_version.lock().map_err(|_| "active lock poisoned")?.clone();
+    let version = validate_bundle_version(&version)?;
+    let dir = active_bundle_path(app, &version)?;
+    let listing = std::fs::read(dir.join(BUNDLE_FILES_NAME))
+        .map_err(|_| format!("bundle {version} was installed without a file manifest"))?;
+    let files: Vec<BundleFile> = serde_json::from_slice(&listing).map_err(|e| e.to_string())?;
+    validate_bundle_files(&files)?;
+    verify_bundle_files(&dir, &version, &files)
+}
+
+fn extract_zip<R: Read + Seek>(zip: R, out_dir: &Path) -> Result<(), String> {
+    let mut z = zip::ZipArchive::new(zip).map_err(|e| e.to_string())?;
+    // Defensive limits: prevent zip bombs and pathological archives.
+    // A production web bundle should be far smaller than these. Precompressed
+    // `.gz`/`.br` siblings count toward them like any other file, so the
+    // entry and total budgets leave room for up to two siblings per asset.
+    let max_files: usize = 3 * 2048;
+    let max_total_uncompressed: u64 = 100 * 1024 * 1024;
+    let max_file_uncompressed: u64 = 10 * 1024 * 1024;
+    if z.len() > max_files {
+        return Err("zip contains too many entries".into());
+    }
+
+    let mut total_uncompressed: u64 = 0;
+    for i in 0..z.len() {
+        let f = z.by_index(i).map_err(|e| e.to_string())?;
+        let out_rel = safe_zip_entry_path(&f).ok_or_else(|| "zip entry path invalid".to_string())?;
+        let mode = extracted_mode(&out_rel, f.unix_mode(), f.is_dir())?;
+        if f.is_dir() {
+            let out_path = out_dir.join(out_rel);
+            std::fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
+            set_mode(&out_path, mode)?;
+            continue;
+        }
+
+        let out_path = out_dir.join(out_rel);
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut out = std::fs::File::create(&out_path).map_err(|e| e.to_string())?;
+        let mut limited = f.take(max_file_uncompressed.saturating_add(1));
+        let written = std::io::copy(&mut limited, &mut out).map_err(|e| e.to_string())?;
+        if written > max_file_uncompressed {
+            return Err("zip entry too large".into());
+        }
+        total_uncompressed = total_uncompressed.saturating_add(written);
+        if total_uncompressed > max_total_uncompressed {
+            return Err("zip expands too large".into());
+        }
+        drop(out);
+        set_mode(&out_path, mode)?;
+    }
+    check_extracted_tree(out_dir)
+}
+
+const S_IFMT: u32 = 0o170000;
+const S_IFREG: u32 = 0o100000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
+
+// Permissions an entry is extracted with: 0644, or 0755 for directories and
+// files the archive marks executable. Nothing else of the archive's mode
+// survives, setuid and setgid included. Symlinks fail the whole archive, since
+// a later entry could write through one to outside the bundle.
+fn extracted_mode(rel: &Path, unix_mode: Option<u32>, is_dir: bool) -> Result<u32, String> {
+    let mode = unix_mode.unwrap_or(0);
+    match mode & S_IFMT {
+        S_IFLNK => return Err(format!("zip entry {} is a symlink", rel.display())),
+        // No type bits: archivers that only record permissions.
+        0 | S_IFREG if !is_dir => {}
+        0 | S_IFDIR if is_dir => {}
+        _ => return Err(format!("zip entry {} is not a regular file or directory", rel.display())),
+    }
+    Ok(if is_dir || mode & 0o111 != 0 { 0o755 } else { 0o644 })
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).map_err(|e| e.to_string())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<(), String> {
+    Ok(())
+}
+
+// Double-checks what extraction left behind without following links: only
+// plain files and directories, all still inside `out_dir`.
+fn check_extracted_tree(out_dir: &Path) -> Result<(), String> {
+    fn walk(root: &Path, dir: &Path) -> Result<(), String> {
+        for entry in std::fs::read_dir(dir).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            let meta = std::fs::symlink_metadata(&path).map_err(|e| e.to_string())?;
+            let rel = path.strip_prefix(root).unwrap_or(&path).display().to_string();
+            if meta.file_type().is_symlink() {
+                return Err(format!("extracted {rel} is a symlink"));
+            }
+            if !path.canonicalize().map_err(|e| e.to_string())?.starts_with(root) {
+                return Err(format!("extracted {rel} is outside the bundle"));
+            }
+            if meta.is_dir() {
+                walk(root, &path)?;
+            } else if !meta.is_file() {
+                return Err(format!("extracted {rel} is not a regular file"));
+            }
+        }
+        Ok(())
+    }
+    let root = out_dir.canonicalize().map_err(|e| e.to_string())?;
+    walk(&root, &root)
+}
+
+fn safe_zip_entry_path(f: &ZipFile<'_>) -> Option<PathBuf> {
+    // `enclosed_name` rejects absolute paths and `..` traversal.
+    let p = f.enclosed_name()?.to_path_buf();
+    // Extra hardening: ignore Windows-y or weird paths that can sneak through.
+    let s = p.to_string_lossy();
+    if s.contains('\\') || s.contains(':') {
+        return None;
+    }
+    // Limit pathological path lengths and components.
+    if s.len() > 1024 {
+        return None;
+    }
+    for c in p.components() {
+        let std::path::Component::Normal(os) = c else { continue };
+        if os.to_string_lossy().len() > 255 {
+            return None;
+        }
+    }
+    Some(p)
+}
+
+fn parse_version(s: &str) -> Option<semver::Version> {
+    semver::Version::parse(s.trim()).ok()
+}
+
+/// The feed's `min_version`, normalized, if it is set and `active` is below it.
+/// An unparseable `min_version` is logged and ignored rather than blocking anyone.
+fn required_min_version(active: &str, min_version: Option<&str>) -> Option<String> {
+    let raw = min_version.filter(|v| !v.trim().is_empty())?;
+    let Some(min) = parse_version(raw) else {
+        tracing::warn!("ignoring manifest min_version {raw:?}: not semver");
+        return None;
+    };
+    parse_version(active).filter(|a| *a < min).map(|_| min.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ManifestCache {
+    /// Normalized feed URL the manifest was fetched from.
+    feed_url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    manifest: WebBundleManifestV1,
+}
+
+// Only an entry for the same normalized URL is usable, so switching feed or
+// channel never revalidates against another feed's validators.
+fn load_manifest_cache_in(cache: &Path, feed_url: &str) -> Option<ManifestCache> {
+    std::fs::read(cache.join(MANIFEST_CACHE_FILE))
+        .ok()
+        .and_then(|b| serde_json::from_slice::<ManifestCache>(&b).ok())
+        .filter(|c| c.feed_url == feed_url)
+}
+
+fn save_manifest_cache_in(cache: &Path, entry: &ManifestCache) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(entry).map_err(|e| e.to_string())?;
+    std::fs::write(cache.join(MANIFEST_CACHE_FILE), json).map_err(|e| e.to_string())
+}
+
+fn conditional_headers(entry: Option<&ManifestCache>) -> Vec<(reqwest::header::HeaderName, String)> {
+    let Some(entry) = entry else {
+        return Vec::new();
+    };
+    let mut headers = Vec::new();
+    if let Some(etag) = &entry.etag {
+        headers.push((reqwest::header::IF_NONE_MATCH, etag.clone()));
+    }
+    if let Some(lm) = &entry.last_modified {
+        headers.push((reqwest::header::IF_MODIFIED_SINCE, lm.clone()));
+    }
+    headers
+}
+
+async fn fetch_manifest(
+    client: &reqwest::Client,
+    cache: &Path,
+    url: &str,
+    channel: UpdateChannel,
+    attempts: u32,
+) -> Result<WebBundleManifestV1, WebUpdateError> {
+    with_retries("manifest fetch", attempts, RETRY_BASE_DELAY, move || fetch_manifest_once(client, cache, url, channel)).await
+}
+
+/// Tries `feeds` in order and returns the first manifest that fetches and
+/// validates, with the feed it came from. Each feed tried is appended to `tried`.
+/// When every feed fails, the first non-transient error wins over a flaky one,
+/// so a broken feed isn't hidden behind one that is merely offline.
+async fn fetch_first_manifest(
+    client: &reqwest::Client,
+    cache: &Path,
+    feeds: &[String],
+    channel: UpdateChannel,
+    attempts: u32,
+    tried: &mut Vec<FeedAttempt>,
+) -> Result<(WebBundleManifestV1, String), WebUpdateError> {
+    let mut failure: Option<WebUpdateError> = None;
+    for feed in feeds {
+        let url = normalize_feed_url(feed, channel);
+        match fetch_manifest(client, cache, feed, channel, attempts).await {
+            Ok(m) => {
+                tried.push(FeedAttempt { feed: feed.clone(), url, error: None });
+                return Ok((m, feed.clone()));
+            }
+            Err(e) => {
+                tracing::warn!("web update feed {url} failed: {e}");
+                tried.push(FeedAttempt { feed: feed.clone(), url, error: Some(e.to_string()) });
+                if failure.as_ref().is_none_or(WebUpdateError::is_transient) {
+                    failure = Some(e);
+                }
+            }
+        }
+    }
+    Err(failure.unwrap_or(WebUpdateError::FeedNotSet))
+}
+
+async fn fetch_manifest_once(client: &reqwest::Client, cache: &Path, url: &str, channel: UpdateChannel) -> Result<WebBundleManifestV1, WebUpdateError> {
+    let feed = normalize_feed_url(url, channel);
+    let cached = load_manifest_cache_in(cache, &feed);
+    let mut req = client.get(&feed).timeout(SMALL_REQUEST_TIMEOUT);
+    for (name, value) in conditional_headers(cached.as_ref()) {
+        req = req.header(name, value);
+    }
+    let resp = req.send().await?;
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(c) = cached {
+            return Ok(c.manifest);
+        }
+    }
+    if !resp.status().is_success() {
+        return Err(WebUpdateError::HttpStatus(resp.status().as_u16()));
+    }
+    let header = |name: reqwest::header::HeaderName| {
+        resp.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+    };
+    let etag = header(reqwest::header::ETAG);
+    let last_modified = header(reqwest::header::LAST_MODIFIED);
+    let m = resp.json::<WebBundleManifestV1>().await?;
+    validate_manifest(&m).map_err(WebUpdateError::ManifestInvalid)?;
+    if etag.is_some() || last_modified.is_some() {
+        let entry = ManifestCache { feed_url: feed, etag, last_modified, manifest: m.clone() };
+        if let Err(e) = save_manifest_cache_in(cache, &entry) {
+            tracing::warn!("failed to cache web manifest: {e}");
+        }
+    }
+    Ok(m)
+}
+
+fn validate_manifest(m: &WebBundleManifestV1) -> Result<(), String> {
+    if m.v != 1 {
+        return Err("manifest.v must be 1".into());
+    }
+    if m.version.trim().is_empty() || m.zip_url.trim().is_empty() || m.sha256.trim().is_empty() {
+        return Err("manifest missing fields".into());
+    }
+    if let Some(files) = &m.files {
+        validate_bundle_files(files)?;
+    }
+    if m.notes.as_ref().is_some_and(|n| n.len() > MAX_NOTES_BYTES) {
+        return Err(format!("manifest notes exceed {} KB", MAX_NOTES_BYTES / 1024));
+    }
+    if let Some(u) = &m.notes_url {
+        validate_notes_url(u)?;
+    }
+    // Also acts as path-hardening (bundle dir names should be safe).
+    let _ = validate_bundle_version(&m.version)?;
+    Ok(())
+}
+
+fn validate_notes_url(url: &str) -> Result<(), String> {
+    match reqwest::Url::parse(url.trim()) {
+        Ok(u) if matches!(u.scheme(), "http" | "https") => Ok(()),
+        _ => Err("manifest notes_url must be an http(s) URL".into()),
+    }
+}
+
+fn notes_content_type_ok(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    matches!(mime.as_str(), "text/plain" | "text/markdown" | "text/x-markdown")
+}
+
+/// Downloads release notes from a manifest's `notes_url`. Anything that isn't
+/// UTF-8 plain text or markdown, or is over `MAX_NOTES_BYTES`, is refused
+/// rather than shown partially.
+async fn fetch_notes(client: &reqwest::Client, url: &str) -> Result<String, WebUpdateError> {
+    validate_notes_url(url).map_err(WebUpdateError::ManifestInvalid)?;
+    let mut resp = client.get(url.trim()).timeout(SMALL_REQUEST_TIMEOUT).send().await?;
+    if !resp.status().is_success() {
+        return Err(WebUpdateError::HttpStatus(resp.status().as_u16()));
+    }
+    let content_type = resp.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or("").to_string();
+    if !notes_content_type_ok(&content_type) {
+        return Err(WebUpdateError::NotesNotText(if content_type.is_empty() { "no content type".into() } else { content_type }));
+    }
+    if resp.content_length().is_some_and(|len| len > MAX_NOTES_BYTES as u64) {
+        return Err(WebUpdateError::NotesTooLarge);
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        if body.len() + chunk.len() > MAX_NOTES_BYTES {
+            return Err(WebUpdateError::NotesTooLarge);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    String::from_utf8(body).map_err(|_| WebUpdateError::NotesNotText(format!("{content_type} (not UTF-8)")))
+}
+
+pub async fn fetch_release_notes(state: &WebUpdateState, url: &str) -> Result<String, WebUpdateError> {
+    let client = &http_client(state);
+    with_retries("release notes fetch", retry_attempts(state), RETRY_BASE_DELAY, move || fetch_notes(client, url)).await
+}
+
+fn normalize_feed_url(feed: &str, channel: UpdateChannel) -> String {
+    let s = feed.trim();
+    if s.is_empty() {
+        return "".to_string();
+    }
+
+    // Shorthand: `gh:owner/repo` (or `github:owner/repo`) resolves to the latest GitHub Release asset:
+    // https://github.com/<owner>/<repo>/releases/latest/download/voxelle-web-manifest.json
+    // Non-stable channels use `voxelle-web-manifest-<channel>.json`.
+    if let Some(rest) = s.strip_prefix("gh:").or_else(|| s.strip_prefix("github:")) {
+        let slug = rest.trim().trim_matches('/');
+        if !slug.is_empty() {
+            let asset = match channel {
+                UpdateChannel::Stable => "voxelle-web-manifest.json".to_string(),
+                c => format!("voxelle-web-manifest-{}.json", c.as_str()),
+            };
+            return format!("https://github.com/{}/releases/latest/download/{}", slug, asset);
+        }
+    }
+
+    // Explicit URLs may carry the channel as a `{channel}` token.
+    s.replace("{channel}", channel.as_str())
+}
+
+// GitHub serves release assets from github.com and redirects the download to
+// its asset CDN, so feeds on github.com may use these too.
+const GITHUB_ASSET_HOSTS: [&str; 3] = ["github.com", "objects.githubusercontent.com", "release-assets.githubusercontent.com"];
+
+/// A hostname on its own: no scheme, port, path or whitespace.
+pub(crate) fn is_bare_host(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 253
+        && host.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+        && !host.starts_with(['.', '-'])
+}
+
+/// The hosts a feed's bundle and delta URLs may point at: the feed's own host,
+/// GitHub's asset hosts for feeds on github.com, and the configured extras.
+#[derive(Debug, Clone, PartialEq)]
+struct TrustedOrigins {
+    feed_host: String,
+    hosts: Vec<String>,
+    /// Plain http is only accepted in debug builds, for local test feeds.
+    allow_http: bool,
+}
+
+impl TrustedOrigins {
+    fn for_feed(feed: &str, channel: UpdateChannel, extra: &[String], allow_http: bool) -> Self {
+        let feed_host = reqwest::Url::parse(&normalize_feed_url(feed, channel))
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_ascii_lowercase))
+            .unwrap_or_default();
+        let mut hosts = vec![feed_host.clone()];
+        if feed_host == "github.com" {
+            hosts.extend(GITHUB_ASSET_HOSTS.iter().map(|h| h.to_string()));
+        }
+        hosts.extend(extra.iter().map(|h| h.trim().to_ascii_lowercase()));
+        hosts.retain(|h| !h.is_empty());
+        Self { feed_host, hosts, allow_http }
+    }
+
+    fn check(&self, url: &reqwest::Url) -> Result<(), WebUpdateError> {
+        let zip_host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let https = url.scheme() == "https";
+        let scheme_ok = https || (self.allow_http && url.scheme() == "http");
+        if scheme_ok && self.hosts.contains(&zip_host) {
+            return Ok(());
+        }
+        Err(WebUpdateError::UntrustedZipOrigin { zip_host, feed_host: self.feed_host.clone(), https })
+    }
+
+    fn check_str(&self, url: &str) -> Result<(), WebUpdateError> {
+        let parsed = reqwest::Url::parse(url.trim()).map_err(|e| WebUpdateError::ManifestInvalid(format!("bundle url {url:?}: {e}")))?;
+        self.check(&parsed)
+    }
+}
+
+// Within a channel only newer versions are offered. After switching channels the
+// new channel's latest is offered whatever its version, and flagged if it is older.
+fn compare_offer(
+    active: &str,
+    active_channel: Option<UpdateChannel>,
+    offered: &str,
+    channel: UpdateChannel,
+) -> (bool, bool) {
+    match (parse_version(active), parse_version(offered)) {
+        (Some(a), Some(n)) => {
+            let switched = active_channel.is_some_and(|c| c != channel);
+            if switched {
+                (n != a, n < a)
+            } else {
+                (n > a, false)
+            }
+        }
+        _ => (offered.trim() != active.trim(), false),
+    }
+}
+
+// Versions that aren't semver are only told apart from the active one, and
+// anything different counts as newer, as in `compare_offer`.
+fn version_relation(active: &str, offered: &str) -> VersionRelation {
+    let ordering = match (parse_version(active), parse_version(offered)) {
+        (Some(a), Some(n)) => n.cmp(&a),
+        _ if offered.trim() == active.trim() => std::cmp::Ordering::Equal,
+        _ => std::cmp::Ordering::Greater,
+    };
+    match ordering {
+        std::cmp::Ordering::Greater => VersionRelation::Newer,
+        std::cmp::Ordering::Equal => VersionRelation::Same,
+        std::cmp::Ordering::Less => VersionRelation::Older,
+    }
+}
+
+fn current_channel(state: &WebUpdateState) -> UpdateChannel {
+    state.settings.lock().map(|g| g.channel).unwrap_or_default()
+}
+
+fn active_channel(state: &WebUpdateState) -> Option<UpdateChannel> {
+    state.active_channel.lock().ok().and_then(|g| *g)
+}
+
+pub fn status(state: &WebUpdateState) -> WebUpdateStatus {
+    let last_check = state.last_check.lock().ok().and_then(|g| g.clone());
+    let feed_urls = state.feed_urls.lock().map(|g| g.clone()).unwrap_or_default();
+    let decisions = crate::auto_update::get_decisions(state);
+    WebUpdateStatus {
+        active_version: state.active_version.lock().map(|g| g.clone()).unwrap_or_default(),
+        active_channel: active_channel(state),
+        channel: current_channel(state),
+        feed_url: feed_urls.first().cloned().unwrap_or_default(),
+        feed_urls,
+        port: state.server.port(),
+        last_checked_at: last_check.as_ref().map(|c| c.checked_at),
+        last_check_source: last_check.as_ref().and_then(|c| c.source.clone()),
+        last_check_result: last_check.map(|c| c.result),
+        repair: state.repair.clone(),
+        last_activation: state.last_activation.lock().ok().and_then(|g| g.clone()),
+        skipped_version: decisions.skipped_version,
+        snoozed_until: decisions.snoozed_until,
+        dev_override: dev_override(state),
+        operation_in_progress: state.operation.current(),
+    }
+}
+
+pub(crate) fn unix_ms_now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+pub fn load_persisted_last_check(app: &tauri::AppHandle) -> Result<Option<LastCheck>, String> {
+    Ok(load_last_check_in(&cache_root(app)?))
+}
+
+fn load_last_check_in(cache: &Path) -> Option<LastCheck> {
+    std::fs::read(cache.join(LAST_CHECK_FILE)).ok().and_then(|b| serde_json::from_slice(&b).ok())
+}
+
+pub fn last_check_details(state: &WebUpdateState) -> Option<LastCheck> {
+    state.last_check.lock().ok().and_then(|g| g.clone())
+}
+
+/// Appends to the update history, rotating it once it is full. The history is
+/// for diagnostics, so a failed write is only logged and never fails what it
+/// describes.
+fn record_history_in(cache: &Path, event: UpdateHistoryEvent) -> UpdateHistoryEntry {
+    let entry = UpdateHistoryEntry { at: unix_ms_now(), event };
+    let written = serde_json::to_value(&entry)
+        .map_err(anyhow::Error::from)
+        .and_then(|v| isnad::append_jsonl_rotating(&cache.join(HISTORY_FILE), &v, HISTORY_MAX_BYTES));
+    if let Err(e) = written {
+        tracing::warn!("recording web update history failed: {e:#}");
+    }
+    entry
+}
+
+/// Newest first, across the current and the rotated file. Lines that don't
+/// parse (a torn write, an event from a newer build) are skipped.
+fn read_history_in(cache: &Path, limit: usize) -> Vec<UpdateHistoryEntry> {
+    let current = cache.join(HISTORY_FILE);
+    [isnad::rotated_jsonl_path(&current), current]
+        .iter()
+        .rev()
+        .flat_map(|path| {
+            let text = read_text_file(path);
+            text.lines().rev().filter_map(|line| serde_json::from_str(line).ok()).collect::<Vec<_>>()
+        })
+        .take(limit)
+        .collect()
+}
+
+fn last_activation_in(cache: &Path) -> Option<UpdateHistoryEntry> {
+    read_history_in(cache, usize::MAX)
+        .into_iter()
+        .find(|e| matches!(e.event, UpdateHistoryEvent::Activation { .. }))
+}
+
+pub fn load_persisted_last_activation(app: &tauri::AppHandle) -> Result<Option<UpdateHistoryEntry>, String> {
+    Ok(last_activation_in(&cache_root(app)?))
+}
+
+pub fn history(app: &tauri::AppHandle, limit: usize) -> Result<Vec<UpdateHistoryEntry>, String> {
+    Ok(read_history_in(&cache_root(app)?, limit))
+}
+
+fn last_check_result(result: &Result<WebUpdateCheckResult, WebUpdateError>) -> LastCheckResult {
+    match result {
+        Ok(r) if r.available => LastCheckResult::Available {
+            version: r.version.clone().unwrap_or_default(),
+            downgrade: r.downgrade,
+        },
+        Ok(r) if r.skipped => LastCheckResult::Skipped { version: r.version.clone().unwrap_or_default() },
+        Ok(_) => LastCheckResult::UpToDate,
+        Err(e) => LastCheckResult::Failed { error: e.to_string() },
+    }
+}
+
+fn record_check(state: &WebUpdateState, cache: &Path, result: &Result<WebUpdateCheckResult, WebUpdateError>, tried: Vec<FeedAttempt>) {
+    let source = result.as_ref().ok().and_then(|r| r.source.clone());
+    let entry = LastCheck { checked_at: unix_ms_now(), result: last_check_result(result), source, sources: tried };
+    if let Ok(json) = serde_json::to_vec_pretty(&entry) {
+        let _ = std::fs::write(cache.join(LAST_CHECK_FILE), json);
+    }
+    if let Ok(mut g) = state.last_check.lock() {
+        *g = Some(entry);
+    }
+}
+
+pub fn set_feed(state: &WebUpdateState, app: &tauri::AppHandle, urls: FeedUrls) -> Result<(), String> {
+    let urls = parse_feed_list(&urls.into_vec().join("\n"));
+    persist_feed_urls(app, &urls)?;
+    if let Ok(mut g) = state.feed_urls.lock() {
+        *g = urls;
+    }
+    Ok(())
+}
+
+fn record_check_history(cache: &Path, result: &Result<WebUpdateCheckResult, WebUpdateError>, started: std::time::Instant) {
+    let source = result.as_ref().ok().and_then(|r| r.source.clone());
+    let latency_ms = started.elapsed().as_millis() as u64;
+    record_history_in(cache, UpdateHistoryEvent::Check { result: last_check_result(result), source, latency_ms });
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn check(state: &WebUpdateState, app: &tauri::AppHandle) -> Result<WebUpdateCheckResult, WebUpdateError> {
+    let _op = state.operation.try_begin(UpdateOperation::Check)?;
+    let cache = cache_root(app)?;
+    let mut tried = Vec::new();
+    let started = std::time::Instant::now();
+    let deadline = tokio::time::Instant::now() + CHECK_DEADLINE;
+    let result = within(deadline, UpdatePhase::Manifest, check_in(state, &cache, &mut tried)).await;
+    record_check_history(&cache, &result, started);
+    record_check(state, &cache, &result, tried);
+    result
+}
+
+/// Like `check`, but a transient failure (offline, timeout, 5xx) is not
+/// recorded, so a flaky network doesn't replace the last real result.
+#[tracing::instrument(skip_all)]
+pub async fn check_in_background(state: &WebUpdateState, app: &tauri::AppHandle) -> Result<WebUpdateCheckResult, WebUpdateError> {
+    let _op = state.operation.try_begin(UpdateOperation::Check)?;
+    let cache = cache_root(app)?;
+    let mut tried = Vec::new();
+    let started = std::time::Instant::now();
+    let deadline = tokio::time::Instant::now() + BACKGROUND_CHECK_DEADLINE;
+    let result = within(deadline, UpdatePhase::Manifest, check_in(state, &cache, &mut tried)).await;
+    // Transient failures stay out of the last check, but the history shows them.
+    record_check_history(&cache, &result, started);
+    if !result.as_ref().is_err_and(WebUpdateError::is_transient) {
+        record_check(state, &cache, &result, tried);
+    }
+    result
+}
+
+fn http_client(state: &WebUpdateState) -> reqwest::Client {
+    state.client.lock().map(|g| g.clone()).unwrap_or_default()
+}
+
+fn retry_attempts(state: &WebUpdateState) -> u32 {
+    state.settings.lock().map(|g| g.retry_attempts).unwrap_or(DEFAULT_RETRY_ATTEMPTS)
+}
+
+async fn check_in(state: &WebUpdateState, cache: &Path, tried: &mut Vec<FeedAttempt>) -> Result<WebUpdateCheckResult, WebUpdateError> {
+    let feeds = state.feed_urls.lock().map_err(|_| "feed lock poisoned".to_string())?.clone();
+    let channel = current_channel(state);
+    let active = state
+        .active_version
+        .lock()
+        .map_err(|_| "active lock poisoned".to_string())?
+        .clone();
+    if feeds.is_empty() {
+        return Ok(WebUpdateCheckResult {
+            available: false,
+            skipped: false,
+            downgrade: false,
+            mandatory: false,
+            min_version: None,
+            channel,
+            source: None,
+            current_version: active,
+            relation: None,
+            version: None,
+            zip_url: None,
+            sha256: None,
+            notes: None,
+            notes_url: None,
+        });
+    }
+    let (m, source) = fetch_first_manifest(&http_client(state), cache, &feeds, channel, retry_attempts(state), tried).await?;
+
+    let (available, downgrade) = compare_offer(&active, active_channel(state), &m.version, channel);
+    let min_version = required_min_version(&active, m.min_version.as_deref());
+    // Only an offer that moves forward can satisfy the minimum.
+    let mandatory = min_version.is_some() && available && !downgrade;
+    if mandatory {
+        crate::auto_update::clear_decisions(state, cache);
+    }
+    let skipped = available && !mandatory && is_skipped(crate::auto_update::get_decisions(state).skipped_version.as_deref(), &m.version);
+
+    Ok(WebUpdateCheckResult {
+        available: available && !skipped,
+        skipped,
+        downgrade,
+        mandatory,
+        min_version,
+        channel,
+        source: Some(source),
+        relation: Some(version_relation(&active, &m.version)),
+        current_version: active,
+        version: Some(m.version),
+        zip_url: Some(m.zip_url),
+        sha256: Some(m.sha256),
+        // Inline notes make the URL redundant.
+        notes_url: m.notes_url.filter(|_| m.notes.is_none()),
+        notes: m.notes,
+    })
+}
+
+fn is_skipped(skipped_version: Option<&str>, offered: &str) -> bool {
+    skipped_version.and_then(parse_version).is_some_and(|v| parse_version(offered) == Some(v))
+}
+
+fn configured_feeds(state: &WebUpdateState) -> Result<Vec<String>, WebUpdateError> {
+    let feeds = state.feed_urls.lock().map_err(|_| "feed lock poisoned".to_string())?.clone();
+    if feeds.is_empty() {
+        return Err(WebUpdateError::FeedNotSet);
+    }
+    Ok(feeds)
+}
+
+// Activation entries don't say which way the version moved; this marks the
+// installs that didn't move it forward.
+fn install_direction(relation: VersionRelation, force: bool, active: &str, version: &str) -> Option<UpdateHistoryEvent> {
+    match relation {
+        VersionRelation::Newer => None,
+        VersionRelation::Same => force.then(|| UpdateHistoryEvent::Reinstall { version: version.to_string() }),
+        VersionRelation::Older => Some(UpdateHistoryEvent::Downgrade { from: active.to_string(), to: version.to_string() }),
+    }
+}
+
+fn confirm_offer(
+    active: &str,
+    active_channel: Option<UpdateChannel>,
+    offered: &str,
+    channel: UpdateChannel,
+    allow_downgrade: bool,
+) -> Result<(), WebUpdateError> {
+    let (_, downgrade) = compare_offer(active, active_channel, offered, channel);
+    if downgrade && !allow_downgrade {
+        return Err(WebUpdateError::DowngradeNotConfirmed { channel, offered: offered.to_string(), active: active.to_string() });
+    }
+    Ok(())
+}
+
+/// `budget` covers the manifest fetch and the download together; see
+/// `auto_update::download_deadline`. With `force` the offer is installed
+/// whatever its version, downloaded and checked against its sha256 again even
+/// if that version is already on disk.
+#[tracing::instrument(skip(state, app))]
+pub async fn download_and_activate(
+    state: &WebUpdateState,
+    app: &tauri::AppHandle,
+    allow_downgrade: bool,
+    force: bool,
+    apply: ApplyMode,
+    budget: std::time::Duration,
+) -> Result<WebUpdateDownloadResult, WebUpdateError> {
+    let _op = state.operation.try_begin(UpdateOperation::Download)?;
+    let deadline = tokio::time::Instant::now() + budget;
+    let feeds = configured_feeds(state)?;
+    let channel = current_channel(state);
+    let attempts = retry_attempts(state);
+    let client = http_client(state);
+    let cache = cache_root(app)?;
+    let mut tried = Vec::new();
+    let fetched = fetch_first_manifest(&client, &cache, &feeds, channel, attempts, &mut tried);
+    let (mut m, feed) = within(deadline, UpdatePhase::Manifest, fetched).await?;
+    m.version = validate_bundle_version(&m.version).map_err(WebUpdateError::ManifestInvalid)?;
+
+    let active = state.active_version.lock().map_err(|_| "active lock poisoned".to_string())?.clone();
+    confirm_offer(&active, active_channel(state), &m.version, channel, allow_downgrade || force)?;
+    let direction = install_direction(version_relation(&active, &m.version), force, &active, &m.version);
+    let extra_hosts = state.settings.lock().map(|g| g.trusted_zip_hosts.clone()).unwrap_or_default();
+    let trusted = TrustedOrigins::for_feed(&feed, channel, &extra_hosts, cfg!(debug_assertions));
+    trusted.check_str(&m.zip_url)?;
+
+    let final_dir = active_bundle_path(app, &m.version)?;
+    let mut staged = None;
+    if force || !final_dir.join("index.html").exists() {
+        let cache = cache_root(app)?;
+        let want_hex = m.sha256.trim().to_lowercase();
+        let part = download_path(&cache, &m.version, &want_hex, "zip").map_err(WebUpdateError::ManifestInvalid)?;
+        let max_bytes = u64::from(state.settings.lock().map(|g| g.max_download_mb).unwrap_or(DEFAULT_MAX_DOWNLOAD_MB)) * 1024 * 1024;
+        let received = std::sync::atomic::AtomicU64::new(0);
+        let progress = |kind| {
+            let (app, version, received) = (app.clone(), m.version.clone(), &received);
+            move |downloaded, total| {
+                received.store(downloaded, std::sync::atomic::Ordering::Relaxed);
+                let _ = app.emit(EVENT_WEB_UPDATE_PROGRESS, DownloadProgress { version: version.clone(), kind, downloaded, total });
+            }
+        };
+        let record_download = |kind, started: std::time::Instant, result: &Result<(), WebUpdateError>| {
+            record_history_in(&cache, UpdateHistoryEvent::Download {
+                version: m.version.clone(),
+                kind,
+                bytes: received.swap(0, std::sync::atomic::Ordering::Relaxed),
+                duration_ms: started.elapsed().as_millis() as u64,
+                error: result.as_ref().err().map(ToString::to_string),
+            });
+        };
+
+        let mut rebuilt = false;
+        if let (Some(delta), Some(base)) = (usable_delta(&m, &active), delta_base(state, &cache, &active)) {
+            let on_progress = progress(DownloadKind::Delta);
+            let target = DeltaTarget { version: &m.version, sha256: &want_hex, dest: &part, max_bytes, trusted: &trusted };
+            let started = std::time::Instant::now();
+            let result = within(deadline, UpdatePhase::Delta, download_delta(&client, &cache, delta, &base, &target, attempts, &on_progress)).await;
+            record_download(DownloadKind::Delta, started, &result);
+            match result {
+                Ok(()) => rebuilt = true,
+                // Anything wrong with the delta is only a missed shortcut.
+                Err(e) => tracing::warn!("delta update {} -> {} failed ({e}); downloading the full bundle", delta.from_version, m.version),
+            }
+        }
+        if !rebuilt {
+            let on_progress = progress(DownloadKind::Full);
+            // Network errors keep the partial file, so each retry resumes where the last one stopped.
+            let source = DownloadSource { url: &m.zip_url, trusted: &trusted };
+            let (client, source, part_path, want, on_progress) = (&client, &source, part.as_path(), want_hex.as_str(), &on_progress);
+            let started = std::time::Instant::now();
+            let download = with_retries("bundle download", attempts, RETRY_BASE_DELAY, move || download_zip(client, source, part_path, want, max_bytes, on_progress));
+            let result = within(deadline, UpdatePhase::Download, download).await;
+            record_download(DownloadKind::Full, started, &result);
+            result?;
+        }
+
+        // Checked before extracting so a nearly full disk fails up front instead of
+        // halfway through. The verified zip is kept for when space has been freed.
+        let declared = declared_uncompressed_size(std::fs::File::open(&part).map_err(|e| e.to_string())?).map_err(WebUpdateError::ExtractFailed)?;
+        let available = fs2::available_space(bundles_dir(app)?).map_err(|e| e.to_string())?;
+        ensure_space_for(declared, available)?;
+
+        // Extracted into a temp dir; it's renamed into place as the first step of
+        // activating, so a partial bundle is never served.
+        let zip = std::fs::File::open(&part).map_err(|e| e.to_string())?;
+        let installed = stage_bundle_from_zip(app, std::io::BufReader::new(zip), &m.version, m.files.as_deref());
+        match &installed {
+            Ok(_) => {
+                if let Err(e) = keep_source_zip_in(&cache, &m.version, &part, &active) {
+                    tracing::warn!("keeping the {} zip for delta updates failed: {e}", m.version);
+                    let _ = std::fs::remove_file(&part);
+                }
+            }
+            // A verified zip that fails to install won't do better next time; don't resume from it.
+            Err(_) => {
+                let _ = std::fs::remove_file(&part);
+            }
+        }
+        staged = Some(installed.map_err(WebUpdateError::ExtractFailed)?);
+    }
+    if apply == ApplyMode::OnNextLaunch {
+        // Nothing points at the bundle until the marker is written, so no journal is needed yet.
+        if let Some(tmp_dir) = &staged {
+            move_into_place(tmp_dir, &final_dir).map_err(WebUpdateError::ExtractFailed)?;
+        }
+        let cache = cache_root(app)?;
+        write_pending_in(&cache, &PendingActivation { version: m.version.clone(), channel: Some(channel) })?;
+        if let Some(event) = direction {
+            record_history_in(&cache, event);
+        }
+        return Ok(WebUpdateDownloadResult { activated_version: m.version, pending: true });
+    }
+    activate(state, app, &m.version, Some(channel), staged)?;
+    if let Some(event) = direction {
+        record_history_in(&cache_root(app)?, event);
+    }
+    if apply == ApplyMode::Immediate {
+        reload_after_grace(app, state.clone());
+    }
+    Ok(WebUpdateDownloadResult { activated_version: m.version, pending: false })
+}
+
+fn reload_after_grace(app: &tauri::AppHandle, state: WebUpdateState) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(RELOAD_GRACE).await;
+        if let Err(e) = reload_main_window(&state, &app) {
+            tracing::warn!("reloading after web update failed: {e}");
+        }
+    });
+}
+
+// Partial downloads live at `downloads/<version>-<sha256>.zip.part`, so a resumed
+// download can only continue the exact same artifact.
+fn source_zip_in(cache: &Path, version: &str) -> PathBuf {
+    cache.join(SOURCE_ZIPS_DIR).join(format!("{version}.zip"))
+}
+
+// Moves the verified zip `version` was installed from into place as the base
+// for its next delta, dropping every other kept zip but the active version's.
+fn keep_source_zip_in(cache: &Path, version: &str, zip: &Path, active: &str) -> Result<(), String> {
+    let dir = cache.join(SOURCE_ZIPS_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let kept = source_zip_in(cache, version);
+    std::fs::rename(zip, &kept).map_err(|e| e.to_string())?;
+    let active = source_zip_in(cache, active);
+    for entry in std::fs::read_dir(&dir).map_err(|e| e.to_string())?.flatten() {
+        let path = entry.path();
+        if path != kept && path != active {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    Ok(())
+}
+
+// The exact zip the active bundle came from, if it's still around.
+fn delta_base(state: &WebUpdateState, cache: &Path, active: &str) -> Option<std::borrow::Cow<'static, [u8]>> {
+    if active == state.embedded_version && !state.embedded_zip.is_empty() {
+        return Some(std::borrow::Cow::Borrowed(state.embedded_zip));
+    }
+    std::fs::read(source_zip_in(cache, active)).ok().map(std::borrow::Cow::Owned)
+}
+
+fn usable_delta<'a>(m: &'a WebBundleManifestV1, active: &str) -> Option<&'a BundleDelta> {
+    let delta = m.delta.as_ref()?;
+    let from = parse_version(&delta.from_version)?;
+    (delta.algorithm != DeltaAlgorithm::Unsupported && Some(from) == parse_version(active)).then_some(delta)
+}
+
+fn apply_delta(algorithm: DeltaAlgorithm, base: &[u8], patch: &[u8], max_bytes: u64) -> Result<Vec<u8>, WebUpdateError> {
+    let mut out = Vec::new();
+    match algorithm {
+        DeltaAlgorithm::ZstdDict => {
+            let decoder = zstd::stream::read::Decoder::with_dictionary(patch, base).map_err(|e| e.to_string())?;
+            decoder.take(max_bytes.saturating_add(1)).read_to_end(&mut out).map_err(|e| format!("delta patch invalid: {e}"))?;
+        }
+        DeltaAlgorithm::Bsdiff => {
+            bsdiff::patch(base, &mut Cursor::new(patch), &mut out).map_err(|e| format!("delta patch invalid: {e}"))?;
+        }
+        DeltaAlgorithm::Unsupported => return Err("delta algorithm unsupported".to_string().into()),
+    }
+    if out.len() as u64 > max_bytes {
+        return Err(WebUpdateError::TooLarge);
+    }
+    Ok(out)
+}
+
+// Applies `patch` to `base` and checks the result is the zip the manifest
+// describes; a patch made from a different base fails here.
+fn rebuild_from_delta(algorithm: DeltaAlgorithm, base: &[u8], patch: &[u8], sha256: &str, max_bytes: u64) -> Result<Vec<u8>, WebUpdateError> {
+    let zip = apply_delta(algorithm, base, patch, max_bytes)?;
+    if !voxelle_protocol::verify_hex_digest(sha256, &Sha256::digest(&zip)) {
+        return Err(WebUpdateError::HashMismatch);
+    }
+    Ok(zip)
+}
+
+// The full zip a delta download has to reproduce at `dest`.
+struct DeltaTarget<'a> {
+    version: &'a str,
+    sha256: &'a str,
+    dest: &'a Path,
+    max_bytes: u64,
+    trusted: &'a TrustedOrigins,
+}
+
+async fn download_delta(
+    client: &reqwest::Client,
+    cache: &Path,
+    delta: &BundleDelta,
+    base: &[u8],
+    target: &DeltaTarget<'_>,
+    attempts: u32,
+    on_progress: OnProgress<'_>,
+) -> Result<(), WebUpdateError> {
+    let patch_sha = delta.sha256.trim().to_lowercase();
+    let patch_path = download_path(cache, target.version, &patch_sha, "patch").map_err(WebUpdateError::ManifestInvalid)?;
+    let source = DownloadSource { url: &delta.patch_url, trusted: target.trusted };
+    let (source, path, sha, max_bytes) = (&source, patch_path.as_path(), patch_sha.as_str(), target.max_bytes);
+    with_retries("delta download", attempts, RETRY_BASE_DELAY, move || download_zip(client, source, path, sha, max_bytes, on_progress)).await?;
+
+    let patch = std::fs::read(&patch_path).map_err(|e| e.to_string());
+    let _ = std::fs::remove_file(&patch_path);
+    let zip = rebuild_from_delta(delta.algorithm, base, &patch?, target.sha256, target.max_bytes)?;
+    std::fs::write(target.dest, zip).map_err(|e| e.to_string().into())
+}
+
+// `ext` tells a bundle zip from a delta patch for the same version.
+fn download_path(cache: &Path, version: &str, sha256: &str, ext: &str) -> Result<PathBuf, String> {
+    if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("manifest sha256 invalid".into());
+    }
+    let dir = cache.join("downloads");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(format!("{}-{}.{ext}.part", validate_bundle_version(version)?, sha256)))
+}
+
+// A 206 only continues our partial file if it starts exactly where the file ends.
+fn resume_accepted(status: u16, content_range: Option<&str>, offset: u64) -> bool {
+    let Some(range) = content_range.and_then(|r| r.trim().strip_prefix("bytes ")) else {
+        return false;
+    };
+    let start = range.split(['-', '/']).next().and_then(|s| s.trim().parse::<u64>().ok());
+    status == 206 && start == Some(offset)
+}
+
+fn sha256_prefix(file: &mut std::fs::File) -> Result<(Sha256, u64), String> {
+    let mut h = Sha256::new();
+    file.rewind().map_err(|e| e.to_string())?;
+    let n = std::io::copy(file, &mut h).map_err(|e| e.to_string())?;
+    Ok((h, n))
+}
+
+// Called with the bytes written so far (resumed ones included) and the expected total.
+type OnProgress<'a> = &'a (dyn Fn(u64, Option<u64>) + Sync);
+
+// A download URL and the hosts it may be served from, redirects included.
+struct DownloadSource<'a> {
+    url: &'a str,
+    trusted: &'a TrustedOrigins,
+}
+
+/// Streams `url` into `dest`, hashing as it goes, resuming from an existing
+/// partial file when the server honours `Range`. The file is kept on network
+/// errors so the next attempt can continue it, and deleted when the finished
+/// download doesn't match `sha256`.
+async fn download_zip(
+    client: &reqwest::Client,
+    source: &DownloadSource<'_>,
+    dest: &Path,
+    sha256: &str,
+    max_bytes: u64,
+    on_progress: OnProgress<'_>,
+) -> Result<(), WebUpdateError> {
+    let resumed = dest.exists();
+    match download_zip_once(client, source, dest, sha256, max_bytes, resumed, on_progress).await {
+        // The partial file may have been from a different artifact after all; start over once.
+        Err(e) if resumed && !dest.exists() => {
+            tracing::warn!("resumed web bundle download failed ({e}); restarting");
+            download_zip_once(client, source, dest, sha256, max_bytes, false, on_progress).await
+        }
+        r => r,
+    }
+}
+
+async fn download_zip_once(
+    client: &reqwest::Client,
+    source: &DownloadSource<'_>,
+    dest: &Path,
+    sha256: &str,
+    max_bytes: u64,
+    resume: bool,
+    on_progress: OnProgress<'_>,
+) -> Result<(), WebUpdateError> {
+    source.trusted.check_str(source.url)?;
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(!resume)
+        .open(dest)
+        .map_err(|e| e.to_string())?;
+    let (mut hasher, mut written) = if resume { sha256_prefix(&mut file)? } else { (Sha256::new(), 0) };
+
+    let mut req = client.get(source.url);
+    if written > 0 {
+        req = req.header(reqwest::header::RANGE, format!("bytes={}-", written));
+    }
+    let mut resp = req.send().await?;
+    // Redirects are followed by the client, so where we ended up is checked too.
+    source.trusted.check(resp.url())?;
+    let status = resp.status();
+    let content_range = resp
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    if written > 0 && !resume_accepted(status.as_u16(), content_range.as_deref(), written) {
+        if status.as_u16() == 416 || !status.is_success() {
+            // Nothing usable to resume from; the retry in `download_zip` starts fresh.
+            drop(file);
+            let _ = std::fs::remove_file(dest);
+            return Err(WebUpdateError::HttpStatus(status.as_u16()));
+        }
+        // Server ignored the Range header and sent the whole file.
+        file.set_len(0).map_err(|e| e.to_string())?;
+        hasher = Sha256::new();
+        written = 0;
+    } else if !status.is_success() {
+        return Err(WebUpdateError::HttpStatus(status.as_u16()));
+    }
+    file.seek(std::io::SeekFrom::Start(written)).map_err(|e| e.to_string())?;
+
+    if resp.content_length().is_some_and(|len| written.saturating_add(len) > max_bytes) {
+        drop(file);
+        let _ = std::fs::remove_file(dest);
+        return Err(WebUpdateError::TooLarge);
+    }
+    let total = resp.content_length().map(|len| written.saturating_add(len));
+    let mut reported = written;
+    on_progress(written, total);
+    while let Some(chunk) = resp.chunk().await? {
+        written = written.saturating_add(chunk.len() as u64);
+        if written > max_bytes {
+            drop(file);
+            let _ = std::fs::remove_file(dest);
+            return Err(WebUpdateError::TooLarge);
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk).map_err(|e| e.to_string())?;
+        if written - reported >= PROGRESS_STEP {
+            on_progress(written, total);
+            reported = written;
+        }
+    }
+    if written != reported {
+        on_progress(written, total);
+    }
+    file.sync_all().map_err(|e| e.to_string())?;
+
+    if !voxelle_protocol::verify_hex_digest(sha256, &hasher.finalize()) {
+        drop(file);
+        let _ = std::fs::remove_file(dest);
+        return Err(WebUpdateError::HashMismatch);
+    }
+    Ok(())
+}
+
+// `staged` is a bundle `stage_bundle_in` extracted for `version`, to be moved
+// into place over any installed copy.
+#[tracing::instrument(skip(state, app, staged))]
+fn activate(
+    state: &WebUpdateState,
+    app: &tauri::AppHandle,
+    version: &str,
+    channel: Option<UpdateChannel>,
+    staged: Option<PathBuf>,
+) -> Result<(), String> {
+    let cache = cache_root(app)?;
+    let current = state.active_version.lock().map_err(|_| "active lock poisoned")?.clone();
+    let dir = bundle_dir_in(&cache, version);
+    // The in-memory state and the served root only change once this is on disk.
+    activate_in(
+        &cache,
+        &PlannedActivation {
+            version: version.to_string(),
+            channel,
+            previous: current.clone(),
+            previous_channel: active_channel(state),
+            tmp_dir: staged,
+            final_dir: dir.clone(),
+        },
+    )?;
+    let entry = record_history_in(&cache, UpdateHistoryEvent::Activation { version: version.to_string(), channel, previous: current });
+    if let Ok(mut g) = state.last_activation.lock() {
+        *g = Some(entry);
+    }
+    if let Ok(mut g) = state.active_version.lock() {
+        *g = version.to_string();
+    }
+    if let Ok(mut g) = state.active_channel.lock() {
+        *g = channel;
+    }
+    state.server.update_config(|c| {
+        c.active_version = version.to_string();
+        c.active_channel = channel;
+    });
+    // A directory override keeps being served; clearing it picks this bundle up.
+    if !matches!(dev_override(state), Some(DevOverride::Dir(_))) {
+        state.server.set_root(dir);
+    }
+    tracing::info!("activated web bundle {version}");
+    let _ = app.emit(EVENT_WEB_UPDATE_READY, version.to_string());
+
+    // Pruning is housekeeping; it must never fail an activation.
+    let keep = load_persisted_keep_bundles(app).unwrap_or(DEFAULT_KEEP_BUNDLES);
+    if let Err(e) = prune_bundles(app, keep) {
+        tracing::warn!("web bundle prune failed: {e}");
+    }
+    Ok(())
+}
+
+/// Switches back to the bundle that was active before the last activation.
+/// Rolling back twice returns to where you started.
+#[tracing::instrument(skip_all)]
+pub fn rollback(state: &WebUpdateState, app: &tauri::AppHandle) -> Result<WebUpdateRollbackResult, RollbackError> {
+    let _op = state.operation.try_begin(UpdateOperation::Rollback)?;
+    let cache = cache_root(app)?;
+    let (version, _) = rollback_target(&cache)?;
+    let from = state.active_version.lock().map(|g| g.clone()).unwrap_or_default();
+    activate(state, app, &version, previous_channel(&cache), None)?;
+    record_history_in(&cache, UpdateHistoryEvent::Rollback { from, to: version.clone() });
+    reload_main_window(state, app)?;
+    Ok(WebUpdateRollbackResult { restored_version: version })
+}
+
+// Update state a reset always removes, on top of every bundle but the embedded one.
+// The update history stays; it is what explains the reset afterwards.
+const RESET_STATE_FILES: [&str; 9] = [
+    ACTIVE_VERSION_FILE,
+    ACTIVE_CHANNEL_FILE,
+    PREVIOUS_VERSION_FILE,
+    PREVIOUS_CHANNEL_FILE,
+    PENDING_ACTIVATION_FILE,
+    PENDING_DELETE_FILE,
+    MANIFEST_CACHE_FILE,
+    LAST_CHECK_FILE,
+    crate::auto_update::DECISIONS_FILE,
+];
+// Removed unless the reset keeps settings. The server port stays either way so
+// the UI keeps its origin, and with it local storage.
+const RESET_SETTINGS_FILES: [&str; 4] =
+    [FEED_FILE, KEEP_BUNDLES_FILE, crate::auto_update::SETTINGS_FILE, crate::update_network::CONFIG_FILE];
+
+// Bytes under `dir`, without following symlinks.
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| match e.metadata() {
+            Ok(m) if m.is_dir() => dir_size(&e.path()),
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+// Removes `path` if it sits inside `cache` (canonical) and returns the bytes it
+// held. A symlink is removed itself; whatever it points to is left alone.
+fn remove_inside(cache: &Path, path: &Path) -> std::io::Result<u64> {
+    let parent = path.parent().map(Path::canonicalize).transpose()?;
+    if path.file_name().is_none() || !parent.is_some_and(|p| p.starts_with(cache)) {
+        return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "outside the app cache dir"));
+    }
+    let meta = std::fs::symlink_metadata(path)?;
+    if meta.is_dir() {
+        let bytes = dir_size(path);
+        std::fs::remove_dir_all(path)?;
+        Ok(bytes)
+    } else {
+        std::fs::remove_file(path)?;
+        Ok(meta.len())
+    }
+}
+
+fn clear_cache_in(cache: &Path, keep_version: &str, keep_settings: bool) -> Result<WebUpdateResetResult, String> {
+    let cache = cache.canonicalize().map_err(|e| e.to_string())?;
+    let mut targets: Vec<PathBuf> = std::fs::read_dir(cache.join("web_bundles"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.file_name() != keep_version)
+        .map(|e| e.path())
+        .collect();
+    targets.sort();
+    targets.push(cache.join("downloads"));
+    targets.push(cache.join(SOURCE_ZIPS_DIR));
+    targets.extend(RESET_STATE_FILES.iter().map(|f| cache.join(f)));
+    if !keep_settings {
+        targets.extend(RESET_SETTINGS_FILES.iter().map(|f| cache.join(f)));
+    }
+
+    let mut result = WebUpdateResetResult { active_version: keep_version.to_string(), ..Default::default() };
+    for path in targets {
+        if std::fs::symlink_metadata(&path).is_err() {
+            continue;
+        }
+        let rel = path.strip_prefix(&cache).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        match remove_inside(&cache, &path) {
+            Ok(bytes) => {
+                result.bytes_reclaimed += bytes;
+                result.removed.push(rel);
+            }
+            Err(e) => {
+                tracing::warn!("web update reset: couldn't remove {rel}: {e}");
+                result.failed.push(rel);
+            }
+        }
+    }
+    // Bundles still in use are finished off at the next launch, like pruned ones.
+    let pending: Vec<String> = result
+        .failed
+        .iter()
+        .filter_map(|rel| rel.strip_prefix("web_bundles/"))
+        .filter(|name| semver::Version::parse(name).is_ok())
+        .map(str::to_string)
+        .collect();
+    write_pending_deletes(&cache, &pending)?;
+    Ok(result)
+}
+
+/// Goes back to the embedded bundle as if freshly installed: it is restored if
+/// damaged and served, then every other bundle, download and piece of update
+/// state is deleted. With `keep_settings` off, the feeds, network config and
+/// update settings go too; proxy credentials in the keyring are left alone.
+#[tracing::instrument(skip(state, app))]
+pub fn reset(state: &WebUpdateState, app: &tauri::AppHandle, keep_settings: bool) -> Result<WebUpdateResetResult, WebUpdateError> {
+    let _op = state.operation.try_begin(UpdateOperation::Reset)?;
+    let cache = cache_root(app)?;
+    let embedded = &state.embedded_version;
+    let (version, dir, _) = ensure_intact_in(&cache, state.embedded_zip, embedded, embedded)?;
+    // Stop serving downloaded bundles before deleting them.
+    if !matches!(dev_override(state), Some(DevOverride::Dir(_))) {
+        state.server.set_root(dir);
+    }
+    // A reset cut short would leave the active version pointing at a deleted bundle.
+    write_journal_in(&cache, &ActivationJournal::Reset { version: version.clone(), keep_settings })?;
+    let result = clear_cache_in(&cache, &version, keep_settings)?;
+    clear_journal_in(&cache);
+
+    let previous = state.active_version.lock().map(|g| g.clone()).unwrap_or_default();
+    let entry = record_history_in(&cache, UpdateHistoryEvent::Activation { version: version.clone(), channel: None, previous });
+    if let Ok(mut g) = state.last_activation.lock() {
+        *g = Some(entry);
+    }
+    state.server.update_config(|c| {
+        c.active_version = version.clone();
+        c.active_channel = None;
+    });
+    if let Ok(mut g) = state.active_version.lock() {
+        *g = version;
+    }
+    if let Ok(mut g) = state.active_channel.lock() {
+        *g = None;
+    }
+    if let Ok(mut g) = state.last_check.lock() {
+        *g = None;
+    }
+    if let Ok(mut g) = state.decisions.lock() {
+        *g = Default::default();
+    }
+    if !keep_settings {
+        if let Ok(mut g) = state.feed_urls.lock() {
+            g.clear();
+        }
+        if let Ok(mut g) = state.settings.lock() {
+            *g = Default::default();
+        }
+        state.settings_changed.notify_one();
+        if let Ok(mut g) = state.network.lock() {
+            *g = Default::default();
+        }
+        if let Ok(mut g) = state.client.lock() {
+            *g = crate::update_network::build_client(&Default::default(), None).unwrap_or_default();
+        }
+    }
+    reload_main_window(state, app)?;
+    Ok(result)
+}
+
+/// Emitted as EVENT_STORAGE_MOVE_PROGRESS while `move_storage` copies the cache.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageMoveProgress {
+    pub copied: u64,
+    pub total: u64,
+}
+
+/// Where the cache (bundles, downloads and update state) is kept.
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageLocation {
+    /// `None` for the OS cache dir.
+    pub storage_root: Option<PathBuf>,
+    pub cache_dir: PathBuf,
+    /// The storage root can't be reached, so the OS cache dir is standing in.
+    pub unavailable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageMoveResult {
+    pub storage_root: Option<PathBuf>,
+    pub cache_dir: PathBuf,
+    pub bytes_moved: u64,
+    /// False when part of the old copy couldn't be deleted (files still open
+    /// on Windows); that is retried at the next launch.
+    pub old_removed: bool,
+}
+
+pub fn storage_location(app: &tauri::AppHandle) -> Result<StorageLocation, String> {
+    Ok(StorageLocation {
+        storage_root: read_storage_root_in(&storage_settings_dir(app)?),
+        cache_dir: cache_root(app)?,
+        unavailable: storage_root_unavailable(app).is_some(),
+    })
+}
+
+// Where the app itself is installed, which an app update may replace wholesale.
+fn app_install_dirs(app: &tauri::AppHandle) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = app.path().resource_dir().ok().into_iter().collect();
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|e| e.parent().map(Path::to_path_buf)) {
+        // The whole .app on macOS, not just Contents/MacOS.
+        dirs.extend(exe_dir.ancestors().find(|d| d.extension().is_some_and(|e| e == "app")).map(Path::to_path_buf));
+        dirs.push(exe_dir);
+    }
+    dirs
+}
+
+/// Checks that `path` is an existing, writable folder outside `install_dirs`
+/// and returns it canonicalized.
+fn validate_storage_root(path: &str, install_dirs: &[PathBuf]) -> Result<PathBuf, String> {
+    let path = Path::new(path.trim());
+    if !path.is_absolute() {
+        return Err(format!("{} is not an absolute path", path.display()));
+    }
+    let root = path.canonicalize().map_err(|e| format!("{}: {e}", path.display()))?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", root.display()));
+    }
+    if install_dirs.iter().filter_map(|d| d.canonicalize().ok()).any(|d| root.starts_with(d)) {
+        return Err(format!("{} is inside the app's install location", root.display()));
+    }
+    // Permissions alone don't say (read-only mounts, ACLs), so try it.
+    let probe = root.join(format!(".voxelle-write-test-{}", std::process::id()));
+    std::fs::write(&probe, b"").map_err(|e| format!("{} is not writable: {e}", root.display()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(root)
+}
+
+fn check_move_target(from: &Path, to: &Path) -> Result<(), String> {
+    if to.starts_with(from) || from.starts_with(to) {
+        return Err(format!("{} overlaps the current location {}", to.display(), from.display()));
+    }
+    // Undoing a move deletes `to`, so it has to start out with nothing in it.
+    if std::fs::symlink_metadata(to).is_ok() && !std::fs::read_dir(to).is_ok_and(|mut d| d.next().is_none()) {
+        return Err(format!("{} already exists and isn't empty", to.display()));
+    }
+    Ok(())
+}
+
+fn ensure_space_to_move(size: u64, available: u64) -> Result<(), WebUpdateError> {
+    let needed = size.saturating_add(INSTALL_SPACE_SLACK);
+    if available < needed {
+        return Err(WebUpdateError::InsufficientSpaceToMove { needed, available });
+    }
+    Ok(())
+}
+
+// Only entries between two cache dirs, one of them this one, are acted on.
+fn move_paths_ok(cache: &Path, from: &Path, to: &Path) -> bool {
+    let named = |d: &Path| d.file_name().is_some_and(|n| n == CACHE_DIR_NAME);
+    named(from) && named(to) && !from.starts_with(to) && !to.starts_with(from) && (cache == from || cache == to)
+}
+
+/// Journals a move and copies every file of `from` to `to`, checking each
+/// copy's sha256 against the original. Returns the bytes copied; on failure
+/// the partial copy is removed again.
+fn copy_cache_in(from: &Path, to: &Path, on_progress: &dyn Fn(StorageMoveProgress)) -> Result<u64, String> {
+    let entry = ActivationJournal::MoveStorage { from: from.to_path_buf(), to: to.to_path_buf() };
+    write_journal_in(from, &entry)?;
+    let copy = || -> Result<u64, String> {
+        let files: Vec<String> = list_files(from)?.into_iter().filter(|f| f != ACTIVATION_JOURNAL_FILE).collect();
+        let total = files.iter().filter_map(|f| std::fs::metadata(from.join(f)).ok()).map(|m| m.len()).sum();
+        let (mut copied, mut reported) = (0, 0);
+        on_progress(StorageMoveProgress { copied, total });
+        for rel in &files {
+            let (src, dst) = (from.join(rel), to.join(rel));
+            if let Some(parent) = dst.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            copied += std::fs::copy(&src, &dst).map_err(|e| format!("copying {rel}: {e}"))?;
+            std::fs::File::open(&dst).and_then(|f| f.sync_all()).map_err(|e| e.to_string())?;
+            if sha256_file(&src)? != sha256_file(&dst)? {
+                return Err(format!("{rel} changed while it was copied"));
+            }
+            if copied - reported >= PROGRESS_STEP {
+                reported = copied;
+                on_progress(StorageMoveProgress { copied, total });
+            }
+        }
+        on_progress(StorageMoveProgress { copied, total });
+        write_journal_in(to, &entry)?;
+        Ok(copied)
+    };
+    copy().inspect_err(|_| {
+        if let Err(e) = undo_move_in(from, to) {
+            tracing::warn!("removing the partial copy at {} failed; retrying at next launch: {e}", to.display());
+        }
+    })
+}
+
+// Once the setting points at `to`: the old copy goes, and the move is done.
+fn finish_move_in(from: &Path, to: &Path) -> Result<(), String> {
+    match std::fs::remove_dir_all(from) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(format!("couldn't delete the old copy at {}: {e}", from.display()));
+        }
+        _ => {}
+    }
+    clear_journal_in(to);
+    Ok(())
+}
+
+// While the setting still points at `from`: the new copy goes.
+fn undo_move_in(from: &Path, to: &Path) -> Result<(), String> {
+    match std::fs::remove_dir_all(to) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+        _ => {}
+    }
+    clear_journal_in(from);
+    Ok(())
+}
+
+/// Moves the cache under `root`, or back to the OS cache dir with `None`, and
+/// keeps it there from then on. Everything is copied and checked before the
+/// setting changes and the old copy is deleted only after, with the server
+/// pointed at the new copy in between, so no restart is needed. A move cut
+/// short is undone, or finished if the setting had already changed, at the
+/// next launch.
+#[tracing::instrument(skip(state, app))]
+pub fn move_storage(state: &WebUpdateState, app: &tauri::AppHandle, root: Option<&str>) -> Result<StorageMoveResult, WebUpdateError> {
+    let _op = state.operation.try_begin(UpdateOperation::MoveStorage)?;
+    // Moving from the stand-in would leave the real cache behind on that disk.
+    if let Some(missing) = storage_root_unavailable(app) {
+        return Err(WebUpdateError::InvalidStorageRoot(format!("the current location {} can't be reached", missing.display())));
+    }
+    let settings = storage_settings_dir(app)?;
+    let root = root.map(|r| validate_storage_root(r, &app_install_dirs(app))).transpose().map_err(WebUpdateError::InvalidStorageRoot)?;
+    let from = cache_root(app)?;
+    let to = match &root {
+        Some(root) => root.join(CACHE_DIR_NAME),
+        None => default_cache_root(app)?,
+    };
+    if to == from {
+        write_storage_root_in(&settings, root.as_deref())?;
+        return Ok(StorageMoveResult { storage_root: root, cache_dir: to, bytes_moved: 0, old_removed: true });
+    }
+    check_move_target(&from, &to).map_err(WebUpdateError::InvalidStorageRoot)?;
+    let parent = to.parent().ok_or_else(|| "storage location has no parent folder".to_string())?;
+    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    let available = fs2::available_space(parent).map_err(|e| e.to_string())?;
+    ensure_space_to_move(dir_size(&from), available)?;
+
+    let bytes_moved = copy_cache_in(&from, &to, &|p| {
+        let _ = app.emit(EVENT_STORAGE_MOVE_PROGRESS, p);
+    })?;
+    write_storage_root_in(&settings, root.as_deref()).inspect_err(|_| {
+        let _ = undo_move_in(&from, &to);
+    })?;
+    if !matches!(dev_override(state), Some(DevOverride::Dir(_))) {
+        let active = state.active_version.lock().map(|g| g.clone()).unwrap_or_default();
+        state.server.set_root(bundle_dir_in(&to, &active));
+    }
+    let old_removed = finish_move_in(&from, &to).inspect_err(|e| tracing::warn!("storage move: {e}")).is_ok();
+    tracing::info!(bytes_moved, old_removed, "moved storage to {}", to.display());
+    Ok(StorageMoveResult { storage_root: root, cache_dir: to, bytes_moved, old_removed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(cache: &Path, version: &str) {
+        let dir = bundle_dir_in(cache, version);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.html"), "<html></html>").unwrap();
+    }
+
+    #[test]
+    fn no_previous_version_is_a_typed_error() {
+        let cache = tempfile::tempdir().unwrap();
+        assert_eq!(rollback_target(cache.path()), Err(RollbackError::NoPreviousVersion));
+
+        // First activation after a fresh install has nothing to record.
+        record_previous_version(cache.path(), "", None, "0.1.0").unwrap();
+        assert_eq!(rollback_target(cache.path()), Err(RollbackError::NoPreviousVersion));
+    }
+
+    #[test]
+    fn records_the_replaced_version() {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.1.0");
+        record_previous_version(cache.path(), "0.1.0", None, "0.2.0").unwrap();
+        let (version, dir) = rollback_target(cache.path()).unwrap();
+        assert_eq!(version, "0.1.0");
+        assert_eq!(dir, bundle_dir_in(cache.path(), "0.1.0"));
+    }
+
+    #[test]
+    fn reactivating_the_same_version_keeps_the_record() {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.1.0");
+        record_previous_version(cache.path(), "0.1.0", None, "0.2.0").unwrap();
+        record_previous_version(cache.path(), "0.2.0", None, "0.2.0").unwrap();
+        assert_eq!(rollback_target(cache.path()).unwrap().0, "0.1.0");
+    }
+
+    #[test]
+    fn missing_or_incomplete_bundle_is_rejected() {
+        let cache = tempfile::tempdir().unwrap();
+        record_previous_version(cache.path(), "0.1.0", None, "0.2.0").unwrap();
+        assert_eq!(
+            rollback_target(cache.path()),
+            Err(RollbackError::PreviousBundleMissing("0.1.0".into()))
+        );
+
+        std::fs::create_dir_all(bundle_dir_in(cache.path(), "0.1.0")).unwrap();
+        assert_eq!(
+            rollback_target(cache.path()),
+            Err(RollbackError::PreviousBundleMissing("0.1.0".into()))
+        );
+    }
+
+    #[test]
+    fn non_semver_record_is_rejected() {
+        let cache = tempfile::tempdir().unwrap();
+        std::fs::write(cache.path().join(PREVIOUS_VERSION_FILE), "../etc").unwrap();
+        assert_eq!(
+            rollback_target(cache.path()),
+            Err(RollbackError::InvalidPreviousVersion("../etc".into()))
+        );
+    }
+
+    fn installed(cache: &Path) -> Vec<String> {
+        let mut v: Vec<String> = std::fs::read_dir(cache.join("web_bundles"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn prune_keeps_the_newest_by_semver() {
+        let cache = tempfile::tempdir().unwrap();
+        for v in ["0.1.0", "0.2.0", "0.10.0", "0.9.1", "1.0.0-beta.1"] {
+            install(cache.path(), v);
+        }
+        let r = prune_in(cache.path(), 2, &[], remove_bundle_dir).unwrap();
+        assert_eq!(r.removed, vec!["0.9.1", "0.2.0", "0.1.0"]);
+        assert!(r.deferred.is_empty());
+        assert_eq!(installed(cache.path()), vec!["0.10.0", "1.0.0-beta.1"]);
+    }
+
+    #[test]
+    fn prune_never_removes_protected_versions() {
+        let cache = tempfile::tempdir().unwrap();
+        for v in ["0.1.0", "0.2.0", "0.3.0", "0.4.0"] {
+            install(cache.path(), v);
+        }
+        let r = prune_in(cache.path(), 1, &["0.1.0", "0.3.0", ""], remove_bundle_dir).unwrap();
+        assert_eq!(r.removed, vec!["0.2.0"]);
+        assert_eq!(installed(cache.path()), vec!["0.1.0", "0.3.0", "0.4.0"]);
+    }
+
+    #[test]
+    fn prune_ignores_non_bundle_entries() {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.1.0");
+        install(cache.path(), "0.2.0");
+        std::fs::create_dir_all(cache.path().join("web_bundles/.tmp-0.3.0-1-2-0")).unwrap();
+        std::fs::write(cache.path().join("web_bundles/0.0.1"), "not a dir").unwrap();
+        let r = prune_in(cache.path(), 1, &[], remove_bundle_dir).unwrap();
+        assert_eq!(r.removed, vec!["0.1.0"]);
+        assert_eq!(installed(cache.path()), vec![".tmp-0.3.0-1-2-0", "0.0.1", "0.2.0"]);
+    }
+
+    #[test]
+    fn failed_removals_are_retried_later() {
+        let cache = tempfile::tempdir().unwrap();
+        for v in ["0.1.0", "0.2.0", "0.3.0"] {
+            install(cache.path(), v);
+        }
+        let in_use = |_: &Path| Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "in use"));
+        let r = prune_in(cache.path(), 1, &[], in_use).unwrap();
+        assert_eq!(r.deferred, vec!["0.2.0", "0.1.0"]);
+        assert_eq!(read_pending_deletes(cache.path()), vec!["0.2.0", "0.1.0"]);
+
+        // Still locked: stays pending, without duplicates.
+        prune_in(cache.path(), 1, &[], in_use).unwrap();
+        let r = retry_pending_deletes(cache.path(), in_use).unwrap();
+        assert_eq!(r.deferred, vec!["0.2.0", "0.1.0"]);
+        assert_eq!(read_pending_deletes(cache.path()), vec!["0.2.0", "0.1.0"]);
+
+        let r = retry_pending_deletes(cache.path(), remove_bundle_dir).unwrap();
+        assert_eq!(r.removed, vec!["0.2.0", "0.1.0"]);
+        assert!(!cache.path().join(PENDING_DELETE_FILE).exists());
+        assert_eq!(installed(cache.path()), vec!["0.3.0"]);
+    }
+
+    #[test]
+    fn pending_entries_cannot_escape_the_bundle_dir() {
+        let cache = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(cache.path().join("keep-me")).unwrap();
+        std::fs::write(cache.path().join(PENDING_DELETE_FILE), "../keep-me\n..").unwrap();
+        let r = retry_pending_deletes(cache.path(), remove_bundle_dir).unwrap();
+        assert_eq!(r, WebUpdatePruneResult::default());
+        assert!(cache.path().join("keep-me").exists());
+    }
+
+    fn fixture(files: &[(&str, &[u8])]) -> (tempfile::TempDir, Vec<BundleFile>) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut listed = Vec::new();
+        for (path, data) in files {
+            let p = dir.path().join(path);
+            std::fs::create_dir_all(p.parent().unwrap()).unwrap();
+            std::fs::write(&p, data).unwrap();
+            listed.push(BundleFile {
+                path: path.to_string(),
+                sha256: hex::encode(Sha256::digest(data)),
+                size: data.len() as u64,
+            });
+        }
+        (dir, listed)
+    }
+
+    fn statuses(report: &BundleVerifyReport) -> Vec<(&str, &FileCheckStatus)> {
+        report.files.iter().map(|c| (c.path.as_str(), &c.status)).collect()
+    }
+
+    const BUNDLE: &[(&str, &[u8])] = &[("index.html", b"<html></html>"), ("assets/app.js", b"console.log(1)")];
+
+    #[test]
+    fn matching_bundle_verifies() {
+        let (dir, files) = fixture(BUNDLE);
+        std::fs::write(dir.path().join("assets/.DS_Store"), "x").unwrap();
+        let report = verify_bundle_files(dir.path(), "0.1.0", &files).unwrap();
+        assert!(report.ok, "{report:?}");
+        verify_and_record_files(dir.path(), "0.1.0", &files).unwrap();
+
+        // The recorded list is itself allowed in the bundle.
+        let recorded: Vec<BundleFile> =
+            serde_json::from_slice(&std::fs::read(dir.path().join(BUNDLE_FILES_NAME)).unwrap()).unwrap();
+        assert_eq!(recorded, files);
+        assert!(verify_bundle_files(dir.path(), "0.1.0", &recorded).unwrap().ok);
+    }
+
+    #[test]
+    fn reports_each_kind_of_mismatch() {
+        let (dir, files) = fixture(BUNDLE);
+        std::fs::write(dir.path().join("index.html"), "<html></html!").unwrap();
+        std::fs::remove_file(dir.path().join("assets/app.js")).unwrap();
+        std::fs::write(dir.path().join("assets/extra.js"), "evil()").unwrap();
+        let report = verify_bundle_files(dir.path(), "0.1.0", &files).unwrap();
+        assert!(!report.ok);
+        assert_eq!(
+            statuses(&report),
+            vec![
+                ("index.html", &FileCheckStatus::HashMismatch),
+                ("assets/app.js", &FileCheckStatus::Missing),
+                ("assets/extra.js", &FileCheckStatus::Unexpected),
+            ]
+        );
+
+        std::fs::write(dir.path().join("index.html"), "<html>").unwrap();
+        let report = verify_bundle_files(dir.path(), "0.1.0", &files).unwrap();
+        assert_eq!(report.files[0].status, FileCheckStatus::SizeMismatch { expected: 13, actual: 6 });
+
+        let err = verify_and_record_files(dir.path(), "0.1.0", &files).unwrap_err();
+        assert!(err.contains("index.html") && err.contains("assets/extra.js"), "{err}");
+        assert!(!dir.path().join(BUNDLE_FILES_NAME).exists());
+    }
+
+    #[test]
+    fn rejects_malformed_file_lists() {
+        let (_, files) = fixture(BUNDLE);
+        let with = |f: BundleFile| {
+            let mut v = files.clone();
+            v.push(f);
+            v
+        };
+        let good = files[0].clone();
+        for bad in [
+            with(BundleFile { path: "../x".into(), ..good.clone() }),
+            with(BundleFile { path: "/etc/passwd".into(), ..good.clone() }),
+            with(BundleFile { path: "a\\b".into(), ..good.clone() }),
+            with(BundleFile { path: "".into(), ..good.clone() }),
+            with(BundleFile { path: "x.js".into(), sha256: "zz".into(), ..good.clone() }),
+            with(good.clone()),
+        ] {
+            assert!(validate_bundle_files(&bad).is_err(), "{bad:?}");
+        }
+        validate_bundle_files(&files).unwrap();
+    }
+
+    #[test]
+    fn report_serializes_per_file_status() {
+        let check = FileCheck {
+            path: "index.html".into(),
+            status: FileCheckStatus::SizeMismatch { expected: 2, actual: 1 },
+        };
+        assert_eq!(
+            serde_json::to_value(check).unwrap(),
+            serde_json::json!({ "path": "index.html", "status": "size_mismatch", "expected": 2, "actual": 1 })
+        );
+    }
+
+    #[test]
+    fn previous_channel_is_recorded_with_the_version() {
+        let cache = tempfile::tempdir().unwrap();
+        record_previous_version(cache.path(), "0.2.0-beta.1", Some(UpdateChannel::Beta), "0.1.0").unwrap();
+        assert_eq!(previous_channel(cache.path()), Some(UpdateChannel::Beta));
+        record_previous_version(cache.path(), "0.1.0", None, "0.2.0").unwrap();
+        assert_eq!(previous_channel(cache.path()), None);
+    }
+
+    #[test]
+    fn gh_shorthand_resolves_per_channel() {
+        assert_eq!(
+            normalize_feed_url("gh:x3haloed/voxelle", UpdateChannel::Stable),
+            "https://github.com/x3haloed/voxelle/releases/latest/download/voxelle-web-manifest.json"
+        );
+        assert_eq!(
+            normalize_feed_url("github:x3haloed/voxelle/", UpdateChannel::Beta),
+            "https://github.com/x3haloed/voxelle/releases/latest/download/voxelle-web-manifest-beta.json"
+        );
+        assert_eq!(
+            normalize_feed_url(" https://example.com/{channel}/manifest.json ", UpdateChannel::Beta),
+            "https://example.com/beta/manifest.json"
+        );
+        assert_eq!(
+            normalize_feed_url("https://example.com/manifest.json", UpdateChannel::Beta),
+            "https://example.com/manifest.json"
+        );
+        assert_eq!(normalize_feed_url("  ", UpdateChannel::Beta), "");
+    }
+
+    #[test]
+    fn same_channel_only_offers_newer_versions() {
+        use UpdateChannel::*;
+        assert_eq!(compare_offer("0.2.0", Some(Stable), "0.3.0", Stable), (true, false));
+        assert_eq!(compare_offer("0.2.0", Some(Stable), "0.2.0", Stable), (false, false));
+        assert_eq!(compare_offer("0.2.0", Some(Stable), "0.1.0", Stable), (false, false));
+        // The embedded bundle has no channel and is treated as the current one.
+        assert_eq!(compare_offer("0.2.0", None, "0.3.0-beta.1", Beta), (true, false));
+        assert_eq!(compare_offer("0.2.0", None, "0.1.0", Beta), (false, false));
+    }
+
+    #[test]
+    fn switching_channel_offers_older_versions_as_downgrades() {
+        use UpdateChannel::*;
+        assert_eq!(compare_offer("0.3.0-beta.2", Some(Beta), "0.2.1", Stable), (true, true));
+        assert_eq!(compare_offer("0.3.0", Some(Beta), "0.2.1", Stable), (true, true));
+        assert_eq!(compare_offer("0.2.1", Some(Stable), "0.3.0-beta.1", Beta), (true, false));
+        assert_eq!(compare_offer("0.2.1", Some(Beta), "0.2.1", Stable), (false, false));
+    }
+
+    #[test]
+    fn min_version_only_applies_to_older_active_versions() {
+        assert_eq!(required_min_version("0.2.0", Some("0.3.0")), Some("0.3.0".into()));
+        assert_eq!(required_min_version("0.3.0-beta.1", Some(" 0.3.0 ")), Some("0.3.0".into()));
+        assert_eq!(required_min_version("0.3.0", Some("0.3.0")), None);
+        assert_eq!(required_min_version("0.4.0", Some("0.3.0")), None);
+        assert_eq!(required_min_version("0.2.0", None), None);
+        assert_eq!(required_min_version("0.2.0", Some("")), None);
+        // Not semver: ignored rather than forcing an update.
+        assert_eq!(required_min_version("0.2.0", Some("3")), None);
+        assert_eq!(required_min_version("0.2.0", Some("latest")), None);
+    }
+
+    #[test]
+    fn manifests_without_min_version_still_parse() {
+        let json = serde_json::json!({ "v": 1, "version": "0.3.0", "zip_url": "https://example.com/web.zip", "sha256": "ab".repeat(32) });
+        let m: WebBundleManifestV1 = serde_json::from_value(json).unwrap();
+        assert_eq!(m.min_version, None);
+        let json = serde_json::json!({ "v": 1, "version": "0.3.0", "zip_url": "https://example.com/web.zip", "sha256": "ab".repeat(32), "min_version": "0.2.0" });
+        let m: WebBundleManifestV1 = serde_json::from_value(json).unwrap();
+        assert_eq!(m.min_version.as_deref(), Some("0.2.0"));
+    }
+
+    #[test]
+    fn manifest_notes_are_capped() {
+        let base = serde_json::json!({ "v": 1, "version": "0.3.0", "zip_url": "https://example.com/web.zip", "sha256": "ab".repeat(32) });
+        let with = |key: &str, value: serde_json::Value| {
+            let mut json = base.clone();
+            json[key] = value;
+            validate_manifest(&serde_json::from_value(json).unwrap())
+        };
+        assert_eq!(with("notes", "## Fixes\n- faster boards".into()), Ok(()));
+        assert_eq!(with("notes", "x".repeat(MAX_NOTES_BYTES).into()), Ok(()));
+        assert_eq!(with("notes", "x".repeat(MAX_NOTES_BYTES + 1).into()), Err("manifest notes exceed 32 KB".into()));
+        assert_eq!(with("notes_url", "https://example.com/NOTES.md".into()), Ok(()));
+        assert!(with("notes_url", "file:///etc/passwd".into()).is_err());
+    }
+
+    #[test]
+    fn release_notes_must_be_small_text() {
+        let client = reqwest::Client::new();
+        let fetch = |content_type: &str, body: &str| {
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            tauri::async_runtime::block_on(fetch_notes(&client, &serve_once(response)))
+        };
+
+        assert_eq!(fetch("text/markdown; charset=utf-8", "## 0.3.0"), Ok("## 0.3.0".into()));
+        assert_eq!(fetch("text/plain", "fixes"), Ok("fixes".into()));
+
+        let e = command_error(fetch("text/html", "<p>hi</p>").unwrap_err());
+        assert_eq!(e.code, WebUpdateErrorCode::NotesUnsupportedType);
+        assert_eq!(e.details, Some(serde_json::json!({ "content_type": "text/html" })));
+        let e = command_error(fetch("text/plain", &"x".repeat(MAX_NOTES_BYTES + 1)).unwrap_err());
+        assert_eq!((e.code, e.message.as_str()), (WebUpdateErrorCode::NotesTooLarge, "release notes exceed 32 KB"));
+        assert_eq!(e.details, Some(serde_json::json!({ "max_bytes": MAX_NOTES_BYTES })));
+    }
+
+    #[test]
+    fn channels_serialize_lowercase() {
+        assert_eq!(serde_json::to_value(UpdateChannel::Beta).unwrap(), serde_json::json!("beta"));
+        assert_eq!(UpdateChannel::parse("stable"), Some(UpdateChannel::Stable));
+        assert_eq!(UpdateChannel::parse("nightly"), None);
+    }
+
+    #[test]
+    fn partial_downloads_are_keyed_by_version_and_hash() {
+        let cache = tempfile::tempdir().unwrap();
+        let sha = "ab".repeat(32);
+        let p = download_path(cache.path(), "0.2.0", &sha, "zip").unwrap();
+        assert_eq!(p, cache.path().join("downloads").join(format!("0.2.0-{sha}.zip.part")));
+        assert_ne!(p, download_path(cache.path(), "0.2.0", &"cd".repeat(32), "zip").unwrap());
+        assert_ne!(p, download_path(cache.path(), "0.2.0", &sha, "patch").unwrap());
+        assert!(download_path(cache.path(), "0.2.0", "../../x", "zip").is_err());
+        assert!(download_path(cache.path(), "../0.2.0", &sha, "zip").is_err());
+    }
+
+    #[test]
+    fn resume_requires_a_matching_partial_response() {
+        assert!(resume_accepted(206, Some("bytes 100-999/1000"), 100));
+        assert!(resume_accepted(206, Some("bytes 100-999/*"), 100));
+        assert!(!resume_accepted(206, Some("bytes 0-999/1000"), 100));
+        assert!(!resume_accepted(206, None, 100));
+        assert!(!resume_accepted(200, Some("bytes 100-999/1000"), 100));
+        assert!(!resume_accepted(206, Some("items 100-999/1000"), 100));
+    }
+
+    #[test]
+    fn partial_file_hash_continues_where_it_left_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("x.part");
+        std::fs::write(&path, b"hello ").unwrap();
+        let mut f = std::fs::OpenOptions::new().read(true).write(true).open(&path).unwrap();
+        let (mut h, n) = sha256_prefix(&mut f).unwrap();
+        assert_eq!(n, 6);
+        h.update(b"world");
+        assert_eq!(hex::encode(h.finalize()), hex::encode(Sha256::digest(b"hello world")));
+    }
+
+    #[test]
+    fn extracts_from_any_seekable_reader() {
+        let mut buf = Cursor::new(Vec::new());
+        {
+            let mut w = zip::ZipWriter::new(&mut buf);
+            let opts = zip::write::SimpleFileOptions::default();
+            w.start_file("index.html", opts).unwrap();
+            w.write_all(b"<html></html>").unwrap();
+            w.finish().unwrap();
+        }
+        let zip_path = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(zip_path.path(), buf.into_inner()).unwrap();
+
+        let out = tempfile::tempdir().unwrap();
+        extract_zip(std::fs::File::open(zip_path.path()).unwrap(), out.path()).unwrap();
+        assert_eq!(std::fs::read(out.path().join("index.html")).unwrap(), b"<html></html>");
+    }
+
+    fn fixture_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut buf = Cursor::new(Vec::new());
+        {
+            let mut w = zip::ZipWriter::new(&mut buf);
+            // Stored, so the zip is at least as large as its contents.
+            let opts = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+            for (name, body) in files {
+                w.start_file(*name, opts).unwrap();
+                w.write_all(body).unwrap();
+            }
+            w.finish().unwrap();
+        }
+        buf.into_inner()
+    }
+
+    // Rewrites `name`'s central directory entry to say it was made on Unix with
+    // `mode`, which zip writers won't produce for links or setuid files on request.
+    fn with_unix_mode(mut zip: Vec<u8>, name: &str, mode: u32) -> Vec<u8> {
+        let u16_at = |z: &[u8], i: usize| usize::from(u16::from_le_bytes([z[i], z[i + 1]]));
+        let mut i = 0;
+        while i + 46 <= zip.len() {
+            if zip[i..i + 4] != *b"PK\x01\x02" {
+                i += 1;
+                continue;
+            }
+            let name_len = u16_at(&zip, i + 28);
+            if &zip[i + 46..i + 46 + name_len] == name.as_bytes() {
+                zip[i + 5] = 3;
+                zip[i + 38..i + 42].copy_from_slice(&(mode << 16).to_le_bytes());
+                return zip;
+            }
+            i += 46 + name_len + u16_at(&zip, i + 30) + u16_at(&zip, i + 32);
+        }
+        panic!("no central directory entry for {name}");
+    }
+
+    #[test]
+    fn archives_with_symlinks_are_refused() {
+        let zip = fixture_zip(&[("index.html", b"<html></html>"), ("assets", b"../../.."), ("assets/owned.js", b"x")]);
+        let zip = with_unix_mode(zip, "assets", 0o120777);
+        let out = tempfile::tempdir().unwrap();
+        let err = extract_zip(Cursor::new(zip), out.path()).unwrap_err();
+        assert_eq!(err, "zip entry assets is a symlink");
+        assert!(std::fs::symlink_metadata(out.path().join("assets")).is_err(), "nothing was created for it");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extracted_files_keep_only_the_executable_bit() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut buf = Cursor::new(Vec::new());
+        {
+            let mut w = zip::ZipWriter::new(&mut buf);
+            let opts = zip::write::SimpleFileOptions::default();
+            w.start_file("index.html", opts.unix_permissions(0o600)).unwrap();
+            w.write_all(b"<html></html>").unwrap();
+            w.start_file("tools/wasm-opt.sh", opts.unix_permissions(0o750)).unwrap();
+            w.write_all(b"#!/bin/sh\n").unwrap();
+            w.start_file("tools/setuid.sh", opts).unwrap();
+            w.write_all(b"#!/bin/sh\n").unwrap();
+            w.start_file("assets/app.js", opts.unix_permissions(0o666)).unwrap();
+            w.write_all(b"x").unwrap();
+            w.finish().unwrap();
+        }
+        let zip = with_unix_mode(buf.into_inner(), "tools/setuid.sh", 0o106755);
+        let out = tempfile::tempdir().unwrap();
+        extract_zip(Cursor::new(zip), out.path()).unwrap();
+
+        let mode = |rel: &str| std::fs::metadata(out.path().join(rel)).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode("index.html"), 0o644);
+        assert_eq!(mode("tools/wasm-opt.sh"), 0o755);
+        assert_eq!(mode("tools/setuid.sh"), 0o755, "setuid and setgid are dropped");
+        assert_eq!(mode("assets/app.js"), 0o644);
+    }
+
+    #[test]
+    fn only_plain_files_and_directories_are_extracted() {
+        let rel = Path::new("a");
+        assert_eq!(extracted_mode(rel, None, false), Ok(0o644));
+        assert_eq!(extracted_mode(rel, Some(0o644), false), Ok(0o644));
+        assert_eq!(extracted_mode(rel, Some(0o100744), false), Ok(0o755));
+        assert_eq!(extracted_mode(rel, Some(0o104755), false), Ok(0o755));
+        assert_eq!(extracted_mode(rel, Some(0o040700), true), Ok(0o755));
+        assert_eq!(extracted_mode(rel, Some(0o120777), false), Err("zip entry a is a symlink".into()));
+        // Named like a file, typed as a directory, or a fifo.
+        assert!(extracted_mode(rel, Some(0o040755), false).is_err());
+        assert!(extracted_mode(rel, Some(0o010644), false).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extracted_trees_with_links_are_rejected() {
+        let out = tempfile::tempdir().unwrap();
+        std::fs::create_dir(out.path().join("assets")).unwrap();
+        std::fs::write(out.path().join("assets/app.js"), "x").unwrap();
+        assert_eq!(check_extracted_tree(out.path()), Ok(()));
+
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), out.path().join("assets/up")).unwrap();
+        assert_eq!(check_extracted_tree(out.path()), Err("extracted assets/up is a symlink".into()));
+    }
+
+    #[test]
+    fn installs_need_the_declared_size_plus_slack() {
+        let zip = fixture_zip(&[("index.html", b"<html></html>"), ("assets/app.js", &[b'x'; 4096])]);
+        let declared = declared_uncompressed_size(Cursor::new(&zip)).unwrap();
+        assert_eq!(declared, 13 + 4096);
+
+        assert_eq!(ensure_space_for(declared, declared + INSTALL_SPACE_SLACK), Ok(()));
+        let e = command_error(ensure_space_for(declared, declared + INSTALL_SPACE_SLACK - 1).unwrap_err());
+        assert_eq!(e.code, WebUpdateErrorCode::InsufficientDiskSpace);
+        assert_eq!(
+            e.details,
+            Some(serde_json::json!({ "needed_bytes": declared + INSTALL_SPACE_SLACK, "available_bytes": declared + INSTALL_SPACE_SLACK - 1 }))
+        );
+        assert_eq!(e.message, "not enough disk space to install the update: needs 17 MB, 16 MB free");
+        assert!(declared_uncompressed_size(Cursor::new(b"not a zip")).is_err());
+    }
+
+    #[test]
+    fn orphaned_extraction_dirs_are_swept() {
+        let cache = tempfile::tempdir().unwrap();
+        assert_eq!(sweep_tmp_dirs_in(cache.path()), Ok(vec![]));
+
+        install(cache.path(), "0.2.0");
+        let orphan = cache.path().join("web_bundles/.tmp-0.3.0-1-2-0");
+        std::fs::create_dir_all(&orphan).unwrap();
+        // Half-extracted from a fixture zip, as a crash would leave it.
+        extract_zip(Cursor::new(fixture_zip(&[("index.html", b"<html></html>")])), &orphan).unwrap();
+        std::fs::write(cache.path().join("web_bundles/.tmp-note"), "a file, not an extraction").unwrap();
+
+        assert_eq!(sweep_tmp_dirs_in(cache.path()), Ok(vec![".tmp-0.3.0-1-2-0".to_string()]));
+        assert_eq!(installed(cache.path()), vec![".tmp-note", "0.2.0"]);
+    }
+
+    #[test]
+    fn intact_active_bundle_is_served_as_is() {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.3.0");
+        let (version, dir, repair) = ensure_intact_in(cache.path(), &[], "0.2.0", "0.3.0").unwrap();
+        assert_eq!((version.as_str(), repair), ("0.3.0", None));
+        assert_eq!(dir, bundle_dir_in(cache.path(), "0.3.0"));
+
+        // First launch extracts the embedded bundle without calling it a repair.
+        let zip = fixture_zip(&[("index.html", b"<html></html>")]);
+        let (version, _, repair) = ensure_intact_in(cache.path(), &zip, "0.2.0", "0.2.0").unwrap();
+        assert_eq!((version.as_str(), repair), ("0.2.0", None));
+        assert!(bundle_dir_in(cache.path(), "0.2.0").join("index.html").is_file());
+    }
+
+    #[test]
+    fn bundle_check_catches_damage() {
+        let (dir, files) = fixture(&[("index.html", b"<html></html>"), ("app.js", b"console.log(1)")]);
+        std::fs::write(dir.path().join(BUNDLE_FILES_NAME), serde_json::to_vec(&files).unwrap()).unwrap();
+        std::fs::write(dir.path().join("Thumbs.db"), "").unwrap();
+        assert_eq!(check_bundle_dir(dir.path()), Ok(()));
+
+        std::fs::write(dir.path().join("app.js"), "console.log(2)").unwrap();
+        assert_eq!(check_bundle_dir(dir.path()), Err("damaged files: app.js".into()));
+        std::fs::write(dir.path().join("index.html"), " \n").unwrap();
+        assert_eq!(check_bundle_dir(dir.path()), Err("index.html is empty".into()));
+        std::fs::remove_file(dir.path().join("index.html")).unwrap();
+        assert_eq!(check_bundle_dir(dir.path()), Err("index.html is missing".into()));
+    }
+
+    #[test]
+    fn damaged_embedded_bundle_is_reextracted() {
+        let cache = tempfile::tempdir().unwrap();
+        let zip = fixture_zip(&[("index.html", b"<html>embedded</html>")]);
+        install(cache.path(), "0.2.0");
+        std::fs::write(bundle_dir_in(cache.path(), "0.2.0").join("index.html"), "").unwrap();
+
+        let (version, dir, repair) = ensure_intact_in(cache.path(), &zip, "0.2.0", "0.2.0").unwrap();
+        assert_eq!(version, "0.2.0");
+        assert_eq!(std::fs::read(dir.join("index.html")).unwrap(), b"<html>embedded</html>");
+        assert_eq!(
+            repair,
+            Some(BundleRepair {
+                broken_version: "0.2.0".into(),
+                problem: "index.html is empty".into(),
+                action: BundleRepairAction::ReextractedEmbedded,
+                activated_version: "0.2.0".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn damaged_update_falls_back_to_the_embedded_bundle() {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.2.0");
+        std::fs::create_dir_all(bundle_dir_in(cache.path(), "0.3.0")).unwrap();
+
+        let (version, _, repair) = ensure_intact_in(cache.path(), &[], "0.2.0", "0.3.0").unwrap();
+        assert_eq!(version, "0.2.0");
+        let repair = serde_json::to_value(repair.unwrap()).unwrap();
+        assert_eq!(
+            repair,
+            serde_json::json!({
+                "broken_version": "0.3.0",
+                "problem": "index.html is missing",
+                "action": "reactivated",
+                "activated_version": "0.2.0",
+            })
+        );
+    }
+
+    #[test]
+    fn broken_embedded_zip_falls_back_to_the_newest_intact_bundle() {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.2.5");
+        install(cache.path(), "0.2.9");
+        std::fs::create_dir_all(bundle_dir_in(cache.path(), "0.4.0")).unwrap();
+        std::fs::create_dir_all(bundle_dir_in(cache.path(), "0.3.0")).unwrap();
+
+        let (version, _, repair) = ensure_intact_in(cache.path(), b"not a zip", "0.1.0", "0.3.0").unwrap();
+        assert_eq!(version, "0.2.9");
+        assert_eq!(repair.unwrap().action, BundleRepairAction::Reactivated);
+    }
+
+    #[test]
+    fn nothing_left_to_fall_back_to_is_fatal() {
+        let cache = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(bundle_dir_in(cache.path(), "0.3.0")).unwrap();
+        // Dev builds embed an empty zip.
+        let e = ensure_intact_in(cache.path(), &[], "0.2.0", "0.3.0").unwrap_err();
+        assert!(e.starts_with("the active web bundle 0.3.0 is damaged (index.html is missing), the embedded bundle could not be restored (this build embeds no web bundle)"), "{e}");
+        assert!(e.contains("reinstall Voxelle"), "{e}");
+        assert!(ensure_intact_in(cache.path(), b"not a zip", "0.2.0", "0.2.0").is_err());
+    }
+
+    fn delta_fixtures() -> (Vec<u8>, Vec<u8>) {
+        let app = [b'a'; 8192];
+        let old = fixture_zip(&[("index.html", b"<html>0.2.0</html>"), ("assets/app.js", &app)]);
+        let new = fixture_zip(&[("index.html", b"<html>0.3.0</html>"), ("assets/app.js", &app), ("assets/chunk.js", b"export {}")]);
+        (old, new)
+    }
+
+    #[test]
+    fn deltas_rebuild_the_next_bundle_zip() {
+        let (old, new) = delta_fixtures();
+        let sha = hex::encode(Sha256::digest(&new));
+        let mut bsdiff_patch = Vec::new();
+        bsdiff::diff(&old, &new, &mut bsdiff_patch).unwrap();
+        let zstd_patch = zstd::bulk::Compressor::with_dictionary(19, &old).unwrap().compress(&new).unwrap();
+        // bsdiff patches aren't compressed, so only the zstd one is smaller than the zip.
+        assert!(zstd_patch.len() < new.len());
+
+        for (algorithm, patch) in [(DeltaAlgorithm::Bsdiff, &bsdiff_patch), (DeltaAlgorithm::ZstdDict, &zstd_patch)] {
+            let zip = rebuild_from_delta(algorithm, &old, patch, &sha, 1 << 20).unwrap();
+            assert_eq!(zip, new, "{algorithm:?}");
+            let out = tempfile::tempdir().unwrap();
+            extract_zip(Cursor::new(zip), out.path()).unwrap();
+            assert_eq!(std::fs::read(out.path().join("assets/chunk.js")).unwrap(), b"export {}");
+
+            // Applied to any other base, the result isn't the manifest's zip.
+            assert!(rebuild_from_delta(algorithm, &new, patch, &sha, 1 << 20).is_err(), "{algorithm:?}");
+            assert_eq!(rebuild_from_delta(algorithm, &old, patch, &sha, 64), Err(WebUpdateError::TooLarge));
+        }
+        assert!(rebuild_from_delta(DeltaAlgorithm::Unsupported, &old, &zstd_patch, &sha, 1 << 20).is_err());
+    }
+
+    #[test]
+    fn deltas_only_apply_to_their_base_version() {
+        let json = serde_json::json!({
+            "v": 1, "version": "0.3.0", "zip_url": "https://example.com/web.zip", "sha256": "ab".repeat(32),
+            "delta": { "from_version": "0.2.0", "patch_url": "https://example.com/0.2.0.patch", "sha256": "cd".repeat(32), "algorithm": "zstd-dict" },
+        });
+        let mut m: WebBundleManifestV1 = serde_json::from_value(json).unwrap();
+        assert_eq!(usable_delta(&m, "0.2.0").map(|d| d.algorithm), Some(DeltaAlgorithm::ZstdDict));
+        assert_eq!(usable_delta(&m, "0.2.1"), None);
+
+        // Algorithms this build doesn't know don't break the manifest.
+        let json = serde_json::json!({ "from_version": "0.2.0", "patch_url": "https://example.com/p", "sha256": "cd".repeat(32), "algorithm": "xdelta3" });
+        m.delta = Some(serde_json::from_value(json).unwrap());
+        assert_eq!(m.delta.as_ref().unwrap().algorithm, DeltaAlgorithm::Unsupported);
+        assert_eq!(usable_delta(&m, "0.2.0"), None);
+    }
+
+    #[test]
+    fn only_the_new_and_active_source_zips_are_kept() {
+        let cache = tempfile::tempdir().unwrap();
+        let part = |name: &str| {
+            let p = cache.path().join(name);
+            std::fs::write(&p, name).unwrap();
+            p
+        };
+        keep_source_zip_in(cache.path(), "0.2.0", &part("a.part"), "0.1.0").unwrap();
+        keep_source_zip_in(cache.path(), "0.3.0", &part("b.part"), "0.2.0").unwrap();
+        keep_source_zip_in(cache.path(), "0.4.0", &part("c.part"), "0.2.0").unwrap();
+
+        let mut kept: Vec<_> = std::fs::read_dir(cache.path().join(SOURCE_ZIPS_DIR))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        kept.sort();
+        assert_eq!(kept, vec!["0.2.0.zip", "0.4.0.zip"]);
+        assert_eq!(std::fs::read(source_zip_in(cache.path(), "0.4.0")).unwrap(), b"c.part");
+        assert!(!cache.path().join("c.part").exists());
+    }
+
+    #[test]
+    fn download_progress_is_reported_in_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("web.zip.part");
+        let body = vec![b'x'; (PROGRESS_STEP * 2 + 100) as usize];
+        let mut response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).into_bytes();
+        response.extend_from_slice(&body);
+        let url = serve_once(response);
+        let sha = hex::encode(Sha256::digest(&body));
+        let seen = Mutex::new(Vec::new());
+        let on_progress = |done: u64, total: Option<u64>| seen.lock().unwrap().push((done, total));
+        let client = reqwest::Client::new();
+        tauri::async_runtime::block_on(download_zip_once(&client, &local(&url), &dest, &sha, 1 << 20, false, &on_progress)).unwrap();
+
+        let seen = seen.into_inner().unwrap();
+        let total = Some(body.len() as u64);
+        assert_eq!(seen.first(), Some(&(0, total)));
+        assert_eq!(seen.last(), Some(&(body.len() as u64, total)));
+        // Chunk sizes vary, but there's one report per step plus the start and end.
+        assert!((3..=4).contains(&seen.len()), "{seen:?}");
+        assert!(seen.windows(2).all(|w| w[0].0 < w[1].0), "{seen:?}");
+    }
+
+    #[test]
+    fn dev_overrides_are_urls_or_bundle_dirs() {
+        assert_eq!(parse_dev_override(" http://localhost:5173 "), Ok(DevOverride::Url("http://localhost:5173/".into())));
+        let e = command_error(parse_dev_override("file:///tmp/dist").unwrap_err());
+        assert_eq!(e.code, WebUpdateErrorCode::InvalidDevOverride);
+        assert_eq!(e.message, "invalid dev override: file:///tmp/dist is not an http(s) URL");
+
+        let dist = tempfile::tempdir().unwrap();
+        assert!(parse_dev_override(dist.path().to_str().unwrap()).is_err());
+        std::fs::write(dist.path().join("index.html"), "<html></html>").unwrap();
+        let dir = dist.path().canonicalize().unwrap();
+        assert_eq!(parse_dev_override(dist.path().to_str().unwrap()), Ok(DevOverride::Dir(dir.clone())));
+        assert!(parse_dev_override(dist.path().join("missing").to_str().unwrap()).is_err());
+
+        assert_eq!(
+            serde_json::to_value(DevOverride::Dir(dir.clone())).unwrap(),
+            serde_json::json!({ "kind": "dir", "target": dir })
+        );
+    }
+
+    #[test]
+    fn release_builds_confirm_dev_overrides() {
+        let url = DevOverride::Url("http://localhost:5173/".into());
+        assert_eq!(check_dev_override_allowed(Some(&url), true, false), Err(WebUpdateError::DevOverrideNotConfirmed));
+        assert_eq!(check_dev_override_allowed(Some(&url), true, true), Ok(()));
+        assert_eq!(check_dev_override_allowed(Some(&url), false, false), Ok(()));
+        // Going back to the managed bundle is always allowed.
+        assert_eq!(check_dev_override_allowed(None, true, false), Ok(()));
+        assert_eq!(command_error(WebUpdateError::DevOverrideNotConfirmed).code, WebUpdateErrorCode::DevOverrideNotConfirmed);
+    }
+
+    fn reset_fixture() -> tempfile::TempDir {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.1.0");
+        install(cache.path(), "0.2.0");
+        std::fs::create_dir_all(cache.path().join("web_bundles/.tmp-0.3.0-1-2-0/assets")).unwrap();
+        std::fs::write(cache.path().join("web_bundles/.tmp-0.3.0-1-2-0/assets/app.js"), [b'x'; 100]).unwrap();
+        std::fs::create_dir_all(cache.path().join("downloads")).unwrap();
+        std::fs::write(cache.path().join("downloads/0.3.0-ab.zip.part"), [b'x'; 50]).unwrap();
+        for f in [ACTIVE_VERSION_FILE, LAST_CHECK_FILE, FEED_FILE, SERVER_PORT_FILE] {
+            std::fs::write(cache.path().join(f), "x").unwrap();
+        }
+        cache
+    }
+
+    #[test]
+    fn reset_removes_everything_but_the_embedded_bundle() {
+        let cache = reset_fixture();
+        let result = clear_cache_in(cache.path(), "0.1.0", true).unwrap();
+        assert_eq!(
+            result.removed,
+            vec![
+                "web_bundles/.tmp-0.3.0-1-2-0",
+                "web_bundles/0.2.0",
+                "downloads",
+                ACTIVE_VERSION_FILE,
+                LAST_CHECK_FILE
+            ]
+        );
+        assert_eq!(result.bytes_reclaimed, 100 + 13 + 50 + 1 + 1);
+        assert_eq!((result.failed.len(), result.active_version.as_str()), (0, "0.1.0"));
+        assert_eq!(installed(cache.path()), vec!["0.1.0"]);
+        assert!(cache.path().join(FEED_FILE).exists());
+
+        let result = clear_cache_in(cache.path(), "0.1.0", false).unwrap();
+        assert_eq!(result.removed, vec![FEED_FILE]);
+        assert!(cache.path().join(SERVER_PORT_FILE).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reset_never_follows_links_out_of_the_cache() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("keep.txt"), "mine").unwrap();
+        let cache = reset_fixture();
+        std::os::unix::fs::symlink(outside.path(), cache.path().join("web_bundles/0.9.0")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), cache.path().join(SOURCE_ZIPS_DIR)).unwrap();
+
+        let result = clear_cache_in(cache.path(), "0.1.0", true).unwrap();
+        assert!(result.removed.contains(&"web_bundles/0.9.0".to_string()));
+        assert!(result.removed.contains(&SOURCE_ZIPS_DIR.to_string()));
+        assert_eq!(std::fs::read_to_string(outside.path().join("keep.txt")).unwrap(), "mine");
+
+        let canonical = cache.path().canonicalize().unwrap();
+        let e = remove_inside(&canonical, &outside.path().join("keep.txt")).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(outside.path().join("keep.txt").exists());
+    }
+
+    #[test]
+    fn download_cap_applies_while_streaming() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("web.zip.part");
+        let zip = fixture_zip(&[("index.html", &[b'x'; 2048])]);
+        // No Content-Length, so the size is only known once the body has been read.
+        let mut response = b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n".to_vec();
+        response.extend_from_slice(&zip);
+        let url = serve_once(response);
+        let sha = hex::encode(Sha256::digest(&zip));
+        let client = reqwest::Client::new();
+        let e = tauri::async_runtime::block_on(download_zip_once(&client, &local(&url), &dest, &sha, 1024, false, &|_, _| {})).unwrap_err();
+        assert_eq!(e, WebUpdateError::TooLarge);
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn overlapping_downloads_are_refused_while_one_runs() {
+        let cache = tempfile::tempdir().unwrap();
+        let zip = fixture_zip(&[("index.html", b"<html>0.2.0</html>")]);
+        let sha = hex::encode(Sha256::digest(&zip));
+        let mut response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", zip.len()).into_bytes();
+        response.extend_from_slice(&zip);
+        // The response is held back until the second download has been turned away.
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = format!("http://127.0.0.1:{}/web.zip", listener.local_addr().unwrap().port());
+        std::thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let _ = s.read(&mut [0u8; 4096]);
+            let _ = released.recv();
+            let _ = s.write_all(&response);
+        });
+
+        let operation = OperationLock::default();
+        let client = reqwest::Client::new();
+        let part = download_path(cache.path(), "0.2.0", &sha, "zip").unwrap();
+        let download = || async {
+            let _op = operation.try_begin(UpdateOperation::Download)?;
+            download_zip(&client, &local(&url), &part, &sha, 1 << 20, &|_, _| {}).await?;
+            let zip = std::fs::File::open(&part).map_err(|e| e.to_string())?;
+            install_bundle_in(cache.path(), zip, "0.2.0", None).map_err(WebUpdateError::ExtractFailed)
+        };
+        // `join!` polls the first download first, so it holds the lock by the time the second starts.
+        let (first, second) = tauri::async_runtime::block_on(async {
+            tokio::join!(download(), async {
+                let second = download().await;
+                release.send(()).unwrap();
+                second
+            })
+        });
+
+        let e = command_error(second.unwrap_err());
+        assert_eq!((e.code, e.retryable), (WebUpdateErrorCode::Busy, true));
+        assert_eq!(e.message, "a web update download is already in progress");
+        assert_eq!(e.details, Some(serde_json::json!({ "operation": "download" })));
+        assert_eq!(first.unwrap(), bundle_dir_in(cache.path(), "0.2.0"));
+        assert_eq!(installed(cache.path()), vec!["0.2.0"]);
+        assert_eq!(read_text_file(&bundle_dir_in(cache.path(), "0.2.0").join("index.html")), "<html>0.2.0</html>");
+        assert_eq!(operation.current(), None);
+        assert!(operation.try_begin(UpdateOperation::Rollback).is_ok());
+    }
+
+    fn cache_entry(feed: &str) -> ManifestCache {
+        ManifestCache {
+            feed_url: feed.into(),
+            etag: Some("\"abc\"".into()),
+            last_modified: Some("Wed, 21 Oct 2026 07:28:00 GMT".into()),
+            manifest: WebBundleManifestV1 {
+                v: 1,
+                version: "0.3.0".into(),
+                zip_url: "https://example.com/web.zip".into(),
+                sha256: "ab".repeat(32),
+                files: None,
+                min_version: None,
+                notes: None,
+                notes_url: None,
+                delta: None,
+            },
+        }
+    }
+
+    #[test]
+    fn manifest_cache_is_keyed_by_feed_url() {
+        let cache = tempfile::tempdir().unwrap();
+        let stable = normalize_feed_url("gh:o/r", UpdateChannel::Stable);
+        let beta = normalize_feed_url("gh:o/r", UpdateChannel::Beta);
+        assert_eq!(load_manifest_cache_in(cache.path(), &stable), None);
+
+        let entry = cache_entry(&stable);
+        save_manifest_cache_in(cache.path(), &entry).unwrap();
+        assert_eq!(load_manifest_cache_in(cache.path(), &stable), Some(entry));
+        assert_eq!(load_manifest_cache_in(cache.path(), &beta), None);
+        assert_eq!(load_manifest_cache_in(cache.path(), "https://other.example/m.json"), None);
+    }
+
+    #[test]
+    fn conditional_headers_come_from_the_cache_entry() {
+        assert!(conditional_headers(None).is_empty());
+
+        let mut entry = cache_entry("https://example.com/m.json");
+        let h = conditional_headers(Some(&entry));
+        assert_eq!(
+            h,
+            vec![
+                (reqwest::header::IF_NONE_MATCH, "\"abc\"".to_string()),
+                (reqwest::header::IF_MODIFIED_SINCE, "Wed, 21 Oct 2026 07:28:00 GMT".to_string()),
+            ]
+        );
+        entry.etag = None;
+        assert_eq!(conditional_headers(Some(&entry)).len(), 1);
+    }
+
+    #[test]
+    fn last_check_serializes_for_the_ui() {
+        let c = LastCheck {
+            checked_at: 1_700_000_000_000,
+            result: LastCheckResult::Available { version: "0.3.0".into(), downgrade: false },
+            source: Some("gh:o/r".into()),
+            sources: vec![FeedAttempt { feed: "gh:o/r".into(), url: "https://example.com/m.json".into(), error: None }],
+        };
+        assert_eq!(
+            serde_json::to_value(&c).unwrap(),
+            serde_json::json!({
+                "checked_at": 1_700_000_000_000u64,
+                "result": { "result": "available", "version": "0.3.0", "downgrade": false },
+                "source": "gh:o/r",
+                "sources": [{ "feed": "gh:o/r", "url": "https://example.com/m.json", "error": null }]
+            })
+        );
+        // Written before fallback feeds existed.
+        let old: LastCheck = serde_json::from_str(r#"{"checked_at":1,"result":{"result":"up_to_date"}}"#).unwrap();
+        assert_eq!((old.source, old.sources), (None, vec![]));
+        assert_eq!(
+            serde_json::to_value(LastCheckResult::UpToDate).unwrap(),
+            serde_json::json!({ "result": "up_to_date" })
+        );
+    }
+
+    #[test]
+    fn only_the_exact_skipped_version_is_skipped() {
+        assert!(is_skipped(Some("0.4.2"), "0.4.2"));
+        assert!(is_skipped(Some("0.4.2"), " 0.4.2\n"));
+        assert!(!is_skipped(Some("0.4.2"), "0.4.3"));
+        assert!(!is_skipped(Some("0.4.2"), "0.4.2-beta.1"));
+        assert!(!is_skipped(None, "0.4.2"));
+        assert_eq!(
+            serde_json::to_value(LastCheckResult::Skipped { version: "0.4.2".into() }).unwrap(),
+            serde_json::json!({ "result": "skipped", "version": "0.4.2" })
+        );
+    }
+
+    #[test]
+    fn history_entries_serialize_flat() {
+        let entry = UpdateHistoryEntry {
+            at: 1_700_000_000_000,
+            event: UpdateHistoryEvent::Download {
+                version: "0.3.0".into(),
+                kind: DownloadKind::Delta,
+                bytes: 1024,
+                duration_ms: 250,
+                error: Some("hash mismatch".into()),
+            },
+        };
+        let json = serde_json::json!({
+            "at": 1_700_000_000_000u64,
+            "event": "download",
+            "version": "0.3.0",
+            "kind": "delta",
+            "bytes": 1024,
+            "duration_ms": 250,
+            "error": "hash mismatch"
+        });
+        assert_eq!(serde_json::to_value(&entry).unwrap(), json);
+        assert_eq!(serde_json::from_value::<UpdateHistoryEntry>(json).unwrap(), entry);
+
+        let check = UpdateHistoryEntry {
+            at: 1,
+            event: UpdateHistoryEvent::Check { result: LastCheckResult::UpToDate, source: None, latency_ms: 80 },
+        };
+        assert_eq!(
+            serde_json::to_value(&check).unwrap(),
+            serde_json::json!({ "at": 1, "event": "check", "result": { "result": "up_to_date" }, "source": null, "latency_ms": 80 })
+        );
+    }
+
+    #[test]
+    fn history_is_read_newest_first_across_rotations() {
+        let cache = tempfile::tempdir().unwrap();
+        assert_eq!(read_history_in(cache.path(), 10), vec![]);
+        assert_eq!(last_activation_in(cache.path()), None);
+
+        let activation = |n: usize| UpdateHistoryEvent::Activation { version: format!("0.{n}.0"), channel: None, previous: "0.1.0".into() };
+        let rollback = |n: usize| UpdateHistoryEvent::Rollback { from: format!("0.{n}.0"), to: "0.1.0".into() };
+        let mut n = 0;
+        while !isnad::rotated_jsonl_path(&cache.path().join(HISTORY_FILE)).exists() {
+            n += 1;
+            record_history_in(cache.path(), activation(n));
+        }
+        let rotated = n - 1;
+        record_history_in(cache.path(), rollback(n));
+
+        let events: Vec<_> = read_history_in(cache.path(), 3).into_iter().map(|e| e.event).collect();
+        assert_eq!(events, vec![rollback(n), activation(n), activation(n - 1)]);
+        assert_eq!(read_history_in(cache.path(), usize::MAX).len(), n + 1);
+        assert_eq!(last_activation_in(cache.path()).map(|e| e.event), Some(activation(n)));
+
+        // The next rotation drops the oldest file's worth of entries.
+        for m in n + 1..=n + rotated {
+            record_history_in(cache.path(), activation(m));
+        }
+        let all = read_history_in(cache.path(), usize::MAX);
+        assert!(all.len() <= rotated + 2, "{} entries kept", all.len());
+        assert_eq!(all.first().map(|e| &e.event), Some(&activation(n + rotated)));
+        assert!(!all.iter().any(|e| e.event == activation(1)));
+
+        // A torn line from a crash mid-write is skipped.
+        let mut file = std::fs::OpenOptions::new().append(true).open(cache.path().join(HISTORY_FILE)).unwrap();
+        std::io::Write::write_all(&mut file, b"{\"at\":").unwrap();
+        assert_eq!(read_history_in(cache.path(), usize::MAX), all);
+    }
+
+    #[test]
+    fn etags_follow_file_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.js");
+        std::fs::write(&path, b"one").unwrap();
+        let cache = EtagCache::default();
+        let first = cache.get(&path).unwrap();
+        assert!(first.starts_with('"') && first.ends_with('"') && first.len() == 34, "{first}");
+        assert_eq!(cache.get(&path).unwrap(), first);
+
+        // A different size invalidates even when the mtime doesn't move.
+        std::fs::write(&path, b"three").unwrap();
+        let second = cache.get(&path).unwrap();
+        assert_ne!(second, first);
+
+        cache.clear();
+        assert!(cache.0.lock().unwrap().is_empty());
+        assert_eq!(cache.get(&path).unwrap(), second);
+    }
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let etag = "\"abc\"";
+        assert!(etag_matches("\"abc\"", etag));
+        assert!(etag_matches("W/\"abc\"", etag));
+        assert!(etag_matches("\"x\", \"abc\"", etag));
+        assert!(etag_matches("*", etag));
+        assert!(!etag_matches("\"abcd\"", etag));
+        assert!(!etag_matches("", etag));
+    }
+
+    #[test]
+    fn only_hashed_assets_are_immutable() {
+        let immutable = "public, max-age=31536000, immutable";
+        assert_eq!(cache_control_for(Path::new("index.html")), "no-cache");
+        assert_eq!(cache_control_for(Path::new("assets/index-B3xk9_Qa.js")), immutable);
+        assert_eq!(cache_control_for(Path::new("assets/vendor.4f9c2d1e.css")), immutable);
+        assert_eq!(cache_control_for(Path::new("favicon.ico")), "no-cache");
+        assert_eq!(cache_control_for(Path::new("assets/icon-large.svg")), "no-cache");
+        assert_eq!(cache_control_for(Path::new("assets/page-a1b2c3d4.html")), "no-cache");
+    }
+
+    fn read_range(path: &Path, header: &str) -> Option<Vec<u8>> {
+        let mut f = std::fs::File::open(path).unwrap();
+        let len = f.metadata().unwrap().len();
+        match parse_range(header, len) {
+            ByteRange::Partial { start, end } => {
+                f.seek(std::io::SeekFrom::Start(start)).unwrap();
+                let mut out = Vec::new();
+                f.take(end - start + 1).read_to_end(&mut out).unwrap();
+                Some(out)
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn ranges_slice_a_fixture_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.webm");
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(read_range(&path, "bytes=100-").unwrap(), &data[100..]);
+        assert_eq!(read_range(&path, "bytes=-500").unwrap(), &data[500..]);
+        assert_eq!(read_range(&path, "bytes=10-19").unwrap(), &data[10..20]);
+        // The end is clamped to the file, and a suffix longer than the file is the whole file.
+        assert_eq!(read_range(&path, "bytes=990-5000").unwrap(), &data[990..]);
+        assert_eq!(read_range(&path, "bytes=-5000").unwrap(), data);
+    }
+
+    #[test]
+    fn out_of_bounds_ranges_are_unsatisfiable() {
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=2000-3000", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-10", 0), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn multi_range_and_malformed_headers_get_the_whole_file() {
+        for h in ["bytes=0-1,5-6", "bytes=5-1", "bytes=a-b", "bytes=-", "items=0-1", "bytes=10", ""] {
+            assert_eq!(parse_range(h, 1000), ByteRange::Full, "{h}");
+        }
+        assert_eq!(parse_range(" bytes= 1 - 2 ", 1000), ByteRange::Partial { start: 1, end: 2 });
+    }
+
+    #[test]
+    fn accept_encoding_honours_q_values() {
+        assert!(accepts_encoding("gzip, deflate, br", "br"));
+        assert!(accepts_encoding("GZIP;q=0.5", "gzip"));
+        assert!(!accepts_encoding("gzip;q=0, br", "gzip"));
+        assert!(!accepts_encoding("identity", "gzip"));
+        assert!(accepts_encoding("*", "br"));
+        assert!(!accepts_encoding("*, br;q=0", "br"));
+        assert!(!accepts_encoding("", "gzip"));
+    }
+
+    #[test]
+    fn precompressed_siblings_are_negotiated() {
+        let dir = tempfile::tempdir().unwrap();
+        let js = dir.path().join("app.js");
+        let css = dir.path().join("app.css");
+        let svg = dir.path().join("logo.svg");
+        for p in [&js, &css, &svg] {
+            std::fs::write(p, b"plain").unwrap();
+        }
+        std::fs::write(dir.path().join("app.js.br"), b"br").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), b"gz").unwrap();
+        std::fs::write(dir.path().join("app.css.gz"), b"gz").unwrap();
+
+        assert_eq!(precompressed_variant(&js, "gzip, br"), Some(("br", dir.path().join("app.js.br"))));
+        assert_eq!(precompressed_variant(&js, "gzip"), Some(("gzip", dir.path().join("app.js.gz"))));
+        assert_eq!(precompressed_variant(&css, "gzip, br"), Some(("gzip", dir.path().join("app.css.gz"))));
+        assert_eq!(precompressed_variant(&js, "identity"), None);
+        assert_eq!(precompressed_variant(&svg, "gzip, br"), None);
+
+        // Different bytes on the wire, different ETags.
+        let etags = EtagCache::default();
+        let plain = etags.get(&js).unwrap();
+        assert_ne!(plain, etags.get(&dir.path().join("app.js.br")).unwrap());
+        assert_ne!(plain, etags.get(&dir.path().join("app.js.gz")).unwrap());
+    }
+
+    #[test]
+    fn token_is_required_in_the_path_or_a_cookie() {
+        let token = "ab".repeat(32);
+        let via_path = |p: &str| Some(Authorized::ViaPath(p.to_string()));
+        let via_cookie = |p: &str| Some(Authorized::ViaCookie(p.to_string()));
+
+        assert_eq!(authorize(&format!("/t/{token}/"), None, &token), via_path("/"));
+        assert_eq!(authorize(&format!("/t/{token}"), None, &token), via_path("/"));
+        assert_eq!(authorize(&format!("/t/{token}/assets/a.js"), None, &token), via_path("/assets/a.js"));
+        assert_eq!(authorize("/t/wrong/index.html", None, &token), None);
+        assert_eq!(authorize("/index.html", None, &token), None);
+
+        let cookie = format!("theme=dark; voxelle_token={token}");
+        assert_eq!(authorize("/assets/a.js", Some(&cookie), &token), via_cookie("/assets/a.js"));
+        assert_eq!(authorize("/assets/a.js", Some("voxelle_token=nope"), &token), None);
+        // A good cookie doesn't rescue a bad path token.
+        assert_eq!(authorize("/t/wrong/", Some(&cookie), &token), None);
+    }
+
+    fn get(port: u16, path: &str, cookie: Option<&str>) -> String {
+        use std::net::TcpStream;
+
+        let mut s = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let cookie = cookie.map(|c| format!("Cookie: {c}\r\n")).unwrap_or_default();
+        write!(s, "GET {path} HTTP/1.1\r\nHost: 127.0.0.1\r\n{cookie}Connection: close\r\n\r\n").unwrap();
+        let mut out = String::new();
+        s.read_to_string(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn server_rejects_requests_without_the_token() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("index.html"), "<html></html>").unwrap();
+        let server = WebBundleServer::start(root.path().canonicalize().unwrap(), None).unwrap();
+        let port = server.port();
+        let base = server.base_url();
+        let prefix = base.trim_start_matches(&format!("http://127.0.0.1:{port}")).to_string();
+
+        assert!(get(port, "/", None).starts_with("HTTP/1.1 403"));
+        assert!(get(port, "/t/0000/", None).starts_with("HTTP/1.1 403"));
+        assert!(get(port, "/", Some("voxelle_token=0000")).starts_with("HTTP/1.1 403"));
+
+        let ok = get(port, &prefix, None);
+        assert!(ok.starts_with("HTTP/1.1 200"), "{ok}");
+        assert!(ok.contains("<html></html>"));
+        let cookie = ok
+            .lines()
+            .find_map(|l| l.strip_prefix("Set-Cookie: "))
+            .and_then(|c| c.split(';').next())
+            .unwrap()
+            .to_string();
+        assert!(get(port, "/index.html", Some(&cookie)).starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn bundles_adjust_the_default_csp() {
+        let default = SecurityHeaders::default();
+        assert!(default.policy().starts_with("default-src 'self'; script-src 'self'; style-src 'self'; img-src 'self' data: blob:"));
+        assert!(!default.report_only && !default.cross_origin_isolated);
+
+        let csp = br#"{ "directives": { "style-src": ["'self'", "'unsafe-inline'"], "worker-src": ["'self'", "blob:"] }, "cross_origin_isolated": true }"#;
+        let adjusted = SecurityHeaders::parse(csp, false).unwrap();
+        let policy = adjusted.policy();
+        assert!(policy.contains("; style-src 'self' 'unsafe-inline'; img-src"), "replaced in place: {policy}");
+        assert!(policy.contains("script-src 'self';"), "{policy}");
+        assert!(policy.ends_with("; worker-src 'self' blob:"), "{policy}");
+        assert!(adjusted.cross_origin_isolated);
+
+        // Report-only is a debug-build escape hatch.
+        let lax = br#"{ "enforce": false }"#;
+        assert!(SecurityHeaders::parse(lax, true).unwrap().report_only);
+        assert!(!SecurityHeaders::parse(lax, false).unwrap().report_only);
+
+        for bad in [
+            &br#"{ "directives": { "script-src": ["'self'; script-src *"] } }"#[..],
+            br#"{ "directives": { "script-src": ["'self'", "https://a.example,https://b.example"] } }"#,
+            br#"{ "directives": { "script-src": ["'self'\r\nSet-Cookie: x=y"] } }"#,
+            br#"{ "directives": { "script-src": [""] } }"#,
+            br#"{ "directives": { "script-src": [] } }"#,
+            br#"{ "directives": { "sript-src": ["*"] } }"#,
+            br#"{ "directive": { "script-src": ["*"] } }"#,
+            b"{ not json",
+        ] {
+            assert!(SecurityHeaders::parse(bad, true).is_err(), "{}", String::from_utf8_lossy(bad));
+        }
+    }
+
+    #[test]
+    fn responses_carry_the_security_headers() {
+        let broken = tempfile::tempdir().unwrap();
+        std::fs::write(broken.path().join("index.html"), "<html></html>").unwrap();
+        std::fs::create_dir(broken.path().join("assets")).unwrap();
+        std::fs::write(broken.path().join("assets/app-1a2b3c4d.js"), "console.log(1)").unwrap();
+        std::fs::write(broken.path().join(CSP_FILE), "{ \"directives\": ").unwrap();
+        let server = WebBundleServer::start(broken.path().canonicalize().unwrap(), None).unwrap();
+        let port = server.port();
+        let base = server.base_url();
+        let prefix = base.trim_start_matches(&format!("http://127.0.0.1:{port}")).to_string();
+        let head = |path: &str| {
+            let resp = get(port, &format!("{prefix}{path}"), None);
+            resp.split_once("\r\n\r\n").unwrap().0.to_string()
+        };
+        let default_csp = format!("Content-Security-Policy: {}", SecurityHeaders::default().policy());
+        let secured = |head: &str| {
+            head.contains(&default_csp)
+                && head.contains("X-Content-Type-Options: nosniff")
+                && head.contains("Referrer-Policy: no-referrer")
+        };
+
+        // A malformed csp.json falls back to the default rather than breaking serving.
+        for path in ["", "index.html", "assets/app-1a2b3c4d.js", "__voxelle/config.json"] {
+            let head = head(path);
+            assert!(head.starts_with("HTTP/1.1 200"), "{path}: {head}");
+            assert!(secured(&head), "{path}: {head}");
+            assert!(!head.contains("Cross-Origin-Opener-Policy"), "{path}: {head}");
+        }
+
+        // Refusals and errors carry them too.
+        let forbidden = get(port, "/index.html", None);
+        assert!(forbidden.starts_with("HTTP/1.1 403"), "{forbidden}");
+        assert!(secured(&forbidden), "{forbidden}");
+        let unsatisfiable = {
+            let mut s = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+            write!(s, "GET {prefix}index.html HTTP/1.1\r\nHost: 127.0.0.1\r\nRange: bytes=999-\r\nConnection: close\r\n\r\n").unwrap();
+            let mut out = String::new();
+            s.read_to_string(&mut out).unwrap();
+            out
+        };
+        assert!(unsatisfiable.starts_with("HTTP/1.1 416"), "{unsatisfiable}");
+        assert!(secured(&unsatisfiable), "{unsatisfiable}");
+
+        let isolated = tempfile::tempdir().unwrap();
+        std::fs::write(isolated.path().join("index.html"), "<html>isolated</html>").unwrap();
+        let csp = r#"{ "directives": { "style-src": ["'self'", "'unsafe-inline'"] }, "cross_origin_isolated": true }"#;
+        std::fs::write(isolated.path().join(CSP_FILE), csp).unwrap();
+        server.set_root(isolated.path().canonicalize().unwrap());
+        let head = head("index.html");
+        assert!(head.contains("style-src 'self' 'unsafe-inline'"), "{head}");
+        assert!(head.contains("Cross-Origin-Opener-Policy: same-origin"), "{head}");
+        assert!(head.contains("Cross-Origin-Embedder-Policy: require-corp"), "{head}");
+        server.shutdown();
+    }
+
+    #[test]
+    fn runtime_config_is_served_live_and_never_from_the_bundle() {
+        let old = tempfile::tempdir().unwrap();
+        std::fs::write(old.path().join("index.html"), "<html></html>").unwrap();
+        // A bundle can't stand in for the endpoint.
+        std::fs::create_dir(old.path().join("__voxelle")).unwrap();
+        std::fs::write(old.path().join("__voxelle/config.json"), r#"{"desktop":false}"#).unwrap();
+        let server = WebBundleServer::start(old.path().canonicalize().unwrap(), None).unwrap();
+        let port = server.port();
+        server.update_config(|c| c.active_version = "0.1.0".into());
+        let base = server.base_url();
+        let prefix = base.trim_start_matches(&format!("http://127.0.0.1:{port}")).to_string();
+        let config = |path: &str| {
+            let resp = get(port, &format!("{prefix}{path}"), None);
+            let (head, body) = resp.split_once("\r\n\r\n").unwrap();
+            (head.to_string(), serde_json::from_str::<serde_json::Value>(body).ok())
+        };
+
+        let (head, body) = config("__voxelle/config.json");
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+        assert!(head.contains("Cache-Control: no-store"), "{head}");
+        let body = body.unwrap();
+        assert_eq!(body["desktop"], true);
+        assert_eq!(body["port"], port);
+        assert_eq!(body["active_version"], "0.1.0");
+        assert_eq!(body["signal_relay_url"], serde_json::Value::Null);
+
+        // What an update does on activation: new root, new version, same server.
+        let new = tempfile::tempdir().unwrap();
+        std::fs::write(new.path().join("index.html"), "<html>new</html>").unwrap();
+        server.set_root(new.path().canonicalize().unwrap());
+        server.update_config(|c| {
+            c.active_version = "0.2.0".into();
+            c.active_channel = Some(UpdateChannel::Beta);
+            c.signal_relay_url = Some("ws://127.0.0.1:7777".into());
+        });
+        let body = config("__voxelle/config.json").1.unwrap();
+        assert_eq!(body["active_version"], "0.2.0");
+        assert_eq!(body["active_channel"], "beta");
+        assert_eq!(body["signal_relay_url"], "ws://127.0.0.1:7777");
+
+        // Unknown runtime paths don't fall back to index.html.
+        let (head, body) = config("__voxelle/other.json");
+        assert!(head.starts_with("HTTP/1.1 404"), "{head}");
+        assert!(body.is_none());
+        server.shutdown();
+    }
+
+    #[test]
+    fn server_can_be_stopped_and_restarted() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("index.html"), "<html></html>").unwrap();
+        let root = root.path().canonicalize().unwrap();
+
+        for i in 0..5 {
+            let server = WebBundleServer::start(root.clone(), None).unwrap();
+            let port = server.port();
+            let thread = Arc::downgrade(&server.thread);
+            assert!(get(port, "/", None).starts_with("HTTP/1.1 403"));
+
+            if i % 2 == 0 {
+                server.shutdown();
+            } else {
+                // Dropping the last handle stops it too.
+                drop(server);
+            }
+            assert!(thread.upgrade().is_none(), "server thread still referenced");
+            // tiny_http closes its listener from its own accept thread, so allow it a moment.
+            wait_until_free(port);
+        }
+    }
+
+    fn wait_until_free(port: u16) {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while std::net::TcpListener::bind(("127.0.0.1", port)).is_err() {
+            assert!(std::time::Instant::now() < deadline, "port {port} still bound after shutdown");
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn server_restarts_on_its_previous_port_when_free() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path().canonicalize().unwrap();
+
+        let server = WebBundleServer::start(root.clone(), None).unwrap();
+        let port = server.port();
+        server.shutdown();
+        wait_until_free(port);
+
+        let server = WebBundleServer::start(root.clone(), Some(port)).unwrap();
+        assert_eq!(server.port(), port);
+        assert!(server.base_url().starts_with(&format!("http://127.0.0.1:{port}/")));
+
+        // Taken (here by the running server): fall back to a fresh port.
+        let other = WebBundleServer::start(root, Some(port)).unwrap();
+        assert_ne!(other.port(), port);
+        assert_ne!(other.port(), 0);
+    }
+
+    #[test]
+    fn pending_activation_switches_the_active_version_once() {
+        let cache = tempfile::tempdir().unwrap();
+        assert_eq!(apply_pending_in(cache.path(), "0.1.0"), Ok(None));
+
+        install(cache.path(), "0.2.0");
+        std::fs::write(cache.path().join(ACTIVE_VERSION_FILE), "0.1.5").unwrap();
+        let pending = PendingActivation { version: "0.2.0".into(), channel: Some(UpdateChannel::Beta) };
+        write_pending_in(cache.path(), &pending).unwrap();
+        assert_eq!(read_pending_in(cache.path()), Some(pending));
+
+        assert_eq!(apply_pending_in(cache.path(), "0.1.0"), Ok(Some("0.2.0".into())));
+        assert_eq!(read_text_file(&cache.path().join(ACTIVE_VERSION_FILE)), "0.2.0");
+        assert_eq!(read_text_file(&cache.path().join(ACTIVE_CHANNEL_FILE)), "beta");
+        // Rollback goes back to what was running before.
+        assert_eq!(read_text_file(&cache.path().join(PREVIOUS_VERSION_FILE)), "0.1.5");
+        assert!(!cache.path().join(PENDING_ACTIVATION_FILE).exists());
+        assert_eq!(apply_pending_in(cache.path(), "0.1.0"), Ok(None));
+        assert_eq!(
+            last_activation_in(cache.path()).map(|e| e.event),
+            Some(UpdateHistoryEvent::Activation { version: "0.2.0".into(), channel: Some(UpdateChannel::Beta), previous: "0.1.5".into() })
+        );
+    }
+
+    #[test]
+    fn pending_activation_of_a_missing_bundle_is_dropped() {
+        let cache = tempfile::tempdir().unwrap();
+        std::fs::write(cache.path().join(ACTIVE_VERSION_FILE), "0.1.5").unwrap();
+        let marker = cache.path().join(PENDING_ACTIVATION_FILE);
+
+        write_pending_in(cache.path(), &PendingActivation { version: "0.2.0".into(), channel: None }).unwrap();
+        assert_eq!(apply_pending_in(cache.path(), "0.1.0"), Ok(None));
+        assert!(!marker.exists());
+
+        install(cache.path(), "0.2.0");
+        for junk in ["not json", r#"{"version":"../etc","channel":null}"#] {
+            std::fs::write(&marker, junk).unwrap();
+            assert_eq!(apply_pending_in(cache.path(), "0.1.0"), Ok(None));
+            assert!(!marker.exists(), "{junk}");
+        }
+        assert_eq!(read_text_file(&cache.path().join(ACTIVE_VERSION_FILE)), "0.1.5");
+    }
+
+    fn planned(cache: &Path, version: &str, previous: &str, tmp_dir: Option<PathBuf>) -> PlannedActivation {
+        PlannedActivation {
+            version: version.into(),
+            channel: Some(UpdateChannel::Beta),
+            previous: previous.into(),
+            previous_channel: Some(UpdateChannel::Stable),
+            tmp_dir,
+            final_dir: bundle_dir_in(cache, version),
+        }
+    }
+
+    fn active_in(cache: &Path) -> (String, String) {
+        (read_text_file(&cache.join(ACTIVE_VERSION_FILE)), read_text_file(&cache.join(ACTIVE_CHANNEL_FILE)))
+    }
+
+    // 0.1.0 active on stable, and 0.2.0 extracted and journaled to replace it:
+    // where a download that's about to activate leaves things.
+    fn journaled(cache: &Path) -> PlannedActivation {
+        install(cache, "0.1.0");
+        write_active_in(cache, "0.1.0", Some(UpdateChannel::Stable)).unwrap();
+        let zip = fixture_zip(&[("index.html", b"<html>0.2.0</html>")]);
+        let tmp_dir = stage_bundle_in(cache, Cursor::new(zip), "0.2.0", None).unwrap();
+        let plan = planned(cache, "0.2.0", "0.1.0", Some(tmp_dir));
+        write_journal_in(cache, &ActivationJournal::Activate(plan.clone())).unwrap();
+        plan
+    }
+
+    fn assert_finished(cache: &Path) {
+        assert_eq!(active_in(cache), ("0.2.0".into(), "beta".into()));
+        assert_eq!(rollback_target(cache).unwrap().0, "0.1.0");
+        assert_eq!(read_text_file(&cache.join(PREVIOUS_CHANNEL_FILE)), "stable");
+        assert_eq!(installed(cache), vec!["0.1.0", "0.2.0"]);
+        assert!(!cache.join(ACTIVATION_JOURNAL_FILE).exists());
+        assert_eq!(recover_activation_in(cache), Ok(None));
+    }
+
+    #[test]
+    fn activations_cut_short_before_the_rename_are_finished() {
+        let cache = tempfile::tempdir().unwrap();
+        journaled(cache.path());
+        assert_eq!(recover_activation_in(cache.path()), Ok(Some(Recovered::Finished("0.2.0".into()))));
+        assert_finished(cache.path());
+        assert_eq!(
+            last_activation_in(cache.path()).map(|e| e.event),
+            Some(UpdateHistoryEvent::Activation { version: "0.2.0".into(), channel: Some(UpdateChannel::Beta), previous: "0.1.0".into() })
+        );
+    }
+
+    #[test]
+    fn activations_cut_short_after_the_rename_are_finished() {
+        let cache = tempfile::tempdir().unwrap();
+        let plan = journaled(cache.path());
+        move_into_place(plan.tmp_dir.as_deref().unwrap(), &plan.final_dir).unwrap();
+        assert_eq!(recover_activation_in(cache.path()), Ok(Some(Recovered::Finished("0.2.0".into()))));
+        assert_finished(cache.path());
+    }
+
+    #[test]
+    fn activations_cut_short_after_persisting_are_finished_the_same_way() {
+        let cache = tempfile::tempdir().unwrap();
+        let plan = journaled(cache.path());
+        move_into_place(plan.tmp_dir.as_deref().unwrap(), &plan.final_dir).unwrap();
+        record_previous_version(cache.path(), &plan.previous, plan.previous_channel, &plan.version).unwrap();
+        write_active_in(cache.path(), &plan.version, plan.channel).unwrap();
+        // Going over the same steps again doesn't make 0.2.0 its own rollback target.
+        assert_eq!(recover_activation_in(cache.path()), Ok(Some(Recovered::Finished("0.2.0".into()))));
+        assert_finished(cache.path());
+    }
+
+    #[test]
+    fn activations_whose_bundle_never_landed_are_undone() {
+        let cache = tempfile::tempdir().unwrap();
+        let plan = journaled(cache.path());
+        std::fs::remove_dir_all(plan.tmp_dir.as_deref().unwrap()).unwrap();
+        write_active_in(cache.path(), "0.2.0", Some(UpdateChannel::Beta)).unwrap();
+
+        assert_eq!(recover_activation_in(cache.path()), Ok(Some(Recovered::Undone("0.1.0".into()))));
+        assert_eq!(active_in(cache.path()), ("0.1.0".into(), "stable".into()));
+        assert_eq!(installed(cache.path()), vec!["0.1.0"]);
+        assert!(!cache.path().join(ACTIVATION_JOURNAL_FILE).exists());
+    }
+
+    #[test]
+    fn failed_activations_put_the_previous_version_back() {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.1.0");
+        write_active_in(cache.path(), "0.1.0", Some(UpdateChannel::Stable)).unwrap();
+        let plan = planned(cache.path(), "0.3.0", "0.1.0", None);
+
+        assert!(activate_in(cache.path(), &plan).is_err());
+        assert_eq!(active_in(cache.path()), ("0.1.0".into(), "stable".into()));
+        assert!(!cache.path().join(ACTIVATION_JOURNAL_FILE).exists());
+
+        install(cache.path(), "0.3.0");
+        activate_in(cache.path(), &plan).unwrap();
+        assert_eq!(active_in(cache.path()), ("0.3.0".into(), "beta".into()));
+        assert!(!cache.path().join(ACTIVATION_JOURNAL_FILE).exists());
+    }
+
+    #[test]
+    fn resets_cut_short_are_finished() {
+        let cache = reset_fixture();
+        let reset = ActivationJournal::Reset { version: "0.1.0".into(), keep_settings: true };
+        write_journal_in(cache.path(), &reset).unwrap();
+        assert_eq!(recover_activation_in(cache.path()), Ok(Some(Recovered::Reset("0.1.0".into()))));
+        assert_eq!(installed(cache.path()), vec!["0.1.0"]);
+        assert!(!cache.path().join(ACTIVE_VERSION_FILE).exists());
+        assert!(cache.path().join(FEED_FILE).exists());
+        assert!(!cache.path().join(ACTIVATION_JOURNAL_FILE).exists());
+    }
+
+    #[test]
+    fn journal_entries_pointing_elsewhere_are_dropped() {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.1.0");
+        write_active_in(cache.path(), "0.1.0", Some(UpdateChannel::Stable)).unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        install(outside.path(), "0.2.0");
+        let elsewhere = bundle_dir_in(outside.path(), "0.2.0");
+
+        let mut entries = vec![
+            PlannedActivation { final_dir: elsewhere.clone(), ..planned(cache.path(), "0.2.0", "0.1.0", None) },
+            planned(cache.path(), "0.2.0", "0.1.0", Some(elsewhere)),
+            planned(cache.path(), "0.2.0", "0.1.0", Some(cache.path().join("web_bundles/0.1.0"))),
+        ];
+        entries.push(PlannedActivation { version: "../etc".into(), ..entries[0].clone() });
+        for plan in entries {
+            write_journal_in(cache.path(), &ActivationJournal::Activate(plan.clone())).unwrap();
+            assert_eq!(recover_activation_in(cache.path()), Ok(None), "{plan:?}");
+            assert!(!cache.path().join(ACTIVATION_JOURNAL_FILE).exists());
+        }
+        std::fs::write(cache.path().join(ACTIVATION_JOURNAL_FILE), "not json").unwrap();
+        assert_eq!(recover_activation_in(cache.path()), Ok(None));
+        assert_eq!(active_in(cache.path()), ("0.1.0".into(), "stable".into()));
+        assert_eq!(installed(cache.path()), vec!["0.1.0"]);
+        assert!(bundle_dir_in(outside.path(), "0.2.0").join("index.html").is_file());
+    }
+
+    #[test]
+    fn storage_roots_are_remembered_until_cleared() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(read_storage_root_in(dir.path()), None);
+        write_storage_root_in(dir.path(), Some(Path::new("/mnt/data"))).unwrap();
+        assert_eq!(read_storage_root_in(dir.path()), Some(PathBuf::from("/mnt/data")));
+        write_storage_root_in(dir.path(), None).unwrap();
+        write_storage_root_in(dir.path(), None).unwrap();
+        assert_eq!(read_storage_root_in(dir.path()), None);
+    }
+
+    #[test]
+    fn storage_roots_must_be_absolute_folders_outside_the_app() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("Voxelle.app");
+        std::fs::create_dir_all(app.join("Contents/Resources")).unwrap();
+        std::fs::write(dir.path().join("file"), "x").unwrap();
+        let install = [app.clone()];
+
+        assert_eq!(validate_storage_root(&dir.path().display().to_string(), &install), Ok(dir.path().canonicalize().unwrap()));
+        for bad in ["relative/dir".into(), dir.path().join("missing"), dir.path().join("file"), app.join("Contents/Resources")] {
+            assert!(validate_storage_root(&bad.display().to_string(), &install).is_err(), "{bad:?}");
+        }
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2, "the write probe is cleaned up");
+    }
+
+    #[test]
+    fn move_targets_must_be_empty_and_apart_from_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("a/voxelle");
+        install(&from, "0.1.0");
+        assert!(check_move_target(&from, &from.join("web_bundles/voxelle")).is_err());
+        assert!(check_move_target(&from, &dir.path().join("a")).is_err());
+        std::fs::create_dir_all(dir.path().join("b/voxelle")).unwrap();
+        assert_eq!(check_move_target(&from, &dir.path().join("b/voxelle")), Ok(()));
+        std::fs::write(dir.path().join("b/voxelle/notes.txt"), "mine").unwrap();
+        assert!(check_move_target(&from, &dir.path().join("b/voxelle")).is_err());
+
+        assert_eq!(ensure_space_to_move(1000, 1000 + INSTALL_SPACE_SLACK), Ok(()));
+        let e = command_error(ensure_space_to_move(1000, 1000).unwrap_err());
+        assert_eq!(e.code, WebUpdateErrorCode::InsufficientDiskSpace);
+        assert_eq!(e.message, "not enough disk space to move the app's storage there: needs 17 MB, 0 MB free");
+    }
+
+    // A cache at `a/voxelle` copied to `b/voxelle`, as far as `move_storage`
+    // gets before it changes the setting.
+    fn copied_cache(dir: &Path) -> (PathBuf, PathBuf, Vec<StorageMoveProgress>) {
+        let (from, to) = (dir.join("a/voxelle"), dir.join("b/voxelle"));
+        install(&from, "0.1.0");
+        write_active_in(&from, "0.1.0", Some(UpdateChannel::Stable)).unwrap();
+        std::fs::create_dir_all(from.join("downloads")).unwrap();
+        std::fs::write(from.join("downloads/0.2.0-ab.zip.part"), vec![b'x'; PROGRESS_STEP as usize]).unwrap();
+        let progress = Mutex::new(Vec::new());
+        copy_cache_in(&from, &to, &|p| progress.lock().unwrap().push(p)).unwrap();
+        (from, to, progress.into_inner().unwrap())
+    }
+
+    #[test]
+    fn moves_copy_every_file_and_journal_both_sides() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to, progress) = copied_cache(dir.path());
+        let files = list_files(&from).unwrap();
+        assert_eq!(files, list_files(&to).unwrap());
+        assert!(files.contains(&ACTIVATION_JOURNAL_FILE.to_string()));
+        assert_eq!(read_text_file(&to.join(ACTIVE_VERSION_FILE)), "0.1.0");
+
+        let total = PROGRESS_STEP + 13 + 5 + 6;
+        assert_eq!(progress.first(), Some(&StorageMoveProgress { copied: 0, total }));
+        assert_eq!(progress.last(), Some(&StorageMoveProgress { copied: total, total }));
+        assert!(progress.len() >= 3);
+    }
+
+    #[test]
+    fn moves_cut_short_before_the_setting_changed_are_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to, _) = copied_cache(dir.path());
+        assert_eq!(recover_activation_in(&from), Ok(Some(Recovered::StorageMoveUndone(from.clone()))));
+        assert!(!to.exists());
+        assert!(!from.join(ACTIVATION_JOURNAL_FILE).exists());
+        assert_eq!(installed(&from), vec!["0.1.0"]);
+    }
+
+    #[test]
+    fn moves_cut_short_after_the_setting_changed_are_finished() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to, _) = copied_cache(dir.path());
+        assert_eq!(recover_activation_in(&to), Ok(Some(Recovered::StorageMoved(to.clone()))));
+        assert!(!from.exists());
+        assert!(!to.join(ACTIVATION_JOURNAL_FILE).exists());
+        assert_eq!(installed(&to), vec!["0.1.0"]);
+        assert_eq!(read_text_file(&to.join(ACTIVE_VERSION_FILE)), "0.1.0");
+    }
+
+    #[test]
+    fn move_journal_entries_between_other_dirs_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("voxelle");
+        let other = dir.path().join("elsewhere");
+        install(&cache, "0.1.0");
+        install(&other, "0.1.0");
+        for (from, to) in [(other.clone(), cache.clone()), (cache.clone(), other.clone()), (dir.path().join("x/voxelle"), dir.path().join("y/voxelle"))] {
+            write_journal_in(&cache, &ActivationJournal::MoveStorage { from, to }).unwrap();
+            assert_eq!(recover_activation_in(&cache), Ok(None));
+        }
+        assert_eq!((installed(&cache), installed(&other)), (vec!["0.1.0".to_string()], vec!["0.1.0".to_string()]));
+    }
+
+    #[test]
+    fn apply_modes_serialize_snake_case() {
+        assert_eq!(serde_json::to_value(ApplyMode::OnNextLaunch).unwrap(), serde_json::json!("on_next_launch"));
+        assert_eq!(ApplyMode::default(), ApplyMode::Prompt);
+    }
+
+    #[test]
+    fn errors_serialize_with_a_code() {
+        assert_eq!(
+            serde_json::to_value(RollbackError::NoPreviousVersion).unwrap(),
+            serde_json::json!({ "code": "no_previous_version" })
+        );
+        assert_eq!(
+            serde_json::to_value(RollbackError::PreviousBundleMissing("0.1.0".into())).unwrap(),
+            serde_json::json!({ "code": "previous_bundle_missing", "detail": "0.1.0" })
+        );
+        let e = command_error(RollbackError::from(WebUpdateError::Busy(UpdateOperation::Download)));
+        assert_eq!((e.code, e.details), (WebUpdateErrorCode::Busy, Some(serde_json::json!({ "operation": "download" }))));
+    }
+
+    // Answers one HTTP request with `response` and returns a URL that reaches it.
+    fn serve_once(response: impl Into<Vec<u8>>) -> String {
+        let response = response.into();
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = s.read(&mut buf);
+            let _ = s.write_all(&response);
+        });
+        format!("http://127.0.0.1:{port}/manifest.json")
+    }
+
+    // The test servers are plain http on 127.0.0.1.
+    static LOCAL_ORIGINS: std::sync::OnceLock<TrustedOrigins> = std::sync::OnceLock::new();
+
+    fn local(url: &str) -> DownloadSource<'_> {
+        let trusted = LOCAL_ORIGINS.get_or_init(|| TrustedOrigins::for_feed("http://127.0.0.1/feed.json", UpdateChannel::Stable, &[], true));
+        DownloadSource { url, trusted }
+    }
+
+    fn respond(status: &str, body: &str) -> String {
+        format!("HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len())
+    }
+
+    fn command_error(e: impl Into<WebUpdateCommandError>) -> WebUpdateCommandError {
+        e.into()
+    }
+
+    #[test]
+    fn command_errors_serialize_with_a_stable_code() {
+        assert_eq!(
+            serde_json::to_value(command_error(WebUpdateError::HttpStatus(503))).unwrap(),
+            serde_json::json!({ "code": "http_status", "message": "http 503", "retryable": true, "details": { "status": 503 } })
+        );
+        assert_eq!(
+            serde_json::to_value(command_error(WebUpdateError::FeedNotSet)).unwrap(),
+            serde_json::json!({ "code": "feed_not_set", "message": "feed url not set", "retryable": false })
+        );
+    }
+
+    #[test]
+    fn unreachable_and_silent_servers_are_retryable() {
+        let closed = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap().local_addr().unwrap().port();
+        let err = tauri::async_runtime::block_on(reqwest::Client::new().get(format!("http://127.0.0.1:{closed}/")).send()).unwrap_err();
+        let e = command_error(WebUpdateError::from(err));
+        assert_eq!((e.code, e.retryable), (WebUpdateErrorCode::Offline, true));
+
+        // Accepts connections but never answers.
+        let silent = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = format!("http://{}/", silent.local_addr().unwrap());
+        let client = reqwest::Client::builder().timeout(std::time::Duration::from_millis(200)).build().unwrap();
+        // `send` starts the timeout's timer, so it has to run inside the runtime.
+        let err = tauri::async_runtime::block_on(async { client.get(url).send().await }).unwrap_err();
+        let e = command_error(WebUpdateError::from(err));
+        assert_eq!((e.code, e.retryable), (WebUpdateErrorCode::Timeout, true));
+    }
+
+    #[test]
+    fn phases_that_run_out_of_time_say_which_phase() {
+        let cache = tempfile::tempdir().unwrap();
+        // Accepts connections but never answers.
+        let silent = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = format!("http://{}/manifest.json", silent.local_addr().unwrap());
+        let client = reqwest::Client::new();
+        let e = tauri::async_runtime::block_on(async {
+            let deadline = tokio::time::Instant::now() + std::time::Duration::from_millis(200);
+            within(deadline, UpdatePhase::Manifest, fetch_manifest_once(&client, cache.path(), &url, UpdateChannel::Stable)).await
+        })
+        .unwrap_err();
+        assert_eq!(e, WebUpdateError::Timeout(Some(UpdatePhase::Manifest)));
+        let e = command_error(e);
+        assert_eq!((e.code, e.retryable), (WebUpdateErrorCode::Timeout, true));
+        assert_eq!(e.message, "the manifest fetch timed out");
+        assert_eq!(e.details, Some(serde_json::json!({ "phase": "manifest" })));
+
+        // A request that times out on its own, well before the deadline, counts against the phase too.
+        let client = reqwest::Client::builder().timeout(std::time::Duration::from_millis(200)).build().unwrap();
+        let dest = cache.path().join("web.zip.part");
+        let e = tauri::async_runtime::block_on(async {
+            let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(60);
+            within(deadline, UpdatePhase::Download, download_zip(&client, &local(&url), &dest, &"0".repeat(64), 1 << 20, &|_, _| {})).await
+        })
+        .unwrap_err();
+        assert_eq!(e, WebUpdateError::Timeout(Some(UpdatePhase::Download)));
+        drop(silent);
+    }
+
+    #[test]
+    fn manifest_fetch_errors_have_codes() {
+        let cache = tempfile::tempdir().unwrap();
+        let client = reqwest::Client::new();
+        let fetch = |response: String| {
+            let url = serve_once(response);
+            command_error(tauri::async_runtime::block_on(fetch_manifest_once(&client, cache.path(), &url, UpdateChannel::Stable)).unwrap_err())
+        };
+
+        let e = fetch(respond("503 Service Unavailable", ""));
+        assert_eq!((e.code, e.retryable), (WebUpdateErrorCode::HttpStatus, true));
+        assert_eq!(e.details, Some(serde_json::json!({ "status": 503 })));
+        let e = fetch(respond("404 Not Found", ""));
+        assert_eq!((e.code, e.retryable), (WebUpdateErrorCode::HttpStatus, false));
+
+        let e = fetch(respond("200 OK", "not json"));
+        assert_eq!(e.code, WebUpdateErrorCode::ManifestInvalid);
+        let wrong_version = r#"{"v":2,"version":"0.3.0","zip_url":"https://example.com/web.zip","sha256":"ab"}"#;
+        let e = fetch(respond("200 OK", wrong_version));
+        assert_eq!((e.code, e.message.as_str()), (WebUpdateErrorCode::ManifestInvalid, "manifest.v must be 1"));
+    }
+
+    #[test]
+    fn download_errors_have_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("web.zip.part");
+        let client = reqwest::Client::new();
+        let download = |response: String, dest: &Path, max_bytes: u64| {
+            let url = serve_once(response);
+            let sha = "00".repeat(32);
+            command_error(tauri::async_runtime::block_on(download_zip_once(&client, &local(&url), dest, &sha, max_bytes, false, &|_, _| {})).unwrap_err())
+        };
+
+        let e = download(respond("200 OK", "zip"), &dest, 1024);
+        assert_eq!((e.code, e.message.as_str()), (WebUpdateErrorCode::HashMismatch, "sha256 mismatch"));
+        assert!(!dest.exists());
+
+        let e = download(respond("200 OK", "0123456789"), &dest, 4);
+        assert_eq!(
+            (e.code, e.message.as_str()),
+            (WebUpdateErrorCode::DownloadTooLarge, "zip exceeds the maximum download size")
+        );
+
+        // The partial file can't be created; nothing is requested.
+        let missing = dir.path().join("missing/web.zip.part");
+        let sha = "00".repeat(32);
+        let e = command_error(
+            tauri::async_runtime::block_on(download_zip_once(&client, &local("http://127.0.0.1:9/"), &missing, &sha, 1024, false, &|_, _| {})).unwrap_err(),
+        );
+        assert_eq!((e.code, e.retryable), (WebUpdateErrorCode::Io, false));
+    }
+
+    #[test]
+    fn zip_origins_are_derived_from_the_feed() {
+        let check = |origins: &TrustedOrigins, url: &str| origins.check_str(url).map_err(command_error);
+
+        let gh = TrustedOrigins::for_feed("gh:x3haloed/voxelle", UpdateChannel::Beta, &[], false);
+        assert_eq!(gh.feed_host, "github.com");
+        for url in [
+            "https://github.com/x3haloed/voxelle/releases/download/web-v0.2.0/web.zip",
+            "https://objects.githubusercontent.com/github-production-release-asset/1/web.zip",
+            "https://release-assets.githubusercontent.com/github-production-release-asset/1/web.zip",
+        ] {
+            assert!(check(&gh, url).is_ok(), "{url}");
+        }
+        let e = check(&gh, "https://evil.example/web.zip").unwrap_err();
+        assert_eq!((e.code, e.retryable), (WebUpdateErrorCode::UntrustedZipOrigin, false));
+        assert_eq!(e.details, Some(serde_json::json!({ "zip_host": "evil.example", "feed_host": "github.com", "https": true })));
+        assert!(e.message.contains("evil.example") && e.message.contains("github.com"), "{}", e.message);
+
+        // Self-hosted feeds trust their own host plus whatever is configured, and only over https.
+        let extra = vec!["CDN.example.net".to_string()];
+        let own = TrustedOrigins::for_feed("https://updates.example.com/{channel}/manifest.json", UpdateChannel::Stable, &extra, false);
+        assert!(check(&own, "https://updates.example.com/web.zip").is_ok());
+        assert!(check(&own, "https://cdn.example.net/web.zip").is_ok());
+        assert_eq!(check(&own, "https://objects.githubusercontent.com/web.zip").unwrap_err().code, WebUpdateErrorCode::UntrustedZipOrigin);
+        let e = check(&own, "http://updates.example.com/web.zip").unwrap_err();
+        assert_eq!(e.details, Some(serde_json::json!({ "zip_host": "updates.example.com", "feed_host": "updates.example.com", "https": false })));
+        assert_eq!(check(&own, "not a url").unwrap_err().code, WebUpdateErrorCode::ManifestInvalid);
+
+        let debug = TrustedOrigins { allow_http: true, ..own };
+        assert!(check(&debug, "http://updates.example.com/web.zip").is_ok());
+        assert!(check(&debug, "ftp://updates.example.com/web.zip").is_err());
+    }
+
+    #[test]
+    fn redirects_to_untrusted_hosts_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("web.zip.part");
+        let client = reqwest::Client::new();
+        let bytes = fixture_zip(&[("index.html", b"<html></html>")]);
+        let sha = hex::encode(Sha256::digest(&bytes));
+        let redirect = |to: &str| serve_once(format!("HTTP/1.1 302 Found\r\nLocation: {to}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"));
+
+        // 127.0.0.1 is trusted; the same server under the name `localhost` is not.
+        let mut ok = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", bytes.len()).into_bytes();
+        ok.extend_from_slice(&bytes);
+        let target = serve_once(ok.clone()).replace("127.0.0.1", "localhost");
+        let url = redirect(&target);
+        let e = tauri::async_runtime::block_on(download_zip_once(&client, &local(&url), &dest, &sha, 1 << 20, false, &|_, _| {})).unwrap_err();
+        assert_eq!(e, WebUpdateError::UntrustedZipOrigin { zip_host: "localhost".into(), feed_host: "127.0.0.1".into(), https: false });
+
+        // Redirects that stay on trusted hosts go through.
+        let url = redirect(&serve_once(ok));
+        tauri::async_runtime::block_on(download_zip_once(&client, &local(&url), &dest, &sha, 1 << 20, false, &|_, _| {})).unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), bytes);
+    }
+
+    #[test]
+    fn bundles_that_fail_to_unpack_have_a_code() {
+        let out = tempfile::tempdir().unwrap();
+        let err = extract_zip(Cursor::new(b"not a zip".to_vec()), out.path()).unwrap_err();
+        assert_eq!(command_error(WebUpdateError::ExtractFailed(err)).code, WebUpdateErrorCode::ExtractFailed);
+    }
+
+    #[test]
+    fn downgrades_need_confirming() {
+        use UpdateChannel::*;
+        let e = command_error(confirm_offer("0.3.0", Some(Beta), "0.2.1", Stable, false).unwrap_err());
+        assert_eq!(e.code, WebUpdateErrorCode::DowngradeNotConfirmed);
+        assert_eq!(e.message, "stable channel offers 0.2.1, older than the active 0.3.0; confirm the downgrade to install it");
+        assert_eq!(e.details, Some(serde_json::json!({ "channel": "stable", "offered": "0.2.1", "active": "0.3.0" })));
+        assert_eq!(confirm_offer("0.3.0", Some(Beta), "0.2.1", Stable, true), Ok(()));
+        assert_eq!(confirm_offer("0.2.0", Some(Stable), "0.3.0", Stable, false), Ok(()));
+    }
+
+    #[test]
+    fn offers_are_related_to_the_active_version() {
+        use VersionRelation::*;
+        assert_eq!(version_relation("0.2.0", "0.3.0"), Newer);
+        assert_eq!(version_relation("0.3.0-beta.2", "0.3.0"), Newer);
+        assert_eq!(version_relation("0.3.0", " 0.3.0 "), Same);
+        assert_eq!(version_relation("0.3.0", "0.2.1"), Older);
+        assert_eq!(version_relation("0.3.0", "0.3.0-beta.2"), Older);
+        // Not semver: only equality is known.
+        assert_eq!(version_relation("nightly", "nightly"), Same);
+        assert_eq!(version_relation("nightly", "0.1.0"), Newer);
+        assert_eq!(serde_json::to_value(Older).unwrap(), "older");
+    }
+
+    #[test]
+    fn forced_installs_take_any_offer_and_record_which_way_it_went() {
+        use UpdateChannel::*;
+        assert_eq!(confirm_offer("0.3.0", Some(Stable), "0.2.1", Stable, false), Ok(()), "older on the same channel isn't offered, but can be forced");
+        let force = |active: &str, offered: &str| {
+            let direction = install_direction(version_relation(active, offered), true, active, offered);
+            confirm_offer(active, Some(Beta), offered, Stable, true).map(|()| direction)
+        };
+        assert_eq!(force("0.2.0", "0.3.0"), Ok(None));
+        assert_eq!(force("0.3.0", "0.3.0"), Ok(Some(UpdateHistoryEvent::Reinstall { version: "0.3.0".into() })));
+        assert_eq!(force("0.3.0", "0.2.1"), Ok(Some(UpdateHistoryEvent::Downgrade { from: "0.3.0".into(), to: "0.2.1".into() })));
+        // Unforced, re-activating the installed version isn't worth a history entry.
+        assert_eq!(install_direction(VersionRelation::Same, false, "0.3.0", "0.3.0"), None);
+        assert_eq!(
+            install_direction(VersionRelation::Older, false, "0.3.0", "0.2.1"),
+            Some(UpdateHistoryEvent::Downgrade { from: "0.3.0".into(), to: "0.2.1".into() })
+        );
+        assert_eq!(
+            serde_json::to_value(UpdateHistoryEntry { at: 1, event: UpdateHistoryEvent::Reinstall { version: "0.3.0".into() } }).unwrap(),
+            serde_json::json!({ "at": 1, "event": "reinstall", "version": "0.3.0" })
+        );
+    }
+
+    #[test]
+    fn reinstalls_replace_the_installed_copy() {
+        let cache = tempfile::tempdir().unwrap();
+        install(cache.path(), "0.2.0");
+        std::fs::write(bundle_dir_in(cache.path(), "0.2.0").join("stray.js"), "x").unwrap();
+        let zip = fixture_zip(&[("index.html", b"<html>fresh</html>")]);
+        let tmp_dir = stage_bundle_in(cache.path(), Cursor::new(zip), "0.2.0", None).unwrap();
+        activate_in(cache.path(), &planned(cache.path(), "0.2.0", "0.2.0", Some(tmp_dir))).unwrap();
+
+        let dir = bundle_dir_in(cache.path(), "0.2.0");
+        assert_eq!(read_text_file(&dir.join("index.html")), "<html>fresh</html>");
+        assert_eq!(list_files(&dir).unwrap(), vec!["index.html"]);
+        assert_eq!(installed(cache.path()), vec!["0.2.0"]);
+        assert!(!cache.path().join(PREVIOUS_VERSION_FILE).exists(), "a version isn't its own rollback target");
+    }
+
+    #[test]
+    fn downloading_needs_a_feed() {
+        let root = tempfile::tempdir().unwrap();
+        let state = WebUpdateState {
+            server: WebBundleServer::start(root.path().canonicalize().unwrap(), None).unwrap(),
+            active_version: Default::default(),
+            active_channel: Default::default(),
+            embedded_version: "0.1.0".into(),
+            embedded_zip: &[],
+            feed_urls: Arc::new(Mutex::new(parse_feed_list("  "))),
+            settings: Default::default(),
+            settings_changed: Default::default(),
+            decisions: Default::default(),
+            network: Default::default(),
+            client: Default::default(),
+            operation: Default::default(),
+            last_check: Default::default(),
+            last_activation: Default::default(),
+            repair: None,
+            dev_override: Default::default(),
+        };
+        let e = command_error(configured_feeds(&state).unwrap_err());
+        assert_eq!((e.code, e.message.as_str()), (WebUpdateErrorCode::FeedNotSet, "feed url not set"));
+
+        *state.feed_urls.lock().unwrap() = vec!["gh:o/r".into()];
+        assert_eq!(configured_feeds(&state), Ok(vec!["gh:o/r".into()]));
+        state.server.shutdown();
+    }
+
+    #[test]
+    fn feed_lists_accept_a_single_url() {
+        assert_eq!(parse_feed_list("gh:o/r"), vec!["gh:o/r"]);
+        assert_eq!(parse_feed_list(" https://a.example/m.json \n\n gh:o/r\n"), vec!["https://a.example/m.json", "gh:o/r"]);
+        assert!(parse_feed_list("").is_empty());
+
+        let one: FeedUrls = serde_json::from_value(serde_json::json!("gh:o/r")).unwrap();
+        assert_eq!(one.into_vec(), vec!["gh:o/r"]);
+        let many: FeedUrls = serde_json::from_value(serde_json::json!(["gh:o/r", "https://b.example/m.json"])).unwrap();
+        assert_eq!(many.into_vec(), vec!["gh:o/r", "https://b.example/m.json"]);
+    }
+
+    #[test]
+    fn the_first_feed_with_a_valid_manifest_wins() {
+        let cache = tempfile::tempdir().unwrap();
+        let client = reqwest::Client::new();
+        let manifest = |version: &str| {
+            let json = serde_json::json!({ "v": 1, "version": version, "zip_url": "https://example.com/web.zip", "sha256": "ab".repeat(32) });
+            respond("200 OK", &json.to_string())
+        };
+        let fetch = |feeds: &[String]| {
+            let mut tried = Vec::new();
+            let r = tauri::async_runtime::block_on(fetch_first_manifest(&client, cache.path(), feeds, UpdateChannel::Stable, 1, &mut tried));
+            (r, tried)
+        };
+        let closed = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap().local_addr().unwrap().port();
+        let offline = format!("http://127.0.0.1:{closed}/manifest.json");
+
+        let invalid = serve_once(respond("200 OK", "not json"));
+        let good = serve_once(manifest("0.3.0"));
+        let (r, tried) = fetch(&[offline.clone(), invalid.clone(), good.clone()]);
+        let (m, source) = r.unwrap();
+        assert_eq!((m.version.as_str(), source.as_str()), ("0.3.0", good.as_str()));
+        assert_eq!(tried.iter().map(|t| t.error.is_some()).collect::<Vec<_>>(), vec![true, true, false]);
+        assert_eq!(tried[1].url, invalid);
+
+        // Later feeds are never contacted, even when they would also answer.
+        let first = serve_once(manifest("0.4.0"));
+        let (r, tried) = fetch(&[first.clone(), "http://127.0.0.1:9/never".into()]);
+        assert_eq!(r.unwrap().1, first);
+        assert_eq!(tried.len(), 1);
+
+        // A broken feed is reported over one that is merely unreachable.
+        let missing = serve_once(respond("404 Not Found", ""));
+        let (r, tried) = fetch(&[missing, offline]);
+        assert_eq!(r.unwrap_err(), WebUpdateError::HttpStatus(404));
+        assert_eq!(tried.len(), 2);
+    }
+
+    #[test]
+    fn rollback_errors_have_codes() {
+        let cache = tempfile::tempdir().unwrap();
+        let code = || command_error(rollback_target(cache.path()).unwrap_err());
+        assert_eq!(code().code, WebUpdateErrorCode::NoPreviousVersion);
+
+        std::fs::write(cache.path().join(PREVIOUS_VERSION_FILE), "../etc").unwrap();
+        assert_eq!(code().code, WebUpdateErrorCode::InvalidPreviousVersion);
+
+        std::fs::write(cache.path().join(PREVIOUS_VERSION_FILE), "0.1.0").unwrap();
+        let e = code();
+        assert_eq!(e.code, WebUpdateErrorCode::PreviousBundleMissing);
+        assert_eq!(e.details, Some(serde_json::json!({ "version": "0.1.0" })));
+        assert_eq!(e.message, "previous bundle 0.1.0 is no longer installed");
+    }
+
+    #[test]
+    fn only_network_and_server_errors_are_transient() {
+        for e in [WebUpdateError::Offline, WebUpdateError::Timeout(None), WebUpdateError::HttpStatus(429), WebUpdateError::HttpStatus(503)] {
+            assert!(e.is_transient(), "{e:?}");
+        }
+        for e in [
+            WebUpdateError::HttpStatus(404),
+            WebUpdateError::ManifestInvalid("bad".into()),
+            WebUpdateError::HashMismatch,
+            WebUpdateError::ExtractFailed("bad".into()),
+            WebUpdateError::Io("disk full".into()),
+        ] {
+            assert!(!e.is_transient(), "{e:?}");
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let base = std::time::Duration::from_millis(500);
+        assert_eq!(backoff_delay(base, 0), base);
+        assert_eq!(backoff_delay(base, 1), base * 2);
+        assert_eq!(backoff_delay(base, 2), base * 4);
+        assert_eq!(backoff_delay(base, 10), RETRY_MAX_DELAY);
+        assert_eq!(backoff_delay(base, u32::MAX), RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn retries_stop_at_the_attempt_limit_or_a_permanent_error() {
+        let run = |attempts: u32, errors: Vec<WebUpdateError>| {
+            let calls = std::cell::Cell::new(0);
+            let result = tauri::async_runtime::block_on(with_retries("test", attempts, std::time::Duration::ZERO, || {
+                let n = calls.get();
+                calls.set(n + 1);
+                let r = errors.get(n).cloned().map_or(Ok(n), Err);
+                async move { r }
+            }));
+            (result, calls.get())
+        };
+
+        assert_eq!(run(3, vec![WebUpdateError::Offline, WebUpdateError::Timeout(None)]), (Ok(2), 3));
+        assert_eq!(run(3, vec![WebUpdateError::Offline; 5]), (Err(WebUpdateError::Offline), 3));
+        assert_eq!(run(3, vec![WebUpdateError::HttpStatus(404)]), (Err(WebUpdateError::HttpStatus(404)), 1));
+        assert_eq!(run(0, vec![WebUpdateError::Offline]), (Err(WebUpdateError::Offline), 1));
+    }
 }
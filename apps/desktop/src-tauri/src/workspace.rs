@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::board::{BoardError, PickedRoots};
+
+const RECENT_FILE: &str = "recent_workspaces.json";
+const MAX_RECENT: usize = 10;
+
+// Serializes read-modify-write of the recent list across windows.
+static RECENT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Workspace {
+    /// Canonical path of the workspace root (the directory holding `.isnad`).
+    pub path: String,
+    pub name: String,
+    /// False when the folder has no `.isnad` yet; folding with `create` scaffolds it.
+    #[serde(default)]
+    pub has_isnad: bool,
+    #[serde(default)]
+    pub opened_at: u64,
+}
+
+fn display_name(root: &Path) -> String {
+    root.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| root.display().to_string())
+}
+
+// A workspace root is a directory that either has `.isnad` or could get one.
+fn inspect(root: &Path) -> Result<Workspace, BoardError> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| BoardError::InvalidRoot(format!("{}: {e}", root.display())))?;
+    if !root.is_dir() {
+        return Err(BoardError::InvalidRoot(format!("{} is not a directory", root.display())));
+    }
+    let isnad_dir = isnad::paths_for(&root).isnad_dir;
+    let has_isnad = isnad_dir.is_dir();
+    if !has_isnad {
+        if isnad_dir.exists() {
+            return Err(BoardError::InvalidRoot(format!("{} is not a directory", isnad_dir.display())));
+        }
+        check_writable(&root)?;
+    }
+    Ok(Workspace {
+        path: root.display().to_string(),
+        name: display_name(&root),
+        has_isnad,
+        opened_at: crate::web_update::unix_ms_now(),
+    })
+}
+
+// Permission bits don't tell the whole story (ACLs, read-only mounts), so try it.
+fn check_writable(dir: &Path) -> Result<(), BoardError> {
+    let probe = dir.join(".voxelle-write-test");
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|e| BoardError::InvalidRoot(format!("can't create .isnad in {}: {e}", dir.display())))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+fn read_recent(path: &Path) -> Vec<Workspace> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+// Written to a sibling temp file and renamed into place, like the secret index.
+fn write_recent(path: &Path, recent: &[Workspace]) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(recent).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+fn update_recent(path: &Path, f: impl FnOnce(&mut Vec<Workspace>)) -> Result<Vec<Workspace>, String> {
+    let _guard = RECENT_LOCK.lock().map_err(|_| "recent workspaces lock poisoned")?;
+    let mut recent = read_recent(path);
+    let before = recent.clone();
+    f(&mut recent);
+    // Folders that were deleted or moved drop out.
+    recent.retain(|w| Path::new(&w.path).is_dir());
+    recent.truncate(MAX_RECENT);
+    if recent != before {
+        write_recent(path, &recent)?;
+    }
+    Ok(recent)
+}
+
+fn remember_in(path: &Path, workspace: &Workspace) -> Result<Vec<Workspace>, String> {
+    update_recent(path, |recent| {
+        recent.retain(|w| w.path != workspace.path);
+        recent.insert(0, workspace.clone());
+    })
+}
+
+fn forget_in(path: &Path, root: &str) -> Result<Vec<Workspace>, String> {
+    // Match on the canonical form when the folder still exists.
+    let canonical = Path::new(root).canonicalize().map(|p| p.display().to_string()).ok();
+    update_recent(path, |recent| {
+        recent.retain(|w| w.path != root && Some(&w.path) != canonical.as_ref());
+    })
+}
+
+fn recent_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(RECENT_FILE))
+}
+
+/// Most recent first; entries whose folder is gone are pruned.
+pub fn recent(app: &tauri::AppHandle) -> Result<Vec<Workspace>, String> {
+    update_recent(&recent_file(app)?, |_| {})
+}
+
+/// Everything in the recent list was picked in the dialog at some point, so
+/// it stays usable by the board commands across restarts.
+pub fn restore_picked(app: &tauri::AppHandle, picked: &PickedRoots) {
+    for w in recent(app).unwrap_or_default() {
+        picked.allow(Path::new(&w.path));
+    }
+}
+
+/// Shows the native folder dialog. `None` when the user cancels.
+pub async fn open(app: &tauri::AppHandle) -> Result<Option<Workspace>, BoardError> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog().file().set_title("Open workspace").pick_folder(move |folder| {
+        let _ = tx.send(folder);
+    });
+    let Some(folder) = rx.await.ok().flatten() else {
+        return Ok(None);
+    };
+    let root = folder.into_path().map_err(|e| BoardError::InvalidRoot(e.to_string()))?;
+    let workspace = inspect(&root)?;
+    remember_in(&recent_file(app)?, &workspace)?;
+    app.state::<PickedRoots>().allow(Path::new(&workspace.path));
+    Ok(Some(workspace))
+}
+
+/// Drops `root` from the recent list and from the roots the board may use
+/// outside the home directory.
+pub fn forget(app: &tauri::AppHandle, root: &str) -> Result<(), String> {
+    forget_in(&recent_file(app)?, root)?;
+    app.state::<PickedRoots>().revoke(Path::new(root));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(dir: &Path) -> Workspace {
+        inspect(dir).unwrap()
+    }
+
+    #[test]
+    fn selections_must_be_usable_as_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let w = workspace(dir.path());
+        assert!(!w.has_isnad);
+        assert_eq!(w.path, dir.path().canonicalize().unwrap().display().to_string());
+        assert!(!dir.path().join(".voxelle-write-test").exists());
+
+        std::fs::create_dir(dir.path().join(".isnad")).unwrap();
+        assert!(workspace(dir.path()).has_isnad);
+
+        let blocked = tempfile::tempdir().unwrap();
+        std::fs::write(blocked.path().join(".isnad"), "not a dir").unwrap();
+        assert!(matches!(inspect(blocked.path()), Err(BoardError::InvalidRoot(_))));
+        assert!(matches!(inspect(&dir.path().join("missing")), Err(BoardError::InvalidRoot(_))));
+    }
+
+    #[test]
+    fn recent_list_is_ordered_capped_and_pruned() {
+        let data = tempfile::tempdir().unwrap();
+        let file = data.path().join(RECENT_FILE);
+        let roots: Vec<_> = (0..MAX_RECENT + 2).map(|_| tempfile::tempdir().unwrap()).collect();
+
+        for root in &roots {
+            remember_in(&file, &workspace(root.path())).unwrap();
+        }
+        let recent = read_recent(&file);
+        assert_eq!(recent.len(), MAX_RECENT);
+        assert_eq!(recent[0].path, workspace(roots[MAX_RECENT + 1].path()).path);
+
+        // Reopening moves an entry to the front without duplicating it.
+        let again = remember_in(&file, &workspace(roots[5].path())).unwrap();
+        assert_eq!(again.len(), MAX_RECENT);
+        assert_eq!(again[0].path, workspace(roots[5].path()).path);
+        assert_eq!(again.iter().filter(|w| w.path == again[0].path).count(), 1);
+
+        let gone = again[1].path.clone();
+        std::fs::remove_dir_all(&gone).unwrap();
+        let pruned = update_recent(&file, |_| {}).unwrap();
+        assert!(pruned.iter().all(|w| w.path != gone));
+        assert_eq!(read_recent(&file), pruned);
+
+        let forgotten = forget_in(&file, &pruned[0].path).unwrap();
+        assert_eq!(forgotten.len(), pruned.len() - 1);
+        assert!(!file.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn concurrent_opens_are_not_lost() {
+        let data = tempfile::tempdir().unwrap();
+        let file = data.path().join(RECENT_FILE);
+        let roots: Vec<_> = (0..8).map(|_| tempfile::tempdir().unwrap()).collect();
+        std::thread::scope(|s| {
+            for root in &roots {
+                let file = &file;
+                s.spawn(move || remember_in(file, &workspace(root.path())).unwrap());
+            }
+        });
+        assert_eq!(read_recent(&file).len(), 8);
+    }
+
+    #[test]
+    fn corrupt_recent_file_reads_as_empty() {
+        let data = tempfile::tempdir().unwrap();
+        let file = data.path().join(RECENT_FILE);
+        std::fs::write(&file, "not json").unwrap();
+        assert!(update_recent(&file, |_| {}).unwrap().is_empty());
+    }
+}
@@ -4,6 +4,7 @@ import { createWebRtcTransport } from '../voxelle/webrtc'
 import { startRoomSync, type SyncStats } from '../voxelle/sync'
 import { createSignalClient, newSessionId, type SignalClient, type SignalState } from '../voxelle/signal_ws'
 import { getRoomEvents, getRoomHeads } from '../voxelle/store'
+import { copySensitive } from '../voxelle/clipboard'
 
 function setUrlParam(name: string, value: string | null) {
   const url = new URL(window.location.href)
@@ -18,8 +19,9 @@ function getUrlParam(name: string): string {
   return url.searchParams.get(name) ?? ''
 }
 
+// Offers, answers and session links; cleared from the clipboard after a while in the desktop app.
 async function copyToClipboard(text: string) {
-  await navigator.clipboard.writeText(text)
+  await copySensitive(text, 120)
 }
 
 export function ConnectionPanel(props: { spaceId: string; roomId: string }) {
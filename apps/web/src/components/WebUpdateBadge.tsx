@@ -1,9 +1,83 @@
 import { useEffect, useState } from 'react'
 import { isTauri, tauriInvoke } from '../voxelle/tauri'
+import {
+  isWebUpdateCommandError,
+  webUpdateFetchNotes,
+  type WebBundleRepair,
+  type WebUpdateDevOverride,
+  type WebUpdateHistoryEntry,
+} from '../voxelle/web_update'
 
-type Status = { active_version: string; feed_url: string; port: number }
-type Check = { available: boolean; version?: string | null; zip_url?: string | null; sha256?: string | null }
-type Download = { activated_version: string }
+type Channel = 'stable' | 'beta'
+type LastCheckResult =
+  | { result: 'up_to_date' }
+  | { result: 'available'; version: string; downgrade: boolean }
+  | { result: 'skipped'; version: string }
+  | { result: 'failed'; error: string }
+type Status = {
+  active_version: string
+  active_channel: Channel | null
+  channel: Channel
+  feed_url: string
+  // Tried in order on every check; feed_url is the first.
+  feed_urls: string[]
+  port: number
+  last_checked_at: number | null
+  last_check_result: LastCheckResult | null
+  last_check_source: string | null
+  // Set when this launch replaced a damaged active bundle.
+  repair: WebBundleRepair | null
+  // The UI isn't coming from active_version while this is set.
+  dev_override: WebUpdateDevOverride | null
+  last_activation: WebUpdateHistoryEntry | null
+  skipped_version: string | null
+  // Unix ms; background checks don't announce updates until then.
+  snoozed_until: number | null
+  operation_in_progress: 'check' | 'download' | 'rollback' | 'prune' | 'reset' | null
+}
+function describeError(e: unknown): string {
+  if (isWebUpdateCommandError(e)) return e.code === 'offline' ? 'Offline: can’t reach the update server.' : e.message
+  return e instanceof Error ? e.message : String(e)
+}
+type Check = {
+  available: boolean
+  // The offer is the skipped version; newer ones are still offered.
+  skipped: boolean
+  downgrade: boolean
+  // The active version is below the feed's min_version; the offer must be installed.
+  mandatory: boolean
+  min_version?: string | null
+  channel: Channel
+  // The feed that served the manifest.
+  source?: string | null
+  current_version: string
+  // How `version` compares with current_version; null when no feed is set.
+  // 'same' and 'older' offers install with web_update_download's `force`.
+  relation: 'newer' | 'same' | 'older' | null
+  version?: string | null
+  zip_url?: string | null
+  sha256?: string | null
+  // Markdown release notes; when only notes_url is set, load them with webUpdateFetchNotes.
+  notes?: string | null
+  notes_url?: string | null
+}
+// `pending`: installed, but applied on the next launch (apply_mode 'on_next_launch').
+type Download = { activated_version: string; pending: boolean }
+
+function describeLastCheck(at: number | null, r: LastCheckResult | null): string | null {
+  if (at == null || r == null) return null
+  const mins = Math.max(0, Math.round((Date.now() - at) / 60_000))
+  const when = mins < 1 ? 'just now' : mins < 60 ? `${mins} min ago` : `${Math.round(mins / 60)} h ago`
+  const what =
+    r.result === 'up_to_date'
+      ? 'up to date'
+      : r.result === 'available'
+        ? `${r.version} available`
+        : r.result === 'skipped'
+          ? `${r.version} skipped`
+          : 'check failed'
+  return `checked ${when} — ${what}`
+}
 
 export function WebUpdateBadge() {
   const [supported, setSupported] = useState(false)
@@ -29,23 +103,48 @@ export function WebUpdateBadge() {
   if (!supported) return null
 
   const v = status?.active_version || 'unknown'
-  const feed = status?.feed_url || ''
+  const feed = (status?.feed_urls ?? (status?.feed_url ? [status.feed_url] : [])).join(', ')
+  const lastCheck = describeLastCheck(status?.last_checked_at ?? null, status?.last_check_result ?? null)
+  const activation = status?.last_activation
+  const activated =
+    activation?.event === 'activation'
+      ? `activated ${new Date(activation.at).toLocaleString()}, replacing ${activation.previous}`
+      : undefined
 
   return (
     <div className="row" style={{ gap: 8, alignItems: 'center' }}>
-      <span className="pill">{`web ${v}`}</span>
-      {check?.available ? <span className="pill accent">{`update → ${check.version}`}</span> : null}
+      <span className="pill" title={activated}>{`web ${v}`}</span>
+      {status?.channel === 'beta' ? <span className="pill">beta</span> : null}
+      {status?.dev_override ? (
+        <span className="pill accent" title={`UI loaded from ${status.dev_override.target}`}>
+          dev override
+        </span>
+      ) : null}
+      {status?.repair ? (
+        <span
+          className="pill"
+          title={`web ${status.repair.broken_version} was damaged (${status.repair.problem}); now running ${status.repair.activated_version}`}
+        >
+          repaired
+        </span>
+      ) : null}
+      {status?.skipped_version ? <span className="pill">{`update skipped — ${status.skipped_version}`}</span> : null}
+      {check?.available ? (
+        <span className="pill accent">{`${check.downgrade ? 'downgrade' : 'update'} → ${check.version}`}</span>
+      ) : null}
       <button
         className="pill"
         onClick={async () => {
           const raw =
             window.prompt(
-              'Web update feed (manifest URL, or GitHub shorthand like "gh:owner/repo", or just "owner/repo"). Leave empty to disable.',
+              'Web update feeds, comma-separated and tried in order (manifest URL, or GitHub shorthand like "gh:owner/repo", or just "owner/repo"). Leave empty to disable.',
               feed,
             ) ?? feed
-          const next = raw.includes('://') || raw.trim() === '' || raw.trim().startsWith('gh:') || raw.trim().startsWith('github:')
-            ? raw
-            : `gh:${raw.trim()}`
+          const next = raw
+            .split(',')
+            .map((f) => f.trim())
+            .filter((f) => f !== '')
+            .map((f) => (f.includes('://') || f.startsWith('gh:') || f.startsWith('github:') ? f : `gh:${f}`))
           setErr(null)
           try {
             setBusy('setting')
@@ -53,7 +152,7 @@ export function WebUpdateBadge() {
             const st = await tauriInvoke<Status>('web_update_status')
             setStatus(st)
           } catch (e) {
-            setErr(e instanceof Error ? e.message : String(e))
+            setErr(describeError(e))
           } finally {
             setBusy(null)
           }
@@ -71,9 +170,11 @@ export function WebUpdateBadge() {
             const r = await tauriInvoke<Check>('web_update_check')
             setCheck(r)
           } catch (e) {
-            setErr(e instanceof Error ? e.message : String(e))
+            setErr(describeError(e))
           } finally {
             setBusy(null)
+            // Failed checks are recorded too, so refresh either way.
+            tauriInvoke<Status>('web_update_status').then(setStatus, () => {})
           }
         }}
         disabled={!!busy}
@@ -84,25 +185,56 @@ export function WebUpdateBadge() {
         <button
           className="pill accent"
           onClick={async () => {
+            if (
+              check.downgrade &&
+              !window.confirm(
+                `The ${check.channel} channel offers ${check.version}, which is older than ${v}. Install it anyway?`,
+              )
+            ) {
+              return
+            }
+            if (check.notes || check.notes_url) {
+              let notes = check.notes ?? ''
+              if (!notes && check.notes_url) {
+                try {
+                  setBusy('notes')
+                  notes = (await webUpdateFetchNotes(check.notes_url)) ?? ''
+                } catch (e) {
+                  notes = `(Couldn’t load the release notes: ${describeError(e)})`
+                } finally {
+                  setBusy(null)
+                }
+              }
+              if (!window.confirm(`What’s new in ${check.version}:\n\n${notes}\n\nDownload this update?`)) return
+            }
             setErr(null)
             try {
               setBusy('downloading')
-              const r = await tauriInvoke<Download>('web_update_download')
+              const r = await tauriInvoke<Download>('web_update_download', { allowDowngrade: check.downgrade })
               const st = await tauriInvoke<Status>('web_update_status')
               setStatus(st)
               setCheck({ ...check, available: false })
-              window.alert(`Update downloaded: ${r.activated_version}. Refresh to apply.`)
+              window.alert(
+                r.pending
+                  ? `Update downloaded: ${r.activated_version}. It will be applied the next time Voxelle starts.`
+                  : `Update downloaded: ${r.activated_version}. Refresh to apply.`,
+              )
             } catch (e) {
-              setErr(e instanceof Error ? e.message : String(e))
+              setErr(describeError(e))
             } finally {
               setBusy(null)
             }
           }}
           disabled={!!busy}
         >
-          {busy === 'downloading' ? 'Downloading…' : 'Download'}
+          {busy === 'downloading' ? 'Downloading…' : busy === 'notes' ? 'Loading notes…' : 'Download'}
         </button>
       ) : null}
+      {lastCheck ? (
+        <span className="muted" style={{ fontSize: 12 }}>
+          {lastCheck}
+        </span>
+      ) : null}
       <button className="pill" onClick={() => window.location.reload()} disabled={!!busy}>
         Refresh
       </button>
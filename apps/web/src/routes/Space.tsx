@@ -2,6 +2,7 @@ import { Link, useParams } from 'react-router-dom'
 import { useEffect, useMemo, useState } from 'react'
 import { exportSpaceGenesis, getState, isSpaceOwner, issueInviteFromOwner, onStateChanged, roomsForSpace } from '../voxelle/store'
 import { encodeInviteToFragment } from '../voxelle/invite_link'
+import { copySensitive } from '../voxelle/clipboard'
 import { parseInviteRendezvous } from '../voxelle/rfc/invite_bootstrap'
 
 export function SpaceRoute() {
@@ -95,7 +96,7 @@ export function SpaceRoute() {
                     } else {
                       setHostLink('')
                     }
-                    await navigator.clipboard.writeText(link)
+                    await copySensitive(link)
                   } catch (e) {
                     setErr(e instanceof Error ? e.message : String(e))
                   } finally {
@@ -119,7 +120,7 @@ export function SpaceRoute() {
                     <div className="row" style={{ gap: 8, flexWrap: 'wrap' }}>
                       <button
                         onClick={async () => {
-                          await navigator.clipboard.writeText(hostLink)
+                          await copySensitive(hostLink)
                         }}
                       >
                         Copy host link
@@ -0,0 +1,22 @@
+import { isTauri, tauriInvoke } from './tauri'
+
+// Optional fields are omitted in dev builds (no local bundle server) or when a
+// path can't be resolved. New fields may be added; none are removed.
+export type AppInfo = {
+  app_version: string
+  web_bundle_version?: string
+  bundle_server_port?: number
+  // Only while the embedded signaling relay is running.
+  signal_relay_port?: number
+  os: string
+  arch: string
+  debug: boolean
+  app_data_dir?: string
+  app_cache_dir?: string
+  protocol_version: string
+}
+
+export async function appInfo(): Promise<AppInfo | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<AppInfo>('voxelle_app_info')
+}
@@ -0,0 +1,149 @@
+import { isTauri, tauriInvoke } from './tauri'
+
+// Mirrors isnad's `Board` (crates/isnad); the desktop app folds and serializes it.
+export type BoardNote = {
+  author: string
+  ts: string
+  seq: number
+  source: 'ledger' | 'control'
+  text: string
+}
+
+export type BoardCard = {
+  task_id: string
+  title: string
+  status: string
+  priority: string
+  updated_at: string
+  updated_seq: number
+  updated_by: string | null
+  updated_via: 'ledger' | 'control' | null
+  latest_snapshot_id: string | null
+  assignee: string | null
+  tags: string[]
+  due_at: string | null
+  overdue: boolean
+  blocks: string[]
+  blocked_by: string[]
+  waiting_on_dependencies: boolean
+  // The latest 50, oldest first.
+  notes: BoardNote[]
+  closed_at: string | null
+  // From `reorder` directives: negative keys sort above unplaced cards, positive below.
+  order_key: number | null
+  parent_task_id: string | null
+  // [closed, total] over the card's subtasks; null unless it has some.
+  subtask_progress: [number, number] | null
+  has_open_subtasks: boolean
+  // Latest ledger update isn't signed by a trusted principal (only when folding with some).
+  unverified: boolean
+  stale: boolean
+  days_since_update: number | null
+  // Label of the source workspace on aggregate boards; '' otherwise.
+  workspace: string
+  unread_directive_count: number
+  provisional: boolean
+}
+
+export type Board = {
+  generated_at: string
+  // A column per status, in the workspace's configured order.
+  columns: Record<string, BoardCard[]>
+  cards: Record<string, BoardCard>
+  // Ids only; kept for one release. Prefer `unread_directive_details`.
+  unread_directives: Record<string, string[]>
+  unread_directive_details: Record<string, UnreadDirective[]>
+  last_ack_directive_id: string | null
+  last_ack_directive_ts: string | null
+  last_ack_control_seq: number
+  // Skipped lines, ignored directives and dependency cycles, as `file:line: reason`.
+  warnings: string[]
+  // Columns over their `wip_limits` from `.isnad/config.json`.
+  wip_violations: WipViolation[]
+  // Aggregate boards only: workspace label -> root.
+  workspaces: Record<string, string>
+}
+
+export type UnreadDirective = {
+  id: string
+  type: string
+  ts: string
+  author: string | null
+  rationale: string | null
+  payload: unknown
+}
+
+export type WipViolation = {
+  status: string
+  limit: number
+  count: number
+}
+
+// Rejections carry `{ code: 'not_a_workspace' | 'invalid_root' | 'outside_allowed_roots' | 'io', detail }`.
+export type BoardErrorCode = 'not_a_workspace' | 'invalid_root' | 'outside_allowed_roots' | 'io'
+
+export function boardAvailable(): boolean {
+  return isTauri()
+}
+
+// With `create`, a root without `.isnad` is scaffolded before folding.
+export async function boardFold(root: string, create = false): Promise<Board> {
+  if (!boardAvailable()) throw new Error('board not available')
+  return await tauriInvoke<Board>('voxelle_board_fold', { root, create })
+}
+
+// Emitted as `{ root, directive_id }` after the desktop app appends a directive.
+export const BOARD_CHANGED_EVENT = 'voxelle:board-changed'
+
+// Appends to `.isnad/control.jsonl` only; returns the new directive id.
+export async function boardDirective(
+  root: string,
+  directiveType: string,
+  taskId?: string,
+  payload?: Record<string, unknown>,
+  rationale?: string,
+): Promise<string> {
+  if (!boardAvailable()) throw new Error('board not available')
+  return await tauriInvoke<string>('voxelle_board_directive', { root, directiveType, taskId, payload, rationale })
+}
+
+export type BoardSettings = { author: string }
+
+export async function boardGetSettings(): Promise<BoardSettings | null> {
+  if (!boardAvailable()) return null
+  return await tauriInvoke<BoardSettings>('voxelle_board_get_settings')
+}
+
+export async function boardSetSettings(settings: BoardSettings): Promise<void> {
+  if (!boardAvailable()) throw new Error('board not available')
+  await tauriInvoke('voxelle_board_set_settings', { settings })
+}
+
+// Emitted as `{ root, files }` when ledger.jsonl or control.jsonl change on
+// disk, batched to a few events a second per workspace.
+export const ISNAD_CHANGED_EVENT = 'voxelle:isnad-changed'
+export type IsnadChanged = { root: string; files: string[] }
+
+// Watches are counted per window; pair every boardWatch with a boardUnwatch.
+export async function boardWatch(root: string): Promise<void> {
+  if (!boardAvailable()) return
+  await tauriInvoke('voxelle_board_watch', { root })
+}
+
+export async function boardUnwatch(root: string): Promise<void> {
+  if (!boardAvailable()) return
+  await tauriInvoke('voxelle_board_unwatch', { root })
+}
+
+// Serves the board over HTTP on localhost so it can be opened in a browser.
+// One server per workspace; serving again returns the same URL.
+export async function boardServe(root: string): Promise<{ url: string }> {
+  if (!boardAvailable()) throw new Error('board not available')
+  return await tauriInvoke<{ url: string }>('voxelle_board_serve', { root })
+}
+
+// Resolves false when no server was running for `root`.
+export async function boardStop(root: string): Promise<boolean> {
+  if (!boardAvailable()) return false
+  return await tauriInvoke<boolean>('voxelle_board_stop', { root })
+}
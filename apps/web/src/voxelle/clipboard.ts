@@ -0,0 +1,18 @@
+import { isTauri, tauriInvoke } from './tauri'
+
+// For invites, session ids and pairing codes. The desktop app clears the
+// clipboard after `ttlSeconds` (1-3600) unless something else was copied since;
+// a later copy replaces the pending clear. Elsewhere this is a plain copy.
+export async function copySensitive(text: string, ttlSeconds = 60): Promise<void> {
+  if (!isTauri()) {
+    await navigator.clipboard.writeText(text)
+    return
+  }
+  await tauriInvoke('voxelle_copy_sensitive', { text, ttlSeconds })
+}
+
+// Keeps the last sensitive copy; resolves to false when no clear was pending.
+export async function copyCancelClear(): Promise<boolean> {
+  if (!isTauri()) return false
+  return await tauriInvoke<boolean>('voxelle_copy_cancel_clear')
+}
@@ -0,0 +1,16 @@
+import { isTauri, tauriInvoke } from './tauri'
+import type { InviteV1 } from './rfc/invite'
+
+// Emitted with a validated `DeepLink` for each `voxelle://` link the app is
+// opened with. The raw URL is never forwarded.
+export const DEEP_LINK_EVENT = 'voxelle:deep-link'
+
+export type DeepLink =
+  | { kind: 'join'; invite: InviteV1 }
+  | { kind: 'session'; sid: string; relay: string }
+
+// Call after subscribing to DEEP_LINK_EVENT; returns links that arrived before that.
+export async function deepLinksReady(): Promise<DeepLink[]> {
+  if (!isTauri()) return []
+  return await tauriInvoke<DeepLink[]>('voxelle_deep_links_ready')
+}
@@ -0,0 +1,62 @@
+import { isTauri, tauriInvoke } from './tauri'
+
+// Device identities held by the desktop app. Private keys stay in the OS
+// keyring; the webview only ever sees the public half and signatures.
+export type DeviceIdentity = {
+  principal_id: string
+  label: string
+  principal_pub_spki_b64: string
+  created_at: number
+}
+
+// Envelope kinds the desktop app will sign; anything else is refused.
+export type SigningContext = 'chat.msg' | 'chat.edit' | 'chat.delete' | 'chat.ack' | 'sync.have'
+
+// The envelope fields plus `sender_pub`, `alg` and `sig`, as sent on the wire.
+export type SignedEnvelope = {
+  v: number
+  kind: SigningContext
+  msg_id: string
+  in_reply_to?: string
+  sender: string
+  ts: number
+  payload: unknown
+  sender_pub: string
+  alg: string
+  sig: string
+}
+
+// Rejections carry `{ code, detail? }`.
+export type IdentityErrorCode =
+  | 'unknown_identity'
+  | 'context_not_allowed'
+  | 'invalid_label'
+  | 'invalid_payload'
+  | 'auth_required'
+  | 'auth_denied'
+  | 'other'
+
+export async function identityCreate(label: string): Promise<DeviceIdentity> {
+  if (!isTauri()) throw new Error('device identities not available')
+  return await tauriInvoke<DeviceIdentity>('voxelle_identity_create', { label })
+}
+
+export async function identityList(): Promise<DeviceIdentity[]> {
+  if (!isTauri()) return []
+  return await tauriInvoke<DeviceIdentity[]>('voxelle_identity_list')
+}
+
+export async function identitySign(principalId: string, context: SigningContext, payload: unknown): Promise<SignedEnvelope> {
+  if (!isTauri()) throw new Error('device identities not available')
+  return await tauriInvoke<SignedEnvelope>('voxelle_identity_sign', {
+    principalId,
+    context,
+    payloadJson: JSON.stringify(payload),
+  })
+}
+
+// Asks the user to confirm with Touch ID, Windows Hello or a native prompt first.
+export async function identityDelete(principalId: string): Promise<void> {
+  if (!isTauri()) throw new Error('device identities not available')
+  await tauriInvoke('voxelle_identity_delete', { principalId })
+}
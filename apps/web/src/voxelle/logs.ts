@@ -0,0 +1,25 @@
+import { isTauri, tauriInvoke } from './tauri'
+
+// The desktop app logs to a file a day in its log folder and keeps the last week.
+export type LogLevel = 'ERROR' | 'WARN' | 'INFO' | 'DEBUG' | 'TRACE'
+export type LogEntry = {
+  timestamp: string
+  level: LogLevel
+  target: string
+  message: string
+  // The event's other fields.
+  fields: Record<string, unknown>
+  // Spans the event happened in, outermost first, e.g. ['download_and_activate', 'activate'].
+  spans: string[]
+}
+
+// Today's last `lines` entries (default 200, at most 5000) at `level` or more severe, oldest first.
+export async function logsRecent(opts: { lines?: number; level?: Lowercase<LogLevel> } = {}): Promise<LogEntry[]> {
+  if (!isTauri()) return []
+  return await tauriInvoke<LogEntry[]>('voxelle_logs_recent', { lines: opts.lines ?? null, levelFilter: opts.level ?? null })
+}
+
+export async function logsOpenFolder(): Promise<void> {
+  if (!isTauri()) return
+  await tauriInvoke('voxelle_logs_open_folder')
+}
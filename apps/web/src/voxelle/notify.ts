@@ -0,0 +1,23 @@
+import { isTauri, tauriInvoke } from './tauri'
+
+// The desktop app also raises these itself for web updates and for watched
+// workspaces, batching bursts into one summary while the window is in the background.
+export type NotifyKind = 'update_ready' | 'directive' | 'blocked' | 'message'
+// 'attention' means notifications are denied, so the app icon bounced or flashed instead.
+export type NotifyOutcome = 'shown' | 'attention' | 'disabled'
+export type NotifySettings = Record<NotifyKind, boolean>
+
+export async function notify(title: string, body: string, kind: NotifyKind = 'message'): Promise<NotifyOutcome | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<NotifyOutcome>('voxelle_notify', { title, body, kind })
+}
+
+export async function notifyGetSettings(): Promise<NotifySettings | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<NotifySettings>('voxelle_notify_get_settings')
+}
+
+export async function notifySetSettings(settings: NotifySettings): Promise<void> {
+  if (!isTauri()) return
+  await tauriInvoke('voxelle_notify_set_settings', { settings })
+}
@@ -0,0 +1,25 @@
+// Served by the desktop app's bundle server at a fixed path, generated per
+// request from its live state, so it reflects updates without a reload.
+// Absent in a plain browser or the Vite dev server, where this resolves to null.
+export type RuntimeConfig = {
+  desktop: boolean
+  app_version: string
+  port: number
+  active_version: string
+  active_channel: 'stable' | 'beta' | null
+  // Only while the embedded signaling relay is running.
+  signal_relay_url: string | null
+  features: { signal_relay: boolean }
+}
+
+export const RUNTIME_CONFIG_PATH = '/__voxelle/config.json'
+
+export async function runtimeConfig(): Promise<RuntimeConfig | null> {
+  try {
+    const res = await fetch(RUNTIME_CONFIG_PATH, { cache: 'no-store' })
+    if (!res.ok || !res.headers.get('content-type')?.includes('application/json')) return null
+    return (await res.json()) as RuntimeConfig
+  } catch {
+    return null
+  }
+}
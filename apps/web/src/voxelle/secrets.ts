@@ -1,21 +1,89 @@
 import { isTauri, tauriInvoke } from './tauri'
 
+// Secrets live in namespaces (e.g. one per space); omitting it uses "default".
+
 export function secretsAvailable(): boolean {
   return isTauri()
 }
 
-export async function secretGet(key: string): Promise<string | null> {
+// Keys stored with `requireAuth` prompt for Touch ID, Windows Hello or a native
+// confirmation before they are returned. Rejections carry
+// `{ code: 'auth_required' | 'auth_denied' | 'other', detail? }`.
+export type SecretErrorCode = 'auth_required' | 'auth_denied' | 'other'
+
+export async function secretGet(key: string, namespace?: string): Promise<string | null> {
   if (!secretsAvailable()) return null
-  return (await tauriInvoke<string | null>('voxelle_secret_get', { key })) ?? null
+  return (await tauriInvoke<string | null>('voxelle_secret_get', { key, namespace })) ?? null
 }
 
-export async function secretSet(key: string, value: string): Promise<void> {
+// Omitting `requireAuth` keeps the key's current setting.
+export async function secretSet(key: string, value: string, namespace?: string, requireAuth?: boolean): Promise<void> {
   if (!secretsAvailable()) throw new Error('secrets not available')
-  await tauriInvoke('voxelle_secret_set', { key, value })
+  await tauriInvoke('voxelle_secret_set', { key, value, namespace, requireAuth })
+}
+
+export async function secretRequiresAuth(key: string, namespace?: string): Promise<boolean> {
+  if (!secretsAvailable()) return false
+  return await tauriInvoke<boolean>('voxelle_secret_requires_auth', { key, namespace })
 }
 
-export async function secretDelete(key: string): Promise<void> {
+// Seconds one successful check covers further protected reads; 0 asks every time.
+export async function secretGetAuthWindow(): Promise<number | null> {
+  if (!secretsAvailable()) return null
+  return await tauriInvoke<number>('voxelle_secret_get_auth_window')
+}
+
+export async function secretSetAuthWindow(secs: number): Promise<void> {
+  if (!secretsAvailable()) throw new Error('secrets not available')
+  await tauriInvoke('voxelle_secret_set_auth_window', { secs })
+}
+
+export async function secretDelete(key: string, namespace?: string): Promise<void> {
   if (!secretsAvailable()) return
-  await tauriInvoke('voxelle_secret_delete', { key })
+  await tauriInvoke('voxelle_secret_delete', { key, namespace })
+}
+
+export async function secretList(namespace?: string): Promise<string[]> {
+  if (!secretsAvailable()) return []
+  return await tauriInvoke<string[]>('voxelle_secret_list', { namespace })
+}
+
+export async function secretDeleteNamespace(namespace: string): Promise<number> {
+  if (!secretsAvailable()) return 0
+  return await tauriInvoke<number>('voxelle_secret_delete_namespace', { namespace })
+}
+
+export type SecretImportStatus = 'created' | 'skipped' | 'overwritten'
+export type SecretImportResult = { namespace: string; key: string; status: SecretImportStatus }
+// Rejections carry `{ code: 'wrong_passphrase' | 'corrupt_blob' | 'unsupported_version' | 'auth_required' |
+// 'auth_denied' | 'other', detail? }`; exports prompt like `secretGet` when they include protected keys.
+
+export async function secretExport(passphrase: string): Promise<string> {
+  if (!secretsAvailable()) throw new Error('secrets not available')
+  return await tauriInvoke<string>('voxelle_secret_export', { passphrase })
+}
+
+export async function secretImport(blob: string, passphrase: string, overwrite = false): Promise<SecretImportResult[]> {
+  if (!secretsAvailable()) throw new Error('secrets not available')
+  return await tauriInvoke<SecretImportResult[]>('voxelle_secret_import', { blob, passphrase, overwrite })
 }
 
+// 'file' means no OS keyring was found and secrets are kept in an encrypted file.
+export type SecretBackend = { backend: 'keyring' | 'file'; passphrase_protected: boolean; locked: boolean }
+
+export async function secretBackend(): Promise<SecretBackend | null> {
+  if (!secretsAvailable()) return null
+  return await tauriInvoke<SecretBackend>('voxelle_secret_backend')
+}
+
+// Unlocks a passphrase-protected file store for this session.
+export async function secretUnlock(passphrase: string): Promise<void> {
+  if (!secretsAvailable()) throw new Error('secrets not available')
+  await tauriInvoke('voxelle_secret_unlock', { passphrase })
+}
+
+// Sets the passphrase on a file store without one; the user confirms their presence first.
+export async function secretSetPassphrase(passphrase: string): Promise<void> {
+  if (!secretsAvailable()) throw new Error('secrets not available')
+  await tauriInvoke('voxelle_secret_set_passphrase', { passphrase })
+}
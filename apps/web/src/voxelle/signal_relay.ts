@@ -0,0 +1,33 @@
+import { isTauri, tauriInvoke } from './tauri'
+
+// The signaling relay the desktop app can host itself, for LAN-only or
+// private use instead of a public relay. Only one runs at a time.
+export type SignalRelayInfo = { port: number; url: string }
+export type SignalRelayStatus = {
+  running: boolean
+  port: number | null
+  url: string | null
+  // Bound to every interface instead of localhost; `warning` explains what that exposes.
+  lan: boolean
+  warning?: string
+}
+export type SignalRelayError =
+  | { code: 'already_running'; detail: number }
+  | { code: 'not_running' }
+  | { code: 'bind'; detail: string }
+  | { code: 'other'; detail: string }
+
+export async function signalRelayStart(opts: { port?: number; allowLan?: boolean } = {}): Promise<SignalRelayInfo | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<SignalRelayInfo>('voxelle_signal_start', { port: opts.port ?? null, allowLan: opts.allowLan ?? false })
+}
+
+export async function signalRelayStop(): Promise<void> {
+  if (!isTauri()) return
+  await tauriInvoke('voxelle_signal_stop')
+}
+
+export async function signalRelayStatus(): Promise<SignalRelayStatus | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<SignalRelayStatus>('voxelle_signal_status')
+}
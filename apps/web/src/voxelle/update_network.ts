@@ -0,0 +1,43 @@
+import { isTauri, tauriInvoke } from './tauri'
+import { secretDelete, secretSet } from './secrets'
+
+// How the desktop app reaches the web update feed. Proxy credentials are not
+// part of this; they are kept in the keyring (see `updateProxyCredentialsSet`).
+export type UpdateNetworkConfig = {
+  proxy_url: string | null
+  // Honor HTTP_PROXY / HTTPS_PROXY / NO_PROXY when no proxy_url is set.
+  use_system_proxy: boolean
+  // PEM file with extra root certificates.
+  extra_root_cert: string | null
+  timeout_secs: number
+}
+
+// Rejections are a `WebUpdateCommandError` with code 'invalid_network_config' and
+// `details.reason` one of 'invalid_proxy_url' | 'proxy_credentials' | 'cert_unreadable'
+// | 'cert_invalid' | 'invalid_timeout'; nothing is saved when the config is rejected.
+export async function updateNetworkConfigGet(): Promise<UpdateNetworkConfig | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<UpdateNetworkConfig>('web_update_get_network_config')
+}
+
+export async function updateNetworkConfigSet(config: UpdateNetworkConfig): Promise<void> {
+  if (!isTauri()) return
+  await tauriInvoke('web_update_set_network_config', { config })
+}
+
+const PROXY_NAMESPACE = 'web_update'
+
+// Stores the proxy credentials and rebuilds the update client so they apply
+// now. Pass null to remove them.
+export async function updateProxyCredentialsSet(creds: { username: string; password: string } | null): Promise<void> {
+  if (!isTauri()) return
+  if (creds) {
+    await secretSet('proxy_username', creds.username, PROXY_NAMESPACE)
+    await secretSet('proxy_password', creds.password, PROXY_NAMESPACE)
+  } else {
+    await secretDelete('proxy_username', PROXY_NAMESPACE)
+    await secretDelete('proxy_password', PROXY_NAMESPACE)
+  }
+  const config = await updateNetworkConfigGet()
+  if (config) await updateNetworkConfigSet(config)
+}
@@ -0,0 +1,239 @@
+import { isTauri, tauriInvoke } from './tauri'
+
+// What every `web_update_*` command rejects with. Branch on `code`; `message`
+// is for display.
+export type WebUpdateErrorCode =
+  | 'feed_not_set'
+  | 'offline'
+  | 'timeout'
+  | 'http_status'
+  | 'manifest_invalid'
+  | 'hash_mismatch'
+  | 'download_too_large'
+  | 'insufficient_disk_space'
+  | 'notes_too_large'
+  | 'notes_unsupported_type'
+  | 'extract_failed'
+  | 'downgrade_not_confirmed'
+  | 'invalid_settings'
+  | 'invalid_dev_override'
+  | 'dev_override_not_confirmed'
+  // voxelle_set_storage_root got a path that isn't an absolute, writable
+  // folder outside the app, or whose voxelle folder isn't empty.
+  | 'invalid_storage_root'
+  // Another check, download, rollback, prune, reset or storage move is running; see
+  // `details.operation` and web_update_status's `operation_in_progress`.
+  | 'busy'
+  // The bundle URL, or a redirect, left the feed's host (or trusted_zip_hosts),
+  // or wasn't https.
+  | 'untrusted_zip_origin'
+  | 'invalid_network_config'
+  | 'no_previous_version'
+  | 'invalid_previous_version'
+  | 'previous_bundle_missing'
+  | 'io'
+export type WebUpdateCommandError = {
+  code: WebUpdateErrorCode
+  message: string
+  // Trying again later may succeed (offline, timeouts, 5xx).
+  retryable: boolean
+  // e.g. `{ status }` for http_status, `{ channel, offered, active }` for
+  // downgrade_not_confirmed, `{ operation }` for busy, `{ phase }` ('manifest',
+  // 'delta' or 'download') for a timeout that hit a check or download deadline,
+  // `{ zip_host, feed_host, https }` for untrusted_zip_origin.
+  details?: Record<string, unknown>
+}
+
+export function isWebUpdateCommandError(e: unknown): e is WebUpdateCommandError {
+  return typeof e === 'object' && e !== null && 'code' in e && 'message' in e
+}
+
+// Events from the desktop app's background update checker for feeds that set
+// `min_version`. The active bundle is older than that minimum and must not
+// keep running against the current protocol.
+
+// Emitted after the required bundle was installed and activated; reload to run it.
+export const WEB_UPDATE_MANDATORY_APPLIED_EVENT = 'voxelle:web-update-mandatory-applied'
+export type WebUpdateMandatoryApplied = { activated_version: string; min_version: string }
+
+// Emitted when the required bundle couldn't be installed; show a blocking
+// screen until a later check succeeds.
+export const WEB_UPDATE_BLOCKED_EVENT = 'voxelle:web-update-blocked'
+export type WebUpdateBlocked = {
+  active_version: string
+  min_version: string
+  error: WebUpdateCommandError
+}
+
+// Emitted at startup when the active bundle failed its integrity check and
+// another one is served instead. It usually fires before the page loads, so
+// web_update_status reports the same thing as `repair`.
+export const WEB_BUNDLE_REPAIRED_EVENT = 'voxelle:web-bundle-repaired'
+export type WebBundleRepair = {
+  broken_version: string
+  problem: string
+  action: 'reextracted_embedded' | 'reactivated'
+  activated_version: string
+}
+
+// Emitted while web_update_download fetches a delta patch or the full zip.
+// A delta that fails is followed by a 'full' download starting from zero.
+export const WEB_UPDATE_PROGRESS_EVENT = 'voxelle:web-update-progress'
+export type WebUpdateProgress = {
+  version: string
+  kind: 'delta' | 'full'
+  downloaded: number
+  // null when the server doesn't send a Content-Length.
+  total: number | null
+}
+
+// One entry per feed the last check tried, in order. Feeds after the one that
+// served the manifest are not contacted.
+export type WebUpdateFeedAttempt = {
+  feed: string
+  // What was fetched for the channel, e.g. the resolved GitHub release URL.
+  url: string
+  // null for the feed that served the manifest.
+  error: string | null
+}
+export type WebUpdateLastCheck = {
+  checked_at: number
+  result:
+    | { result: 'up_to_date' }
+    | { result: 'available'; version: string; downgrade: boolean }
+    | { result: 'skipped'; version: string }
+    | { result: 'failed'; error: string }
+  source: string | null
+  sources: WebUpdateFeedAttempt[]
+}
+
+// The persisted log of checks, downloads, activations, rollbacks and repairs
+// (the last ~500 survive), for diagnostics. web_update_status carries the
+// latest activation as `last_activation`.
+export type WebUpdateHistoryEntry = { at: number } & (
+  | { event: 'check'; result: WebUpdateLastCheck['result']; source: string | null; latency_ms: number }
+  | {
+      event: 'download'
+      version: string
+      kind: 'delta' | 'full'
+      bytes: number
+      duration_ms: number
+      // null when the download verified.
+      error: string | null
+    }
+  | { event: 'activation'; version: string; channel: 'stable' | 'beta' | null; previous: string }
+  | { event: 'rollback'; from: string; to: string }
+  // Besides the activation: an older version installed on purpose, or the
+  // active one reinstalled with `force`.
+  | { event: 'downgrade'; from: string; to: string }
+  | { event: 'reinstall'; version: string }
+  | ({ event: 'repair' } & WebBundleRepair)
+)
+
+// Newest first; the desktop app defaults `limit` to 100.
+export async function webUpdateHistory(limit?: number): Promise<WebUpdateHistoryEntry[]> {
+  if (!isTauri()) return []
+  return await tauriInvoke<WebUpdateHistoryEntry[]>('web_update_history', { limit })
+}
+
+export async function webUpdateLastCheckDetails(): Promise<WebUpdateLastCheck | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<WebUpdateLastCheck | null>('web_update_last_check_details')
+}
+
+// Release notes linked from the manifest (`notes_url` in a check result).
+// Rejects with 'notes_too_large' or 'notes_unsupported_type' rather than
+// returning partial or non-text content.
+export async function webUpdateFetchNotes(url: string): Promise<string | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<string>('web_update_fetch_notes', { url })
+}
+
+// "Skip this version": checks offering exactly `version` report
+// `available: false, skipped: true`; newer versions are offered as usual. null
+// un-skips. A mandatory update (feed `min_version`) clears the skip and any snooze.
+export async function webUpdateSkipVersion(version: string | null): Promise<string | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<string | null>('web_update_skip_version', { version })
+}
+
+export async function webUpdateGetSkippedVersion(): Promise<string | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<string | null>('web_update_get_skipped_version')
+}
+
+// Background checks keep running but stop emitting the update-available event
+// for `hours` (0 ends the snooze). Resolves to the deadline in unix ms.
+export async function webUpdateSnooze(hours: number): Promise<number | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<number | null>('web_update_snooze', { hours })
+}
+
+export async function webUpdateGetSnooze(): Promise<number | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<number | null>('web_update_get_snooze')
+}
+
+// Loads the UI from a local directory (e.g. apps/web/dist) or an http(s) URL
+// such as a Vite dev server, instead of the installed bundle; null goes back.
+// Never persisted, so a restart also goes back. Release builds reject
+// overrides with 'dev_override_not_confirmed' unless `confirm` is set.
+export type WebUpdateDevOverride = { kind: 'dir'; target: string } | { kind: 'url'; target: string }
+
+export async function webUpdateSetDevOverride(
+  pathOrUrl: string | null,
+  confirm = false,
+): Promise<WebUpdateDevOverride | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<WebUpdateDevOverride | null>('web_update_set_dev_override', { pathOrUrl, confirm })
+}
+
+// Goes back to the bundle shipped with the app and deletes every downloaded
+// bundle and cached update state; with keepSettings false the feeds, network
+// config and update settings are reset too. The window reloads afterwards.
+export type WebUpdateResetResult = {
+  removed: string[]
+  bytes_reclaimed: number
+  // Left in place (e.g. files in use); bundles among them go on next launch.
+  failed: string[]
+  active_version: string
+}
+
+export async function webUpdateReset(keepSettings: boolean): Promise<WebUpdateResetResult | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<WebUpdateResetResult>('web_update_reset', { keepSettings })
+}
+
+// Where bundles, downloads and update state are kept: a `voxelle` folder under
+// `storage_root`, or in the OS cache dir when that is null.
+export type StorageLocation = {
+  storage_root: string | null
+  cache_dir: string
+  // The storage root's disk isn't mounted, so the OS cache dir stands in.
+  unavailable: boolean
+}
+
+// Emitted while voxelle_set_storage_root copies the cache.
+export const STORAGE_MOVE_PROGRESS_EVENT = 'voxelle:storage-move-progress'
+export type StorageMoveProgress = { copied: number; total: number }
+
+export type StorageMoveResult = {
+  storage_root: string | null
+  cache_dir: string
+  bytes_moved: number
+  // False if some of the old copy couldn't be deleted yet; retried next launch.
+  old_removed: boolean
+}
+
+export async function getStorageRoot(): Promise<StorageLocation | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<StorageLocation>('voxelle_get_storage_root')
+}
+
+// Copies everything under `path` (null: back to the OS cache dir), checks it
+// and deletes the old copy; the UI keeps running throughout. Rejects with a
+// WebUpdateCommandError, `insufficient_disk_space` when it won't fit.
+export async function setStorageRoot(path: string | null): Promise<StorageMoveResult | null> {
+  if (!isTauri()) return null
+  return await tauriInvoke<StorageMoveResult>('voxelle_set_storage_root', { path })
+}
@@ -0,0 +1,27 @@
+import { isTauri, tauriInvoke } from './tauri'
+
+// A folder opened through the native dialog. Board commands accept roots
+// outside the home directory only if they were opened this way.
+export type Workspace = { path: string; name: string; has_isnad: boolean; opened_at: number }
+
+export function workspacesAvailable(): boolean {
+  return isTauri()
+}
+
+// Resolves to null when the user cancels the dialog. Rejections carry the same
+// `{ code, detail }` shape as the board commands.
+export async function workspaceOpen(): Promise<Workspace | null> {
+  if (!workspacesAvailable()) return null
+  return (await tauriInvoke<Workspace | null>('voxelle_workspace_open')) ?? null
+}
+
+// Most recent first, at most 10; folders that no longer exist are dropped.
+export async function workspaceRecent(): Promise<Workspace[]> {
+  if (!workspacesAvailable()) return []
+  return await tauriInvoke<Workspace[]>('voxelle_workspace_recent')
+}
+
+export async function workspaceForget(path: string): Promise<void> {
+  if (!workspacesAvailable()) return
+  await tauriInvoke('voxelle_workspace_forget', { path })
+}
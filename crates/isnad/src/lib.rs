@@ -1,22 +1,79 @@
 use anyhow::{anyhow, Context, Result};
-use chrono::{SecondsFormat, Utc};
+use base64::Engine;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::{Signature, Signer, SigningKey};
+use fs2::FileExt;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 use uuid::Uuid;
+use voxelle_protocol::{ed25519_public_key_from_spki_der, jcs_bytes, principal_id_from_spki_der, CanonicalTime};
 
+/// The statuses and priorities a workspace has unless config.json says otherwise.
 pub const STATUSES: [&str; 6] = ["backlog", "next", "doing", "blocked", "done", "rejected"];
 pub const PRIORITIES: [&str; 4] = ["low", "medium", "high", "urgent"];
+/// Statuses fold moves cards into by itself, which a configured list must keep.
+const REQUIRED_STATUSES: [&str; 5] = ["backlog", "doing", "blocked", "done", "rejected"];
+/// Directive types that act on a single card and so must carry a `task_id`.
+pub const TASK_SCOPED_DIRECTIVES: [&str; 14] = [
+    "set_status",
+    "set_priority",
+    "set_assignee",
+    "add_tag",
+    "remove_tag",
+    "set_due",
+    "link_tasks",
+    "unlink_tasks",
+    "pause",
+    "resume",
+    "close_task",
+    "reopen_task",
+    "note",
+    "reorder",
+];
+/// Every directive type fold knows, task-scoped or not.
+pub const DIRECTIVE_TYPES: [&str; 18] = [
+    "open_task",
+    "set_status",
+    "set_priority",
+    "set_assignee",
+    "add_tag",
+    "remove_tag",
+    "set_due",
+    "link_tasks",
+    "unlink_tasks",
+    "pause",
+    "resume",
+    "close_task",
+    "reopen_task",
+    "note",
+    "reorder",
+    "set_goal",
+    "request_summary",
+    "reject_record",
+];
+pub const MAX_TAG_LEN: usize = 64;
+/// Notes kept on a card in the board; [`task_history`] has them all.
+pub const MAX_CARD_NOTES: usize = 50;
+
+// Serializes appends from this process (board server handlers, desktop commands).
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+// Numbers temp files, so writers in one process don't share them either.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 pub fn utc_now() -> String {
-    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    CanonicalTime::now().to_string()
 }
 
 pub fn new_id(prefix: &str, suffix_hex_len: usize) -> String {
-    let ts = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let ts = CanonicalTime::now().as_datetime().format("%Y%m%dT%H%M%SZ");
     let mut hex = Uuid::new_v4().simple().to_string();
     hex.truncate(suffix_hex_len);
     format!("{prefix}_{ts}_{hex}")
@@ -31,7 +88,14 @@ pub struct Paths {
     pub state_dir: PathBuf,
     pub board_json: PathBuf,
     pub board_md: PathBuf,
+    pub board_html: PathBuf,
+    pub tasks_dir: PathBuf,
     pub cursors: PathBuf,
+    pub config: PathBuf,
+    pub conflicts: PathBuf,
+    pub fold_cache: PathBuf,
+    pub last_diff: PathBuf,
+    pub sequence: PathBuf,
 }
 
 pub fn paths_for(root: impl AsRef<Path>) -> Paths {
@@ -46,7 +110,14 @@ pub fn paths_for(root: impl AsRef<Path>) -> Paths {
         state_dir: state_dir.clone(),
         board_json: state_dir.join("board.json"),
         board_md: state_dir.join("board.md"),
+        board_html: state_dir.join("board.html"),
+        tasks_dir: state_dir.join("tasks"),
         cursors: state_dir.join("cursors.json"),
+        config: isnad_dir.join("config.json"),
+        conflicts: isnad_dir.join("conflicts.jsonl"),
+        fold_cache: state_dir.join("fold_cache.json"),
+        last_diff: state_dir.join("last_diff.json"),
+        sequence: state_dir.join("sequence.json"),
     }
 }
 
@@ -54,6 +125,22 @@ fn ensure_dir(path: &Path) -> Result<()> {
     fs::create_dir_all(path).with_context(|| format!("create dir {}", path.display()))
 }
 
+// A sibling of `path` no other writer uses, to write into and rename over it.
+fn temp_path(path: &Path) -> PathBuf {
+    let n = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{n}.tmp", std::process::id()));
+    path.with_file_name(name)
+}
+
+// Replaces `path` with `bytes` by rename, so readers see the old file or the
+// new one and concurrent writers each leave a whole file behind.
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = temp_path(path);
+    fs::write(&tmp, bytes).with_context(|| format!("write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("write {}", path.display()))
+}
+
 fn write_json_pretty(path: &Path, value: &Value) -> Result<()> {
     ensure_dir(
         path.parent()
@@ -64,17 +151,147 @@ fn write_json_pretty(path: &Path, value: &Value) -> Result<()> {
     Ok(())
 }
 
+/// Appends one record as a line. Appends are serialized within the process and
+/// each line goes out in a single write, so concurrent writers never interleave.
 pub fn append_jsonl(path: &Path, value: &Value) -> Result<()> {
     ensure_dir(
         path.parent()
             .ok_or_else(|| anyhow!("no parent for {}", path.display()))?,
     )?;
+    let line = format!("{}\n", serde_json::to_string(value)?);
+    let _guard = APPEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    append_line_locked(path, &line)
+}
+
+/// Where [`append_jsonl_rotating`] moves a full log: `history.jsonl` becomes
+/// `history.1.jsonl`.
+pub fn rotated_jsonl_path(path: &Path) -> PathBuf {
+    path.with_extension("1.jsonl")
+}
+
+/// Like [`append_jsonl`], but once the file has reached `max_bytes` it is first
+/// moved to [`rotated_jsonl_path`], replacing the previous one. At most two
+/// files' worth of records are kept. Rotation happens under the append lock so
+/// concurrent writers never lose a line to it.
+pub fn append_jsonl_rotating(path: &Path, value: &Value, max_bytes: u64) -> Result<()> {
+    ensure_dir(
+        path.parent()
+            .ok_or_else(|| anyhow!("no parent for {}", path.display()))?,
+    )?;
+    let line = format!("{}\n", serde_json::to_string(value)?);
+    let _guard = APPEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if fs::metadata(path).is_ok_and(|m| m.len() >= max_bytes) {
+        let rotated = rotated_jsonl_path(path);
+        fs::rename(path, &rotated)
+            .with_context(|| format!("rotate {} to {}", path.display(), rotated.display()))?;
+    }
+    append_line_locked(path, &line)
+}
+
+fn append_line_locked(path: &Path, line: &str) -> Result<()> {
     let mut file = fs::OpenOptions::new()
         .create(true)
         .append(true)
         .open(path)
         .with_context(|| format!("open {}", path.display()))?;
-    writeln!(file, "{}", serde_json::to_string(value)?)?;
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("append {}", path.display()))?;
+    Ok(())
+}
+
+/// The counter behind `_seq`, kept in `state/sequence.json`: the last number
+/// given out, and how far into each log it has looked for higher ones.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct SeqCounter {
+    last: i64,
+    ledger_at: JsonlCursor,
+    control_at: JsonlCursor,
+}
+
+// Opens `state/sequence.json` and takes an exclusive lock on it, held until
+// the file is dropped. Every process appending to the workspace's logs goes
+// through it.
+fn lock_sequence(p: &Paths) -> Result<fs::File> {
+    ensure_dir(&p.state_dir)?;
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&p.sequence)
+        .with_context(|| format!("open {}", p.sequence.display()))?;
+    file.lock_exclusive()
+        .with_context(|| format!("lock {}", p.sequence.display()))?;
+    Ok(file)
+}
+
+/// Appends `record` to the workspace's ledger or control log (`log` is
+/// `p.ledger` or `p.control`), stamped with the next `_seq`. The numbers run
+/// across both logs and each is higher than any either log holds, so a fold
+/// applies the two in the order they were written. Appends from every process
+/// are serialized by a lock on `state/sequence.json`. Returns the `_seq` given.
+pub fn append_record(p: &Paths, log: &Path, record: &Value) -> Result<i64> {
+    if log != p.ledger && log != p.control {
+        anyhow::bail!("{} is neither the ledger nor the control log", log.display());
+    }
+    let mut record = record.clone();
+    let obj = record.as_object_mut().ok_or_else(|| anyhow!("record must be a JSON object"))?;
+
+    let _guard = APPEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut lock = lock_sequence(p)?;
+    let mut counter = read_counter(p, &mut lock)?;
+    let mut last = counter.last;
+    for (path, at) in [(&p.ledger, &mut counter.ledger_at), (&p.control, &mut counter.control_at)] {
+        if file_len(path) < at.bytes || !ends_line_at(path, at.bytes) {
+            *at = JsonlCursor::default();
+        }
+        read_jsonl_from(path, at, |_, rec| {
+            if let Ok((_, rec)) = rec {
+                last = last.max(rec.get("_seq").and_then(|v| v.as_i64()).unwrap_or(0));
+            }
+        })?;
+    }
+
+    counter.last = last + 1;
+    obj.insert("_seq".to_string(), counter.last.into());
+    append_line_locked(log, &format!("{}\n", serde_json::to_string(&record)?))?;
+    write_counter(p, &mut lock, &counter)?;
+    Ok(counter.last)
+}
+
+// Unreadable means starting over: the logs themselves say how far it got.
+fn read_counter(p: &Paths, lock: &mut fs::File) -> Result<SeqCounter> {
+    let mut raw = Vec::new();
+    lock.seek(SeekFrom::Start(0))
+        .and_then(|_| lock.read_to_end(&mut raw))
+        .with_context(|| format!("read {}", p.sequence.display()))?;
+    Ok(serde_json::from_slice(&raw).unwrap_or_default())
+}
+
+fn write_counter(p: &Paths, lock: &mut fs::File, counter: &SeqCounter) -> Result<()> {
+    let bytes = serde_json::to_vec(counter)?;
+    lock.set_len(0)
+        .and_then(|_| lock.seek(SeekFrom::Start(0)))
+        .and_then(|_| lock.write_all(&bytes))
+        .with_context(|| format!("write {}", p.sequence.display()))
+}
+
+pub fn validate_task_id(task_id: &str) -> Result<()> {
+    if task_id.is_empty() || task_id.len() > 64 {
+        anyhow::bail!("Invalid task id: must be 1-64 chars");
+    }
+    let mut chars = task_id.chars();
+    let Some(first) = chars.next() else {
+        anyhow::bail!("Invalid task id: empty");
+    };
+    if !first.is_ascii_alphanumeric() {
+        anyhow::bail!("Invalid task id: must start with letter or digit");
+    }
+    for c in chars {
+        if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            anyhow::bail!("Invalid task id: only letters/digits/_/- allowed");
+        }
+    }
     Ok(())
 }
 
@@ -101,6 +318,11 @@ pub fn scaffold(root: impl AsRef<Path>, force: bool) -> Result<Paths> {
         fs::write(&p.control, "").with_context(|| format!("write {}", p.control.display()))?;
     }
 
+    // Never overwritten, even with `force`: it's edited by hand.
+    if !p.config.exists() {
+        write_json_pretty(&p.config, &serde_json::to_value(Config::default())?)?;
+    }
+
     if force || !p.board_json.exists() {
         let mut columns = Map::new();
         for status in STATUSES {
@@ -112,6 +334,7 @@ pub fn scaffold(root: impl AsRef<Path>, force: bool) -> Result<Paths> {
                 ("columns".to_string(), Value::Object(columns)),
                 ("cards".to_string(), Value::Object(Map::new())),
                 ("unread_directives".to_string(), Value::Object(Map::new())),
+                ("unread_directive_details".to_string(), Value::Object(Map::new())),
             ]
             .into_iter()
             .collect(),
@@ -142,32 +365,79 @@ pub fn scaffold(root: impl AsRef<Path>, force: bool) -> Result<Paths> {
     Ok(p)
 }
 
-fn read_jsonl_with_seq(path: &Path) -> Result<Vec<Map<String, Value>>> {
+/// How far into a JSONL file a read has got.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct JsonlCursor {
+    /// Offset just past the last complete line.
+    bytes: u64,
+    /// Lines read, blank and unreadable ones included.
+    lines: u64,
+    /// Records read; each not stamped with a `_seq` gets the next number as one.
+    seq: i64,
+    /// Where the last record read goes among both logs' records: its stamped
+    /// `_seq`, or for one without, the key of the record before it.
+    #[serde(default)]
+    key: i64,
+}
+
+/// Reads the complete lines of `path` from `at` on, handing `f` each line's
+/// number and either its record, with a `_seq` and its key in the order of
+/// both logs, or why it was skipped. Blank lines are passed over quietly. A
+/// line still being written (no newline yet) is left for the next read.
+fn read_jsonl_from(
+    path: &Path,
+    at: &mut JsonlCursor,
+    mut f: impl FnMut(u64, std::result::Result<(i64, Map<String, Value>), String>),
+) -> Result<()> {
     if !path.exists() {
-        return Ok(vec![]);
+        return Ok(());
     }
-    let file = fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
-    let reader = BufReader::new(file);
+    let mut file = fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
+    file.seek(SeekFrom::Start(at.bytes))
+        .with_context(|| format!("seek {}", path.display()))?;
+    let mut reader = BufReader::new(file);
 
-    let mut seq: i64 = 0;
-    let mut out = vec![];
-    for line in reader.lines() {
-        let line = line?;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let n = reader.read_until(b'\n', &mut buf)?;
+        if n == 0 || buf.last() != Some(&b'\n') {
+            break;
+        }
+        at.bytes += n as u64;
+        at.lines += 1;
+        let Ok(line) = std::str::from_utf8(&buf) else {
+            f(at.lines, Err("not valid UTF-8".to_string()));
+            continue;
+        };
         let line = line.trim();
         if line.is_empty() {
             continue;
         }
-        let Ok(Value::Object(mut obj)) = serde_json::from_str::<Value>(line) else {
-            continue;
+        let mut obj = match serde_json::from_str::<Value>(line) {
+            Ok(Value::Object(obj)) => obj,
+            Ok(_) => {
+                f(at.lines, Err("not a JSON object".to_string()));
+                continue;
+            }
+            Err(e) => {
+                f(at.lines, Err(format!("not valid JSON: {e}")));
+                continue;
+            }
         };
-        seq += 1;
-        obj.insert("_seq".to_string(), Value::Number(seq.into()));
-        out.push(obj);
+        at.seq += 1;
+        match obj.get("_seq").and_then(|v| v.as_i64()) {
+            Some(stamped) => at.key = stamped,
+            None => {
+                obj.insert("_seq".to_string(), Value::Number(at.seq.into()));
+            }
+        }
+        f(at.lines, Ok((at.key, obj)));
     }
-    Ok(out)
+    Ok(())
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Card {
     task_id: String,
     title: String,
@@ -176,34 +446,130 @@ struct Card {
     updated_at: String,
     updated_seq: i64,
     latest_snapshot_id: Option<String>,
+    assignee: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    due_at: Option<String>,
+    #[serde(default)]
+    notes: Vec<NoteOut>,
+    #[serde(default)]
+    closed_at: Option<String>,
     provisional: bool,
+    /// The latest ledger record that updated the card, if it was signed, so
+    /// folds can check it against whichever keys they trust.
+    #[serde(default)]
+    signed_update: Option<Map<String, Value>>,
+    /// Where the record that set `updated_seq` came from, and who wrote it.
+    #[serde(default)]
+    updated_by: Option<String>,
+    #[serde(default)]
+    updated_via: Option<String>,
+}
+
+// The log a record that updates a card is in, and its author: `meta.actor`
+// in the ledger, `author` in control.
+#[derive(Clone, Copy)]
+struct UpdateSource<'a> {
+    via: &'static str,
+    by: Option<&'a str>,
 }
 
-fn set_updated(card: &mut Card, ts: &str, seq: i64) {
+fn set_updated(card: &mut Card, ts: &str, seq: i64, source: UpdateSource) {
     if seq >= card.updated_seq {
         card.updated_seq = seq;
+        card.updated_via = Some(source.via.to_string());
+        card.updated_by = source.by.map(str::to_string);
         if !ts.is_empty() {
             card.updated_at = ts.to_string();
         }
     }
 }
 
-fn is_status(s: &str) -> bool {
-    STATUSES.iter().any(|x| *x == s)
+/// Tags are 1–64 characters with no whitespace; others are dropped.
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && tag.chars().count() <= MAX_TAG_LEN && !tag.chars().any(char::is_whitespace)
+}
+
+fn add_tag(card: &mut Card, tag: &str) -> bool {
+    if !is_valid_tag(tag) || card.tags.iter().any(|t| t == tag) {
+        return false;
+    }
+    card.tags.push(tag.to_string());
+    true
+}
+
+/// When a due date falls due: the instant given, or the end of the day (UTC)
+/// for a bare `YYYY-MM-DD`. `None` for anything that isn't RFC 3339.
+pub fn due_deadline(due: &str) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(due) {
+        return Some(at.with_timezone(&Utc));
+    }
+    let day = NaiveDate::parse_from_str(due, "%Y-%m-%d").ok()?;
+    Some(day.succ_opt()?.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+// Like `parse_assignee`, and dates that don't parse are ignored too.
+fn parse_due(v: Option<&Value>) -> Option<Option<String>> {
+    let due = v?.as_str()?.trim();
+    if due.is_empty() {
+        return Some(None);
+    }
+    due_deadline(due).map(|_| Some(due.to_string()))
+}
+
+fn is_closed(status: &str) -> bool {
+    matches!(status, "done" | "rejected")
+}
+
+// The status a close leaves: `done` unless the resolution says `rejected`.
+fn resolution_status(resolution: Option<&Value>) -> Option<&'static str> {
+    match resolution.map(|v| v.as_str()) {
+        None => Some("done"),
+        Some(Some("done")) => Some("done"),
+        Some(Some("rejected")) => Some("rejected"),
+        Some(_) => None,
+    }
 }
 
-fn is_priority(p: &str) -> bool {
-    PRIORITIES.iter().any(|x| *x == p)
+fn close_card(card: &mut Card, status: &str, ts: &str, seq: i64, source: UpdateSource) {
+    card.status = status.to_string();
+    if card.closed_at.is_none() {
+        card.closed_at = Some(ts.to_string());
+    }
+    set_updated(card, ts, seq, source);
 }
 
-fn priority_rank(p: &str) -> i64 {
-    match p {
-        "low" => 1,
-        "medium" => 2,
-        "high" => 3,
-        "urgent" => 4,
-        _ => 0,
+/// Each cycle in the "blocks" graph as the path around it, first task
+/// repeated at the end. Found by depth-first search without recursion, so
+/// long chains can't overflow the stack.
+fn dependency_cycles(edges: &BTreeMap<String, BTreeSet<String>>) -> Vec<Vec<String>> {
+    let none = BTreeSet::new();
+    let mut finished: HashSet<&str> = HashSet::new();
+    let mut cycles = Vec::new();
+    for start in edges.keys() {
+        if finished.contains(start.as_str()) {
+            continue;
+        }
+        // The tasks being explored, each with the edges it has left to follow.
+        let mut path = vec![(start.as_str(), edges[start].iter())];
+        while let Some((task, next)) = path.last_mut() {
+            let task = *task;
+            let Some(to) = next.next() else {
+                finished.insert(task);
+                path.pop();
+                continue;
+            };
+            if let Some(at) = path.iter().position(|(t, _)| *t == to.as_str()) {
+                let mut cycle: Vec<String> = path[at..].iter().map(|(t, _)| t.to_string()).collect();
+                cycle.push(to.clone());
+                cycles.push(cycle);
+            } else if !finished.contains(to.as_str()) {
+                path.push((to.as_str(), edges.get(to).unwrap_or(&none).iter()));
+            }
+        }
     }
+    cycles
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -214,301 +580,2265 @@ pub struct CardOut {
     pub priority: String,
     pub updated_at: String,
     pub updated_seq: i64,
+    /// Who wrote the record behind `updated_seq`, when it says.
+    #[serde(default)]
+    pub updated_by: Option<String>,
+    /// `"ledger"` or `"control"`: which log that record is in.
+    #[serde(default)]
+    pub updated_via: Option<String>,
     pub latest_snapshot_id: Option<String>,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    /// In the order they were added.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// As given: an RFC 3339 date or datetime.
+    #[serde(default)]
+    pub due_at: Option<String>,
+    /// Past due when the board was folded, and not done or rejected.
+    #[serde(default)]
+    pub overdue: bool,
+    /// Tasks this one blocks, and the ones blocking it.
+    #[serde(default)]
+    pub blocks: Vec<String>,
+    #[serde(default)]
+    pub blocked_by: Vec<String>,
+    /// Some task in `blocked_by` is neither done nor rejected.
+    #[serde(default)]
+    pub waiting_on_dependencies: bool,
+    /// The latest [`MAX_CARD_NOTES`] notes, oldest first.
+    #[serde(default)]
+    pub notes: Vec<NoteOut>,
+    /// When a `task_closed` record or `close_task` directive closed the task.
+    /// Until a `reopen_task`, nothing else changes its status.
+    #[serde(default)]
+    pub closed_at: Option<String>,
+    /// Placed by `reorder` directives. Within a column, cards with a negative
+    /// key come first and those with a positive key last, lowest first; the
+    /// rest keep the priority order in between.
+    #[serde(default)]
+    pub order_key: Option<f64>,
+    /// From `open_task`'s `payload.parent_task_id`. Subtasks nest one level.
+    #[serde(default)]
+    pub parent_task_id: Option<String>,
+    /// For parents: subtasks done or rejected, and subtasks in all.
+    #[serde(default)]
+    pub subtask_progress: Option<(usize, usize)>,
+    /// Done or rejected while some subtask is still open.
+    #[serde(default)]
+    pub has_open_subtasks: bool,
+    /// The latest ledger record about the task isn't signed by a trusted key;
+    /// only set when the fold was given [`FoldOptions::trusted_principals`].
+    #[serde(default)]
+    pub unverified: bool,
+    /// Open and not updated for at least the fold's `stale_after_days`.
+    #[serde(default)]
+    pub stale: bool,
+    /// Since `updated_at`, or for cards never updated, since the first
+    /// directive about them. `None` when neither has a usable time.
+    #[serde(default)]
+    pub days_since_update: Option<f64>,
+    /// Label of the workspace the card came from, on boards from
+    /// [`fold_many`]; empty otherwise.
+    #[serde(default)]
+    pub workspace: String,
     pub unread_directive_count: usize,
     pub provisional: bool,
 }
 
+/// A directive no `ack_directive` in the ledger names yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnreadDirective {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub ts: String,
+    pub author: Option<String>,
+    pub rationale: Option<String>,
+    pub payload: Value,
+}
+
+/// A `note` directive, or a ledger record of type `note`, about a task.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteOut {
+    pub author: String,
+    pub ts: String,
+    /// Position in `source`, which is `"ledger"` or `"control"`.
+    pub seq: i64,
+    pub source: String,
+    pub text: String,
+}
+
+// Ledger notes carry their text in `claim`; directives in `payload.text`.
+fn note_from(rec: &Map<String, Value>, source: &str) -> Option<NoteOut> {
+    if rec.get("type").and_then(|v| v.as_str()) != Some("note") {
+        return None;
+    }
+    let (text, author) = if source == "ledger" {
+        let actor = rec.get("meta").and_then(|m| m.get("actor")).and_then(|v| v.as_str());
+        (rec.get("claim"), actor.unwrap_or("agent"))
+    } else {
+        let author = rec.get("author").and_then(|v| v.as_str());
+        (rec.get("payload").and_then(|p| p.get("text")), author.unwrap_or("human"))
+    };
+    let text = text.and_then(|v| v.as_str()).map(str::trim).filter(|t| !t.is_empty())?;
+    Some(NoteOut {
+        author: author.to_string(),
+        ts: rec.get("ts").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+        seq: rec.get("_seq").and_then(|v| v.as_i64()).unwrap_or(0),
+        source: source.to_string(),
+        text: text.to_string(),
+    })
+}
+
+// Oldest first by timestamp; ledger notes go before directives at the same
+// time, and each source keeps its own order.
+fn sort_notes(notes: &mut [NoteOut]) {
+    notes.sort_by(|a, b| (&a.ts, a.source != "ledger", a.seq).cmp(&(&b.ts, b.source != "ledger", b.seq)));
+}
+
+fn keep_latest_notes(notes: &mut Vec<NoteOut>) {
+    if notes.len() > MAX_CARD_NOTES {
+        notes.drain(..notes.len() - MAX_CARD_NOTES);
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Board {
     pub generated_at: String,
-    pub columns: HashMap<String, Vec<CardOut>>,
+    /// A column per status, in the workspace's configured order.
+    pub columns: IndexMap<String, Vec<CardOut>>,
     pub cards: HashMap<String, CardOut>,
+    /// Task id -> the ids of its unread directives. Kept for one more release
+    /// for readers that only count them; `unread_directive_details` has the rest.
     pub unread_directives: HashMap<String, Vec<String>>,
+    /// Task id -> its unread directives in control order, as written.
+    #[serde(default)]
+    pub unread_directive_details: HashMap<String, Vec<UnreadDirective>>,
     pub last_ack_directive_id: Option<String>,
     pub last_ack_directive_ts: Option<String>,
     pub last_ack_control_seq: i64,
+    /// Problems found while folding that don't stop the board from being
+    /// built, such as dependency cycles.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Columns holding more cards than `wip_limits` in config.json allows.
+    #[serde(default)]
+    pub wip_violations: Vec<WipViolation>,
+    /// Boards from [`fold_many`] only: each member's label and root.
+    #[serde(default)]
+    pub workspaces: BTreeMap<String, PathBuf>,
 }
 
-pub fn fold(root: impl AsRef<Path>) -> Result<Board> {
-    let p = paths_for(root);
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WipViolation {
+    pub status: String,
+    pub limit: usize,
+    pub count: usize,
+}
 
-    let ledger = read_jsonl_with_seq(&p.ledger)?;
-    let control = read_jsonl_with_seq(&p.control)?;
+/// `.isnad/config.json`. Every field is optional; a missing file is the default.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    /// The status columns, in board order. [`STATUSES`] by default.
+    #[serde(default = "default_statuses")]
+    pub statuses: Vec<String>,
+    /// The priorities cards can have. [`PRIORITIES`], ranked 1 to 4, by default.
+    #[serde(default = "default_priorities")]
+    pub priorities: Vec<PriorityLevel>,
+    /// Most cards each status column should hold, e.g. `{"doing": 3}`.
+    #[serde(default)]
+    pub wip_limits: BTreeMap<String, usize>,
+    /// Days an open card can go without an update before it's stale; 0 never.
+    #[serde(default = "default_stale_after_days")]
+    pub stale_after_days: u32,
+}
 
-    let mut cards: HashMap<String, Card> = HashMap::new();
-    let mut unread_directives: HashMap<String, Vec<String>> = HashMap::new();
-    let mut acked_directives: HashSet<String> = HashSet::new();
-    let mut last_ack_directive_id: Option<String> = None;
-    let mut last_ack_directive_ts: Option<String> = None;
-    let mut last_ack_control_seq: i64 = 0;
+/// A priority and its rank; cards with higher ranks sort first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriorityLevel {
+    pub name: String,
+    pub rank: i64,
+}
 
-    for rec in &ledger {
-        let rec_type = rec.get("type").and_then(|v| v.as_str()).unwrap_or("");
-        let ts = rec.get("ts").and_then(|v| v.as_str()).unwrap_or("");
-        let seq = rec.get("_seq").and_then(|v| v.as_i64()).unwrap_or(0);
-        let task_id = rec.get("task_id").and_then(|v| v.as_str());
+fn default_statuses() -> Vec<String> {
+    STATUSES.iter().map(|s| s.to_string()).collect()
+}
 
-        if rec_type == "task_opened" {
-            let Some(task_id) = task_id.filter(|t| !t.is_empty()) else {
-                continue;
-            };
-            let mut title = rec.get("claim").and_then(|v| v.as_str()).unwrap_or("Untitled task");
-            if let Some(Value::Object(meta)) = rec.get("meta") {
-                if let Some(t) = meta.get("title").and_then(|v| v.as_str()) {
-                    if !t.is_empty() {
-                        title = t;
-                    }
-                }
-            }
-            let mut card = Card {
-                task_id: task_id.to_string(),
-                title: title.to_string(),
-                status: "backlog".to_string(),
-                priority: "medium".to_string(),
-                updated_at: "".to_string(),
-                updated_seq: 0,
-                latest_snapshot_id: None,
-                provisional: false,
-            };
-            set_updated(&mut card, ts, seq);
-            cards.insert(task_id.to_string(), card);
+fn default_stale_after_days() -> u32 {
+    3
+}
+
+fn default_priorities() -> Vec<PriorityLevel> {
+    (1..).zip(PRIORITIES).map(|(rank, name)| PriorityLevel { name: name.to_string(), rank }).collect()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            statuses: default_statuses(),
+            priorities: default_priorities(),
+            wip_limits: BTreeMap::new(),
+            stale_after_days: default_stale_after_days(),
         }
+    }
+}
 
-        if rec_type == "task_updated" {
-            let Some(task_id) = task_id.filter(|t| !t.is_empty()) else {
-                continue;
-            };
-            let Some(card) = cards.get_mut(task_id) else {
-                continue;
-            };
-            if let Some(Value::Object(meta)) = rec.get("meta") {
-                if let Some(t) = meta.get("title").and_then(|v| v.as_str()) {
-                    if !t.is_empty() {
-                        card.title = t.to_string();
-                    }
-                }
+impl Config {
+    pub fn is_status(&self, s: &str) -> bool {
+        self.statuses.iter().any(|status| status == s)
+    }
+
+    pub fn is_priority(&self, p: &str) -> bool {
+        self.priorities.iter().any(|level| level.name == p)
+    }
+
+    /// 0 for priorities that aren't configured.
+    pub fn priority_rank(&self, p: &str) -> i64 {
+        self.priorities.iter().find(|level| level.name == p).map_or(0, |level| level.rank)
+    }
+
+    // Cards start in `backlog` at `medium`, and closing, pausing and
+    // reopening move them to fixed statuses, so those have to exist.
+    fn check(&self) -> Result<()> {
+        for (i, status) in self.statuses.iter().enumerate() {
+            if self.statuses[..i].contains(status) {
+                anyhow::bail!("statuses: {status:?} is listed twice");
             }
-            set_updated(card, ts, seq);
         }
-
-        if rec_type == "snapshot" {
-            let Some(task_id) = task_id.filter(|t| !t.is_empty()) else {
-                continue;
-            };
-            let Some(card) = cards.get_mut(task_id) else {
-                continue;
-            };
-            card.latest_snapshot_id = rec.get("id").and_then(|v| v.as_str()).map(|s| s.to_string());
-            set_updated(card, ts, seq);
-        }
-
-        if rec_type == "ack_directive" {
-            if let Some(Value::Object(meta)) = rec.get("meta") {
-                if let Some(did) = meta.get("directive_id").and_then(|v| v.as_str()) {
-                    if !did.is_empty() {
-                        acked_directives.insert(did.to_string());
-                        last_ack_directive_id = Some(did.to_string());
-                        if !ts.is_empty() {
-                            last_ack_directive_ts = Some(ts.to_string());
-                        }
-                    }
-                }
+        if let Some(missing) = REQUIRED_STATUSES.iter().find(|s| !self.is_status(s)) {
+            anyhow::bail!("statuses: must include {missing:?}");
+        }
+        for (i, level) in self.priorities.iter().enumerate() {
+            if self.priorities[..i].iter().any(|l| l.name == level.name) {
+                anyhow::bail!("priorities: {:?} is listed twice", level.name);
             }
         }
+        if !self.is_priority("medium") {
+            anyhow::bail!("priorities: must include \"medium\"");
+        }
+        Ok(())
     }
+}
 
-    for d in &control {
-        let d_id = d.get("id").and_then(|v| v.as_str());
-        let d_type = d.get("type").and_then(|v| v.as_str()).unwrap_or("");
-        let ts = d.get("ts").and_then(|v| v.as_str()).unwrap_or("");
-        let seq = d.get("_seq").and_then(|v| v.as_i64()).unwrap_or(0);
-        let task_id = d.get("task_id").and_then(|v| v.as_str());
-        let payload = d.get("payload").and_then(|v| v.as_object());
+/// The workspace's config.json, checked so that every card has a column.
+pub fn load_config(root: impl AsRef<Path>) -> Result<Config> {
+    let path = paths_for(root).config;
+    let raw = match fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+    };
+    let config: Config = serde_json::from_str(&raw).with_context(|| format!("parse {}", path.display()))?;
+    config.check().with_context(|| format!("check {}", path.display()))?;
+    Ok(config)
+}
 
-        if d_type == "open_task" {
-            let Some(task_id) = task_id.filter(|t| !t.is_empty()) else {
-                continue;
-            };
-            let title = payload.and_then(|p| p.get("title")).and_then(|v| v.as_str());
-            let status = payload.and_then(|p| p.get("status")).and_then(|v| v.as_str());
-            let priority = payload.and_then(|p| p.get("priority")).and_then(|v| v.as_str());
+/// Bumped whenever what [`FoldState`] or the cache holds changes meaning, so
+/// caches written by other versions are folded again from scratch.
+const FOLD_CACHE_VERSION: u32 = 9;
 
-            let card = cards.entry(task_id.to_string()).or_insert_with(|| Card {
-                task_id: task_id.to_string(),
-                title: title.unwrap_or("Untitled task").to_string(),
-                status: "backlog".to_string(),
-                priority: "medium".to_string(),
-                updated_at: "".to_string(),
-                updated_seq: 0,
-                latest_snapshot_id: None,
-                provisional: true,
+/// Everything a board is built from, carried forward record by record. Both
+/// logs are applied in one order, by `_seq` (see [`append_record`]), so this
+/// plus the offsets reached is all a fold needs to pick up where it left off.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FoldState {
+    cards: HashMap<String, Card>,
+    acked_directives: HashSet<String>,
+    last_ack_directive_id: Option<String>,
+    last_ack_directive_ts: Option<String>,
+    /// Task id -> its directives no ack names yet, with their `_seq`s, in the
+    /// order applied.
+    unread: HashMap<String, Vec<(i64, UnreadDirective)>>,
+    last_ack_control_seq: i64,
+    /// Paused cards and the status `resume` gives back: `None` for cards that
+    /// had none of their own, having first appeared with the pause.
+    paused: HashMap<String, Option<String>>,
+    /// Blocking task -> the tasks it blocks.
+    blocks: BTreeMap<String, BTreeSet<String>>,
+    order: HashMap<String, f64>,
+    /// Subtask -> its parent, and the line of the directive that said so.
+    parents: BTreeMap<String, (String, Option<u64>)>,
+    /// Time of the first directive about each task, for cards never updated.
+    first_seen: HashMap<String, String>,
+    /// Lines that couldn't be read, reported ahead of `ignored`.
+    skipped: Vec<FoldWarning>,
+    /// Directives, or parts of them, that were ignored.
+    ignored: Vec<FoldWarning>,
+}
+
+/// What [`fold`] keeps in `state/fold_cache.json` between runs: the state
+/// reached, the config it was reached under, and how much of each file that
+/// covers. The cursors' byte offsets are mirrored in `cursors.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FoldCache {
+    version: u32,
+    config: Config,
+    ledger_at: JsonlCursor,
+    control_at: JsonlCursor,
+    /// Key and log (0 for the ledger, 1 for control) of the furthest record
+    /// applied; anything read later that goes before it means a full fold.
+    applied: Option<(i64, u8)>,
+    state: FoldState,
+}
+
+fn fold_ledger_record(state: &mut FoldState, rec: &Map<String, Value>) {
+    apply_ledger_record(state, rec);
+    let rec_type = rec.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    if matches!(rec_type, "task_opened" | "task_updated" | "task_closed" | "snapshot" | "note") {
+        let card = rec.get("task_id").and_then(|v| v.as_str()).and_then(|t| state.cards.get_mut(t));
+        if let Some(card) = card {
+            let signed = rec.get("meta").and_then(|m| m.get("sig")).is_some();
+            card.signed_update = signed.then(|| {
+                let mut rec = rec.clone();
+                rec.remove("_seq");
+                rec
             });
+        }
+    }
+}
 
-            if let Some(t) = title {
-                if !t.is_empty() && (card.title == "(unopened task)" || card.title == "Untitled task") {
-                    card.title = t.to_string();
-                }
-            }
+fn apply_ledger_record(state: &mut FoldState, rec: &Map<String, Value>) {
+    let rec_type = rec.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let ts = rec.get("ts").and_then(|v| v.as_str()).unwrap_or("");
+    let seq = rec.get("_seq").and_then(|v| v.as_i64()).unwrap_or(0);
+    let task_id = rec.get("task_id").and_then(|v| v.as_str());
+    let actor = rec.get("meta").and_then(|m| m.get("actor")).and_then(|v| v.as_str());
+    let source = UpdateSource { via: "ledger", by: actor };
 
-            if let Some(s) = status {
-                if is_status(s) {
-                    card.status = s.to_string();
+    if rec_type == "task_opened" {
+        let Some(task_id) = task_id.filter(|t| !t.is_empty()) else {
+            return;
+        };
+        let mut title = rec.get("claim").and_then(|v| v.as_str());
+        if let Some(Value::Object(meta)) = rec.get("meta") {
+            if let Some(t) = meta.get("title").and_then(|v| v.as_str()) {
+                if !t.is_empty() {
+                    title = Some(t);
                 }
             }
-            if let Some(pv) = priority {
-                if is_priority(pv) {
-                    card.priority = pv.to_string();
-                }
+        }
+        // A card directives opened first is taken over, keeping what they set.
+        if let Some(card) = state.cards.get_mut(task_id).filter(|c| c.provisional) {
+            if let Some(title) = title {
+                card.title = title.to_string();
             }
-            set_updated(card, ts, seq);
+            card.provisional = false;
+            set_assignee_from_meta(card, rec);
+            set_updated(card, ts, seq, source);
+            return;
+        }
+        let title = title.unwrap_or("Untitled task");
+        let mut card = Card {
+            task_id: task_id.to_string(),
+            title: title.to_string(),
+            status: "backlog".to_string(),
+            priority: "medium".to_string(),
+            updated_at: "".to_string(),
+            updated_seq: 0,
+            latest_snapshot_id: None,
+            assignee: None,
+            tags: Vec::new(),
+            due_at: None,
+            notes: Vec::new(),
+            closed_at: None,
+            provisional: false,
+            signed_update: None,
+            updated_by: None,
+            updated_via: None,
+        };
+        set_assignee_from_meta(&mut card, rec);
+        set_updated(&mut card, ts, seq, source);
+        // Opening a closed task again doesn't reopen it; that takes `reopen_task`.
+        if let Some(old) = state.cards.get(task_id).filter(|c| c.closed_at.is_some()) {
+            card.status = old.status.clone();
+            card.closed_at = old.closed_at.clone();
         }
+        state.cards.insert(task_id.to_string(), card);
+    }
 
-        if d_type != "open_task" {
-            if let Some(task_id) = task_id.filter(|t| !t.is_empty()) {
-                cards.entry(task_id.to_string()).or_insert_with(|| Card {
-                    task_id: task_id.to_string(),
-                    title: "(unopened task)".to_string(),
-                    status: "backlog".to_string(),
-                    priority: "medium".to_string(),
-                    updated_at: "".to_string(),
-                    updated_seq: 0,
-                    latest_snapshot_id: None,
-                    provisional: true,
-                });
-            }
+    if rec_type == "task_closed" {
+        let Some(card) = task_id.and_then(|t| state.cards.get_mut(t)) else {
+            return;
+        };
+        let resolution = rec.get("meta").and_then(|m| m.get("resolution"));
+        if let Some(status) = resolution_status(resolution) {
+            close_card(card, status, ts, seq, source);
         }
+    }
 
-        if let Some(task_id) = task_id.filter(|t| !t.is_empty()) {
-            if let Some(card) = cards.get_mut(task_id) {
-                if d_type == "set_status" {
-                    if let Some(s) = payload.and_then(|p| p.get("status")).and_then(|v| v.as_str()) {
-                        if is_status(s) {
-                            card.status = s.to_string();
-                            set_updated(card, ts, seq);
-                        }
-                    }
+    if rec_type == "task_updated" {
+        let Some(task_id) = task_id.filter(|t| !t.is_empty()) else {
+            return;
+        };
+        let Some(card) = state.cards.get_mut(task_id) else {
+            return;
+        };
+        if let Some(Value::Object(meta)) = rec.get("meta") {
+            if let Some(t) = meta.get("title").and_then(|v| v.as_str()) {
+                if !t.is_empty() {
+                    card.title = t.to_string();
                 }
+            }
+        }
+        set_assignee_from_meta(card, rec);
+        set_updated(card, ts, seq, source);
+    }
 
-                if d_type == "set_priority" {
-                    if let Some(pr) = payload
-                        .and_then(|p| p.get("priority"))
-                        .and_then(|v| v.as_str())
-                    {
-                        if is_priority(pr) {
-                            card.priority = pr.to_string();
-                            set_updated(card, ts, seq);
-                        }
-                    }
-                }
+    if rec_type == "snapshot" {
+        let Some(task_id) = task_id.filter(|t| !t.is_empty()) else {
+            return;
+        };
+        let Some(card) = state.cards.get_mut(task_id) else {
+            return;
+        };
+        card.latest_snapshot_id = rec.get("id").and_then(|v| v.as_str()).map(|s| s.to_string());
+        set_updated(card, ts, seq, source);
+    }
 
-                if d_type == "pause" {
-                    card.status = "blocked".to_string();
-                    set_updated(card, ts, seq);
-                }
+    if rec_type == "note" {
+        let Some(card) = task_id.and_then(|t| state.cards.get_mut(t)) else {
+            return;
+        };
+        if let Some(note) = note_from(rec, "ledger") {
+            card.notes.push(note);
+            keep_latest_notes(&mut card.notes);
+            set_updated(card, ts, seq, source);
+        }
+    }
 
-                if let Some(d_id) = d_id.filter(|id| !id.is_empty()) {
-                    if !acked_directives.contains(d_id) {
-                        unread_directives
-                            .entry(task_id.to_string())
-                            .or_default()
-                            .push(d_id.to_string());
-                    } else {
-                        last_ack_control_seq = last_ack_control_seq.max(seq);
+    if rec_type == "ack_directive" {
+        if let Some(Value::Object(meta)) = rec.get("meta") {
+            if let Some(did) = meta.get("directive_id").and_then(|v| v.as_str()) {
+                if !did.is_empty() {
+                    // Directives applied before their ack are read now.
+                    for pending in state.unread.values_mut() {
+                        pending.retain(|(seq, d)| {
+                            if d.id == did {
+                                state.last_ack_control_seq = state.last_ack_control_seq.max(*seq);
+                            }
+                            d.id != did
+                        });
+                    }
+                    state.unread.retain(|_, pending| !pending.is_empty());
+                    state.acked_directives.insert(did.to_string());
+                    state.last_ack_directive_id = Some(did.to_string());
+                    if !ts.is_empty() {
+                        state.last_ack_directive_ts = Some(ts.to_string());
                     }
                 }
             }
         }
     }
+}
 
-    let mut columns: HashMap<String, Vec<CardOut>> =
-        STATUSES.iter().map(|s| (s.to_string(), vec![])).collect();
-    let mut cards_out: HashMap<String, CardOut> = HashMap::new();
+// An empty name clears the assignee; anything that isn't a string is ignored.
+fn parse_assignee(v: Option<&Value>) -> Option<Option<String>> {
+    let name = v?.as_str()?.trim();
+    Some((!name.is_empty()).then(|| name.to_string()))
+}
 
-    for (task_id, card) in cards {
-        let unread = unread_directives.get(&task_id).map(|v| v.len()).unwrap_or(0);
-        let out = CardOut {
-            task_id: card.task_id,
-            title: card.title,
-            status: card.status.clone(),
-            priority: card.priority.clone(),
-            updated_at: card.updated_at,
-            updated_seq: card.updated_seq,
-            latest_snapshot_id: card.latest_snapshot_id,
-            unread_directive_count: unread,
-            provisional: card.provisional,
-        };
-        cards_out.insert(task_id.clone(), out.clone());
-        if let Some(col) = columns.get_mut(&out.status) {
-            col.push(out);
-        }
+// Ledger records claim a card through `meta.assignee`.
+fn set_assignee_from_meta(card: &mut Card, rec: &Map<String, Value>) {
+    let meta = rec.get("meta").and_then(|v| v.as_object());
+    if let Some(assignee) = parse_assignee(meta.and_then(|m| m.get("assignee"))) {
+        card.assignee = assignee;
     }
+}
 
-    for status in STATUSES {
-        if let Some(col) = columns.get_mut(status) {
-            col.sort_by(|a, b| {
-                let ra = priority_rank(&a.priority);
-                let rb = priority_rank(&b.priority);
-                (rb, b.updated_seq).cmp(&(ra, a.updated_seq))
-            });
-        }
+fn file_len(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+// Whether the byte before `offset` is the newline that ended the last line
+// read. Catches a file replaced by a longer one since the last fold.
+fn ends_line_at(path: &Path, offset: u64) -> bool {
+    if offset == 0 {
+        return true;
     }
+    let Ok(mut file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut byte = [0u8; 1];
+    file.seek(SeekFrom::Start(offset - 1)).is_ok()
+        && std::io::Read::read_exact(&mut file, &mut byte).is_ok()
+        && byte[0] == b'\n'
+}
 
-    Ok(Board {
-        generated_at: utc_now(),
-        columns,
-        cards: cards_out,
-        unread_directives,
-        last_ack_directive_id,
-        last_ack_directive_ts,
-        last_ack_control_seq,
-    })
+fn cursor_bytes(cursors: &Value, field: &str) -> u64 {
+    cursors.get(field).and_then(|v| v.as_u64()).unwrap_or(0)
 }
 
-pub fn render_markdown(board: &Board) -> String {
-    let mut out = String::new();
-    out.push_str("# Board (derived)\n\n");
-    out.push_str(&format!("Generated: {}\n\n", board.generated_at));
+// The cache, if it was written by this version, agrees with `cursors.json`,
+// and still describes a prefix of both files. Anything else means a full fold.
+fn load_fold_cache(p: &Paths) -> Option<FoldCache> {
+    let cache: FoldCache = serde_json::from_slice(&fs::read(&p.fold_cache).ok()?).ok()?;
+    let cursors: Value = serde_json::from_slice(&fs::read(&p.cursors).ok()?).ok()?;
+    let consistent = cache.version == FOLD_CACHE_VERSION
+        && cursor_bytes(&cursors, "folded_ledger_bytes") == cache.ledger_at.bytes
+        && cursor_bytes(&cursors, "folded_control_bytes") == cache.control_at.bytes
+        && file_len(&p.ledger) >= cache.ledger_at.bytes
+        && file_len(&p.control) >= cache.control_at.bytes
+        && ends_line_at(&p.ledger, cache.ledger_at.bytes)
+        && ends_line_at(&p.control, cache.control_at.bytes);
+    consistent.then_some(cache)
+}
+
+// The cache goes first, so a crash between the two writes leaves offsets
+// that disagree and the next fold starts over; so does a concurrent fold
+// saving between them.
+fn save_fold_cache(p: &Paths, cache: &FoldCache) -> Result<()> {
+    write_atomic(&p.fold_cache, &serde_json::to_vec(cache)?)?;
+
+    let mut cursors = fs::read(&p.cursors)
+        .ok()
+        .and_then(|b| serde_json::from_slice::<Value>(&b).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    cursors["folded_ledger_bytes"] = cache.ledger_at.bytes.into();
+    cursors["folded_control_bytes"] = cache.control_at.bytes.into();
+    write_atomic(&p.cursors, format!("{}\n", serde_json::to_string_pretty(&cursors)?).as_bytes())
+}
 
-    for status in STATUSES {
-        let mut chars = status.chars();
-        let heading = match chars.next() {
-            Some(first) => format!("{}{}", first.to_ascii_uppercase(), chars.as_str()),
-            None => status.to_string(),
+// Hands `f` both logs' records in the order they're applied: by key, the
+// ledger's first where keys are equal, and otherwise in each log's own order.
+fn merge_logs<T>(ledger: Vec<(i64, T)>, control: Vec<(i64, T)>, mut f: impl FnMut(&'static str, i64, T)) {
+    let mut ledger = ledger.into_iter().peekable();
+    let mut control = control.into_iter().peekable();
+    loop {
+        let from_ledger = match (ledger.peek(), control.peek()) {
+            (Some(l), Some(c)) => l.0 <= c.0,
+            (l, _) => l.is_some(),
         };
-        out.push_str(&format!("## {heading}\n"));
-        if let Some(col) = board.columns.get(status) {
-            for card in col {
-                let provisional = if card.provisional { " (provisional)" } else { "" };
-                let suffix = if card.unread_directive_count > 0 {
-                    format!(" (unread:{})", card.unread_directive_count)
-                } else {
-                    "".to_string()
-                };
-                out.push_str(&format!(
-                    "- [{}] {}{}  ({}){}\n",
-                    card.task_id, card.title, provisional, card.priority, suffix
-                ));
-            }
-        }
-        out.push('\n');
+        let (source, next) = if from_ledger { ("ledger", ledger.next()) } else { ("control", control.next()) };
+        let Some((key, rec)) = next else {
+            break;
+        };
+        f(source, key, rec);
     }
+}
 
-    out
+// Records read from one log: each one's key, line number and body.
+type LogRecords = Vec<(i64, (u64, Map<String, Value>))>;
+
+// Reads what was appended to both logs since `cache`'s cursors and applies
+// it. False when some of it goes before a record already applied, which
+// leaves `cache` to be thrown away for a fold from the start.
+fn fold_appended(p: &Paths, cache: &mut FoldCache, ledger_file: &str, control_file: &str) -> Result<bool> {
+    let FoldCache { config, ledger_at, control_at, applied, state, .. } = cache;
+    let mut read = |path: &Path, at: &mut JsonlCursor, file: &str| -> Result<LogRecords> {
+        let mut records = Vec::new();
+        read_jsonl_from(path, at, |line, rec| match rec {
+            Ok((key, rec)) => records.push((key, (line, rec))),
+            Err(reason) => state.skipped.push(FoldWarning::at(file, Some(line), reason)),
+        })?;
+        Ok(records)
+    };
+    let ledger = read(&p.ledger, ledger_at, ledger_file)?;
+    let control = read(&p.control, control_at, control_file)?;
+
+    let heads = [ledger.first().map(|r| (r.0, 0)), control.first().map(|r| (r.0, 1))];
+    if heads.into_iter().flatten().any(|head| Some(head) < *applied) {
+        return Ok(false);
+    }
+    merge_logs(ledger, control, |source, key, (line, rec)| {
+        if source == "ledger" {
+            *applied = (*applied).max(Some((key, 0)));
+            fold_ledger_record(state, &rec);
+        } else {
+            *applied = (*applied).max(Some((key, 1)));
+            apply_directive(state, &rec, Some(line), control_file, config);
+        }
+    });
+    Ok(true)
 }
 
-pub fn write_state(root: impl AsRef<Path>, board: &Board) -> Result<(PathBuf, PathBuf)> {
-    let p = paths_for(root);
-    ensure_dir(&p.state_dir)?;
+/// Folds the ledger and control log into a board, applying the records of
+/// both in `_seq` order. Only what was appended since the last fold is read:
+/// the folded state and the offsets reached are cached under `.isnad/state`,
+/// and the work starts over if either file shrank or was replaced, or if
+/// something appended goes before what was already folded. So folding
+/// writes `fold_cache.json` and `cursors.json` even though it only reads the
+/// logs; [`FoldOptions::no_cache`] folds without them. Workspaces without a
+/// state dir are folded in full every time and nothing is written.
+pub fn fold(root: impl AsRef<Path>) -> Result<Board> {
+    fold_with(root, &FoldOptions::default())
+}
 
-    let json = serde_json::to_value(board)?;
-    write_json_pretty(&p.board_json, &json)?;
-    fs::write(&p.board_md, render_markdown(board)).with_context(|| format!("write {}", p.board_md.display()))?;
-    Ok((p.board_json, p.board_md))
+/// Narrows what [`fold_with`] returns. The cache always covers the whole board.
+#[derive(Debug, Clone, Default)]
+pub struct FoldOptions {
+    /// Only cards carrying this tag, and their unread directives.
+    pub tag: Option<String>,
+    /// Principal id -> SPKI DER of the keys whose signed ledger records are
+    /// trusted. When set, a card whose latest ledger update isn't signed by one
+    /// of them is marked `unverified`.
+    pub trusted_principals: Option<HashMap<String, Vec<u8>>>,
+    /// With `trusted_principals`, unsigned records count as unverified too
+    /// instead of being accepted as legacy records.
+    pub require_signatures: bool,
+    /// Overrides `stale_after_days` in config.json; 0 marks nothing stale.
+    pub stale_after_days: Option<u32>,
+    /// Fold in full without reading or writing the cache, leaving `.isnad`
+    /// untouched. For reading workspaces this process doesn't own.
+    pub no_cache: bool,
 }
 
-pub fn read_jsonl_values(path: &Path) -> Result<Vec<Value>> {
-    if !path.exists() {
+/// Signs a ledger record before it's appended: sets `meta.principal` to the
+/// key's principal id, then `meta.sig` to a base64 Ed25519 signature over the
+/// JCS bytes of the record without `meta.sig`.
+pub fn sign_record(record: &Value, signing_key: &SigningKey) -> Result<Value> {
+    let spki = signing_key
+        .verifying_key()
+        .to_public_key_der()
+        .map_err(|e| anyhow!("encode public key: {e}"))?;
+    let mut record = record.clone();
+    let obj = record.as_object_mut().ok_or_else(|| anyhow!("ledger record must be a JSON object"))?;
+    let meta = obj.entry("meta").or_insert_with(|| Value::Object(Map::new()));
+    let meta = meta.as_object_mut().ok_or_else(|| anyhow!("ledger record meta must be a JSON object"))?;
+    meta.remove("sig");
+    meta.insert("principal".to_string(), principal_id_from_spki_der(spki.as_bytes()).into());
+
+    let sig = signing_key.sign(&jcs_bytes(&record)?);
+    record["meta"]["sig"] = base64::engine::general_purpose::STANDARD.encode(sig.to_bytes()).into();
+    Ok(record)
+}
+
+/// Whether `record` carries a valid `meta.sig` by the key `spki_der`, whose
+/// principal must also be the record's `meta.principal`. Unsigned records are
+/// `Ok(false)`; a key that isn't Ed25519 is an error. `_seq` is stamped after
+/// signing, on append, so it isn't covered.
+pub fn verify_record(record: &Value, spki_der: &[u8]) -> Result<bool> {
+    let key = ed25519_public_key_from_spki_der(spki_der)?;
+    let Some(meta) = record.get("meta").and_then(|m| m.as_object()) else {
+        return Ok(false);
+    };
+    let text = |k: &str| meta.get(k).and_then(|v| v.as_str());
+    let (Some(sig), Some(principal)) = (text("sig"), text("principal")) else {
+        return Ok(false);
+    };
+    if principal != principal_id_from_spki_der(spki_der) {
+        return Ok(false);
+    }
+    let sig = base64::engine::general_purpose::STANDARD.decode(sig).ok();
+    let Some(sig) = sig.and_then(|b| Signature::from_slice(&b).ok()) else {
+        return Ok(false);
+    };
+
+    let mut unsigned = record.clone();
+    if let Some(obj) = unsigned.as_object_mut() {
+        obj.remove("_seq");
+    }
+    if let Some(meta) = unsigned.get_mut("meta").and_then(|m| m.as_object_mut()) {
+        meta.remove("sig");
+    }
+    Ok(key.verify_strict(&jcs_bytes(&unsigned)?, &sig).is_ok())
+}
+
+// Whether a card's latest ledger update passes `options`' signature policy.
+fn update_verified(card: &Card, trusted: &HashMap<String, Vec<u8>>, require_signatures: bool) -> bool {
+    let Some(rec) = &card.signed_update else {
+        return !require_signatures;
+    };
+    let principal = rec.get("meta").and_then(|m| m.get("principal")).and_then(|v| v.as_str());
+    let Some(spki_der) = principal.and_then(|p| trusted.get(p)) else {
+        return false;
+    };
+    verify_record(&Value::Object(rec.clone()), spki_der).unwrap_or(false)
+}
+
+/// [`fold`], then filtered by `options`.
+pub fn fold_with(root: impl AsRef<Path>, options: &FoldOptions) -> Result<Board> {
+    Ok(fold_report(root, options)?.board)
+}
+
+/// Something [`fold_report`] passed over or couldn't make sense of. `file`
+/// (relative to the root) and `line` (1-based) point at the record, if any.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FoldWarning {
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub reason: String,
+}
+
+impl FoldWarning {
+    fn at(file: &str, line: Option<u64>, reason: String) -> Self {
+        Self { file: Some(file.to_string()), line, reason }
+    }
+}
+
+impl std::fmt::Display for FoldWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{file}:{line}: {}", self.reason),
+            (Some(file), None) => write!(f, "{file}: {}", self.reason),
+            _ => f.write_str(&self.reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FoldReport {
+    /// Its `warnings` are those below, as text.
+    pub board: Board,
+    pub warnings: Vec<FoldWarning>,
+}
+
+impl FoldReport {
+    fn push_warning(&mut self, warning: FoldWarning) {
+        self.board.warnings.push(warning.to_string());
+        self.warnings.push(warning);
+    }
+}
+
+/// [`fold_with`], along with everything skipped or ignored on the way: lines
+/// that aren't JSON objects, directives of unknown types, directives for tasks
+/// that were never opened, and invalid statuses and priorities. Warnings cover
+/// the whole board whatever `options` filters out.
+pub fn fold_report(root: impl AsRef<Path>, options: &FoldOptions) -> Result<FoldReport> {
+    let p = paths_for(root);
+    let relative = |path: &Path| path.strip_prefix(&p.root).unwrap_or(path).display().to_string();
+    let (ledger_file, control_file) = (relative(&p.ledger), relative(&p.control));
+    let config_file = relative(&p.config);
+    // Folding goes on with the built-in config rather than losing the board.
+    let (mut config, config_error) = match load_config(&p.root) {
+        Ok(config) => (config, None),
+        Err(e) => (Config::default(), Some(format!("not a valid config: {}", e.root_cause()))),
+    };
+
+    let cacheable = !options.no_cache && p.state_dir.is_dir();
+    let fresh = || FoldCache { version: FOLD_CACHE_VERSION, config: config.clone(), ..FoldCache::default() };
+    let mut cache = cacheable
+        .then(|| load_fold_cache(&p))
+        .flatten()
+        .filter(|cache| cache.config == config)
+        .unwrap_or_else(fresh);
+    let from = (cache.ledger_at, cache.control_at);
+    if !fold_appended(&p, &mut cache, &ledger_file, &control_file)? {
+        cache = fresh();
+        fold_appended(&p, &mut cache, &ledger_file, &control_file)?;
+    }
+
+    if cacheable && (cache.ledger_at, cache.control_at) != from {
+        // The board is still right without a cache, just slower next time.
+        let _ = save_fold_cache(&p, &cache);
+    }
+    // Only cards the ledger has opened have ledger updates to check.
+    let unverified: HashSet<String> = match &options.trusted_principals {
+        Some(trusted) => cache
+            .state
+            .cards
+            .iter()
+            .filter(|(_, card)| !card.provisional && !update_verified(card, trusted, options.require_signatures))
+            .map(|(task_id, _)| task_id.clone())
+            .collect(),
+        None => HashSet::new(),
+    };
+    if let Some(days) = options.stale_after_days {
+        config.stale_after_days = days;
+    }
+    let mut report = board_from(&cache.state, &control_file, &unverified, &config);
+    if let Some(reason) = config_error {
+        report.push_warning(FoldWarning::at(&config_file, None, reason));
+    }
+    for (status, &limit) in &config.wip_limits {
+        if !config.is_status(status) {
+            report.push_warning(FoldWarning::at(&config_file, None, format!("wip_limits: unknown status {status:?}")));
+            continue;
+        }
+        let count = report.board.columns.get(status).map_or(0, Vec::len);
+        if count > limit {
+            report.board.wip_violations.push(WipViolation { status: status.clone(), limit, count });
+        }
+    }
+    let columns = &report.board.columns;
+    report.board.wip_violations.sort_by_key(|v| columns.get_index_of(&v.status));
+    if let Some(tag) = &options.tag {
+        report.board.retain_cards(|card| card.tags.contains(tag));
+    }
+    Ok(report)
+}
+
+/// A member of an aggregate board, and the label its task ids are prefixed
+/// with there (`label:task_id`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceRoot {
+    pub root: PathBuf,
+    pub label: String,
+}
+
+impl WorkspaceRoot {
+    /// Labeled with the root's directory name.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let name = |p: &Path| p.file_name().map(|n| n.to_string_lossy().into_owned());
+        let label = name(&root)
+            .or_else(|| root.canonicalize().ok().as_deref().and_then(name))
+            .unwrap_or_else(|| "workspace".to_string());
+        Self { root, label }
+    }
+}
+
+/// [`fold_workspaces`], each labeled with its directory name.
+pub fn fold_many(roots: &[PathBuf]) -> Result<Board> {
+    let workspaces: Vec<WorkspaceRoot> = roots.iter().map(WorkspaceRoot::new).collect();
+    fold_workspaces(&workspaces)
+}
+
+/// Folds each workspace and merges them into one board. Task ids (and the ids
+/// cards point at) get their workspace's label as a prefix, and warnings and
+/// WIP limit violations are reported as warnings under that label. Columns
+/// keep the first workspace's order, with others' extra statuses after. The
+/// latest acknowledgement across all of them is kept; `last_ack_control_seq`
+/// counts per control log, so it's 0 here. Members are folded without their
+/// caches, and nothing is written to them.
+pub fn fold_workspaces(workspaces: &[WorkspaceRoot]) -> Result<Board> {
+    let mut merged = Board {
+        generated_at: utc_now(),
+        // In the order the workspaces' columns first appear.
+        columns: IndexMap::new(),
+        cards: HashMap::new(),
+        unread_directives: HashMap::new(),
+        unread_directive_details: HashMap::new(),
+        last_ack_directive_id: None,
+        last_ack_directive_ts: None,
+        last_ack_control_seq: 0,
+        warnings: vec![],
+        wip_violations: vec![],
+        workspaces: BTreeMap::new(),
+    };
+    for ws in workspaces {
+        let label = ws.label.as_str();
+        if label.is_empty() || label.contains(':') {
+            anyhow::bail!("invalid workspace label {label:?}: must be non-empty without ':'");
+        }
+        if merged.workspaces.insert(label.to_string(), ws.root.clone()).is_some() {
+            anyhow::bail!("workspace label {label:?} is used twice; give one of them another label");
+        }
+
+        // Members are only read: their caches are theirs to keep.
+        let options = FoldOptions { no_cache: true, ..FoldOptions::default() };
+        let board = fold_with(&ws.root, &options).with_context(|| format!("fold {}", ws.root.display()))?;
+        let prefixed = |task_id: &str| format!("{label}:{task_id}");
+        for (status, cards) in board.columns {
+            let column = merged.columns.entry(status).or_default();
+            for mut card in cards {
+                card.task_id = prefixed(&card.task_id);
+                for id in card.blocks.iter_mut().chain(card.blocked_by.iter_mut()).chain(card.parent_task_id.as_mut()) {
+                    *id = prefixed(id);
+                }
+                card.workspace = label.to_string();
+                merged.cards.insert(card.task_id.clone(), card.clone());
+                column.push(card);
+            }
+        }
+        for (task_id, ids) in board.unread_directives {
+            merged.unread_directives.insert(prefixed(&task_id), ids);
+        }
+        for (task_id, details) in board.unread_directive_details {
+            merged.unread_directive_details.insert(prefixed(&task_id), details);
+        }
+        if board.last_ack_directive_ts > merged.last_ack_directive_ts {
+            merged.last_ack_directive_id = board.last_ack_directive_id;
+            merged.last_ack_directive_ts = board.last_ack_directive_ts;
+        }
+        merged.warnings.extend(board.warnings.iter().map(|w| format!("{label}: {w}")));
+        merged.warnings.extend(board.wip_violations.iter().map(|v| {
+            format!("{label}: WIP limit exceeded: {} has {} cards (limit {})", v.status, v.count, v.limit)
+        }));
+    }
+    Ok(merged)
+}
+
+/// Writes an aggregate board's board.json and board.md (and board.html if
+/// asked) into `out_dir`, which must lie outside every member workspace.
+pub fn write_aggregate_state(out_dir: &Path, board: &Board, options: &WriteOptions) -> Result<(PathBuf, PathBuf)> {
+    if options.per_task_files || options.metrics {
+        anyhow::bail!("per-task files and metrics aren't written for aggregate boards");
+    }
+    // Checked before anything is created, so a refused directory isn't left behind.
+    let existing = out_dir.ancestors().find(|a| a.exists()).unwrap_or(Path::new("."));
+    let rest = out_dir.strip_prefix(existing).unwrap_or(out_dir);
+    let out = existing.canonicalize().with_context(|| format!("canonicalize {}", existing.display()))?.join(rest);
+    for (label, root) in &board.workspaces {
+        let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        if out.starts_with(&root) {
+            anyhow::bail!("{} is inside workspace {label:?} ({}); write aggregate boards elsewhere", out.display(), root.display());
+        }
+    }
+    write_board_files(&out, board, &render_markdown(board), options.html)
+}
+
+/// One record about a task, from either log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskEvent {
+    /// `"ledger"` or `"control"`.
+    pub source: String,
+    /// The record's `_seq`, as in the board's `updated_seq`.
+    pub seq: i64,
+    pub ts: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// For directives, whether a ledger `ack_directive` names it.
+    pub acknowledged: Option<bool>,
+    pub record: Value,
+}
+
+/// Everything recorded about one task, and what follows from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskHistory {
+    pub task_id: String,
+    /// Both logs merged in the order [`fold`] applies them.
+    pub events: Vec<TaskEvent>,
+    /// Every note, oldest first, where the board keeps only the latest
+    /// [`MAX_CARD_NOTES`].
+    pub notes: Vec<NoteOut>,
+    pub first_seen_at: Option<String>,
+    pub latest_snapshot_id: Option<String>,
+    /// Status as of the last event, following the same rules as [`fold`].
+    pub status: Option<String>,
+    pub closed_at: Option<String>,
+    /// Whole seconds spent in each status, up to `as_of` for the current one.
+    pub seconds_in_status: BTreeMap<String, i64>,
+    /// Every status the task moved into, in order, starting with the one it
+    /// was first seen in.
+    #[serde(default)]
+    pub transitions: Vec<StatusEntry>,
+    pub as_of: String,
+}
+
+/// A task moving into `status` at `at`, the time of the event that moved it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusEntry {
+    pub status: String,
+    pub at: String,
+}
+
+/// The full record of one task. Reads both logs in full.
+pub fn task_history(root: impl AsRef<Path>, task_id: &str) -> Result<TaskHistory> {
+    let p = paths_for(root);
+    let config = load_config(&p.root).unwrap_or_default();
+    let (acked, mut logs) = read_task_logs(&p, |t| t == task_id)?;
+    let [ledger, control] = logs.remove(task_id).unwrap_or_default();
+    Ok(history_from(task_id, ledger, control, &acked, &config, &CanonicalTime::now()))
+}
+
+// The ledger and control records of each task `wanted` accepts, with the keys
+// the fold orders them by, and every acknowledged directive id.
+type TaskLogs = HashMap<String, [Vec<(i64, Map<String, Value>)>; 2]>;
+
+fn read_task_logs(p: &Paths, wanted: impl Fn(&str) -> bool) -> Result<(HashSet<String>, TaskLogs)> {
+    let mut acked: HashSet<String> = HashSet::new();
+    let mut logs = TaskLogs::new();
+    for (i, path) in [&p.ledger, &p.control].into_iter().enumerate() {
+        read_jsonl_from(path, &mut JsonlCursor::default(), |_, rec| {
+            let Ok((key, rec)) = rec else {
+                return;
+            };
+            if rec.get("type").and_then(|v| v.as_str()) == Some("ack_directive") {
+                let did = rec.get("meta").and_then(|m| m.get("directive_id")).and_then(|v| v.as_str());
+                acked.extend(did.map(str::to_string));
+            }
+            let task_id = rec.get("task_id").and_then(|v| v.as_str()).filter(|t| !t.is_empty() && wanted(t));
+            if let Some(task_id) = task_id {
+                logs.entry(task_id.to_string()).or_default()[i].push((key, rec));
+            }
+        })?;
+    }
+    Ok((acked, logs))
+}
+
+fn history_from(
+    task_id: &str,
+    ledger: Vec<(i64, Map<String, Value>)>,
+    control: Vec<(i64, Map<String, Value>)>,
+    acked: &HashSet<String>,
+    config: &Config,
+    now: &CanonicalTime,
+) -> TaskHistory {
+    let event = |source: &str, mut rec: Map<String, Value>| {
+        let seq = rec.remove("_seq").and_then(|v| v.as_i64()).unwrap_or(0);
+        let text = |k: &str| rec.get(k).and_then(|v| v.as_str()).unwrap_or("").to_string();
+        let acknowledged = (source == "control").then(|| acked.contains(&text("id")));
+        TaskEvent {
+            source: source.to_string(),
+            seq,
+            ts: text("ts"),
+            kind: text("type"),
+            acknowledged,
+            record: Value::Object(rec),
+        }
+    };
+
+    let mut history = TaskHistory {
+        task_id: task_id.to_string(),
+        notes: Vec::new(),
+        closed_at: None,
+        first_seen_at: None,
+        latest_snapshot_id: None,
+        status: None,
+        seconds_in_status: BTreeMap::new(),
+        transitions: Vec::new(),
+        as_of: now.to_string(),
+        events: Vec::new(),
+    };
+    // The task's records go through the fold itself, in its order, and the
+    // card it builds is looked at after each one.
+    let mut state = FoldState::default();
+    let mut since: Option<DateTime<Utc>> = None;
+    merge_logs(ledger, control, |source, _, rec| {
+        if source == "ledger" {
+            apply_ledger_record(&mut state, &rec);
+        } else {
+            apply_directive(&mut state, &rec, None, "", config);
+        }
+        history.notes.extend(note_from(&rec, source));
+        let e = event(source, rec);
+
+        let status = state.cards.get(task_id).map(|card| card.status.clone());
+        let at = DateTime::parse_from_rfc3339(&e.ts).ok().map(|t| t.with_timezone(&Utc));
+        if let (Some(status), Some(from), Some(to)) = (&history.status, since, at) {
+            *history.seconds_in_status.entry(status.clone()).or_default() += (to - from).num_seconds().max(0);
+        }
+        if at.is_some() {
+            since = at;
+        }
+        if let Some(status) = status.filter(|s| history.status.as_ref() != Some(s)) {
+            history.transitions.push(StatusEntry { status: status.clone(), at: e.ts.clone() });
+            history.status = Some(status);
+        }
+        history.events.push(e);
+    });
+    if let (Some(status), Some(from)) = (&history.status, since) {
+        *history.seconds_in_status.entry(status.clone()).or_default() +=
+            (*now.as_datetime() - from).num_seconds().max(0);
+    }
+    if let Some(card) = state.cards.get(task_id) {
+        history.closed_at = card.closed_at.clone();
+        history.latest_snapshot_id = card.latest_snapshot_id.clone();
+    }
+    history.first_seen_at = history.events.iter().map(|e| &e.ts).filter(|ts| !ts.is_empty()).min().cloned();
+    sort_notes(&mut history.notes);
+    history
+}
+
+/// Weeks [`BoardMetrics::weekly_throughput`] covers.
+pub const THROUGHPUT_WEEKS: usize = 12;
+
+/// How long finished tasks took and how many finish each week, from [`metrics`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoardMetrics {
+    pub as_of: String,
+    /// Every task in either log, by id.
+    pub tasks: Vec<TaskMetrics>,
+    /// From first entering `doing` to the last move to `done`.
+    pub cycle_time: DurationStats,
+    /// From first being seen to the last move to `done`.
+    pub lead_time: DurationStats,
+    /// Tasks whose last move to `done` fell in each week (Monday to Sunday,
+    /// UTC), oldest first, ending with the current week.
+    pub weekly_throughput: Vec<WeekThroughput>,
+    /// Tasks whose times don't tell the whole story, or were clamped to 0.
+    pub anomalies: Vec<MetricsAnomaly>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskMetrics {
+    pub task_id: String,
+    pub status: Option<String>,
+    pub transitions: Vec<StatusEntry>,
+    /// Only for tasks that are done.
+    pub done_at: Option<String>,
+    pub cycle_seconds: Option<i64>,
+    pub lead_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DurationStats {
+    pub count: usize,
+    pub mean_seconds: Option<i64>,
+    pub median_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeekThroughput {
+    /// The Monday, as `YYYY-MM-DD`.
+    pub week_start: String,
+    pub done: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricsAnomaly {
+    pub task_id: String,
+    pub reason: String,
+}
+
+/// Cycle time, lead time and weekly throughput, from each task's history as
+/// [`task_history`] works it out. Reads both logs in full.
+pub fn metrics(root: impl AsRef<Path>) -> Result<BoardMetrics> {
+    let p = paths_for(root);
+    let config = load_config(&p.root).unwrap_or_default();
+    let (acked, logs) = read_task_logs(&p, |_| true)?;
+    let now = CanonicalTime::now();
+    let mut logs: Vec<_> = logs.into_iter().collect();
+    logs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut anomalies = vec![];
+    let mut tasks = vec![];
+    for (task_id, [ledger, control]) in logs {
+        let history = history_from(&task_id, ledger, control, &acked, &config, &now);
+        tasks.push(task_metrics(history, &mut anomalies));
+    }
+
+    let today = now.as_datetime().date_naive();
+    let this_week = today - chrono::Duration::days(today.weekday().num_days_from_monday().into());
+    let weekly_throughput = (0..THROUGHPUT_WEEKS)
+        .rev()
+        .map(|weeks_ago| {
+            let start = this_week - chrono::Duration::weeks(weeks_ago as i64);
+            let end = start + chrono::Duration::weeks(1);
+            let done = tasks
+                .iter()
+                .filter_map(|t| DateTime::parse_from_rfc3339(t.done_at.as_deref()?).ok())
+                .filter(|at| (start..end).contains(&at.with_timezone(&Utc).date_naive()))
+                .count();
+            WeekThroughput { week_start: start.format("%Y-%m-%d").to_string(), done }
+        })
+        .collect();
+
+    Ok(BoardMetrics {
+        as_of: now.to_string(),
+        cycle_time: duration_stats(tasks.iter().filter_map(|t| t.cycle_seconds).collect()),
+        lead_time: duration_stats(tasks.iter().filter_map(|t| t.lead_seconds).collect()),
+        tasks,
+        weekly_throughput,
+        anomalies,
+    })
+}
+
+fn task_metrics(history: TaskHistory, anomalies: &mut Vec<MetricsAnomaly>) -> TaskMetrics {
+    let task_id = history.task_id;
+    let mut flag = |reason: String| anomalies.push(MetricsAnomaly { task_id: task_id.clone(), reason });
+    let transitions = history.transitions;
+    let reopened = transitions.windows(2).any(|w| is_closed(&w[0].status) && !is_closed(&w[1].status));
+    if reopened {
+        flag("reopened after being closed; times run to the last close".to_string());
+    }
+
+    let done_at = transitions.last().filter(|t| t.status == "done").map(|t| t.at.clone());
+    let (mut cycle_seconds, mut lead_seconds) = (None, None);
+    if let Some(done_at) = &done_at {
+        let time = |ts: &str| DateTime::parse_from_rfc3339(ts).ok();
+        let mut seconds = |what: &str, from: Option<&str>| -> Option<i64> {
+            let (Some(from), Some(to)) = (time(from?), time(done_at)) else {
+                flag(format!("no usable time for its {what}"));
+                return None;
+            };
+            let seconds = (to - from).num_seconds();
+            if seconds < 0 {
+                flag(format!("{what} would be negative ({seconds}s); counted as 0"));
+            }
+            Some(seconds.max(0))
+        };
+        lead_seconds = seconds("lead time", history.first_seen_at.as_deref());
+        match transitions.iter().find(|t| t.status == "doing") {
+            Some(doing) => cycle_seconds = seconds("cycle time", Some(&doing.at)),
+            None => flag("done without ever being in doing; no cycle time".to_string()),
+        }
+    }
+    TaskMetrics { task_id, status: history.status, transitions, done_at, cycle_seconds, lead_seconds }
+}
+
+fn duration_stats(mut seconds: Vec<i64>) -> DurationStats {
+    seconds.sort_unstable();
+    let count = seconds.len();
+    let median_seconds = match count {
+        0 => None,
+        n if n % 2 == 1 => Some(seconds[n / 2]),
+        n => Some((seconds[n / 2 - 1] + seconds[n / 2]) / 2),
+    };
+    let mean_seconds = (count > 0).then(|| seconds.iter().sum::<i64>() / count as i64);
+    DurationStats { count, mean_seconds, median_seconds }
+}
+
+/// The "Metrics" section [`WriteOptions::metrics`] appends to board.md.
+pub fn render_metrics_markdown(metrics: &BoardMetrics) -> String {
+    let days = |s: Option<i64>| format!("{:.1}d", s.unwrap_or(0) as f64 / 86_400.0);
+    let stats = |d: &DurationStats| match d.count {
+        0 => "no finished tasks".to_string(),
+        n => format!("median {}, mean {} over {n} tasks", days(d.median_seconds), days(d.mean_seconds)),
+    };
+    let mut out = String::from("## Metrics\n");
+    out.push_str(&format!("- Cycle time (doing → done): {}\n", stats(&metrics.cycle_time)));
+    out.push_str(&format!("- Lead time (opened → done): {}\n", stats(&metrics.lead_time)));
+    let weekly: Vec<String> = metrics.weekly_throughput.iter().map(|w| w.done.to_string()).collect();
+    out.push_str(&format!("- Done per week, last {} weeks: {}\n", weekly.len(), weekly.join(" ")));
+    if !metrics.anomalies.is_empty() {
+        out.push_str("- Anomalies:\n");
+        for a in &metrics.anomalies {
+            out.push_str(&format!("  - [{}] {}\n", a.task_id, a.reason));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+impl Board {
+    fn retain_cards(&mut self, keep: impl Fn(&CardOut) -> bool) {
+        self.cards.retain(|_, card| keep(card));
+        for col in self.columns.values_mut() {
+            col.retain(&keep);
+        }
+        let cards = &self.cards;
+        self.unread_directives.retain(|task_id, _| cards.contains_key(task_id));
+        self.unread_directive_details.retain(|task_id, _| cards.contains_key(task_id));
+    }
+}
+
+// How a status, priority and so on that can't be used is reported.
+fn invalid(field: &str, value: Option<&Value>) -> String {
+    match value {
+        Some(v) => format!("invalid {field} {v}"),
+        None => format!("missing {field}"),
+    }
+}
+
+// The order key a `reorder` directive gives `task_id`. Keys only matter
+// within a column, so only the keys of the cards in the one it's placed in
+// count: the other card's column for `before`, its own for top and bottom.
+fn reorder_key(
+    order: &HashMap<String, f64>,
+    cards: &HashMap<String, Card>,
+    task_id: &str,
+    payload: Option<&Map<String, Value>>,
+) -> Result<f64, String> {
+    let status = |t: &str| cards.get(t).map(|c| c.status.as_str());
+    let others = |column: Option<&str>| -> Vec<f64> {
+        order
+            .iter()
+            .filter(|(t, _)| t.as_str() != task_id && status(t) == column)
+            .map(|(_, k)| *k)
+            .collect()
+    };
+    if let Some(before) = payload.and_then(|p| p.get("before")) {
+        let Some(other) = before.as_str().filter(|t| *t != task_id && cards.contains_key(*t)) else {
+            return Err(format!("reorder ignored: {} isn't another task on the board", before));
+        };
+        // Cards without a key sort as 0, so going before one of them means
+        // going just ahead of all of them.
+        let at = order.get(other).copied().unwrap_or(0.0);
+        let floor = if at > 0.0 { Some(0.0) } else { None };
+        let prev = others(status(other)).into_iter().filter(|k| *k < at).chain(floor).reduce(f64::max);
+        return Ok(match prev {
+            Some(prev) => (prev + at) / 2.0,
+            None => at - 1.0,
+        });
+    }
+    let own = status(task_id);
+    match payload.and_then(|p| p.get("position")).and_then(|v| v.as_str()) {
+        Some("top") => Ok(others(own).into_iter().fold(0.0, f64::min) - 1.0),
+        Some("bottom") => Ok(others(own).into_iter().fold(0.0, f64::max) + 1.0),
+        _ => Err(invalid("position", payload.and_then(|p| p.get("position")))),
+    }
+}
+
+// Applies one directive, read from line `line` of `control_file`, noting
+// whatever of it is ignored.
+fn apply_directive(state: &mut FoldState, d: &Map<String, Value>, line: Option<u64>, control_file: &str, config: &Config) {
+    let FoldState {
+        cards,
+        acked_directives,
+        unread,
+        last_ack_control_seq,
+        paused,
+        blocks,
+        order,
+        parents,
+        first_seen,
+        ignored,
+        ..
+    } = state;
+    let d_id = d.get("id").and_then(|v| v.as_str());
+    let d_type = d.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let ts = d.get("ts").and_then(|v| v.as_str()).unwrap_or("");
+    let seq = d.get("_seq").and_then(|v| v.as_i64()).unwrap_or(0);
+    let task_id = d.get("task_id").and_then(|v| v.as_str());
+    let source = UpdateSource { via: "control", by: d.get("author").and_then(|v| v.as_str()) };
+    let payload = d.get("payload").and_then(|v| v.as_object());
+    let field = |name: &str| payload.and_then(|p| p.get(name));
+    let mut warn = |reason: String| ignored.push(FoldWarning::at(control_file, line, reason));
+    if let Some(task_id) = task_id.filter(|t| !t.is_empty() && !ts.is_empty()) {
+        first_seen.entry(task_id.to_string()).or_insert_with(|| ts.to_string());
+    }
+
+    if d_type.is_empty() {
+        warn("directive has no type".to_string());
+    } else if !DIRECTIVE_TYPES.contains(&d_type) {
+        warn(format!("unknown directive type {d_type:?}"));
+    }
+    if (d_type == "open_task" || TASK_SCOPED_DIRECTIVES.contains(&d_type))
+        && task_id.is_none_or(|t| t.is_empty())
+    {
+        warn(format!("{d_type} directive has no task_id"));
+    }
+
+    if d_type == "open_task" {
+        let Some(task_id) = task_id.filter(|t| !t.is_empty()) else {
+            return;
+        };
+        let title = field("title").and_then(|v| v.as_str());
+        let status = field("status").and_then(|v| v.as_str());
+        let priority = field("priority").and_then(|v| v.as_str());
+        if field("status").is_some() && !status.is_some_and(|s| config.is_status(s)) {
+            warn(invalid("status", field("status")));
+        }
+        if field("priority").is_some() && !priority.is_some_and(|p| config.is_priority(p)) {
+            warn(invalid("priority", field("priority")));
+        }
+
+        let card = cards.entry(task_id.to_string()).or_insert_with(|| Card {
+            task_id: task_id.to_string(),
+            title: title.unwrap_or("Untitled task").to_string(),
+            status: "backlog".to_string(),
+            priority: "medium".to_string(),
+            updated_at: "".to_string(),
+            updated_seq: 0,
+            latest_snapshot_id: None,
+            assignee: None,
+            tags: Vec::new(),
+            due_at: None,
+            notes: Vec::new(),
+            closed_at: None,
+            provisional: true,
+            signed_update: None,
+            updated_by: None,
+            updated_via: None,
+        });
+
+        if let Some(t) = title {
+            if !t.is_empty() && (card.title == "(unopened task)" || card.title == "Untitled task") {
+                card.title = t.to_string();
+            }
+        }
+
+        if let Some(s) = status.filter(|s| config.is_status(s)) {
+            if card.closed_at.is_some() {
+                warn(format!("open_task status ignored: task {task_id:?} is closed"));
+            } else {
+                card.status = s.to_string();
+            }
+        }
+        if let Some(pv) = priority {
+            if config.is_priority(pv) {
+                card.priority = pv.to_string();
+            }
+        }
+        let tags = payload.and_then(|p| p.get("tags")).and_then(|v| v.as_array());
+        for tag in tags.into_iter().flatten().filter_map(|v| v.as_str()) {
+            add_tag(card, tag);
+        }
+        if let Some(due) = parse_due(payload.and_then(|p| p.get("due"))) {
+            card.due_at = due;
+        }
+        if let Some(parent) = field("parent_task_id") {
+            match parent.as_str().filter(|p| *p != task_id && validate_task_id(p).is_ok()) {
+                Some(p) => {
+                    parents.insert(task_id.to_string(), (p.to_string(), line));
+                }
+                None => warn(invalid("parent_task_id", Some(parent))),
+            }
+        }
+        set_updated(card, ts, seq, source);
+    }
+
+    let mut placeholder = false;
+    if d_type != "open_task" {
+        if let Some(task_id) = task_id.filter(|t| !t.is_empty()) {
+            placeholder = !cards.contains_key(task_id);
+            if placeholder {
+                warn(format!("{d_type} directive refers to task {task_id:?}, which was never opened"));
+            }
+            cards.entry(task_id.to_string()).or_insert_with(|| Card {
+                task_id: task_id.to_string(),
+                title: "(unopened task)".to_string(),
+                status: "backlog".to_string(),
+                priority: "medium".to_string(),
+                updated_at: "".to_string(),
+                updated_seq: 0,
+                latest_snapshot_id: None,
+                assignee: None,
+                tags: Vec::new(),
+                due_at: None,
+                notes: Vec::new(),
+                closed_at: None,
+                provisional: true,
+                signed_update: None,
+                updated_by: None,
+                updated_via: None,
+            });
+        }
+    }
+
+    if d_type == "reorder" {
+        if let Some(task_id) = task_id.filter(|t| !t.is_empty()) {
+            match reorder_key(order, cards, task_id, payload) {
+                Ok(key) => {
+                    order.insert(task_id.to_string(), key);
+                }
+                Err(reason) => warn(reason),
+            }
+        }
+    }
+
+    if let Some(task_id) = task_id.filter(|t| !t.is_empty()) {
+        if let Some(card) = cards.get_mut(task_id) {
+            let closed = card.closed_at.is_some();
+            if closed && matches!(d_type, "set_status" | "pause" | "resume") {
+                warn(format!("{d_type} ignored: task {task_id:?} is closed; send reopen_task first"));
+            }
+
+            if d_type == "close_task" {
+                match resolution_status(field("resolution")) {
+                    Some(status) => {
+                        paused.remove(task_id);
+                        close_card(card, status, ts, seq, source);
+                    }
+                    None => warn(invalid("resolution", field("resolution"))),
+                }
+            }
+
+            if d_type == "reopen_task" {
+                if closed {
+                    card.closed_at = None;
+                    card.status = field("status")
+                        .and_then(|v| v.as_str())
+                        .filter(|s| config.is_status(s) && !is_closed(s))
+                        .unwrap_or("doing")
+                        .to_string();
+                    set_updated(card, ts, seq, source);
+                } else {
+                    warn(format!("reopen_task ignored: task {task_id:?} isn't closed"));
+                }
+            }
+
+            if d_type == "set_status" && !closed {
+                match field("status").and_then(|v| v.as_str()) {
+                    Some(s) if config.is_status(s) => {
+                        card.status = s.to_string();
+                        paused.remove(task_id);
+                        set_updated(card, ts, seq, source);
+                    }
+                    _ => warn(invalid("status", field("status"))),
+                }
+            }
+
+            if d_type == "set_priority" {
+                match field("priority").and_then(|v| v.as_str()) {
+                    Some(pr) if config.is_priority(pr) => {
+                        card.priority = pr.to_string();
+                        set_updated(card, ts, seq, source);
+                    }
+                    _ => warn(invalid("priority", field("priority"))),
+                }
+            }
+
+            if d_type == "set_assignee" {
+                if let Some(assignee) = parse_assignee(payload.and_then(|p| p.get("assignee"))) {
+                    card.assignee = assignee;
+                    set_updated(card, ts, seq, source);
+                }
+            }
+
+            if d_type == "link_tasks" || d_type == "unlink_tasks" {
+                let other = payload
+                    .and_then(|p| p.get("blocks"))
+                    .and_then(|v| v.as_str())
+                    .filter(|t| *t != task_id && validate_task_id(t).is_ok());
+                if let Some(other) = other {
+                    let changed = if d_type == "link_tasks" {
+                        blocks.entry(task_id.to_string()).or_default().insert(other.to_string())
+                    } else {
+                        blocks.get_mut(task_id).is_some_and(|b| b.remove(other))
+                    };
+                    if changed {
+                        set_updated(card, ts, seq, source);
+                    }
+                }
+            }
+
+            if d_type == "set_due" {
+                if let Some(due) = parse_due(payload.and_then(|p| p.get("due"))) {
+                    card.due_at = due;
+                    set_updated(card, ts, seq, source);
+                }
+            }
+
+            if d_type == "add_tag" || d_type == "remove_tag" {
+                if let Some(tag) = payload.and_then(|p| p.get("tag")).and_then(|v| v.as_str()) {
+                    let changed = if d_type == "add_tag" {
+                        add_tag(card, tag)
+                    } else {
+                        let before = card.tags.len();
+                        card.tags.retain(|t| t != tag);
+                        card.tags.len() != before
+                    };
+                    if changed {
+                        set_updated(card, ts, seq, source);
+                    }
+                }
+            }
+
+            if d_type == "note" {
+                match note_from(d, "control") {
+                    Some(note) => {
+                        card.notes.push(note);
+                        set_updated(card, ts, seq, source);
+                    }
+                    None => warn(invalid("text", field("text"))),
+                }
+            }
+
+            if d_type == "pause" && !closed {
+                // Pausing again keeps the status from before the first pause.
+                paused
+                    .entry(task_id.to_string())
+                    .or_insert_with(|| (!placeholder).then(|| card.status.clone()));
+                card.status = "blocked".to_string();
+                set_updated(card, ts, seq, source);
+            }
+
+            if d_type == "resume" && !closed {
+                if let Some(before) = paused.remove(task_id) {
+                    card.status = before.unwrap_or_else(|| "doing".to_string());
+                    set_updated(card, ts, seq, source);
+                }
+            }
+
+            if let Some(d_id) = d_id.filter(|id| !id.is_empty()) {
+                if !acked_directives.contains(d_id) {
+                    let text = |k: &str| d.get(k).and_then(|v| v.as_str()).map(str::to_string);
+                    let directive = UnreadDirective {
+                        id: d_id.to_string(),
+                        kind: d_type.to_string(),
+                        ts: ts.to_string(),
+                        author: text("author"),
+                        rationale: text("rationale"),
+                        payload: d.get("payload").cloned().unwrap_or(Value::Null),
+                    };
+                    unread.entry(task_id.to_string()).or_default().push((seq, directive));
+                } else {
+                    *last_ack_control_seq = (*last_ack_control_seq).max(seq);
+                }
+            }
+        }
+    }
+}
+
+fn board_from(state: &FoldState, control_file: &str, unverified: &HashSet<String>, config: &Config) -> FoldReport {
+    let FoldState { cards, blocks, order, parents, first_seen, .. } = state;
+    let mut warnings: Vec<FoldWarning> = state.skipped.iter().chain(&state.ignored).cloned().collect();
+    let unread_directive_details: HashMap<String, Vec<UnreadDirective>> = state
+        .unread
+        .iter()
+        .map(|(t, pending)| (t.clone(), pending.iter().map(|(_, d)| d.clone()).collect()))
+        .collect();
+    let unread_directives: HashMap<String, Vec<String>> = unread_directive_details
+        .iter()
+        .map(|(t, details)| (t.clone(), details.iter().map(|d| d.id.clone()).collect()))
+        .collect();
+    let now = CanonicalTime::now();
+
+    let mut columns: IndexMap<String, Vec<CardOut>> =
+        config.statuses.iter().map(|s| (s.to_string(), vec![])).collect();
+    let mut cards_out: HashMap<String, CardOut> = HashMap::new();
+
+    let mut blocked_by: HashMap<&str, Vec<String>> = HashMap::new();
+    for (blocker, blocked) in blocks {
+        for task_id in blocked {
+            blocked_by.entry(task_id).or_default().push(blocker.clone());
+        }
+    }
+    warnings.extend(dependency_cycles(blocks).into_iter().map(|cycle| FoldWarning {
+        file: None,
+        line: None,
+        reason: format!("dependency cycle: {}", cycle.join(" -> ")),
+    }));
+    let statuses: HashMap<String, String> = cards.iter().map(|(id, c)| (id.clone(), c.status.clone())).collect();
+
+    let mut parent_of: HashMap<String, String> = HashMap::new();
+    for (task_id, (parent, line)) in parents {
+        if !cards.contains_key(parent) {
+            warnings.push(FoldWarning::at(
+                control_file,
+                *line,
+                format!("parent task {parent:?} of {task_id:?} isn't on the board"),
+            ));
+        } else if parents.contains_key(parent) {
+            warnings.push(FoldWarning::at(
+                control_file,
+                *line,
+                format!("{task_id:?} can't be a subtask of {parent:?}, which is itself a subtask"),
+            ));
+            continue;
+        }
+        parent_of.insert(task_id.clone(), parent.clone());
+    }
+    let mut progress: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (task_id, parent) in &parent_of {
+        if cards.contains_key(parent) {
+            let (done, total) = progress.entry(parent).or_default();
+            *total += 1;
+            if statuses.get(task_id).is_some_and(|s| is_closed(s)) {
+                *done += 1;
+            }
+        }
+    }
+
+    for (task_id, card) in cards {
+        let mut card = card.clone();
+        sort_notes(&mut card.notes);
+        keep_latest_notes(&mut card.notes);
+        let blocked_by = blocked_by.remove(task_id.as_str()).unwrap_or_default();
+        let unread = unread_directives.get(task_id).map(|v| v.len()).unwrap_or(0);
+        let since = Some(card.updated_at.as_str())
+            .filter(|t| !t.is_empty())
+            .or(first_seen.get(task_id).map(String::as_str));
+        let days_since_update = since
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| (*now.as_datetime() - t.with_timezone(&Utc)).num_seconds().max(0) as f64 / 86_400.0);
+        let out = CardOut {
+            task_id: card.task_id,
+            title: card.title,
+            status: card.status.clone(),
+            priority: card.priority.clone(),
+            updated_at: card.updated_at,
+            updated_seq: card.updated_seq,
+            updated_by: card.updated_by,
+            updated_via: card.updated_via,
+            latest_snapshot_id: card.latest_snapshot_id,
+            assignee: card.assignee,
+            tags: card.tags,
+            overdue: !is_closed(&card.status)
+                && card.due_at.as_deref().and_then(due_deadline).is_some_and(|d| d <= *now.as_datetime()),
+            due_at: card.due_at,
+            blocks: blocks.get(task_id).map(|b| b.iter().cloned().collect()).unwrap_or_default(),
+            waiting_on_dependencies: blocked_by
+                .iter()
+                .any(|b| statuses.get(b).is_some_and(|s| !is_closed(s))),
+            blocked_by,
+            notes: card.notes,
+            closed_at: card.closed_at,
+            order_key: order.get(task_id).copied(),
+            parent_task_id: parent_of.get(task_id).cloned(),
+            subtask_progress: progress.get(task_id.as_str()).copied(),
+            has_open_subtasks: is_closed(&card.status)
+                && progress.get(task_id.as_str()).is_some_and(|(done, total)| done < total),
+            unverified: unverified.contains(task_id),
+            stale: config.stale_after_days > 0
+                && !is_closed(&card.status)
+                && days_since_update.is_some_and(|d| d >= f64::from(config.stale_after_days)),
+            days_since_update,
+            workspace: String::new(),
+            unread_directive_count: unread,
+            provisional: card.provisional,
+        };
+        cards_out.insert(task_id.clone(), out.clone());
+        if let Some(col) = columns.get_mut(&out.status) {
+            col.push(out);
+        }
+    }
+
+    for col in columns.values_mut() {
+        col.sort_by(|a, b| {
+            let ra = config.priority_rank(&a.priority);
+            let rb = config.priority_rank(&b.priority);
+            let placed = a.order_key.unwrap_or(0.0).total_cmp(&b.order_key.unwrap_or(0.0));
+            placed.then_with(|| (rb, b.overdue, b.updated_seq).cmp(&(ra, a.overdue, a.updated_seq)))
+        });
+    }
+
+    let board = Board {
+        generated_at: now.to_string(),
+        columns,
+        cards: cards_out,
+        unread_directives,
+        unread_directive_details,
+        last_ack_directive_id: state.last_ack_directive_id.clone(),
+        last_ack_directive_ts: state.last_ack_directive_ts.clone(),
+        last_ack_control_seq: state.last_ack_control_seq,
+        warnings: warnings.iter().map(ToString::to_string).collect(),
+        wip_violations: vec![],
+        workspaces: BTreeMap::new(),
+    };
+    FoldReport { board, warnings }
+}
+
+/// What [`render_markdown_with`] puts in board.md.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Columns to render, in this order; empty for all of the board's.
+    pub statuses: Vec<String>,
+    /// Cards shown per column; the rest are counted on a closing line.
+    pub max_cards_per_column: Option<usize>,
+    /// Put cards under a sub-heading per priority, highest first.
+    pub group_by_priority: bool,
+    pub include_provisional: bool,
+    /// Append a section listing each task's unread directive ids.
+    pub unread_summary: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            statuses: vec![],
+            max_cards_per_column: None,
+            group_by_priority: false,
+            include_provisional: true,
+            unread_summary: false,
+        }
+    }
+}
+
+/// [`render_markdown_with`] the default options, as written to board.md.
+pub fn render_markdown(board: &Board) -> String {
+    render_markdown_with(board, &RenderOptions::default())
+}
+
+pub fn render_markdown_with(board: &Board, options: &RenderOptions) -> String {
+    let mut out = String::new();
+    out.push_str("# Board (derived)\n\n");
+    out.push_str(&format!("Generated: {}\n\n", board.generated_at));
+    for v in &board.wip_violations {
+        out.push_str(&format!("> **WIP limit exceeded:** {} has {} cards (limit {})\n", v.status, v.count, v.limit));
+    }
+    if !board.wip_violations.is_empty() {
+        out.push('\n');
+    }
+
+    let statuses = match options.statuses.as_slice() {
+        [] => board.columns.keys().collect(),
+        chosen => chosen.iter().collect::<Vec<_>>(),
+    };
+    for status in statuses {
+        out.push_str(&format!("## {}\n", capitalize(status)));
+        let cards: Vec<&CardOut> = board
+            .columns
+            .get(status)
+            .into_iter()
+            .flatten()
+            .filter(|c| options.include_provisional || !c.provisional)
+            .collect();
+        // Subtasks go under their parent when it's in the same column.
+        let in_column: HashSet<&str> = cards.iter().map(|c| c.task_id.as_str()).collect();
+        let mut subtasks: HashMap<&str, Vec<&CardOut>> = HashMap::new();
+        let mut top = vec![];
+        for card in cards {
+            match card.parent_task_id.as_deref().filter(|p| in_column.contains(p)) {
+                Some(parent) => subtasks.entry(parent).or_default().push(card),
+                None => top.push(card),
+            }
+        }
+        // Aggregate boards put each workspace's cards under its own heading.
+        let by_workspace = !board.workspaces.is_empty();
+        if by_workspace {
+            top.sort_by(|a, b| a.workspace.cmp(&b.workspace));
+        }
+        let priority_heading = if by_workspace { "####" } else { "###" };
+        let shown = options.max_cards_per_column.unwrap_or(top.len()).min(top.len());
+        let (mut workspace, mut priority) = (None, None);
+        for card in &top[..shown] {
+            if by_workspace && workspace != Some(card.workspace.as_str()) {
+                workspace = Some(card.workspace.as_str());
+                priority = None;
+                out.push_str(&format!("### {}\n", card.workspace));
+            }
+            if options.group_by_priority && priority != Some(card.priority.as_str()) {
+                priority = Some(card.priority.as_str());
+                out.push_str(&format!("{priority_heading} {}\n", capitalize(&card.priority)));
+            }
+            render_card(&mut out, board, card, "");
+            for sub in subtasks.get(card.task_id.as_str()).into_iter().flatten() {
+                render_card(&mut out, board, sub, "  ");
+            }
+        }
+        let hidden: usize = top[shown..].iter().map(|c| 1 + subtasks.get(c.task_id.as_str()).map_or(0, Vec::len)).sum();
+        if hidden > 0 {
+            out.push_str(&format!("- … and {hidden} more\n"));
+        }
+        out.push('\n');
+    }
+
+    if options.unread_summary {
+        let mut unread: Vec<(&String, &Vec<String>)> =
+            board.unread_directives.iter().filter(|(_, ids)| !ids.is_empty()).collect();
+        if !unread.is_empty() {
+            unread.sort();
+            out.push_str("## Unread directives\n");
+            for (task_id, ids) in unread {
+                out.push_str(&format!("- [{task_id}] {}\n", ids.join(", ")));
+            }
+            out.push('\n');
+        }
+    }
+
+    if !board.warnings.is_empty() {
+        out.push_str("## Warnings\n");
+        for warning in &board.warnings {
+            out.push_str(&format!("- {warning}\n"));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}", first.to_ascii_uppercase(), chars.as_str()),
+        None => s.to_string(),
+    }
+}
+
+fn render_card(out: &mut String, board: &Board, card: &CardOut, indent: &str) {
+    let assignee = card.assignee.as_deref().map(|a| format!(" @{a}")).unwrap_or_default();
+    let provisional = if card.provisional { " (provisional)" } else { "" };
+    let overdue = if card.overdue { " (overdue)" } else { "" };
+    let waiting = if card.waiting_on_dependencies {
+        let open: Vec<&str> = card
+            .blocked_by
+            .iter()
+            .filter(|b| board.cards.get(*b).is_some_and(|c| !is_closed(&c.status)))
+            .map(String::as_str)
+            .collect();
+        format!(" (waiting on {})", open.join(", "))
+    } else {
+        "".to_string()
+    };
+    let suffix = if card.unread_directive_count > 0 {
+        format!(" (unread:{})", card.unread_directive_count)
+    } else {
+        "".to_string()
+    };
+    let progress = card.subtask_progress.map(|(done, total)| format!(" {done}/{total}")).unwrap_or_default();
+    let open_subtasks = if card.has_open_subtasks { " (open subtasks)" } else { "" };
+    let unverified = if card.unverified { " (unverified)" } else { "" };
+    let stale = match card.days_since_update {
+        Some(days) if card.stale => format!(" (stale {}d)", days.floor()),
+        _ => "".to_string(),
+    };
+    let updated_by = card.updated_by.as_deref().map(|by| format!(" by {by}")).unwrap_or_default();
+    out.push_str(&format!(
+        "{indent}- [{}] {}{}{}{}{}{}{}{}  ({}){}{}{}{}\n",
+        card.task_id,
+        card.title,
+        progress,
+        assignee,
+        provisional,
+        unverified,
+        overdue,
+        waiting,
+        open_subtasks,
+        card.priority,
+        suffix,
+        card.tags.iter().map(|t| format!(" #{t}")).collect::<String>(),
+        stale,
+        updated_by
+    ));
+    if let Some(note) = card.notes.last() {
+        let text = note.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        out.push_str(&format!("{indent}  - {} ({}): {}\n", note.author, note.ts, text));
+    }
+    if let Some(pending) = board.unread_directive_details.get(&card.task_id).filter(|d| !d.is_empty()) {
+        let kinds: Vec<&str> = pending.iter().map(|d| d.kind.as_str()).collect();
+        out.push_str(&format!("{indent}  - pending: {}\n", kinds.join(", ")));
+    }
+}
+
+const HTML_STYLE: &str = "\
+:root { --bg:#0b1020; --panel:#121a33; --text:#e6e9f2; --muted:#a8b0c6; --card:#1a2550; --accent:#7aa2f7; --warn:#f7768e; --high:#ff9e64; }
+* { box-sizing: border-box; font-family: ui-sans-serif, system-ui, -apple-system, Segoe UI, Roboto, Helvetica, Arial; }
+body { margin:0; background:var(--bg); color:var(--text); }
+header { padding:12px 16px; border-bottom:1px solid rgba(255,255,255,.08); display:flex; gap:16px; align-items:center; }
+.badge { padding:2px 8px; border:1px solid rgba(255,255,255,.15); border-radius:999px; color:var(--muted); font-size:12px; }
+main { padding:16px; }
+.cols { display:grid; gap:12px; }
+.col { background:var(--panel); border:1px solid rgba(255,255,255,.08); border-radius:12px; padding:10px; }
+.col h2 { margin:0 0 8px 0; font-size:14px; color:var(--muted); text-transform: uppercase; letter-spacing: .08em; }
+.card { background:var(--card); border:1px solid rgba(255,255,255,.10); border-left:4px solid var(--muted); border-radius:10px; padding:10px; margin:8px 0; overflow-wrap:anywhere; }
+.card.medium { border-left-color:var(--accent); }
+.card.high { border-left-color:var(--high); }
+.card.urgent { border-left-color:var(--warn); }
+.card small { color:var(--muted); display:block; margin-top:4px; }
+.row { display:flex; flex-wrap:wrap; gap:6px; align-items:center; margin-top:6px; }
+.pill { padding:2px 8px; border-radius:999px; border:1px solid rgba(255,255,255,.18); font-size:12px; color:var(--muted); }
+.pill.unread, .pill.overdue { border-color: rgba(247,118,142,.55); color: var(--warn); }
+.col.over { border-color: rgba(247,118,142,.55); }
+.col.over h2 { color:var(--warn); }
+.warnings { margin-top:16px; color:var(--warn); font-size:13px; }
+";
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// The board as one self-contained HTML page: a column per status, cards
+/// edged by priority, no scripts or external assets.
+pub fn render_html(board: &Board) -> String {
+    let mut out = String::new();
+    out.push_str("<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\" />\n");
+    out.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\" />\n");
+    out.push_str("<title>Voxelle Board</title>\n<style>\n");
+    out.push_str(HTML_STYLE);
+    out.push_str("</style>\n</head>\n<body>\n<header>\n<strong>Voxelle Board</strong>\n");
+    out.push_str(&format!("<span class=\"badge\">Generated {}</span>\n", escape_html(&board.generated_at)));
+    out.push_str("</header>\n<main>\n");
+    out.push_str(&format!(
+        "<div class=\"cols\" style=\"grid-template-columns: repeat({}, minmax(0, 1fr))\">\n",
+        board.columns.len().max(1)
+    ));
+
+    for (status, cards) in &board.columns {
+        let name = escape_html(status);
+        match board.wip_violations.iter().find(|v| v.status == *status) {
+            Some(v) => out.push_str(&format!("<section class=\"col over\">\n<h2>{name} ({}/{})</h2>\n", v.count, v.limit)),
+            None => out.push_str(&format!("<section class=\"col\">\n<h2>{name} ({})</h2>\n", cards.len())),
+        }
+        for card in cards {
+            // Only the built-in priorities have colours.
+            let priority = if PRIORITIES.contains(&card.priority.as_str()) { card.priority.as_str() } else { "low" };
+            out.push_str(&format!("<div class=\"card {priority}\">\n<div>{}</div>\n", escape_html(&card.title)));
+            out.push_str(&format!("<small>{}</small>\n<div class=\"row\">\n", escape_html(&card.task_id)));
+            out.push_str(&format!("<span class=\"pill\">{}</span>\n", escape_html(&card.priority)));
+            if card.unread_directive_count > 0 {
+                out.push_str(&format!("<span class=\"pill unread\">unread {}</span>\n", card.unread_directive_count));
+            }
+            if let Some((done, total)) = card.subtask_progress {
+                out.push_str(&format!("<span class=\"pill\">{done}/{total} subtasks</span>\n"));
+            }
+            if card.overdue {
+                out.push_str("<span class=\"pill overdue\">overdue</span>\n");
+            }
+            if card.provisional {
+                out.push_str("<span class=\"pill\">provisional</span>\n");
+            }
+            if card.unverified {
+                out.push_str("<span class=\"pill unread\">unverified</span>\n");
+            }
+            if let Some(days) = card.days_since_update.filter(|_| card.stale) {
+                out.push_str(&format!("<span class=\"pill overdue\">stale {}d</span>\n", days.floor()));
+            }
+            if let Some(assignee) = &card.assignee {
+                out.push_str(&format!("<span class=\"pill\">@{}</span>\n", escape_html(assignee)));
+            }
+            for tag in &card.tags {
+                out.push_str(&format!("<span class=\"pill\">#{}</span>\n", escape_html(tag)));
+            }
+            out.push_str("</div>\n");
+            if let Some(note) = card.notes.last() {
+                out.push_str(&format!("<small>{}: {}</small>\n", escape_html(&note.author), escape_html(&note.text)));
+            }
+            out.push_str("</div>\n");
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</div>\n");
+
+    if !board.warnings.is_empty() {
+        out.push_str("<ul class=\"warnings\">\n");
+        for warning in &board.warnings {
+            out.push_str(&format!("<li>{}</li>\n", escape_html(warning)));
+        }
+        out.push_str("</ul>\n");
+    }
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
+
+/// Extra files [`write_state_with`] writes next to board.json and board.md.
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Also write `state/board.html` from [`render_html`].
+    pub html: bool,
+    /// Also write `state/tasks/<task_id>.md` for every card, and remove the
+    /// files of tasks no longer on the board.
+    pub per_task_files: bool,
+    /// Append a "Metrics" section from [`metrics`] to board.md.
+    pub metrics: bool,
+}
+
+pub fn write_state(root: impl AsRef<Path>, board: &Board) -> Result<(PathBuf, PathBuf)> {
+    write_state_with(root, board, &WriteOptions::default())
+}
+
+pub fn write_state_with(root: impl AsRef<Path>, board: &Board, options: &WriteOptions) -> Result<(PathBuf, PathBuf)> {
+    let p = paths_for(root);
+    let mut markdown = render_markdown(board);
+    if options.metrics {
+        markdown.push_str(&render_metrics_markdown(&metrics(&p.root)?));
+    }
+    let written = write_board_files(&p.state_dir, board, &markdown, options.html)?;
+    if options.per_task_files {
+        write_task_files(&p, board)?;
+    }
+    Ok(written)
+}
+
+// board.json, board.md (`markdown`) and optionally board.html in `dir`.
+fn write_board_files(dir: &Path, board: &Board, markdown: &str, html: bool) -> Result<(PathBuf, PathBuf)> {
+    ensure_dir(dir)?;
+    let (json_path, md_path) = (dir.join("board.json"), dir.join("board.md"));
+
+    let json = serde_json::to_value(board)?;
+    write_json_pretty(&json_path, &json)?;
+    fs::write(&md_path, markdown).with_context(|| format!("write {}", md_path.display()))?;
+    if html {
+        let html_path = dir.join("board.html");
+        fs::write(&html_path, render_html(board)).with_context(|| format!("write {}", html_path.display()))?;
+    }
+    Ok((json_path, md_path))
+}
+
+fn write_task_files(p: &Paths, board: &Board) -> Result<()> {
+    let mut directives: HashMap<&str, Vec<Map<String, Value>>> = HashMap::new();
+    read_jsonl_from(&p.control, &mut JsonlCursor::default(), |_, rec| {
+        let Ok((_, rec)) = rec else {
+            return;
+        };
+        let task_id = rec.get("task_id").and_then(|v| v.as_str()).unwrap_or("");
+        if let Some((task_id, _)) = board.cards.get_key_value(task_id) {
+            directives.entry(task_id.as_str()).or_default().push(rec);
+        }
+    })?;
+
+    ensure_dir(&p.tasks_dir)?;
+    for (task_id, card) in &board.cards {
+        // Ids come from the logs; only ones that are safe file names get a file.
+        if validate_task_id(task_id).is_err() {
+            continue;
+        }
+        let unread = board.unread_directives.get(task_id);
+        let md = render_task_markdown(card, directives.get(task_id.as_str()).map(Vec::as_slice).unwrap_or_default(), unread);
+        let path = p.tasks_dir.join(format!("{task_id}.md"));
+        // Left alone when unchanged, so refolds don't touch every file.
+        if fs::read_to_string(&path).ok().as_deref() != Some(md.as_str()) {
+            fs::write(&path, md).with_context(|| format!("write {}", path.display()))?;
+        }
+    }
+
+    for entry in fs::read_dir(&p.tasks_dir).with_context(|| format!("read dir {}", p.tasks_dir.display()))? {
+        let path = entry?.path();
+        let stale = path.extension().is_some_and(|e| e == "md")
+            && path.file_stem().and_then(|s| s.to_str()).is_some_and(|id| !board.cards.contains_key(id));
+        if stale {
+            fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn render_task_markdown(card: &CardOut, directives: &[Map<String, Value>], unread: Option<&Vec<String>>) -> String {
+    let mut out = format!("# [{}] {}\n\n", card.task_id, card.title);
+    out.push_str(&format!("- Status: {}\n", card.status));
+    out.push_str(&format!("- Priority: {}\n", card.priority));
+    if let Some(assignee) = &card.assignee {
+        out.push_str(&format!("- Assignee: @{assignee}\n"));
+    }
+    if !card.tags.is_empty() {
+        out.push_str(&format!("- Tags: {}\n", card.tags.iter().map(|t| format!("#{t}")).collect::<Vec<_>>().join(" ")));
+    }
+    if let Some(due_at) = &card.due_at {
+        out.push_str(&format!("- Due: {due_at}{}\n", if card.overdue { " (overdue)" } else { "" }));
+    }
+    if !card.blocked_by.is_empty() {
+        out.push_str(&format!("- Blocked by: {}\n", card.blocked_by.join(", ")));
+    }
+    if !card.blocks.is_empty() {
+        out.push_str(&format!("- Blocks: {}\n", card.blocks.join(", ")));
+    }
+    if let Some(parent) = &card.parent_task_id {
+        out.push_str(&format!("- Parent: {parent}\n"));
+    }
+    if let Some((done, total)) = card.subtask_progress {
+        out.push_str(&format!("- Subtasks: {done}/{total} closed{}\n", if card.has_open_subtasks { " (some still open)" } else { "" }));
+    }
+    if let Some(closed_at) = &card.closed_at {
+        out.push_str(&format!("- Closed: {closed_at}\n"));
+    }
+    out.push_str(&format!("- Latest snapshot: {}\n", card.latest_snapshot_id.as_deref().unwrap_or("none")));
+    out.push_str(&format!("- Updated: {}\n", card.updated_at));
+
+    out.push_str("\n## Notes\n");
+    if card.notes.is_empty() {
+        out.push_str("- none\n");
+    }
+    for note in &card.notes {
+        let text = note.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        out.push_str(&format!("- {} ({}): {}\n", note.author, note.ts, text));
+    }
+
+    out.push_str("\n## Directives\n");
+    if directives.is_empty() {
+        out.push_str("- none\n");
+    }
+    for rec in directives {
+        let text = |k: &str| rec.get(k).and_then(|v| v.as_str()).unwrap_or("");
+        let payload = rec.get("payload").filter(|v| v.as_object().is_some_and(|o| !o.is_empty()));
+        let payload = payload.map(|v| format!(" `{v}`")).unwrap_or_default();
+        let is_unread = unread.is_some_and(|ids| ids.iter().any(|id| id == text("id")));
+        out.push_str(&format!(
+            "- {} {} by {}{}{}\n",
+            text("ts"),
+            text("type"),
+            if text("author").is_empty() { "unknown" } else { text("author") },
+            payload,
+            if is_unread { " (unread)" } else { "" }
+        ));
+    }
+    out
+}
+
+/// One thing that changed on a card between two folds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BoardChange {
+    Added { task_id: String, title: String, status: String },
+    Removed { task_id: String, title: String },
+    StatusChanged { task_id: String, from: String, to: String },
+    PriorityChanged { task_id: String, from: String, to: String },
+    TitleChanged { task_id: String, from: String, to: String },
+    /// Directives that weren't unread on the old board, oldest first.
+    NewUnread { task_id: String, directive_ids: Vec<String> },
+}
+
+impl BoardChange {
+    pub fn task_id(&self) -> &str {
+        match self {
+            BoardChange::Added { task_id, .. }
+            | BoardChange::Removed { task_id, .. }
+            | BoardChange::StatusChanged { task_id, .. }
+            | BoardChange::PriorityChanged { task_id, .. }
+            | BoardChange::TitleChanged { task_id, .. }
+            | BoardChange::NewUnread { task_id, .. } => task_id,
+        }
+    }
+}
+
+impl std::fmt::Display for BoardChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoardChange::Added { task_id, status, .. } => write!(f, "{task_id} added to {status}"),
+            BoardChange::Removed { task_id, .. } => write!(f, "{task_id} removed"),
+            BoardChange::StatusChanged { task_id, from, to } => write!(f, "{task_id} moved {from} → {to}"),
+            BoardChange::PriorityChanged { task_id, from, to } => write!(f, "{task_id} priority {from} → {to}"),
+            BoardChange::TitleChanged { task_id, to, .. } => write!(f, "{task_id} retitled {to:?}"),
+            BoardChange::NewUnread { task_id, directive_ids } => {
+                write!(f, "{task_id} has {} new directive(s)", directive_ids.len())
+            }
+        }
+    }
+}
+
+/// What changed between two folds, from [`diff_boards`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardDiff {
+    /// `generated_at` of the old and new boards.
+    pub from: String,
+    pub to: String,
+    /// Ordered by task id, and for each task in the order of [`BoardChange`]'s variants.
+    pub changes: Vec<BoardChange>,
+}
+
+impl BoardDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// A one-line summary, e.g. `T1 moved doing → done; T2 added to todo`.
+impl std::fmt::Display for BoardDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.changes.is_empty() {
+            return f.write_str("no changes");
+        }
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn diff_boards(old: &Board, new: &Board) -> BoardDiff {
+    let task_ids: BTreeSet<&String> = old.cards.keys().chain(new.cards.keys()).collect();
+    let mut changes = vec![];
+    for task_id in task_ids {
+        let (before, after) = match (old.cards.get(task_id), new.cards.get(task_id)) {
+            (None, Some(card)) => {
+                changes.push(BoardChange::Added {
+                    task_id: task_id.clone(),
+                    title: card.title.clone(),
+                    status: card.status.clone(),
+                });
+                (None, card)
+            }
+            (Some(card), None) => {
+                changes.push(BoardChange::Removed { task_id: task_id.clone(), title: card.title.clone() });
+                continue;
+            }
+            (Some(before), Some(after)) => (Some(before), after),
+            (None, None) => continue,
+        };
+        if let Some(before) = before {
+            let changed = |from: &String, to: &String| (from != to).then(|| (from.clone(), to.clone()));
+            if let Some((from, to)) = changed(&before.status, &after.status) {
+                changes.push(BoardChange::StatusChanged { task_id: task_id.clone(), from, to });
+            }
+            if let Some((from, to)) = changed(&before.priority, &after.priority) {
+                changes.push(BoardChange::PriorityChanged { task_id: task_id.clone(), from, to });
+            }
+            if let Some((from, to)) = changed(&before.title, &after.title) {
+                changes.push(BoardChange::TitleChanged { task_id: task_id.clone(), from, to });
+            }
+        }
+        let was_unread: HashSet<&String> = old.unread_directives.get(task_id).into_iter().flatten().collect();
+        let directive_ids: Vec<String> = new
+            .unread_directives
+            .get(task_id)
+            .into_iter()
+            .flatten()
+            .filter(|id| !was_unread.contains(id))
+            .cloned()
+            .collect();
+        if !directive_ids.is_empty() {
+            changes.push(BoardChange::NewUnread { task_id: task_id.clone(), directive_ids });
+        }
+    }
+    BoardDiff { from: old.generated_at.clone(), to: new.generated_at.clone(), changes }
+}
+
+/// Writes `state/last_diff.json` and returns its path.
+pub fn write_diff(root: impl AsRef<Path>, diff: &BoardDiff) -> Result<PathBuf> {
+    let p = paths_for(root);
+    write_json_pretty(&p.last_diff, &serde_json::to_value(diff)?)?;
+    Ok(p.last_diff)
+}
+
+pub fn read_jsonl_values(path: &Path) -> Result<Vec<Value>> {
+    if !path.exists() {
         return Ok(vec![]);
     }
     let file = fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
@@ -529,3 +2859,356 @@ pub fn read_jsonl_values(path: &Path) -> Result<Vec<Value>> {
     }
     Ok(out)
 }
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Also match tasks whose ledger records mention the query in their
+    /// `claim` or `action`.
+    pub ledger_text: bool,
+}
+
+/// [`search_with`] over the board alone.
+pub fn search(root: impl AsRef<Path>, query: &str) -> Result<Vec<CardOut>> {
+    search_with(root, query, &SearchOptions::default())
+}
+
+/// Cards whose id, title, tags or latest note contain `query`, ignoring case,
+/// highest priority first and then most recently updated.
+pub fn search_with(root: impl AsRef<Path>, query: &str, options: &SearchOptions) -> Result<Vec<CardOut>> {
+    let root = root.as_ref();
+    let needle = query.trim().to_lowercase();
+    let board = fold(root)?;
+    let config = load_config(root).unwrap_or_default();
+
+    let mut mentioned: HashSet<String> = HashSet::new();
+    if options.ledger_text {
+        let ledger = paths_for(root).ledger;
+        read_jsonl_from(&ledger, &mut JsonlCursor::default(), |_, rec| {
+            let Ok((_, rec)) = rec else {
+                return;
+            };
+            let Some(task_id) = rec.get("task_id").and_then(|v| v.as_str()) else {
+                return;
+            };
+            let text = |k: &str| rec.get(k).and_then(|v| v.as_str()).unwrap_or("");
+            if !mentioned.contains(task_id) && (contains_folded(text("claim"), &needle) || contains_folded(text("action"), &needle)) {
+                mentioned.insert(task_id.to_string());
+            }
+        })?;
+    }
+
+    let mut found: Vec<CardOut> = board
+        .cards
+        .into_values()
+        .filter(|c| {
+            contains_folded(&c.task_id, &needle)
+                || contains_folded(&c.title, &needle)
+                || c.tags.iter().any(|t| contains_folded(t, &needle))
+                || c.notes.last().is_some_and(|n| contains_folded(&n.text, &needle))
+                || mentioned.contains(&c.task_id)
+        })
+        .collect();
+    found.sort_by(|a, b| {
+        (config.priority_rank(&b.priority), &b.updated_at, b.updated_seq, &a.task_id).cmp(&(
+            config.priority_rank(&a.priority),
+            &a.updated_at,
+            a.updated_seq,
+            &b.task_id,
+        ))
+    });
+    Ok(found)
+}
+
+// Whether `haystack` contains `needle`, which is already lowercase. ASCII text,
+// by far the common case, is compared in place without allocating.
+fn contains_folded(haystack: &str, needle: &str) -> bool {
+    if needle.is_empty() {
+        return true;
+    }
+    if haystack.is_ascii() && needle.is_ascii() {
+        let (h, n) = (haystack.as_bytes(), needle.as_bytes());
+        return h.windows(n.len()).any(|w| w.eq_ignore_ascii_case(n));
+    }
+    haystack.to_lowercase().contains(needle)
+}
+
+/// What [`archive`] moves out of the live logs.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveOptions {
+    /// Tasks that are done or rejected and were closed (or, without a close
+    /// record, last changed) at least this many days ago.
+    pub older_than_days: u32,
+    /// Work out what would move without writing anything.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArchiveReport {
+    pub task_ids: Vec<String>,
+    pub ledger_records: usize,
+    pub control_records: usize,
+    pub ledger_archive: PathBuf,
+    pub control_archive: PathBuf,
+}
+
+/// Moves every ledger and control record of old closed tasks to
+/// `.isnad/archive/{ledger,control}-<date>.jsonl`. The archives are appended
+/// to and synced before the live files are replaced (by rename), so a crash
+/// leaves records in both places rather than neither. Putting the archives back
+/// in front of the live files restores the tasks. [`append_record`] waits
+/// while the logs are rewritten, and lines appended some other way meanwhile
+/// are picked up before the rename. The fold cache and cursors are reset,
+/// since their offsets mean nothing in the new files.
+pub fn archive(root: impl AsRef<Path>, options: &ArchiveOptions) -> Result<ArchiveReport> {
+    let root = root.as_ref();
+    let p = paths_for(root);
+    let now = Utc::now();
+    let cutoff = now - chrono::Duration::days(options.older_than_days.into());
+
+    let board = fold_with(root, &FoldOptions { no_cache: true, ..FoldOptions::default() })?;
+    let mut task_ids: Vec<String> = board
+        .cards
+        .values()
+        .filter(|c| is_closed(&c.status))
+        .filter(|c| {
+            let since = c.closed_at.as_deref().unwrap_or(&c.updated_at);
+            DateTime::parse_from_rfc3339(since).is_ok_and(|at| at <= cutoff)
+        })
+        .map(|c| c.task_id.clone())
+        .collect();
+    task_ids.sort();
+
+    let archive_dir = p.isnad_dir.join("archive");
+    let day = now.format("%Y-%m-%d");
+    let mut report = ArchiveReport {
+        ledger_archive: archive_dir.join(format!("ledger-{day}.jsonl")),
+        control_archive: archive_dir.join(format!("control-{day}.jsonl")),
+        ..ArchiveReport::default()
+    };
+    if task_ids.is_empty() {
+        return Ok(report);
+    }
+    let archived: HashSet<&str> = task_ids.iter().map(String::as_str).collect();
+
+    // Appends from this process, then from every process, wait until the
+    // files are replaced. A dry run writes nothing, the lock file included.
+    let _guard = APPEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut lock = if options.dry_run { None } else { Some(lock_sequence(&p)?) };
+    let mut ledger = SplitLines::default();
+    let mut control = SplitLines::default();
+    split_task_lines(&p.ledger, &archived, &mut ledger)?;
+    split_task_lines(&p.control, &archived, &mut control)?;
+
+    if let Some(lock) = &mut lock {
+        ensure_dir(&archive_dir)?;
+        append_synced(&report.ledger_archive, &ledger.moved)?;
+        append_synced(&report.control_archive, &control.moved)?;
+        // Lines appended without the lock meanwhile go along too.
+        let synced = (ledger.moved.len(), control.moved.len());
+        split_task_lines(&p.ledger, &archived, &mut ledger)?;
+        split_task_lines(&p.control, &archived, &mut control)?;
+        append_synced(&report.ledger_archive, &ledger.moved[synced.0..])?;
+        append_synced(&report.control_archive, &control.moved[synced.1..])?;
+        replace_file(&p.ledger, &ledger.kept)?;
+        replace_file(&p.control, &control.kept)?;
+
+        // Offsets into the old files mean nothing now. The counter keeps its
+        // last `_seq` and rescans the new files from the start.
+        let _ = fs::remove_file(&p.fold_cache);
+        if let Some(mut cursors) = fs::read(&p.cursors).ok().and_then(|b| serde_json::from_slice::<Value>(&b).ok()) {
+            if let Some(obj) = cursors.as_object_mut() {
+                obj.insert("folded_ledger_bytes".to_string(), 0.into());
+                obj.insert("folded_control_bytes".to_string(), 0.into());
+                write_atomic(&p.cursors, format!("{}\n", serde_json::to_string_pretty(&cursors)?).as_bytes())?;
+            }
+        }
+        let counter = read_counter(&p, lock)?;
+        write_counter(&p, lock, &SeqCounter { last: counter.last, ..SeqCounter::default() })?;
+    }
+    report.ledger_records = ledger.moved_count;
+    report.control_records = control.moved_count;
+    report.task_ids = task_ids;
+    Ok(report)
+}
+
+#[derive(Default)]
+struct SplitLines {
+    kept: Vec<u8>,
+    moved: Vec<u8>,
+    moved_count: usize,
+    /// How much of the file has been split.
+    read: u64,
+}
+
+// Splits what `path` has past `out.read` into `out`. Lines are moved byte for
+// byte; anything that isn't a record of one of `task_ids` stays, including
+// lines that don't parse.
+fn split_task_lines(path: &Path, task_ids: &HashSet<&str>, out: &mut SplitLines) -> Result<()> {
+    let mut bytes = Vec::new();
+    match fs::File::open(path) {
+        Ok(mut f) => {
+            f.seek(SeekFrom::Start(out.read))
+                .and_then(|_| f.read_to_end(&mut bytes))
+                .with_context(|| format!("read {}", path.display()))?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+    }
+    out.read += bytes.len() as u64;
+    for line in bytes.split_inclusive(|b| *b == b'\n') {
+        let task_id = serde_json::from_slice::<Value>(line)
+            .ok()
+            .and_then(|v| v.get("task_id").and_then(|t| t.as_str()).map(str::to_string));
+        if task_id.is_some_and(|t| task_ids.contains(t.as_str())) {
+            out.moved.extend_from_slice(line);
+            out.moved_count += 1;
+        } else {
+            out.kept.extend_from_slice(line);
+        }
+    }
+    Ok(())
+}
+
+fn append_synced(path: &Path, bytes: &[u8]) -> Result<()> {
+    if bytes.is_empty() {
+        return Ok(());
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    file.write_all(bytes).with_context(|| format!("append {}", path.display()))?;
+    file.sync_all().with_context(|| format!("sync {}", path.display()))
+}
+
+fn replace_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("jsonl.tmp");
+    let mut file = fs::File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+    file.write_all(bytes).with_context(|| format!("write {}", tmp.display()))?;
+    file.sync_all().with_context(|| format!("sync {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))
+}
+
+/// What [`merge_jsonl`] did with each line of the source log.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MergeReport {
+    pub appended: usize,
+    /// Already in the destination, byte for byte or as the same JSON.
+    pub skipped: usize,
+    /// Records whose id is in the destination with a different body; they go
+    /// to `conflicts.jsonl` beside it.
+    pub conflicting: usize,
+    /// Lines that aren't JSON objects, left out.
+    pub unreadable: usize,
+}
+
+/// Appends to `dst` every record of `src` that it doesn't already have, in
+/// `src`'s order. Records are the same when their `id`s are (or, without an
+/// id, their whole bodies), `_seq` aside, and are copied byte for byte: their
+/// `_seq`s come along, and a fold places them among `dst`'s by those. A record whose id
+/// `dst` already has with another body is written to `conflicts.jsonl` in
+/// `dst`'s directory, once, for someone to sort out. Records archived out of
+/// `dst` count as missing, so merging from a log that still has them brings
+/// them back.
+pub fn merge_jsonl(dst: &Path, src: &Path) -> Result<MergeReport> {
+    let src_bytes = fs::read(src).with_context(|| format!("read {}", src.display()))?;
+    let conflicts_path = dst.with_file_name("conflicts.jsonl");
+    let file_name = dst.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+
+    // Nothing in this process appends to `dst` between reading and writing it.
+    let _guard = APPEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dst_bytes = match fs::read(dst) {
+        Ok(b) => b,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e).with_context(|| format!("read {}", dst.display())),
+    };
+    let mut by_id: HashMap<String, Value> = HashMap::new();
+    let mut bodies: HashSet<String> = HashSet::new();
+    for line in dst_bytes.split(|b| *b == b'\n') {
+        if let Ok(rec @ Value::Object(_)) = serde_json::from_slice::<Value>(line) {
+            remember_record(&mut by_id, &mut bodies, without_seq(rec));
+        }
+    }
+    let mut logged: HashSet<String> = HashSet::new();
+    if let Ok(raw) = fs::read(&conflicts_path) {
+        for line in raw.split(|b| *b == b'\n') {
+            if let Ok(conflict) = serde_json::from_slice::<Value>(line) {
+                logged.insert(conflict_key(&conflict["file"], &without_seq(conflict["record"].clone())));
+            }
+        }
+    }
+
+    let mut report = MergeReport::default();
+    let mut appended: Vec<u8> = Vec::new();
+    let mut conflicts: Vec<u8> = Vec::new();
+    for line in src_bytes.split_inclusive(|b| *b == b'\n') {
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        let rec = match serde_json::from_slice::<Value>(line) {
+            Ok(rec @ Value::Object(_)) => rec,
+            _ => {
+                report.unreadable += 1;
+                continue;
+            }
+        };
+        let body = without_seq(rec.clone());
+        match rec.get("id").and_then(|v| v.as_str()).filter(|id| !id.is_empty()) {
+            Some(id) if by_id.get(id).is_some_and(|kept| *kept != body) => {
+                report.conflicting += 1;
+                let file = Value::String(file_name.clone());
+                if logged.insert(conflict_key(&file, &body)) {
+                    let conflict = serde_json::json!({
+                        "ts": utc_now(),
+                        "file": file,
+                        "id": id,
+                        "from": src.display().to_string(),
+                        "record": rec,
+                    });
+                    conflicts.extend_from_slice(format!("{conflict}\n").as_bytes());
+                }
+            }
+            Some(id) if by_id.contains_key(id) => report.skipped += 1,
+            None if bodies.contains(&body.to_string()) => report.skipped += 1,
+            _ => {
+                report.appended += 1;
+                appended.extend_from_slice(line.trim_ascii_end());
+                appended.push(b'\n');
+                remember_record(&mut by_id, &mut bodies, body);
+            }
+        }
+    }
+
+    // A last line cut short mustn't swallow the first appended record.
+    if !appended.is_empty() && dst_bytes.last().is_some_and(|b| *b != b'\n') {
+        appended.insert(0, b'\n');
+    }
+    append_synced(&conflicts_path, &conflicts)?;
+    append_synced(dst, &appended)?;
+    Ok(report)
+}
+
+fn remember_record(by_id: &mut HashMap<String, Value>, bodies: &mut HashSet<String>, rec: Value) {
+    match rec.get("id").and_then(|v| v.as_str()).filter(|id| !id.is_empty()) {
+        Some(id) => {
+            by_id.entry(id.to_string()).or_insert(rec);
+        }
+        None => {
+            bodies.insert(rec.to_string());
+        }
+    }
+}
+
+// Each log numbers its records itself, so the same record can carry
+// different `_seq`s in two of them.
+fn without_seq(mut rec: Value) -> Value {
+    if let Some(obj) = rec.as_object_mut() {
+        obj.remove("_seq");
+    }
+    rec
+}
+
+fn conflict_key(file: &Value, record: &Value) -> String {
+    format!("{file}\n{record}")
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use isnad::{WorkspaceRoot, WriteOptions};
+use serde_json::{json, Value};
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    for rec in records {
+        writeln!(file, "{rec}").unwrap();
+    }
+}
+
+fn opened(task_id: &str, title: &str) -> Value {
+    json!({ "id": format!("L-{task_id}"), "type": "task_opened", "ts": "2020-01-01T10:00:00.000Z", "task_id": task_id, "meta": { "title": title } })
+}
+
+fn directive(id: &str, kind: &str, task_id: &str, payload: Value) -> Value {
+    json!({ "id": id, "type": kind, "ts": "2020-01-01T11:00:00.000Z", "task_id": task_id, "payload": payload })
+}
+
+// Two workspaces, `api` and `web`, that both use the task id T1.
+fn workspaces(parent: &Path) -> Vec<PathBuf> {
+    let (api, web) = (parent.join("api"), parent.join("web"));
+    for root in [&api, &web] {
+        fs::create_dir(root).unwrap();
+        isnad::scaffold(root, false).unwrap();
+    }
+    append(&isnad::paths_for(&api).ledger, [opened("T1", "api task"), opened("T2", "api follow-up")]);
+    append(
+        &isnad::paths_for(&api).control,
+        [directive("D1", "link_tasks", "T1", json!({ "blocks": "T2" })), directive("D2", "set_status", "T2", json!({ "status": "doing" }))],
+    );
+    append(&isnad::paths_for(&web).ledger, [opened("T1", "web task")]);
+    append(&isnad::paths_for(&web).control, [directive("D1", "set_status", "T1", json!({ "status": "doing" })), directive("D2", "set_status", "T9", json!({ "status": "doing" }))]);
+    vec![api, web]
+}
+
+#[test]
+fn fold_many_prefixes_task_ids_by_workspace() {
+    let dir = tempfile::tempdir().unwrap();
+    let roots = workspaces(dir.path());
+    let board = isnad::fold_many(&roots).unwrap();
+
+    let mut ids: Vec<&str> = board.cards.keys().map(String::as_str).collect();
+    ids.sort();
+    assert_eq!(ids, ["api:T1", "api:T2", "web:T1", "web:T9"]);
+    assert_eq!(board.cards["api:T1"].title, "api task");
+    assert_eq!(board.cards["api:T1"].workspace, "api");
+    assert_eq!(board.cards["api:T1"].blocks, ["api:T2"]);
+    assert_eq!(board.cards["api:T2"].blocked_by, ["api:T1"]);
+    assert_eq!(board.unread_directives["web:T1"], ["D1"]);
+    assert_eq!(board.workspaces["web"], roots[1]);
+    assert_eq!(board.warnings.len(), 1);
+    assert!(board.warnings[0].starts_with("web: .isnad/control.jsonl:2: "), "{}", board.warnings[0]);
+
+    let md = isnad::render_markdown(&board);
+    let doing = md.split("## Doing\n").nth(1).unwrap().split("\n\n").next().unwrap();
+    assert_eq!(
+        doing.lines().filter(|l| l.starts_with("###")).collect::<Vec<_>>(),
+        ["### api", "### web"]
+    );
+    assert!(doing.starts_with("### api\n- [api:T2] api follow-up (waiting on api:T1)"), "{doing}");
+}
+
+#[test]
+fn labels_can_be_overridden_and_must_be_unique() {
+    let dir = tempfile::tempdir().unwrap();
+    let roots = workspaces(dir.path());
+
+    let labeled = [
+        WorkspaceRoot { root: roots[0].clone(), label: "backend".into() },
+        WorkspaceRoot::new(&roots[1]),
+    ];
+    let board = isnad::fold_workspaces(&labeled).unwrap();
+    assert!(board.cards.contains_key("backend:T1") && board.cards.contains_key("web:T1"));
+
+    let clash = [WorkspaceRoot::new(&roots[0]), WorkspaceRoot { root: roots[1].clone(), label: "api".into() }];
+    assert!(isnad::fold_workspaces(&clash).is_err());
+}
+
+#[test]
+fn aggregate_state_is_written_outside_the_workspaces() {
+    let dir = tempfile::tempdir().unwrap();
+    let roots = workspaces(dir.path());
+    let board = isnad::fold_many(&roots).unwrap();
+    let member_state = || -> Vec<String> {
+        roots.iter().map(|r| fs::read_to_string(isnad::paths_for(r).board_json).unwrap()).collect()
+    };
+    let before = member_state();
+
+    let out = dir.path().join("combined");
+    let (json_path, md_path) = isnad::write_aggregate_state(&out, &board, &WriteOptions::default()).unwrap();
+    assert_eq!(json_path, out.canonicalize().unwrap().join("board.json"));
+    assert!(md_path.exists());
+    assert_eq!(member_state(), before);
+
+    for inside in [roots[0].clone(), roots[1].join(".isnad/state"), roots[1].join("new/dir")] {
+        assert!(isnad::write_aggregate_state(&inside, &board, &WriteOptions::default()).is_err(), "{}", inside.display());
+    }
+    assert!(!roots[1].join("new").exists());
+    assert!(!roots[0].join("board.json").exists());
+    assert_eq!(member_state(), before);
+}
+
+#[test]
+fn folding_members_writes_nothing_to_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let roots = workspaces(dir.path());
+    let state = |root: &Path| -> Vec<(PathBuf, Vec<u8>)> {
+        let mut files: Vec<_> = fs::read_dir(isnad::paths_for(root).state_dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.is_file())
+            .map(|p| (p.clone(), fs::read(&p).unwrap()))
+            .collect();
+        files.sort();
+        files
+    };
+    let before: Vec<_> = roots.iter().map(|r| state(r)).collect();
+
+    isnad::fold_many(&roots).unwrap();
+    assert_eq!(roots.iter().map(|r| state(r)).collect::<Vec<_>>(), before);
+    assert!(!isnad::paths_for(&roots[0]).fold_cache.exists());
+}
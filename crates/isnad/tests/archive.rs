@@ -0,0 +1,121 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+const LONG_AGO: &str = "2020-01-01T00:00:00Z";
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    for rec in records {
+        writeln!(file, "{rec}").unwrap();
+    }
+}
+
+fn directive(id: &str, ts: &str, kind: &str, task_id: &str, payload: Value) -> Value {
+    json!({ "id": id, "type": kind, "ts": ts, "task_id": task_id, "payload": payload })
+}
+
+// A card as the board shows it, less the fold-order sequence, which counts
+// records and so shifts when some are moved out, and the attribution that
+// follows it.
+fn card(board: &isnad::Board, task_id: &str) -> Value {
+    let mut v = serde_json::to_value(&board.cards[task_id]).unwrap();
+    for field in ["updated_seq", "updated_by", "updated_via"] {
+        v.as_object_mut().unwrap().remove(field);
+    }
+    v
+}
+
+fn workspace() -> (tempfile::TempDir, isnad::Paths) {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let now = isnad::utc_now();
+    append(
+        &p.ledger,
+        [
+            json!({ "type": "task_opened", "ts": LONG_AGO, "task_id": "T1", "meta": { "title": "old and done" } }),
+            json!({ "type": "task_opened", "ts": LONG_AGO, "task_id": "T4", "meta": { "title": "old but open" } }),
+            json!({ "type": "ack_directive", "ts": LONG_AGO, "task_id": "T1", "meta": { "directive_id": "D1" } }),
+        ],
+    );
+    append(
+        &p.control,
+        [
+            directive("D1", LONG_AGO, "set_status", "T1", json!({ "status": "done" })),
+            directive("D2", LONG_AGO, "open_task", "T2", json!({ "title": "old and rejected", "status": "rejected", "tags": ["v1"] })),
+            directive("D3", &now, "open_task", "T3", json!({ "title": "just done", "status": "done" })),
+            directive("D4", LONG_AGO, "link_tasks", "T4", json!({ "blocks": "T1" })),
+            directive("D5", LONG_AGO, "set_priority", "T2", json!({ "priority": "low" })),
+        ],
+    );
+    (dir, p)
+}
+
+#[test]
+fn dry_runs_only_report() {
+    let (dir, p) = workspace();
+    let before = (fs::read(&p.ledger).unwrap(), fs::read(&p.control).unwrap());
+
+    let report = isnad::archive(dir.path(), &isnad::ArchiveOptions { older_than_days: 30, dry_run: true }).unwrap();
+    assert_eq!(report.task_ids, ["T1", "T2"]);
+    assert_eq!((report.ledger_records, report.control_records), (2, 3));
+    assert_eq!((fs::read(&p.ledger).unwrap(), fs::read(&p.control).unwrap()), before);
+    assert!(!p.isnad_dir.join("archive").exists());
+}
+
+#[test]
+fn archived_tasks_leave_the_rest_of_the_board_alone_and_can_be_restored() {
+    let (dir, p) = workspace();
+    let before = isnad::fold(dir.path()).unwrap();
+
+    let report = isnad::archive(dir.path(), &isnad::ArchiveOptions { older_than_days: 30, dry_run: false }).unwrap();
+    assert_eq!(report.task_ids, ["T1", "T2"]);
+
+    let after = isnad::fold(dir.path()).unwrap();
+    let mut remaining: Vec<&String> = after.cards.keys().collect();
+    remaining.sort();
+    assert_eq!(remaining, ["T3", "T4"]);
+    assert_eq!(card(&after, "T3"), card(&before, "T3"));
+    assert_eq!(card(&after, "T4"), card(&before, "T4"));
+    assert!(fs::read_to_string(&p.control).unwrap().contains("\"D4\""));
+
+    // Nothing left that's old enough.
+    let again = isnad::archive(dir.path(), &isnad::ArchiveOptions { older_than_days: 30, dry_run: false }).unwrap();
+    assert!(again.task_ids.is_empty());
+
+    let restored = tempfile::tempdir().unwrap();
+    let r = isnad::scaffold(restored.path(), false).unwrap();
+    for (archived, live, into) in
+        [(&report.ledger_archive, &p.ledger, &r.ledger), (&report.control_archive, &p.control, &r.control)]
+    {
+        let mut bytes = fs::read(archived).unwrap();
+        bytes.extend(fs::read(live).unwrap());
+        fs::write(into, bytes).unwrap();
+    }
+    let restored = isnad::fold(restored.path()).unwrap();
+    for task_id in ["T1", "T2", "T3", "T4"] {
+        assert_eq!(card(&restored, task_id), card(&before, task_id), "{task_id}");
+    }
+}
+
+#[test]
+fn appends_during_an_archive_are_kept() {
+    let (dir, p) = workspace();
+    let note = |i: usize| json!({ "id": format!("N{i}"), "type": "note", "ts": isnad::utc_now(), "task_id": "T4", "claim": "still here" });
+
+    let last = std::thread::scope(|s| {
+        let appender = s.spawn(|| (0..50).map(|i| isnad::append_record(&p, &p.ledger, &note(i)).unwrap()).max());
+        isnad::archive(dir.path(), &isnad::ArchiveOptions { older_than_days: 30, dry_run: false }).unwrap();
+        appender.join().unwrap().unwrap()
+    });
+    let ledger = fs::read_to_string(&p.ledger).unwrap();
+    assert!((0..50).all(|i| ledger.contains(&format!("\"N{i}\""))));
+    assert!(!ledger.contains("\"T1\""));
+
+    // The fold starts over on the new files, and `_seq` carries on past them.
+    assert!(!p.fold_cache.exists());
+    assert_eq!(isnad::fold(dir.path()).unwrap().cards["T4"].notes.len(), 50);
+    assert_eq!(isnad::append_record(&p, &p.ledger, &note(50)).unwrap(), last + 1);
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use isnad::BoardChange;
+use serde_json::{json, Value};
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    for rec in records {
+        writeln!(file, "{rec}").unwrap();
+    }
+}
+
+fn opened(task_id: &str, title: &str) -> Value {
+    json!({ "id": format!("L-{task_id}-{title}"), "type": "task_opened", "ts": "2020-01-01T10:00:00.000Z", "task_id": task_id, "meta": { "title": title } })
+}
+
+fn directive(id: &str, kind: &str, task_id: &str, payload: Value) -> Value {
+    json!({ "id": id, "type": kind, "ts": "2020-01-01T11:00:00.000Z", "task_id": task_id, "payload": payload })
+}
+
+fn ack(directive_id: &str) -> Value {
+    json!({ "id": format!("L-ack-{directive_id}"), "type": "ack_directive", "ts": "2020-01-01T12:00:00.000Z", "meta": { "directive_id": directive_id } })
+}
+
+#[test]
+fn diff_reports_each_kind_of_change_by_task() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "first"), opened("T2", "second")]);
+    append(&p.control, [directive("D1", "set_status", "T1", json!({ "status": "doing" }))]);
+    let old = isnad::fold(dir.path()).unwrap();
+
+    append(&p.ledger, [opened("T3", "third"), ack("D1")]);
+    append(
+        &p.control,
+        [
+            directive("D2", "set_status", "T1", json!({ "status": "done" })),
+            directive("D3", "set_priority", "T1", json!({ "priority": "high" })),
+            directive("D4", "note", "T2", json!({ "text": "hello" })),
+        ],
+    );
+    let mut new = isnad::fold(dir.path()).unwrap();
+    new.cards.get_mut("T2").unwrap().title = "second, renamed".to_string();
+
+    let diff = isnad::diff_boards(&old, &new);
+    assert_eq!(
+        diff.changes,
+        [
+            BoardChange::StatusChanged { task_id: "T1".into(), from: "doing".into(), to: "done".into() },
+            BoardChange::PriorityChanged { task_id: "T1".into(), from: "medium".into(), to: "high".into() },
+            BoardChange::NewUnread { task_id: "T1".into(), directive_ids: vec!["D2".into(), "D3".into()] },
+            BoardChange::TitleChanged { task_id: "T2".into(), from: "second".into(), to: "second, renamed".into() },
+            BoardChange::NewUnread { task_id: "T2".into(), directive_ids: vec!["D4".into()] },
+            BoardChange::Added { task_id: "T3".into(), title: "third".into(), status: "backlog".into() },
+        ]
+    );
+    assert_eq!(
+        diff.to_string(),
+        "T1 moved doing → done; T1 priority medium → high; T1 has 2 new directive(s); \
+         T2 retitled \"second, renamed\"; T2 has 1 new directive(s); T3 added to backlog"
+    );
+
+    let back = isnad::diff_boards(&new, &old);
+    assert!(back.changes.contains(&BoardChange::Removed { task_id: "T3".into(), title: "third".into() }));
+    assert!(isnad::diff_boards(&new, &new).is_empty());
+}
+
+#[test]
+fn diff_round_trips_through_last_diff_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let old = isnad::fold(dir.path()).unwrap();
+    append(&p.ledger, [opened("T1", "first")]);
+    let new = isnad::fold(dir.path()).unwrap();
+
+    let diff = isnad::diff_boards(&old, &new);
+    let path = isnad::write_diff(dir.path(), &diff).unwrap();
+    assert_eq!(path, p.last_diff);
+
+    let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(written["changes"], json!([{ "kind": "added", "task_id": "T1", "title": "first", "status": "backlog" }]));
+    let read: isnad::BoardDiff = serde_json::from_value(written).unwrap();
+    assert_eq!(read.changes, diff.changes);
+}
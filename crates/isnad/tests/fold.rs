@@ -0,0 +1,1017 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    let mut out = std::io::BufWriter::new(file);
+    for rec in records {
+        writeln!(out, "{rec}").unwrap();
+    }
+    out.flush().unwrap();
+}
+
+fn opened(task_id: &str, title: &str) -> Value {
+    json!({ "type": "task_opened", "ts": "2026-10-16T12:00:00Z", "task_id": task_id, "meta": { "title": title } })
+}
+
+fn snapshot(i: usize) -> Value {
+    json!({ "id": format!("S{i:06}"), "type": "snapshot", "ts": "2026-10-16T12:00:01Z", "task_id": format!("T{}", i % 100) })
+}
+
+fn folded_bytes(root: &Path) -> (u64, u64) {
+    let cursors: Value = serde_json::from_slice(&fs::read(isnad::paths_for(root).cursors).unwrap()).unwrap();
+    (
+        cursors["folded_ledger_bytes"].as_u64().unwrap(),
+        cursors["folded_control_bytes"].as_u64().unwrap(),
+    )
+}
+
+// Everything but the timestamp of the fold itself.
+fn comparable(board: &isnad::Board) -> Value {
+    let mut v = serde_json::to_value(board).unwrap();
+    v.as_object_mut().unwrap().remove("generated_at");
+    v
+}
+
+fn full_fold(root: &Path) -> isnad::Board {
+    let _ = fs::remove_file(isnad::paths_for(root).fold_cache);
+    isnad::fold(root).unwrap()
+}
+
+#[test]
+fn second_fold_reads_only_what_was_appended() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, (0..100).map(|i| opened(&format!("T{i}"), "alpha")));
+
+    isnad::fold(dir.path()).unwrap();
+    let folded = fs::metadata(&p.ledger).unwrap().len();
+    assert_eq!(folded_bytes(dir.path()), (folded, 0));
+
+    // Same length, different title: only a fold that rereads the start sees it.
+    let ledger = fs::read_to_string(&p.ledger).unwrap().replacen("alpha", "omega", 1);
+    fs::write(&p.ledger, ledger).unwrap();
+    append(&p.ledger, (0..100_000).map(snapshot));
+
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(folded_bytes(dir.path()).0, fs::metadata(&p.ledger).unwrap().len());
+    assert!(board.cards.values().all(|c| c.title == "alpha"));
+    assert_eq!(board.cards["T0"].latest_snapshot_id.as_deref(), Some("S099900"));
+    assert_eq!(board.cards["T99"].latest_snapshot_id.as_deref(), Some("S099999"));
+
+    assert_eq!(full_fold(dir.path()).cards.values().filter(|c| c.title == "omega").count(), 1);
+}
+
+#[test]
+fn incremental_folds_match_a_full_fold() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "first"), opened("T2", "second")]);
+    append(
+        &p.control,
+        [json!({ "id": "D1", "type": "set_status", "ts": "2026-10-16T12:00:02Z", "task_id": "T1", "payload": { "status": "doing" } })],
+    );
+    isnad::fold(dir.path()).unwrap();
+
+    append(
+        &p.ledger,
+        [
+            json!({ "type": "ack_directive", "ts": "2026-10-16T12:00:03Z", "meta": { "directive_id": "D1" } }),
+            json!({ "type": "task_updated", "ts": "2026-10-16T12:00:04Z", "task_id": "T2", "meta": { "title": "renamed" } }),
+        ],
+    );
+    append(
+        &p.control,
+        [
+            json!({ "id": "D2", "type": "set_priority", "ts": "2026-10-16T12:00:05Z", "task_id": "T2", "payload": { "priority": "high" } }),
+            json!({ "id": "D3", "type": "open_task", "ts": "2026-10-16T12:00:06Z", "task_id": "T3", "payload": { "title": "third" } }),
+        ],
+    );
+
+    let incremental = isnad::fold(dir.path()).unwrap();
+    assert_eq!(incremental.cards["T1"].status, "doing");
+    assert_eq!(incremental.cards["T2"].title, "renamed");
+    assert_eq!(incremental.cards["T2"].priority, "high");
+    assert_eq!(incremental.last_ack_directive_id.as_deref(), Some("D1"));
+    assert_eq!(comparable(&incremental), comparable(&full_fold(dir.path())));
+}
+
+#[test]
+fn directives_already_folded_are_not_replayed() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "first")]);
+    append(
+        &p.control,
+        [json!({ "id": "D1", "type": "set_priority", "ts": "2026-10-16T12:00:01Z", "task_id": "T1", "payload": { "priority": "urgent" } })],
+    );
+    isnad::fold(dir.path()).unwrap();
+    assert_eq!(folded_bytes(dir.path()).1, fs::metadata(&p.control).unwrap().len());
+
+    // Same length, different priority: only a fold that replays D1 sees it.
+    let control = fs::read_to_string(&p.control).unwrap().replacen("\"urgent\"", "\"medium\"", 1);
+    fs::write(&p.control, control).unwrap();
+    append(
+        &p.control,
+        [json!({ "id": "D2", "type": "add_tag", "ts": "2026-10-16T12:00:02Z", "task_id": "T1", "payload": { "tag": "ops" } })],
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(folded_bytes(dir.path()).1, fs::metadata(&p.control).unwrap().len());
+    assert_eq!(board.cards["T1"].priority, "urgent");
+    assert_eq!(board.cards["T1"].tags, ["ops"]);
+    assert_eq!(full_fold(dir.path()).cards["T1"].priority, "medium");
+}
+
+#[test]
+fn concurrent_folds_each_save_a_whole_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, (0..200).map(|i| opened(&format!("T{i}"), "alpha")));
+
+    std::thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..5 {
+                    let _ = fs::remove_file(&p.fold_cache);
+                    assert_eq!(isnad::fold(dir.path()).unwrap().cards.len(), 200);
+                }
+            });
+        }
+    });
+    let names: Vec<_> = fs::read_dir(&p.state_dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert!(names.iter().all(|n| !n.to_string_lossy().ends_with(".tmp")), "{names:?}");
+    assert_eq!(isnad::fold(dir.path()).unwrap().cards.len(), 200);
+}
+
+#[test]
+fn shrunk_logs_are_folded_again() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "first"), opened("T2", "second")]);
+    isnad::fold(dir.path()).unwrap();
+
+    fs::write(&p.ledger, format!("{}\n", opened("T9", "fresh"))).unwrap();
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.cards.keys().collect::<Vec<_>>(), ["T9"]);
+    assert_eq!(folded_bytes(dir.path()).0, fs::metadata(&p.ledger).unwrap().len());
+}
+
+#[test]
+fn a_line_still_being_written_waits_for_its_newline() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let complete = fs::metadata(&p.ledger).unwrap().len();
+    let line = opened("T1", "first").to_string();
+    let (head, tail) = line.split_at(20);
+
+    let write = |bytes: &str| {
+        fs::OpenOptions::new().append(true).open(&p.ledger).unwrap().write_all(bytes.as_bytes()).unwrap()
+    };
+
+    write(head);
+    assert!(isnad::fold(dir.path()).unwrap().cards.is_empty());
+    assert_eq!(folded_bytes(dir.path()).0, complete);
+
+    write(&format!("{tail}\n"));
+    assert_eq!(isnad::fold(dir.path()).unwrap().cards["T1"].title, "first");
+}
+
+fn directive(id: &str, kind: &str, task_id: &str, payload: Value) -> Value {
+    json!({ "id": id, "type": kind, "ts": "2026-10-16T12:00:00Z", "task_id": task_id, "payload": payload })
+}
+
+#[test]
+fn both_logs_apply_in_the_order_they_were_appended() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let closed = json!({ "type": "task_closed", "ts": "2026-10-16T12:00:00Z", "task_id": "T1", "meta": { "resolution": "done" } });
+    let seqs = [
+        isnad::append_record(&p, &p.ledger, &opened("T1", "first")).unwrap(),
+        isnad::append_record(&p, &p.control, &directive("D1", "reopen_task", "T1", json!({}))).unwrap(),
+        isnad::append_record(&p, &p.ledger, &closed).unwrap(),
+    ];
+    assert!(seqs[0] < seqs[1] && seqs[1] < seqs[2], "{seqs:?}");
+
+    // The reopen came before the close, so there was nothing to reopen.
+    let report = isnad::fold_report(dir.path(), &isnad::FoldOptions::default()).unwrap();
+    assert_eq!(report.board.cards["T1"].status, "done");
+    assert_eq!(report.board.cards["T1"].updated_seq, seqs[2]);
+    assert_eq!(report.board.warnings, [".isnad/control.jsonl:1: reopen_task ignored: task \"T1\" isn't closed"]);
+    assert_eq!(report.board.unread_directives["T1"], ["D1"]);
+
+    // An ack folded after its directive marks it read.
+    let ack = json!({ "type": "ack_directive", "ts": "2026-10-16T12:00:00Z", "task_id": "T1", "meta": { "directive_id": "D1" } });
+    isnad::append_record(&p, &p.ledger, &ack).unwrap();
+    let board = isnad::fold(dir.path()).unwrap();
+    assert!(board.unread_directives.is_empty());
+    assert_eq!(board.last_ack_control_seq, seqs[1]);
+
+    // A line appended by hand goes right after the record before it in its
+    // log, here ahead of the close.
+    append(&p.control, [directive("D2", "reopen_task", "T1", json!({ "status": "next" }))]);
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.cards["T1"].status, "done");
+    assert_eq!(board.unread_directives["T1"], ["D2"]);
+    assert_eq!(comparable(&board), comparable(&full_fold(dir.path())));
+}
+
+#[test]
+fn resume_restores_the_status_from_before_the_pause() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "first")]);
+    append(
+        &p.control,
+        [
+            directive("D1", "set_status", "T1", json!({ "status": "next" })),
+            directive("D2", "pause", "T1", json!({ "reason": "waiting on design" })),
+            directive("D3", "set_priority", "T1", json!({ "priority": "high" })),
+        ],
+    );
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!((board.cards["T1"].status.as_str(), board.cards["T1"].priority.as_str()), ("blocked", "high"));
+
+    append(&p.control, [directive("D4", "resume", "T1", json!({ "note": "go" }))]);
+    let card = &isnad::fold(dir.path()).unwrap().cards["T1"];
+    assert_eq!((card.status.as_str(), card.priority.as_str()), ("next", "high"));
+    assert_eq!(card.updated_seq, 4);
+
+    // A second resume has no pause to end.
+    append(&p.control, [directive("D5", "resume", "T1", json!({}))]);
+    assert_eq!(isnad::fold(dir.path()).unwrap().cards["T1"].updated_seq, 4);
+}
+
+#[test]
+fn pause_and_resume_cycles() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "first"), opened("T2", "second")]);
+    append(
+        &p.control,
+        [
+            directive("D1", "resume", "T1", json!({})),
+            directive("D2", "set_status", "T1", json!({ "status": "doing" })),
+            directive("D3", "pause", "T1", json!({})),
+            directive("D4", "pause", "T1", json!({})),
+            directive("D5", "resume", "T1", json!({})),
+            directive("D6", "pause", "T2", json!({})),
+            directive("D7", "set_status", "T2", json!({ "status": "next" })),
+            directive("D8", "resume", "T2", json!({})),
+            directive("D9", "pause", "T3", json!({})),
+            directive("D10", "resume", "T3", json!({})),
+        ],
+    );
+    let board = isnad::fold(dir.path()).unwrap();
+    // Resuming before any pause left the default alone; pausing twice still
+    // remembers what came before the first.
+    assert_eq!(board.cards["T1"].status, "doing");
+    // An explicit status ends the pause.
+    assert_eq!(board.cards["T2"].status, "next");
+    assert_eq!(board.cards["T2"].updated_seq, 7);
+    // Paused before it was ever opened: nothing to go back to.
+    assert_eq!(board.cards["T3"].status, "doing");
+}
+
+#[test]
+fn set_assignee_overrides_the_ledger_claim() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(
+        &p.ledger,
+        [
+            json!({ "type": "task_opened", "ts": "2026-10-16T12:00:00Z", "task_id": "T1", "meta": { "title": "first", "assignee": "agent-a" } }),
+            opened("T2", "second"),
+            json!({ "type": "task_updated", "ts": "2026-10-16T12:00:01Z", "task_id": "T2", "meta": { "assignee": "agent-b" } }),
+        ],
+    );
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.cards["T1"].assignee.as_deref(), Some("agent-a"));
+    assert_eq!(board.cards["T2"].assignee.as_deref(), Some("agent-b"));
+    assert!(isnad::render_markdown(&board).contains("- [T1] first @agent-a  (medium)"));
+
+    append(
+        &p.control,
+        [
+            directive("D1", "set_assignee", "T1", json!({ "assignee": "agent-b" })),
+            directive("D2", "set_assignee", "T1", json!({ "assignee": "agent-c" })),
+            directive("D3", "set_assignee", "T2", json!({ "assignee": "" })),
+            directive("D4", "set_assignee", "T2", json!({ "assignee": 7 })),
+        ],
+    );
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.cards["T1"].assignee.as_deref(), Some("agent-c"));
+    assert_eq!(board.cards["T2"].assignee, None);
+    assert!(isnad::render_markdown(&board).contains("- [T2] second  (medium)"));
+}
+
+#[test]
+fn tags_fold_in_order_and_filter_the_board() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let long = "x".repeat(isnad::MAX_TAG_LEN + 1);
+    append(
+        &p.control,
+        [
+            directive("D1", "open_task", "T1", json!({ "title": "first", "tags": ["bug", "bug", "", "two words", long, 5, "infra"] })),
+            directive("D2", "open_task", "T2", json!({ "title": "second", "tags": ["v2"] })),
+            directive("D3", "add_tag", "T1", json!({ "tag": "v2" })),
+            directive("D4", "add_tag", "T1", json!({ "tag": "infra" })),
+            directive("D5", "remove_tag", "T1", json!({ "tag": "bug" })),
+            directive("D6", "add_tag", "T2", json!({ "tag": " " })),
+            directive("D7", "remove_tag", "T2", json!({ "tag": "missing" })),
+            directive("D8", "add_tag", "T1", json!({ "tag": "bug" })),
+        ],
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.cards["T1"].tags, ["infra", "v2", "bug"]);
+    assert_eq!(board.cards["T2"].tags, ["v2"]);
+    assert_eq!(board.cards["T2"].updated_seq, 2);
+    assert!(isnad::render_markdown(&board).contains("  (medium) (unread:5) #infra #v2 #bug\n"));
+
+    let only = |tag: &str| isnad::fold_with(dir.path(), &isnad::FoldOptions { tag: Some(tag.to_string()), ..Default::default() }).unwrap();
+    let v2 = only("v2");
+    assert_eq!(v2.cards.len(), 2);
+    let infra = only("infra");
+    assert_eq!(infra.cards.keys().collect::<Vec<_>>(), ["T1"]);
+    assert_eq!(infra.columns.values().map(Vec::len).sum::<usize>(), 1);
+    assert_eq!(infra.unread_directives.keys().collect::<Vec<_>>(), ["T1"]);
+    assert!(only("nope").cards.is_empty());
+}
+
+#[test]
+fn past_due_cards_are_flagged_and_sorted_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(
+        &p.control,
+        [
+            directive("D1", "open_task", "T1", json!({ "title": "late", "status": "next", "due": "2020-01-01" })),
+            directive("D2", "open_task", "T2", json!({ "title": "fresh", "status": "next", "due": "2999-01-01T09:00:00+02:00" })),
+            directive("D3", "open_task", "T3", json!({ "title": "bad", "status": "next", "due": "next tuesday" })),
+            directive("D4", "open_task", "T4", json!({ "title": "finished", "status": "done", "due": "2020-01-01T00:00:00Z" })),
+            directive("D5", "set_due", "T3", json!({ "due": "2020-13-45" })),
+            directive("D6", "set_due", "T2", json!({ "due": "" })),
+            directive("D7", "open_task", "T5", json!({ "title": "later", "status": "next" })),
+            directive("D8", "set_due", "T5", json!({ "due": "2999-12-31" })),
+        ],
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    let card = |id: &str| (board.cards[id].due_at.as_deref(), board.cards[id].overdue);
+    assert_eq!(card("T1"), (Some("2020-01-01"), true));
+    assert_eq!(card("T2"), (None, false));
+    assert_eq!(card("T3"), (None, false));
+    assert_eq!(card("T4"), (Some("2020-01-01T00:00:00Z"), false));
+    assert_eq!(card("T5"), (Some("2999-12-31"), false));
+
+    // T1 is the oldest card in the column but overdue, so it goes first.
+    let next: Vec<&str> = board.columns["next"].iter().map(|c| c.task_id.as_str()).collect();
+    assert_eq!(next, ["T1", "T5", "T2", "T3"]);
+    assert!(isnad::render_markdown(&board).contains("- [T1] late (provisional) (overdue)  (medium)"));
+}
+
+#[test]
+fn bare_dates_are_due_at_the_end_of_the_day() {
+    let end = isnad::due_deadline("2026-10-16").unwrap();
+    assert_eq!(end.to_rfc3339(), "2026-10-17T00:00:00+00:00");
+    assert_eq!(isnad::due_deadline("2026-10-16T10:00:00-04:00").unwrap().to_rfc3339(), "2026-10-16T14:00:00+00:00");
+    assert!(isnad::due_deadline("16/10/2026").is_none());
+}
+
+#[test]
+fn closing_a_blocker_releases_the_task_it_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "schema"), opened("T2", "migration"), opened("T3", "rollout")]);
+    append(
+        &p.control,
+        [
+            directive("D1", "link_tasks", "T1", json!({ "blocks": "T2" })),
+            directive("D2", "link_tasks", "T2", json!({ "blocks": "T3" })),
+            directive("D3", "link_tasks", "T2", json!({ "blocks": "T3" })),
+            directive("D4", "link_tasks", "T3", json!({ "blocks": "T3" })),
+            directive("D5", "link_tasks", "T3", json!({ "blocks": "not a task" })),
+        ],
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    let waiting = |board: &isnad::Board| -> Vec<bool> {
+        ["T1", "T2", "T3"].iter().map(|t| board.cards[*t].waiting_on_dependencies).collect()
+    };
+    assert_eq!(waiting(&board), [false, true, true]);
+    assert_eq!(board.cards["T2"].blocks, ["T3"]);
+    assert_eq!(board.cards["T2"].blocked_by, ["T1"]);
+    assert!(board.cards["T3"].blocks.is_empty());
+    assert!(board.warnings.is_empty());
+    assert!(isnad::render_markdown(&board).contains("- [T3] rollout (waiting on T2)  (medium)"));
+
+    append(&p.control, [directive("D6", "set_status", "T2", json!({ "status": "done" }))]);
+    assert_eq!(waiting(&isnad::fold(dir.path()).unwrap()), [false, true, false]);
+
+    append(
+        &p.control,
+        [
+            directive("D7", "set_status", "T2", json!({ "status": "doing" })),
+            directive("D8", "unlink_tasks", "T2", json!({ "blocks": "T3" })),
+        ],
+    );
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(waiting(&board), [false, true, false]);
+    assert!(board.cards["T3"].blocked_by.is_empty());
+}
+
+#[test]
+fn dependency_cycles_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("A", "a"), opened("B", "b"), opened("C", "c")]);
+    append(
+        &p.control,
+        [
+            directive("D1", "link_tasks", "A", json!({ "blocks": "B" })),
+            directive("D2", "link_tasks", "B", json!({ "blocks": "C" })),
+            directive("D3", "link_tasks", "C", json!({ "blocks": "A" })),
+            directive("D4", "link_tasks", "C", json!({ "blocks": "D" })),
+        ],
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.warnings, ["dependency cycle: A -> B -> C -> A"]);
+    assert!(["A", "B", "C"].iter().all(|t| board.cards[*t].waiting_on_dependencies));
+    assert_eq!(board.cards["C"].blocks, ["A", "D"]);
+    assert!(isnad::render_markdown(&board).contains("## Warnings\n- dependency cycle: A -> B -> C -> A\n"));
+}
+
+#[test]
+fn clean_logs_fold_without_warnings() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "first")]);
+    append(
+        &p.control,
+        [
+            directive("D1", "open_task", "T2", json!({ "title": "second", "status": "next", "priority": "high" })),
+            directive("D2", "set_status", "T1", json!({ "status": "doing" })),
+            directive("D3", "set_goal", "", json!({ "goal": "ship" })),
+        ],
+    );
+    let report = isnad::fold_report(dir.path(), &isnad::FoldOptions::default()).unwrap();
+    assert_eq!(report.warnings, []);
+    assert!(report.board.warnings.is_empty());
+    assert!(!isnad::render_markdown(&report.board).contains("Warnings"));
+}
+
+#[test]
+fn skipped_lines_and_ignored_directives_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "first")]);
+    let mut ledger = fs::OpenOptions::new().append(true).open(&p.ledger).unwrap();
+    ledger.write_all(b"{\"type\": \"snapshot\", \"task_id\": \n[1, 2]\n\n\xff\xfe\n").unwrap();
+    append(
+        &p.control,
+        [
+            directive("D1", "set_status", "T1", json!({ "status": "finished" })),
+            directive("D2", "set_priority", "T1", json!({})),
+            directive("D3", "frobnicate", "T1", json!({})),
+            directive("D4", "note", "T9", json!({ "text": "hi" })),
+            directive("D5", "open_task", "T2", json!({ "title": "second", "status": "later", "priority": 3 })),
+            json!({ "id": "D6", "type": "pause", "ts": "2026-10-16T12:00:00Z" }),
+        ],
+    );
+
+    let expected = [
+        ".isnad/ledger.jsonl:3: not valid JSON: EOF while parsing a value at line 1 column 31",
+        ".isnad/ledger.jsonl:4: not a JSON object",
+        ".isnad/ledger.jsonl:6: not valid UTF-8",
+        ".isnad/control.jsonl:1: invalid status \"finished\"",
+        ".isnad/control.jsonl:2: missing priority",
+        ".isnad/control.jsonl:3: unknown directive type \"frobnicate\"",
+        ".isnad/control.jsonl:4: note directive refers to task \"T9\", which was never opened",
+        ".isnad/control.jsonl:5: invalid status \"later\"",
+        ".isnad/control.jsonl:5: invalid priority 3",
+        ".isnad/control.jsonl:6: pause directive has no task_id",
+    ];
+    let report = isnad::fold_report(dir.path(), &isnad::FoldOptions::default()).unwrap();
+    let warnings: Vec<String> = report.warnings.iter().map(ToString::to_string).collect();
+    assert_eq!(warnings, expected);
+    assert_eq!(report.warnings[0].line, Some(3));
+    assert_eq!(report.board.warnings, expected);
+    assert!(isnad::render_markdown(&report.board).contains("## Warnings\n- .isnad/ledger.jsonl:3: not valid JSON"));
+
+    // Lines read by an earlier fold are still reported.
+    append(&p.ledger, [opened("T3", "third")]);
+    assert_eq!(isnad::fold(dir.path()).unwrap().warnings, expected);
+}
+
+#[test]
+fn notes_from_both_logs_land_on_the_card() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(
+        &p.ledger,
+        [
+            opened("T1", "first"),
+            json!({ "type": "note", "ts": "2026-10-16T12:00:02Z", "task_id": "T1", "claim": "tests pass locally", "meta": { "actor": "agent-a" } }),
+            json!({ "type": "note", "ts": "2026-10-16T12:00:03Z", "task_id": "T1", "claim": "  " }),
+            json!({ "type": "note", "ts": "2026-10-16T12:00:01Z", "task_id": "T1", "claim": "same second", "meta": { "actor": "agent-a" } }),
+        ],
+    );
+    append(
+        &p.control,
+        [
+            json!({ "id": "D1", "type": "note", "ts": "2026-10-16T12:00:01Z", "task_id": "T1", "author": "sam", "payload": { "text": "check the flaky test" } }),
+            json!({ "id": "D2", "type": "note", "ts": "2026-10-16T12:00:04Z", "task_id": "T1", "payload": { "text": "looks good,\nmerge it" } }),
+        ],
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    let notes: Vec<(&str, &str, &str)> = board.cards["T1"]
+        .notes
+        .iter()
+        .map(|n| (n.author.as_str(), n.source.as_str(), n.text.as_str()))
+        .collect();
+    assert_eq!(
+        notes,
+        [
+            ("agent-a", "ledger", "same second"),
+            ("sam", "control", "check the flaky test"),
+            ("agent-a", "ledger", "tests pass locally"),
+            ("human", "control", "looks good,\nmerge it"),
+        ]
+    );
+    assert_eq!(board.cards["T1"].notes[2].seq, 3);
+    let options = isnad::FoldOptions { stale_after_days: Some(0), ..Default::default() };
+    let board = isnad::fold_with(dir.path(), &options).unwrap();
+    assert!(isnad::render_markdown(&board)
+        .contains("- [T1] first  (medium) (unread:2) by agent-a\n  - human (2026-10-16T12:00:04Z): looks good, merge it\n"));
+}
+
+#[test]
+fn cards_keep_the_latest_notes_and_history_has_them_all() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "first"), opened("T2", "second")]);
+    append(
+        &p.control,
+        (0..60).map(|i| {
+            let ts = format!("2026-10-16T12:{:02}:00Z", i);
+            json!({ "id": format!("D{i}"), "type": "note", "ts": ts, "task_id": "T1", "payload": { "text": format!("note {i}") } })
+        }),
+    );
+    append(&p.control, [directive("D60", "note", "T2", json!({ "text": "elsewhere" }))]);
+
+    let board = isnad::fold(dir.path()).unwrap();
+    let notes = &board.cards["T1"].notes;
+    assert_eq!(notes.len(), isnad::MAX_CARD_NOTES);
+    assert_eq!((notes[0].text.as_str(), notes[49].text.as_str()), ("note 10", "note 59"));
+
+    let history = isnad::task_history(dir.path(), "T1").unwrap().notes;
+    assert_eq!(history.len(), 60);
+    assert_eq!(history[0].text, "note 0");
+    assert!(isnad::task_history(dir.path(), "T3").unwrap().notes.is_empty());
+}
+
+#[test]
+fn closed_tasks_stay_closed_until_reopened() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(
+        &p.ledger,
+        [
+            opened("T1", "agent closes"),
+            opened("T2", "human closes"),
+            json!({ "type": "task_closed", "ts": "2026-10-16T12:00:01Z", "task_id": "T1" }),
+            // A late, buffered reopen from the agent's side.
+            opened("T1", "agent closes"),
+        ],
+    );
+    append(
+        &p.control,
+        [
+            // Buffered before the close was seen.
+            directive("D1", "set_status", "T1", json!({ "status": "doing" })),
+            directive("D2", "close_task", "T2", json!({ "resolution": "rejected" })),
+            directive("D3", "pause", "T2", json!({})),
+            directive("D4", "close_task", "T2", json!({ "resolution": "wontfix" })),
+        ],
+    );
+
+    let report = isnad::fold_report(dir.path(), &isnad::FoldOptions::default()).unwrap();
+    let board = &report.board;
+    assert_eq!(board.cards["T1"].status, "done");
+    assert_eq!(board.cards["T1"].closed_at.as_deref(), Some("2026-10-16T12:00:01Z"));
+    assert_eq!(board.cards["T2"].status, "rejected");
+    assert_eq!(board.cards["T2"].closed_at.as_deref(), Some("2026-10-16T12:00:00Z"));
+    let reasons: Vec<&str> = report.warnings.iter().map(|w| w.reason.as_str()).collect();
+    assert_eq!(
+        reasons,
+        [
+            "set_status ignored: task \"T1\" is closed; send reopen_task first",
+            "pause ignored: task \"T2\" is closed; send reopen_task first",
+            "invalid resolution \"wontfix\"",
+        ]
+    );
+
+    append(
+        &p.control,
+        [
+            directive("D5", "reopen_task", "T1", json!({})),
+            directive("D6", "reopen_task", "T2", json!({ "status": "next" })),
+            directive("D7", "reopen_task", "T2", json!({})),
+            directive("D8", "set_status", "T1", json!({ "status": "blocked" })),
+        ],
+    );
+    let report = isnad::fold_report(dir.path(), &isnad::FoldOptions::default()).unwrap();
+    let board = &report.board;
+    assert_eq!((board.cards["T1"].status.as_str(), board.cards["T1"].closed_at.as_deref()), ("blocked", None));
+    assert_eq!((board.cards["T2"].status.as_str(), board.cards["T2"].closed_at.as_deref()), ("next", None));
+    assert_eq!(report.warnings.last().unwrap().reason, "reopen_task ignored: task \"T2\" isn't closed");
+
+    let history = isnad::task_history(dir.path(), "T2").unwrap();
+    assert_eq!((history.status.as_deref(), history.closed_at.as_deref()), (Some("next"), None));
+}
+
+#[test]
+fn wip_limits_flag_columns_over_their_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, (1..=4).map(|i| opened(&format!("T{i}"), "task")));
+    append(
+        &p.control,
+        (1..=4).map(|i| json!({ "id": format!("D{i}"), "type": "set_status", "ts": "2026-10-16T12:00:02Z", "task_id": format!("T{i}"), "payload": { "status": "doing" } })),
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    assert!(board.wip_violations.is_empty());
+    assert!(!isnad::render_markdown(&board).contains("WIP"));
+
+    fs::write(&p.config, r#"{ "wip_limits": { "doing": 3, "next": 0, "review": 1 } }"#).unwrap();
+    assert_eq!(isnad::load_config(dir.path()).unwrap().wip_limits["doing"], 3);
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.wip_violations, [isnad::WipViolation { status: "doing".into(), limit: 3, count: 4 }]);
+    assert_eq!(board.warnings, [r#".isnad/config.json: wip_limits: unknown status "review""#]);
+    assert!(isnad::render_markdown(&board).contains("> **WIP limit exceeded:** doing has 4 cards (limit 3)\n"));
+}
+
+#[test]
+fn unreadable_config_is_a_warning_not_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "task")]);
+    fs::write(&p.config, r#"{ "wip_limits": { "doing": -1 } }"#).unwrap();
+
+    assert!(isnad::load_config(dir.path()).is_err());
+    let board = isnad::fold(dir.path()).unwrap();
+    assert!(board.cards.contains_key("T1"));
+    assert!(board.wip_violations.is_empty());
+    assert_eq!(board.warnings.len(), 1);
+    assert!(board.warnings[0].starts_with(".isnad/config.json: not a valid config: "), "{}", board.warnings[0]);
+}
+
+#[test]
+fn configured_statuses_and_priorities_replace_the_built_in_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    assert_eq!(isnad::load_config(dir.path()).unwrap(), isnad::Config::default());
+    fs::write(
+        &p.config,
+        r#"{
+            "statuses": ["backlog", "doing", "review", "blocked", "done", "rejected"],
+            "priorities": [{ "name": "medium", "rank": 1 }, { "name": "p0", "rank": 9 }]
+        }"#,
+    )
+    .unwrap();
+    append(&p.ledger, ["T1", "T2", "T3"].map(|t| opened(t, "task")));
+    let directive = |id: &str, kind: &str, task_id: &str, payload: Value| {
+        json!({ "id": id, "type": kind, "ts": "2026-10-16T12:00:02Z", "task_id": task_id, "payload": payload })
+    };
+    append(
+        &p.control,
+        [
+            directive("D1", "set_status", "T1", json!({ "status": "review" })),
+            directive("D2", "set_status", "T2", json!({ "status": "next" })),
+            directive("D3", "set_priority", "T3", json!({ "priority": "p0" })),
+            directive("D4", "set_priority", "T2", json!({ "priority": "urgent" })),
+        ],
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    let order = ["backlog", "doing", "review", "blocked", "done", "rejected"];
+    assert_eq!(board.columns.keys().collect::<Vec<_>>(), order);
+    assert_eq!(board.cards["T1"].status, "review");
+    assert_eq!(board.columns["backlog"].iter().map(|c| c.task_id.as_str()).collect::<Vec<_>>(), ["T3", "T2"]);
+    assert_eq!(
+        board.warnings,
+        [r#".isnad/control.jsonl:2: invalid status "next""#, r#".isnad/control.jsonl:4: invalid priority "urgent""#]
+    );
+
+    let json = serde_json::to_string(&board).unwrap();
+    let at = |status: &str| json.find(&format!("\"{status}\":[")).unwrap();
+    assert!(order.windows(2).all(|w| at(w[0]) < at(w[1])));
+    let md = isnad::render_markdown(&board);
+    let headings: Vec<&str> = md.lines().filter(|l| l.starts_with("## ")).collect();
+    assert_eq!(headings, ["## Backlog", "## Doing", "## Review", "## Blocked", "## Done", "## Rejected", "## Warnings"]);
+}
+
+#[test]
+fn configs_missing_statuses_fold_needs_fall_back_to_the_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "task")]);
+    fs::write(&p.config, r#"{ "statuses": ["todo", "doing", "done"] }"#).unwrap();
+
+    assert!(isnad::load_config(dir.path()).is_err());
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.columns.keys().collect::<Vec<_>>(), isnad::STATUSES);
+    assert_eq!(board.columns["backlog"].len(), 1);
+    assert_eq!(
+        board.warnings,
+        [r#".isnad/config.json: not a valid config: statuses: must include "backlog""#]
+    );
+}
+
+#[test]
+fn open_cards_not_updated_for_days_are_stale() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let now = isnad::utc_now();
+    append(
+        &p.ledger,
+        [
+            json!({ "type": "task_opened", "ts": "2020-01-01T00:00:00Z", "task_id": "old", "meta": { "title": "old" } }),
+            json!({ "type": "task_opened", "ts": now, "task_id": "fresh", "meta": { "title": "fresh" } }),
+            json!({ "type": "task_opened", "ts": "2020-01-01T00:00:00Z", "task_id": "closed", "meta": { "title": "closed" } }),
+            json!({ "type": "task_closed", "ts": "2020-01-02T00:00:00Z", "task_id": "closed" }),
+            json!({ "type": "task_opened", "task_id": "untimed", "meta": { "title": "untimed" } }),
+        ],
+    );
+    // Never opened, so its card has no updated_at; the directive's time stands in.
+    let mut ghost = directive("D1", "reorder", "ghost", json!({ "position": "top" }));
+    ghost["ts"] = json!("2020-01-01T00:00:00Z");
+    append(&p.control, [ghost]);
+
+    let board = isnad::fold(dir.path()).unwrap();
+    let stale = |id: &str| (board.cards[id].stale, board.cards[id].days_since_update.map(|d| d > 365.0));
+    assert_eq!(stale("old"), (true, Some(true)));
+    assert_eq!(stale("fresh"), (false, Some(false)));
+    assert_eq!(stale("closed"), (false, Some(true)));
+    assert_eq!(stale("untimed"), (false, None));
+    assert_eq!(board.cards["ghost"].updated_at, "");
+    assert_eq!(stale("ghost"), (true, Some(true)));
+
+    let days = board.cards["old"].days_since_update.unwrap().floor();
+    assert!(isnad::render_markdown(&board).contains(&format!("- [old] old  (medium) (stale {days}d)\n")));
+    assert!(!isnad::render_markdown(&board).contains("[fresh] fresh  (medium) (stale"));
+
+    let options = isnad::FoldOptions { stale_after_days: Some(0), ..Default::default() };
+    assert!(isnad::fold_with(dir.path(), &options).unwrap().cards.values().all(|c| !c.stale));
+    fs::write(&p.config, r#"{ "stale_after_days": 100000 }"#).unwrap();
+    assert!(!isnad::fold(dir.path()).unwrap().cards["old"].stale);
+}
+
+#[test]
+fn unread_directives_carry_what_was_asked() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "task")]);
+    append(
+        &p.control,
+        [
+            json!({ "id": "D1", "type": "set_status", "ts": "2026-10-16T12:00:02Z", "task_id": "T1", "author": "sam", "rationale": "blocked on review", "payload": { "status": "blocked" } }),
+            json!({ "id": "D2", "type": "note", "ts": "2026-10-16T12:00:03Z", "task_id": "T1", "payload": { "text": "ping me" } }),
+        ],
+    );
+
+    // Once folded from the logs and once from the cache.
+    for _ in 0..2 {
+        let board = isnad::fold(dir.path()).unwrap();
+        assert_eq!(board.unread_directives["T1"], ["D1", "D2"]);
+        let details = &board.unread_directive_details["T1"];
+        assert_eq!(
+            details[0],
+            isnad::UnreadDirective {
+                id: "D1".into(),
+                kind: "set_status".into(),
+                ts: "2026-10-16T12:00:02Z".into(),
+                author: Some("sam".into()),
+                rationale: Some("blocked on review".into()),
+                payload: json!({ "status": "blocked" }),
+            }
+        );
+        assert_eq!((details[1].kind.as_str(), details[1].author.as_deref()), ("note", None));
+        assert!(isnad::render_markdown(&board).contains("\n  - pending: set_status, note\n"));
+    }
+
+    append(&p.ledger, [json!({ "id": "L-ack", "type": "ack_directive", "ts": "2026-10-16T12:00:04Z", "meta": { "directive_id": "D1" } })]);
+    let board = isnad::fold(dir.path()).unwrap();
+    let ids: Vec<&str> = board.unread_directive_details["T1"].iter().map(|d| d.id.as_str()).collect();
+    assert_eq!(ids, ["D2"]);
+    let json = serde_json::to_value(&board).unwrap();
+    assert_eq!(json["unread_directive_details"]["T1"][0]["type"], "note");
+}
+
+#[test]
+fn reorder_directives_override_the_priority_sort() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, ["A", "B", "C", "D", "E"].map(|t| opened(t, "task")));
+    let directive = |id: &str, kind: &str, task_id: &str, payload: Value| {
+        json!({ "id": id, "type": kind, "ts": "2026-10-16T12:00:02Z", "task_id": task_id, "payload": payload })
+    };
+    append(
+        &p.control,
+        [
+            directive("D1", "set_priority", "A", json!({ "priority": "urgent" })),
+            directive("D2", "set_priority", "B", json!({ "priority": "high" })),
+            directive("D3", "reorder", "E", json!({ "position": "top" })),
+            directive("D4", "reorder", "A", json!({ "position": "bottom" })),
+            directive("D5", "reorder", "D", json!({ "before": "E" })),
+            directive("D6", "reorder", "C", json!({ "before": "A" })),
+        ],
+    );
+    let column = |board: &isnad::Board| -> Vec<String> {
+        board.columns["backlog"].iter().map(|c| c.task_id.clone()).collect()
+    };
+
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(column(&board), ["D", "E", "B", "C", "A"]);
+    assert!(board.cards["B"].order_key.is_none());
+    assert!(board.warnings.is_empty(), "{:?}", board.warnings);
+
+    append(
+        &p.control,
+        [
+            directive("D7", "reorder", "B", json!({ "before": "C" })),
+            directive("D8", "reorder", "E", json!({ "before": "nope" })),
+            directive("D9", "reorder", "E", json!({ "before": "E" })),
+            directive("D10", "reorder", "E", json!({ "position": "middle" })),
+        ],
+    );
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(column(&board), ["D", "E", "B", "C", "A"]);
+    assert_eq!(board.warnings.len(), 3, "{:?}", board.warnings);
+    assert!(board.warnings[2].ends_with(r#"invalid position "middle""#), "{}", board.warnings[2]);
+
+    append(&p.control, [directive("D11", "reorder", "B", json!({ "before": "D" }))]);
+    assert_eq!(column(&isnad::fold(dir.path()).unwrap()), ["B", "D", "E", "C", "A"]);
+}
+
+#[test]
+fn reorder_keys_only_count_the_destination_column() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, ["A", "B", "X", "Y", "F"].map(|t| opened(t, "task")));
+    let directive = |id: &str, kind: &str, task_id: &str, payload: Value| {
+        json!({ "id": id, "type": kind, "ts": "2026-10-16T12:00:02Z", "task_id": task_id, "payload": payload })
+    };
+    append(
+        &p.control,
+        [
+            directive("D1", "set_status", "X", json!({ "status": "doing" })),
+            directive("D2", "set_status", "Y", json!({ "status": "doing" })),
+            directive("D3", "set_status", "F", json!({ "status": "doing" })),
+            directive("D4", "reorder", "X", json!({ "position": "top" })),
+            directive("D5", "reorder", "Y", json!({ "position": "top" })),
+            directive("D6", "reorder", "A", json!({ "position": "bottom" })),
+            // F leaves doing, then goes to the top of backlog and before A.
+            directive("D7", "set_status", "F", json!({ "status": "backlog" })),
+            directive("D8", "reorder", "F", json!({ "position": "top" })),
+        ],
+    );
+    let column = |board: &isnad::Board, status: &str| -> Vec<String> {
+        board.columns[status].iter().map(|c| c.task_id.clone()).collect()
+    };
+
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(column(&board, "doing"), ["Y", "X"]);
+    assert_eq!(column(&board, "backlog"), ["F", "B", "A"]);
+    // Doing's keys (-1 and -2) don't push backlog's further out.
+    assert_eq!(board.cards["F"].order_key, Some(-1.0));
+    assert_eq!(board.cards["A"].order_key, Some(1.0));
+
+    append(
+        &p.control,
+        [
+            directive("D9", "set_status", "Y", json!({ "status": "backlog" })),
+            directive("D10", "reorder", "Y", json!({ "before": "A" })),
+        ],
+    );
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(column(&board, "doing"), ["X"]);
+    assert_eq!(column(&board, "backlog"), ["F", "B", "Y", "A"]);
+    assert_eq!(board.cards["Y"].order_key, Some(0.5));
+    assert!(board.warnings.is_empty(), "{:?}", board.warnings);
+}
+
+#[test]
+fn subtasks_roll_up_into_their_parent() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let directive = |id: &str, kind: &str, task_id: &str, payload: Value| {
+        json!({ "id": id, "type": kind, "ts": "2026-10-16T12:00:02Z", "task_id": task_id, "payload": payload })
+    };
+    append(
+        &p.control,
+        [
+            directive("D1", "open_task", "P", json!({ "title": "parent", "status": "doing" })),
+            directive("D2", "open_task", "S1", json!({ "title": "one", "status": "doing", "parent_task_id": "P" })),
+            directive("D3", "open_task", "S2", json!({ "title": "two", "status": "doing", "parent_task_id": "P" })),
+            directive("D4", "open_task", "S3", json!({ "title": "three", "parent_task_id": "P" })),
+            directive("D5", "open_task", "G", json!({ "title": "grandchild", "parent_task_id": "S1" })),
+            directive("D6", "open_task", "O", json!({ "title": "orphan", "status": "doing", "parent_task_id": "gone" })),
+            directive("D7", "close_task", "S2", json!({ "resolution": "done" })),
+        ],
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.cards["P"].subtask_progress, Some((1, 3)));
+    assert!(!board.cards["P"].has_open_subtasks);
+    assert_eq!(board.cards["S1"].parent_task_id.as_deref(), Some("P"));
+    assert_eq!(board.cards["S1"].subtask_progress, None);
+    assert_eq!(board.cards["G"].parent_task_id, None);
+    assert_eq!(board.cards["O"].parent_task_id.as_deref(), Some("gone"));
+    assert_eq!(
+        board.warnings,
+        [
+            r#".isnad/control.jsonl:5: "G" can't be a subtask of "S1", which is itself a subtask"#,
+            r#".isnad/control.jsonl:6: parent task "gone" of "O" isn't on the board"#,
+        ]
+    );
+
+    let md = isnad::render_markdown(&board);
+    let doing: Vec<&str> = md.split("## Doing\n").nth(1).unwrap().split("\n\n").next().unwrap().lines().collect();
+    // Cards only, without the pending directives listed under them.
+    let doing: Vec<&str> = doing.into_iter().filter(|l| !l.trim_start().starts_with("- pending: ")).collect();
+    assert_eq!(doing.len(), 3, "{md}");
+    assert!(doing.iter().any(|l| l.starts_with("- [P] parent 1/3 (provisional)")), "{md}");
+    assert!(doing.iter().any(|l| l.starts_with("- [O] orphan (provisional)")), "{md}");
+    let p_line = doing.iter().position(|l| l.starts_with("- [P]")).unwrap();
+    assert!(doing[p_line + 1].starts_with("  - [S1] one"), "{md}");
+    assert!(md.contains("\n- [S3] three (provisional)"), "{md}");
+    assert!(md.contains("\n- [S2] two (provisional)"), "{md}");
+
+    append(&p.control, [directive("D8", "set_status", "P", json!({ "status": "done" }))]);
+    let board = isnad::fold(dir.path()).unwrap();
+    assert!(board.cards["P"].has_open_subtasks);
+    assert_eq!(board.cards["P"].status, "done");
+    assert!(isnad::render_markdown(&board).contains("- [P] parent 1/3 (provisional) (open subtasks)"));
+}
+
+#[test]
+fn the_last_update_is_attributed_to_whoever_wrote_it() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    // Behind the scaffold's own record, these are ledger sequences 2 and 3.
+    append(
+        &p.ledger,
+        [
+            json!({ "type": "task_opened", "ts": "2026-10-16T12:00:00Z", "task_id": "T1", "meta": { "title": "first", "actor": "agent-a" } }),
+            json!({ "type": "task_updated", "ts": "2026-10-16T12:00:01Z", "task_id": "T1", "meta": { "actor": "agent-b", "status": "doing" } }),
+        ],
+    );
+    append(
+        &p.control,
+        [
+            json!({ "id": "D1", "type": "set_priority", "ts": "2026-10-16T12:00:02Z", "task_id": "T1", "author": "sam", "payload": { "priority": "high" } }),
+            json!({ "id": "D2", "type": "add_tag", "ts": "2026-10-16T12:00:03Z", "task_id": "T1", "author": "sam", "payload": { "tag": "ui" } }),
+        ],
+    );
+    let card = &isnad::fold(dir.path()).unwrap().cards["T1"];
+    assert_eq!((card.updated_by.as_deref(), card.updated_via.as_deref()), (Some("agent-b"), Some("ledger")));
+    assert_eq!(card.priority, "high");
+
+    append(&p.control, [json!({ "id": "D3", "type": "add_tag", "ts": "2026-10-16T12:00:04Z", "task_id": "T1", "author": "lee", "payload": { "tag": "api" } })]);
+    let board = isnad::fold(dir.path()).unwrap();
+    let card = &board.cards["T1"];
+    assert_eq!((card.updated_by.as_deref(), card.updated_via.as_deref()), (Some("lee"), Some("control")));
+    let md = isnad::render_markdown(&board);
+    assert!(md.contains("- [T1] first  (high) (unread:3) #ui #api by lee\n"), "{md}");
+}
+
+#[test]
+fn concurrent_appends_get_distinct_seqs() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let mut seqs: Vec<i64> = std::thread::scope(|s| {
+        let writers: Vec<_> = (0..4)
+            .map(|w| {
+                let p = &p;
+                s.spawn(move || {
+                    let log = if w % 2 == 0 { &p.ledger } else { &p.control };
+                    (0..25).map(|i| isnad::append_record(p, log, &opened(&format!("T{w}-{i}"), "task")).unwrap()).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        writers.into_iter().flat_map(|w| w.join().unwrap()).collect()
+    });
+    seqs.sort();
+    // After the scaffold's unstamped init record, which counts as 1.
+    assert_eq!(seqs, (2..102).collect::<Vec<i64>>());
+    assert!(isnad::append_record(&p, &dir.path().join("other.jsonl"), &opened("T1", "task")).is_err());
+}
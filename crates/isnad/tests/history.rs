@@ -0,0 +1,106 @@
+use serde_json::{json, Value};
+
+fn at(hms: &str) -> String {
+    format!("2020-01-01T{hms}.000Z")
+}
+
+fn ledger(kind: &str, hms: &str, task_id: &str, extra: Value) -> Value {
+    let mut rec = json!({ "id": format!("L-{kind}-{hms}"), "type": kind, "ts": at(hms), "task_id": task_id });
+    rec.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    rec
+}
+
+fn directive(id: &str, hms: &str, kind: &str, task_id: &str, payload: Value) -> Value {
+    json!({ "id": id, "type": kind, "ts": at(hms), "task_id": task_id, "payload": payload })
+}
+
+#[test]
+fn history_interleaves_both_logs_and_derives_time_in_status() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    // Appended as they happened, so each gets the next `_seq` across both logs.
+    for (log, rec) in [
+        (&p.ledger, ledger("task_opened", "10:00:00", "T1", json!({ "meta": { "title": "first" } }))),
+        (&p.control, directive("D1", "10:15:00", "set_status", "T1", json!({ "status": "doing" }))),
+        (&p.control, directive("D2", "10:20:00", "set_priority", "T1", json!({ "priority": "high" }))),
+        (&p.ledger, ledger("snapshot", "10:30:00", "T1", json!({ "id": "S1" }))),
+        (&p.ledger, ledger("ack_directive", "10:45:00", "T1", json!({ "meta": { "directive_id": "D2" } }))),
+        (&p.control, directive("D3", "11:00:00", "pause", "T1", json!({ "reason": "waiting on review" }))),
+        (&p.control, directive("D4", "11:05:00", "set_status", "T2", json!({ "status": "doing" }))),
+        (&p.control, directive("D5", "11:15:00", "resume", "T1", json!({}))),
+        (&p.ledger, ledger("note", "11:30:00", "T1", json!({ "claim": "halfway" }))),
+        (&p.ledger, ledger("task_opened", "11:40:00", "T2", json!({ "meta": { "title": "second" } }))),
+        (&p.ledger, ledger("snapshot", "12:00:00", "T1", json!({ "id": "S2" }))),
+        (&p.control, directive("D6", "12:30:00", "set_status", "T1", json!({ "status": "done" }))),
+    ] {
+        isnad::append_record(&p, log, &rec).unwrap();
+    }
+
+    let history = isnad::task_history(dir.path(), "T1").unwrap();
+    let events: Vec<(&str, i64, &str, Option<bool>)> = history
+        .events
+        .iter()
+        .map(|e| (e.source.as_str(), e.seq, e.kind.as_str(), e.acknowledged))
+        .collect();
+    assert_eq!(
+        events,
+        [
+            ("ledger", 2, "task_opened", None),
+            ("control", 3, "set_status", Some(false)),
+            ("control", 4, "set_priority", Some(true)),
+            ("ledger", 5, "snapshot", None),
+            ("ledger", 6, "ack_directive", None),
+            ("control", 7, "pause", Some(false)),
+            ("control", 9, "resume", Some(false)),
+            ("ledger", 10, "note", None),
+            ("ledger", 12, "snapshot", None),
+            ("control", 13, "set_status", Some(false)),
+        ]
+    );
+    assert_eq!(history.events[0].record["meta"]["title"], "first");
+    assert!(history.events[0].record.get("_seq").is_none());
+
+    assert_eq!(history.first_seen_at, Some(at("10:00:00")));
+    assert_eq!(history.latest_snapshot_id.as_deref(), Some("S2"));
+    assert_eq!(history.status.as_deref(), Some("done"));
+    assert_eq!(history.notes.len(), 1);
+    let seconds = &history.seconds_in_status;
+    assert_eq!(seconds["backlog"], 15 * 60);
+    assert_eq!(seconds["doing"], 45 * 60 + 75 * 60);
+    assert_eq!(seconds["blocked"], 15 * 60);
+    assert!(seconds["done"] > 0);
+    assert_eq!(history.status, Some(isnad::fold(dir.path()).unwrap().cards["T1"].status.clone()));
+}
+
+#[test]
+fn history_follows_the_fold_order_not_the_timestamps() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    // The close was written first, so the reopen that claims to be older
+    // comes after it and takes effect, as it does on the board.
+    for (log, rec) in [
+        (&p.ledger, ledger("task_opened", "10:00:00", "T1", json!({ "meta": { "title": "first" } }))),
+        (&p.ledger, ledger("task_closed", "11:00:00", "T1", json!({}))),
+        (&p.control, directive("D1", "10:30:00", "reopen_task", "T1", json!({ "status": "next" }))),
+    ] {
+        isnad::append_record(&p, log, &rec).unwrap();
+    }
+
+    let history = isnad::task_history(dir.path(), "T1").unwrap();
+    let kinds: Vec<&str> = history.events.iter().map(|e| e.kind.as_str()).collect();
+    assert_eq!(kinds, ["task_opened", "task_closed", "reopen_task"]);
+    let statuses: Vec<&str> = history.transitions.iter().map(|t| t.status.as_str()).collect();
+    assert_eq!(statuses, ["backlog", "done", "next"]);
+    assert_eq!(history.closed_at, None);
+    assert_eq!(history.status, Some(isnad::fold(dir.path()).unwrap().cards["T1"].status.clone()));
+}
+
+#[test]
+fn unknown_tasks_have_an_empty_history() {
+    let dir = tempfile::tempdir().unwrap();
+    isnad::scaffold(dir.path(), false).unwrap();
+    let history = isnad::task_history(dir.path(), "T9").unwrap();
+    assert!(history.events.is_empty());
+    assert_eq!((history.first_seen_at, history.status), (None, None));
+    assert!(history.seconds_in_status.is_empty());
+}
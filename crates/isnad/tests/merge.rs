@@ -0,0 +1,96 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use isnad::MergeReport;
+use serde_json::{json, Value};
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path).unwrap();
+    for rec in records {
+        writeln!(file, "{rec}").unwrap();
+    }
+}
+
+fn opened(id: &str, task_id: &str, title: &str) -> Value {
+    json!({ "id": id, "type": "task_opened", "ts": "2020-01-01T10:00:00.000Z", "task_id": task_id, "meta": { "title": title } })
+}
+
+fn lines(path: &Path) -> Vec<Value> {
+    fs::read_to_string(path).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+}
+
+#[test]
+fn merge_appends_missing_records_in_source_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let (dst, src) = (dir.path().join("ledger.jsonl"), dir.path().join("theirs.jsonl"));
+    append(&dst, [opened("L1", "T1", "first"), opened("L2", "T2", "second")]);
+    append(&src, [opened("L3", "T3", "third"), opened("L1", "T1", "first"), opened("L4", "T4", "fourth")]);
+    let anonymous = json!({ "type": "note", "ts": "2020-01-01T11:00:00.000Z", "task_id": "T1", "claim": "no id" });
+    append(&src, [anonymous.clone(), anonymous.clone()]);
+    fs::OpenOptions::new().append(true).open(&src).unwrap().write_all(b"not json\n\n").unwrap();
+
+    let report = isnad::merge_jsonl(&dst, &src).unwrap();
+    assert_eq!(report, MergeReport { appended: 3, skipped: 2, conflicting: 0, unreadable: 1 });
+    let ids: Vec<Value> = lines(&dst).iter().map(|r| r["id"].clone()).collect();
+    assert_eq!(ids, [json!("L1"), json!("L2"), json!("L3"), json!("L4"), Value::Null]);
+
+    let again = isnad::merge_jsonl(&dst, &src).unwrap();
+    assert_eq!(again, MergeReport { appended: 0, skipped: 5, conflicting: 0, unreadable: 1 });
+    assert!(!dir.path().join("conflicts.jsonl").exists());
+}
+
+#[test]
+fn same_id_with_another_body_goes_to_conflicts_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let src = dir.path().join("theirs.jsonl");
+    append(&p.ledger, [opened("L1", "T1", "ours")]);
+    append(&src, [opened("L1", "T1", "theirs"), opened("L2", "T2", "new")]);
+
+    let report = isnad::merge_jsonl(&p.ledger, &src).unwrap();
+    assert_eq!(report, MergeReport { appended: 1, skipped: 0, conflicting: 1, unreadable: 0 });
+    let board = isnad::fold(dir.path()).unwrap();
+    assert_eq!(board.cards["T1"].title, "ours");
+    assert_eq!(board.cards["T2"].title, "new");
+
+    let conflicts = lines(&p.conflicts);
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0]["file"], "ledger.jsonl");
+    assert_eq!(conflicts[0]["id"], "L1");
+    assert_eq!(conflicts[0]["record"], opened("L1", "T1", "theirs"));
+
+    let again = isnad::merge_jsonl(&p.ledger, &src).unwrap();
+    assert_eq!(again.conflicting, 1);
+    assert_eq!(lines(&p.conflicts).len(), 1);
+}
+
+#[test]
+fn records_differing_only_in_seq_are_the_same() {
+    let dir = tempfile::tempdir().unwrap();
+    let (dst, src) = (dir.path().join("ledger.jsonl"), dir.path().join("theirs.jsonl"));
+    let stamped = |mut rec: Value, seq: i64| {
+        rec["_seq"] = seq.into();
+        rec
+    };
+    let anonymous = json!({ "type": "note", "ts": "2020-01-01T11:00:00.000Z", "task_id": "T1", "claim": "no id" });
+    append(&dst, [stamped(opened("L1", "T1", "first"), 3), stamped(anonymous.clone(), 4)]);
+    append(&src, [stamped(opened("L1", "T1", "first"), 7), stamped(anonymous, 8), stamped(opened("L2", "T2", "second"), 9)]);
+
+    let report = isnad::merge_jsonl(&dst, &src).unwrap();
+    assert_eq!(report, MergeReport { appended: 1, skipped: 2, conflicting: 0, unreadable: 0 });
+    assert_eq!(lines(&dst)[2], stamped(opened("L2", "T2", "second"), 9));
+}
+
+#[test]
+fn merge_does_not_join_onto_a_cut_short_last_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let (dst, src) = (dir.path().join("ledger.jsonl"), dir.path().join("theirs.jsonl"));
+    append(&dst, [opened("L1", "T1", "first")]);
+    fs::OpenOptions::new().append(true).open(&dst).unwrap().write_all(b"{\"id\":\"L2\"").unwrap();
+    append(&src, [opened("L3", "T3", "third")]);
+
+    isnad::merge_jsonl(&dst, &src).unwrap();
+    let raw = fs::read_to_string(&dst).unwrap();
+    assert_eq!(raw.lines().last().unwrap(), opened("L3", "T3", "third").to_string());
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use isnad::{DurationStats, MetricsAnomaly};
+use serde_json::{json, Value};
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    for rec in records {
+        writeln!(file, "{rec}").unwrap();
+    }
+}
+
+fn at(hm: &str) -> String {
+    format!("2020-01-01T{hm}:00.000Z")
+}
+
+fn opened(task_id: &str, hm: &str) -> Value {
+    json!({ "id": format!("L-{task_id}"), "type": "task_opened", "ts": at(hm), "task_id": task_id, "meta": { "title": task_id } })
+}
+
+fn directive(id: &str, hm: &str, kind: &str, task_id: &str, payload: Value) -> Value {
+    json!({ "id": id, "type": kind, "ts": at(hm), "task_id": task_id, "payload": payload })
+}
+
+fn anomaly(task_id: &str, reason: &str) -> MetricsAnomaly {
+    MetricsAnomaly { task_id: task_id.into(), reason: reason.into() }
+}
+
+fn workspace() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("A", "10:00"), opened("B", "10:00"), opened("C", "10:00"), opened("D", "09:00"), opened("E", "10:00")]);
+    append(
+        &p.control,
+        [
+            directive("A1", "11:00", "set_status", "A", json!({ "status": "doing" })),
+            directive("A2", "13:00", "close_task", "A", json!({ "resolution": "done" })),
+            // Written out of order, as clocks on two machines can be.
+            directive("B1", "12:00", "set_status", "B", json!({ "status": "doing" })),
+            directive("B2", "11:00", "set_status", "B", json!({ "status": "done" })),
+            directive("C1", "10:30", "close_task", "C", json!({})),
+            directive("D1", "10:00", "set_status", "D", json!({ "status": "doing" })),
+            directive("D2", "11:00", "close_task", "D", json!({})),
+            directive("D3", "12:00", "reopen_task", "D", json!({})),
+            directive("D4", "14:00", "close_task", "D", json!({})),
+            directive("E1", "10:00", "set_status", "E", json!({ "status": "doing" })),
+        ],
+    );
+    dir
+}
+
+#[test]
+fn cycle_and_lead_times_cover_finished_tasks() {
+    let dir = workspace();
+    let metrics = isnad::metrics(dir.path()).unwrap();
+
+    let ids: Vec<&str> = metrics.tasks.iter().map(|t| t.task_id.as_str()).collect();
+    assert_eq!(ids, ["A", "B", "C", "D", "E"]);
+    let a = &metrics.tasks[0];
+    let entered: Vec<(&str, &str)> = a.transitions.iter().map(|t| (t.status.as_str(), t.at.as_str())).collect();
+    assert_eq!(entered, [("backlog", at("10:00").as_str()), ("doing", &at("11:00")), ("done", &at("13:00"))]);
+    assert_eq!((a.cycle_seconds, a.lead_seconds), (Some(7200), Some(10800)));
+    assert_eq!(metrics.tasks[4].done_at, None);
+
+    assert_eq!(metrics.cycle_time, DurationStats { count: 3, mean_seconds: Some(7200), median_seconds: Some(7200) });
+    assert_eq!(metrics.lead_time, DurationStats { count: 4, mean_seconds: Some(8550), median_seconds: Some(7200) });
+    assert_eq!(
+        metrics.anomalies,
+        [
+            anomaly("B", "cycle time would be negative (-3600s); counted as 0"),
+            anomaly("C", "done without ever being in doing; no cycle time"),
+            anomaly("D", "reopened after being closed; times run to the last close"),
+        ]
+    );
+}
+
+#[test]
+fn throughput_counts_the_last_twelve_weeks() {
+    let dir = workspace();
+    let p = isnad::paths_for(dir.path());
+    append(&p.control, [json!({ "id": "E2", "type": "close_task", "ts": isnad::utc_now(), "task_id": "E", "payload": {} })]);
+
+    let metrics = isnad::metrics(dir.path()).unwrap();
+    let done: Vec<usize> = metrics.weekly_throughput.iter().map(|w| w.done).collect();
+    assert_eq!(done, [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    let starts: Vec<&str> = metrics.weekly_throughput.iter().map(|w| w.week_start.as_str()).collect();
+    assert!(starts.windows(2).all(|w| w[0] < w[1]), "{starts:?}");
+    assert!(starts[11] <= &metrics.as_of[..10]);
+}
+
+#[test]
+fn board_md_gets_a_metrics_section_when_asked() {
+    let dir = workspace();
+    let board = isnad::fold(dir.path()).unwrap();
+    let (_, md_path) = isnad::write_state(dir.path(), &board).unwrap();
+    assert!(!fs::read_to_string(&md_path).unwrap().contains("## Metrics"));
+
+    let options = isnad::WriteOptions { metrics: true, ..Default::default() };
+    isnad::write_state_with(dir.path(), &board, &options).unwrap();
+    let md = fs::read_to_string(&md_path).unwrap();
+    let section = md.split("## Metrics\n").nth(1).unwrap();
+    assert!(section.starts_with("- Cycle time (doing → done): median 0.1d, mean 0.1d over 3 tasks\n"), "{section}");
+    assert!(section.contains("- Done per week, last 12 weeks: 0 0 0 0 0 0 0 0 0 0 0 0\n"));
+    assert!(section.contains("  - [C] done without ever being in doing; no cycle time\n"));
+}
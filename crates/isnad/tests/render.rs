@@ -0,0 +1,130 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use isnad::RenderOptions;
+use serde_json::{json, Value};
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    for rec in records {
+        writeln!(file, "{rec}").unwrap();
+    }
+}
+
+fn at(hms: &str) -> String {
+    format!("2020-01-01T{hms}.000Z")
+}
+
+fn opened(task_id: &str, hms: &str, title: &str) -> Value {
+    json!({ "id": format!("L-{task_id}"), "type": "task_opened", "ts": at(hms), "task_id": task_id, "meta": { "title": title } })
+}
+
+fn directive(id: &str, hms: &str, kind: &str, task_id: &str, payload: Value) -> Value {
+    json!({ "id": id, "type": kind, "ts": at(hms), "task_id": task_id, "payload": payload })
+}
+
+fn board() -> isnad::Board {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(
+        &p.ledger,
+        [
+            opened("T1", "10:00:00", "Ship the release"),
+            opened("T2", "10:01:00", "Write changelog"),
+            opened("T3", "10:02:00", "Fix flaky test"),
+            opened("T4", "10:03:00", "Bump dependencies"),
+            opened("T5", "10:04:00", "Old spike"),
+            json!({ "id": "L-ack-D1", "type": "ack_directive", "ts": at("10:30:00"), "meta": { "directive_id": "D1" } }),
+        ],
+    );
+    append(
+        &p.control,
+        [
+            directive("D1", "11:00:00", "set_priority", "T1", json!({ "priority": "urgent" })),
+            directive("D2", "11:01:00", "set_priority", "T3", json!({ "priority": "high" })),
+            directive("D3", "11:02:00", "set_status", "T2", json!({ "status": "doing" })),
+            directive("D4", "11:03:00", "add_tag", "T2", json!({ "tag": "docs" })),
+            directive("D5", "11:04:00", "note", "T2", json!({ "text": "cover the new flags" })),
+            directive("D6", "11:05:00", "close_task", "T5", json!({ "resolution": "done" })),
+            directive("D7", "11:06:00", "open_task", "T6", json!({ "title": "Idea from review" })),
+        ],
+    );
+    // Every card would be stale by now, and by a different number of days each run.
+    let options = isnad::FoldOptions { stale_after_days: Some(0), ..Default::default() };
+    let mut board = isnad::fold_with(dir.path(), &options).unwrap();
+    board.generated_at = at("12:00:00");
+    board
+}
+
+// Set UPDATE_GOLDEN=1 to rewrite the files after an intended change.
+fn assert_golden(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+    }
+    let expected = fs::read_to_string(&path).unwrap();
+    assert_eq!(actual, expected, "{} is out of date", path.display());
+}
+
+#[test]
+fn default_options_match_render_markdown() {
+    let board = board();
+    let rendered = isnad::render_markdown_with(&board, &RenderOptions::default());
+    assert_eq!(rendered, isnad::render_markdown(&board));
+    assert_golden("board_default.md", &rendered);
+}
+
+#[test]
+fn grouped_by_priority_without_provisional_cards() {
+    let options = RenderOptions {
+        statuses: vec!["backlog".into(), "doing".into()],
+        group_by_priority: true,
+        include_provisional: false,
+        unread_summary: true,
+        ..RenderOptions::default()
+    };
+    assert_golden("board_grouped.md", &isnad::render_markdown_with(&board(), &options));
+}
+
+#[test]
+fn column_limit_counts_the_hidden_cards() {
+    let options = RenderOptions {
+        statuses: vec!["backlog".into()],
+        max_cards_per_column: Some(2),
+        ..RenderOptions::default()
+    };
+    assert_golden("board_limited.md", &isnad::render_markdown_with(&board(), &options));
+}
+
+#[test]
+fn html_lays_out_every_column_and_escapes_card_text() {
+    let mut board = board();
+    board.cards.get_mut("T1").unwrap().title = "<script>alert('x')</script> & co".to_string();
+    board.columns.get_mut("backlog").unwrap()[0].title = board.cards["T1"].title.clone();
+
+    let html = isnad::render_html(&board);
+    assert!(html.starts_with("<!doctype html>"));
+    assert!(!html.contains("<script") && !html.contains("<link") && !html.contains("src="));
+    assert!(html.contains("&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; co"));
+    assert!(html.contains("Generated 2020-01-01T12:00:00.000Z"));
+    for status in isnad::STATUSES {
+        assert!(html.contains(&format!("<h2>{status} (")), "{status}");
+    }
+    assert!(html.contains("<h2>backlog (4)</h2>"));
+    assert!(html.contains("<div class=\"card urgent\">"));
+    assert!(html.contains("<span class=\"pill unread\">unread 3</span>"));
+}
+
+#[test]
+fn write_state_writes_html_only_when_asked() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    let board = isnad::fold(dir.path()).unwrap();
+
+    isnad::write_state(dir.path(), &board).unwrap();
+    assert!(!p.board_html.exists());
+
+    isnad::write_state_with(dir.path(), &board, &isnad::WriteOptions { html: true, ..Default::default() }).unwrap();
+    assert_eq!(fs::read_to_string(&p.board_html).unwrap(), isnad::render_html(&board));
+}
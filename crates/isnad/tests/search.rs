@@ -0,0 +1,92 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    for rec in records {
+        writeln!(file, "{rec}").unwrap();
+    }
+}
+
+fn at(hms: &str) -> String {
+    format!("2020-01-01T{hms}.000Z")
+}
+
+fn opened(task_id: &str, hms: &str, title: &str) -> Value {
+    json!({ "id": format!("L-{task_id}"), "type": "task_opened", "ts": at(hms), "task_id": task_id, "meta": { "title": title } })
+}
+
+fn directive(id: &str, hms: &str, kind: &str, task_id: &str, payload: Value) -> Value {
+    json!({ "id": id, "type": kind, "ts": at(hms), "task_id": task_id, "payload": payload })
+}
+
+fn ids(cards: &[isnad::CardOut]) -> Vec<&str> {
+    cards.iter().map(|c| c.task_id.as_str()).collect()
+}
+
+fn workspace() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(
+        &p.ledger,
+        [
+            opened("T-login", "10:00:00", "Fix Login redirect"),
+            opened("T-docs", "10:01:00", "Write onboarding docs"),
+            opened("T-cache", "10:02:00", "Cache fold results"),
+            opened("T-old", "10:03:00", "Old login cleanup"),
+            json!({ "id": "L-act", "type": "action", "ts": at("10:04:00"), "task_id": "T-cache", "action": "profiled the LOGIN page" }),
+        ],
+    );
+    append(
+        &p.control,
+        [
+            directive("D1", "11:00:00", "set_priority", "T-old", json!({ "priority": "high" })),
+            directive("D2", "11:01:00", "add_tag", "T-docs", json!({ "tag": "login-flow" })),
+            directive("D3", "11:02:00", "note", "T-docs", json!({ "text": "ask about the login copy" })),
+            directive("D4", "11:03:00", "note", "T-docs", json!({ "text": "copy approved" })),
+            directive("D5", "11:04:00", "set_status", "T-login", json!({ "status": "doing" })),
+        ],
+    );
+    dir
+}
+
+#[test]
+fn search_matches_title_id_and_tags_ignoring_case() {
+    let dir = workspace();
+
+    let cards = isnad::search(dir.path(), "LOGIN").unwrap();
+    assert_eq!(ids(&cards), ["T-old", "T-login", "T-docs"]);
+
+    assert_eq!(ids(&isnad::search(dir.path(), "t-CACHE").unwrap()), ["T-cache"]);
+    assert_eq!(ids(&isnad::search(dir.path(), "approved").unwrap()), ["T-docs"]);
+    assert!(isnad::search(dir.path(), "nothing like this").unwrap().is_empty());
+}
+
+#[test]
+fn search_only_looks_at_the_latest_note() {
+    let dir = workspace();
+    let cards = isnad::search(dir.path(), "login copy").unwrap();
+    assert!(cards.is_empty());
+}
+
+#[test]
+fn search_can_include_ledger_claims_and_actions() {
+    let dir = workspace();
+    let options = isnad::SearchOptions { ledger_text: true };
+
+    let cards = isnad::search_with(dir.path(), "login", &options).unwrap();
+    assert_eq!(ids(&cards), ["T-old", "T-login", "T-docs", "T-cache"]);
+    assert_eq!(ids(&isnad::search_with(dir.path(), "profiled", &options).unwrap()), ["T-cache"]);
+}
+
+#[test]
+fn search_handles_non_ascii_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "10:00:00", "Übersetzung prüfen")]);
+
+    assert_eq!(ids(&isnad::search(dir.path(), "ÜBERSETZUNG").unwrap()), ["T1"]);
+}
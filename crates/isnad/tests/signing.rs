@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::SigningKey;
+use serde_json::{json, Value};
+use voxelle_protocol::principal_id_from_spki_der;
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    for rec in records {
+        writeln!(file, "{rec}").unwrap();
+    }
+}
+
+fn key(seed: u8) -> (SigningKey, Vec<u8>) {
+    let key = SigningKey::from_bytes(&[seed; 32]);
+    let spki = key.verifying_key().to_public_key_der().unwrap().as_bytes().to_vec();
+    (key, spki)
+}
+
+fn record(task_id: &str, kind: &str, claim: &str) -> Value {
+    json!({ "id": format!("L-{task_id}-{kind}"), "type": kind, "ts": "2020-01-01T10:00:00.000Z", "task_id": task_id, "claim": claim, "meta": { "actor": "agent" } })
+}
+
+#[test]
+fn signed_records_verify_and_tampering_is_caught() {
+    let (agent, agent_spki) = key(1);
+    let (_, other_spki) = key(2);
+
+    let signed = isnad::sign_record(&record("T1", "note", "tests pass"), &agent).unwrap();
+    assert_eq!(signed["meta"]["principal"], principal_id_from_spki_der(&agent_spki));
+    assert_eq!(signed["meta"]["actor"], "agent");
+    assert!(isnad::verify_record(&signed, &agent_spki).unwrap());
+    assert!(!isnad::verify_record(&signed, &other_spki).unwrap());
+
+    let mut forged = signed.clone();
+    forged["claim"] = json!("tests pass, ship it");
+    assert!(!isnad::verify_record(&forged, &agent_spki).unwrap());
+
+    let mut garbled = signed.clone();
+    garbled["meta"]["sig"] = json!("not base64!");
+    assert!(!isnad::verify_record(&garbled, &agent_spki).unwrap());
+
+    assert!(!isnad::verify_record(&record("T1", "note", "unsigned"), &agent_spki).unwrap());
+    assert!(isnad::verify_record(&signed, b"not a key").is_err());
+}
+
+#[test]
+fn fold_marks_cards_whose_latest_ledger_update_is_unverified() {
+    let (agent, agent_spki) = key(1);
+    let (stranger, _) = key(2);
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+
+    let mut forged = isnad::sign_record(&record("forged", "note", "done"), &agent).unwrap();
+    forged["claim"] = json!("done, trust me");
+    append(
+        &p.ledger,
+        [
+            isnad::sign_record(&record("signed", "task_opened", "signed"), &agent).unwrap(),
+            isnad::sign_record(&record("forged", "task_opened", "forged"), &agent).unwrap(),
+            forged,
+            isnad::sign_record(&record("stranger", "task_opened", "stranger"), &stranger).unwrap(),
+            record("legacy", "task_opened", "legacy"),
+            // Unsigned, but an action doesn't update the card.
+            record("signed", "action", "ran the tests"),
+        ],
+    );
+
+    let board = isnad::fold(dir.path()).unwrap();
+    assert!(board.cards.values().all(|c| !c.unverified));
+
+    let trusted = HashMap::from([(principal_id_from_spki_der(&agent_spki), agent_spki)]);
+    let unverified = |options: &isnad::FoldOptions| -> Vec<String> {
+        let board = isnad::fold_with(dir.path(), options).unwrap();
+        let mut ids: Vec<String> = board.cards.values().filter(|c| c.unverified).map(|c| c.task_id.clone()).collect();
+        ids.sort();
+        ids
+    };
+
+    let options = isnad::FoldOptions { trusted_principals: Some(trusted), ..Default::default() };
+    assert_eq!(unverified(&options), ["forged", "stranger"]);
+    let board = isnad::fold_with(dir.path(), &options).unwrap();
+    assert!(isnad::render_markdown(&board).contains("- [forged] forged (unverified)"));
+
+    let strict = isnad::FoldOptions { require_signatures: true, ..options.clone() };
+    assert_eq!(unverified(&strict), ["forged", "legacy", "stranger"]);
+
+    // The `_seq` stamped on append isn't part of what was signed.
+    let update = isnad::sign_record(&record("legacy", "task_updated", "legacy"), &agent).unwrap();
+    isnad::append_record(&p, &p.ledger, &update).unwrap();
+    assert_eq!(unverified(&options), ["forged", "stranger"]);
+}
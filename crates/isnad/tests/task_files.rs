@@ -0,0 +1,112 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use isnad::WriteOptions;
+use serde_json::{json, Value};
+
+fn append(path: &Path, records: impl IntoIterator<Item = Value>) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    for rec in records {
+        writeln!(file, "{rec}").unwrap();
+    }
+}
+
+fn at(hms: &str) -> String {
+    format!("2020-01-01T{hms}.000Z")
+}
+
+fn opened(task_id: &str, hms: &str, title: &str) -> Value {
+    json!({ "id": format!("L-{task_id}"), "type": "task_opened", "ts": at(hms), "task_id": task_id, "meta": { "title": title } })
+}
+
+fn directive(id: &str, hms: &str, kind: &str, task_id: &str, payload: Value) -> Value {
+    json!({ "id": id, "type": kind, "ts": at(hms), "task_id": task_id, "author": "ana", "payload": payload })
+}
+
+const TASK_FILES: WriteOptions = WriteOptions { html: false, per_task_files: true, metrics: false };
+
+fn write(root: &Path) {
+    let board = isnad::fold(root).unwrap();
+    isnad::write_state_with(root, &board, &TASK_FILES).unwrap();
+}
+
+fn listing(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().into_string().unwrap()).collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn task_file_describes_the_card_and_its_directives() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(
+        &p.ledger,
+        [
+            opened("T1", "10:00:00", "Ship it"),
+            json!({ "id": "S1", "type": "snapshot", "ts": at("10:10:00"), "task_id": "T1" }),
+            json!({ "id": "L-ack", "type": "ack_directive", "ts": at("10:30:00"), "meta": { "directive_id": "D1" } }),
+        ],
+    );
+    append(
+        &p.control,
+        [
+            directive("D1", "10:20:00", "set_priority", "T1", json!({ "priority": "high" })),
+            directive("D2", "10:40:00", "add_tag", "T1", json!({ "tag": "release" })),
+            directive("D3", "10:50:00", "note", "T1", json!({ "text": "after the freeze" })),
+        ],
+    );
+    write(dir.path());
+
+    assert_eq!(
+        fs::read_to_string(p.tasks_dir.join("T1.md")).unwrap(),
+        "# [T1] Ship it\n\n\
+         - Status: backlog\n\
+         - Priority: high\n\
+         - Tags: #release\n\
+         - Latest snapshot: S1\n\
+         - Updated: 2020-01-01T10:50:00.000Z\n\
+         \n## Notes\n\
+         - ana (2020-01-01T10:50:00.000Z): after the freeze\n\
+         \n## Directives\n\
+         - 2020-01-01T10:20:00.000Z set_priority by ana `{\"priority\":\"high\"}`\n\
+         - 2020-01-01T10:40:00.000Z add_tag by ana `{\"tag\":\"release\"}` (unread)\n\
+         - 2020-01-01T10:50:00.000Z note by ana `{\"text\":\"after the freeze\"}` (unread)\n"
+    );
+}
+
+#[test]
+fn task_files_are_opt_in() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "10:00:00", "Ship it")]);
+    let board = isnad::fold(dir.path()).unwrap();
+    isnad::write_state(dir.path(), &board).unwrap();
+    assert!(!p.tasks_dir.exists());
+}
+
+#[test]
+fn rewriting_is_idempotent_and_drops_tasks_that_left_the_board() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = isnad::scaffold(dir.path(), false).unwrap();
+    append(&p.ledger, [opened("T1", "10:00:00", "Keep"), opened("T2", "10:01:00", "Finish")]);
+    append(&p.control, [directive("D1", "10:02:00", "close_task", "T2", json!({ "resolution": "done" }))]);
+    fs::create_dir_all(&p.tasks_dir).unwrap();
+    fs::write(p.tasks_dir.join("notes.txt"), "not ours").unwrap();
+
+    write(dir.path());
+    let first = listing(&p.tasks_dir);
+    let t1 = fs::read_to_string(p.tasks_dir.join("T1.md")).unwrap();
+    let modified = fs::metadata(p.tasks_dir.join("T1.md")).unwrap().modified().unwrap();
+    write(dir.path());
+    assert_eq!(first, ["T1.md", "T2.md", "notes.txt"]);
+    assert_eq!(listing(&p.tasks_dir), first);
+    assert_eq!(fs::read_to_string(p.tasks_dir.join("T1.md")).unwrap(), t1);
+    assert_eq!(fs::metadata(p.tasks_dir.join("T1.md")).unwrap().modified().unwrap(), modified);
+
+    let report = isnad::archive(dir.path(), &isnad::ArchiveOptions { older_than_days: 30, dry_run: false }).unwrap();
+    assert_eq!(report.task_ids, ["T2"]);
+    write(dir.path());
+    assert_eq!(listing(&p.tasks_dir), ["T1.md", "notes.txt"]);
+}
@@ -0,0 +1,249 @@
+//! The Work Board web UI for a `.isnad` workspace: an axum [`router`] that
+//! `voxelle-board serve` runs standalone and [`BoardServer`] embeds in
+//! another process. It folds the board on request and writes control
+//! directives only; it never edits the ledger.
+
+use axum::{
+    extract::{Path as UrlPath, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use isnad::{
+    append_record, fold, new_id, paths_for, scaffold, utc_now, validate_task_id, write_state, Board, TASK_SCOPED_DIRECTIVES,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+const HTML: &str = include_str!("ui.html");
+
+#[derive(Debug, Clone)]
+pub struct BoardConfig {
+    /// Canonical workspace root.
+    pub root: PathBuf,
+    /// Stamped as `author` on directives sent from the UI.
+    pub author: String,
+    pub via: String,
+    pub operator: Option<String>,
+    /// When set, every request needs it: `/t/<token>` hands out a cookie and
+    /// redirects to the board, and everything else is refused without that
+    /// cookie. Meant for loopback servers other local processes could reach.
+    pub token: Option<String>,
+}
+
+#[derive(Clone)]
+struct AppState {
+    root: PathBuf,
+    author: String,
+    via: String,
+    operator: Option<String>,
+}
+
+async fn index() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static("text/html; charset=utf-8")),
+        ],
+        HTML,
+    )
+}
+
+async fn api_board(State(state): State<Arc<AppState>>) -> Result<Json<Board>, (StatusCode, String)> {
+    let board = fold(&state.root).map_err(internal_error)?;
+    write_state(&state.root, &board).map_err(internal_error)?;
+    Ok(Json(board))
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenTaskReq {
+    payload: Option<Value>,
+}
+
+async fn api_open_task(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<OpenTaskReq>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    scaffold(&state.root, false).map_err(internal_error)?;
+    let p = paths_for(&state.root);
+
+    let task_id = new_id("T", 8);
+    let directive = serde_json::json!({
+        "id": new_id("D", 12),
+        "ts": utc_now(),
+        "type": "open_task",
+        "task_id": task_id,
+        "author": state.author,
+        "meta": {
+            "via": state.via,
+            "operator": state.operator,
+        },
+        "payload": req.payload.unwrap_or_else(|| serde_json::json!({}))
+    });
+    append_record(&p, &p.control, &directive).map_err(internal_error)?;
+    Ok(Json(serde_json::json!({"ok": true, "directive_id": directive["id"], "task_id": directive["task_id"]})))
+}
+
+#[derive(Debug, Deserialize)]
+struct DirectiveReq {
+    #[serde(rename = "type")]
+    d_type: String,
+    task_id: Option<String>,
+    payload: Option<Value>,
+}
+
+async fn api_directives(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<DirectiveReq>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    scaffold(&state.root, false).map_err(internal_error)?;
+    let p = paths_for(&state.root);
+
+    if req.d_type.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "missing type".to_string()));
+    }
+    let needs_task = TASK_SCOPED_DIRECTIVES.contains(&req.d_type.as_str());
+    if needs_task && req.task_id.as_deref().unwrap_or("").is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "missing task_id".to_string()));
+    }
+    // Same charset rules as the CLI and the desktop app.
+    if let Some(task_id) = req.task_id.as_deref().filter(|t| !t.is_empty()) {
+        validate_task_id(task_id).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    }
+
+    let directive = serde_json::json!({
+        "id": new_id("D", 12),
+        "ts": utc_now(),
+        "type": req.d_type,
+        "task_id": req.task_id,
+        "author": state.author,
+        "meta": {
+            "via": state.via,
+            "operator": state.operator,
+        },
+        "payload": req.payload.unwrap_or_else(|| serde_json::json!({}))
+    });
+    append_record(&p, &p.control, &directive).map_err(internal_error)?;
+    Ok(Json(serde_json::json!({"ok": true, "directive_id": directive["id"], "task_id": directive["task_id"]})))
+}
+
+fn internal_error<E: std::fmt::Display>(e: E) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+struct TokenAuth {
+    token: String,
+    // Cookies are scoped by host, not port, so each server needs its own name.
+    cookie: String,
+}
+
+fn has_token_cookie(req: &Request, auth: &TokenAuth) -> bool {
+    req.headers()
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|h| h.split(';'))
+        .filter_map(|c| c.trim().split_once('='))
+        .any(|(name, value)| name == auth.cookie && voxelle_protocol::ct_eq_str(value.trim(), &auth.token))
+}
+
+async fn require_token(State(auth): State<Arc<TokenAuth>>, req: Request, next: Next) -> Response {
+    if has_token_cookie(&req, &auth) {
+        next.run(req).await
+    } else {
+        (StatusCode::FORBIDDEN, "forbidden").into_response()
+    }
+}
+
+fn enter(auth: &TokenAuth, given: &str) -> Response {
+    if !voxelle_protocol::ct_eq_str(given, &auth.token) {
+        return (StatusCode::FORBIDDEN, "forbidden").into_response();
+    }
+    let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict", auth.cookie, auth.token);
+    (StatusCode::SEE_OTHER, [(header::LOCATION, "/".to_string()), (header::SET_COOKIE, cookie)]).into_response()
+}
+
+/// The board UI and its JSON API for `config.root`.
+pub fn router(config: BoardConfig) -> Router {
+    let state = Arc::new(AppState {
+        root: config.root,
+        author: config.author,
+        via: config.via,
+        operator: config.operator.filter(|o| !o.trim().is_empty()),
+    });
+    let app = Router::new()
+        .route("/", get(index))
+        .route("/api/board", get(api_board).post(api_board))
+        .route("/api/open_task", post(api_open_task))
+        .route("/api/directives", post(api_directives))
+        .with_state(state);
+
+    let Some(token) = config.token else {
+        return app;
+    };
+    let cookie = format!("voxelle_board_{}", token.chars().filter(char::is_ascii_alphanumeric).take(8).collect::<String>());
+    let auth = Arc::new(TokenAuth { token, cookie });
+    let entry = auth.clone();
+    app.route_layer(middleware::from_fn_with_state(auth, require_token))
+        .route("/t/:token", get(move |UrlPath(given): UrlPath<String>| std::future::ready(enter(&entry, &given))))
+}
+
+/// A board server running on the current tokio runtime. Dropping it stops it too.
+pub struct BoardServer {
+    addr: SocketAddr,
+    url: String,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl BoardServer {
+    /// Binds `addr` (port 0 picks a free one) and starts serving in the background.
+    pub async fn start(addr: SocketAddr, config: BoardConfig) -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let url = match &config.token {
+            Some(token) => format!("http://{addr}/t/{token}"),
+            None => format!("http://{addr}/"),
+        };
+        let app = router(config);
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let shutdown = async {
+                let _ = rx.await;
+            };
+            if let Err(e) = axum::serve(listener, app).with_graceful_shutdown(shutdown).await {
+                tracing::warn!("board server on {addr} failed: {e}");
+            }
+        });
+        Ok(Self { addr, url, shutdown: Some(tx) })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Where to open the board; includes the token when there is one.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn stop(mut self) {
+        self.signal_stop();
+    }
+
+    fn signal_stop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+impl Drop for BoardServer {
+    fn drop(&mut self) {
+        self.signal_stop();
+    }
+}
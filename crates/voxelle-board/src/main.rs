@@ -1,24 +1,17 @@
 use anyhow::{Context, Result};
-use axum::{
-    extract::{State},
-    http::{header, HeaderValue, StatusCode},
-    response::IntoResponse,
-    routing::{get, post},
-    Json, Router,
-};
 use clap::{Parser, Subcommand};
 use isnad::{
-    append_jsonl, fold, new_id, paths_for, read_jsonl_values, scaffold, utc_now, write_state, Board,
+    append_record, archive, diff_boards, fold, fold_workspaces, merge_jsonl, metrics, new_id, paths_for,
+    read_jsonl_values, render_html, render_markdown, render_metrics_markdown, scaffold, search_with, utc_now,
+    validate_task_id, write_aggregate_state, write_diff, write_state, write_state_with, ArchiveOptions, SearchOptions,
+    WorkspaceRoot, WriteOptions, TASK_SCOPED_DIRECTIVES,
 };
-use serde::Deserialize;
 use serde_json::Value;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
 use tokio::signal;
-use tracing::{error, info};
-
-const HTML: &str = include_str!("ui.html");
+use tracing::{error, info, warn};
+use voxelle_board::BoardConfig;
 
 #[derive(Debug, Parser)]
 #[command(name = "voxelle-board", about = "Local Work Board UI for .isnad (derived; writes control only).")]
@@ -42,6 +35,15 @@ enum Command {
         watch: bool,
         #[arg(long, default_value_t = 0.75)]
         interval: f64,
+        /// Also write .isnad/state/board.html.
+        #[arg(long)]
+        html: bool,
+        /// Also write .isnad/state/tasks/<task_id>.md per card.
+        #[arg(long)]
+        task_files: bool,
+        /// Append cycle time, lead time and throughput to board.md.
+        #[arg(long)]
+        metrics: bool,
     },
     Serve {
         #[arg(long, default_value = ".")]
@@ -107,127 +109,60 @@ enum Command {
         #[arg(long)]
         dry_run: bool,
     },
-}
-
-#[derive(Clone)]
-struct AppState {
-    root: PathBuf,
-    author: String,
-    via: String,
-    operator: Option<String>,
+    Archive {
+        #[arg(long, default_value = ".")]
+        root: String,
+        #[arg(long, value_name = "N")]
+        older_than_days: u32,
+        #[arg(long)]
+        dry_run: bool,
+    },
+    Search {
+        #[arg(long, default_value = ".")]
+        root: String,
+        query: String,
+        /// Also match ledger claim and action text.
+        #[arg(long)]
+        ledger: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    Aggregate {
+        /// Workspace roots, each optionally labeled as LABEL=ROOT.
+        #[arg(required = true)]
+        roots: Vec<String>,
+        /// Where board.json and board.md go; must be outside every workspace.
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long)]
+        html: bool,
+    },
+    Export {
+        #[arg(long, default_value = ".")]
+        root: String,
+        #[arg(long, default_value = "html", value_parser = ["html", "md", "json"])]
+        format: String,
+        /// Write here instead of stdout.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    Metrics {
+        #[arg(long, default_value = ".")]
+        root: String,
+        #[arg(long)]
+        json: bool,
+    },
+    Merge {
+        #[arg(long, default_value = ".")]
+        root: String,
+        /// Another copy of this workspace, e.g. from a second machine.
+        #[arg(long)]
+        from: String,
+    },
 }
 
 fn normalize_root(root: &str) -> Result<PathBuf> {
-    Ok(Path::new(root).canonicalize().with_context(|| format!("canonicalize {root}"))?)
-}
-
-async fn index() -> impl IntoResponse {
-    (
-        [
-            (header::CONTENT_TYPE, HeaderValue::from_static("text/html; charset=utf-8")),
-        ],
-        HTML,
-    )
-}
-
-async fn api_board(State(state): State<Arc<AppState>>) -> Result<Json<Board>, (StatusCode, String)> {
-    let board = fold(&state.root).map_err(internal_error)?;
-    write_state(&state.root, &board).map_err(internal_error)?;
-    Ok(Json(board))
-}
-
-#[derive(Debug, Deserialize)]
-struct OpenTaskReq {
-    payload: Option<Value>,
-}
-
-async fn api_open_task(
-    State(state): State<Arc<AppState>>,
-    Json(req): Json<OpenTaskReq>,
-) -> Result<Json<Value>, (StatusCode, String)> {
-    scaffold(&state.root, false).map_err(internal_error)?;
-    let p = paths_for(&state.root);
-
-    let task_id = new_id("T", 8);
-    let directive = serde_json::json!({
-        "id": new_id("D", 12),
-        "ts": utc_now(),
-        "type": "open_task",
-        "task_id": task_id,
-        "author": state.author,
-        "meta": {
-            "via": state.via,
-            "operator": state.operator,
-        },
-        "payload": req.payload.unwrap_or_else(|| serde_json::json!({}))
-    });
-    append_jsonl(&p.control, &directive).map_err(internal_error)?;
-    Ok(Json(serde_json::json!({"ok": true, "directive_id": directive["id"], "task_id": directive["task_id"]})))
-}
-
-#[derive(Debug, Deserialize)]
-struct DirectiveReq {
-    #[serde(rename = "type")]
-    d_type: String,
-    task_id: Option<String>,
-    payload: Option<Value>,
-}
-
-async fn api_directives(
-    State(state): State<Arc<AppState>>,
-    Json(req): Json<DirectiveReq>,
-) -> Result<Json<Value>, (StatusCode, String)> {
-    scaffold(&state.root, false).map_err(internal_error)?;
-    let p = paths_for(&state.root);
-
-    if req.d_type.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "missing type".to_string()));
-    }
-    let needs_task = matches!(
-        req.d_type.as_str(),
-        "set_status" | "set_priority" | "pause" | "resume" | "note"
-    );
-    if needs_task && req.task_id.as_deref().unwrap_or("").is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "missing task_id".to_string()));
-    }
-
-    let directive = serde_json::json!({
-        "id": new_id("D", 12),
-        "ts": utc_now(),
-        "type": req.d_type,
-        "task_id": req.task_id,
-        "author": state.author,
-        "meta": {
-            "via": state.via,
-            "operator": state.operator,
-        },
-        "payload": req.payload.unwrap_or_else(|| serde_json::json!({}))
-    });
-    append_jsonl(&p.control, &directive).map_err(internal_error)?;
-    Ok(Json(serde_json::json!({"ok": true, "directive_id": directive["id"], "task_id": directive["task_id"]})))
-}
-
-fn internal_error<E: std::fmt::Display>(e: E) -> (StatusCode, String) {
-    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
-}
-
-fn validate_task_id(task_id: &str) -> Result<()> {
-    if task_id.is_empty() || task_id.len() > 64 {
-        anyhow::bail!("Invalid task id: must be 1-64 chars");
-    }
-    let mut chars = task_id.chars();
-    let Some(first) = chars.next() else {
-        anyhow::bail!("Invalid task id: empty");
-    };
-    if !first.is_ascii_alphanumeric() {
-        anyhow::bail!("Invalid task id: must start with letter or digit");
-    }
-    for c in chars {
-        if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
-            anyhow::bail!("Invalid task id: only letters/digits/_/- allowed");
-        }
-    }
-    Ok(())
+    Path::new(root).canonicalize().with_context(|| format!("canonicalize {root}"))
 }
 
 fn parse_json_object(s: &str, what: &str) -> Result<Value> {
@@ -283,13 +218,20 @@ async fn main() -> Result<()> {
             root,
             watch,
             interval,
+            html,
+            task_files,
+            metrics,
         } => {
             let root = normalize_root(&root)?;
             scaffold(&root, false)?;
-            let board = fold(&root)?;
-            let (json_path, md_path) = write_state(&root, &board)?;
+            let options = WriteOptions { html, per_task_files: task_files, metrics };
+            let mut board = fold(&root)?;
+            let (json_path, md_path) = write_state_with(&root, &board, &options)?;
             info!("Wrote {}", json_path.display());
             info!("Wrote {}", md_path.display());
+            for warning in &board.warnings {
+                warn!("{warning}");
+            }
 
             if watch {
                 let p = paths_for(&root);
@@ -305,10 +247,12 @@ async fn main() -> Result<()> {
                         continue;
                     }
                     last = cur;
-                    let board = fold(&root)?;
-                    let (json_path, md_path) = write_state(&root, &board)?;
-                    info!("Wrote {}", json_path.display());
-                    info!("Wrote {}", md_path.display());
+                    let next = fold(&root)?;
+                    write_state_with(&root, &next, &options)?;
+                    let diff = diff_boards(&board, &next);
+                    write_diff(&root, &diff)?;
+                    info!("{diff}");
+                    board = next;
                 }
             }
         }
@@ -324,24 +268,14 @@ async fn main() -> Result<()> {
             let root = normalize_root(&root)?;
             scaffold(&root, false)?;
 
-            let state = Arc::new(AppState {
+            let app = voxelle_board::router(BoardConfig {
                 root: root.clone(),
                 author,
                 via,
-                operator: if operator.trim().is_empty() {
-                    None
-                } else {
-                    Some(operator)
-                },
+                operator: Some(operator),
+                token: None,
             });
 
-            let app = Router::new()
-                .route("/", get(index))
-                .route("/api/board", get(api_board).post(api_board))
-                .route("/api/open_task", post(api_open_task))
-                .route("/api/directives", post(api_directives))
-                .with_state(state);
-
             let addr: SocketAddr = format!("{host}:{port}").parse().context("parse bind addr")?;
             let url = format!("http://{host}:{port}/");
             info!("Serving {url}");
@@ -371,8 +305,7 @@ async fn main() -> Result<()> {
             scaffold(&root, false)?;
             let p = paths_for(&root);
 
-            let task_scoped =
-                matches!(r#type.as_str(), "set_status" | "set_priority" | "pause" | "resume" | "note");
+            let task_scoped = TASK_SCOPED_DIRECTIVES.contains(&r#type.as_str());
             if task_scoped && task.as_deref().unwrap_or("").is_empty() {
                 anyhow::bail!("--task is required for --type {type}", type = r#type);
             }
@@ -402,7 +335,7 @@ async fn main() -> Result<()> {
                 directive["rationale"] = Value::String(rationale);
             }
 
-            append_jsonl(&p.control, &directive)?;
+            append_record(&p, &p.control, &directive)?;
             info!("Appended directive {} to {}", directive["id"], p.control.display());
         }
         Command::AppendLedger {
@@ -451,7 +384,7 @@ async fn main() -> Result<()> {
                 record["next_decision"] = Value::String(next);
             }
 
-            append_jsonl(&p.ledger, &record)?;
+            append_record(&p, &p.ledger, &record)?;
             info!("Appended record {} to {}", record["id"], p.ledger.display());
         }
         Command::AckDirectives {
@@ -504,11 +437,143 @@ async fn main() -> Result<()> {
                 if dry_run {
                     println!("{}", serde_json::to_string_pretty(&receipt)?);
                 } else {
-                    append_jsonl(&p.ledger, &receipt)?;
+                    append_record(&p, &p.ledger, &receipt)?;
                     info!("acked {} -> {}", receipt["meta"]["directive_id"], receipt["id"]);
                 }
             }
         }
+        Command::Archive {
+            root,
+            older_than_days,
+            dry_run,
+        } => {
+            let root = normalize_root(&root)?;
+            scaffold(&root, false)?;
+
+            let report = archive(&root, &ArchiveOptions { older_than_days, dry_run })?;
+            if report.task_ids.is_empty() {
+                info!("No tasks closed for {older_than_days} days or more.");
+                return Ok(());
+            }
+            let verb = if dry_run { "Would move" } else { "Moved" };
+            for task_id in &report.task_ids {
+                println!("{task_id}");
+            }
+            info!(
+                "{verb} {} tasks: {} ledger records to {}, {} control records to {}",
+                report.task_ids.len(),
+                report.ledger_records,
+                report.ledger_archive.display(),
+                report.control_records,
+                report.control_archive.display()
+            );
+            if !dry_run {
+                let board = fold(&root)?;
+                let (json_path, md_path) = write_state(&root, &board)?;
+                info!("Wrote {}", json_path.display());
+                info!("Wrote {}", md_path.display());
+            }
+        }
+        Command::Search {
+            root,
+            query,
+            ledger,
+            json,
+        } => {
+            let root = normalize_root(&root)?;
+            let cards = search_with(&root, &query, &SearchOptions { ledger_text: ledger })?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&cards)?);
+                return Ok(());
+            }
+            if cards.is_empty() {
+                info!("No cards match {query:?}.");
+                return Ok(());
+            }
+            let id_w = cards.iter().map(|c| c.task_id.len()).max().unwrap_or(0).max("TASK".len());
+            let status_w = cards.iter().map(|c| c.status.len()).max().unwrap_or(0).max("STATUS".len());
+            println!("{:id_w$}  {:status_w$}  {:8}  TITLE", "TASK", "STATUS", "PRIORITY");
+            for c in &cards {
+                println!("{:id_w$}  {:status_w$}  {:8}  {}", c.task_id, c.status, c.priority, c.title);
+            }
+        }
+        Command::Aggregate { roots, out, html } => {
+            let mut workspaces = vec![];
+            for arg in &roots {
+                let workspace = match arg.split_once('=') {
+                    Some((label, root)) => WorkspaceRoot { root: normalize_root(root)?, label: label.to_string() },
+                    None => WorkspaceRoot::new(normalize_root(arg)?),
+                };
+                workspaces.push(workspace);
+            }
+            let board = fold_workspaces(&workspaces)?;
+            let (json_path, md_path) = write_aggregate_state(&out, &board, &WriteOptions { html, ..WriteOptions::default() })?;
+            info!("Wrote {}", json_path.display());
+            info!("Wrote {}", md_path.display());
+            for warning in &board.warnings {
+                warn!("{warning}");
+            }
+        }
+        Command::Metrics { root, json } => {
+            let root = normalize_root(&root)?;
+            let metrics = metrics(&root)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&metrics)?);
+            } else {
+                print!("{}", render_metrics_markdown(&metrics));
+            }
+        }
+        Command::Merge { root, from } => {
+            let root = normalize_root(&root)?;
+            let from = normalize_root(&from)?;
+            if from == root {
+                anyhow::bail!("--from is this workspace");
+            }
+            let (p, src) = (scaffold(&root, false)?, paths_for(&from));
+            if !src.isnad_dir.is_dir() {
+                anyhow::bail!("{} has no .isnad directory", from.display());
+            }
+
+            for (dst, src) in [(&p.ledger, &src.ledger), (&p.control, &src.control)] {
+                if !src.exists() {
+                    continue;
+                }
+                let report = merge_jsonl(dst, src)?;
+                info!(
+                    "{}: appended {}, already present {}, conflicting {}",
+                    dst.display(),
+                    report.appended,
+                    report.skipped,
+                    report.conflicting
+                );
+                if report.unreadable > 0 {
+                    warn!("{}: left out {} lines that aren't JSON objects", src.display(), report.unreadable);
+                }
+                if report.conflicting > 0 {
+                    warn!("Conflicting records were written to {}", p.conflicts.display());
+                }
+            }
+            let board = fold(&root)?;
+            let (json_path, md_path) = write_state(&root, &board)?;
+            info!("Wrote {}", json_path.display());
+            info!("Wrote {}", md_path.display());
+        }
+        Command::Export { root, format, out } => {
+            let root = normalize_root(&root)?;
+            let board = fold(&root)?;
+            let rendered = match format.as_str() {
+                "md" => render_markdown(&board),
+                "json" => format!("{}\n", serde_json::to_string_pretty(&board)?),
+                _ => render_html(&board),
+            };
+            match out {
+                Some(path) => {
+                    std::fs::write(&path, rendered).with_context(|| format!("write {}", path.display()))?;
+                    info!("Wrote {}", path.display());
+                }
+                None => print!("{rendered}"),
+            }
+        }
     }
 
     Ok(())
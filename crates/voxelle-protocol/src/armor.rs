@@ -0,0 +1,143 @@
+use base64::Engine;
+use flate2::read::{DeflateDecoder, DeflateEncoder};
+use flate2::Compression;
+use std::io::Read;
+
+use crate::error::{ProtocolError, Result};
+
+// Copy-paste armor for SDP blobs:
+//
+//   -----BEGIN VOXELLE OFFER-----
+//   <base64url(raw deflate(sdp)), wrapped at 76 columns>
+//   =<crc32(sdp) as 8 lowercase hex digits>
+//   -----END VOXELLE OFFER-----
+//
+// `=` never appears in unpadded base64url, so the checksum line is unambiguous
+// even after all whitespace has been stripped by a chat client.
+
+pub const ARMOR_LINE_WIDTH: usize = 76;
+pub const MAX_ARMORED_CHARS: usize = 256 * 1024;
+pub const MAX_DEARMORED_SDP_BYTES: usize = 128 * 1024;
+
+const BEGIN: &str = "-----BEGINVOXELLE";
+const END: &str = "-----ENDVOXELLE";
+const DASHES: &str = "-----";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdpKind {
+    Offer,
+    Answer,
+}
+
+impl SdpKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Offer => "OFFER",
+            Self::Answer => "ANSWER",
+        }
+    }
+
+    fn from_label(s: &str) -> Option<Self> {
+        match s {
+            "OFFER" => Some(Self::Offer),
+            "ANSWER" => Some(Self::Answer),
+            _ => None,
+        }
+    }
+}
+
+pub fn armor_sdp(kind: SdpKind, sdp: &str) -> String {
+    let mut compressed = vec![];
+    DeflateEncoder::new(sdp.as_bytes(), Compression::best())
+        .read_to_end(&mut compressed)
+        .expect("in-memory deflate cannot fail");
+    let body = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(compressed);
+
+    let label = kind.label();
+    let mut out = format!("-----BEGIN VOXELLE {label}-----\n");
+    for chunk in body.as_bytes().chunks(ARMOR_LINE_WIDTH) {
+        out.push_str(std::str::from_utf8(chunk).expect("base64 is ascii"));
+        out.push('\n');
+    }
+    out.push_str(&format!("={:08x}\n", crc32fast::hash(sdp.as_bytes())));
+    out.push_str(&format!("-----END VOXELLE {label}-----\n"));
+    out
+}
+
+pub fn dearmor_sdp(text: &str) -> Result<String> {
+    dearmor_sdp_with_kind(text).map(|(_, sdp)| sdp)
+}
+
+pub fn dearmor_sdp_with_kind(text: &str) -> Result<(SdpKind, String)> {
+    if text.len() > MAX_ARMORED_CHARS {
+        return Err(ProtocolError::Armor("armored sdp too large"));
+    }
+    let s = normalize_pasted(text);
+
+    let start = s
+        .find(BEGIN)
+        .ok_or(ProtocolError::Armor("armor BEGIN line not found"))?;
+    let rest = &s[start + BEGIN.len()..];
+    let label_len = rest
+        .find(|c: char| !c.is_ascii_uppercase())
+        .unwrap_or(rest.len());
+    let label = &rest[..label_len];
+    let kind = SdpKind::from_label(label).ok_or(ProtocolError::Armor("unknown armor label"))?;
+    let rest = rest[label_len..]
+        .strip_prefix(DASHES)
+        .ok_or(ProtocolError::Armor("armor BEGIN line malformed"))?;
+
+    let crc_at = rest
+        .find('=')
+        .ok_or(ProtocolError::Armor("armor checksum line missing"))?;
+    let body = &rest[..crc_at];
+    let rest = &rest[crc_at + 1..];
+    if rest.len() < 8 {
+        return Err(ProtocolError::Armor("armor checksum truncated"));
+    }
+    let (crc_hex, rest) = rest.split_at(8);
+    let want_crc = u32::from_str_radix(crc_hex, 16)
+        .map_err(|_| ProtocolError::Armor("armor checksum not hex"))?;
+
+    let end = format!("{END}{label}{DASHES}");
+    if !rest.starts_with(&end) {
+        return Err(ProtocolError::Armor("armor END line missing or mismatched"));
+    }
+
+    let compressed = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(body)
+        .map_err(|_| ProtocolError::Armor("armor body is not base64url"))?;
+    let mut decoded = vec![];
+    DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_DEARMORED_SDP_BYTES as u64 + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|_| ProtocolError::Armor("armor body failed to inflate"))?;
+    if decoded.len() > MAX_DEARMORED_SDP_BYTES {
+        return Err(ProtocolError::Armor("dearmored sdp too large"));
+    }
+    if crc32fast::hash(&decoded) != want_crc {
+        return Err(ProtocolError::Armor("armor checksum mismatch"));
+    }
+    let sdp = String::from_utf8(decoded)
+        .map_err(|_| ProtocolError::Armor("dearmored sdp is not utf-8"))?;
+    Ok((kind, sdp))
+}
+
+// Undo common chat-client damage: drop all whitespace (rewrapping, indentation,
+// NBSP), quotes and zero-width characters, and turn typographic dashes back
+// into ASCII (`--` is commonly auto-replaced by an em dash).
+fn normalize_pasted(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            c if c.is_whitespace() => {}
+            '"' | '\'' | '`' | '\u{201c}' | '\u{201d}' | '\u{2018}' | '\u{2019}' | '\u{00ab}'
+            | '\u{00bb}' => {}
+            '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{2060}' | '\u{feff}' => {}
+            '\u{2014}' => out.push_str("--"),
+            '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2212}' => out.push('-'),
+            c => out.push(c),
+        }
+    }
+    out
+}
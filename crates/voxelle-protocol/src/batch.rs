@@ -0,0 +1,92 @@
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use std::fmt;
+
+use crate::envelope::SignedEnvelope;
+use crate::error::ProtocolError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchError {
+    /// Indices (ascending) of the items that failed verification or could
+    /// not be decoded.
+    pub failed: Vec<usize>,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} signature(s) failed verification at {:?}",
+            self.failed.len(),
+            self.failed
+        )
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// Verifies all signatures with a single batched check, falling back to
+/// per-item verification to pinpoint the failures when the batch is rejected.
+pub fn verify_batch(items: &[(VerifyingKey, &[u8], &Signature)]) -> Result<(), BatchError> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    let keys: Vec<VerifyingKey> = items.iter().map(|(k, _, _)| *k).collect();
+    let msgs: Vec<&[u8]> = items.iter().map(|(_, m, _)| *m).collect();
+    let sigs: Vec<Signature> = items.iter().map(|(_, _, s)| **s).collect();
+    if ed25519_dalek::verify_batch(&msgs, &sigs, &keys).is_ok() {
+        return Ok(());
+    }
+
+    let failed: Vec<usize> = items
+        .iter()
+        .enumerate()
+        .filter(|(_, (k, m, s))| k.verify(m, s).is_err())
+        .map(|(i, _)| i)
+        .collect();
+    if failed.is_empty() {
+        // The batch equation is cofactored while single verification is not;
+        // a batch rejection with no individual failure still means "don't trust it".
+        return Err(BatchError {
+            failed: (0..items.len()).collect(),
+        });
+    }
+    Err(BatchError { failed })
+}
+
+pub fn verify_envelopes_batch(envelopes: &[SignedEnvelope]) -> Result<(), BatchError> {
+    let mut failed = vec![];
+    let mut prepared = vec![];
+    for (i, env) in envelopes.iter().enumerate() {
+        // Only Ed25519 can be batched; other algorithms are checked on the spot.
+        let parts = (|| -> Result<_, ProtocolError> {
+            let pk = env.public_key()?;
+            match pk.as_ed25519() {
+                Some(vk) => Ok(Some((
+                    *vk,
+                    env.envelope.signature_input()?,
+                    env.signature()?,
+                ))),
+                None => env.verify().map(|()| None),
+            }
+        })();
+        match parts {
+            Ok(Some(p)) => prepared.push((i, p)),
+            Ok(None) => {}
+            Err(_) => failed.push(i),
+        }
+    }
+
+    let items: Vec<(VerifyingKey, &[u8], &Signature)> = prepared
+        .iter()
+        .map(|(_, (k, m, s))| (*k, m.as_slice(), s))
+        .collect();
+    if let Err(e) = verify_batch(&items) {
+        failed.extend(e.failed.into_iter().map(|j| prepared[j].0));
+    }
+
+    if failed.is_empty() {
+        return Ok(());
+    }
+    failed.sort_unstable();
+    Err(BatchError { failed })
+}
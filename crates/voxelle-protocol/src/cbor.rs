@@ -0,0 +1,149 @@
+use base64::Engine;
+use ciborium::Value;
+
+use crate::envelope::{Envelope, EnvelopeLimits, SignedEnvelope};
+use crate::error::{ProtocolError, Result};
+use crate::netstring::{netstring, parse_netstring};
+
+// Deterministic CBOR (RFC 8949 §4.2.1) encodings of envelopes and frames.
+//
+// Signatures are still over `Envelope::signature_input`, which is built from
+// JCS, so an envelope can move between the JSON and CBOR encodings without
+// being re-signed. `sender_pub` and `sig` travel as byte strings rather than
+// Base64 text; that and the binary framing is where the savings come from.
+
+impl Envelope {
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        encode(value_of(self)?)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        decode(bytes)?
+            .deserialized()
+            .map_err(|e| ProtocolError::Cbor(format!("decode envelope: {e}")))
+    }
+}
+
+impl SignedEnvelope {
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        let mut map = value_of(&self.envelope)?
+            .into_map()
+            .expect("envelope serializes to a map");
+        map.push(("sender_pub".into(), Value::Bytes(self.sender_pub_bytes()?)));
+        map.push(("alg".into(), self.alg.as_str().into()));
+        map.push(("sig".into(), Value::Bytes(self.signature_bytes()?)));
+        encode(Value::Map(map))
+    }
+
+    pub fn from_cbor(bytes: &[u8], limits: &EnvelopeLimits) -> Result<Self> {
+        if bytes.len() > limits.max_frame_bytes {
+            return Err(ProtocolError::Cbor(format!(
+                "envelope too large ({} > {} bytes)",
+                bytes.len(),
+                limits.max_frame_bytes
+            )));
+        }
+        let mut map = decode(bytes)?
+            .into_map()
+            .map_err(|_| ProtocolError::Cbor("signed envelope must be a map".into()))?;
+        let sender_pub = take_bytes(&mut map, "sender_pub")?;
+        let sig = take_bytes(&mut map, "sig")?;
+        let alg = match map.iter().position(|(k, _)| k.as_text() == Some("alg")) {
+            Some(i) => map
+                .remove(i)
+                .1
+                .deserialized()
+                .map_err(|e| ProtocolError::Cbor(format!("decode signed envelope alg: {e}")))?,
+            // As in JSON, envelopes that predate `alg` are Ed25519.
+            None => Default::default(),
+        };
+        let envelope = Value::Map(map)
+            .deserialized()
+            .map_err(|e| ProtocolError::Cbor(format!("decode envelope: {e}")))?;
+        let b64 = base64::engine::general_purpose::STANDARD;
+        Ok(Self {
+            envelope,
+            sender_pub: b64.encode(sender_pub),
+            alg,
+            sig: b64.encode(sig),
+        })
+    }
+
+    /// `netstring(CBOR(SignedEnvelope))`, the CBOR counterpart of `to_frame`.
+    pub fn to_cbor_frame(&self) -> Result<Vec<u8>> {
+        Ok(netstring(&self.to_cbor()?))
+    }
+
+    pub fn from_cbor_frame(frame: &[u8], limits: &EnvelopeLimits) -> Result<Self> {
+        let (body, rest) = parse_netstring(frame, limits.max_frame_bytes)?;
+        if !rest.is_empty() {
+            return Err(ProtocolError::Netstring(
+                "trailing bytes after envelope frame",
+            ));
+        }
+        Self::from_cbor(body, limits)
+    }
+}
+
+fn value_of<T: serde::Serialize>(v: &T) -> Result<Value> {
+    Value::serialized(v).map_err(|e| ProtocolError::Cbor(format!("encode: {e}")))
+}
+
+fn take_bytes(map: &mut Vec<(Value, Value)>, key: &'static str) -> Result<Vec<u8>> {
+    let i = map
+        .iter()
+        .position(|(k, _)| k.as_text() == Some(key))
+        .ok_or_else(|| ProtocolError::Cbor(format!("missing {key}")))?;
+    map.remove(i)
+        .1
+        .into_bytes()
+        .map_err(|_| ProtocolError::Cbor(format!("{key} must be a byte string")))
+}
+
+fn encode(value: Value) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    ciborium::into_writer(&canonicalize(value)?, &mut out)
+        .map_err(|e| ProtocolError::Cbor(format!("encode: {e}")))?;
+    Ok(out)
+}
+
+// Only the deterministic encoding is accepted, so every message has exactly
+// one CBOR form: re-encoding what was read must reproduce the input.
+// ciborium already emits definite lengths and the shortest integer and float
+// forms, which leaves map key order and duplicate keys to `canonicalize`.
+fn decode(bytes: &[u8]) -> Result<Value> {
+    let value: Value =
+        ciborium::from_reader(bytes).map_err(|e| ProtocolError::Cbor(format!("decode: {e}")))?;
+    if encode(value.clone())? != bytes {
+        return Err(ProtocolError::Cbor("not in deterministic encoding".into()));
+    }
+    Ok(value)
+}
+
+// Sorts map keys by their encoded bytes (RFC 8949 §4.2.1), recursively.
+fn canonicalize(value: Value) -> Result<Value> {
+    Ok(match value {
+        Value::Array(items) => {
+            Value::Array(items.into_iter().map(canonicalize).collect::<Result<_>>()?)
+        }
+        Value::Map(entries) => {
+            let mut keyed = entries
+                .into_iter()
+                .map(|(k, v)| {
+                    let k = canonicalize(k)?;
+                    let mut kb = Vec::new();
+                    ciborium::into_writer(&k, &mut kb)
+                        .map_err(|e| ProtocolError::Cbor(format!("encode: {e}")))?;
+                    Ok((kb, k, canonicalize(v)?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            keyed.sort_by(|a, b| a.0.cmp(&b.0));
+            if keyed.windows(2).any(|w| w[0].0 == w[1].0) {
+                return Err(ProtocolError::Cbor("duplicate map key".into()));
+            }
+            Value::Map(keyed.into_iter().map(|(_, k, v)| (k, v)).collect())
+        }
+        Value::Tag(tag, inner) => Value::Tag(tag, Box::new(canonicalize(*inner)?)),
+        other => other,
+    })
+}
@@ -0,0 +1,25 @@
+use subtle::ConstantTimeEq;
+
+// Constant-time equality for secrets and authenticators (session secrets,
+// tokens, digests, ids checked during authentication). Only the length of the
+// inputs may leak; the contents never short-circuit the comparison.
+
+pub fn ct_eq_bytes(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.ct_eq(b).into()
+}
+
+pub fn ct_eq_str(a: &str, b: &str) -> bool {
+    ct_eq_bytes(a.as_bytes(), b.as_bytes())
+}
+
+/// Compares `actual` against a hex digest (either case). Malformed hex or a
+/// length mismatch is simply "not equal".
+pub fn verify_hex_digest(expected_hex: &str, actual: &[u8]) -> bool {
+    let Ok(expected) = hex::decode(expected_hex.trim()) else {
+        return false;
+    };
+    ct_eq_bytes(&expected, actual)
+}
@@ -0,0 +1,287 @@
+use base64::Engine;
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::{Signature, Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io;
+
+use crate::ct::ct_eq_str;
+use crate::error::{ProtocolError, Result};
+use crate::ids::PrincipalId;
+use crate::jcs::{jcs_bytes, jcs_bytes_with_policy, JcsPolicy};
+use crate::netstring::{netstring, parse_netstring, NetstringWriter};
+use crate::public_key::{parse_spki, PublicKey, PublicKeyAlg};
+use crate::session::random_hex;
+use crate::time::CanonicalTime;
+
+pub const ENVELOPE_PREFIX: &str = "p2pspace/envelope/v0\n";
+pub const MSG_ID_BYTES: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub v: u32,
+    pub kind: String,
+    pub msg_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_reply_to: Option<String>,
+    pub sender: PrincipalId,
+    /// Unix ms.
+    pub ts: i64,
+    pub payload: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnvelopeLimits {
+    pub max_kind_bytes: usize,
+    pub max_msg_id_bytes: usize,
+    pub max_payload_bytes: usize,
+    pub max_frame_bytes: usize,
+}
+
+impl Default for EnvelopeLimits {
+    fn default() -> Self {
+        Self {
+            max_kind_bytes: 64,
+            max_msg_id_bytes: 128,
+            max_payload_bytes: 64 * 1024,
+            max_frame_bytes: 96 * 1024,
+        }
+    }
+}
+
+// What actually goes on the wire: the envelope plus the sender's SPKI and a
+// signature over `Envelope::signature_input` by that key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedEnvelope {
+    #[serde(flatten)]
+    pub envelope: Envelope,
+    /// SPKI DER, standard Base64.
+    pub sender_pub: String,
+    /// Must match the algorithm of `sender_pub`. Frames from before this field
+    /// existed are Ed25519.
+    #[serde(default)]
+    pub alg: PublicKeyAlg,
+    /// Standard Base64 signature, see `PublicKey::verify` for the format.
+    pub sig: String,
+}
+
+impl Envelope {
+    pub fn new(kind: &str, sender: PrincipalId, ts: i64, payload: Value) -> Self {
+        Self {
+            v: 1,
+            kind: kind.to_string(),
+            msg_id: random_hex(MSG_ID_BYTES),
+            in_reply_to: None,
+            sender,
+            ts,
+            payload,
+        }
+    }
+
+    pub fn reply(&self, kind: &str, sender: PrincipalId, ts: i64, payload: Value) -> Self {
+        Self {
+            in_reply_to: Some(self.msg_id.clone()),
+            ..Self::new(kind, sender, ts, payload)
+        }
+    }
+
+    /// `ts` as a `CanonicalTime`. The signed field stays unix ms, so this
+    /// drops the milliseconds.
+    pub fn sent_at(&self) -> Result<CanonicalTime> {
+        CanonicalTime::from_unix_ms(self.ts)
+    }
+
+    pub fn validate(&self, limits: &EnvelopeLimits) -> Result<()> {
+        if self.v != 1 {
+            return Err(ProtocolError::InvalidEnvelope("v must be 1".into()));
+        }
+        if self.kind.is_empty() || self.kind.len() > limits.max_kind_bytes {
+            return Err(ProtocolError::InvalidEnvelope(format!(
+                "kind must be 1-{} bytes",
+                limits.max_kind_bytes
+            )));
+        }
+        if self.msg_id.is_empty() || self.msg_id.len() > limits.max_msg_id_bytes {
+            return Err(ProtocolError::InvalidEnvelope(format!(
+                "msg_id must be 1-{} bytes",
+                limits.max_msg_id_bytes
+            )));
+        }
+        if let Some(r) = &self.in_reply_to {
+            if r.is_empty() || r.len() > limits.max_msg_id_bytes {
+                return Err(ProtocolError::InvalidEnvelope(format!(
+                    "in_reply_to must be 1-{} bytes",
+                    limits.max_msg_id_bytes
+                )));
+            }
+        }
+        let payload_len = jcs_bytes(&self.payload)?.len();
+        if payload_len > limits.max_payload_bytes {
+            return Err(ProtocolError::InvalidEnvelope(format!(
+                "payload too large ({payload_len} > {} bytes)",
+                limits.max_payload_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Signed payloads are integers-only JCS, see `JcsPolicy::IntegersOnly`.
+    pub fn signature_input(&self) -> Result<Vec<u8>> {
+        let payload_jcs = jcs_bytes_with_policy(&self.payload, JcsPolicy::IntegersOnly)?;
+        Ok(self
+            .write_signature_input(NetstringWriter::new(Vec::new()), &payload_jcs)
+            .expect("vec write cannot fail"))
+    }
+
+    fn write_signature_input(
+        &self,
+        mut w: NetstringWriter<Vec<u8>>,
+        payload_jcs: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        w.write_prefix(ENVELOPE_PREFIX)?;
+        w.write_int(i64::from(self.v))?;
+        w.write_str(&self.kind)?;
+        w.write_str(&self.msg_id)?;
+        w.write_str(self.in_reply_to.as_deref().unwrap_or(""))?;
+        w.write_str(self.sender.as_str())?;
+        w.write_int(self.ts)?;
+        w.write_bytes(payload_jcs)?;
+        Ok(w.into_inner())
+    }
+
+    pub fn sign(self, key: &SigningKey) -> Result<SignedEnvelope> {
+        let spki = key
+            .verifying_key()
+            .to_public_key_der()
+            .expect("Ed25519 SPKI encoding cannot fail");
+        if !ct_eq_str(
+            PrincipalId::from_spki_der(spki.as_bytes()).as_str(),
+            self.sender.as_str(),
+        ) {
+            return Err(ProtocolError::EnvelopeSender(
+                "signing key does not match envelope sender",
+            ));
+        }
+        let sig = key.sign(&self.signature_input()?);
+        Ok(SignedEnvelope {
+            envelope: self,
+            sender_pub: base64::engine::general_purpose::STANDARD.encode(spki.as_bytes()),
+            alg: PublicKeyAlg::Ed25519,
+            sig: base64::engine::general_purpose::STANDARD.encode(sig.to_bytes()),
+        })
+    }
+
+    /// Attaches a signature made elsewhere (e.g. by a hardware-backed key) over
+    /// `signature_input`, and checks it before returning.
+    pub fn with_signature(self, sender_spki_der: &[u8], sig: &[u8]) -> Result<SignedEnvelope> {
+        let signed = SignedEnvelope {
+            alg: parse_spki(sender_spki_der)?.alg(),
+            envelope: self,
+            sender_pub: base64::engine::general_purpose::STANDARD.encode(sender_spki_der),
+            sig: base64::engine::general_purpose::STANDARD.encode(sig),
+        };
+        signed.verify()?;
+        Ok(signed)
+    }
+
+    /// Signs and frames the envelope: `netstring(JCS(SignedEnvelope))`.
+    pub fn signed_frame(self, key: &SigningKey) -> Result<Vec<u8>> {
+        self.sign(key)?.to_frame()
+    }
+
+    /// Decodes a frame, verifying the signature and that it came from `expected_sender`.
+    pub fn from_frame(frame: &[u8], expected_sender: &PrincipalId) -> Result<Self> {
+        Self::from_frame_with_limits(frame, expected_sender, &EnvelopeLimits::default())
+    }
+
+    pub fn from_frame_with_limits(
+        frame: &[u8],
+        expected_sender: &PrincipalId,
+        limits: &EnvelopeLimits,
+    ) -> Result<Self> {
+        let signed = SignedEnvelope::from_frame(frame, limits)?;
+        if !ct_eq_str(signed.envelope.sender.as_str(), expected_sender.as_str()) {
+            return Err(ProtocolError::EnvelopeSender(
+                "envelope sender is not the expected peer",
+            ));
+        }
+        signed.envelope.validate(limits)?;
+        signed.verify()?;
+        Ok(signed.envelope)
+    }
+}
+
+impl SignedEnvelope {
+    pub fn to_frame(&self) -> Result<Vec<u8>> {
+        Ok(netstring(&jcs_bytes(self)?))
+    }
+
+    pub fn from_frame(frame: &[u8], limits: &EnvelopeLimits) -> Result<Self> {
+        let (body, rest) = parse_netstring(frame, limits.max_frame_bytes)?;
+        if !rest.is_empty() {
+            return Err(ProtocolError::Netstring(
+                "trailing bytes after envelope frame",
+            ));
+        }
+        serde_json::from_slice(body).map_err(ProtocolError::EnvelopeDecode)
+    }
+
+    /// The sender key, checked against `envelope.sender` and `alg`.
+    pub fn public_key(&self) -> Result<PublicKey> {
+        let spki = self.sender_pub_bytes()?;
+        if !ct_eq_str(
+            PrincipalId::from_spki_der(&spki).as_str(),
+            self.envelope.sender.as_str(),
+        ) {
+            return Err(ProtocolError::EnvelopeSender(
+                "envelope sender does not match sender_pub",
+            ));
+        }
+        let pk = parse_spki(&spki)?;
+        if pk.alg() != self.alg {
+            return Err(ProtocolError::AlgorithmMismatch {
+                declared: self.alg,
+                actual: pk.alg(),
+            });
+        }
+        Ok(pk)
+    }
+
+    /// The sender key for Ed25519 envelopes; other algorithms are rejected.
+    pub fn verifying_key(&self) -> Result<ed25519_dalek::VerifyingKey> {
+        let pk = self.public_key()?;
+        pk.as_ed25519()
+            .copied()
+            .ok_or_else(|| ProtocolError::WrongAlgorithm(pk.alg().to_string()))
+    }
+
+    pub fn sender_pub_bytes(&self) -> Result<Vec<u8>> {
+        base64::engine::general_purpose::STANDARD
+            .decode(self.sender_pub.trim())
+            .map_err(|_| ProtocolError::Base64("envelope sender_pub"))
+    }
+
+    pub fn signature_bytes(&self) -> Result<Vec<u8>> {
+        base64::engine::general_purpose::STANDARD
+            .decode(self.sig.trim())
+            .map_err(|_| ProtocolError::Base64("envelope sig"))
+    }
+
+    /// The Ed25519 signature; see `signature_bytes` for other algorithms.
+    pub fn signature(&self) -> Result<Signature> {
+        Signature::from_slice(&self.signature_bytes()?)
+            .map_err(|_| ProtocolError::MalformedSignature("envelope"))
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        let pk = self.public_key()?;
+        let sig = self.signature_bytes()?;
+        pk.verify(&self.envelope.signature_input()?, &sig)
+            .map_err(|e| match e {
+                ProtocolError::MalformedSignature(_) => {
+                    ProtocolError::MalformedSignature("envelope")
+                }
+                _ => ProtocolError::SignatureInvalid("envelope"),
+            })
+    }
+}
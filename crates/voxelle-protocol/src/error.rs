@@ -0,0 +1,111 @@
+use thiserror::Error;
+
+use crate::batch::BatchError;
+use crate::jcs::JcsError;
+use crate::negotiate::NegotiationError;
+use crate::public_key::PublicKeyAlg;
+
+pub(crate) type Result<T, E = ProtocolError> = std::result::Result<T, E>;
+
+// Every fallible public API in this crate returns `ProtocolError`, so callers can
+// match on the failure instead of its message. It converts into `anyhow::Error`
+// through anyhow's blanket `From` impl.
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("parse SPKI SubjectPublicKeyInfo: {0}")]
+    SpkiParse(#[source] spki::der::Error),
+    #[error("SPKI is {0} bytes, over the size limit")]
+    SpkiTooLarge(usize),
+    #[error("{0} trailing bytes after SPKI")]
+    SpkiTrailingBytes(usize),
+    #[error("unexpected SPKI algorithm {0}")]
+    WrongAlgorithm(String),
+    #[error("signed with {declared} but sender key is {actual}")]
+    AlgorithmMismatch {
+        declared: PublicKeyAlg,
+        actual: PublicKeyAlg,
+    },
+    #[error("Ed25519 public key must be 32 bytes (got {0})")]
+    BadKeyLength(usize),
+    #[error("invalid Ed25519 public key")]
+    InvalidPublicKey,
+    #[error(transparent)]
+    Jcs(#[from] JcsError),
+    #[error("malformed netstring: {0}")]
+    Netstring(&'static str),
+    #[error("invalid principal id: {0}")]
+    InvalidPrincipalId(&'static str),
+    /// Named field is not valid standard Base64.
+    #[error("{0} is not valid base64")]
+    Base64(&'static str),
+    #[error("{0} signature is malformed")]
+    MalformedSignature(&'static str),
+    #[error("{0} signature invalid")]
+    SignatureInvalid(&'static str),
+    #[error("invalid envelope: {0}")]
+    InvalidEnvelope(String),
+    #[error("envelope sender mismatch: {0}")]
+    EnvelopeSender(&'static str),
+    #[error("decode envelope frame: {0}")]
+    EnvelopeDecode(#[source] serde_json::Error),
+    #[error("invalid revocation: {0}")]
+    InvalidRevocation(String),
+    #[error("invalid delegation: {0}")]
+    InvalidDelegation(String),
+    #[error("{kind} {id} is revoked")]
+    Revoked { kind: &'static str, id: String },
+    #[error("{0}")]
+    Armor(&'static str),
+    #[error("invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+    #[cfg(feature = "cbor")]
+    #[error("cbor: {0}")]
+    Cbor(String),
+    #[error("invalid invite: {0}")]
+    InvalidInvite(String),
+    #[error("invalid pairing code: {0}")]
+    InvalidPairingCode(String),
+    #[error("sealed box: {0}")]
+    SealedBox(String),
+    #[error(transparent)]
+    Negotiation(#[from] NegotiationError),
+    #[error(transparent)]
+    Batch(#[from] BatchError),
+}
+
+impl ProtocolError {
+    /// Stable machine-readable name of the variant, e.g. for the `code` of JS
+    /// exceptions thrown by the wasm bindings.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::SpkiParse(_) => "spki_parse",
+            Self::SpkiTooLarge(_) => "spki_too_large",
+            Self::SpkiTrailingBytes(_) => "spki_trailing_bytes",
+            Self::WrongAlgorithm(_) => "wrong_algorithm",
+            Self::AlgorithmMismatch { .. } => "algorithm_mismatch",
+            Self::BadKeyLength(_) => "bad_key_length",
+            Self::InvalidPublicKey => "invalid_public_key",
+            Self::Jcs(_) => "jcs",
+            Self::Netstring(_) => "netstring",
+            Self::InvalidPrincipalId(_) => "invalid_principal_id",
+            Self::Base64(_) => "base64",
+            Self::MalformedSignature(_) => "malformed_signature",
+            Self::SignatureInvalid(_) => "signature_invalid",
+            Self::InvalidEnvelope(_) => "invalid_envelope",
+            Self::EnvelopeSender(_) => "envelope_sender",
+            Self::EnvelopeDecode(_) => "envelope_decode",
+            Self::InvalidRevocation(_) => "invalid_revocation",
+            Self::InvalidDelegation(_) => "invalid_delegation",
+            Self::Revoked { .. } => "revoked",
+            Self::Armor(_) => "armor",
+            Self::InvalidTimestamp(_) => "invalid_timestamp",
+            #[cfg(feature = "cbor")]
+            Self::Cbor(_) => "cbor",
+            Self::InvalidInvite(_) => "invalid_invite",
+            Self::InvalidPairingCode(_) => "invalid_pairing_code",
+            Self::SealedBox(_) => "sealed_box",
+            Self::Negotiation(_) => "negotiation",
+            Self::Batch(_) => "batch",
+        }
+    }
+}
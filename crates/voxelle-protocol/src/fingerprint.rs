@@ -0,0 +1,81 @@
+use sha2::{Digest, Sha256};
+
+use crate::ids::PrincipalId;
+use crate::session::wordlist;
+
+// Domain-separated from the id hash itself (and from any SAS derivation), so a
+// fingerprint shown in one context can't be replayed as a code in another.
+pub const FINGERPRINT_PREFIX: &[u8] = b"p2pspace/fingerprint/v0\n";
+
+pub const FINGERPRINT_HEX_GROUPS: usize = 8;
+pub const FINGERPRINT_WORDS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint([u8; 32]);
+
+pub fn fingerprint(id: &PrincipalId) -> Fingerprint {
+    let mut h = Sha256::new();
+    h.update(FINGERPRINT_PREFIX);
+    h.update(id.hash_bytes());
+    Fingerprint(h.finalize().into())
+}
+
+impl Fingerprint {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// `3f2a 91bc 04de ...`: the first 8 groups of 4 lowercase hex digits.
+    pub fn grouped_hex(&self) -> String {
+        self.0[..FINGERPRINT_HEX_GROUPS * 2]
+            .chunks(2)
+            .map(hex::encode)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Each word is picked by the next big-endian `u16` of the fingerprint.
+    /// Values at or past the last whole multiple of the list's length are
+    /// skipped, so every word is equally likely; if the 32 bytes run out,
+    /// hashing them again gives more.
+    pub fn words(&self) -> Vec<&'static str> {
+        let list = wordlist();
+        let limit = (1 << 16) / list.len() * list.len();
+        let mut words = Vec::with_capacity(FINGERPRINT_WORDS);
+        let mut block = self.0;
+        while words.len() < FINGERPRINT_WORDS {
+            let picks = block
+                .chunks(2)
+                .map(|c| usize::from(u16::from_be_bytes([c[0], c[1]])))
+                .filter(|v| *v < limit)
+                .map(|v| list[v % list.len()]);
+            words.extend(picks.take(FINGERPRINT_WORDS - words.len()));
+            block = Sha256::digest(block).into();
+        }
+        words
+    }
+
+    pub fn word_string(&self) -> String {
+        self.words().join(" ")
+    }
+
+    /// Seed for a UI avatar generator; taken from bytes not used by the other renderings.
+    pub fn identicon_seed(&self) -> u64 {
+        u64::from_be_bytes(self.0[24..32].try_into().expect("8 bytes"))
+    }
+
+    /// Compares user-typed text against either rendering, ignoring case,
+    /// whitespace and separators such as `-`, `:` or `.`.
+    pub fn matches(&self, typed: &str) -> bool {
+        let typed = squash(typed);
+        !typed.is_empty()
+            && (typed == squash(&self.grouped_hex()) || typed == squash(&self.word_string()))
+    }
+}
+
+fn squash(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
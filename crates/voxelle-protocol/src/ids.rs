@@ -1,5 +1,10 @@
 use base64::Engine;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{ProtocolError, Result};
 
 pub fn principal_id_from_spki_der(spki_der: &[u8]) -> String {
     format!("ed25519:{}", base64url_sha256(spki_der))
@@ -13,3 +18,65 @@ fn base64url_sha256(bytes: &[u8]) -> String {
     let digest = Sha256::digest(bytes);
     base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(digest)
 }
+
+// Validated `ed25519:<base64url(sha256(spki))>` id.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PrincipalId(String);
+
+impl PrincipalId {
+    pub fn from_spki_der(spki_der: &[u8]) -> Self {
+        Self(principal_id_from_spki_der(spki_der))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn hash_bytes(&self) -> [u8; 32] {
+        let (_, b64) = self.0.split_once(':').expect("validated on construction");
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(b64)
+            .expect("validated on construction");
+        bytes.try_into().expect("validated on construction")
+    }
+}
+
+impl FromStr for PrincipalId {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let b64 = s
+            .strip_prefix("ed25519:")
+            .ok_or(ProtocolError::InvalidPrincipalId(
+                "must start with ed25519:",
+            ))?;
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(b64)
+            .map_err(|_| ProtocolError::InvalidPrincipalId("hash is not base64url"))?;
+        if bytes.len() != 32 {
+            return Err(ProtocolError::InvalidPrincipalId("hash must be 32 bytes"));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for PrincipalId {
+    type Error = ProtocolError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<PrincipalId> for String {
+    fn from(id: PrincipalId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for PrincipalId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -0,0 +1,200 @@
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io;
+
+use crate::ct::ct_eq_str;
+use crate::error::{ProtocolError, Result};
+use crate::ids::principal_id_from_spki_der;
+use crate::netstring::NetstringWriter;
+use crate::revocation::RevocationSet;
+use crate::spki_ed25519::ed25519_public_key_from_spki_der;
+use crate::time::CanonicalTime;
+
+pub const MAX_INVITE_LINK_CHARS: usize = 16 * 1024;
+pub const DELEGATION_PREFIX: &str = "p2pspace/delegation/v0\n";
+
+// RFC §5.2, as embedded in invites and events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelegationCert {
+    pub v: u32,
+    pub principal_id: String,
+    /// SPKI DER, standard Base64.
+    pub principal_pub: String,
+    /// SPKI DER, standard Base64.
+    pub device_pub: String,
+    pub device_id: String,
+    /// Unix ms.
+    pub not_before_ts: i64,
+    /// Unix ms.
+    pub expires_ts: i64,
+    pub scopes: Vec<String>,
+    pub sig: String,
+}
+
+// RFC §8.2. Shared as `#invite=<base64url(JSON)>` link fragments by the web client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Invite {
+    pub v: u32,
+    pub space_id: String,
+    pub invite_id: String,
+    /// Unix ms.
+    pub issued_ts: i64,
+    /// Unix ms.
+    pub expires_ts: i64,
+    pub issuer_principal_id: String,
+    pub issuer_device_id: String,
+    /// SPKI DER, standard Base64.
+    pub issuer_device_pub: String,
+    pub issuer_delegation: DelegationCert,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite_issuer: Option<Value>,
+    pub scopes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraints: Option<Value>,
+    #[serde(default)]
+    pub bootstrap: Value,
+    pub sig: String,
+}
+
+impl DelegationCert {
+    pub fn not_before(&self) -> Result<CanonicalTime> {
+        CanonicalTime::from_unix_ms(self.not_before_ts)
+    }
+
+    pub fn expires_at(&self) -> Result<CanonicalTime> {
+        CanonicalTime::from_unix_ms(self.expires_ts)
+    }
+
+    /// RFC §7.3.2.
+    pub fn signature_input(&self) -> Vec<u8> {
+        self.write_signature_input(NetstringWriter::new(Vec::new()))
+            .expect("vec write cannot fail")
+    }
+
+    fn write_signature_input(&self, mut w: NetstringWriter<Vec<u8>>) -> io::Result<Vec<u8>> {
+        w.write_prefix(DELEGATION_PREFIX)?;
+        w.write_int(i64::from(self.v))?;
+        w.write_str(&self.principal_id)?;
+        w.write_str(&self.principal_pub)?;
+        w.write_str(&self.device_id)?;
+        w.write_str(&self.device_pub)?;
+        w.write_int(self.not_before_ts)?;
+        w.write_int(self.expires_ts)?;
+        w.write_list(self.scopes.iter().map(|s| s.as_bytes()))?;
+        Ok(w.into_inner())
+    }
+
+    /// Verifies the certificate as of `at_ms` (RFC §5.2): both ids derive
+    /// from their keys, `at_ms` is within the validity window, and `sig` is
+    /// the principal root key's.
+    pub fn verify(&self, at_ms: i64) -> Result<()> {
+        let invalid = |reason: &str| Err(ProtocolError::InvalidDelegation(reason.into()));
+        if self.v != 1 {
+            return invalid("v must be 1");
+        }
+        let decode = |b64: &str, field| {
+            base64::engine::general_purpose::STANDARD
+                .decode(b64.trim())
+                .map_err(|_| ProtocolError::Base64(field))
+        };
+        let principal_spki = decode(&self.principal_pub, "delegation principal_pub")?;
+        let device_spki = decode(&self.device_pub, "delegation device_pub")?;
+        if !ct_eq_str(
+            &principal_id_from_spki_der(&principal_spki),
+            &self.principal_id,
+        ) {
+            return invalid("principal_id does not match principal_pub");
+        }
+        if !ct_eq_str(&principal_id_from_spki_der(&device_spki), &self.device_id) {
+            return invalid("device_id does not match device_pub");
+        }
+        if at_ms < self.not_before_ts {
+            return invalid("not valid yet");
+        }
+        if at_ms > self.expires_ts {
+            return invalid("expired");
+        }
+
+        let vk = ed25519_public_key_from_spki_der(&principal_spki)?;
+        let sig_bytes = decode(&self.sig, "delegation sig")?;
+        let sig = Signature::from_slice(&sig_bytes)
+            .map_err(|_| ProtocolError::MalformedSignature("delegation"))?;
+        vk.verify(&self.signature_input(), &sig)
+            .map_err(|_| ProtocolError::SignatureInvalid("delegation"))
+    }
+
+    /// [`verify`](Self::verify), then fails if the device or its principal
+    /// is revoked at `at_ms`.
+    pub fn verify_unrevoked(&self, at_ms: i64, revocations: &RevocationSet) -> Result<()> {
+        self.verify(at_ms)?;
+        revocations.check_delegation(&self.principal_id, &self.device_id, at_ms)
+    }
+}
+
+impl Invite {
+    pub fn issued_at(&self) -> Result<CanonicalTime> {
+        CanonicalTime::from_unix_ms(self.issued_ts)
+    }
+
+    pub fn expires_at(&self) -> Result<CanonicalTime> {
+        CanonicalTime::from_unix_ms(self.expires_ts)
+    }
+
+    /// Checks `issuer_delegation` as RFC §8.2 requires: it verifies as of
+    /// `at_ms` and delegates `issuer_device_id` for `issuer_principal_id`.
+    /// The invite's own signature and issuer authorization aren't checked.
+    pub fn verify_issuer_delegation(&self, at_ms: i64) -> Result<()> {
+        let cert = &self.issuer_delegation;
+        cert.verify(at_ms)?;
+        if !ct_eq_str(&cert.device_id, &self.issuer_device_id)
+            || !ct_eq_str(&cert.principal_id, &self.issuer_principal_id)
+        {
+            return Err(ProtocolError::InvalidDelegation(
+                "does not delegate the invite's issuer".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Parses an invite link (`https://…/#invite=…`), a bare `#invite=…` fragment,
+/// or the base64url payload on its own.
+///
+/// This only checks structure; the delegation and invite signatures are not
+/// verified here.
+pub fn parse_invite(text: &str) -> Result<Invite> {
+    let text = text.trim();
+    if text.len() > MAX_INVITE_LINK_CHARS {
+        return Err(ProtocolError::InvalidInvite("link too large".into()));
+    }
+    let payload = match text.find("invite=") {
+        Some(at) => text[at + "invite=".len()..].split('&').next().unwrap_or(""),
+        None => text,
+    };
+    // The web client strips padding, but tolerate links that kept it.
+    let json = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|_| ProtocolError::InvalidInvite("payload is not base64url".into()))?;
+    let invite: Invite = serde_json::from_slice(&json)
+        .map_err(|e| ProtocolError::InvalidInvite(format!("decode invite: {e}")))?;
+
+    if invite.v != 1 {
+        return Err(ProtocolError::InvalidInvite("v must be 1".into()));
+    }
+    if invite.space_id.is_empty() || invite.invite_id.is_empty() || invite.sig.is_empty() {
+        return Err(ProtocolError::InvalidInvite(
+            "space_id, invite_id and sig are required".into(),
+        ));
+    }
+    invite.issued_at()?;
+    invite.expires_at()?;
+    let read_scope = format!("space:{}:read", invite.space_id);
+    if !invite.scopes.contains(&read_scope) {
+        return Err(ProtocolError::InvalidInvite(format!(
+            "missing required scope {read_scope}"
+        )));
+    }
+    Ok(invite)
+}
@@ -1,7 +1,430 @@
-use anyhow::{Context, Result};
+use serde::ser::{self, Serialize};
+use serde_json::{Map, Number, Value};
+use std::fmt;
 
-pub fn jcs_bytes<T: serde::Serialize>(value: &T) -> Result<Vec<u8>> {
-    let s = serde_jcs::to_string(value).context("serialize to JCS")?;
+// Largest integer every JCS implementation can represent exactly (IEEE 754 double).
+pub const JCS_MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JcsPolicy {
+    /// Finite floats are allowed and formatted per RFC 8785 §3.2.2.3.
+    #[default]
+    AllowFloats,
+    /// Any floating point value is rejected. Use for signature-bearing payloads,
+    /// where float formatting differences across languages break verification.
+    IntegersOnly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JcsError {
+    NonFiniteNumber { path: String },
+    FloatForbidden { path: String },
+    IntegerOutOfRange { path: String },
+    NonStringKey { path: String },
+    Serialize(String),
+}
+
+impl JcsError {
+    /// JSON pointer (RFC 6901) of the offending value, if known.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::NonFiniteNumber { path }
+            | Self::FloatForbidden { path }
+            | Self::IntegerOutOfRange { path }
+            | Self::NonStringKey { path } => Some(path),
+            Self::Serialize(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for JcsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFiniteNumber { path } => {
+                write!(f, "NaN or Infinity at {path:?} is not valid JSON")
+            }
+            Self::FloatForbidden { path } => {
+                write!(f, "floating point number at {path:?} is not allowed")
+            }
+            Self::IntegerOutOfRange { path } => {
+                write!(
+                    f,
+                    "integer at {path:?} exceeds the JCS safe range (2^53 - 1)"
+                )
+            }
+            Self::NonStringKey { path } => write!(f, "map key at {path:?} must be a string"),
+            Self::Serialize(msg) => write!(f, "serialize to JCS: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for JcsError {}
+
+impl ser::Error for JcsError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Serialize(msg.to_string())
+    }
+}
+
+pub fn jcs_bytes<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, JcsError> {
+    jcs_bytes_with_policy(value, JcsPolicy::AllowFloats)
+}
+
+pub fn jcs_bytes_with_policy<T: serde::Serialize>(
+    value: &T,
+    policy: JcsPolicy,
+) -> Result<Vec<u8>, JcsError> {
+    let value = jcs_value_with_policy(value, policy)?;
+    let s = serde_jcs::to_string(&value).map_err(|e| JcsError::Serialize(e.to_string()))?;
     Ok(s.into_bytes())
 }
 
+/// Normalizes `value` to a `Value` whose numbers are all representable in JCS,
+/// rejecting NaN/Infinity and unsafe integers with the JSON pointer to blame.
+pub fn jcs_value<T: serde::Serialize>(value: &T) -> Result<Value, JcsError> {
+    jcs_value_with_policy(value, JcsPolicy::AllowFloats)
+}
+
+pub fn jcs_value_with_policy<T: serde::Serialize>(
+    value: &T,
+    policy: JcsPolicy,
+) -> Result<Value, JcsError> {
+    value.serialize(ValueSerializer {
+        path: String::new(),
+        policy,
+    })
+}
+
+fn push_pointer(path: &str, token: &str) -> String {
+    format!("{path}/{}", token.replace('~', "~0").replace('/', "~1"))
+}
+
+struct ValueSerializer {
+    path: String,
+    policy: JcsPolicy,
+}
+
+impl ValueSerializer {
+    fn child(&self, token: &str) -> Self {
+        Self {
+            path: push_pointer(&self.path, token),
+            policy: self.policy,
+        }
+    }
+
+    fn float(self, v: f64) -> Result<Value, JcsError> {
+        if self.policy == JcsPolicy::IntegersOnly {
+            return Err(JcsError::FloatForbidden { path: self.path });
+        }
+        Number::from_f64(v)
+            .map(Value::Number)
+            .ok_or(JcsError::NonFiniteNumber { path: self.path })
+    }
+
+    fn signed(self, v: i128) -> Result<Value, JcsError> {
+        if v.unsigned_abs() > JCS_MAX_SAFE_INTEGER as u128 {
+            return Err(JcsError::IntegerOutOfRange { path: self.path });
+        }
+        Ok(Value::Number((v as i64).into()))
+    }
+
+    fn unsigned(self, v: u128) -> Result<Value, JcsError> {
+        if v > JCS_MAX_SAFE_INTEGER as u128 {
+            return Err(JcsError::IntegerOutOfRange { path: self.path });
+        }
+        Ok(Value::Number((v as u64).into()))
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = JcsError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = VariantSerializer<MapSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, JcsError> {
+        Ok(Value::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Value, JcsError> {
+        self.signed(v.into())
+    }
+    fn serialize_i16(self, v: i16) -> Result<Value, JcsError> {
+        self.signed(v.into())
+    }
+    fn serialize_i32(self, v: i32) -> Result<Value, JcsError> {
+        self.signed(v.into())
+    }
+    fn serialize_i64(self, v: i64) -> Result<Value, JcsError> {
+        self.signed(v.into())
+    }
+    fn serialize_i128(self, v: i128) -> Result<Value, JcsError> {
+        self.signed(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<Value, JcsError> {
+        self.unsigned(v.into())
+    }
+    fn serialize_u16(self, v: u16) -> Result<Value, JcsError> {
+        self.unsigned(v.into())
+    }
+    fn serialize_u32(self, v: u32) -> Result<Value, JcsError> {
+        self.unsigned(v.into())
+    }
+    fn serialize_u64(self, v: u64) -> Result<Value, JcsError> {
+        self.unsigned(v.into())
+    }
+    fn serialize_u128(self, v: u128) -> Result<Value, JcsError> {
+        self.unsigned(v)
+    }
+    fn serialize_f32(self, v: f32) -> Result<Value, JcsError> {
+        self.float(v.into())
+    }
+    fn serialize_f64(self, v: f64) -> Result<Value, JcsError> {
+        self.float(v)
+    }
+    fn serialize_char(self, v: char) -> Result<Value, JcsError> {
+        Ok(Value::String(v.to_string()))
+    }
+    fn serialize_str(self, v: &str) -> Result<Value, JcsError> {
+        Ok(Value::String(v.to_string()))
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, JcsError> {
+        Ok(Value::Array(
+            v.iter().map(|b| Value::Number((*b).into())).collect(),
+        ))
+    }
+    fn serialize_none(self) -> Result<Value, JcsError> {
+        Ok(Value::Null)
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, JcsError> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Value, JcsError> {
+        Ok(Value::Null)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, JcsError> {
+        Ok(Value::Null)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, JcsError> {
+        Ok(Value::String(variant.to_string()))
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, JcsError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, JcsError> {
+        let inner = value.serialize(self.child(variant))?;
+        Ok(Value::Object(
+            [(variant.to_string(), inner)].into_iter().collect(),
+        ))
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, JcsError> {
+        Ok(SeqSerializer {
+            ser: self,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, JcsError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, JcsError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<SeqSerializer>, JcsError> {
+        let inner = self.child(variant).serialize_seq(Some(len))?;
+        Ok(VariantSerializer { variant, inner })
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, JcsError> {
+        Ok(MapSerializer {
+            ser: self,
+            map: Map::new(),
+            next_key: None,
+        })
+    }
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer, JcsError> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<MapSerializer>, JcsError> {
+        let inner = self.child(variant).serialize_map(Some(len))?;
+        Ok(VariantSerializer { variant, inner })
+    }
+}
+
+struct SeqSerializer {
+    ser: ValueSerializer,
+    items: Vec<Value>,
+}
+
+impl SeqSerializer {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), JcsError> {
+        let child = self.ser.child(&self.items.len().to_string());
+        self.items.push(value.serialize(child)?);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = JcsError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), JcsError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, JcsError> {
+        Ok(Value::Array(self.items))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = JcsError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), JcsError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, JcsError> {
+        Ok(Value::Array(self.items))
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = JcsError;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), JcsError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, JcsError> {
+        Ok(Value::Array(self.items))
+    }
+}
+
+struct MapSerializer {
+    ser: ValueSerializer,
+    map: Map<String, Value>,
+    next_key: Option<String>,
+}
+
+impl MapSerializer {
+    fn insert<T: ?Sized + Serialize>(&mut self, key: String, value: &T) -> Result<(), JcsError> {
+        let child = self.ser.child(&key);
+        let v = value.serialize(child)?;
+        self.map.insert(key, v);
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = JcsError;
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), JcsError> {
+        // Like serde_json, accept integer keys by stringifying them.
+        let key = match key.serialize(ValueSerializer {
+            path: self.ser.path.clone(),
+            policy: JcsPolicy::IntegersOnly,
+        }) {
+            Ok(Value::String(s)) => s,
+            Ok(Value::Number(n)) => n.to_string(),
+            _ => {
+                return Err(JcsError::NonStringKey {
+                    path: self.ser.path.clone(),
+                })
+            }
+        };
+        self.next_key = Some(key);
+        Ok(())
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), JcsError> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| JcsError::Serialize("map value without key".into()))?;
+        self.insert(key, value)
+    }
+    fn end(self) -> Result<Value, JcsError> {
+        Ok(Value::Object(self.map))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = JcsError;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), JcsError> {
+        self.insert(key.to_string(), value)
+    }
+    fn end(self) -> Result<Value, JcsError> {
+        Ok(Value::Object(self.map))
+    }
+}
+
+struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> VariantSerializer<S> {
+    fn wrap(variant: &'static str, inner: Value) -> Value {
+        Value::Object([(variant.to_string(), inner)].into_iter().collect())
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Value;
+    type Error = JcsError;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), JcsError> {
+        self.inner.push(value)
+    }
+    fn end(self) -> Result<Value, JcsError> {
+        Ok(Self::wrap(self.variant, Value::Array(self.inner.items)))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer> {
+    type Ok = Value;
+    type Error = JcsError;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), JcsError> {
+        self.inner.insert(key.to_string(), value)
+    }
+    fn end(self) -> Result<Value, JcsError> {
+        Ok(Self::wrap(self.variant, Value::Object(self.inner.map)))
+    }
+}
@@ -1,10 +1,60 @@
+mod armor;
+mod batch;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod ct;
+mod envelope;
+mod error;
+mod fingerprint;
 mod ids;
+mod invite;
 mod jcs;
+mod negotiate;
 mod netstring;
+mod public_key;
+mod revocation;
+mod sealed;
+mod session;
 mod spki_ed25519;
+mod time;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-pub use ids::{principal_id_from_spki_der, space_id_from_spki_der};
-pub use jcs::jcs_bytes;
-pub use netstring::{netstring, NetstringWriter};
+pub use armor::{
+    armor_sdp, dearmor_sdp, dearmor_sdp_with_kind, SdpKind, ARMOR_LINE_WIDTH, MAX_ARMORED_CHARS,
+    MAX_DEARMORED_SDP_BYTES,
+};
+pub use batch::{verify_batch, verify_envelopes_batch, BatchError};
+pub use ct::{ct_eq_bytes, ct_eq_str, verify_hex_digest};
+pub use envelope::{Envelope, EnvelopeLimits, SignedEnvelope, ENVELOPE_PREFIX, MSG_ID_BYTES};
+pub use error::ProtocolError;
+pub use fingerprint::{
+    fingerprint, Fingerprint, FINGERPRINT_HEX_GROUPS, FINGERPRINT_PREFIX, FINGERPRINT_WORDS,
+};
+pub use ids::{principal_id_from_spki_der, space_id_from_spki_der, PrincipalId};
+pub use invite::{parse_invite, DelegationCert, Invite, DELEGATION_PREFIX, MAX_INVITE_LINK_CHARS};
+pub use jcs::{
+    jcs_bytes, jcs_bytes_with_policy, jcs_value, jcs_value_with_policy, JcsError, JcsPolicy,
+    JCS_MAX_SAFE_INTEGER,
+};
+pub use negotiate::{negotiate, Agreed, Capabilities, NegotiationError, Offer, VersionRange};
+pub use netstring::{netstring, parse_netstring, ListLimits, NetstringReader, NetstringWriter};
+pub use public_key::{
+    parse_spki, PublicKey, PublicKeyAlg, ED25519_SPKI_DER_LEN, MAX_SPKI_DER_BYTES,
+};
+pub use revocation::{PrincipalOrDeviceId, Revocation, RevocationSet, REVOCATION_PREFIX};
+pub use sealed::{
+    derive_x25519_from_ed25519, derive_x25519_public_from_ed25519, open, seal, SEALED_BOX_INFO,
+    SEALED_BOX_V1,
+};
+pub use session::{
+    generate_pairing_code, generate_session_id, normalize_pairing_code, pairing_code_to_sid,
+    wordlist, PAIRING_SID_INFO, SESSION_ID_BYTES,
+};
 pub use spki_ed25519::{ed25519_public_key_from_spki_der, is_ed25519_spki};
+pub use time::CanonicalTime;
+#[cfg(feature = "wasm")]
+pub use wasm::VerifiedResult;
 
+/// Version of this crate, for diagnostics in embedding apps.
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
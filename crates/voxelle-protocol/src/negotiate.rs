@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+// Capabilities are carried as plain strings so that a peer relaying an offer
+// keeps features it does not understand yet instead of silently dropping them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Capabilities(BTreeSet<String>);
+
+impl Capabilities {
+    pub const CANDIDATES: &'static str = "candidates";
+    pub const COMPRESSION: &'static str = "compression";
+    pub const RELAY: &'static str = "relay";
+    pub const DELEGATION: &'static str = "delegation";
+
+    pub const KNOWN: [&'static str; 4] = [
+        Self::CANDIDATES,
+        Self::COMPRESSION,
+        Self::RELAY,
+        Self::DELEGATION,
+    ];
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn all_known() -> Self {
+        Self::KNOWN.iter().copied().collect()
+    }
+
+    pub fn insert(&mut self, cap: &str) -> bool {
+        self.0.insert(cap.to_string())
+    }
+
+    pub fn contains(&self, cap: &str) -> bool {
+        self.0.contains(cap)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self(self.0.intersection(&other.0).cloned().collect())
+    }
+
+    pub fn unknown(&self) -> impl Iterator<Item = &str> {
+        self.iter().filter(|c| !Self::KNOWN.contains(c))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'a> FromIterator<&'a str> for Capabilities {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        Self(iter.into_iter().map(str::to_string).collect())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl VersionRange {
+    pub fn new(min: u32, max: u32) -> Self {
+        Self { min, max }
+    }
+
+    pub fn exact(v: u32) -> Self {
+        Self { min: v, max: v }
+    }
+
+    pub fn contains(&self, v: u32) -> bool {
+        self.min <= v && v <= self.max
+    }
+
+    fn is_valid(&self) -> bool {
+        self.min <= self.max
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Offer {
+    pub versions: VersionRange,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Agreed {
+    pub version: u32,
+    pub capabilities: Capabilities,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NegotiationError {
+    InvalidOurRange(VersionRange),
+    InvalidTheirRange(VersionRange),
+    NoCommonVersion {
+        ours: VersionRange,
+        theirs: VersionRange,
+    },
+}
+
+impl fmt::Display for NegotiationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidOurRange(r) => {
+                write!(
+                    f,
+                    "our version range is invalid (min {} > max {})",
+                    r.min, r.max
+                )
+            }
+            Self::InvalidTheirRange(r) => {
+                write!(
+                    f,
+                    "peer version range is invalid (min {} > max {})",
+                    r.min, r.max
+                )
+            }
+            Self::NoCommonVersion { ours, theirs } => write!(
+                f,
+                "no common protocol version (ours {}..={}, theirs {}..={})",
+                ours.min, ours.max, theirs.min, theirs.max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NegotiationError {}
+
+pub fn negotiate(ours: &Offer, theirs: &Offer) -> Result<Agreed, NegotiationError> {
+    if !ours.versions.is_valid() {
+        return Err(NegotiationError::InvalidOurRange(ours.versions));
+    }
+    if !theirs.versions.is_valid() {
+        return Err(NegotiationError::InvalidTheirRange(theirs.versions));
+    }
+    let lo = ours.versions.min.max(theirs.versions.min);
+    let hi = ours.versions.max.min(theirs.versions.max);
+    if lo > hi {
+        return Err(NegotiationError::NoCommonVersion {
+            ours: ours.versions,
+            theirs: theirs.versions,
+        });
+    }
+    Ok(Agreed {
+        version: hi,
+        capabilities: ours.capabilities.intersection(&theirs.capabilities),
+    })
+}
@@ -1,5 +1,7 @@
 use std::io::{self, Write};
 
+use crate::error::{ProtocolError, Result};
+
 pub fn netstring(bytes: &[u8]) -> Vec<u8> {
     let mut out = Vec::with_capacity(bytes.len() + 32);
     write_netstring(&mut out, bytes).expect("vec write cannot fail");
@@ -35,15 +37,180 @@ impl<W: Write> NetstringWriter<W> {
         write_netstring(&mut self.inner, bytes)
     }
 
+    /// Flat list: `count` followed by each item, e.g. `["ab", "c"]` is
+    /// `1:2,2:ab,1:c,`. This is the layout signature inputs use for arrays.
+    pub fn write_list<'a, I>(&mut self, items: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let items: Vec<&[u8]> = items.into_iter().collect();
+        self.write_count(items.len())?;
+        for item in items {
+            self.write_bytes(item)?;
+        }
+        Ok(())
+    }
+
+    /// Nested list: the flat list wrapped in one outer netstring so readers can
+    /// skip it without understanding it, e.g. `["ab", "c"]` is `13:1:2,2:ab,1:c,,`.
+    pub fn write_nested_list<'a, I>(&mut self, items: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut inner = NetstringWriter::new(Vec::new());
+        inner.write_list(items)?;
+        self.write_bytes(&inner.into_inner())
+    }
+
     pub fn into_inner(self) -> W {
         self.inner
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListLimits {
+    pub max_items: usize,
+    pub max_item_bytes: usize,
+    pub max_total_bytes: usize,
+}
+
+impl Default for ListLimits {
+    fn default() -> Self {
+        Self {
+            max_items: 1024,
+            max_item_bytes: 64 * 1024,
+            max_total_bytes: 1024 * 1024,
+        }
+    }
+}
+
+// Reads the netstring sequences produced by `NetstringWriter` from a byte slice.
+pub struct NetstringReader<'a> {
+    input: &'a [u8],
+    max_len: usize,
+}
+
+impl<'a> NetstringReader<'a> {
+    pub fn new(input: &'a [u8], max_len: usize) -> Self {
+        Self { input, max_len }
+    }
+
+    pub fn read_prefix(&mut self, prefix: &str) -> Result<()> {
+        let rest = self
+            .input
+            .strip_prefix(prefix.as_bytes())
+            .ok_or_else(|| malformed("prefix mismatch"))?;
+        self.input = rest;
+        Ok(())
+    }
+
+    pub fn read_bytes(&mut self) -> Result<&'a [u8]> {
+        let (item, rest) = parse_netstring(self.input, self.max_len)?;
+        self.input = rest;
+        Ok(item)
+    }
+
+    pub fn read_str(&mut self) -> Result<&'a str> {
+        std::str::from_utf8(self.read_bytes()?).map_err(|_| malformed("not utf-8"))
+    }
+
+    pub fn read_int(&mut self) -> Result<i64> {
+        let s = self.read_str()?;
+        let digits = s.strip_prefix('-').unwrap_or(s);
+        if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) || s == "-0" {
+            return Err(malformed("integer not canonical"));
+        }
+        s.parse().map_err(|_| malformed("integer invalid"))
+    }
+
+    pub fn read_count(&mut self) -> Result<usize> {
+        let n = self.read_int()?;
+        usize::try_from(n).map_err(|_| malformed("count negative"))
+    }
+
+    pub fn read_list(&mut self, limits: &ListLimits) -> Result<Vec<&'a [u8]>> {
+        let count = self.read_count()?;
+        if count > limits.max_items {
+            return Err(malformed("list has too many items"));
+        }
+        let mut total = 0usize;
+        let mut items = Vec::with_capacity(count);
+        for _ in 0..count {
+            let item = self.read_bytes()?;
+            if item.len() > limits.max_item_bytes {
+                return Err(malformed("list item too large"));
+            }
+            total = total.saturating_add(item.len());
+            if total > limits.max_total_bytes {
+                return Err(malformed("list too large"));
+            }
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    pub fn read_nested_list(&mut self, limits: &ListLimits) -> Result<Vec<&'a [u8]>> {
+        let body = self.read_bytes()?;
+        let mut inner = NetstringReader::new(body, self.max_len);
+        let items = inner.read_list(limits)?;
+        if !inner.is_empty() {
+            return Err(malformed("trailing bytes in nested list"));
+        }
+        Ok(items)
+    }
+
+    /// Skips the next netstring (e.g. a nested list this reader doesn't understand).
+    pub fn skip(&mut self) -> Result<()> {
+        self.read_bytes().map(|_| ())
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        self.input
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+}
+
+/// Splits one netstring off the front of `input`, returning `(payload, rest)`.
+pub fn parse_netstring(input: &[u8], max_len: usize) -> Result<(&[u8], &[u8])> {
+    let colon = input
+        .iter()
+        .take(21)
+        .position(|b| *b == b':')
+        .ok_or_else(|| malformed("length prefix missing"))?;
+    let digits = &input[..colon];
+    if digits.is_empty()
+        || !digits.iter().all(u8::is_ascii_digit)
+        || (digits.len() > 1 && digits[0] == b'0')
+    {
+        return Err(malformed("length prefix invalid"));
+    }
+    let len: usize = std::str::from_utf8(digits)
+        .expect("ascii digits")
+        .parse()
+        .map_err(|_| malformed("length overflow"))?;
+    if len > max_len {
+        return Err(malformed("too large"));
+    }
+    let body = &input[colon + 1..];
+    if body.len() < len + 1 {
+        return Err(malformed("truncated"));
+    }
+    if body[len] != b',' {
+        return Err(malformed("terminator missing"));
+    }
+    Ok((&body[..len], &body[len + 1..]))
+}
+
+fn malformed(msg: &'static str) -> ProtocolError {
+    ProtocolError::Netstring(msg)
+}
+
 fn write_netstring<W: Write>(w: &mut W, bytes: &[u8]) -> io::Result<()> {
     write!(w, "{}:", bytes.len())?;
     w.write_all(bytes)?;
     w.write_all(b",")?;
     Ok(())
 }
-
@@ -0,0 +1,149 @@
+use ed25519_dalek::Verifier;
+use serde::{Deserialize, Serialize};
+use spki::der::{Decode, Reader, SliceReader};
+use spki::{ObjectIdentifier, SubjectPublicKeyInfoRef};
+use std::fmt;
+
+use crate::error::{ProtocolError, Result};
+
+pub(crate) const OID_ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+#[cfg(feature = "p256")]
+const OID_EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+#[cfg(feature = "p256")]
+const OID_PRIME256V1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+
+/// Length of a DER Ed25519 SubjectPublicKeyInfo (RFC 8410 §4).
+pub const ED25519_SPKI_DER_LEN: usize = 44;
+/// Inputs longer than this are rejected before they reach the DER parser.
+/// An uncompressed P-256 SPKI is 91 bytes.
+pub const MAX_SPKI_DER_BYTES: usize = 96;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PublicKeyAlg {
+    #[default]
+    Ed25519,
+    /// ECDSA over SHA-256 on NIST P-256; needs the `p256` feature to verify.
+    P256,
+}
+
+impl PublicKeyAlg {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ed25519 => "ed25519",
+            Self::P256 => "p256",
+        }
+    }
+}
+
+impl fmt::Display for PublicKeyAlg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A key parsed from SPKI DER. Principal ids hash the SPKI bytes, so they stay
+// the same shape whichever variant the key is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKey {
+    Ed25519(ed25519_dalek::VerifyingKey),
+    #[cfg(feature = "p256")]
+    P256(p256::ecdsa::VerifyingKey),
+}
+
+impl PublicKey {
+    pub fn alg(&self) -> PublicKeyAlg {
+        match self {
+            Self::Ed25519(_) => PublicKeyAlg::Ed25519,
+            #[cfg(feature = "p256")]
+            Self::P256(_) => PublicKeyAlg::P256,
+        }
+    }
+
+    pub fn as_ed25519(&self) -> Option<&ed25519_dalek::VerifyingKey> {
+        match self {
+            Self::Ed25519(vk) => Some(vk),
+            #[cfg(feature = "p256")]
+            Self::P256(_) => None,
+        }
+    }
+
+    /// Both algorithms use 64-byte signatures: `R || S` for Ed25519 and
+    /// `r || s` (IEEE P1363, what WebCrypto produces) for P-256.
+    pub fn verify(&self, msg: &[u8], sig: &[u8]) -> Result<()> {
+        match self {
+            Self::Ed25519(vk) => {
+                let sig = ed25519_dalek::Signature::from_slice(sig)
+                    .map_err(|_| ProtocolError::MalformedSignature("ed25519"))?;
+                vk.verify(msg, &sig)
+                    .map_err(|_| ProtocolError::SignatureInvalid("ed25519"))
+            }
+            #[cfg(feature = "p256")]
+            Self::P256(vk) => {
+                let sig = p256::ecdsa::Signature::from_slice(sig)
+                    .map_err(|_| ProtocolError::MalformedSignature("p256"))?;
+                vk.verify(msg, &sig)
+                    .map_err(|_| ProtocolError::SignatureInvalid("p256"))
+            }
+        }
+    }
+}
+
+pub fn parse_spki(spki_der: &[u8]) -> Result<PublicKey> {
+    let spki = parse_spki_der(spki_der)?;
+    let oid = spki.algorithm.oid;
+
+    if oid == OID_ED25519 {
+        // RFC 8410 §3: the parameters MUST be absent.
+        if spki.algorithm.parameters.is_some() {
+            return Err(ProtocolError::WrongAlgorithm(format!(
+                "{oid} with parameters"
+            )));
+        }
+        // `None` when the BIT STRING has unused bits, which no 32-byte key has.
+        let pk_bytes = spki
+            .subject_public_key
+            .as_bytes()
+            .ok_or(ProtocolError::InvalidPublicKey)?;
+        let pk: [u8; 32] = pk_bytes
+            .try_into()
+            .map_err(|_| ProtocolError::BadKeyLength(pk_bytes.len()))?;
+        return ed25519_dalek::VerifyingKey::from_bytes(&pk)
+            .map(PublicKey::Ed25519)
+            .map_err(|_| ProtocolError::InvalidPublicKey);
+    }
+
+    #[cfg(feature = "p256")]
+    if oid == OID_EC_PUBLIC_KEY {
+        // RFC 5480 §2.1.1: the parameters name the curve.
+        if spki.algorithm.parameters_oid().ok() != Some(OID_PRIME256V1) {
+            return Err(ProtocolError::WrongAlgorithm(format!(
+                "{oid} on a curve other than P-256"
+            )));
+        }
+        let point = spki
+            .subject_public_key
+            .as_bytes()
+            .ok_or(ProtocolError::InvalidPublicKey)?;
+        return p256::ecdsa::VerifyingKey::from_sec1_bytes(point)
+            .map(PublicKey::P256)
+            .map_err(|_| ProtocolError::InvalidPublicKey);
+    }
+
+    Err(ProtocolError::WrongAlgorithm(oid.to_string()))
+}
+
+// SPKIs arrive straight from the network, so bound the input before parsing
+// and refuse anything after the outer SEQUENCE.
+pub(crate) fn parse_spki_der(spki_der: &[u8]) -> Result<SubjectPublicKeyInfoRef<'_>> {
+    if spki_der.len() > MAX_SPKI_DER_BYTES {
+        return Err(ProtocolError::SpkiTooLarge(spki_der.len()));
+    }
+    let mut reader = SliceReader::new(spki_der).map_err(ProtocolError::SpkiParse)?;
+    let spki = SubjectPublicKeyInfoRef::decode(&mut reader).map_err(ProtocolError::SpkiParse)?;
+    let trailing = u32::from(reader.remaining_len()) as usize;
+    if trailing != 0 {
+        return Err(ProtocolError::SpkiTrailingBytes(trailing));
+    }
+    Ok(spki)
+}
@@ -0,0 +1,220 @@
+use base64::Engine;
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use serde::{Deserialize, Serialize};
+use std::io;
+
+use crate::ct::ct_eq_str;
+use crate::error::{ProtocolError, Result};
+use crate::ids::principal_id_from_spki_der;
+use crate::netstring::NetstringWriter;
+use crate::spki_ed25519::ed25519_public_key_from_spki_der;
+
+pub const REVOCATION_PREFIX: &str = "p2pspace/revocation/v0\n";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum PrincipalOrDeviceId {
+    Principal(String),
+    Device(String),
+}
+
+impl PrincipalOrDeviceId {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Principal(_) => "principal",
+            Self::Device(_) => "device",
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Principal(id) | Self::Device(id) => id,
+        }
+    }
+}
+
+// A revocation is signed by the principal root key that controls `subject`:
+// the delegating principal for a device, or the principal itself for a
+// self-revocation (e.g. a compromised root).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revocation {
+    pub v: u32,
+    pub subject: PrincipalOrDeviceId,
+    pub principal_id: String,
+    /// SPKI DER, standard Base64.
+    pub principal_pub: String,
+    pub reason: String,
+    /// Unix ms at which the subject stops being trusted.
+    pub ts: i64,
+    /// Standard Base64 Ed25519 signature by the principal root key.
+    pub sig_by_principal: String,
+}
+
+impl Revocation {
+    pub fn issue(
+        principal_key: &SigningKey,
+        subject: PrincipalOrDeviceId,
+        reason: &str,
+        ts: i64,
+    ) -> Self {
+        let spki = principal_key
+            .verifying_key()
+            .to_public_key_der()
+            .expect("Ed25519 SPKI encoding cannot fail");
+        let mut rev = Self {
+            v: 1,
+            subject,
+            principal_id: principal_id_from_spki_der(spki.as_bytes()),
+            principal_pub: base64::engine::general_purpose::STANDARD.encode(spki.as_bytes()),
+            reason: reason.to_string(),
+            ts,
+            sig_by_principal: String::new(),
+        };
+        let sig = principal_key.sign(&rev.signature_input());
+        rev.sig_by_principal = base64::engine::general_purpose::STANDARD.encode(sig.to_bytes());
+        rev
+    }
+
+    pub fn signature_input(&self) -> Vec<u8> {
+        self.write_signature_input(NetstringWriter::new(Vec::new()))
+            .expect("vec write cannot fail")
+    }
+
+    fn write_signature_input(&self, mut w: NetstringWriter<Vec<u8>>) -> io::Result<Vec<u8>> {
+        w.write_prefix(REVOCATION_PREFIX)?;
+        w.write_int(i64::from(self.v))?;
+        w.write_str(self.subject.kind())?;
+        w.write_str(self.subject.id())?;
+        w.write_str(&self.principal_id)?;
+        w.write_str(&self.principal_pub)?;
+        w.write_str(&self.reason)?;
+        w.write_int(self.ts)?;
+        Ok(w.into_inner())
+    }
+
+    /// Verifies the revocation was issued by `controller_principal_id`, the
+    /// principal that delegated the device (or the principal itself).
+    pub fn verify(&self, controller_principal_id: &str) -> Result<()> {
+        if self.v != 1 {
+            return Err(ProtocolError::InvalidRevocation("v must be 1".into()));
+        }
+        let spki = base64::engine::general_purpose::STANDARD
+            .decode(self.principal_pub.trim())
+            .map_err(|_| ProtocolError::Base64("revocation principal_pub"))?;
+        if !ct_eq_str(&principal_id_from_spki_der(&spki), &self.principal_id) {
+            return Err(ProtocolError::InvalidRevocation(
+                "principal_id does not match principal_pub".into(),
+            ));
+        }
+        if !ct_eq_str(&self.principal_id, controller_principal_id) {
+            return Err(ProtocolError::InvalidRevocation(
+                "not signed by the controlling principal".into(),
+            ));
+        }
+        if let PrincipalOrDeviceId::Principal(id) = &self.subject {
+            // A principal may only revoke itself; revoking another principal
+            // would need that principal's root key.
+            if !ct_eq_str(id, &self.principal_id) {
+                return Err(ProtocolError::InvalidRevocation(
+                    "principal revocation must be self-signed".into(),
+                ));
+            }
+        }
+        if self.subject.id().is_empty() {
+            return Err(ProtocolError::InvalidRevocation("subject missing".into()));
+        }
+
+        let vk = ed25519_public_key_from_spki_der(&spki)?;
+        let sig_bytes = base64::engine::general_purpose::STANDARD
+            .decode(self.sig_by_principal.trim())
+            .map_err(|_| ProtocolError::Base64("revocation sig_by_principal"))?;
+        let sig = Signature::from_slice(&sig_bytes)
+            .map_err(|_| ProtocolError::MalformedSignature("revocation"))?;
+        vk.verify(&self.signature_input(), &sig)
+            .map_err(|_| ProtocolError::SignatureInvalid("revocation"))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RevocationSet {
+    revocations: Vec<Revocation>,
+}
+
+impl RevocationSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verifies `rev` against its controlling principal and records it.
+    pub fn insert(&mut self, rev: Revocation, controller_principal_id: &str) -> Result<()> {
+        rev.verify(controller_principal_id)?;
+        if !self.revocations.contains(&rev) {
+            self.revocations.push(rev);
+        }
+        Ok(())
+    }
+
+    /// Whether `subject` is revoked at `at_ms`. Only revocations of the same
+    /// kind count: revoking a device doesn't revoke a principal with its id.
+    /// Uses before the earliest revocation timestamp remain valid.
+    pub fn is_revoked(&self, subject: &PrincipalOrDeviceId, at_ms: i64) -> bool {
+        self.revoked_since(subject).is_some_and(|ts| ts <= at_ms)
+    }
+
+    pub fn revoked_since(&self, subject: &PrincipalOrDeviceId) -> Option<i64> {
+        self.revocations
+            .iter()
+            .filter(|r| r.subject == *subject)
+            .map(|r| r.ts)
+            .min()
+    }
+
+    /// Re-check hook for an already verified delegation: fails if either the
+    /// device or its delegating principal is revoked at `at_ms`.
+    pub fn check_delegation(&self, principal_id: &str, device_id: &str, at_ms: i64) -> Result<()> {
+        for subject in [
+            PrincipalOrDeviceId::Principal(principal_id.to_string()),
+            PrincipalOrDeviceId::Device(device_id.to_string()),
+        ] {
+            if self.is_revoked(&subject, at_ms) {
+                return Err(ProtocolError::Revoked {
+                    kind: subject.kind(),
+                    id: subject.id().to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-verifies every entry, e.g. after deserializing a gossiped set.
+    /// `controller_of` maps a subject to the principal allowed to revoke it.
+    pub fn verify_all(
+        &self,
+        controller_of: impl Fn(&PrincipalOrDeviceId) -> Option<String>,
+    ) -> Result<()> {
+        for rev in &self.revocations {
+            let controller = controller_of(&rev.subject).ok_or_else(|| {
+                ProtocolError::InvalidRevocation(format!(
+                    "unknown controller for {}",
+                    rev.subject.id()
+                ))
+            })?;
+            rev.verify(&controller)?;
+        }
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Revocation> {
+        self.revocations.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.revocations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revocations.is_empty()
+    }
+}
@@ -0,0 +1,142 @@
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::error::{ProtocolError, Result};
+
+// Sealed box wire format (v1):
+//
+//   version (1 byte, 0x01) || ephemeral X25519 public key (32) || nonce (24)
+//   || XChaCha20-Poly1305 ciphertext + tag
+//
+// key = HKDF-SHA256(ikm = X25519(eph, recipient), salt = eph_pub || recipient_pub,
+//                   info = SEALED_BOX_INFO)
+// The AEAD associated data is the 57-byte header followed by the caller's aad,
+// so the version and ephemeral key are authenticated too.
+
+pub const SEALED_BOX_V1: u8 = 1;
+pub const SEALED_BOX_INFO: &[u8] = b"p2pspace/sealed-box/v1\n";
+const HEADER_LEN: usize = 1 + 32 + 24;
+const TAG_LEN: usize = 16;
+
+/// X25519 secret for the same identity as an Ed25519 signing key (the
+/// clamped scalar from the Ed25519 expanded secret, as in libsodium).
+pub fn derive_x25519_from_ed25519(sk: &SigningKey) -> StaticSecret {
+    StaticSecret::from(sk.to_scalar_bytes())
+}
+
+pub fn derive_x25519_public_from_ed25519(vk: &VerifyingKey) -> PublicKey {
+    PublicKey::from(vk.to_montgomery().to_bytes())
+}
+
+pub fn seal(recipient: &PublicKey, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let eph = StaticSecret::random_from_rng(OsRng);
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+    seal_with(recipient, plaintext, aad, &eph, &nonce)
+}
+
+fn seal_with(
+    recipient: &PublicKey,
+    plaintext: &[u8],
+    aad: &[u8],
+    eph: &StaticSecret,
+    nonce: &[u8; 24],
+) -> Result<Vec<u8>> {
+    let eph_pub = PublicKey::from(eph);
+    let cipher = cipher_for(
+        &eph.diffie_hellman(recipient).to_bytes(),
+        &eph_pub,
+        recipient,
+    );
+
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + TAG_LEN);
+    out.push(SEALED_BOX_V1);
+    out.extend_from_slice(eph_pub.as_bytes());
+    out.extend_from_slice(nonce);
+
+    let full_aad = [out.as_slice(), aad].concat();
+    let ct = cipher
+        .encrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: plaintext,
+                aad: &full_aad,
+            },
+        )
+        .map_err(|_| ProtocolError::SealedBox("encryption failed".into()))?;
+    out.extend_from_slice(&ct);
+    Ok(out)
+}
+
+pub fn open(our_secret: &StaticSecret, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < HEADER_LEN + TAG_LEN {
+        return Err(ProtocolError::SealedBox("truncated".into()));
+    }
+    if sealed[0] != SEALED_BOX_V1 {
+        return Err(ProtocolError::SealedBox(format!(
+            "unsupported version {}",
+            sealed[0]
+        )));
+    }
+    let (header, ct) = sealed.split_at(HEADER_LEN);
+    let eph_bytes: [u8; 32] = header[1..33].try_into().expect("32 bytes");
+    let eph_pub = PublicKey::from(eph_bytes);
+    let nonce = XNonce::from_slice(&header[33..]);
+
+    let our_pub = PublicKey::from(our_secret);
+    let shared = our_secret.diffie_hellman(&eph_pub);
+    if !shared.was_contributory() {
+        return Err(ProtocolError::SealedBox(
+            "ephemeral key is low order".into(),
+        ));
+    }
+    let cipher = cipher_for(&shared.to_bytes(), &eph_pub, &our_pub);
+
+    let full_aad = [header, aad].concat();
+    cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ct,
+                aad: &full_aad,
+            },
+        )
+        .map_err(|_| ProtocolError::SealedBox("authentication failed".into()))
+}
+
+fn cipher_for(shared: &[u8; 32], eph_pub: &PublicKey, recipient: &PublicKey) -> XChaCha20Poly1305 {
+    let salt = [
+        eph_pub.as_bytes().as_slice(),
+        recipient.as_bytes().as_slice(),
+    ]
+    .concat();
+    let hk = Hkdf::<Sha256>::new(Some(&salt), shared);
+    let mut key = [0u8; 32];
+    hk.expand(SEALED_BOX_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    XChaCha20Poly1305::new(&key.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The same vector is opened by the known-answer test in tests/sealed.rs.
+    #[test]
+    fn deterministic_seal_matches_pinned_vector() {
+        let recipient_sk = SigningKey::from_bytes(&[1u8; 32]);
+        let recipient = derive_x25519_public_from_ed25519(&recipient_sk.verifying_key());
+        let eph = StaticSecret::from([2u8; 32]);
+        let sealed = seal_with(&recipient, b"v=0\r\n", b"sid", &eph, &[3u8; 24]).unwrap();
+        assert_eq!(
+            hex::encode(&sealed),
+            include_str!("../tests/vectors/sealed_box_v1.hex").trim()
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+use sha2::Sha256;
+use std::sync::OnceLock;
+
+use crate::error::{ProtocolError, Result};
+
+pub const SESSION_ID_BYTES: usize = 32;
+pub const PAIRING_SID_INFO: &[u8] = b"p2pspace/pairing-sid/v0\n";
+
+// EFF large wordlist (CC BY 3.0, https://www.eff.org/dice) minus the four
+// hyphenated entries, since `-` is the pairing code word separator.
+// 7772 words gives ~12.9 bits per word.
+const WORDLIST_TXT: &str = include_str!("wordlists/eff_large.txt");
+
+pub fn wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| WORDLIST_TXT.lines().filter(|w| !w.is_empty()).collect())
+}
+
+/// 32 CSPRNG bytes as lowercase hex; accepted by the relay's sid validation.
+pub fn generate_session_id() -> String {
+    random_hex(SESSION_ID_BYTES)
+}
+
+pub(crate) fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+pub fn generate_pairing_code(words: usize) -> String {
+    let list = wordlist();
+    (0..words)
+        .map(|_| list[OsRng.gen_range(0..list.len())])
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Lowercases the code and collapses any run of whitespace, `-`, `_` or `.`
+/// into a single `-`, then checks every word is in the wordlist.
+pub fn normalize_pairing_code(code: &str) -> Result<String> {
+    let words: Vec<String> = code
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_' || c == '.')
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.is_empty() {
+        return Err(ProtocolError::InvalidPairingCode("code is empty".into()));
+    }
+    let list = wordlist();
+    for w in &words {
+        if list.binary_search(&w.as_str()).is_err() {
+            return Err(ProtocolError::InvalidPairingCode(format!(
+                "word not recognized: {w}"
+            )));
+        }
+    }
+    Ok(words.join("-"))
+}
+
+/// Derives the relay sid both sides of a pairing converge on:
+/// HKDF-SHA256(ikm = normalized code, salt, info = PAIRING_SID_INFO).
+pub fn pairing_code_to_sid(code: &str, salt: &[u8]) -> Result<String> {
+    let normalized = normalize_pairing_code(code)?;
+    let hk = Hkdf::<Sha256>::new(Some(salt), normalized.as_bytes());
+    let mut okm = [0u8; SESSION_ID_BYTES];
+    hk.expand(PAIRING_SID_INFO, &mut okm)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    Ok(hex::encode(okm))
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, SecondsFormat, SubsecRound, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{ProtocolError, Result};
+
+// A UTC instant with whole-second precision, written as RFC 3339 with a `Z`
+// suffix (`2024-05-01T12:00:00Z`). That string is the only accepted form, so
+// two implementations that sign a timestamp always sign the same bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CanonicalTime(DateTime<Utc>);
+
+impl CanonicalTime {
+    /// The current time, truncated to the second.
+    pub fn now() -> Self {
+        Self::truncating(Utc::now())
+    }
+
+    /// Drops any sub-second part. Use this to normalize a clock reading;
+    /// parsing never truncates.
+    pub fn truncating(dt: DateTime<Utc>) -> Self {
+        Self(dt.trunc_subsecs(0))
+    }
+
+    /// Converts the unix-ms timestamps carried by envelopes and invites,
+    /// truncating to the second.
+    pub fn from_unix_ms(ms: i64) -> Result<Self> {
+        Utc.timestamp_millis_opt(ms)
+            .single()
+            .map(Self::truncating)
+            .ok_or_else(|| ProtocolError::InvalidTimestamp(format!("{ms} ms is out of range")))
+    }
+
+    pub fn unix_secs(&self) -> i64 {
+        self.0.timestamp()
+    }
+
+    pub fn unix_ms(&self) -> i64 {
+        self.0.timestamp_millis()
+    }
+
+    pub fn as_datetime(&self) -> &DateTime<Utc> {
+        &self.0
+    }
+}
+
+impl FromStr for CanonicalTime {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let dt = DateTime::parse_from_rfc3339(s)
+            .map_err(|e| ProtocolError::InvalidTimestamp(format!("{s:?}: {e}")))?;
+        let t = Self(dt.with_timezone(&Utc));
+        // RFC 3339 allows offsets, fractions, lowercase `t`/`z` and a space
+        // separator; anything that doesn't print back identically is one of those.
+        if t.to_string() != s {
+            return Err(ProtocolError::InvalidTimestamp(format!(
+                "{s:?} is not canonical, expected {t}"
+            )));
+        }
+        Ok(t)
+    }
+}
+
+impl TryFrom<String> for CanonicalTime {
+    type Error = ProtocolError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<CanonicalTime> for String {
+    fn from(t: CanonicalTime) -> Self {
+        t.to_string()
+    }
+}
+
+impl fmt::Display for CanonicalTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
+}
@@ -0,0 +1,117 @@
+use wasm_bindgen::prelude::*;
+
+use crate::armor::{armor_sdp, dearmor_sdp, SdpKind};
+use crate::envelope::{Envelope, EnvelopeLimits, SignedEnvelope};
+use crate::error::ProtocolError;
+use crate::ids::principal_id_from_spki_der;
+use crate::invite::parse_invite;
+use crate::jcs::jcs_bytes;
+use crate::public_key::PublicKeyAlg;
+
+// Errors are thrown as JS `Error`s with `code` set to `ProtocolError::code()`.
+fn to_js(err: ProtocolError) -> JsValue {
+    let js = js_sys::Error::new(&err.to_string());
+    js_sys::Reflect::set(&js, &"code".into(), &err.code().into())
+        .expect("setting a property on a fresh Error cannot fail");
+    js.into()
+}
+
+#[wasm_bindgen(js_name = principalIdFromSpkiDer)]
+pub fn principal_id_from_spki_der_js(spki_der: &[u8]) -> String {
+    principal_id_from_spki_der(spki_der)
+}
+
+/// An envelope whose signature and sender binding have been checked.
+#[wasm_bindgen]
+pub struct VerifiedResult {
+    envelope: Envelope,
+    alg: PublicKeyAlg,
+}
+
+#[wasm_bindgen]
+impl VerifiedResult {
+    /// `"ed25519"` or `"p256"`.
+    #[wasm_bindgen(getter)]
+    pub fn alg(&self) -> String {
+        self.alg.to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> String {
+        self.envelope.kind.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = msgId)]
+    pub fn msg_id(&self) -> String {
+        self.envelope.msg_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = inReplyTo)]
+    pub fn in_reply_to(&self) -> Option<String> {
+        self.envelope.in_reply_to.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn sender(&self) -> String {
+        self.envelope.sender.to_string()
+    }
+
+    /// Unix ms; `number` is exact up to 2^53.
+    #[wasm_bindgen(getter)]
+    pub fn ts(&self) -> f64 {
+        self.envelope.ts as f64
+    }
+
+    /// The payload as JCS, i.e. exactly the bytes that were signed over.
+    #[wasm_bindgen(getter, js_name = payloadJson)]
+    pub fn payload_json(&self) -> String {
+        let bytes = jcs_bytes(&self.envelope.payload).expect("verified payload is valid JCS");
+        String::from_utf8(bytes).expect("JCS output is utf-8")
+    }
+}
+
+/// Verifies a `SignedEnvelope` JSON object (the body of a frame, without the
+/// netstring wrapper).
+#[wasm_bindgen(js_name = verifyEnvelope)]
+pub fn verify_envelope(json: &str) -> Result<VerifiedResult, JsValue> {
+    let limits = EnvelopeLimits::default();
+    if json.len() > limits.max_frame_bytes {
+        return Err(to_js(ProtocolError::InvalidEnvelope(format!(
+            "frame too large ({} > {} bytes)",
+            json.len(),
+            limits.max_frame_bytes
+        ))));
+    }
+    let signed: SignedEnvelope =
+        serde_json::from_str(json).map_err(|e| to_js(ProtocolError::EnvelopeDecode(e)))?;
+    signed.envelope.validate(&limits).map_err(to_js)?;
+    signed.verify().map_err(to_js)?;
+    Ok(VerifiedResult {
+        envelope: signed.envelope,
+        alg: signed.alg,
+    })
+}
+
+/// Returns the invite as a plain object. Signatures are not verified.
+#[wasm_bindgen(js_name = parseInvite)]
+pub fn parse_invite_js(text: &str) -> Result<JsValue, JsValue> {
+    let invite = parse_invite(text).map_err(to_js)?;
+    let json = serde_json::to_string(&invite).expect("invite serializes to JSON");
+    js_sys::JSON::parse(&json)
+}
+
+/// `kind` is the `RTCSdpType`: `"offer"` or `"answer"`.
+#[wasm_bindgen(js_name = armorSdp)]
+pub fn armor_sdp_js(kind: &str, sdp: &str) -> Result<String, JsValue> {
+    let kind = match kind {
+        "offer" => SdpKind::Offer,
+        "answer" => SdpKind::Answer,
+        _ => return Err(to_js(ProtocolError::Armor("unknown armor label"))),
+    };
+    Ok(armor_sdp(kind, sdp))
+}
+
+#[wasm_bindgen(js_name = dearmorSdp)]
+pub fn dearmor_sdp_js(text: &str) -> Result<String, JsValue> {
+    dearmor_sdp(text).map_err(to_js)
+}
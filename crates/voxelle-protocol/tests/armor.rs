@@ -0,0 +1,94 @@
+use base64::Engine;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::Write;
+use voxelle_protocol::{armor_sdp, dearmor_sdp, dearmor_sdp_with_kind, SdpKind};
+
+const SDP: &str = "v=0\r
+o=- 4611731400430051336 2 IN IP4 127.0.0.1\r
+s=-\r
+t=0 0\r
+a=group:BUNDLE 0\r
+a=extmap-allow-mixed\r
+a=msid-semantic: WMS\r
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r
+c=IN IP4 0.0.0.0\r
+a=candidate:842163049 1 udp 1677729535 203.0.113.7 61234 typ srflx raddr 0.0.0.0 rport 0 generation 0 network-cost 999\r
+a=candidate:1467250027 1 udp 2122260223 192.168.1.20 61234 typ host generation 0 network-id 1\r
+a=candidate:3289912957 1 udp 2122194687 fd00:1234:5678::20 53011 typ host generation 0 network-id 2\r
+a=candidate:1 1 udp 33562367 198.51.100.9 3478 typ relay raddr 203.0.113.7 rport 61234\r
+a=ice-ufrag:Xy9a\r
+a=ice-pwd:8oE2XlcMZbQ0h1N4rCq5pUAb\r
+a=ice-options:trickle\r
+a=fingerprint:sha-256 6B:8B:F0:65:5F:78:E2:51:3B:AC:6F:F3:3F:46:1B:35:DC:B8:5F:64:1A:24:C2:43:F0:A1:58:D0:A1:2C:19:08\r
+a=setup:actpass\r
+a=mid:0\r
+a=sctp-port:5000\r
+a=max-message-size:262144\r
+";
+
+#[test]
+fn armor_round_trips_and_is_wrapped() {
+    let armored = armor_sdp(SdpKind::Offer, SDP);
+    assert!(armored.starts_with("-----BEGIN VOXELLE OFFER-----\n"));
+    assert!(armored.ends_with("-----END VOXELLE OFFER-----\n"));
+    assert!(armored.lines().all(|l| l.len() <= 76));
+    assert!(armored.len() < SDP.len());
+    assert_eq!(dearmor_sdp(&armored).unwrap(), SDP);
+
+    let answer = armor_sdp(SdpKind::Answer, SDP);
+    assert_eq!(
+        dearmor_sdp_with_kind(&answer).unwrap(),
+        (SdpKind::Answer, SDP.to_string())
+    );
+}
+
+#[test]
+fn dearmor_tolerates_chat_damage() {
+    let armored = armor_sdp(SdpKind::Offer, SDP);
+    let flat: String = armored.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    // Reflow at a different width, indent, and wrap in smart quotes with an
+    // em dash replacing the fence dashes.
+    let mut reflowed = String::from("here you go: \u{201c}  ");
+    for (i, chunk) in flat.as_bytes().chunks(33).enumerate() {
+        if i > 0 {
+            reflowed.push_str("\n    ");
+        }
+        reflowed.push_str(std::str::from_utf8(chunk).unwrap());
+    }
+    reflowed.push_str("\u{201d}\n\n");
+    let reflowed = reflowed.replacen("-----BEGIN", "\u{2014}\u{2014}-BEGIN", 1);
+
+    assert_eq!(dearmor_sdp(&reflowed).unwrap(), SDP);
+}
+
+#[test]
+fn dearmor_rejects_corruption() {
+    let armored = armor_sdp(SdpKind::Offer, SDP);
+    let crc_line = armored.lines().find(|l| l.starts_with('=')).unwrap();
+    let bad_crc = armored.replace(crc_line, "=00000000");
+    assert!(dearmor_sdp(&bad_crc).is_err());
+
+    let mismatched = armored.replace("END VOXELLE OFFER", "END VOXELLE ANSWER");
+    assert!(dearmor_sdp(&mismatched).is_err());
+
+    assert!(dearmor_sdp("no armor here").is_err());
+    assert!(dearmor_sdp(&armored[..armored.len() / 2]).is_err());
+}
+
+#[test]
+fn dearmor_guards_against_decompression_bombs() {
+    let bomb = vec![b'a'; 4 * 1024 * 1024];
+    let mut enc = DeflateEncoder::new(Vec::new(), Compression::best());
+    enc.write_all(&bomb).unwrap();
+    let compressed = enc.finish().unwrap();
+    let body = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(compressed);
+    let armored = format!(
+        "-----BEGIN VOXELLE OFFER-----\n{body}\n={:08x}\n-----END VOXELLE OFFER-----\n",
+        crc32fast::hash(&bomb)
+    );
+    assert!(armored.len() < voxelle_protocol::MAX_ARMORED_CHARS);
+    let err = dearmor_sdp(&armored).unwrap_err();
+    assert!(err.to_string().contains("too large"));
+}
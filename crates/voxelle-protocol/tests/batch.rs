@@ -0,0 +1,82 @@
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use serde_json::json;
+use voxelle_protocol::{verify_batch, verify_envelopes_batch, BatchError, Envelope, PrincipalId};
+
+fn signed_messages(n: usize) -> (Vec<VerifyingKey>, Vec<Vec<u8>>, Vec<Signature>) {
+    let keys: Vec<SigningKey> = (0..16).map(|_| SigningKey::generate(&mut OsRng)).collect();
+    let mut vks = Vec::with_capacity(n);
+    let mut msgs = Vec::with_capacity(n);
+    let mut sigs = Vec::with_capacity(n);
+    for i in 0..n {
+        let sk = &keys[i % keys.len()];
+        let msg = format!("record {i}").into_bytes();
+        sigs.push(sk.sign(&msg));
+        vks.push(sk.verifying_key());
+        msgs.push(msg);
+    }
+    (vks, msgs, sigs)
+}
+
+fn items<'a>(
+    vks: &[VerifyingKey],
+    msgs: &'a [Vec<u8>],
+    sigs: &'a [Signature],
+) -> Vec<(VerifyingKey, &'a [u8], &'a Signature)> {
+    vks.iter()
+        .zip(msgs)
+        .zip(sigs)
+        .map(|((k, m), s)| (*k, m.as_slice(), s))
+        .collect()
+}
+
+#[test]
+fn handles_ten_thousand_signatures() {
+    let (vks, msgs, sigs) = signed_messages(10_000);
+    verify_batch(&items(&vks, &msgs, &sigs)).unwrap();
+}
+
+#[test]
+fn forged_signature_in_the_middle_is_pinpointed() {
+    let (vks, mut msgs, sigs) = signed_messages(257);
+    msgs[128] = b"forged".to_vec();
+    let err = verify_batch(&items(&vks, &msgs, &sigs)).unwrap_err();
+    assert_eq!(err, BatchError { failed: vec![128] });
+}
+
+#[test]
+fn multiple_failures_are_all_reported() {
+    let (mut vks, msgs, mut sigs) = signed_messages(64);
+    sigs[3] = sigs[4];
+    vks[60] = SigningKey::generate(&mut OsRng).verifying_key();
+    let err = verify_batch(&items(&vks, &msgs, &sigs)).unwrap_err();
+    assert_eq!(err.failed, vec![3, 60]);
+}
+
+#[test]
+fn empty_batch_is_ok() {
+    verify_batch(&[]).unwrap();
+    verify_envelopes_batch(&[]).unwrap();
+}
+
+#[test]
+fn envelope_batch_pinpoints_tampered_and_malformed() {
+    let sk = SigningKey::generate(&mut OsRng);
+    let spki = sk.verifying_key().to_public_key_der().unwrap();
+    let id = PrincipalId::from_spki_der(spki.as_bytes());
+
+    let mut envs: Vec<_> = (0..32)
+        .map(|i| {
+            Envelope::new("chat.msg", id.clone(), i, json!({ "n": i }))
+                .sign(&sk)
+                .unwrap()
+        })
+        .collect();
+    verify_envelopes_batch(&envs).unwrap();
+
+    envs[7].envelope.kind = "chat.edit".into();
+    envs[20].sig = "not base64!".into();
+    let err = verify_envelopes_batch(&envs).unwrap_err();
+    assert_eq!(err.failed, vec![7, 20]);
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "cbor")]
+
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::SigningKey;
+use serde_json::json;
+use voxelle_protocol::{Envelope, EnvelopeLimits, PrincipalId, ProtocolError, SignedEnvelope};
+
+fn pinned() -> SignedEnvelope {
+    serde_json::from_str(include_str!("vectors/signed_envelope_v1.json")).unwrap()
+}
+
+fn sync_envelope() -> SignedEnvelope {
+    let sk = SigningKey::from_bytes(&[7u8; 32]);
+    let spki = sk.verifying_key().to_public_key_der().unwrap();
+    let id = PrincipalId::from_spki_der(spki.as_bytes());
+    let payload = json!({
+        "space_id": id.as_str(),
+        "have": [
+            "3f2a91bc04de5f7788a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f607",
+            "9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d2e1f0a9b8c",
+        ],
+        "seq": 1234,
+    });
+    Envelope::new("sync.have", id, 1_700_000_000_000, payload)
+        .sign(&sk)
+        .unwrap()
+}
+
+#[test]
+fn json_to_cbor_and_back_keeps_the_signature() {
+    let signed = pinned();
+    let cbor = signed.to_cbor().unwrap();
+    let back = SignedEnvelope::from_cbor(&cbor, &EnvelopeLimits::default()).unwrap();
+    back.verify().unwrap();
+    assert_eq!(back, signed);
+    assert_eq!(back.to_frame().unwrap(), signed.to_frame().unwrap());
+}
+
+#[test]
+fn envelope_round_trips() {
+    let env = sync_envelope().envelope;
+    let cbor = env.to_cbor().unwrap();
+    assert_eq!(Envelope::from_cbor(&cbor).unwrap(), env);
+    assert_eq!(Envelope::from_cbor(&cbor).unwrap().to_cbor().unwrap(), cbor);
+}
+
+#[test]
+fn cbor_frames_round_trip() {
+    let signed = sync_envelope();
+    let frame = signed.to_cbor_frame().unwrap();
+    let back = SignedEnvelope::from_cbor_frame(&frame, &EnvelopeLimits::default()).unwrap();
+    back.verify().unwrap();
+    assert_eq!(back, signed);
+
+    let mut trailing = frame;
+    trailing.push(b'x');
+    assert!(matches!(
+        SignedEnvelope::from_cbor_frame(&trailing, &EnvelopeLimits::default()),
+        Err(ProtocolError::Netstring(_))
+    ));
+}
+
+#[test]
+fn cbor_is_smaller_than_json() {
+    let signed = sync_envelope();
+    let json = signed.to_frame().unwrap();
+    let cbor = signed.to_cbor_frame().unwrap();
+    assert!(
+        cbor.len() < json.len(),
+        "cbor {} bytes, json {} bytes",
+        cbor.len(),
+        json.len()
+    );
+}
+
+#[test]
+fn map_keys_are_in_deterministic_order() {
+    let cbor = pinned().envelope.to_cbor().unwrap();
+    // Shorter keys sort first: map(6), then text(1) "v".
+    assert_eq!(&cbor[..3], &[0xa6, 0x61, b'v']);
+}
+
+#[test]
+fn rejects_non_deterministic_encodings() {
+    let limits = EnvelopeLimits::default();
+    let cbor = pinned().to_cbor().unwrap();
+
+    let mut trailing = cbor.clone();
+    trailing.push(0);
+
+    // {"b": 1, "a": 2}: keys out of order.
+    let unsorted = [0xa2, 0x61, b'b', 0x01, 0x61, b'a', 0x02];
+    // {"a": 1, "a": 2}
+    let duplicate = [0xa2, 0x61, b'a', 0x01, 0x61, b'a', 0x02];
+    // 1 encoded in two bytes instead of one.
+    let long_int = [0x18, 0x01];
+    // 1.0 as a double rather than a half.
+    let long_float = [0xfb, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0];
+
+    for bytes in [
+        &trailing[..],
+        &unsorted,
+        &duplicate,
+        &long_int,
+        &long_float,
+        &cbor[..cbor.len() - 1],
+    ] {
+        assert!(
+            matches!(
+                SignedEnvelope::from_cbor(bytes, &limits),
+                Err(ProtocolError::Cbor(_))
+            ),
+            "{} should be rejected",
+            hex::encode(bytes)
+        );
+    }
+}
+
+#[test]
+fn rejects_oversized_input() {
+    let cbor = sync_envelope().to_cbor().unwrap();
+    let limits = EnvelopeLimits {
+        max_frame_bytes: cbor.len() - 1,
+        ..EnvelopeLimits::default()
+    };
+    assert!(matches!(
+        SignedEnvelope::from_cbor(&cbor, &limits),
+        Err(ProtocolError::Cbor(_))
+    ));
+}
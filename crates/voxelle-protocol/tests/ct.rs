@@ -0,0 +1,38 @@
+use sha2::{Digest, Sha256};
+use voxelle_protocol::{ct_eq_bytes, ct_eq_str, verify_hex_digest};
+
+#[test]
+fn ct_eq_bytes_and_str() {
+    assert!(ct_eq_bytes(b"", b""));
+    assert!(ct_eq_bytes(b"token", b"token"));
+    assert!(!ct_eq_bytes(b"token", b"tokeN"));
+    assert!(!ct_eq_bytes(b"token", b"token!"));
+    assert!(!ct_eq_bytes(b"", b"x"));
+
+    assert!(ct_eq_str("ed25519:abc", "ed25519:abc"));
+    assert!(!ct_eq_str("ed25519:abc", "ed25519:abd"));
+    assert!(!ct_eq_str("abc", "ab"));
+}
+
+#[test]
+fn verify_hex_digest_is_case_insensitive_and_safe() {
+    let data = b"voxelle-web-0.1.1.zip";
+    let digest = Sha256::digest(data);
+    let lower = hex::encode(digest);
+    let upper = lower.to_uppercase();
+
+    assert!(verify_hex_digest(&lower, &digest));
+    assert!(verify_hex_digest(&upper, &digest));
+    assert!(verify_hex_digest(&format!("  {lower}\n"), &digest));
+
+    let mut other = digest;
+    other[31] ^= 1;
+    assert!(!verify_hex_digest(&lower, &other));
+    // Odd length, non-hex, truncated and over-long inputs never match.
+    assert!(!verify_hex_digest(&lower[..63], &digest));
+    assert!(!verify_hex_digest(&format!("{}zz", &lower[..62]), &digest));
+    assert!(!verify_hex_digest(&lower[..62], &digest));
+    assert!(!verify_hex_digest(&format!("{lower}00"), &digest));
+    assert!(!verify_hex_digest("", &digest));
+    assert!(verify_hex_digest("", b""));
+}
@@ -0,0 +1,161 @@
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::SigningKey;
+use rand::rngs::OsRng;
+use serde_json::json;
+use voxelle_protocol::{Envelope, EnvelopeLimits, PrincipalId, SignedEnvelope};
+
+fn identity() -> (SigningKey, PrincipalId) {
+    let sk = SigningKey::generate(&mut OsRng);
+    let spki = sk.verifying_key().to_public_key_der().expect("spki");
+    let id = PrincipalId::from_spki_der(spki.as_bytes());
+    (sk, id)
+}
+
+#[test]
+fn signed_frame_round_trips() {
+    let (sk, id) = identity();
+    let env = Envelope::new(
+        "chat.msg",
+        id.clone(),
+        1_700_000_000_000,
+        json!({ "text": "hi" }),
+    );
+    assert_eq!(env.msg_id.len(), 32);
+
+    let frame = env.clone().signed_frame(&sk).unwrap();
+    let (body, rest) = voxelle_protocol::parse_netstring(&frame, usize::MAX).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(
+        serde_json::from_slice::<SignedEnvelope>(body)
+            .unwrap()
+            .envelope,
+        env
+    );
+    let back = Envelope::from_frame(&frame, &id).unwrap();
+    assert_eq!(back, env);
+
+    let reply = back.reply("chat.ack", id.clone(), 1_700_000_000_001, json!({}));
+    assert_eq!(reply.in_reply_to.as_deref(), Some(env.msg_id.as_str()));
+    let frame = reply.clone().signed_frame(&sk).unwrap();
+    assert_eq!(Envelope::from_frame(&frame, &id).unwrap(), reply);
+}
+
+#[test]
+fn msg_ids_are_unique() {
+    let (_, id) = identity();
+    let a = Envelope::new("k", id.clone(), 0, json!(null));
+    let b = Envelope::new("k", id, 0, json!(null));
+    assert_ne!(a.msg_id, b.msg_id);
+}
+
+#[test]
+fn changing_kind_after_signing_fails() {
+    let (sk, id) = identity();
+    let mut signed = Envelope::new("chat.msg", id.clone(), 1, json!({ "text": "hi" }))
+        .sign(&sk)
+        .unwrap();
+    signed.verify().unwrap();
+    signed.envelope.kind = "chat.delete".into();
+    assert!(signed.verify().is_err());
+    assert!(Envelope::from_frame(&signed.to_frame().unwrap(), &id).is_err());
+}
+
+#[test]
+fn payload_and_reply_are_signature_bound() {
+    let (sk, id) = identity();
+    let signed = Envelope::new("k", id, 1, json!({ "n": 1 }))
+        .sign(&sk)
+        .unwrap();
+
+    let mut payload = signed.clone();
+    payload.envelope.payload = json!({ "n": 2 });
+    assert!(payload.verify().is_err());
+
+    let mut reply = signed.clone();
+    reply.envelope.in_reply_to = Some("abc".into());
+    assert!(reply.verify().is_err());
+
+    let mut ts = signed;
+    ts.envelope.ts = 2;
+    assert!(ts.verify().is_err());
+}
+
+#[test]
+fn wrong_sender_is_rejected() {
+    let (sk, id) = identity();
+    let (other_sk, other_id) = identity();
+
+    let frame = Envelope::new("k", id.clone(), 1, json!({}))
+        .signed_frame(&sk)
+        .unwrap();
+    assert!(Envelope::from_frame(&frame, &other_id).is_err());
+
+    // Claiming someone else's id is caught at signing and at verification.
+    assert!(Envelope::new("k", id.clone(), 1, json!({}))
+        .sign(&other_sk)
+        .is_err());
+    let mut forged = Envelope::new("k", other_id, 1, json!({}))
+        .sign(&other_sk)
+        .unwrap();
+    forged.envelope.sender = id.clone();
+    assert!(Envelope::from_frame(&forged.to_frame().unwrap(), &id).is_err());
+}
+
+#[test]
+fn floats_cannot_be_signed() {
+    let (sk, id) = identity();
+    assert!(Envelope::new("k", id, 1, json!({ "x": 0.5 }))
+        .sign(&sk)
+        .is_err());
+}
+
+#[test]
+fn validate_enforces_limits() {
+    let (sk, id) = identity();
+    let limits = EnvelopeLimits::default();
+
+    let mut env = Envelope::new("k", id.clone(), 1, json!({}));
+    env.validate(&limits).unwrap();
+    env.kind = String::new();
+    assert!(env.validate(&limits).is_err());
+    env.kind = "k".repeat(limits.max_kind_bytes + 1);
+    assert!(env.validate(&limits).is_err());
+
+    let big = Envelope::new(
+        "k",
+        id.clone(),
+        1,
+        json!({ "blob": "x".repeat(limits.max_payload_bytes) }),
+    );
+    assert!(big.validate(&limits).is_err());
+    let frame = big.signed_frame(&sk).unwrap();
+    assert!(Envelope::from_frame(&frame, &id).is_err());
+
+    let frame = Envelope::new("k", id.clone(), 1, json!({}))
+        .signed_frame(&sk)
+        .unwrap();
+    let mut trailing = frame.clone();
+    trailing.push(b'x');
+    assert!(Envelope::from_frame(&trailing, &id).is_err());
+    assert!(SignedEnvelope::from_frame(&frame[..frame.len() - 1], &limits).is_err());
+}
+
+// Also verified by the JS bindings test in tests/wasm.
+#[test]
+fn pinned_vector_verifies() {
+    let signed: SignedEnvelope =
+        serde_json::from_str(include_str!("vectors/signed_envelope_v1.json")).unwrap();
+    signed.verify().unwrap();
+    assert_eq!(
+        signed.envelope.sender.as_str(),
+        "ed25519:Mkvi3qi8REYbAjPlH6SJAu1rHMZx53Oa8lUeC_5o9U4"
+    );
+    assert_eq!(
+        signed
+            .envelope
+            .clone()
+            .sign(&SigningKey::from_bytes(&[7u8; 32]))
+            .unwrap(),
+        signed
+    );
+}
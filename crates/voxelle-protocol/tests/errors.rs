@@ -0,0 +1,286 @@
+use base64::Engine;
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use serde_json::json;
+use voxelle_protocol::{
+    dearmor_sdp, derive_x25519_from_ed25519, ed25519_public_key_from_spki_der, negotiate,
+    netstring, normalize_pairing_code, open, parse_invite, parse_netstring, verify_batch,
+    CanonicalTime, Envelope, EnvelopeLimits, JcsError, NegotiationError, Offer, PrincipalId,
+    PrincipalOrDeviceId, ProtocolError, PublicKeyAlg, Revocation, RevocationSet, SignedEnvelope,
+    VersionRange,
+};
+
+// SEQUENCE { SEQUENCE { OID 1.3.101.<oid_last> }, BIT STRING { 0 unused bits, key } }
+fn spki(oid_last: u8, key: &[u8]) -> Vec<u8> {
+    let bits = key.len() as u8 + 1;
+    let alg = [0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, oid_last];
+    [&[0x30, 9 + bits][..], &alg, &[0x03, bits, 0x00], key].concat()
+}
+
+fn identity() -> (SigningKey, PrincipalId) {
+    let sk = SigningKey::generate(&mut OsRng);
+    let spki = sk.verifying_key().to_public_key_der().expect("spki");
+    (sk, PrincipalId::from_spki_der(spki.as_bytes()))
+}
+
+fn signed() -> (SignedEnvelope, PrincipalId) {
+    let (sk, id) = identity();
+    let env = Envelope::new("chat.msg", id.clone(), 1, json!({ "text": "hi" }));
+    (env.sign(&sk).unwrap(), id)
+}
+
+#[test]
+fn truncated_der_is_spki_parse() {
+    let der = spki(112, &[9u8; 32]);
+    assert!(matches!(
+        ed25519_public_key_from_spki_der(&der[..20]),
+        Err(ProtocolError::SpkiParse(_))
+    ));
+}
+
+#[test]
+fn oversized_input_is_spki_too_large() {
+    assert!(matches!(
+        ed25519_public_key_from_spki_der(&[0x30; 100]),
+        Err(ProtocolError::SpkiTooLarge(100))
+    ));
+}
+
+#[test]
+fn trailing_garbage_is_spki_trailing_bytes() {
+    let der = [spki(112, &[9u8; 32]), vec![0, 0]].concat();
+    assert!(matches!(
+        ed25519_public_key_from_spki_der(&der),
+        Err(ProtocolError::SpkiTrailingBytes(2))
+    ));
+}
+
+#[test]
+fn x25519_oid_is_wrong_algorithm() {
+    match ed25519_public_key_from_spki_der(&spki(110, &[9u8; 32])) {
+        Err(ProtocolError::WrongAlgorithm(oid)) => assert_eq!(oid, "1.3.101.110"),
+        other => panic!("expected WrongAlgorithm, got {other:?}"),
+    }
+}
+
+#[test]
+fn short_key_is_bad_key_length() {
+    assert!(matches!(
+        ed25519_public_key_from_spki_der(&spki(112, &[9u8; 31])),
+        Err(ProtocolError::BadKeyLength(31))
+    ));
+}
+
+#[test]
+fn off_curve_point_is_invalid_public_key() {
+    let bad = (0..=255u8)
+        .map(|b| [b; 32])
+        .find(|k| VerifyingKey::from_bytes(k).is_err())
+        .expect("some repeated-byte encoding is off the curve");
+    assert!(matches!(
+        ed25519_public_key_from_spki_der(&spki(112, &bad)),
+        Err(ProtocolError::InvalidPublicKey)
+    ));
+}
+
+#[test]
+fn float_payload_is_jcs() {
+    let (_, id) = identity();
+    let env = Envelope::new("chat.msg", id, 1, json!({ "x": 1.5 }));
+    assert!(matches!(
+        env.signature_input(),
+        Err(ProtocolError::Jcs(JcsError::FloatForbidden { .. }))
+    ));
+}
+
+#[test]
+fn truncated_netstring_is_netstring() {
+    assert!(matches!(
+        parse_netstring(b"3:ab", 64),
+        Err(ProtocolError::Netstring("truncated"))
+    ));
+}
+
+#[test]
+fn bad_prefix_is_invalid_principal_id() {
+    assert!(matches!(
+        "rsa:abc".parse::<PrincipalId>(),
+        Err(ProtocolError::InvalidPrincipalId(_))
+    ));
+}
+
+#[test]
+fn non_base64_sig_is_base64() {
+    let (mut signed, _) = signed();
+    signed.sig = "not base64!".into();
+    assert!(matches!(
+        signed.signature(),
+        Err(ProtocolError::Base64("envelope sig"))
+    ));
+}
+
+#[test]
+fn short_sig_is_malformed_signature() {
+    let (mut signed, _) = signed();
+    signed.sig = base64::engine::general_purpose::STANDARD.encode([0u8; 10]);
+    assert!(matches!(
+        signed.signature(),
+        Err(ProtocolError::MalformedSignature("envelope"))
+    ));
+}
+
+#[test]
+fn tampered_payload_is_signature_invalid() {
+    let (mut signed, _) = signed();
+    signed.envelope.payload = json!({ "text": "bye" });
+    assert!(matches!(
+        signed.verify(),
+        Err(ProtocolError::SignatureInvalid("envelope"))
+    ));
+}
+
+#[test]
+fn relabelled_alg_is_algorithm_mismatch() {
+    let (mut signed, _) = signed();
+    signed.alg = PublicKeyAlg::P256;
+    assert!(matches!(
+        signed.verify(),
+        Err(ProtocolError::AlgorithmMismatch { .. })
+    ));
+}
+
+#[test]
+fn empty_kind_is_invalid_envelope() {
+    let (_, id) = identity();
+    let env = Envelope::new("", id, 1, json!({}));
+    assert!(matches!(
+        env.validate(&EnvelopeLimits::default()),
+        Err(ProtocolError::InvalidEnvelope(_))
+    ));
+}
+
+#[test]
+fn unexpected_peer_is_envelope_sender() {
+    let (signed, _) = signed();
+    let (_, other) = identity();
+    assert!(matches!(
+        Envelope::from_frame(&signed.to_frame().unwrap(), &other),
+        Err(ProtocolError::EnvelopeSender(_))
+    ));
+}
+
+#[test]
+fn non_envelope_json_is_envelope_decode() {
+    assert!(matches!(
+        SignedEnvelope::from_frame(&netstring(b"{}"), &EnvelopeLimits::default()),
+        Err(ProtocolError::EnvelopeDecode(_))
+    ));
+}
+
+#[test]
+fn wrong_controller_is_invalid_revocation() {
+    let (principal, _) = identity();
+    let (_, other) = identity();
+    let rev = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Device("dev".into()),
+        "lost",
+        1,
+    );
+    assert!(matches!(
+        rev.verify(other.as_str()),
+        Err(ProtocolError::InvalidRevocation(_))
+    ));
+}
+
+#[test]
+fn revoked_device_is_revoked() {
+    let (principal, principal_id) = identity();
+    let rev = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Device("dev".into()),
+        "lost",
+        1,
+    );
+    let mut set = RevocationSet::new();
+    set.insert(rev, principal_id.as_str()).unwrap();
+    match set.check_delegation(principal_id.as_str(), "dev", 2) {
+        Err(ProtocolError::Revoked { kind, id }) => {
+            assert_eq!((kind, id.as_str()), ("device", "dev"))
+        }
+        other => panic!("expected Revoked, got {other:?}"),
+    }
+}
+
+#[test]
+fn missing_armor_is_armor() {
+    assert!(matches!(
+        dearmor_sdp("no armor here"),
+        Err(ProtocolError::Armor(_))
+    ));
+}
+
+#[test]
+fn garbage_link_is_invalid_invite() {
+    assert!(matches!(
+        parse_invite("https://voxelle.example/#invite=%%%"),
+        Err(ProtocolError::InvalidInvite(_))
+    ));
+}
+
+#[test]
+fn offset_is_invalid_timestamp() {
+    assert!(matches!(
+        "2024-05-01T12:00:00+00:00".parse::<CanonicalTime>(),
+        Err(ProtocolError::InvalidTimestamp(_))
+    ));
+}
+
+#[test]
+fn unknown_word_is_invalid_pairing_code() {
+    assert!(matches!(
+        normalize_pairing_code("abacus-notaword"),
+        Err(ProtocolError::InvalidPairingCode(_))
+    ));
+}
+
+#[test]
+fn truncated_box_is_sealed_box() {
+    let (sk, _) = identity();
+    assert!(matches!(
+        open(&derive_x25519_from_ed25519(&sk), &[1u8; 20], b""),
+        Err(ProtocolError::SealedBox(_))
+    ));
+}
+
+#[test]
+fn negotiation_and_batch_errors_convert() {
+    let offer = |min, max| Offer {
+        versions: VersionRange::new(min, max),
+        capabilities: Default::default(),
+    };
+    let err: ProtocolError = negotiate(&offer(1, 1), &offer(2, 2)).unwrap_err().into();
+    assert!(matches!(
+        err,
+        ProtocolError::Negotiation(NegotiationError::NoCommonVersion { .. })
+    ));
+
+    let (sk, _) = identity();
+    let sig = sk.sign(b"a");
+    let err: ProtocolError = verify_batch(&[(sk.verifying_key(), b"b", &sig)])
+        .unwrap_err()
+        .into();
+    assert!(matches!(err, ProtocolError::Batch(e) if e.failed == vec![0]));
+}
+
+#[test]
+fn converts_into_anyhow() {
+    fn parse(s: &str) -> anyhow::Result<PrincipalId> {
+        Ok(s.parse::<PrincipalId>()?)
+    }
+    let err = parse("nope").unwrap_err();
+    let err = err.downcast_ref::<ProtocolError>().unwrap();
+    assert!(matches!(err, ProtocolError::InvalidPrincipalId(_)));
+    assert_eq!(err.code(), "invalid_principal_id");
+}
@@ -0,0 +1,83 @@
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::SigningKey;
+use voxelle_protocol::{fingerprint, PrincipalId};
+
+fn id_from(seed: u8) -> PrincipalId {
+    let sk = SigningKey::from_bytes(&[seed; 32]);
+    let spki = sk.verifying_key().to_public_key_der().expect("spki");
+    PrincipalId::from_spki_der(spki.as_bytes())
+}
+
+fn fixed_id() -> PrincipalId {
+    id_from(7)
+}
+
+#[test]
+fn fingerprint_vectors_are_pinned() {
+    let id = fixed_id();
+    assert_eq!(
+        id.as_str(),
+        "ed25519:Mkvi3qi8REYbAjPlH6SJAu1rHMZx53Oa8lUeC_5o9U4"
+    );
+    let fp = fingerprint(&id);
+    assert_eq!(fp.grouped_hex(), "cdf6 3293 0970 d30d f1e3 632d b2b3 8d91");
+    assert_eq!(fp.word_string(), "snack refinish exit upscale vineyard");
+    assert_eq!(fp.identicon_seed(), 4460048716758647955);
+}
+
+#[test]
+fn words_skip_values_that_would_favor_the_start_of_the_list() {
+    let list = voxelle_protocol::wordlist();
+    let limit = (1 << 16) / list.len() * list.len();
+    let value = |c: &[u8]| usize::from(u16::from_be_bytes([c[0], c[1]]));
+    let mut skipped = 0;
+    for seed in 0..64 {
+        let fp = fingerprint(&id_from(seed));
+        let values = fp.as_bytes().chunks(2).map(value);
+        skipped += values.clone().take(5).filter(|v| *v >= limit).count();
+        let expected: Vec<&str> = values
+            .filter(|v| *v < limit)
+            .take(5)
+            .map(|v| list[v % list.len()])
+            .collect();
+        assert_eq!(fp.words(), expected);
+    }
+    assert!(skipped > 0);
+}
+
+#[test]
+fn fingerprint_is_domain_separated_from_id_hash() {
+    let id = fixed_id();
+    assert_ne!(fingerprint(&id).as_bytes(), &id.hash_bytes());
+}
+
+#[test]
+fn matches_is_forgiving() {
+    let fp = fingerprint(&fixed_id());
+    assert!(fp.matches("cdf6 3293 0970 d30d f1e3 632d b2b3 8d91"));
+    assert!(fp.matches("CDF6-3293-0970-D30D-F1E3-632D-B2B3-8D91"));
+    assert!(fp.matches("cdf632930970d30df1e3632db2b38d91"));
+    assert!(fp.matches("  cdf6:3293:0970:d30d\nf1e3:632d:b2b3:8d91 "));
+    assert!(fp.matches("Snack Refinish  exit-upscale.vineyard"));
+
+    assert!(!fp.matches(""));
+    assert!(!fp.matches("cdf6 3293 0970 d30d"));
+    assert!(!fp.matches("cdf6 3293 0970 d30d f1e3 632d b2b3 8d90"));
+    assert!(!fp.matches("snack refinish exit upscale"));
+}
+
+#[test]
+fn principal_id_parsing() {
+    let id = fixed_id();
+    let parsed: PrincipalId = id.as_str().parse().unwrap();
+    assert_eq!(parsed, id);
+    assert!("ed25519:short".parse::<PrincipalId>().is_err());
+    assert!("rsa:Mkvi3qi8REYbAjPlH6SJAu1rHMZx53Oa8lUeC_5o9U4"
+        .parse::<PrincipalId>()
+        .is_err());
+
+    let json = serde_json::to_string(&id).unwrap();
+    assert_eq!(json, format!("\"{id}\""));
+    assert_eq!(serde_json::from_str::<PrincipalId>(&json).unwrap(), id);
+    assert!(serde_json::from_str::<PrincipalId>("\"nope\"").is_err());
+}
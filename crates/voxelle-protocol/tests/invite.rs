@@ -0,0 +1,212 @@
+use base64::Engine;
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::{Signer, SigningKey};
+use rand::rngs::OsRng;
+use serde_json::json;
+use voxelle_protocol::{
+    parse_invite, DelegationCert, PrincipalOrDeviceId, ProtocolError, Revocation, RevocationSet,
+};
+
+fn invite_json() -> serde_json::Value {
+    json!({
+        "v": 1,
+        "space_id": "ed25519:space",
+        "invite_id": "abc",
+        "issued_ts": 1,
+        "expires_ts": 2,
+        "issuer_principal_id": "ed25519:space",
+        "issuer_device_id": "ed25519:device",
+        "issuer_device_pub": "MCow",
+        "issuer_delegation": {
+            "v": 1,
+            "principal_id": "ed25519:space",
+            "principal_pub": "MCow",
+            "device_pub": "MCow",
+            "device_id": "ed25519:device",
+            "not_before_ts": 0,
+            "expires_ts": 2,
+            "scopes": [],
+            "sig": "c2ln"
+        },
+        "scopes": ["space:ed25519:space:read"],
+        "constraints": {},
+        "bootstrap": { "peers": [] },
+        "sig": "c2ln"
+    })
+}
+
+fn encode(v: &serde_json::Value) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(v).unwrap())
+}
+
+#[test]
+fn accepts_links_fragments_and_bare_payloads() {
+    let b64 = encode(&invite_json());
+    for text in [
+        format!("https://voxelle.example/#invite={b64}"),
+        format!("https://voxelle.example/join?x=1#invite={b64}&via=qr"),
+        format!("#invite={b64}"),
+        format!("  {b64}==\n"),
+    ] {
+        let invite = parse_invite(&text).unwrap();
+        assert_eq!(invite.invite_id, "abc");
+        assert_eq!(invite.issuer_delegation.device_id, "ed25519:device");
+        assert!(invite.invite_issuer.is_none());
+    }
+}
+
+#[test]
+fn reserializes_like_the_web_client() {
+    let invite = parse_invite(&encode(&invite_json())).unwrap();
+    assert_eq!(serde_json::to_value(&invite).unwrap(), invite_json());
+}
+
+#[test]
+fn rejects_invalid_invites() {
+    let mut no_read = invite_json();
+    no_read["scopes"] = json!(["space:ed25519:space:post"]);
+    let mut v2 = invite_json();
+    v2["v"] = json!(2);
+    let mut missing = invite_json();
+    missing.as_object_mut().unwrap().remove("issuer_delegation");
+
+    for text in [
+        encode(&no_read),
+        encode(&v2),
+        encode(&missing),
+        "#invite=not*base64".to_string(),
+        "a".repeat(voxelle_protocol::MAX_INVITE_LINK_CHARS + 1),
+    ] {
+        assert!(matches!(
+            parse_invite(&text),
+            Err(ProtocolError::InvalidInvite(_))
+        ));
+    }
+}
+
+#[test]
+fn exposes_timestamps_as_canonical_time() {
+    let mut json = invite_json();
+    json["issued_ts"] = json!(1_714_566_896_789i64);
+    let invite = parse_invite(&encode(&json)).unwrap();
+    assert_eq!(
+        invite.issued_at().unwrap().to_string(),
+        "2024-05-01T12:34:56Z"
+    );
+    assert_eq!(
+        invite.issuer_delegation.not_before().unwrap().unix_secs(),
+        0
+    );
+
+    json["expires_ts"] = json!(i64::MAX);
+    assert!(matches!(
+        parse_invite(&encode(&json)),
+        Err(ProtocolError::InvalidTimestamp(_))
+    ));
+}
+
+fn spki_b64(sk: &SigningKey) -> (String, String) {
+    let spki = sk.verifying_key().to_public_key_der().expect("spki");
+    (
+        voxelle_protocol::principal_id_from_spki_der(spki.as_bytes()),
+        base64::engine::general_purpose::STANDARD.encode(spki.as_bytes()),
+    )
+}
+
+fn delegation(principal: &SigningKey, device: &SigningKey) -> DelegationCert {
+    let ((principal_id, principal_pub), (device_id, device_pub)) =
+        (spki_b64(principal), spki_b64(device));
+    let mut cert = DelegationCert {
+        v: 1,
+        principal_id,
+        principal_pub,
+        device_pub,
+        device_id,
+        not_before_ts: 1_000,
+        expires_ts: 2_000,
+        scopes: vec!["space:sp:join".into()],
+        sig: String::new(),
+    };
+    let sig = principal.sign(&cert.signature_input());
+    cert.sig = base64::engine::general_purpose::STANDARD.encode(sig.to_bytes());
+    cert
+}
+
+#[test]
+fn delegations_verify_within_their_window() {
+    let (principal, device) = (
+        SigningKey::generate(&mut OsRng),
+        SigningKey::generate(&mut OsRng),
+    );
+    let cert = delegation(&principal, &device);
+    cert.verify(1_000).unwrap();
+    cert.verify(2_000).unwrap();
+    for at in [999, 2_001] {
+        assert!(matches!(
+            cert.verify(at),
+            Err(ProtocolError::InvalidDelegation(_))
+        ));
+    }
+
+    let mut tampered = cert.clone();
+    tampered.scopes.push("space:sp:governance".into());
+    assert!(matches!(
+        tampered.verify(1_500),
+        Err(ProtocolError::SignatureInvalid("delegation"))
+    ));
+
+    // Signed by the device itself rather than its principal.
+    let mut self_signed = delegation(&device, &device);
+    self_signed.principal_id = cert.principal_id.clone();
+    assert!(self_signed.verify(1_500).is_err());
+}
+
+#[test]
+fn revoked_delegations_fail() {
+    let (principal, device) = (
+        SigningKey::generate(&mut OsRng),
+        SigningKey::generate(&mut OsRng),
+    );
+    let cert = delegation(&principal, &device);
+    let mut set = RevocationSet::new();
+    let rev = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Device(cert.device_id.clone()),
+        "lost",
+        1_500,
+    );
+    set.insert(rev, &cert.principal_id).unwrap();
+
+    cert.verify_unrevoked(1_200, &set).unwrap();
+    assert!(matches!(
+        cert.verify_unrevoked(1_600, &set),
+        Err(ProtocolError::Revoked { kind: "device", .. })
+    ));
+}
+
+#[test]
+fn invites_need_a_delegation_for_their_issuer() {
+    let (principal, device) = (
+        SigningKey::generate(&mut OsRng),
+        SigningKey::generate(&mut OsRng),
+    );
+    let cert = delegation(&principal, &device);
+    let mut json = invite_json();
+    json["issuer_principal_id"] = json!(cert.principal_id);
+    json["issuer_device_id"] = json!(cert.device_id);
+    json["issuer_delegation"] = serde_json::to_value(&cert).unwrap();
+    let invite = parse_invite(&encode(&json)).unwrap();
+    invite.verify_issuer_delegation(1_500).unwrap();
+
+    json["issuer_device_id"] = json!("ed25519:someone-else");
+    let invite = parse_invite(&encode(&json)).unwrap();
+    assert!(matches!(
+        invite.verify_issuer_delegation(1_500),
+        Err(ProtocolError::InvalidDelegation(_))
+    ));
+    // The fixture's placeholder delegation doesn't verify at all.
+    assert!(parse_invite(&encode(&invite_json()))
+        .unwrap()
+        .verify_issuer_delegation(1)
+        .is_err());
+}
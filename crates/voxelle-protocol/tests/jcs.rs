@@ -0,0 +1,121 @@
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use voxelle_protocol::{jcs_bytes, jcs_bytes_with_policy, jcs_value, JcsError, JcsPolicy};
+
+fn jcs_str(v: &impl Serialize) -> String {
+    String::from_utf8(jcs_bytes(v).unwrap()).unwrap()
+}
+
+// RFC 8785 §3.2.2 sample.
+#[test]
+fn rfc8785_sample_object() {
+    let input = r#"{
+        "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+        "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+        "literals": [null, true, false]
+    }"#;
+    let v: serde_json::Value = serde_json::from_str(input).unwrap();
+    assert_eq!(
+        jcs_str(&v),
+        r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+    );
+}
+
+// RFC 8785 Appendix B number serialization vectors (IEEE 754 bit patterns).
+#[test]
+fn rfc8785_number_vectors() {
+    let vectors: [(u64, &str); 10] = [
+        (0x0000000000000000, "0"),
+        (0x8000000000000000, "0"),
+        (0x0000000000000001, "5e-324"),
+        (0x7fefffffffffffff, "1.7976931348623157e+308"),
+        (0x4340000000000000, "9007199254740992"),
+        (0x4430000000000000, "295147905179352830000"),
+        (0x44b52d02c7e14af6, "1e+23"),
+        (0x3eb0c6f7a0b5ed8d, "0.000001"),
+        (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+        (0x41b3de4355555555, "333333333.3333333"),
+    ];
+    for (bits, want) in vectors {
+        assert_eq!(jcs_str(&f64::from_bits(bits)), want, "bits {bits:#018x}");
+    }
+}
+
+#[test]
+fn non_finite_numbers_report_their_path() {
+    #[derive(Serialize)]
+    struct Inner {
+        xs: Vec<f64>,
+    }
+    #[derive(Serialize)]
+    struct Outer {
+        #[serde(rename = "a/b")]
+        inner: Inner,
+    }
+    let v = Outer {
+        inner: Inner {
+            xs: vec![1.0, f64::NAN],
+        },
+    };
+    let err = jcs_value(&v).unwrap_err();
+    assert_eq!(
+        err,
+        JcsError::NonFiniteNumber {
+            path: "/a~1b/xs/1".into()
+        }
+    );
+
+    let err = jcs_value(&json!({ "k": 1 })).and_then(|_| jcs_value(&[f64::INFINITY]));
+    assert_eq!(err.unwrap_err().path(), Some("/0"));
+    assert!(jcs_bytes(&f64::NEG_INFINITY).is_err());
+}
+
+#[test]
+fn unsafe_integers_are_rejected() {
+    jcs_value(&json!({ "n": 9007199254740991u64 })).unwrap();
+    assert_eq!(
+        jcs_value(&json!({ "n": 9007199254740992u64 })).unwrap_err(),
+        JcsError::IntegerOutOfRange { path: "/n".into() }
+    );
+    assert_eq!(
+        jcs_value(&vec![i64::MIN]).unwrap_err(),
+        JcsError::IntegerOutOfRange { path: "/0".into() }
+    );
+}
+
+#[test]
+fn integers_only_policy_forbids_floats() {
+    let ok = jcs_bytes_with_policy(
+        &json!({ "ts": 1700000000000u64, "v": 1 }),
+        JcsPolicy::IntegersOnly,
+    )
+    .unwrap();
+    assert_eq!(ok, br#"{"ts":1700000000000,"v":1}"#);
+
+    let err = voxelle_protocol::jcs_value_with_policy(
+        &json!({ "body": { "x": 1.0 } }),
+        JcsPolicy::IntegersOnly,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        JcsError::FloatForbidden {
+            path: "/body/x".into()
+        }
+    );
+    assert!(err.to_string().contains("/body/x"));
+}
+
+#[test]
+fn struct_and_map_output_is_sorted() {
+    #[derive(Serialize)]
+    enum Kind {
+        Pair(u8, u8),
+    }
+    let mut m = BTreeMap::new();
+    m.insert(2u32, "two");
+    m.insert(10u32, "ten");
+    assert_eq!(jcs_str(&m), r#"{"10":"ten","2":"two"}"#);
+    assert_eq!(jcs_str(&Kind::Pair(1, 2)), r#"{"Pair":[1,2]}"#);
+}
@@ -0,0 +1,107 @@
+use voxelle_protocol::{negotiate, Capabilities, NegotiationError, Offer, VersionRange};
+
+fn offer(min: u32, max: u32, caps: &[&str]) -> Offer {
+    Offer {
+        versions: VersionRange::new(min, max),
+        capabilities: caps.iter().copied().collect(),
+    }
+}
+
+#[test]
+fn picks_highest_common_version() {
+    let agreed = negotiate(&offer(1, 3, &[]), &offer(2, 5, &[])).unwrap();
+    assert_eq!(agreed.version, 3);
+
+    let agreed = negotiate(&offer(1, 1, &[]), &offer(1, 1, &[])).unwrap();
+    assert_eq!(agreed.version, 1);
+
+    let agreed = negotiate(&offer(4, 9, &[]), &offer(1, 4, &[])).unwrap();
+    assert_eq!(agreed.version, 4);
+}
+
+#[test]
+fn disjoint_ranges_fail_precisely() {
+    let ours = offer(1, 2, &[]);
+    let theirs = offer(3, 4, &[]);
+    let err = negotiate(&ours, &theirs).unwrap_err();
+    assert_eq!(
+        err,
+        NegotiationError::NoCommonVersion {
+            ours: ours.versions,
+            theirs: theirs.versions,
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "no common protocol version (ours 1..=2, theirs 3..=4)"
+    );
+
+    // Symmetric.
+    assert!(matches!(
+        negotiate(&theirs, &ours),
+        Err(NegotiationError::NoCommonVersion { .. })
+    ));
+}
+
+#[test]
+fn inverted_ranges_are_rejected() {
+    assert_eq!(
+        negotiate(&offer(3, 1, &[]), &offer(1, 3, &[])),
+        Err(NegotiationError::InvalidOurRange(VersionRange::new(3, 1)))
+    );
+    assert_eq!(
+        negotiate(&offer(1, 3, &[]), &offer(3, 1, &[])),
+        Err(NegotiationError::InvalidTheirRange(VersionRange::new(3, 1)))
+    );
+}
+
+#[test]
+fn capabilities_are_intersected() {
+    let ours = offer(
+        1,
+        1,
+        &[
+            Capabilities::CANDIDATES,
+            Capabilities::COMPRESSION,
+            Capabilities::RELAY,
+        ],
+    );
+    let theirs = offer(1, 1, &[Capabilities::COMPRESSION]);
+    let agreed = negotiate(&ours, &theirs).unwrap();
+    assert_eq!(
+        agreed.capabilities,
+        [Capabilities::COMPRESSION].into_iter().collect()
+    );
+
+    let none = negotiate(&ours, &offer(1, 1, &[])).unwrap();
+    assert!(none.capabilities.is_empty());
+
+    let all = negotiate(
+        &offer(1, 1, &Capabilities::KNOWN),
+        &offer(1, 1, &Capabilities::KNOWN),
+    )
+    .unwrap();
+    assert_eq!(all.capabilities, Capabilities::all_known());
+}
+
+#[test]
+fn unknown_capabilities_round_trip() {
+    let json = r#"{"versions":{"min":1,"max":2},"capabilities":["relay","x-future-feature"]}"#;
+    let parsed: Offer = serde_json::from_str(json).unwrap();
+    assert!(parsed.capabilities.contains("x-future-feature"));
+    assert_eq!(
+        parsed.capabilities.unknown().collect::<Vec<_>>(),
+        vec!["x-future-feature"]
+    );
+    assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+
+    // Both sides knowing the same future feature keeps it in the agreement.
+    let agreed = negotiate(&parsed, &parsed).unwrap();
+    assert!(agreed.capabilities.contains("x-future-feature"));
+}
+
+#[test]
+fn missing_capabilities_default_to_empty() {
+    let parsed: Offer = serde_json::from_str(r#"{"versions":{"min":1,"max":1}}"#).unwrap();
+    assert!(parsed.capabilities.is_empty());
+}
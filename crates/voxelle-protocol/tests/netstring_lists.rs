@@ -0,0 +1,106 @@
+use voxelle_protocol::{ListLimits, NetstringReader, NetstringWriter};
+
+// Golden bytes: these layouts are wire format and must not change.
+#[test]
+fn flat_list_golden_bytes() {
+    let mut w = NetstringWriter::new(Vec::<u8>::new());
+    w.write_list([b"ab".as_slice(), b"c".as_slice()]).unwrap();
+    assert_eq!(w.into_inner(), b"1:2,2:ab,1:c,");
+
+    let mut w = NetstringWriter::new(Vec::<u8>::new());
+    w.write_list(std::iter::empty::<&[u8]>()).unwrap();
+    assert_eq!(w.into_inner(), b"1:0,");
+}
+
+#[test]
+fn nested_list_golden_bytes() {
+    let mut w = NetstringWriter::new(Vec::<u8>::new());
+    w.write_nested_list([b"ab".as_slice(), b"c".as_slice()])
+        .unwrap();
+    assert_eq!(w.into_inner(), b"13:1:2,2:ab,1:c,,");
+}
+
+#[test]
+fn lists_round_trip_and_nested_can_be_skipped() {
+    let mut w = NetstringWriter::new(Vec::<u8>::new());
+    w.write_prefix("p2pspace/test/v0\n").unwrap();
+    w.write_str("head").unwrap();
+    w.write_nested_list([b"x".as_slice(), b"".as_slice(), b"yz".as_slice()])
+        .unwrap();
+    w.write_list([b"one".as_slice()]).unwrap();
+    w.write_int(-42).unwrap();
+    let bytes = w.into_inner();
+
+    let limits = ListLimits::default();
+    let mut r = NetstringReader::new(&bytes, 1024);
+    r.read_prefix("p2pspace/test/v0\n").unwrap();
+    assert_eq!(r.read_str().unwrap(), "head");
+    assert_eq!(
+        r.read_nested_list(&limits).unwrap(),
+        vec![b"x".as_slice(), b"", b"yz"]
+    );
+    assert_eq!(r.read_list(&limits).unwrap(), vec![b"one".as_slice()]);
+    assert_eq!(r.read_int().unwrap(), -42);
+    assert!(r.is_empty());
+
+    let mut r = NetstringReader::new(&bytes, 1024);
+    r.read_prefix("p2pspace/test/v0\n").unwrap();
+    r.skip().unwrap();
+    r.skip().unwrap();
+    assert_eq!(r.read_list(&limits).unwrap(), vec![b"one".as_slice()]);
+}
+
+#[test]
+fn read_list_enforces_limits() {
+    let mut w = NetstringWriter::new(Vec::<u8>::new());
+    w.write_list([b"aaaa".as_slice(), b"bbbb".as_slice(), b"cccc".as_slice()])
+        .unwrap();
+    let bytes = w.into_inner();
+
+    let read = |limits: ListLimits| {
+        NetstringReader::new(&bytes, 1024)
+            .read_list(&limits)
+            .map(|v| v.len())
+    };
+    let base = ListLimits {
+        max_items: 3,
+        max_item_bytes: 4,
+        max_total_bytes: 12,
+    };
+    assert_eq!(read(base).unwrap(), 3);
+    assert!(read(ListLimits {
+        max_items: 2,
+        ..base
+    })
+    .is_err());
+    assert!(read(ListLimits {
+        max_item_bytes: 3,
+        ..base
+    })
+    .is_err());
+    assert!(read(ListLimits {
+        max_total_bytes: 11,
+        ..base
+    })
+    .is_err());
+}
+
+#[test]
+fn reader_rejects_malformed_input() {
+    let limits = ListLimits::default();
+    // Count claims more items than present.
+    assert!(NetstringReader::new(b"1:3,1:a,", 64)
+        .read_list(&limits)
+        .is_err());
+    // Non-canonical counts.
+    assert!(NetstringReader::new(b"2:01,", 64).read_count().is_err());
+    assert!(NetstringReader::new(b"2:-1,", 64).read_count().is_err());
+    // Leading zero length, missing terminator, oversize item.
+    assert!(NetstringReader::new(b"01:a,", 64).read_bytes().is_err());
+    assert!(NetstringReader::new(b"1:ab", 64).read_bytes().is_err());
+    assert!(NetstringReader::new(b"5:abcde,", 4).read_bytes().is_err());
+    // Trailing garbage inside a nested list.
+    assert!(NetstringReader::new(b"9:1:1,1:a,x,", 64)
+        .read_nested_list(&limits)
+        .is_err());
+}
@@ -0,0 +1,231 @@
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::{Signer, SigningKey};
+use serde_json::json;
+use voxelle_protocol::{
+    ed25519_public_key_from_spki_der, is_ed25519_spki, parse_spki, principal_id_from_spki_der,
+    Envelope, PrincipalId, ProtocolError, PublicKeyAlg, SignedEnvelope,
+};
+
+// id-ecPublicKey / prime256v1 SPKI header, followed by an uncompressed point.
+const P256_SPKI_HEADER: &str = "3059301306072a8648ce3d020106082a8648ce3d030107034200";
+// Public point of the P-256 scalar [7; 32].
+const P256_POINT: &str = "041e18532fd4754c02f3041d9c75ceb33b83ffd81ac7ce4fe882ccb1c98bc5896ea46c311c4e2ff40dd96a3653e6e45445d32dfe486eced75c7a90c6a18881c0a3";
+
+fn p256_spki() -> Vec<u8> {
+    hex::decode(format!("{P256_SPKI_HEADER}{P256_POINT}")).unwrap()
+}
+
+fn ed25519_spki(sk: &SigningKey) -> Vec<u8> {
+    sk.verifying_key()
+        .to_public_key_der()
+        .unwrap()
+        .as_bytes()
+        .to_vec()
+}
+
+#[test]
+fn parses_ed25519() {
+    let sk = SigningKey::from_bytes(&[7u8; 32]);
+    let pk = parse_spki(&ed25519_spki(&sk)).unwrap();
+    assert_eq!(pk.alg(), PublicKeyAlg::Ed25519);
+    assert_eq!(pk.as_ed25519(), Some(&sk.verifying_key()));
+
+    let sig = sk.sign(b"msg").to_bytes();
+    pk.verify(b"msg", &sig).unwrap();
+    assert!(matches!(
+        pk.verify(b"other", &sig),
+        Err(ProtocolError::SignatureInvalid("ed25519"))
+    ));
+    assert!(matches!(
+        pk.verify(b"msg", &sig[..63]),
+        Err(ProtocolError::MalformedSignature("ed25519"))
+    ));
+}
+
+#[test]
+fn ids_hash_the_spki_whatever_the_algorithm() {
+    let spki = p256_spki();
+    let id = principal_id_from_spki_der(&spki);
+    assert!(id.parse::<PrincipalId>().is_ok());
+    assert_ne!(
+        id,
+        principal_id_from_spki_der(&ed25519_spki(&SigningKey::from_bytes(&[7u8; 32])))
+    );
+}
+
+#[test]
+fn ed25519_only_entry_points_reject_p256() {
+    let spki = p256_spki();
+    assert!(!is_ed25519_spki(&spki));
+    assert!(matches!(
+        ed25519_public_key_from_spki_der(&spki),
+        Err(ProtocolError::WrongAlgorithm(_))
+    ));
+}
+
+#[test]
+fn alg_defaults_to_ed25519_for_older_frames() {
+    let signed: SignedEnvelope =
+        serde_json::from_str(include_str!("vectors/signed_envelope_v1.json")).unwrap();
+    assert_eq!(signed.alg, PublicKeyAlg::Ed25519);
+    signed.verify().unwrap();
+    assert!(serde_json::to_string(&signed)
+        .unwrap()
+        .contains(r#""alg":"ed25519""#));
+}
+
+#[test]
+fn ed25519_envelope_relabelled_p256_is_rejected() {
+    let sk = SigningKey::from_bytes(&[7u8; 32]);
+    let id = PrincipalId::from_spki_der(&ed25519_spki(&sk));
+    let mut signed = Envelope::new("chat.msg", id, 1, json!({}))
+        .sign(&sk)
+        .unwrap();
+    signed.alg = PublicKeyAlg::P256;
+    assert!(matches!(
+        signed.verify(),
+        Err(ProtocolError::AlgorithmMismatch {
+            declared: PublicKeyAlg::P256,
+            actual: PublicKeyAlg::Ed25519,
+        })
+    ));
+    assert!(voxelle_protocol::verify_envelopes_batch(&[signed]).is_err());
+}
+
+#[cfg(not(feature = "p256"))]
+#[test]
+fn p256_needs_the_feature() {
+    assert!(matches!(
+        parse_spki(&p256_spki()),
+        Err(ProtocolError::WrongAlgorithm(oid)) if oid == "1.2.840.10045.2.1"
+    ));
+}
+
+#[cfg(feature = "p256")]
+mod p256_keys {
+    use super::*;
+    use base64::Engine;
+
+    fn p256_key() -> p256::ecdsa::SigningKey {
+        p256::ecdsa::SigningKey::from_bytes(&[7u8; 32].into()).unwrap()
+    }
+
+    fn p256_sign(msg: &[u8]) -> Vec<u8> {
+        let sig: p256::ecdsa::Signature = p256_key().sign(msg);
+        sig.to_bytes().to_vec()
+    }
+
+    #[test]
+    fn parses_and_verifies_p256() {
+        let pk = parse_spki(&p256_spki()).unwrap();
+        assert_eq!(pk.alg(), PublicKeyAlg::P256);
+        assert_eq!(pk.as_ed25519(), None);
+        pk.verify(b"msg", &p256_sign(b"msg")).unwrap();
+        assert!(matches!(
+            pk.verify(b"other", &p256_sign(b"msg")),
+            Err(ProtocolError::SignatureInvalid("p256"))
+        ));
+    }
+
+    #[test]
+    fn compressed_points_are_accepted() {
+        let point = p256_key()
+            .verifying_key()
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec();
+        let der = [
+            &hex::decode("3039301306072a8648ce3d020106082a8648ce3d030107032200").unwrap()[..],
+            &point,
+        ]
+        .concat();
+        assert_eq!(parse_spki(&der).unwrap(), parse_spki(&p256_spki()).unwrap());
+    }
+
+    #[test]
+    fn other_curves_and_bad_points_are_rejected() {
+        // secp384r1 named curve with the same point.
+        let p384 = hex::decode(
+            format!("305630100607 2a8648ce3d0201 0605 2b81040022 034200{P256_POINT}")
+                .replace(' ', ""),
+        )
+        .unwrap();
+        assert!(matches!(
+            parse_spki(&p384),
+            Err(ProtocolError::WrongAlgorithm(_))
+        ));
+
+        let mut off_curve = p256_spki();
+        let last = off_curve.len() - 1;
+        off_curve[last] ^= 1;
+        assert!(matches!(
+            parse_spki(&off_curve),
+            Err(ProtocolError::InvalidPublicKey)
+        ));
+    }
+
+    #[test]
+    fn p256_envelope_round_trips() {
+        let spki = p256_spki();
+        let id = PrincipalId::from_spki_der(&spki);
+        let env = Envelope::new("chat.msg", id.clone(), 1, json!({ "text": "hi" }));
+        let sig = p256_sign(&env.signature_input().unwrap());
+        let signed = env.with_signature(&spki, &sig).unwrap();
+        assert_eq!(signed.alg, PublicKeyAlg::P256);
+
+        let frame = signed.to_frame().unwrap();
+        assert_eq!(Envelope::from_frame(&frame, &id).unwrap(), signed.envelope);
+        assert!(voxelle_protocol::verify_envelopes_batch(std::slice::from_ref(&signed)).is_ok());
+        assert!(matches!(
+            signed.verifying_key(),
+            Err(ProtocolError::WrongAlgorithm(_))
+        ));
+    }
+
+    #[test]
+    fn p256_envelope_labelled_ed25519_is_rejected() {
+        let spki = p256_spki();
+        let env = Envelope::new("chat.msg", PrincipalId::from_spki_der(&spki), 1, json!({}));
+        let sig = p256_sign(&env.signature_input().unwrap());
+        let mut signed = env.with_signature(&spki, &sig).unwrap();
+        signed.alg = PublicKeyAlg::Ed25519;
+        assert!(matches!(
+            signed.verify(),
+            Err(ProtocolError::AlgorithmMismatch {
+                declared: PublicKeyAlg::Ed25519,
+                actual: PublicKeyAlg::P256,
+            })
+        ));
+    }
+
+    #[test]
+    fn ed25519_signature_under_p256_key_is_rejected() {
+        // Both schemes use 64-byte signatures, so the bytes parse either way.
+        let spki = p256_spki();
+        let env = Envelope::new("chat.msg", PrincipalId::from_spki_der(&spki), 1, json!({}));
+        let ed_sig = SigningKey::from_bytes(&[7u8; 32])
+            .sign(&env.signature_input().unwrap())
+            .to_bytes();
+        assert!(matches!(
+            env.clone().with_signature(&spki, &ed_sig),
+            Err(ProtocolError::SignatureInvalid("envelope"))
+        ));
+
+        // And a P-256 signature presented with an Ed25519 sender key.
+        let ed_key = SigningKey::from_bytes(&[7u8; 32]);
+        let ed_spki = ed25519_spki(&ed_key);
+        let env = Envelope::new(
+            "chat.msg",
+            PrincipalId::from_spki_der(&ed_spki),
+            1,
+            json!({}),
+        );
+        let p_sig = p256_sign(&env.signature_input().unwrap());
+        let mut forged = env.sign(&ed_key).unwrap();
+        forged.sig = base64::engine::general_purpose::STANDARD.encode(p_sig);
+        assert!(matches!(
+            forged.verify(),
+            Err(ProtocolError::SignatureInvalid("envelope"))
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::SigningKey;
+use rand::rngs::OsRng;
+use voxelle_protocol::{PrincipalOrDeviceId, Revocation, RevocationSet};
+
+fn id_of(sk: &SigningKey) -> String {
+    let spki = sk.verifying_key().to_public_key_der().expect("spki");
+    voxelle_protocol::principal_id_from_spki_der(spki.as_bytes())
+}
+
+#[test]
+fn revoked_before_use_is_rejected() {
+    let principal = SigningKey::generate(&mut OsRng);
+    let device = SigningKey::generate(&mut OsRng);
+    let (principal_id, device_id) = (id_of(&principal), id_of(&device));
+
+    let rev = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Device(device_id.clone()),
+        "lost phone",
+        1_000,
+    );
+    let mut set = RevocationSet::new();
+    set.insert(rev, &principal_id).unwrap();
+
+    let subject = PrincipalOrDeviceId::Device(device_id.clone());
+    assert!(set.is_revoked(&subject, 1_000));
+    assert!(set.is_revoked(&subject, 5_000));
+    assert!(set
+        .check_delegation(&principal_id, &device_id, 2_000)
+        .is_err());
+}
+
+#[test]
+fn uses_before_revocation_stay_valid() {
+    // Delegation expired at t=500, revocation issued afterwards at t=2_000:
+    // historical uses inside the validity window are not retroactively revoked.
+    let principal = SigningKey::generate(&mut OsRng);
+    let device = SigningKey::generate(&mut OsRng);
+    let (principal_id, device_id) = (id_of(&principal), id_of(&device));
+
+    let rev = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Device(device_id.clone()),
+        "cleanup after expiry",
+        2_000,
+    );
+    let mut set = RevocationSet::new();
+    set.insert(rev, &principal_id).unwrap();
+
+    let subject = PrincipalOrDeviceId::Device(device_id.clone());
+    assert!(!set.is_revoked(&subject, 400));
+    set.check_delegation(&principal_id, &device_id, 400)
+        .unwrap();
+    assert!(set.is_revoked(&subject, 2_000));
+}
+
+#[test]
+fn unsigned_revocation_is_rejected() {
+    let principal = SigningKey::generate(&mut OsRng);
+    let device = SigningKey::generate(&mut OsRng);
+    let principal_id = id_of(&principal);
+
+    let mut rev = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Device(id_of(&device)),
+        "",
+        1,
+    );
+    rev.sig_by_principal = String::new();
+    assert!(rev.verify(&principal_id).is_err());
+
+    let mut set = RevocationSet::new();
+    assert!(set.insert(rev, &principal_id).is_err());
+    assert!(set.is_empty());
+}
+
+#[test]
+fn revocation_by_other_principal_is_rejected() {
+    let principal = SigningKey::generate(&mut OsRng);
+    let attacker = SigningKey::generate(&mut OsRng);
+    let device = SigningKey::generate(&mut OsRng);
+
+    let rev = Revocation::issue(
+        &attacker,
+        PrincipalOrDeviceId::Device(id_of(&device)),
+        "",
+        1,
+    );
+    rev.verify(&id_of(&attacker)).unwrap();
+    assert!(rev.verify(&id_of(&principal)).is_err());
+}
+
+#[test]
+fn self_revocation_only_for_own_principal() {
+    let principal = SigningKey::generate(&mut OsRng);
+    let other = SigningKey::generate(&mut OsRng);
+    let principal_id = id_of(&principal);
+
+    let own = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Principal(principal_id.clone()),
+        "root compromised",
+        10,
+    );
+    own.verify(&principal_id).unwrap();
+
+    let foreign = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Principal(id_of(&other)),
+        "",
+        10,
+    );
+    assert!(foreign.verify(&principal_id).is_err());
+}
+
+#[test]
+fn tampered_revocation_fails_and_set_roundtrips() {
+    let principal = SigningKey::generate(&mut OsRng);
+    let device = SigningKey::generate(&mut OsRng);
+    let principal_id = id_of(&principal);
+
+    let rev = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Device(id_of(&device)),
+        "lost",
+        100,
+    );
+    let mut tampered = rev.clone();
+    tampered.ts = 1;
+    assert!(tampered.verify(&principal_id).is_err());
+
+    let mut set = RevocationSet::new();
+    set.insert(rev, &principal_id).unwrap();
+    let json = serde_json::to_string(&set).unwrap();
+    let back: RevocationSet = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, set);
+    back.verify_all(|_| Some(principal_id.clone())).unwrap();
+}
+
+#[test]
+fn revocations_only_match_their_own_kind() {
+    let principal = SigningKey::generate(&mut OsRng);
+    let principal_id = id_of(&principal);
+
+    // A device revocation that happens to name the principal's own id.
+    let rev = Revocation::issue(
+        &principal,
+        PrincipalOrDeviceId::Device(principal_id.clone()),
+        "lost",
+        1,
+    );
+    let mut set = RevocationSet::new();
+    set.insert(rev, &principal_id).unwrap();
+
+    assert!(set.is_revoked(&PrincipalOrDeviceId::Device(principal_id.clone()), 2));
+    assert!(!set.is_revoked(&PrincipalOrDeviceId::Principal(principal_id.clone()), 2));
+    set.check_delegation(&principal_id, "other-device", 2)
+        .unwrap();
+}
@@ -0,0 +1,70 @@
+use ed25519_dalek::SigningKey;
+use rand::rngs::OsRng;
+use voxelle_protocol::{
+    derive_x25519_from_ed25519, derive_x25519_public_from_ed25519, open, seal, SEALED_BOX_V1,
+};
+use x25519_dalek::PublicKey;
+
+const PINNED: &str = include_str!("vectors/sealed_box_v1.hex");
+
+#[test]
+fn x25519_derivation_matches_for_secret_and_public() {
+    let sk = SigningKey::from_bytes(&[1u8; 32]);
+    let from_secret = PublicKey::from(&derive_x25519_from_ed25519(&sk));
+    let from_public = derive_x25519_public_from_ed25519(&sk.verifying_key());
+    assert_eq!(from_secret.as_bytes(), from_public.as_bytes());
+}
+
+#[test]
+fn known_answer_opens() {
+    let sk = SigningKey::from_bytes(&[1u8; 32]);
+    let sealed = hex::decode(PINNED.trim()).unwrap();
+    assert_eq!(sealed[0], SEALED_BOX_V1);
+    let opened = open(&derive_x25519_from_ed25519(&sk), &sealed, b"sid").unwrap();
+    assert_eq!(opened, b"v=0\r\n");
+}
+
+#[test]
+fn seal_round_trips_with_fresh_randomness() {
+    let sk = SigningKey::generate(&mut OsRng);
+    let recipient = derive_x25519_public_from_ed25519(&sk.verifying_key());
+    let a = seal(&recipient, b"offer sdp", b"aad").unwrap();
+    let b = seal(&recipient, b"offer sdp", b"aad").unwrap();
+    assert_ne!(a, b);
+    let secret = derive_x25519_from_ed25519(&sk);
+    assert_eq!(open(&secret, &a, b"aad").unwrap(), b"offer sdp");
+    assert_eq!(open(&secret, &b, b"aad").unwrap(), b"offer sdp");
+}
+
+#[test]
+fn tampering_is_detected() {
+    let sk = SigningKey::from_bytes(&[1u8; 32]);
+    let secret = derive_x25519_from_ed25519(&sk);
+    let sealed = hex::decode(PINNED.trim()).unwrap();
+
+    for i in 0..sealed.len() {
+        let mut bad = sealed.clone();
+        bad[i] ^= 0x01;
+        assert!(
+            open(&secret, &bad, b"sid").is_err(),
+            "flip at byte {i} accepted"
+        );
+    }
+    assert!(open(&secret, &sealed[..sealed.len() - 1], b"sid").is_err());
+    assert!(open(&secret, &sealed[..20], b"sid").is_err());
+    assert!(open(&secret, &sealed, b"other-sid").is_err());
+    assert!(open(&secret, &sealed, b"").is_err());
+}
+
+#[test]
+fn wrong_recipient_cannot_open() {
+    let alice = SigningKey::generate(&mut OsRng);
+    let mallory = SigningKey::generate(&mut OsRng);
+    let sealed = seal(
+        &derive_x25519_public_from_ed25519(&alice.verifying_key()),
+        b"secret",
+        b"",
+    )
+    .unwrap();
+    assert!(open(&derive_x25519_from_ed25519(&mallory), &sealed, b"").is_err());
+}
@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+
+#[test]
+fn session_ids_are_lowercase_hex_and_unique() {
+    let mut seen = HashSet::new();
+    for _ in 0..256 {
+        let sid = voxelle_protocol::generate_session_id();
+        assert_eq!(sid.len(), voxelle_protocol::SESSION_ID_BYTES * 2);
+        assert!(sid.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')));
+        assert!(seen.insert(sid));
+    }
+}
+
+#[test]
+fn wordlist_is_sorted_unique_and_large() {
+    let list = voxelle_protocol::wordlist();
+    assert_eq!(list.len(), 7772);
+    assert!(list.windows(2).all(|w| w[0] < w[1]));
+    assert!(list
+        .iter()
+        .all(|w| w.chars().all(|c| c.is_ascii_lowercase())));
+    // ~13 bits of entropy per word.
+    assert!((list.len() as f64).log2() > 12.9);
+}
+
+#[test]
+fn pairing_codes_use_wordlist_words() {
+    let mut seen = HashSet::new();
+    for _ in 0..256 {
+        let code = voxelle_protocol::generate_pairing_code(4);
+        let words: Vec<&str> = code.split('-').collect();
+        assert_eq!(words.len(), 4);
+        for w in &words {
+            assert!(voxelle_protocol::wordlist().binary_search(w).is_ok());
+        }
+        seen.insert(code);
+    }
+    // 52 bits per code: collisions across 256 samples would indicate a broken RNG.
+    assert_eq!(seen.len(), 256);
+}
+
+#[test]
+fn pairing_code_sid_is_deterministic_and_forgiving() {
+    let a = voxelle_protocol::pairing_code_to_sid("abacus-zoom-tiger-olive", b"salt").unwrap();
+    let b = voxelle_protocol::pairing_code_to_sid("  Abacus zoom\tTIGER_olive ", b"salt").unwrap();
+    assert_eq!(a, b);
+    assert_eq!(a.len(), 64);
+    assert!(a.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')));
+
+    let other_salt =
+        voxelle_protocol::pairing_code_to_sid("abacus-zoom-tiger-olive", b"pepper").unwrap();
+    assert_ne!(a, other_salt);
+    let other_code =
+        voxelle_protocol::pairing_code_to_sid("abacus-zoom-tiger-oboe", b"salt").unwrap();
+    assert_ne!(a, other_code);
+}
+
+#[test]
+fn pairing_code_rejects_unknown_words() {
+    assert!(voxelle_protocol::normalize_pairing_code("").is_err());
+    assert!(voxelle_protocol::normalize_pairing_code(" - ").is_err());
+    assert!(voxelle_protocol::pairing_code_to_sid("abacus-notaword", b"").is_err());
+}
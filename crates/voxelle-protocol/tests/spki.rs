@@ -0,0 +1,108 @@
+// proptest is a native-only dev-dependency.
+#![cfg(not(target_arch = "wasm32"))]
+
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::SigningKey;
+use proptest::prelude::*;
+use voxelle_protocol::{
+    ed25519_public_key_from_spki_der, is_ed25519_spki, ProtocolError, ED25519_SPKI_DER_LEN,
+    MAX_SPKI_DER_BYTES,
+};
+
+const ED25519_SPKI_HEADER: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+fn valid_spki(seed: [u8; 32]) -> Vec<u8> {
+    SigningKey::from_bytes(&seed)
+        .verifying_key()
+        .to_public_key_der()
+        .expect("spki")
+        .as_bytes()
+        .to_vec()
+}
+
+#[test]
+fn corpus() {
+    for line in include_str!("vectors/spki_corpus.txt").lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let (fields, note) = line.split_once('#').unwrap_or((line, ""));
+        let mut fields = fields.split_whitespace();
+        let want = fields.next().expect("expected result");
+        let der = hex::decode(fields.next().unwrap_or("")).expect("corpus hex");
+
+        let got = match ed25519_public_key_from_spki_der(&der) {
+            Ok(_) => "ok",
+            Err(e) => e.code(),
+        };
+        assert_eq!(got, want, "{}", note.trim());
+        // The cheap check must not accept what the parser rejects as malformed.
+        if matches!(
+            want,
+            "spki_parse" | "spki_trailing_bytes" | "spki_too_large"
+        ) {
+            assert!(!is_ed25519_spki(&der), "{}", note.trim());
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn random_bytes_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..2 * MAX_SPKI_DER_BYTES)) {
+        if let Ok(vk) = ed25519_public_key_from_spki_der(&bytes) {
+            prop_assert_eq!(&bytes[..12], &ED25519_SPKI_HEADER[..]);
+            prop_assert_eq!(&bytes[12..], vk.as_bytes().as_slice());
+        }
+    }
+
+    #[test]
+    fn random_bodies_behind_a_valid_header(key in proptest::collection::vec(any::<u8>(), 0..64)) {
+        let der = [&ED25519_SPKI_HEADER[..], &key].concat();
+        match ed25519_public_key_from_spki_der(&der) {
+            Ok(vk) => prop_assert_eq!(vk.as_bytes().as_slice(), &key[..]),
+            Err(ProtocolError::InvalidPublicKey) => prop_assert_eq!(key.len(), 32),
+            Err(ProtocolError::SpkiTooLarge(_)) => prop_assert!(der.len() > MAX_SPKI_DER_BYTES),
+            Err(ProtocolError::SpkiTrailingBytes(n)) => prop_assert_eq!(n, key.len() - 32),
+            Err(e) => prop_assert!(matches!(e, ProtocolError::SpkiParse(_)) && key.len() < 32, "{e}"),
+        }
+    }
+
+    #[test]
+    fn truncations_are_rejected(seed in any::<[u8; 32]>(), len in 0..ED25519_SPKI_DER_LEN) {
+        let der = valid_spki(seed);
+        prop_assert!(matches!(
+            ed25519_public_key_from_spki_der(&der[..len]),
+            Err(ProtocolError::SpkiParse(_))
+        ));
+    }
+
+    #[test]
+    fn header_bit_flips_are_rejected(seed in any::<[u8; 32]>(), bit in 0..12 * 8usize) {
+        let mut der = valid_spki(seed);
+        der[bit / 8] ^= 1 << (bit % 8);
+        prop_assert!(ed25519_public_key_from_spki_der(&der).is_err());
+    }
+
+    #[test]
+    fn key_bit_flips_change_the_key_or_fail(seed in any::<[u8; 32]>(), bit in 12 * 8..ED25519_SPKI_DER_LEN * 8) {
+        let original = ed25519_public_key_from_spki_der(&valid_spki(seed)).unwrap();
+        let mut der = valid_spki(seed);
+        der[bit / 8] ^= 1 << (bit % 8);
+        match ed25519_public_key_from_spki_der(&der) {
+            Ok(vk) => prop_assert_ne!(vk, original),
+            Err(e) => prop_assert!(matches!(e, ProtocolError::InvalidPublicKey), "{e}"),
+        }
+    }
+
+    #[test]
+    fn appended_bytes_are_rejected(seed in any::<[u8; 32]>(), extra in proptest::collection::vec(any::<u8>(), 1..64)) {
+        let der = [valid_spki(seed), extra.clone()].concat();
+        match ed25519_public_key_from_spki_der(&der) {
+            Err(ProtocolError::SpkiTrailingBytes(n)) => prop_assert_eq!(n, extra.len()),
+            Err(ProtocolError::SpkiTooLarge(n)) => prop_assert!(n > MAX_SPKI_DER_BYTES),
+            other => prop_assert!(false, "unexpected {other:?}"),
+        }
+    }
+}
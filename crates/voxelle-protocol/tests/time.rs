@@ -0,0 +1,64 @@
+use chrono::{TimeZone, Utc};
+use voxelle_protocol::{CanonicalTime, ProtocolError};
+
+#[test]
+fn round_trips_the_canonical_form() {
+    let t: CanonicalTime = "2024-05-01T12:34:56Z".parse().unwrap();
+    assert_eq!(t.to_string(), "2024-05-01T12:34:56Z");
+    assert_eq!(t.unix_secs(), 1_714_566_896);
+    assert_eq!(
+        serde_json::to_string(&t).unwrap(),
+        r#""2024-05-01T12:34:56Z""#
+    );
+    assert_eq!(
+        serde_json::from_str::<CanonicalTime>(r#""2024-05-01T12:34:56Z""#).unwrap(),
+        t
+    );
+}
+
+#[test]
+fn rejects_non_canonical_variants() {
+    for s in [
+        "2024-05-01T12:34:56+00:00",
+        "2024-05-01T12:34:56-00:00",
+        "2024-05-01T14:34:56+02:00",
+        "2024-05-01T12:34:56.000Z",
+        "2024-05-01T12:34:56.5Z",
+        "2024-05-01t12:34:56z",
+        "2024-05-01 12:34:56Z",
+        "2024-05-01T12:34Z",
+        "2024-05-01",
+        " 2024-05-01T12:34:56Z",
+        "",
+    ] {
+        assert!(
+            matches!(
+                s.parse::<CanonicalTime>(),
+                Err(ProtocolError::InvalidTimestamp(_))
+            ),
+            "{s:?} should be rejected"
+        );
+        assert!(serde_json::from_value::<CanonicalTime>(s.into()).is_err());
+    }
+}
+
+#[test]
+fn normalizing_drops_subseconds_explicitly() {
+    let dt = Utc.timestamp_millis_opt(1_714_566_896_789).unwrap();
+    let t = CanonicalTime::truncating(dt);
+    assert_eq!(t.to_string(), "2024-05-01T12:34:56Z");
+    assert_eq!(CanonicalTime::from_unix_ms(1_714_566_896_789).unwrap(), t);
+    assert_eq!(t.unix_ms(), 1_714_566_896_000);
+    assert!(matches!(
+        CanonicalTime::from_unix_ms(i64::MAX),
+        Err(ProtocolError::InvalidTimestamp(_))
+    ));
+}
+
+#[test]
+fn now_is_canonical_and_ordered() {
+    let t = CanonicalTime::now();
+    assert_eq!(t.to_string().parse::<CanonicalTime>().unwrap(), t);
+    let earlier: CanonicalTime = "2000-01-01T00:00:00Z".parse().unwrap();
+    assert!(earlier < t);
+}
@@ -0,0 +1,87 @@
+// Exercises the `wasm` feature bindings under node: `npm test` in this directory
+// (needs wasm-pack on PATH).
+import assert from 'node:assert/strict'
+import { createHash, createPrivateKey, createPublicKey } from 'node:crypto'
+import { readFileSync } from 'node:fs'
+import { test } from 'node:test'
+import protocol from './pkg/voxelle_protocol.js'
+
+const SIGNED_ENVELOPE = readFileSync(new URL('../vectors/signed_envelope_v1.json', import.meta.url), 'utf8')
+
+function spkiFromSeed(seed) {
+  const pkcs8 = Buffer.concat([Buffer.from('302e020100300506032b657004220420', 'hex'), seed])
+  const sk = createPrivateKey({ key: pkcs8, format: 'der', type: 'pkcs8' })
+  return new Uint8Array(createPublicKey(sk).export({ format: 'der', type: 'spki' }))
+}
+
+function throwsCode(fn, code) {
+  assert.throws(fn, (e) => e instanceof Error && e.code === code)
+}
+
+test('principal id matches the Rust vector and a JS derivation', () => {
+  const spki = spkiFromSeed(Buffer.alloc(32, 7))
+  assert.equal(protocol.principalIdFromSpkiDer(spki), 'ed25519:Mkvi3qi8REYbAjPlH6SJAu1rHMZx53Oa8lUeC_5o9U4')
+
+  // base64url without padding, the mismatch that bit the UI before.
+  const other = spkiFromSeed(Buffer.alloc(32, 1))
+  const hash = createHash('sha256').update(other).digest('base64url')
+  assert.equal(protocol.principalIdFromSpkiDer(other), `ed25519:${hash}`)
+})
+
+test('verifyEnvelope accepts the pinned vector', () => {
+  const res = protocol.verifyEnvelope(SIGNED_ENVELOPE)
+  assert.equal(res.kind, 'chat.msg')
+  assert.equal(res.msgId, '00112233445566778899aabbccddeeff')
+  assert.equal(res.inReplyTo, undefined)
+  assert.equal(res.sender, 'ed25519:Mkvi3qi8REYbAjPlH6SJAu1rHMZx53Oa8lUeC_5o9U4')
+  assert.equal(res.ts, 1700000000000)
+  assert.equal(res.payloadJson, '{"n":42,"text":"hi from rust"}')
+})
+
+test('verifyEnvelope throws typed errors', () => {
+  const env = JSON.parse(SIGNED_ENVELOPE)
+  throwsCode(() => protocol.verifyEnvelope(JSON.stringify({ ...env, payload: { n: 43 } })), 'signature_invalid')
+  throwsCode(() => protocol.verifyEnvelope(JSON.stringify({ ...env, sig: 'AAAA' })), 'malformed_signature')
+  throwsCode(() => protocol.verifyEnvelope(JSON.stringify({ ...env, kind: '' })), 'invalid_envelope')
+  throwsCode(() => protocol.verifyEnvelope('{}'), 'envelope_decode')
+})
+
+test('armor round trip', () => {
+  const sdp = 'v=0\r\no=- 1 2 IN IP4 127.0.0.1\r\ns=-\r\n'
+  const armored = protocol.armorSdp('offer', sdp)
+  assert.match(armored, /^-----BEGIN VOXELLE OFFER-----\n/)
+  assert.equal(protocol.dearmorSdp(armored.replaceAll('\n', ' ')), sdp)
+  throwsCode(() => protocol.dearmorSdp('no armor here'), 'armor')
+  throwsCode(() => protocol.armorSdp('pranswer', sdp), 'armor')
+})
+
+test('parseInvite decodes invite links', () => {
+  const invite = {
+    v: 1,
+    space_id: 'ed25519:space',
+    invite_id: 'abc',
+    issued_ts: 1,
+    expires_ts: 2,
+    issuer_principal_id: 'ed25519:space',
+    issuer_device_id: 'ed25519:device',
+    issuer_device_pub: 'MCow',
+    issuer_delegation: {
+      v: 1,
+      principal_id: 'ed25519:space',
+      principal_pub: 'MCow',
+      device_pub: 'MCow',
+      device_id: 'ed25519:device',
+      not_before_ts: 0,
+      expires_ts: 2,
+      scopes: [],
+      sig: 'c2ln',
+    },
+    scopes: ['space:ed25519:space:read'],
+    constraints: {},
+    bootstrap: { peers: [] },
+    sig: 'c2ln',
+  }
+  const b64 = Buffer.from(JSON.stringify(invite)).toString('base64url')
+  assert.deepEqual(protocol.parseInvite(`https://voxelle.example/#invite=${b64}`), invite)
+  throwsCode(() => protocol.parseInvite('#invite=%%%'), 'invalid_invite')
+})
@@ -0,0 +1,63 @@
+// Runs under node: `wasm-pack test --node crates/voxelle-protocol`.
+#![cfg(target_arch = "wasm32")]
+
+use ed25519_dalek::pkcs8::EncodePublicKey;
+use ed25519_dalek::SigningKey;
+use rand::rngs::OsRng;
+use serde_json::json;
+use voxelle_protocol::{
+    ed25519_public_key_from_spki_der, generate_session_id, is_ed25519_spki,
+    principal_id_from_spki_der, Envelope, PrincipalId, ProtocolError,
+};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn spki_of(sk: &SigningKey) -> Vec<u8> {
+    sk.verifying_key()
+        .to_public_key_der()
+        .expect("spki")
+        .as_bytes()
+        .to_vec()
+}
+
+#[wasm_bindgen_test]
+fn principal_id_matches_native_vector() {
+    // Same key and id as the native fingerprint tests.
+    let spki = spki_of(&SigningKey::from_bytes(&[7u8; 32]));
+    assert_eq!(
+        principal_id_from_spki_der(&spki),
+        "ed25519:Mkvi3qi8REYbAjPlH6SJAu1rHMZx53Oa8lUeC_5o9U4"
+    );
+}
+
+#[wasm_bindgen_test]
+fn spki_parsing() {
+    let sk = SigningKey::from_bytes(&[7u8; 32]);
+    let spki = spki_of(&sk);
+    assert!(is_ed25519_spki(&spki));
+    assert_eq!(
+        ed25519_public_key_from_spki_der(&spki).unwrap(),
+        sk.verifying_key()
+    );
+    assert!(matches!(
+        ed25519_public_key_from_spki_der(&spki[..20]),
+        Err(ProtocolError::SpkiParse(_))
+    ));
+}
+
+#[wasm_bindgen_test]
+fn envelope_round_trip_with_js_randomness() {
+    assert_eq!(generate_session_id().len(), 64);
+
+    let sk = SigningKey::generate(&mut OsRng);
+    let id = PrincipalId::from_spki_der(&spki_of(&sk));
+    let frame = Envelope::new("chat.msg", id.clone(), 1, json!({ "text": "hi" }))
+        .signed_frame(&sk)
+        .unwrap();
+    let env = Envelope::from_frame(&frame, &id).unwrap();
+    assert_eq!(env.payload, json!({ "text": "hi" }));
+
+    let mut tampered = frame.clone();
+    let at = tampered.len() - 10;
+    tampered[at] ^= 1;
+    assert!(Envelope::from_frame(&tampered, &id).is_err());
+}
@@ -0,0 +1,476 @@
+//! Untrusted, optional WebSocket signaling relay for Voxelle WebRTC
+//! offer/answer exchange. The `voxelle-signal` binary runs it standalone;
+//! [`Relay`] embeds it in another process.
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use futures_util::{sink::SinkExt, stream::StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::sync::{oneshot, watch};
+
+const MAX_WS_TEXT_BYTES: usize = 64 * 1024;
+const MAX_SID_CHARS: usize = 128;
+const MAX_SDP_CODE_CHARS: usize = 128 * 1024;
+const MAX_SESSIONS: usize = 10_000;
+const MAX_CLIENTS_PER_SESSION: usize = 16;
+pub const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone)]
+pub struct RelayConfig {
+    /// How long a session's offer/answer is kept after it is created.
+    pub ttl: Duration,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self { ttl: DEFAULT_TTL }
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    ttl: Duration,
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    // Flips to true when the relay shuts down, so open sockets close too.
+    stopping: watch::Receiver<bool>,
+}
+
+#[derive(Debug, Clone)]
+struct Session {
+    created_at: SystemTime,
+    offer: Option<String>,
+    answer: Option<String>,
+    clients: Vec<tokio::sync::mpsc::UnboundedSender<Message>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Info {
+    name: &'static str,
+    v: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "t")]
+enum ClientMsg {
+    #[serde(rename = "join")]
+    Join { v: u32, sid: String },
+    #[serde(rename = "set_offer")]
+    SetOffer { v: u32, sid: String, offer: String },
+    #[serde(rename = "set_answer")]
+    SetAnswer { v: u32, sid: String, answer: String },
+    #[serde(rename = "get_state")]
+    GetState { v: u32, sid: String },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "t")]
+enum ServerMsg {
+    #[serde(rename = "hello")]
+    Hello { v: u32 },
+    #[serde(rename = "state")]
+    State {
+        v: u32,
+        sid: String,
+        has_offer: bool,
+        has_answer: bool,
+        offer: Option<String>,
+        answer: Option<String>,
+    },
+    #[serde(rename = "error")]
+    Error { v: u32, error: String },
+}
+
+fn json_msg<T: Serialize>(v: T) -> Message {
+    Message::Text(serde_json::to_string(&v).unwrap_or_else(|_| "{\"t\":\"error\",\"v\":1,\"error\":\"encode\"}".into()))
+}
+
+fn validate_sid(sid: &str) -> Result<()> {
+    let s = sid.trim();
+    if s.is_empty() || s.len() > MAX_SID_CHARS {
+        anyhow::bail!("invalid sid");
+    }
+    if !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("invalid sid");
+    }
+    Ok(())
+}
+
+fn validate_sdp_code(s: &str) -> Result<()> {
+    if s.is_empty() || s.len() > MAX_SDP_CODE_CHARS {
+        anyhow::bail!("sdp blob too large");
+    }
+    Ok(())
+}
+
+fn purge_expired(state: &AppState) {
+    let mut sessions = state.sessions.lock().expect("lock");
+    let ttl = state.ttl;
+    let now = SystemTime::now();
+    sessions.retain(|_, s| now.duration_since(s.created_at).unwrap_or_default() <= ttl);
+}
+
+fn purge_closed_clients(state: &AppState) {
+    let mut sessions = state.sessions.lock().expect("lock");
+    for s in sessions.values_mut() {
+        s.clients.retain(|c| !c.is_closed());
+    }
+}
+
+async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+async fn handle_socket(socket: WebSocket, state: AppState) {
+    purge_expired(&state);
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Message>();
+    let mut joined_sid: Option<String> = None;
+    let mut last_rate = Instant::now();
+    let mut rate_budget: i32 = 40;
+
+    let (mut socket_tx, mut socket_rx) = socket.split();
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let _ = socket_tx.send(msg).await;
+        }
+    });
+
+    let _ = tx.send(json_msg(ServerMsg::Hello { v: 1 }));
+
+    let mut stopping = state.stopping.clone();
+    loop {
+        let next = tokio::select! {
+            next = socket_rx.next() => next,
+            _ = stopping.wait_for(|s| *s) => {
+                let _ = tx.send(Message::Close(None));
+                break;
+            }
+        };
+        let Some(Ok(msg)) = next else { break };
+        let Message::Text(text) = msg else { continue };
+        if text.len() > MAX_WS_TEXT_BYTES {
+            let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "message too large".into() }));
+            break;
+        }
+
+        // Simple per-connection rate limiter.
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(last_rate);
+        if elapsed.as_secs_f32() >= 1.0 {
+            rate_budget = 40;
+            last_rate = now;
+        }
+        rate_budget -= 1;
+        if rate_budget < 0 {
+            let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "rate limited".into() }));
+            continue;
+        }
+
+        let parsed: Result<ClientMsg> = serde_json::from_str(&text).context("parse");
+        let Ok(cmd) = parsed else {
+            let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "invalid json".into() }));
+            continue;
+        };
+
+        match cmd {
+            ClientMsg::Join { v: 1, sid } => {
+                if validate_sid(&sid).is_err() {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "invalid sid".into() }));
+                    continue;
+                }
+                joined_sid = Some(sid.clone());
+                let mut sessions = state.sessions.lock().expect("lock");
+                if sessions.len() >= MAX_SESSIONS && !sessions.contains_key(&sid) {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "server busy".into() }));
+                    continue;
+                }
+                let entry = sessions.entry(sid.clone()).or_insert_with(|| Session {
+                    created_at: SystemTime::now(),
+                    offer: None,
+                    answer: None,
+                    clients: vec![],
+                });
+                if entry.clients.len() >= MAX_CLIENTS_PER_SESSION {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "session full".into() }));
+                    continue;
+                }
+                entry.clients.push(tx.clone());
+                let offer = entry.offer.clone();
+                let answer = entry.answer.clone();
+                let _ = tx.send(json_msg(ServerMsg::State {
+                    v: 1,
+                    sid,
+                    has_offer: offer.is_some(),
+                    has_answer: answer.is_some(),
+                    offer,
+                    answer,
+                }));
+            }
+            ClientMsg::SetOffer { v: 1, sid, offer } => {
+                if validate_sid(&sid).is_err() {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "invalid sid".into() }));
+                    continue;
+                }
+                if joined_sid.as_deref() != Some(sid.as_str()) {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "join required".into() }));
+                    continue;
+                }
+                if validate_sdp_code(&offer).is_err() {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "offer too large".into() }));
+                    continue;
+                }
+                let mut sessions = state.sessions.lock().expect("lock");
+                let entry = sessions.entry(sid.clone()).or_insert_with(|| Session {
+                    created_at: SystemTime::now(),
+                    offer: None,
+                    answer: None,
+                    clients: vec![],
+                });
+                entry.offer = Some(offer);
+                let broadcast = json_msg(ServerMsg::State {
+                    v: 1,
+                    sid: sid.clone(),
+                    has_offer: true,
+                    has_answer: entry.answer.is_some(),
+                    offer: entry.offer.clone(),
+                    answer: entry.answer.clone(),
+                });
+                entry.clients.retain(|c| c.send(broadcast.clone()).is_ok());
+            }
+            ClientMsg::SetAnswer { v: 1, sid, answer } => {
+                if validate_sid(&sid).is_err() {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "invalid sid".into() }));
+                    continue;
+                }
+                if joined_sid.as_deref() != Some(sid.as_str()) {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "join required".into() }));
+                    continue;
+                }
+                if validate_sdp_code(&answer).is_err() {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "answer too large".into() }));
+                    continue;
+                }
+                let mut sessions = state.sessions.lock().expect("lock");
+                let entry = sessions.entry(sid.clone()).or_insert_with(|| Session {
+                    created_at: SystemTime::now(),
+                    offer: None,
+                    answer: None,
+                    clients: vec![],
+                });
+                entry.answer = Some(answer);
+                let broadcast = json_msg(ServerMsg::State {
+                    v: 1,
+                    sid: sid.clone(),
+                    has_offer: entry.offer.is_some(),
+                    has_answer: true,
+                    offer: entry.offer.clone(),
+                    answer: entry.answer.clone(),
+                });
+                entry.clients.retain(|c| c.send(broadcast.clone()).is_ok());
+            }
+            ClientMsg::GetState { v: 1, sid } => {
+                if validate_sid(&sid).is_err() {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "invalid sid".into() }));
+                    continue;
+                }
+                if joined_sid.as_deref() != Some(sid.as_str()) {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "join required".into() }));
+                    continue;
+                }
+                let sessions = state.sessions.lock().expect("lock");
+                let Some(entry) = sessions.get(&sid) else {
+                    let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "unknown sid".into() }));
+                    continue;
+                };
+                let _ = tx.send(json_msg(ServerMsg::State {
+                    v: 1,
+                    sid,
+                    has_offer: entry.offer.is_some(),
+                    has_answer: entry.answer.is_some(),
+                    offer: entry.offer.clone(),
+                    answer: entry.answer.clone(),
+                }));
+            }
+            _ => {
+                let _ = tx.send(json_msg(ServerMsg::Error { v: 1, error: "unsupported version".into() }));
+            }
+        }
+    }
+
+    if let Some(sid) = joined_sid {
+        let mut sessions = state.sessions.lock().expect("lock");
+        if let Some(entry) = sessions.get_mut(&sid) {
+            entry.clients.retain(|c| !c.is_closed());
+        }
+    }
+}
+
+async fn info_handler() -> impl IntoResponse {
+    Json(Info { name: "voxelle-signal", v: 1 })
+}
+
+/// Serves the relay on `listener` until `shutdown` completes. Open
+/// WebSockets are closed at that point rather than left to finish.
+pub async fn serve(
+    listener: tokio::net::TcpListener,
+    config: RelayConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let (stop_tx, stopping) = watch::channel(false);
+    let state = AppState {
+        ttl: config.ttl,
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+        stopping,
+    };
+
+    // Background purge task: TTL cleanup + closed-client pruning.
+    let purge = {
+        let st = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(30));
+            loop {
+                interval.tick().await;
+                purge_expired(&st);
+                purge_closed_clients(&st);
+            }
+        })
+    };
+
+    let app = Router::new()
+        .route("/info", get(info_handler))
+        .route("/ws", get(ws_handler))
+        .with_state(state);
+
+    let served = axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown.await;
+            let _ = stop_tx.send(true);
+        })
+        .await;
+    purge.abort();
+    served
+}
+
+/// A relay running on the current tokio runtime. Dropping it stops it too.
+pub struct Relay {
+    addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl Relay {
+    /// Binds `addr` (port 0 picks a free one) and starts serving in the background.
+    pub async fn start(addr: SocketAddr, config: RelayConfig) -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let shutdown = async {
+                let _ = rx.await;
+            };
+            if let Err(e) = serve(listener, config, shutdown).await {
+                tracing::warn!("signaling relay on {addr} failed: {e}");
+            }
+        });
+        Ok(Self { addr, shutdown: Some(tx) })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stops accepting connections and closes the open ones. The port is
+    /// released shortly after, once the listener task sees the signal.
+    pub fn stop(mut self) {
+        self.signal_stop();
+    }
+
+    fn signal_stop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        self.signal_stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sid_validation_accepts_hex() {
+        validate_sid("0123abcdef").unwrap();
+        validate_sid("ABCDEF0123").unwrap();
+    }
+
+    #[test]
+    fn sid_validation_rejects_weird() {
+        assert!(validate_sid("").is_err());
+        assert!(validate_sid(" ").is_err());
+        assert!(validate_sid("not-hex").is_err());
+        assert!(validate_sid("..").is_err());
+        assert!(validate_sid(&"a".repeat(MAX_SID_CHARS + 1)).is_err());
+    }
+
+    #[test]
+    fn protocol_generated_sids_pass_validation() {
+        for _ in 0..64 {
+            validate_sid(&voxelle_protocol::generate_session_id()).unwrap();
+        }
+        let sid = voxelle_protocol::pairing_code_to_sid("abacus-zoom-tiger", b"").unwrap();
+        validate_sid(&sid).unwrap();
+    }
+
+    #[test]
+    fn sdp_limit_enforced() {
+        assert!(validate_sdp_code("").is_err());
+        validate_sdp_code(&"x".repeat(16)).unwrap();
+        assert!(validate_sdp_code(&"x".repeat(MAX_SDP_CODE_CHARS + 1)).is_err());
+    }
+
+    fn get_info(addr: SocketAddr) -> std::io::Result<String> {
+        use std::io::{Read, Write};
+        let mut s = std::net::TcpStream::connect(addr)?;
+        s.write_all(b"GET /info HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")?;
+        let mut out = String::new();
+        s.read_to_string(&mut out)?;
+        Ok(out)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn embedded_relay_serves_until_stopped() {
+        let relay = Relay::start(([127, 0, 0, 1], 0).into(), RelayConfig::default()).await.unwrap();
+        let addr = relay.local_addr();
+        assert_ne!(addr.port(), 0);
+        let info = tokio::task::spawn_blocking(move || get_info(addr)).await.unwrap().unwrap();
+        assert!(info.contains(r#"{"name":"voxelle-signal","v":1}"#), "{info}");
+
+        relay.stop();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while std::net::TcpStream::connect(addr).is_ok() {
+            assert!(Instant::now() < deadline, "relay still listening on {addr}");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        // The port can be bound again.
+        Relay::start(addr, RelayConfig::default()).await.unwrap().stop();
+    }
+}